@@ -0,0 +1,338 @@
+//! # Load Generator
+//!
+//! Drives a steady request rate against a running server and prints latency
+//! percentiles per scenario. Useful for checking the effect of pooling,
+//! middleware, and limit changes before and after.
+//!
+//! Scenarios, picked round-robin:
+//! - `health`: `GET /health`
+//! - `movies`: add, get, update, then delete one movie
+//! - `auth`:   `POST /webauthn/auth/start` for an unknown user (401 expected),
+//!   which exercises the repository and Redis without a real authenticator
+//! - `register`: `POST /webauthn/register/start` for a new user, which
+//!   creates the user and stores a challenge. The users are left behind for
+//!   the orphaned-user cleanup
+//! - `session`: `GET /account/usage` with a session token, which measures
+//!   session validation. Pass a token from a sign-in with `--session-token`;
+//!   without one each request sends an unknown token (401 expected), which
+//!   still builds the key and queries Redis
+//!
+//! Run with:
+//! ```text
+//! cargo run --release --example loadgen -- --url http://127.0.0.1:8080 --rps 200 --duration 30
+//! ```
+//!
+//! Options (all optional):
+//! - `--url URL`          server base URL (default `http://127.0.0.1:8080`)
+//! - `--rps N`            scenario starts per second (default 100)
+//! - `--duration SECS`    how long to run (default 10)
+//! - `--concurrency N`    most scenarios in flight at once (default 256)
+//! - `--scenarios LIST`   comma-separated subset of `health,movies,auth,register,session`
+//! - `--session-token T`  session token for the `session` scenario
+
+use anyhow::{bail, Context, Result};
+use reqwest::{Client, StatusCode};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+
+const SCENARIOS: [&str; 5] = ["health", "movies", "auth", "register", "session"];
+
+/// Command-line options.
+#[derive(Debug)]
+struct Options {
+    // ---
+    url: String,
+    rps: u32,
+    duration: Duration,
+    concurrency: usize,
+    scenarios: Vec<&'static str>,
+    session_token: Option<String>,
+}
+
+impl Options {
+    // ---
+
+    fn parse() -> Result<Self> {
+        // ---
+        let mut options = Options {
+            url: "http://127.0.0.1:8080".to_string(),
+            rps: 100,
+            duration: Duration::from_secs(10),
+            concurrency: 256,
+            scenarios: SCENARIOS.to_vec(),
+            session_token: None,
+        };
+
+        let mut args = std::env::args().skip(1);
+        while let Some(flag) = args.next() {
+            let value = args
+                .next()
+                .with_context(|| format!("missing value for {flag}"))?;
+            match flag.as_str() {
+                "--url" => options.url = value.trim_end_matches('/').to_string(),
+                "--rps" => options.rps = value.parse().context("--rps")?,
+                "--duration" => {
+                    options.duration = Duration::from_secs(value.parse().context("--duration")?)
+                }
+                "--concurrency" => options.concurrency = value.parse().context("--concurrency")?,
+                "--scenarios" => {
+                    options.scenarios = value
+                        .split(',')
+                        .map(|name| {
+                            SCENARIOS
+                                .into_iter()
+                                .find(|known| *known == name.trim())
+                                .with_context(|| format!("unknown scenario {name}"))
+                        })
+                        .collect::<Result<_>>()?;
+                }
+                "--session-token" => options.session_token = Some(value),
+                _ => bail!("unknown option {flag}"),
+            }
+        }
+
+        if options.rps == 0 || options.concurrency == 0 || options.scenarios.is_empty() {
+            bail!("--rps, --concurrency, and --scenarios must be non-empty");
+        }
+        Ok(options)
+    }
+}
+
+/// Latencies and outcomes of one request type.
+#[derive(Default)]
+struct Samples {
+    // ---
+    latencies: Vec<Duration>,
+    statuses: BTreeMap<u16, u64>,
+    errors: u64,
+}
+
+/// Collected results, keyed by request name (e.g. `movies.add`).
+#[derive(Clone, Default)]
+struct Recorder {
+    // ---
+    samples: Arc<Mutex<BTreeMap<&'static str, Samples>>>,
+}
+
+impl Recorder {
+    // ---
+
+    /// Sends `request`, records its latency and status, and returns the
+    /// response if one arrived.
+    async fn send(
+        &self,
+        name: &'static str,
+        request: reqwest::RequestBuilder,
+    ) -> Option<reqwest::Response> {
+        // ---
+        let start = Instant::now();
+        let result = request.send().await;
+        let elapsed = start.elapsed();
+
+        let mut samples = self.samples.lock().unwrap();
+        let entry = samples.entry(name).or_default();
+        match result {
+            Ok(response) => {
+                entry.latencies.push(elapsed);
+                *entry
+                    .statuses
+                    .entry(response.status().as_u16())
+                    .or_default() += 1;
+                Some(response)
+            }
+            Err(_) => {
+                entry.errors += 1;
+                None
+            }
+        }
+    }
+
+    fn report(&self, elapsed: Duration, started: u64) {
+        // ---
+        println!(
+            "\n{started} scenarios in {:.1}s ({:.1}/s)\n",
+            elapsed.as_secs_f64(),
+            started as f64 / elapsed.as_secs_f64()
+        );
+        println!(
+            "{:<16} {:>8} {:>9} {:>9} {:>9} {:>9} {:>7}  statuses",
+            "request", "count", "p50 ms", "p90 ms", "p99 ms", "max ms", "errors"
+        );
+
+        let mut samples = self.samples.lock().unwrap();
+        for (name, entry) in samples.iter_mut() {
+            entry.latencies.sort();
+            let statuses: Vec<String> = entry
+                .statuses
+                .iter()
+                .map(|(status, count)| format!("{status}x{count}"))
+                .collect();
+            println!(
+                "{:<16} {:>8} {:>9.2} {:>9.2} {:>9.2} {:>9.2} {:>7}  {}",
+                name,
+                entry.latencies.len(),
+                percentile(&entry.latencies, 0.50),
+                percentile(&entry.latencies, 0.90),
+                percentile(&entry.latencies, 0.99),
+                percentile(&entry.latencies, 1.0),
+                entry.errors,
+                statuses.join(" ")
+            );
+        }
+    }
+}
+
+/// The `q` quantile of sorted latencies, in milliseconds.
+fn percentile(sorted: &[Duration], q: f64) -> f64 {
+    // ---
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = ((sorted.len() as f64 * q).ceil() as usize).clamp(1, sorted.len());
+    sorted[rank - 1].as_secs_f64() * 1000.0
+}
+
+async fn health(client: &Client, base: &str, recorder: &Recorder) {
+    // ---
+    recorder
+        .send("health", client.get(format!("{base}/health")))
+        .await;
+}
+
+async fn movies(client: &Client, base: &str, recorder: &Recorder, n: u64) {
+    // ---
+    let movie = json!({ "title": format!("loadgen {n}"), "year": 2000, "stars": 3.5 });
+    let Some(response) = recorder
+        .send(
+            "movies.add",
+            client.post(format!("{base}/movies/add")).json(&movie),
+        )
+        .await
+    else {
+        return;
+    };
+    if response.status() != StatusCode::CREATED {
+        return;
+    }
+    let Ok(body) = response.json::<serde_json::Value>().await else {
+        return;
+    };
+    let Some(id) = body["data"]["id"].as_str() else {
+        return;
+    };
+
+    recorder
+        .send("movies.get", client.get(format!("{base}/movies/get/{id}")))
+        .await;
+
+    let updated = json!({ "title": format!("loadgen {n}"), "year": 2000, "stars": 4.0 });
+    recorder
+        .send(
+            "movies.update",
+            client
+                .put(format!("{base}/movies/update/{id}"))
+                .json(&updated),
+        )
+        .await;
+
+    recorder
+        .send(
+            "movies.delete",
+            client.delete(format!("{base}/movies/delete/{id}")),
+        )
+        .await;
+}
+
+async fn auth(client: &Client, base: &str, recorder: &Recorder, n: u64) {
+    // ---
+    let body = json!({ "username": format!("loadgen-unknown-{n}") });
+    recorder
+        .send(
+            "auth.start",
+            client
+                .post(format!("{base}/webauthn/auth/start"))
+                .json(&body),
+        )
+        .await;
+}
+
+async fn register(client: &Client, base: &str, recorder: &Recorder, n: u64) {
+    // ---
+    let body = json!({ "username": format!("loadgen-{}-{n}", std::process::id()) });
+    recorder
+        .send(
+            "register.start",
+            client
+                .post(format!("{base}/webauthn/register/start"))
+                .json(&body),
+        )
+        .await;
+}
+
+async fn session(client: &Client, base: &str, recorder: &Recorder, token: Option<&str>, n: u64) {
+    // ---
+    let unknown = format!("loadgen-unknown-{n}");
+    recorder
+        .send(
+            "session",
+            client
+                .get(format!("{base}/account/usage"))
+                .bearer_auth(token.unwrap_or(&unknown)),
+        )
+        .await;
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    // ---
+    let options = Options::parse()?;
+    let base = Arc::new(format!("{}/api/v1", options.url));
+    let client = Client::builder().timeout(Duration::from_secs(30)).build()?;
+    let recorder = Recorder::default();
+    let session_token = Arc::new(options.session_token.clone());
+    let slots = Arc::new(Semaphore::new(options.concurrency));
+    let started = Arc::new(AtomicU64::new(0));
+
+    println!(
+        "Sending {} scenarios/s to {} for {}s ({})",
+        options.rps,
+        base,
+        options.duration.as_secs(),
+        options.scenarios.join(", ")
+    );
+
+    let mut ticks = tokio::time::interval(Duration::from_secs(1) / options.rps);
+    let begin = Instant::now();
+    let mut tasks = Vec::new();
+
+    while begin.elapsed() < options.duration {
+        ticks.tick().await;
+        // Waiting here lowers the achieved rate, which the report shows
+        let slot = slots.clone().acquire_owned().await?;
+        let n = started.fetch_add(1, Ordering::Relaxed);
+        let scenario = options.scenarios[n as usize % options.scenarios.len()];
+        let (client, base, recorder) = (client.clone(), base.clone(), recorder.clone());
+        let session_token = session_token.clone();
+
+        tasks.push(tokio::spawn(async move {
+            let _slot = slot;
+            match scenario {
+                "health" => health(&client, &base, &recorder).await,
+                "movies" => movies(&client, &base, &recorder, n).await,
+                "register" => register(&client, &base, &recorder, n).await,
+                "session" => session(&client, &base, &recorder, session_token.as_deref(), n).await,
+                _ => auth(&client, &base, &recorder, n).await,
+            }
+        }));
+    }
+
+    for task in tasks {
+        task.await?;
+    }
+    recorder.report(begin.elapsed(), started.load(Ordering::Relaxed));
+    Ok(())
+}
@@ -0,0 +1,8 @@
+-- Soft delete: rows with deleted_at set are hidden from all lookups and
+-- permanently removed by the admin purge once past the retention window.
+ALTER TABLE users ADD COLUMN deleted_at TIMESTAMPTZ;
+ALTER TABLE credentials ADD COLUMN deleted_at TIMESTAMPTZ;
+
+-- Indexes for the purge scan (only soft-deleted rows are indexed)
+CREATE INDEX idx_users_deleted_at ON users(deleted_at) WHERE deleted_at IS NOT NULL;
+CREATE INDEX idx_credentials_deleted_at ON credentials(deleted_at) WHERE deleted_at IS NOT NULL;
@@ -0,0 +1,3 @@
+-- WebAuthn signature counters are unsigned 32-bit; INTEGER is signed and
+-- cannot hold values above 2^31 - 1. SQLite INTEGER is already 64-bit.
+ALTER TABLE credentials ALTER COLUMN counter TYPE BIGINT;
@@ -0,0 +1,3 @@
+-- Set when a sign-in shows a signature counter that did not increase, which
+-- suggests the authenticator has been cloned.
+ALTER TABLE credentials ADD COLUMN compromised_at TIMESTAMPTZ;
@@ -0,0 +1,5 @@
+-- WebAuthn backup flags: BE (eligible, i.e. a synced/multi-device passkey)
+-- and BS (currently backed up). NULL for credentials registered before
+-- these were recorded; filled in on their next sign-in.
+ALTER TABLE credentials ADD COLUMN backup_eligible BOOLEAN;
+ALTER TABLE credentials ADD COLUMN backup_state BOOLEAN;
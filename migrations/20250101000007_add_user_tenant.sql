@@ -0,0 +1,11 @@
+-- Multi-tenancy: every user belongs to a tenant (one WebAuthn relying
+-- party), and usernames are unique per tenant rather than globally.
+-- Existing users belong to the default tenant. Credentials are scoped
+-- through their owning user.
+ALTER TABLE users ADD COLUMN tenant_id VARCHAR(64) NOT NULL DEFAULT 'default';
+
+ALTER TABLE users DROP CONSTRAINT users_username_key;
+DROP INDEX idx_users_username;
+
+-- Index for username lookups, which are always made within one tenant
+CREATE UNIQUE INDEX idx_users_tenant_id_username ON users(tenant_id, username);
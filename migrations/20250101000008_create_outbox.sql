@@ -0,0 +1,13 @@
+-- Transactional outbox: events written in the same transaction as the
+-- change they describe, deleted once delivered. A claimed event is hidden
+-- from other workers until locked_until.
+CREATE TABLE outbox (
+    id UUID PRIMARY KEY,
+    kind TEXT NOT NULL,
+    payload TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    attempts INTEGER NOT NULL DEFAULT 0,
+    locked_until TIMESTAMPTZ
+);
+
+CREATE INDEX idx_outbox_created_at ON outbox(created_at);
@@ -0,0 +1,22 @@
+-- Usernames are matched ignoring case and Unicode normalization: each user
+-- also stores its username lowercased and NFC-normalized, as computed by
+-- the application, and uniqueness per tenant moves to that column. The
+-- username itself keeps the spelling it was registered with.
+--
+-- Existing rows are backfilled with PostgreSQL's lower() and normalize(),
+-- which agree with the application for ASCII and, under a UTF-8 locale,
+-- for other scripts. ASCII names are already NFC and skip normalize(),
+-- which needs a UTF-8 server encoding. Creating the index fails if two
+-- users of one tenant collide once folded; rename one of them first.
+ALTER TABLE users ADD COLUMN username_normalized TEXT;
+UPDATE users SET username_normalized = CASE
+    WHEN username ~ '[^[:ascii:]]' THEN normalize(lower(username), NFC)
+    ELSE lower(username)
+END;
+ALTER TABLE users ALTER COLUMN username_normalized SET NOT NULL;
+
+DROP INDEX idx_users_tenant_id_username;
+
+-- Index for username lookups, which are always made within one tenant
+CREATE UNIQUE INDEX idx_users_tenant_id_username_normalized
+    ON users(tenant_id, username_normalized);
@@ -0,0 +1,10 @@
+-- Create users table for WebAuthn authentication (SQLite)
+-- UUIDs are stored as 16-byte BLOBs, timestamps as RFC 3339 TEXT.
+CREATE TABLE users (
+    id BLOB PRIMARY KEY,
+    username TEXT UNIQUE NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+-- Index for username lookups
+CREATE INDEX idx_users_username ON users(username);
@@ -0,0 +1,14 @@
+-- Create credentials table for WebAuthn passkeys (SQLite)
+CREATE TABLE credentials (
+    id BLOB PRIMARY KEY,
+    user_id BLOB NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    public_key BLOB NOT NULL,
+    counter INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+-- Index for user credential lookups
+CREATE INDEX idx_credentials_user_id ON credentials(user_id);
+
+-- Ensure credential IDs are unique per user
+CREATE UNIQUE INDEX idx_credentials_user_id_id ON credentials(user_id, id);
@@ -0,0 +1,24 @@
+-- Multi-tenancy (SQLite): every user belongs to a tenant (one WebAuthn
+-- relying party), and usernames are unique per tenant rather than globally.
+-- Existing users belong to the default tenant.
+--
+-- SQLite cannot drop the inline UNIQUE on username, so the table is
+-- rebuilt. Migrations run with foreign keys off (see `sqlite_repository`),
+-- so dropping the old table does not cascade to credentials.
+CREATE TABLE users_new (
+    id BLOB PRIMARY KEY,
+    tenant_id TEXT NOT NULL DEFAULT 'default',
+    username TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    deleted_at TEXT
+);
+
+INSERT INTO users_new (id, username, created_at, deleted_at)
+    SELECT id, username, created_at, deleted_at FROM users;
+
+DROP TABLE users;
+ALTER TABLE users_new RENAME TO users;
+
+-- Index for username lookups, which are always made within one tenant
+CREATE UNIQUE INDEX idx_users_tenant_id_username ON users(tenant_id, username);
+CREATE INDEX idx_users_deleted_at ON users(deleted_at) WHERE deleted_at IS NOT NULL;
@@ -0,0 +1,13 @@
+-- Transactional outbox (SQLite): events written in the same transaction as
+-- the change they describe, deleted once delivered. A claimed event is
+-- hidden from other workers until locked_until.
+CREATE TABLE outbox (
+    id BLOB PRIMARY KEY,
+    kind TEXT NOT NULL,
+    payload TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    attempts INTEGER NOT NULL DEFAULT 0,
+    locked_until TEXT
+);
+
+CREATE INDEX idx_outbox_created_at ON outbox(created_at);
@@ -0,0 +1,4 @@
+-- W3C Trace Context of the request that emitted an outbox event, sent
+-- with its deliveries so they join the originating trace.
+ALTER TABLE outbox ADD COLUMN traceparent TEXT;
+ALTER TABLE outbox ADD COLUMN tracestate TEXT;
@@ -0,0 +1,16 @@
+-- Usernames are matched ignoring case and Unicode normalization (SQLite):
+-- each user also stores its username lowercased and NFC-normalized, as
+-- computed by the application, and uniqueness per tenant moves to that
+-- column.
+--
+-- SQLite's lower() only folds ASCII, so existing non-ASCII usernames keep
+-- their other characters until the user is recreated. Creating the index
+-- fails if two users of one tenant collide once folded.
+ALTER TABLE users ADD COLUMN username_normalized TEXT NOT NULL DEFAULT '';
+UPDATE users SET username_normalized = lower(username);
+
+DROP INDEX idx_users_tenant_id_username;
+
+-- Index for username lookups, which are always made within one tenant
+CREATE UNIQUE INDEX idx_users_tenant_id_username_normalized
+    ON users(tenant_id, username_normalized);
@@ -0,0 +1,3 @@
+-- When the credential last signed in (SQLite). NULL until its first
+-- sign-in after this was added.
+ALTER TABLE credentials ADD COLUMN last_used_at TEXT;
@@ -0,0 +1,7 @@
+-- Password hashes (argon2id, PHC string format) for users who sign in with
+-- a password instead of, or besides, passkeys. At most one per user (SQLite).
+CREATE TABLE user_passwords (
+    user_id BLOB PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    password_hash TEXT NOT NULL,
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
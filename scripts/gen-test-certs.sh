@@ -0,0 +1,58 @@
+#!/bin/bash
+
+# gen-test-certs.sh
+# Regenerates the certificates in tests/fixtures/tls used by the mutual
+# TLS tests (`--features tls`). Not for production use: the keys are
+# committed and the certificates are valid for 100 years.
+
+set -e  # Exit on any error
+
+SCRIPT_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+OUT="$(dirname "$SCRIPT_DIR")/tests/fixtures/tls"
+DAYS=36500
+
+mkdir -p "$OUT"
+cd "$OUT"
+
+key() {
+    openssl genpkey -algorithm EC -pkeyopt ec_paramgen_curve:P-256 -out "$1.key"
+}
+
+# Self-signed CA: $1 = name, $2 = subject
+ca() {
+    key "$1"
+    openssl req -x509 -new -key "$1.key" -subj "$2" -days $DAYS -out "$1.pem" \
+        -addext "basicConstraints=critical,CA:TRUE" \
+        -addext "keyUsage=critical,keyCertSign,cRLSign"
+}
+
+# Leaf certificate: $1 = name, $2 = subject, $3 = CA, $4 = extensions
+leaf() {
+    key "$1"
+    openssl req -new -key "$1.key" -subj "$2" -out "$1.csr"
+    printf '%s\n' "basicConstraints=critical,CA:FALSE" "$4" > "$1.ext"
+    openssl x509 -req -in "$1.csr" -CA "$3.pem" -CAkey "$3.key" -CAcreateserial \
+        -days $DAYS -extfile "$1.ext" -out "$1.pem"
+    rm "$1.csr" "$1.ext"
+}
+
+ca ca "/CN=axum-quickstart test CA"
+ca untrusted-ca "/CN=untrusted test CA"
+
+leaf server "/CN=localhost" ca \
+    "$(printf '%s\n' "extendedKeyUsage=serverAuth" "subjectAltName=DNS:localhost,IP:127.0.0.1")"
+
+# CN names the service
+leaf client "/O=Example/CN=billing-service" ca \
+    "$(printf '%s\n' "extendedKeyUsage=clientAuth" "subjectAltName=DNS:billing.internal")"
+
+# No CN; the identity comes from the SAN (a SPIFFE ID)
+leaf client-san "/O=Example" ca \
+    "$(printf '%s\n' "extendedKeyUsage=clientAuth" "subjectAltName=URI:spiffe://example.org/reports")"
+
+# Signed by a CA the server does not trust
+leaf untrusted-client "/CN=intruder" untrusted-ca \
+    "$(printf '%s\n' "extendedKeyUsage=clientAuth")"
+
+rm -f ./*.srl ca.key untrusted-ca.key untrusted-ca.pem
+echo "Wrote test certificates to $OUT"
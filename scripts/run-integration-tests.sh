@@ -109,12 +109,12 @@ docker compose --ansi never exec postgres psql -U postgres -c "SELECT pg_reload_
 echo "------------------------------------------------"
 echo "---------------- integration tests -------------"
 echo "------------------------------------------------"
-cargo test ${QUIET} --test integration -- --nocapture
+cargo test ${QUIET} --features test-utils --test integration -- --nocapture
 
 echo "-----------------------------------------------------"
 echo "---------------- webauthn_registration tests --------"
 echo "-----------------------------------------------------"
-cargo test ${QUIET} --test webauthn_registration -- --nocapture
+cargo test ${QUIET} --features test-utils --test webauthn_registration -- --nocapture
 
 echo "------------------------------------------------"
 echo "---------------- metrics_endpoint tests --------"
@@ -124,7 +124,7 @@ cargo test ${QUIET} --test metrics_endpoint -- --nocapture
 echo "------------------------------------------------"
 echo "---------------- webauthn_authentication tests --------"
 echo "------------------------------------------------"
-cargo test ${QUIET} --test webauthn_authentication -- --nocapture
+cargo test ${QUIET} --features test-utils --test webauthn_authentication -- --nocapture
 
 echo "------------------------------------------------------"
 echo "---------------- database::postgres_repository tests -"
@@ -139,7 +139,32 @@ cargo test --lib ${QUIET} -- infrastructure::database::tests --nocapture
 echo "------------------------------------------------"
 echo "---------------- webauthn_credentials tests ----"
 echo "------------------------------------------------"
-cargo test ${QUIET} --test webauthn_credentials -- --nocapture
+cargo test ${QUIET} --features test-utils --test webauthn_credentials -- --nocapture
+
+echo "------------------------------------------------"
+echo "---------------- contract (snapshot) tests -----"
+echo "------------------------------------------------"
+cargo test ${QUIET} --features test-utils --test contract -- --nocapture
+
+echo "------------------------------------------------"
+echo "---------------- chaos (fault injection) tests -"
+echo "------------------------------------------------"
+cargo test ${QUIET} --features chaos --test chaos -- --nocapture
+
+echo "------------------------------------------------"
+echo "---------------- mutual TLS tests --------------"
+echo "------------------------------------------------"
+cargo test ${QUIET} --features tls --test tls -- --nocapture
+
+echo "------------------------------------------------"
+echo "---------------- typed API client tests --------"
+echo "------------------------------------------------"
+cargo test ${QUIET} --features client,test-utils --test client --test client_contract -- --nocapture
+
+echo "------------------------------------------------"
+echo "---------------- password sign-in tests --------"
+echo "------------------------------------------------"
+cargo test ${QUIET} --features password --test password -- --nocapture
 
 echo "✅ Integration tests completed successfully!"
 exit_status=0
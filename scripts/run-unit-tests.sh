@@ -36,6 +36,10 @@ cargo build --quiet
 echo "🧪 Running unit tests..."
 cargo test --lib -- --skip infrastructure::database
 
+# Run SQLite repository tests (in-memory, no services required)
+echo "🪶 Running SQLite repository tests..."
+cargo test --lib --features sqlite -- sqlite
+
 # Run doc tests
 echo "📚 Running doc tests..."
 cargo test --doc
@@ -0,0 +1,481 @@
+//! Builder for assembling the application router from explicit dependencies.
+//!
+//! `create_router()` reads everything from the environment. `AppBuilder` is
+//! the lower-level entry point for library consumers and tests that want to
+//! embed the app in another binary or inject their own implementations.
+
+use crate::app_state::AppState;
+#[cfg(feature = "chaos")]
+use crate::chaos::{Chaos, ChaosRepository};
+use crate::config::{AccessLogConfig, AppConfig, ServerConfig};
+use crate::domain::{KeyProviderPtr, MetricsPtr, RepositoryPtr};
+use crate::events::EventBus;
+use crate::export::AuditExporter;
+use crate::infrastructure::{
+    create_noop_metrics, create_webauthn, DeadlineRepository, EncryptedRepository, HttpClient,
+    InstrumentedRepository, LocalKeyProvider,
+};
+use crate::jobs::RetentionEngine;
+use crate::redis_retry::RedisRetry;
+use crate::redis_source::RedisSource;
+use crate::reload::ConfigReloader;
+use crate::serverless::AppService;
+use crate::session::SessionManager;
+use crate::shutdown::ShutdownSignal;
+use crate::tenant::TenantRegistry;
+use crate::webhooks::WebhookDispatcher;
+use anyhow::Result;
+use axum::Router;
+use redis::Client;
+use std::sync::Arc;
+use webauthn_rs::Webauthn;
+
+/// Builds the application [`Router`] from injected dependencies.
+///
+/// The repository is required because opening a database pool is async;
+/// create one with [`create_repository`](crate::create_repository). Every
+/// other dependency is optional and, if not supplied, is created from the
+/// configuration when [`AppBuilder::build`] is called:
+///
+/// | Dependency     | Default                                      |
+/// |:---------------|:---------------------------------------------|
+/// | `config`       | [`AppConfig::from_env`]                      |
+/// | `metrics`      | No-op metrics                                |
+/// | `redis`        | `config.redis.url`, or its Sentinel primary  |
+/// | `webauthn`     | Built from `config.webauthn`                 |
+/// | `http_client`  | Built from `config.http`                     |
+/// | `webhooks`     | Worker spawned on the current runtime        |
+/// | `events`       | New [`EventBus`]                             |
+/// | `shutdown`     | New, never-triggered [`ShutdownSignal`]      |
+/// | `key_provider` | Local keys from `config.encryption`, if set  |
+/// | `reloader`     | New [`ConfigReloader`] for `config`          |
+/// | `chaos`        | `Chaos::from_env()` (`chaos` feature only)   |
+///
+/// When a key provider is available, the repository is wrapped in an
+/// [`EncryptedRepository`] so stored passkeys are encrypted at rest. When
+/// `config.server.request_timeout` is set, each request gets that time
+/// budget (or its path's `config.server.route_timeouts` override) and
+/// repository and Redis calls made while handling it are bounded by it. At most `config.server.max_in_flight` requests are handled at
+/// once; the excess is rejected with 503. When `config.export` names a
+/// bucket, audit events and metrics snapshots are uploaded to it in the
+/// background, with a last upload when `shutdown` is triggered. Every
+/// repository call is timed in `db_query_duration_seconds`, and calls
+/// slower than `config.database.slow_query_threshold` are logged. Every
+/// request runs in a span continuing the caller's W3C trace (see
+/// [`TraceContext`](crate::TraceContext)), or starting one. What services
+/// do (registrations, sign-ins, movie changes, ...) is published as domain
+/// events, and the metrics, audit log lines, SSE events, and webhooks that
+/// follow are produced by subscriber tasks on the current runtime.
+///
+/// With the `chaos` feature, Redis connects and repository calls pass
+/// through `Chaos` first, which may delay or fail them.
+///
+/// # Example
+/// ```no_run
+/// # async fn run() -> anyhow::Result<()> {
+/// use axum_quickstart::{create_prom_metrics, create_repository, AppBuilder, AppConfig};
+///
+/// let config = AppConfig::from_env()?;
+/// let repository = create_repository(&config.database).await?;
+///
+/// let router = AppBuilder::new()
+///     .config(config)
+///     .repository(repository)
+///     .metrics(create_prom_metrics()?)
+///     .build()?;
+/// # let _ = router;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Default)]
+pub struct AppBuilder {
+    // ---
+    config: Option<AppConfig>,
+    repository: Option<RepositoryPtr>,
+    metrics: Option<MetricsPtr>,
+    redis_client: Option<Client>,
+    webauthn: Option<Arc<Webauthn>>,
+    http_client: Option<HttpClient>,
+    webhooks: Option<WebhookDispatcher>,
+    events: Option<EventBus>,
+    shutdown: Option<ShutdownSignal>,
+    key_provider: Option<KeyProviderPtr>,
+    reloader: Option<ConfigReloader>,
+    separate_admin: bool,
+    #[cfg(feature = "chaos")]
+    chaos: Option<Chaos>,
+}
+
+/// The routers returned by [`AppBuilder::build_routers`], all sharing one
+/// application state.
+pub struct AppRouters {
+    // ---
+    /// The application, served on `API_BIND_ADDR`.
+    pub app: Router,
+
+    /// The operator routes (`/health`, `/metrics`, `/admin`, ...), when
+    /// [`separate_admin`](AppBuilder::separate_admin) split them off.
+    pub admin: Option<Router>,
+
+    /// The gRPC services; serve them on a port of their own, over HTTP/2.
+    #[cfg(feature = "grpc")]
+    pub grpc: Router,
+}
+
+impl AppBuilder {
+    // ---
+
+    /// Creates a builder with no dependencies set.
+    pub fn new() -> Self {
+        // ---
+        Self::default()
+    }
+
+    /// Sets the application configuration.
+    pub fn config(mut self, config: AppConfig) -> Self {
+        // ---
+        self.config = Some(config);
+        self
+    }
+
+    /// Sets the persistence backend.
+    pub fn repository(mut self, repository: RepositoryPtr) -> Self {
+        // ---
+        self.repository = Some(repository);
+        self
+    }
+
+    /// Sets the metrics backend.
+    pub fn metrics(mut self, metrics: MetricsPtr) -> Self {
+        // ---
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Sets the Redis client used for challenges, sessions, and movies,
+    /// instead of connecting to `config.redis` (and its Sentinel, if any).
+    pub fn redis_client(mut self, redis_client: Client) -> Self {
+        // ---
+        self.redis_client = Some(redis_client);
+        self
+    }
+
+    /// Sets the WebAuthn relying-party instance of the default tenant. The
+    /// tenants in `config.tenants` are always built from their configuration.
+    pub fn webauthn(mut self, webauthn: Arc<Webauthn>) -> Self {
+        // ---
+        self.webauthn = Some(webauthn);
+        self
+    }
+
+    /// Sets the client for outbound HTTP calls (webhook deliveries, audit
+    /// exports), instead of one built from `config.http`.
+    pub fn http_client(mut self, http_client: HttpClient) -> Self {
+        // ---
+        self.http_client = Some(http_client);
+        self
+    }
+
+    /// Sets the webhook dispatcher, e.g. [`WebhookDispatcher::disabled`].
+    pub fn webhooks(mut self, webhooks: WebhookDispatcher) -> Self {
+        // ---
+        self.webhooks = Some(webhooks);
+        self
+    }
+
+    /// Sets the server event bus, e.g. to subscribe to or publish events
+    /// from outside the HTTP handlers.
+    pub fn events(mut self, events: EventBus) -> Self {
+        // ---
+        self.events = Some(events);
+        self
+    }
+
+    /// Sets the shutdown signal. Trigger it when the server begins graceful
+    /// shutdown so WebSocket clients receive a close frame.
+    pub fn shutdown(mut self, shutdown: ShutdownSignal) -> Self {
+        // ---
+        self.shutdown = Some(shutdown);
+        self
+    }
+
+    /// Sets the reloader that later configuration reloads go through. It
+    /// should be created from the same configuration passed to
+    /// [`config`](Self::config).
+    pub fn reloader(mut self, reloader: ConfigReloader) -> Self {
+        // ---
+        self.reloader = Some(reloader);
+        self
+    }
+
+    /// Sets the key provider for encrypting stored passkeys, e.g. one
+    /// backed by a KMS. Overrides `AXUM_DATA_ENCRYPTION_KEY`.
+    pub fn key_provider(mut self, key_provider: KeyProviderPtr) -> Self {
+        // ---
+        self.key_provider = Some(key_provider);
+        self
+    }
+
+    /// Moves the health, metrics, admin, and profiling routes out of the
+    /// application router into a router of their own, returned as
+    /// [`AppRouters::admin`] by [`build_routers`](Self::build_routers), to
+    /// serve on a separate port. The other build methods then leave them
+    /// out. The separate router is not subject to the in-flight request
+    /// limit, so health checks and scrapes are answered under load.
+    pub fn separate_admin(mut self, separate: bool) -> Self {
+        // ---
+        self.separate_admin = separate;
+        self
+    }
+
+    /// Injects the faults set on `chaos` into Redis connects and repository
+    /// calls. Keep a clone to change them while the server runs.
+    #[cfg(feature = "chaos")]
+    pub fn chaos(mut self, chaos: Chaos) -> Self {
+        // ---
+        self.chaos = Some(chaos);
+        self
+    }
+
+    /// Assembles the application state and returns the fully routed [`Router`].
+    ///
+    /// # Errors
+    /// Returns an error if no repository was supplied, configuration is
+    /// missing from the environment (when not supplied), or a default
+    /// dependency cannot be created (invalid Redis URL, malformed WebAuthn
+    /// origin).
+    pub fn build(self) -> Result<Router> {
+        // ---
+        Ok(self.assemble()?.0)
+    }
+
+    /// Like [`build`](Self::build), returning every router: the application,
+    /// the operator routes if [`separate_admin`](Self::separate_admin) is
+    /// set, and, with the `grpc` feature, the gRPC services.
+    ///
+    /// # Errors
+    /// As for [`build`](Self::build).
+    pub fn build_routers(self) -> Result<AppRouters> {
+        // ---
+        let (app, admin, _app_state) = self.assemble()?;
+        Ok(AppRouters {
+            app,
+            admin,
+            #[cfg(feature = "grpc")]
+            grpc: crate::grpc::grpc_router(_app_state),
+        })
+    }
+
+    /// Like [`build`](Self::build), returning the application as a boxed
+    /// `tower::Service` for hosts without a TCP listener, such as AWS Lambda
+    /// (see [`run_lambda`](crate::run_lambda) with the `lambda` feature).
+    /// Insert `ConnectInfo<SocketAddr>` into requests to supply the client
+    /// address.
+    ///
+    /// # Errors
+    /// As for [`build`](Self::build).
+    pub fn build_service(self) -> Result<AppService> {
+        // ---
+        Ok(AppService::new(self.build()?))
+    }
+
+    /// Like [`build`](Self::build), also returning the gRPC router, which
+    /// shares the application state. Serve it on its own port; it speaks
+    /// HTTP/2 only.
+    ///
+    /// # Errors
+    /// As for [`build`](Self::build).
+    #[cfg(feature = "grpc")]
+    pub fn build_with_grpc(self) -> Result<(Router, Router)> {
+        // ---
+        let (router, _, app_state) = self.assemble()?;
+        Ok((router, crate::grpc::grpc_router(app_state)))
+    }
+
+    /// The routed [`Router`], the operator router if split off, and the
+    /// state behind them.
+    fn assemble(self) -> Result<(Router, Option<Router>, AppState)> {
+        // ---
+        let config = match self.config {
+            Some(config) => config,
+            None => AppConfig::from_env()?,
+        };
+
+        let metrics = match self.metrics {
+            Some(metrics) => metrics,
+            None => create_noop_metrics()?,
+        };
+
+        let redis = match self.redis_client {
+            Some(client) => RedisSource::from(client),
+            None => RedisSource::from_config(&config.redis)?,
+        };
+
+        let repository = self.repository.ok_or_else(|| {
+            anyhow::anyhow!("AppBuilder requires a repository; see create_repository()")
+        })?;
+        let repository: RepositoryPtr = Arc::new(InstrumentedRepository::new(
+            repository,
+            metrics.clone(),
+            config.database.slow_query_threshold,
+        ));
+
+        let key_provider = self.key_provider.or_else(|| {
+            LocalKeyProvider::from_config(&config.encryption)
+                .map(|provider| Arc::new(provider) as KeyProviderPtr)
+        });
+        let repository = match key_provider {
+            Some(keys) => Arc::new(EncryptedRepository::new(repository, keys)) as RepositoryPtr,
+            None => repository,
+        };
+        #[cfg(feature = "chaos")]
+        let chaos = self.chaos.unwrap_or_else(Chaos::from_env);
+        #[cfg(feature = "chaos")]
+        if !chaos.redis().is_none() || !chaos.database().is_none() {
+            tracing::warn!(
+                "Chaos faults active: redis {:?}, database {:?}",
+                chaos.redis(),
+                chaos.database()
+            );
+        }
+        #[cfg(feature = "chaos")]
+        let repository: RepositoryPtr = Arc::new(ChaosRepository::new(repository, chaos.clone()));
+        let repository: RepositoryPtr = Arc::new(DeadlineRepository::new(repository));
+
+        let webauthn = match self.webauthn {
+            Some(webauthn) => webauthn,
+            None => Arc::new(create_webauthn(&config.webauthn)?),
+        };
+        let tenants = Arc::new(TenantRegistry::new(webauthn, &config.tenants)?);
+
+        let http = match self.http_client {
+            Some(http) => http,
+            None => HttpClient::new(&config.http, metrics.clone())?,
+        };
+
+        // The worker needs a runtime; outside one (e.g. building a router in
+        // a sync test) webhooks are disabled rather than panicking.
+        let webhooks = match self.webhooks {
+            Some(webhooks) => webhooks,
+            None if tokio::runtime::Handle::try_current().is_ok() => WebhookDispatcher::spawn_from(
+                redis.clone(),
+                Some(repository.clone()),
+                http.clone(),
+                metrics.clone(),
+                config.webhooks.clone(),
+            ),
+            None => {
+                tracing::warn!("No Tokio runtime; webhooks disabled");
+                WebhookDispatcher::disabled()
+            }
+        };
+
+        let events = self.events.unwrap_or_default();
+        let shutdown = self.shutdown.unwrap_or_default();
+        let export = match config.export.is_enabled() {
+            true if tokio::runtime::Handle::try_current().is_ok() => AuditExporter::spawn(
+                &config.export,
+                &events,
+                http.clone(),
+                metrics.clone(),
+                shutdown.clone(),
+            )?,
+            true => {
+                tracing::warn!("No Tokio runtime; audit export disabled");
+                AuditExporter::disabled()
+            }
+            false => AuditExporter::disabled(),
+        };
+
+        let server = config.server.clone();
+        let access_log = Arc::new(config.access_log.clone());
+        let reloader = self
+            .reloader
+            .unwrap_or_else(|| ConfigReloader::new(&config));
+        let retention = match tokio::runtime::Handle::try_current() {
+            Ok(_) => RetentionEngine::spawn(
+                &config.retention,
+                repository.clone(),
+                export.clone(),
+                metrics.clone(),
+                reloader.live(),
+                shutdown.clone(),
+            ),
+            Err(_) => RetentionEngine::disabled(),
+        };
+        let shed_metrics = metrics.clone();
+
+        // Build application state with all dependencies
+        let app_state = AppState::new(
+            redis,
+            metrics,
+            repository,
+            tenants,
+            config.redis.webauthn_challenge_ttl,
+            RedisRetry::from_config(&config.redis),
+            reloader.live(),
+            SessionManager::from_config(&config.session),
+            webhooks,
+            events,
+            export,
+            retention,
+            shutdown,
+        )
+        .with_base_path(&config.server.base_path);
+        #[cfg(feature = "chaos")]
+        let app_state = app_state.with_chaos(chaos);
+
+        // Without a runtime there is nothing to react to domain events
+        match tokio::runtime::Handle::try_current() {
+            Ok(_) => {
+                crate::application::spawn_subscribers(&app_state);
+                crate::infrastructure::spawn_event_stream(
+                    &config.event_stream,
+                    app_state.domain_events(),
+                    app_state.metrics().clone(),
+                    app_state.shutdown().clone(),
+                )?;
+            }
+            Err(_) => tracing::warn!("No Tokio runtime; domain event subscribers disabled"),
+        }
+
+        let (router, admin) = crate::build_routes(app_state.clone(), self.separate_admin);
+        let server = Arc::new(server);
+        let admin = admin
+            .map(|admin| with_middleware(admin, &server, None, &access_log, shed_metrics.clone()));
+        let max_in_flight = server.max_in_flight;
+        let router = with_middleware(router, &server, max_in_flight, &access_log, shed_metrics);
+        Ok((router, admin, app_state))
+    }
+}
+
+/// Wraps `router` in the per-request middleware, shedding requests beyond
+/// `max_in_flight`.
+fn with_middleware(
+    router: Router,
+    server: &Arc<ServerConfig>,
+    max_in_flight: Option<usize>,
+    access_log: &Arc<AccessLogConfig>,
+    metrics: MetricsPtr,
+) -> Router {
+    // ---
+    // Innermost, so the layers below see a panic as a 500 response
+    let router = crate::middleware::catch_panic(router, metrics.clone());
+    let router = router.layer(axum::middleware::from_fn_with_state(
+        server.clone(),
+        crate::middleware::request_deadline,
+    ));
+    let router = crate::middleware::load_shed(router, max_in_flight, metrics);
+    let router = match access_log.enabled {
+        true => router.layer(axum::middleware::from_fn_with_state(
+            access_log.clone(),
+            crate::middleware::access_log,
+        )),
+        false => router,
+    };
+
+    // Outermost, so the access log line is inside the request's span
+    router.layer(axum::middleware::from_fn(
+        crate::middleware::propagate_trace,
+    ))
+}
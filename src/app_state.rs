@@ -9,12 +9,30 @@
 //! where needed) so it can be passed efficiently to each request handler
 //! without expensive copying of resources.
 
+#[cfg(feature = "chaos")]
+use crate::chaos::Chaos;
+#[cfg(feature = "password")]
+use crate::config::PasswordPolicy;
+use crate::config::{AccessConfig, AdminConfig, CredentialPolicy, QuotaConfig};
+use crate::deadline::{bounded, Deadline};
 use crate::domain::{MetricsPtr, RepositoryPtr};
+use crate::events::{DomainEventBus, EventBus};
+use crate::export::AuditExporter;
+use crate::health_history::HealthHistory;
+use crate::jobs::RetentionEngine;
+use crate::redis_retry::RedisRetry;
+use crate::redis_source::RedisConnection;
+use crate::redis_source::RedisSource;
+use crate::reload::{Live, LiveConfigPtr};
+use crate::session::SessionManager;
+use crate::shutdown::ShutdownSignal;
+use crate::tenant::TenantRegistry;
+use crate::webhooks::WebhookDispatcher;
 use axum::http::StatusCode;
-use redis::Client;
+use redis::{AsyncConnectionConfig, RedisResult};
+use std::future::Future;
 use std::sync::Arc;
 use std::time::Duration;
-use webauthn_rs::Webauthn;
 
 /// Shared application state passed to all Axum handlers.
 ///
@@ -40,18 +58,34 @@ use webauthn_rs::Webauthn;
 ///
 /// # Fields
 ///
-/// - `redis_client`: Client for creating ephemeral Redis connections (challenges, sessions)
+/// - `redis`: Source of ephemeral Redis connections (challenges, sessions),
+///   either a fixed server or the Sentinel primary
 /// - `metrics`: Metrics implementation for observability (Prometheus or no-op)
 /// - `repository`: Database abstraction for persistent storage (users, credentials)
-/// - `webauthn`: WebAuthn protocol handler for passkey operations (registration, authentication)
+/// - `tenants`: WebAuthn protocol handler of each tenant, for passkey operations
+///   (registration, authentication)
 /// - `challenge_ttl`: Time-to-live for WebAuthn challenges stored in Redis
+/// - `redis_retry`: Retry policy for transient Redis errors
+/// - `live`: Reloadable settings (admin API, client IP access lists,
+///   credential policy, request quotas)
+/// - `sessions`: Session token backend (Redis or signed)
+/// - `webhooks`: Queue for outbound auth-event webhooks
+/// - `events`: In-process event bus feeding the SSE stream
+/// - `domain_events`: Bus carrying what the service layer did to the
+///   subscribers acting on it (metrics, audit log, SSE, webhooks)
+/// - `export`: Uploads audit events and metrics snapshots to S3
+/// - `retention`: Scheduled removal of data past its retention period
+/// - `shutdown`: Signal telling WebSocket connections to close
+/// - `health_history`: Recent dependency health transitions and uptime
+/// - `base_path`: Prefix all routes are served under, for generated links
+/// - `chaos`: Faults injected into Redis connects (`chaos` feature only)
 #[derive(Clone)]
 pub(crate) struct AppState {
-    /// Redis client for creating multiplexed async connections on demand.
+    /// Opens multiplexed async Redis connections on demand.
     ///
     /// Used for ephemeral data (WebAuthn challenges, session tokens, cache).
     /// Handlers call `get_conn()` to obtain a connection for each request.
-    redis_client: Client,
+    redis: RedisSource,
 
     /// Metrics implementation for recording application events.
     ///
@@ -66,54 +100,192 @@ pub(crate) struct AppState {
     /// Wrapped in `Arc` via `RepositoryPtr` for cheap cloning.
     repository: RepositoryPtr,
 
-    /// WebAuthn protocol handler.
+    /// WebAuthn protocol handlers, one per tenant.
     ///
-    /// Configured with relying party identity (RP ID, origin, name).
-    /// Used for generating challenges and verifying credentials.
-    /// Wrapped in `Arc` because `Webauthn` does not implement `Clone`.
-    webauthn: Arc<Webauthn>,
+    /// Each is configured with its relying party identity (RP ID, origin,
+    /// name) and used for generating challenges and verifying credentials.
+    /// Handlers get the one for the request's host via the `Tenant`
+    /// extractor.
+    tenants: Arc<TenantRegistry>,
 
     /// Time-to-live for WebAuthn challenges in Redis.
     ///
     /// Challenges expire after this duration to prevent replay attacks.
     /// Typically 5 minutes (300 seconds).
     challenge_ttl: Duration,
+
+    /// Retry policy for idempotent Redis operations (connect, reads).
+    redis_retry: RedisRetry,
+
+    /// Settings that can be reloaded while running: admin API settings,
+    /// client IP restrictions for `/admin/*` and `/metrics`, how
+    /// suspicious credentials are handled at sign-in, and request quotas.
+    live: LiveConfigPtr,
+
+    /// Issues and validates session tokens.
+    sessions: SessionManager,
+
+    /// Outbound webhook queue for auth events.
+    ///
+    /// Emitting is fire-and-forget; delivery happens on a background worker.
+    webhooks: WebhookDispatcher,
+
+    /// Broadcast bus for live server events (`GET /events`).
+    events: EventBus,
+
+    /// Domain events published by the service layer.
+    domain_events: DomainEventBus,
+
+    /// Periodic export of audit events and metrics to S3, which admins can
+    /// also trigger.
+    export: AuditExporter,
+
+    /// Scheduled removal of data past its retention period.
+    retention: RetentionEngine,
+
+    /// Triggered on graceful shutdown so upgraded connections can close.
+    shutdown: ShutdownSignal,
+
+    /// Dependency health transitions seen by full health checks, and the
+    /// time the state was created, for `GET /health/history`.
+    health_history: Arc<HealthHistory>,
+
+    /// Prefix all routes are served under (`AXUM_BASE_PATH`), or empty.
+    /// Links the server generates start with it.
+    base_path: Arc<str>,
+
+    /// Faults injected into Redis connects, for resilience tests.
+    #[cfg(feature = "chaos")]
+    chaos: Chaos,
 }
 
 impl AppState {
     // ---
 
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
-        redis_client: Client,
+        redis: RedisSource,
         metrics: MetricsPtr,
         repository: RepositoryPtr,
-        webauthn: Arc<Webauthn>,
+        tenants: Arc<TenantRegistry>,
         challenge_ttl: Duration,
+        redis_retry: RedisRetry,
+        live: LiveConfigPtr,
+        sessions: SessionManager,
+        webhooks: WebhookDispatcher,
+        events: EventBus,
+        export: AuditExporter,
+        retention: RetentionEngine,
+        shutdown: ShutdownSignal,
     ) -> Self {
         // ---
         AppState {
-            redis_client,
+            redis,
             metrics,
             repository,
-            webauthn,
+            tenants,
             challenge_ttl,
+            redis_retry,
+            live,
+            sessions,
+            webhooks,
+            events,
+            domain_events: DomainEventBus::default(),
+            export,
+            retention,
+            shutdown,
+            health_history: Arc::new(HealthHistory::new()),
+            base_path: Arc::from(""),
+            #[cfg(feature = "chaos")]
+            chaos: Chaos::default(),
+        }
+    }
+
+    /// Sets the prefix all routes are served under.
+    pub fn with_base_path(mut self, base_path: &str) -> Self {
+        // ---
+        self.base_path = Arc::from(base_path);
+        self
+    }
+
+    /// Injects the Redis faults set on `chaos` into every connect.
+    #[cfg(feature = "chaos")]
+    pub fn with_chaos(mut self, chaos: Chaos) -> Self {
+        // ---
+        self.chaos = chaos;
+        self
+    }
+
+    /// Opens a multiplexed Redis connection. While handling a request,
+    /// connecting and each command sent on the connection time out once the
+    /// request's remaining budget is spent.
+    async fn connect(&self) -> RedisResult<RedisConnection> {
+        // ---
+        #[cfg(feature = "chaos")]
+        self.chaos.before_redis_connect().await?;
+
+        match Deadline::current() {
+            Some(deadline) => {
+                let remaining = deadline.remaining();
+                let config = AsyncConnectionConfig::new()
+                    .set_connection_timeout(remaining)
+                    .set_response_timeout(remaining);
+                self.redis.connect_with_config(&config).await
+            }
+            None => self.redis.connect().await,
         }
     }
 
     /// Creates a new multiplexed Redis connection.
     ///
-    /// Logs an error if connection fails and returns HTTP 500.
-    pub(crate) async fn get_conn(&self) -> Result<redis::aio::MultiplexedConnection, StatusCode> {
+    /// Transient connect failures are retried per the Redis retry policy,
+    /// but not past the request deadline. Logs an error if connection fails
+    /// and returns HTTP 500, or HTTP 503 if the deadline passed.
+    pub(crate) async fn get_conn(&self) -> Result<RedisConnection, StatusCode> {
         // ---
-        self.redis_client
-            .get_multiplexed_async_connection()
+        bounded(self.redis_retry.run(&self.metrics, || self.connect()))
             .await
+            .map_err(|_| {
+                tracing::warn!("Redis connect exceeded the request deadline");
+                StatusCode::SERVICE_UNAVAILABLE
+            })?
             .map_err(|err| {
                 tracing::error!("Failed to connect to Redis: {:?}", err);
                 StatusCode::INTERNAL_SERVER_ERROR
             })
     }
 
+    /// Runs an idempotent Redis read (`GET`, `EXISTS`, ...) on a fresh
+    /// connection, retrying transient failures per the Redis retry policy.
+    ///
+    /// Only pass operations that are safe to repeat; see [`RedisRetry`].
+    /// Retries stop at the request deadline, failing with a timeout error.
+    pub(crate) async fn redis_read<T, F, Fut>(&self, op: F) -> RedisResult<T>
+    where
+        F: Fn(RedisConnection) -> Fut,
+        Fut: Future<Output = RedisResult<T>>,
+    {
+        // ---
+        let op = &op;
+        bounded(self.redis_retry.run(&self.metrics, move || async move {
+            op(self.connect().await?).await
+        }))
+        .await?
+    }
+
+    /// Get the prefix all routes are served under, or `""`.
+    pub(crate) fn base_path(&self) -> &str {
+        // ---
+        &self.base_path
+    }
+
+    /// Get the path of version 1 of the API as clients see it, below the
+    /// base path.
+    pub(crate) fn api_v1_prefix(&self) -> String {
+        // ---
+        format!("{}{}", self.base_path, crate::API_V1_PREFIX)
+    }
+
     /// Get a reference to the metrics implementation.
     pub(crate) fn metrics(&self) -> &MetricsPtr {
         // ---
@@ -126,10 +298,10 @@ impl AppState {
         &self.repository
     }
 
-    /// Get a reference to the WebAuthn instance.
-    pub(crate) fn webauthn(&self) -> &Webauthn {
+    /// Get the tenants and their WebAuthn instances.
+    pub(crate) fn tenants(&self) -> &TenantRegistry {
         // ---
-        &self.webauthn
+        &self.tenants
     }
 
     /// Get the WebAuthn challenge TTL.
@@ -137,6 +309,85 @@ impl AppState {
         // ---
         self.challenge_ttl
     }
+
+    /// Get the admin API configuration.
+    pub(crate) fn admin(&self) -> Live<AdminConfig> {
+        // ---
+        Live::load(&self.live, |live| &live.admin)
+    }
+
+    /// Get the operator endpoint access lists.
+    pub(crate) fn access(&self) -> Live<AccessConfig> {
+        // ---
+        Live::load(&self.live, |live| &live.access)
+    }
+
+    /// Get the session token backend.
+    pub(crate) fn sessions(&self) -> &SessionManager {
+        // ---
+        &self.sessions
+    }
+
+    /// Get the sign-in credential policy.
+    pub(crate) fn credential_policy(&self) -> Live<CredentialPolicy> {
+        // ---
+        Live::load(&self.live, |live| &live.credentials)
+    }
+
+    /// Get the password policy.
+    #[cfg(feature = "password")]
+    pub(crate) fn password_policy(&self) -> Live<PasswordPolicy> {
+        // ---
+        Live::load(&self.live, |live| &live.passwords)
+    }
+
+    /// Get the per-caller request quotas.
+    pub(crate) fn quotas(&self) -> Live<QuotaConfig> {
+        // ---
+        Live::load(&self.live, |live| &live.quotas)
+    }
+
+    /// Get the webhook dispatcher.
+    pub(crate) fn webhooks(&self) -> &WebhookDispatcher {
+        // ---
+        &self.webhooks
+    }
+
+    /// Get the server event bus.
+    pub(crate) fn events(&self) -> &EventBus {
+        // ---
+        &self.events
+    }
+
+    /// Get the domain event bus.
+    pub(crate) fn domain_events(&self) -> &DomainEventBus {
+        // ---
+        &self.domain_events
+    }
+
+    /// Get the audit and metrics exporter.
+    pub(crate) fn export(&self) -> &AuditExporter {
+        // ---
+        &self.export
+    }
+
+    /// Get the retention job.
+    pub(crate) fn retention(&self) -> &RetentionEngine {
+        // ---
+        &self.retention
+    }
+
+    /// Get the shutdown signal.
+    pub(crate) fn shutdown(&self) -> &ShutdownSignal {
+        // ---
+        &self.shutdown
+    }
+
+    /// Get the dependency health history.
+    pub(crate) fn health_history(&self) -> &HealthHistory {
+        // ---
+        &self.health_history
+    }
 }
 
 #[cfg(test)]
@@ -146,9 +397,14 @@ mod tests {
     use super::*;
     use crate::config::WebAuthnConfig;
     use crate::create_webauthn;
-    use crate::domain::{Credential, Repository, User};
+    use crate::domain::{
+        Credential, CredentialStats, OutboxEvent, OutboxWrite, PurgeSummary, Repository, User,
+    };
     use crate::infrastructure::create_noop_metrics;
+    use crate::reload::LiveConfig;
     use anyhow::Result;
+    use chrono::{DateTime, Utc};
+    use redis::Client;
     use uuid::Uuid;
 
     // Mock repository for unit tests - not used, just satisfies AppState requirements
@@ -158,21 +414,44 @@ mod tests {
     impl Repository for MockRepository {
         // ---
 
-        async fn create_user(&self, _username: &str) -> Result<User> {
+        async fn ping(&self) -> Result<()> {
+            unimplemented!()
+        }
+
+        async fn create_user_in(&self, _tenant_id: &str, _username: &str) -> Result<User> {
             unimplemented!("Mock repository - not used in AppState unit tests")
         }
-        async fn get_user_by_username(&self, _username: &str) -> Result<Option<User>> {
+        async fn get_user_by_username_in(
+            &self,
+            _tenant_id: &str,
+            _username: &str,
+        ) -> Result<Option<User>> {
+            unimplemented!()
+        }
+        async fn get_or_create_user_in(&self, _tenant_id: &str, _username: &str) -> Result<User> {
             unimplemented!()
         }
         async fn get_user_by_id(&self, _user_id: Uuid) -> Result<Option<User>> {
             unimplemented!()
         }
+        async fn set_password_hash(&self, _user_id: Uuid, _password_hash: &str) -> Result<bool> {
+            unimplemented!()
+        }
+        async fn get_password_hash(&self, _user_id: Uuid) -> Result<Option<String>> {
+            unimplemented!()
+        }
         async fn save_credential(&self, _credential: Credential) -> Result<()> {
             unimplemented!()
         }
+        async fn save_credentials(&self, _credentials: Vec<Credential>) -> Result<()> {
+            unimplemented!()
+        }
         async fn get_credentials_by_user(&self, _user_id: Uuid) -> Result<Vec<Credential>> {
             unimplemented!()
         }
+        async fn credentials_modified_at(&self, _user_id: Uuid) -> Result<Option<DateTime<Utc>>> {
+            unimplemented!()
+        }
         async fn get_credential_by_id(&self, _credential_id: &[u8]) -> Result<Option<Credential>> {
             unimplemented!()
         }
@@ -182,6 +461,63 @@ mod tests {
         async fn delete_credential(&self, _credential_id: &[u8]) -> Result<()> {
             unimplemented!()
         }
+        async fn delete_credentials_by_user(&self, _user_id: Uuid) -> Result<u64> {
+            unimplemented!()
+        }
+        async fn delete_user(&self, _user_id: Uuid) -> Result<bool> {
+            unimplemented!()
+        }
+        async fn soft_delete_user(&self, _user_id: Uuid) -> Result<bool> {
+            unimplemented!()
+        }
+        async fn restore_user(&self, _user_id: Uuid) -> Result<bool> {
+            unimplemented!()
+        }
+        async fn soft_delete_credential(&self, _credential_id: &[u8]) -> Result<bool> {
+            unimplemented!()
+        }
+        async fn restore_credential(&self, _credential_id: &[u8]) -> Result<bool> {
+            unimplemented!()
+        }
+        async fn mark_credential_compromised(&self, _credential_id: &[u8]) -> Result<bool> {
+            unimplemented!()
+        }
+        async fn purge_deleted(
+            &self,
+            _cutoff: DateTime<Utc>,
+            _dry_run: bool,
+        ) -> Result<PurgeSummary> {
+            unimplemented!()
+        }
+        async fn delete_orphaned_users(&self, _before: DateTime<Utc>, _dry: bool) -> Result<u64> {
+            unimplemented!()
+        }
+        async fn credential_stats(&self) -> Result<CredentialStats> {
+            unimplemented!()
+        }
+        async fn list_all_credentials(
+            &self,
+            _after: Option<&[u8]>,
+            _limit: u32,
+        ) -> Result<Vec<Credential>> {
+            unimplemented!()
+        }
+        async fn replace_public_key(&self, _id: &[u8], _old: &[u8], _new: &[u8]) -> Result<bool> {
+            unimplemented!()
+        }
+        async fn write_with_event(
+            &self,
+            _write: OutboxWrite,
+            _event: &OutboxEvent,
+        ) -> Result<bool> {
+            unimplemented!()
+        }
+        async fn claim_events(&self, _limit: u32, _lease: Duration) -> Result<Vec<OutboxEvent>> {
+            unimplemented!()
+        }
+        async fn delete_event(&self, _event_id: Uuid) -> Result<()> {
+            unimplemented!()
+        }
     }
 
     fn test_webauthn_config() -> WebAuthnConfig {
@@ -190,27 +526,59 @@ mod tests {
             rp_id: "localhost".to_string(),
             rp_name: "Test App".to_string(),
             origin: "http://localhost:8080".to_string(),
+            additional_origins: Vec::new(),
         }
     }
 
+    fn test_live_config() -> LiveConfigPtr {
+        // ---
+        Arc::new(arc_swap::ArcSwap::from_pointee(LiveConfig {
+            admin: AdminConfig {
+                api_token: None,
+                service_tokens: Vec::new(),
+                soft_delete_retention: Duration::from_secs(86_400),
+            },
+            access: AccessConfig::default(),
+            credentials: CredentialPolicy::default(),
+            #[cfg(feature = "password")]
+            passwords: PasswordPolicy::default(),
+            quotas: QuotaConfig::default(),
+        }))
+    }
+
     #[test]
     fn test_app_state_creation_and_clone() {
         // ---
         // Test basic creation and that Clone works
-        let redis_client = Client::open("redis://127.0.0.1:6379").unwrap();
+        let redis = Client::open("redis://127.0.0.1:6379").unwrap().into();
         let metrics = create_noop_metrics().unwrap();
         let repository = Arc::new(MockRepository);
         let webauthn_config = test_webauthn_config();
         let webauthn = Arc::new(create_webauthn(&webauthn_config).unwrap());
+        let tenants = Arc::new(TenantRegistry::single(webauthn));
         let challenge_ttl = Duration::from_secs(300);
 
-        let app_state = AppState::new(redis_client, metrics, repository, webauthn, challenge_ttl);
+        let app_state = AppState::new(
+            redis,
+            metrics,
+            repository,
+            tenants,
+            challenge_ttl,
+            RedisRetry::default(),
+            test_live_config(),
+            SessionManager::default(),
+            WebhookDispatcher::disabled(),
+            EventBus::default(),
+            AuditExporter::disabled(),
+            RetentionEngine::disabled(),
+            ShutdownSignal::new(),
+        );
         let _cloned = app_state.clone();
 
         // Verify accessors work
         let _metrics_ref = app_state.metrics();
         let _repo_ref = app_state.repository();
-        let _webauthn_ref = app_state.webauthn();
+        let _tenants_ref = app_state.tenants();
         assert_eq!(app_state.challenge_ttl(), Duration::from_secs(300));
     }
 
@@ -218,16 +586,31 @@ mod tests {
     async fn test_redis_connection_failure() {
         // ---
         // Test that connection failures return proper error
-        let redis_client = Client::open("redis://invalid-host:6379").unwrap();
+        let redis = Client::open("redis://invalid-host:6379").unwrap().into();
         let metrics = create_noop_metrics().unwrap();
         let repository = Arc::new(MockRepository);
         let webauthn_config = test_webauthn_config();
         let webauthn = Arc::new(create_webauthn(&webauthn_config).unwrap());
+        let tenants = Arc::new(TenantRegistry::single(webauthn));
         let challenge_ttl = Duration::from_secs(300);
 
-        let app_state = AppState::new(redis_client, metrics, repository, webauthn, challenge_ttl);
+        let app_state = AppState::new(
+            redis,
+            metrics,
+            repository,
+            tenants,
+            challenge_ttl,
+            RedisRetry::default(),
+            test_live_config(),
+            SessionManager::default(),
+            WebhookDispatcher::disabled(),
+            EventBus::default(),
+            AuditExporter::disabled(),
+            RetentionEngine::disabled(),
+            ShutdownSignal::new(),
+        );
 
         let result = app_state.get_conn().await;
-        assert_eq!(result.unwrap_err(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(result.err(), Some(StatusCode::INTERNAL_SERVER_ERROR));
     }
 }
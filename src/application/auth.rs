@@ -0,0 +1,1215 @@
+//! Passkey registration and sign-in, and the sessions they create.
+//!
+//! Both ceremonies run in two phases. `*_start` stores the WebAuthn state
+//! in Redis under a new flow ID and returns the challenge for the
+//! authenticator; `*_finish` consumes that state and verifies the
+//! authenticator's answer. Registration stores the new passkey, subject to
+//! the user's credential limit; sign-in checks the signature counter,
+//! updates the stored passkey, and creates a session.
+
+use super::challenge::{challenge_key, serialize_challenge, AUTHENTICATION, REGISTRATION};
+use super::credentials::{within_limit, CredentialService};
+use super::{ServiceError, StatsService};
+use crate::app_state::AppState;
+use crate::config::{FingerprintPolicy, SignCountPolicy};
+use crate::domain::OutboxWrite;
+use crate::events::{DomainEvent, ServerEvent};
+use crate::handlers::constant_time_eq;
+use crate::redact;
+use crate::redis_keys;
+use crate::redis_source::RedisConnection;
+use crate::session::{ClientFingerprint, FingerprintChange, SessionInfo};
+use crate::tenant::Tenant;
+use crate::webhooks::{WebhookEvent, WebhookEventKind};
+use axum::http::StatusCode;
+use base64::Engine;
+use redis::AsyncCommands;
+use sha2::{Digest, Sha256};
+use std::net::IpAddr;
+use uuid::Uuid;
+use webauthn_rs::prelude::*;
+
+/// The role reported for every session; accounts have no other roles yet.
+pub(crate) const USER_ROLE: &str = "user";
+
+/// Authentication operations shared by the HTTP handlers and the gRPC
+/// server. Cheap to clone.
+#[derive(Clone)]
+pub(crate) struct AuthService {
+    // ---
+    state: AppState,
+}
+
+impl AuthService {
+    // ---
+
+    pub(crate) fn new(state: AppState) -> Self {
+        // ---
+        Self { state }
+    }
+
+    /// Starts registering a passkey for `username` in `tenant`, creating
+    /// the user if needed. Returns the credential creation options and the
+    /// flow ID the finish call must echo.
+    ///
+    /// # Errors
+    /// `500` if the database or Redis fails.
+    pub(crate) async fn start_registration(
+        &self,
+        tenant: &Tenant,
+        username: &str,
+    ) -> Result<(CreationChallengeResponse, Uuid), ServiceError> {
+        // ---
+        let state = &self.state;
+
+        // Get or create the user in one statement, so concurrent starts
+        // for a new username agree on the user
+        let user = state
+            .repository()
+            .get_or_create_user_in(tenant.id(), username)
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to get or create user: {}", e);
+                ServiceError::internal("Failed to create user")
+            })?;
+
+        // Generate WebAuthn challenge, naming the passkey after the account
+        // as first registered rather than this request's spelling
+        let (challenge_response, registration_state) = tenant
+            .webauthn()
+            .start_passkey_registration(user.id, &user.username, &user.username, None)
+            .map_err(|e| {
+                tracing::error!("Failed to start registration: {}", e);
+                ServiceError::internal("Failed to generate challenge")
+            })?;
+
+        // Store registration state in Redis with TTL, keyed by a new flow ID
+        let flow_id = Uuid::new_v4();
+        let state_key = challenge_key(tenant, REGISTRATION, username, flow_id);
+        let state_bytes = serialize_challenge(&registration_state).map_err(|e| {
+            ServiceError::internal(format!(
+                "failed to serialize webauthn registration state: {e}"
+            ))
+        })?;
+
+        let mut conn = state
+            .get_conn()
+            .await
+            .map_err(|status| ServiceError::new(status, "Redis connection failed"))?;
+
+        let ttl_secs = state.challenge_ttl().as_secs();
+        let _: () = conn
+            .set_ex(&state_key, &*state_bytes, ttl_secs)
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to store challenge in Redis: {}", e);
+                ServiceError::internal("Failed to store challenge")
+            })?;
+
+        tracing::info!(
+            "Registration started for user: {}",
+            redact::username(username)
+        );
+        Ok((challenge_response, flow_id))
+    }
+
+    /// Verifies the authenticator's answer to registration flow `flow_id`
+    /// and stores the new passkey with its `user.registered` webhook event.
+    /// Returns the credential ID.
+    ///
+    /// # Errors
+    /// - `400` if the flow is unknown or expired, or verification fails
+    /// - `404` if the user no longer exists
+    /// - `409` if the user already holds their limit of passkeys
+    /// - `500` if the database or Redis fails
+    /// - `503` if Redis fails while taking the challenge
+    pub(crate) async fn finish_registration(
+        &self,
+        tenant: &Tenant,
+        username: &str,
+        flow_id: Uuid,
+        credential: &RegisterPublicKeyCredential,
+    ) -> Result<Vec<u8>, ServiceError> {
+        // ---
+        let state = &self.state;
+
+        // Retrieve registration state from Redis
+        let state_key = challenge_key(tenant, REGISTRATION, username, flow_id);
+        let mut conn = state
+            .get_conn()
+            .await
+            .map_err(|_| challenge_store_unavailable(state, REGISTRATION))?;
+
+        // A challenge must be consumed, not fetched then deleted later, i.e. this must
+        // be atomic
+        let registration_state: PasskeyRegistration =
+            take_challenge(state, &mut conn, REGISTRATION, &state_key, username)
+                .await?
+                .map_err(|e| {
+                    ServiceError::internal(format!(
+                        "failed to deserialize webauthn registration state: {e}"
+                    ))
+                })?;
+
+        // Verify the credential
+        let passkey = tenant
+            .webauthn()
+            .finish_passkey_registration(credential, &registration_state)
+            .map_err(|e| {
+                tracing::error!("Credential verification failed: {}", e);
+                ServiceError::new(StatusCode::BAD_REQUEST, "Credential verification failed")
+            })?;
+
+        // Get user from database
+        let user = state
+            .repository()
+            .get_user_by_username_in(tenant.id(), username)
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to query user: {}", e);
+                ServiceError::internal("Database error")
+            })?
+            .ok_or_else(|| ServiceError::new(StatusCode::NOT_FOUND, "User not found"))?;
+
+        // Enforce the per-user credential limit. Two registrations finishing at
+        // the same moment can both pass; the limit bounds growth, not an exact count.
+        let (limit, _) = CredentialService::new(state.clone())
+            .limit(user.id)
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to read credential limit: {}", e);
+                ServiceError::internal("Internal server error")
+            })?;
+        let held = state
+            .repository()
+            .get_credentials_by_user(user.id)
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to count credentials: {}", e);
+                ServiceError::internal("Database error")
+            })?
+            .len();
+        if !within_limit(limit, held) {
+            tracing::warn!(
+                "Credential limit reached for user: {} ({} of {})",
+                redact::username(username),
+                held,
+                limit
+            );
+            return Err(ServiceError::new(
+                StatusCode::CONFLICT,
+                format!(
+                    "Credential limit reached: at most {limit} passkeys per user; delete one first"
+                ),
+            ));
+        }
+
+        // Store credential in database
+        // Note: Passkey is serialized as the public_key, counter is extracted separately
+        let cred_id = passkey.cred_id().to_vec();
+        let passkey_bytes = serde_json::to_vec(&passkey).map_err(|e| {
+            tracing::error!("Failed to serialize passkey: {}", e);
+            ServiceError::internal("Serialization error")
+        })?;
+
+        let (backup_eligible, backup_state) = backup_flags(&passkey);
+        let mut credential = crate::domain::Credential::new(
+            cred_id.clone(),
+            user.id,
+            passkey_bytes,
+            0, // Initial counter value for new credentials
+        );
+        credential.backup_eligible = backup_eligible;
+        credential.backup_state = backup_state;
+
+        // Stored with its `user.registered` webhook event, in one transaction
+        let credential_b64 = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(&cred_id);
+        let event = WebhookEvent::new(
+            WebhookEventKind::UserRegistered,
+            user.id,
+            &user.username,
+            &credential_b64,
+        );
+        state
+            .webhooks()
+            .emit_with(
+                &**state.repository(),
+                OutboxWrite::SaveCredential(credential),
+                event,
+            )
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to save credential: {}", e);
+                ServiceError::internal("Failed to save credential")
+            })?;
+
+        tracing::info!(
+            "Registration completed for user: {} (credential: {})",
+            redact::username(username),
+            hex::encode(&cred_id)
+        );
+
+        state.domain_events().publish(match held {
+            0 => DomainEvent::UserRegistered {
+                user_id: user.id,
+                username: user.username,
+                credential_id: Some(credential_b64),
+            },
+            _ => DomainEvent::CredentialAdded {
+                user_id: user.id,
+                username: user.username,
+                credential_id: credential_b64,
+            },
+        });
+        Ok(cred_id)
+    }
+
+    /// Starts a sign-in for `username` in `tenant`. Returns the credential
+    /// request options and the flow ID the finish call must echo.
+    ///
+    /// # Errors
+    /// - `401` if the user does not exist or has no passkeys; the same
+    ///   generic message either way, to prevent username enumeration
+    /// - `500` if the database or Redis fails
+    pub(crate) async fn start_authentication(
+        &self,
+        tenant: &Tenant,
+        username: &str,
+    ) -> Result<(RequestChallengeResponse, Uuid), ServiceError> {
+        // ---
+        let state = &self.state;
+        let failed = || ServiceError::new(StatusCode::UNAUTHORIZED, "Authentication failed");
+
+        // Get user from database
+        let user = state
+            .repository()
+            .get_user_by_username_in(tenant.id(), username)
+            .await
+            .map_err(|e| {
+                tracing::error!(
+                    "Database error fetching user '{}': {:?}",
+                    redact::username(username),
+                    e
+                );
+                ServiceError::internal("Internal server error")
+            })?
+            .ok_or_else(|| {
+                tracing::warn!(
+                    "Authentication attempt for non-existent user: {}",
+                    redact::username(username)
+                );
+                failed()
+            })?;
+
+        // Fetch user's credentials
+        let credentials = state
+            .repository()
+            .get_credentials_by_user(user.id)
+            .await
+            .map_err(|e| {
+                tracing::error!(
+                    "Database error fetching credentials for user '{}': {:?}",
+                    redact::username(username),
+                    e
+                );
+                ServiceError::internal("Internal server error")
+            })?;
+
+        if credentials.is_empty() {
+            tracing::warn!(
+                "User '{}' has no registered credentials",
+                redact::username(username)
+            );
+            return Err(failed());
+        }
+
+        // Convert stored credentials to webauthn-rs Passkey format. Under the
+        // warn-only policy the stored counters are hidden from webauthn-rs, which
+        // would otherwise reject a non-increasing counter itself.
+        let reset_counter = state.credential_policy().sign_count == SignCountPolicy::WarnOnly;
+        let passkeys: Vec<Passkey> = credentials
+            .iter()
+            .filter_map(|cred| {
+                load_passkey(&cred.public_key, reset_counter)
+                    .map_err(|e| {
+                        tracing::error!(
+                            "Failed to deserialize passkey for credential {}: {:?}",
+                            hex::encode(&cred.id),
+                            e
+                        );
+                    })
+                    .ok()
+            })
+            .collect();
+
+        if passkeys.is_empty() {
+            tracing::error!(
+                "User '{}' has credentials but all failed deserialization",
+                redact::username(username)
+            );
+            return Err(ServiceError::internal("Internal server error"));
+        }
+
+        // Generate authentication challenge
+        let (options, auth_state) = tenant
+            .webauthn()
+            .start_passkey_authentication(&passkeys)
+            .map_err(|e| {
+                tracing::error!("Failed to generate auth challenge: {:?}", e);
+                ServiceError::internal("Internal server error")
+            })?;
+
+        // Serialize and store challenge in Redis
+        let state_json = serialize_challenge(&auth_state).map_err(|e| {
+            tracing::error!("Failed to serialize auth state: {:?}", e);
+            ServiceError::internal("Internal server error")
+        })?;
+
+        let flow_id = Uuid::new_v4();
+        let redis_key = challenge_key(tenant, AUTHENTICATION, username, flow_id);
+        let ttl_seconds = state.challenge_ttl().as_secs();
+
+        let mut conn = state.get_conn().await.map_err(|status| {
+            tracing::error!("Failed to get Redis connection");
+            ServiceError::new(status, "Internal server error")
+        })?;
+
+        conn.set_ex::<_, _, ()>(&redis_key, &*state_json, ttl_seconds)
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to store auth challenge in Redis: {:?}", e);
+                ServiceError::internal("Internal server error")
+            })?;
+
+        tracing::info!(
+            "Generated auth challenge for user: {}",
+            redact::username(username)
+        );
+        Ok((options, flow_id))
+    }
+
+    /// Verifies the authenticator's answer to sign-in flow `flow_id`,
+    /// persists the updated passkey, and creates a session for `client`.
+    /// Returns the session token.
+    ///
+    /// # Security
+    /// - The challenge is consumed atomically (GETDEL) and expires after its TTL
+    /// - Counter must increment (prevents replay attacks). A counter that does
+    ///   not suggests a cloned authenticator: the credential is flagged and the
+    ///   sign-in fails with 403 instead of 401 (see [`report_suspected_clone`]).
+    ///   `AXUM_SIGN_COUNT_POLICY` decides which anomalies are rejected; see
+    ///   [`sign_count_verdict`]
+    /// - Failures carry generic messages (no information leakage)
+    ///
+    /// Successes and client errors are counted for the sign-in statistics
+    /// (see [`StatsService`]); server errors are not.
+    ///
+    /// # Errors
+    /// - `400` if the flow is unknown or expired
+    /// - `401` if verification fails
+    /// - `403` if the credential looks cloned
+    /// - `500` if the database or Redis fails
+    /// - `503` if Redis fails while taking the challenge
+    pub(crate) async fn finish_authentication(
+        &self,
+        tenant: &Tenant,
+        username: &str,
+        flow_id: Uuid,
+        credential: &PublicKeyCredential,
+        client: &ClientFingerprint,
+    ) -> Result<String, ServiceError> {
+        // ---
+        let result = self
+            .sign_in(tenant, username, flow_id, credential, client)
+            .await;
+
+        let stats = StatsService::new(self.state.clone());
+        match &result {
+            Ok(_) => stats.record_auth(true).await,
+            Err(e) if e.status.is_client_error() => stats.record_auth(false).await,
+            Err(_) => {}
+        }
+        result
+    }
+
+    /// [`finish_authentication`](Self::finish_authentication) without the
+    /// statistics.
+    async fn sign_in(
+        &self,
+        tenant: &Tenant,
+        username: &str,
+        flow_id: Uuid,
+        credential: &PublicKeyCredential,
+        client: &ClientFingerprint,
+    ) -> Result<String, ServiceError> {
+        // ---
+        let state = &self.state;
+        let client_ip = client.ip;
+        let failed = |status| ServiceError::new(status, "Authentication failed");
+
+        // Atomically retrieve and delete challenge from Redis
+        let redis_key = challenge_key(tenant, AUTHENTICATION, username, flow_id);
+        let mut conn = state
+            .get_conn()
+            .await
+            .map_err(|_| challenge_store_unavailable(state, AUTHENTICATION))?;
+
+        let auth_state: PasskeyAuthentication =
+            take_challenge(state, &mut conn, AUTHENTICATION, &redis_key, username)
+                .await?
+                .map_err(|e| {
+                    tracing::error!("Failed to deserialize auth state: {:?}", e);
+                    failed(StatusCode::INTERNAL_SERVER_ERROR)
+                })?;
+
+        // Verify the credential using webauthn-rs
+        let auth_result = match tenant
+            .webauthn()
+            .finish_passkey_authentication(credential, &auth_state)
+        {
+            Ok(auth_result) => auth_result,
+            Err(WebauthnError::CredentialPossibleCompromise) => {
+                let credential_id = credential.raw_id.as_ref();
+                return Err(report_suspected_clone(state, credential_id, client_ip).await);
+            }
+            Err(e) => {
+                tracing::warn!(
+                    "Authentication verification failed for user '{}': {:?}",
+                    redact::username(username),
+                    e
+                );
+                return Err(failed(StatusCode::UNAUTHORIZED));
+            }
+        };
+
+        // Fetch the stored credential to validate counter
+        let credential_id = auth_result.cred_id().to_vec();
+        let mut stored_credential = state
+            .repository()
+            .get_credential_by_id(&credential_id)
+            .await
+            .map_err(|e| {
+                tracing::error!("Database error fetching credential: {:?}", e);
+                failed(StatusCode::INTERNAL_SERVER_ERROR)
+            })?
+            .ok_or_else(|| {
+                tracing::error!(
+                    "Credential not found in database: {}",
+                    hex::encode(&credential_id)
+                );
+                failed(StatusCode::INTERNAL_SERVER_ERROR)
+            })?;
+
+        // Validate counter to prevent replay attacks (WebAuthn u32, stored as i64)
+        let new_counter = i64::from(auth_result.counter());
+        let policy = state.credential_policy().sign_count;
+        match sign_count_verdict(policy, stored_credential.counter, new_counter) {
+            SignCountVerdict::Accept => {}
+            SignCountVerdict::Warn => {
+                tracing::warn!(
+                    "Counter did not increase for user '{}': stored={}, provided={} (allowed by policy)",
+                    redact::username(username),
+                    stored_credential.counter,
+                    new_counter
+                );
+                state.metrics().record_sign_count_anomaly(false);
+                state.events().publish(
+                    ServerEvent::audit(
+                        "credential.sign_count_anomaly",
+                        serde_json::json!({
+                            "user_id": stored_credential.user_id,
+                            "username": username,
+                            "credential_id": base64::engine::general_purpose::URL_SAFE_NO_PAD
+                                .encode(&credential_id),
+                            "stored_counter": stored_credential.counter,
+                            "provided_counter": new_counter,
+                        }),
+                    )
+                    .with_client_ip(client_ip),
+                );
+            }
+            SignCountVerdict::Reject => {
+                tracing::error!(
+                    "Counter replay attack detected for user '{}': stored={}, provided={}",
+                    redact::username(username),
+                    stored_credential.counter,
+                    new_counter
+                );
+                return Err(report_suspected_clone(state, &credential_id, client_ip).await);
+            }
+        }
+
+        // Apply the new counter and backup flags to the stored passkey
+        sync_passkey(&mut stored_credential, &auth_result).map_err(|e| {
+            tracing::error!("Failed to update stored passkey: {:?}", e);
+            failed(StatusCode::INTERNAL_SERVER_ERROR)
+        })?;
+        state
+            .repository()
+            .update_credential(stored_credential.clone())
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to update credential: {:?}", e);
+                failed(StatusCode::INTERNAL_SERVER_ERROR)
+            })?;
+
+        // Get user for session creation
+        let user = state
+            .repository()
+            .get_user_by_id(stored_credential.user_id)
+            .await
+            .map_err(|e| {
+                tracing::error!("Database error fetching user: {:?}", e);
+                failed(StatusCode::INTERNAL_SERVER_ERROR)
+            })?
+            .ok_or_else(|| {
+                tracing::error!("User not found for credential");
+                failed(StatusCode::INTERNAL_SERVER_ERROR)
+            })?;
+
+        // Create session token
+        let session_token = state
+            .sessions()
+            .create(
+                &mut conn,
+                user.id,
+                user.username.clone(),
+                &user.tenant_id,
+                client,
+            )
+            .await
+            .map_err(|status| {
+                tracing::error!(
+                    "Failed to create session for user: {}",
+                    redact::username(&user.username)
+                );
+                failed(status)
+            })?;
+
+        tracing::info!(
+            "User '{}' authenticated successfully",
+            redact::username(username)
+        );
+
+        state.domain_events().publish(DomainEvent::AuthSucceeded {
+            user_id: user.id,
+            username: user.username,
+            credential_id: Some(
+                base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(&stored_credential.id),
+            ),
+            client_ip,
+        });
+        Ok(session_token)
+    }
+
+    /// Ends the session `token` belongs to in `tenant`: Redis sessions are
+    /// deleted, signed tokens are added to the revocation denylist until
+    /// they expire. Returns the ended session.
+    ///
+    /// # Errors
+    /// - `401` if the token is invalid, expired, or from another tenant
+    /// - `500` if Redis fails
+    pub(crate) async fn logout(
+        &self,
+        token: &str,
+        tenant: &Tenant,
+    ) -> Result<SessionInfo, ServiceError> {
+        // ---
+        let sessions = self.state.sessions();
+        let mut conn = self
+            .state
+            .get_conn()
+            .await
+            .map_err(|status| ServiceError::new(status, "Internal server error"))?;
+
+        let session = sessions
+            .validate(&mut conn, token)
+            .await
+            .and_then(|session| in_tenant(session, tenant))
+            .map_err(|status| ServiceError::new(status, "Invalid or expired session"))?;
+
+        sessions
+            .revoke(&mut conn, token)
+            .await
+            .map_err(|status| ServiceError::new(status, "Internal server error"))?;
+
+        tracing::info!("User '{}' logged out", redact::username(&session.username));
+        Ok(session)
+    }
+
+    /// Checks the bearer token of a downstream service calling a
+    /// service-to-service route: one of `AXUM_SERVICE_TOKENS`, or the admin
+    /// token.
+    ///
+    /// # Errors
+    /// - `403` if neither is configured, which disables these routes
+    /// - `401` if `token` matches none of them
+    pub(crate) fn authorize_service(&self, token: &str) -> Result<(), ServiceError> {
+        // ---
+        let admin = self.state.admin();
+        if admin.service_tokens.is_empty() && admin.api_token.is_none() {
+            tracing::warn!(
+                "Service request rejected: neither AXUM_SERVICE_TOKENS nor AXUM_ADMIN_TOKEN is set"
+            );
+            return Err(ServiceError::new(
+                StatusCode::FORBIDDEN,
+                "Service authentication is disabled",
+            ));
+        }
+
+        if !self.is_service_token(token) {
+            tracing::warn!("Service request rejected: invalid token");
+            return Err(ServiceError::new(
+                StatusCode::UNAUTHORIZED,
+                "Invalid service token",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Whether `token` is one of `AXUM_SERVICE_TOKENS` or the admin token.
+    pub(crate) fn is_service_token(&self, token: &str) -> bool {
+        // ---
+        let admin = self.state.admin();
+
+        // Compare against every token so timing does not reveal which matched
+        admin
+            .service_tokens
+            .iter()
+            .chain(&admin.api_token)
+            .fold(false, |matched, expected| {
+                constant_time_eq(token.as_bytes(), expected.as_bytes()) | matched
+            })
+    }
+
+    /// The session `token` belongs to, or `None` if it is unknown, expired,
+    /// or revoked. As in OAuth token introspection (RFC 7662), an invalid
+    /// token is an answer, not an error.
+    ///
+    /// # Errors
+    /// `500` if Redis fails, leaving the token's validity unknown.
+    pub(crate) async fn introspect(&self, token: &str) -> Result<Option<SessionInfo>, StatusCode> {
+        // ---
+        let mut conn = self.state.get_conn().await?;
+        match self.state.sessions().validate(&mut conn, token).await {
+            Ok(session) => Ok(Some(session)),
+            Err(StatusCode::UNAUTHORIZED) => Ok(None),
+            Err(status) => Err(status),
+        }
+    }
+
+    /// The session `token` belongs to, presented by `client` to `tenant`.
+    ///
+    /// A token presented from another network and with another user agent
+    /// than its session was created by is handled per
+    /// `AXUM_SESSION_FINGERPRINT`: reported once per session (log, audit
+    /// event, `session.fingerprint_changed` webhook), and with `revoke` also
+    /// ended, rejecting the request.
+    ///
+    /// # Errors
+    /// - `401` if the token is invalid, expired, revoked, including
+    ///   revoked now for its fingerprint, or from another tenant
+    /// - `500` if Redis fails
+    pub(crate) async fn authenticate(
+        &self,
+        token: &str,
+        client: &ClientFingerprint,
+        tenant: &Tenant,
+    ) -> Result<SessionInfo, StatusCode> {
+        // ---
+        let sessions = self.state.sessions();
+        let mut conn = self.state.get_conn().await?;
+        let session = in_tenant(sessions.validate(&mut conn, token).await?, tenant)?;
+
+        let policy = sessions.fingerprint_policy();
+        if policy == FingerprintPolicy::Off {
+            return Ok(session);
+        }
+        let Some(change) = client.change_from(&session.issued_to()) else {
+            return Ok(session);
+        };
+
+        let revoke = policy == FingerprintPolicy::Revoke;
+        if revoke {
+            sessions.revoke(&mut conn, token).await?;
+        } else if !first_fingerprint_report(&mut conn, token, &session).await {
+            return Ok(session);
+        }
+        report_fingerprint_change(&self.state, &session, &change, client, revoke).await;
+
+        match revoke {
+            true => Err(StatusCode::UNAUTHORIZED),
+            false => Ok(session),
+        }
+    }
+}
+
+/// `session`, unless it was created in another tenant than `tenant`.
+///
+/// # Errors
+/// `401` if the session belongs to another tenant, as for an unknown token.
+fn in_tenant(session: SessionInfo, tenant: &Tenant) -> Result<SessionInfo, StatusCode> {
+    // ---
+    if session.belongs_to(tenant) {
+        return Ok(session);
+    }
+    tracing::debug!(
+        "Session of '{}' from tenant '{}' rejected on tenant '{}'",
+        redact::username(&session.username),
+        session.tenant_id,
+        tenant.id()
+    );
+    Err(StatusCode::UNAUTHORIZED)
+}
+
+/// Consumes the ceremony state stored under `key` (atomic GETDEL, so a
+/// challenge can only be answered once) and deserializes it.
+///
+/// # Errors
+/// - `400` if the flow is unknown or expired
+/// - `503` if Redis fails, so an outage is not mistaken for an expired
+///   challenge
+///
+/// A state that does not deserialize is returned as the inner error.
+async fn take_challenge<T: serde::de::DeserializeOwned>(
+    state: &AppState,
+    conn: &mut RedisConnection,
+    ceremony: &str,
+    key: &str,
+    username: &str,
+) -> Result<serde_json::Result<T>, ServiceError> {
+    // ---
+    // A missing key is nil, which would otherwise decode as an empty Vec
+    let state_bytes: Option<Vec<u8>> = conn.get_del(key).await.map_err(|e| {
+        tracing::error!("Failed to take challenge from Redis: {:?}", e);
+        challenge_store_unavailable(state, ceremony)
+    })?;
+
+    let Some(state_bytes) = state_bytes else {
+        tracing::warn!(
+            "Challenge not found or expired for user: {}",
+            redact::username(username)
+        );
+        state.metrics().record_challenge_failure(ceremony, false);
+        return Err(ServiceError::new(
+            StatusCode::BAD_REQUEST,
+            "Challenge not found or expired",
+        ));
+    };
+    Ok(serde_json::from_slice(&state_bytes))
+}
+
+/// `503` for a ceremony finish that could not reach Redis to take its
+/// challenge, recorded as a challenge failure.
+fn challenge_store_unavailable(state: &AppState, ceremony: &str) -> ServiceError {
+    // ---
+    state.metrics().record_challenge_failure(ceremony, true);
+    ServiceError::new(
+        StatusCode::SERVICE_UNAVAILABLE,
+        "Challenge store unavailable",
+    )
+}
+
+/// Handles a sign-in whose signature counter did not increase.
+///
+/// The credential is flagged as possibly cloned (`compromised_at`) and, if
+/// `AXUM_DISABLE_CLONED_CREDENTIALS` is set, soft-deleted. The first time a
+/// credential is flagged, a `credential.suspected_clone` webhook and audit
+/// event are emitted. Storage failures are logged; the sign-in is rejected
+/// either way.
+///
+/// Returns the error for the client: 403 Forbidden with the same generic
+/// message as other failures, so operators can tell the cases apart in
+/// access logs without telling the client more.
+async fn report_suspected_clone(
+    state: &AppState,
+    credential_id: &[u8],
+    client_ip: Option<IpAddr>,
+) -> ServiceError {
+    // ---
+    let credential_hex = hex::encode(credential_id);
+    let repository = state.repository();
+    state.metrics().record_sign_count_anomaly(true);
+
+    // Look up the owner first; disabling hides the credential.
+    let owner = match repository.get_credential_by_id(credential_id).await {
+        Ok(Some(credential)) => repository
+            .get_user_by_id(credential.user_id)
+            .await
+            .ok()
+            .flatten(),
+        _ => None,
+    };
+
+    // The owner's `credential.suspected_clone` webhook event is stored with
+    // the flag, in one transaction
+    let credential_b64 = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(credential_id);
+    let flagged = match &owner {
+        Some(user) => {
+            let event = WebhookEvent::new(
+                WebhookEventKind::CredentialSuspectedClone,
+                user.id,
+                &user.username,
+                &credential_b64,
+            );
+            let write = OutboxWrite::MarkCredentialCompromised(credential_id.to_vec());
+            state
+                .webhooks()
+                .emit_with(&**repository, write, event)
+                .await
+        }
+        None => repository.mark_credential_compromised(credential_id).await,
+    }
+    .inspect_err(|e| tracing::error!("Failed to flag credential {credential_hex}: {:?}", e))
+    .unwrap_or(false);
+
+    let disabled = state.credential_policy().disable_cloned
+        && repository
+            .soft_delete_credential(credential_id)
+            .await
+            .inspect_err(|e| {
+                tracing::error!("Failed to disable credential {credential_hex}: {:?}", e)
+            })
+            .unwrap_or(false);
+
+    tracing::warn!(
+        "Possible cloned authenticator for credential {} (disabled: {})",
+        credential_hex,
+        disabled
+    );
+
+    if let (true, Some(user)) = (flagged, owner) {
+        state.events().publish(
+            ServerEvent::audit(
+                "credential.suspected_clone",
+                serde_json::json!({
+                    "user_id": user.id,
+                    "username": user.username,
+                    "credential_id": credential_b64,
+                    "disabled": disabled,
+                }),
+            )
+            .with_client_ip(client_ip),
+        );
+    }
+
+    ServiceError::new(StatusCode::FORBIDDEN, "Authentication failed")
+}
+
+/// Marks `session` as reported for a fingerprint change until it expires.
+/// Returns whether it was not marked yet; if Redis fails, the change is
+/// reported again rather than not at all.
+async fn first_fingerprint_report(
+    conn: &mut RedisConnection,
+    token: &str,
+    session: &SessionInfo,
+) -> bool {
+    // ---
+    let digest = hex::encode(Sha256::digest(token.as_bytes()));
+    let ttl = (session.expires_at - chrono::Utc::now())
+        .num_seconds()
+        .max(1) as u64;
+    redis::cmd("SET")
+        .arg(redis_keys::session_fingerprint_reported(&digest))
+        .arg(1)
+        .arg("NX")
+        .arg("EX")
+        .arg(ttl)
+        .query_async::<Option<String>>(conn)
+        .await
+        .inspect_err(|e| tracing::warn!("Failed to mark fingerprint change as reported: {e}"))
+        .map(|set| set.is_some())
+        .unwrap_or(true)
+}
+
+/// Logs, audits, and emits the `session.fingerprint_changed` webhook event
+/// for `session`, now presented by `client`. Failures to store the event are
+/// logged and otherwise ignored.
+async fn report_fingerprint_change(
+    state: &AppState,
+    session: &SessionInfo,
+    change: &FingerprintChange,
+    client: &ClientFingerprint,
+    revoked: bool,
+) {
+    // ---
+    tracing::warn!(
+        "Session of {} used from {} with another user agent (created from {}; revoked: {})",
+        redact::username(&session.username),
+        change.to_network,
+        change.from_network,
+        revoked
+    );
+
+    state.events().publish(
+        ServerEvent::audit(
+            "session.fingerprint_changed",
+            serde_json::json!({
+                "user_id": session.user_id,
+                "username": session.username,
+                "from_network": change.from_network,
+                "to_network": change.to_network,
+                "revoked": revoked,
+            }),
+        )
+        .with_client_ip(client.ip),
+    );
+
+    let event = WebhookEvent::for_session(
+        WebhookEventKind::SessionFingerprintChanged,
+        session.user_id,
+        &session.username,
+    );
+    let emitted = state
+        .webhooks()
+        .emit_with(&**state.repository(), OutboxWrite::EventOnly, event)
+        .await;
+    if let Err(e) = emitted {
+        tracing::warn!(
+            "Failed to store session.fingerprint_changed event for {}: {e}",
+            redact::username(&session.username)
+        );
+    }
+}
+
+/// What to do with a sign-in, given its signature counter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SignCountVerdict {
+    // ---
+    Accept,
+    Warn,
+    Reject,
+}
+
+/// Applies the sign count policy to a stored and a newly reported counter.
+///
+/// An increasing counter is always accepted. Both counters at 0 means the
+/// authenticator does not implement one, which only [`SignCountPolicy::Strict`]
+/// rejects. Any other non-increasing counter is rejected as a possible clone
+/// unless the policy is [`SignCountPolicy::WarnOnly`].
+fn sign_count_verdict(policy: SignCountPolicy, stored: i64, provided: i64) -> SignCountVerdict {
+    // ---
+    if provided > stored {
+        return SignCountVerdict::Accept;
+    }
+
+    match policy {
+        SignCountPolicy::Strict => SignCountVerdict::Reject,
+        _ if stored == 0 && provided == 0 => SignCountVerdict::Accept,
+        SignCountPolicy::IgnoreWhenZero => SignCountVerdict::Reject,
+        SignCountPolicy::WarnOnly => SignCountVerdict::Warn,
+    }
+}
+
+/// Deserializes a stored passkey, optionally with its signature counter
+/// reset to 0 so `webauthn-rs` accepts any counter the authenticator reports.
+fn load_passkey(bytes: &[u8], reset_counter: bool) -> serde_json::Result<Passkey> {
+    // ---
+    if !reset_counter {
+        return serde_json::from_slice(bytes);
+    }
+
+    let mut value: serde_json::Value = serde_json::from_slice(bytes)?;
+    if let Some(counter) = value.pointer_mut("/cred/counter") {
+        *counter = 0.into();
+    }
+    serde_json::from_value(value)
+}
+
+/// Applies an authentication result to a stored credential.
+///
+/// `webauthn-rs` reports the new counter and the authenticator's current
+/// backup flags; they are written into the serialized [`Passkey`] so the
+/// next authentication starts from them, and into the credential's
+/// `backup_*` columns for reporting. The credential's `last_used_at` is set
+/// to now. Returns whether the passkey changed.
+///
+/// # Errors
+///
+/// Returns an error if the stored passkey cannot be deserialized, or the
+/// result is for a different credential.
+fn sync_passkey(
+    credential: &mut crate::domain::Credential,
+    auth_result: &AuthenticationResult,
+) -> anyhow::Result<bool> {
+    // ---
+    let mut passkey: Passkey = serde_json::from_slice(&credential.public_key)?;
+    let changed = passkey
+        .update_credential(auth_result)
+        .ok_or_else(|| anyhow::anyhow!("authentication result is for another credential"))?;
+
+    if changed {
+        credential.public_key = serde_json::to_vec(&passkey)?;
+    }
+    credential.counter = credential.counter.max(auth_result.counter().into());
+    credential.last_used_at = Some(chrono::Utc::now());
+    credential.backup_eligible = Some(auth_result.backup_eligible());
+    credential.backup_state = Some(auth_result.backup_state());
+    Ok(changed)
+}
+
+/// Backup eligibility and state (the BE and BS flags) of a new passkey.
+///
+/// `Passkey` does not expose these without the `danger-credential-internals`
+/// feature, so they are read from its serialized form. `None` if the format
+/// ever changes, rather than guessing.
+fn backup_flags(passkey: &Passkey) -> (Option<bool>, Option<bool>) {
+    // ---
+    let Ok(value) = serde_json::to_value(passkey) else {
+        return (None, None);
+    };
+    (
+        value["cred"]["backup_eligible"].as_bool(),
+        value["cred"]["backup_state"].as_bool(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+
+    /// Serialized passkey as stored at registration: counter 0, backup
+    /// eligible but not yet backed up.
+    fn stored_credential() -> crate::domain::Credential {
+        // ---
+        let passkey = serde_json::json!({
+            "cred": {
+                "cred_id": "AQID",
+                "cred": {
+                    "type_": "ES256",
+                    "key": { "EC_EC2": {
+                        "curve": "SECP256R1",
+                        "x": "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA",
+                        "y": "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA",
+                    }},
+                },
+                "counter": 0,
+                "transports": null,
+                "user_verified": true,
+                "backup_eligible": true,
+                "backup_state": false,
+                "registration_policy": "required",
+                "extensions": {},
+                "attestation": { "data": "None", "metadata": "None" },
+                "attestation_format": "none",
+            }
+        });
+        crate::domain::Credential::new(
+            vec![1, 2, 3],
+            Uuid::new_v4(),
+            serde_json::to_vec(&passkey).unwrap(),
+            0,
+        )
+    }
+
+    fn auth_result(cred_id: &str, counter: u32, backup_state: bool) -> AuthenticationResult {
+        // ---
+        serde_json::from_value(serde_json::json!({
+            "cred_id": cred_id,
+            "needs_update": true,
+            "user_verified": true,
+            "backup_state": backup_state,
+            "backup_eligible": true,
+            "counter": counter,
+            "extensions": {},
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn sync_passkey_persists_counter_and_backup_state() {
+        // ---
+        let mut credential = stored_credential();
+
+        assert!(sync_passkey(&mut credential, &auth_result("AQID", 5, true)).unwrap());
+        assert_eq!(credential.counter, 5);
+
+        let passkey: serde_json::Value = serde_json::from_slice(&credential.public_key).unwrap();
+        assert_eq!(passkey["cred"]["counter"], 5);
+        assert_eq!(passkey["cred"]["backup_state"], true);
+        assert_eq!(credential.backup_eligible, Some(true));
+        assert_eq!(credential.backup_state, Some(true));
+
+        // Same state again: nothing to rewrite
+        let before = credential.public_key.clone();
+        assert!(!sync_passkey(&mut credential, &auth_result("AQID", 5, true)).unwrap());
+        assert_eq!(credential.public_key, before);
+    }
+
+    #[test]
+    fn sign_count_policies() {
+        // ---
+        use SignCountPolicy::*;
+        use SignCountVerdict::*;
+
+        // (stored, provided) -> verdict for Strict, IgnoreWhenZero, WarnOnly
+        let cases = [
+            ((0, 1), [Accept, Accept, Accept]),
+            ((0, 0), [Reject, Accept, Accept]),
+            ((5, 5), [Reject, Reject, Warn]),
+            ((5, 0), [Reject, Reject, Warn]),
+        ];
+        for ((stored, provided), expected) in cases {
+            for (policy, verdict) in [Strict, IgnoreWhenZero, WarnOnly].into_iter().zip(expected) {
+                assert_eq!(
+                    sign_count_verdict(policy, stored, provided),
+                    verdict,
+                    "{policy:?} stored={stored} provided={provided}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn load_passkey_can_reset_counter() {
+        // ---
+        let mut credential = stored_credential();
+        sync_passkey(&mut credential, &auth_result("AQID", 7, false)).unwrap();
+
+        let reset =
+            serde_json::to_value(load_passkey(&credential.public_key, true).unwrap()).unwrap();
+        assert_eq!(reset["cred"]["counter"], 0);
+        let kept =
+            serde_json::to_value(load_passkey(&credential.public_key, false).unwrap()).unwrap();
+        assert_eq!(kept["cred"]["counter"], 7);
+    }
+
+    #[test]
+    fn sync_passkey_rejects_other_credential() {
+        // ---
+        let mut credential = stored_credential();
+        assert!(sync_passkey(&mut credential, &auth_result("BAUG", 5, true)).is_err());
+        assert_eq!(credential.counter, 0);
+    }
+
+    #[test]
+    fn backup_flags_read_from_passkey() {
+        // ---
+        let passkey: Passkey = serde_json::from_value(serde_json::json!({
+            "cred": {
+                "cred_id": "AQID",
+                "cred": {
+                    "type_": "ES256",
+                    "key": { "EC_EC2": {
+                        "curve": "SECP256R1",
+                        "x": "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA",
+                        "y": "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA",
+                    }},
+                },
+                "counter": 0,
+                "transports": null,
+                "user_verified": true,
+                "backup_eligible": true,
+                "backup_state": false,
+                "registration_policy": "required",
+                "extensions": {},
+                "attestation": { "data": "None", "metadata": "None" },
+                "attestation_format": "none",
+            }
+        }))
+        .unwrap();
+
+        assert_eq!(backup_flags(&passkey), (Some(true), Some(false)));
+    }
+}
@@ -0,0 +1,171 @@
+//! Redis keys and serialization for in-flight WebAuthn ceremonies.
+//!
+//! Each `*/start` call opens a flow with a server-generated ID that the
+//! client echoes at `*/finish`. Keying challenges by user *and* flow lets a
+//! user run several ceremonies at once (e.g. registering two devices)
+//! without one overwriting the other's challenge, and each flow expires on
+//! its own TTL. Keys of non-default tenants also carry the tenant ID, as
+//! the same username can exist in several tenants.
+//!
+//! Ceremony states are serialized into buffers taken from a small pool and
+//! returned once Redis has the state, so concurrent `*/start` calls reuse
+//! a few buffers rather than allocating and growing a new one each time.
+
+use crate::domain::normalize_username;
+use crate::redis_keys;
+use crate::tenant::Tenant;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::ops::Deref;
+use std::sync::{Mutex, PoisonError};
+use uuid::Uuid;
+
+/// Buffers kept for reuse; starts beyond this many at once allocate.
+const MAX_POOLED_BUFFERS: usize = 64;
+
+/// Buffers that grew past this (a sign-in offering many passkeys) are
+/// dropped rather than kept. States are usually a few KB.
+const MAX_POOLED_CAPACITY: usize = 64 * 1024;
+
+static BUFFERS: Lazy<BufferPool> = Lazy::new(BufferPool::default);
+
+/// Registration ceremony (`/webauthn/register/*`).
+pub(super) const REGISTRATION: &str = "reg";
+
+/// Authentication ceremony (`/webauthn/auth/*`).
+pub(super) const AUTHENTICATION: &str = "auth";
+
+/// Returns the Redis key holding the challenge state for one flow. The
+/// username is normalized, so the finish call may spell it differently.
+pub(super) fn challenge_key(
+    tenant: &Tenant,
+    ceremony: &str,
+    username: &str,
+    flow_id: Uuid,
+) -> String {
+    // ---
+    let username = normalize_username(username);
+    redis_keys::challenge(tenant.id(), ceremony, &username, flow_id)
+}
+
+/// Serializes a ceremony state as JSON into a pooled buffer.
+pub(super) fn serialize_challenge<T: Serialize>(
+    state: &T,
+) -> serde_json::Result<PooledBuffer<'static>> {
+    // ---
+    let mut buffer = BUFFERS.take();
+    serde_json::to_writer(&mut buffer.bytes, state)?;
+    Ok(buffer)
+}
+
+#[derive(Default)]
+struct BufferPool {
+    // ---
+    buffers: Mutex<Vec<Vec<u8>>>,
+}
+
+impl BufferPool {
+    // ---
+
+    fn take(&self) -> PooledBuffer<'_> {
+        // ---
+        let bytes = self
+            .buffers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .pop()
+            .unwrap_or_default();
+        PooledBuffer { pool: self, bytes }
+    }
+
+    fn give_back(&self, mut bytes: Vec<u8>) {
+        // ---
+        if bytes.capacity() > MAX_POOLED_CAPACITY {
+            return;
+        }
+        bytes.clear();
+        let mut buffers = self.buffers.lock().unwrap_or_else(PoisonError::into_inner);
+        if buffers.len() < MAX_POOLED_BUFFERS {
+            buffers.push(bytes);
+        }
+    }
+}
+
+/// Serialized bytes, returned to their pool when dropped.
+pub(super) struct PooledBuffer<'a> {
+    // ---
+    pool: &'a BufferPool,
+    bytes: Vec<u8>,
+}
+
+impl Deref for PooledBuffer<'_> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        // ---
+        &self.bytes
+    }
+}
+
+impl Drop for PooledBuffer<'_> {
+    fn drop(&mut self) {
+        // ---
+        self.pool.give_back(std::mem::take(&mut self.bytes));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+    use crate::domain::DEFAULT_TENANT;
+    use redis_keys::challenge as tenant_challenge_key;
+
+    #[test]
+    fn flows_are_namespaced_per_tenant_user_and_ceremony() {
+        // ---
+        let flow = Uuid::new_v4();
+        let key = tenant_challenge_key(DEFAULT_TENANT, REGISTRATION, "alice", flow);
+        assert_eq!(key, format!("webauthn:reg:alice:{flow}"));
+
+        let key_for = |tenant, ceremony, username, flow| {
+            tenant_challenge_key(tenant, ceremony, username, flow)
+        };
+        assert_ne!(
+            key,
+            key_for(DEFAULT_TENANT, REGISTRATION, "alice", Uuid::new_v4())
+        );
+        assert_ne!(key, key_for(DEFAULT_TENANT, AUTHENTICATION, "alice", flow));
+        assert_ne!(key, key_for(DEFAULT_TENANT, REGISTRATION, "bob", flow));
+        assert_eq!(
+            key_for("acme", REGISTRATION, "alice", flow),
+            format!("webauthn:acme:reg:alice:{flow}")
+        );
+    }
+
+    #[test]
+    fn buffers_are_reused_unless_oversized() {
+        // ---
+        let pool = BufferPool::default();
+        let mut buffer = pool.take();
+        buffer.bytes.extend_from_slice(b"{}");
+        let reused = buffer.bytes.as_ptr();
+        drop(buffer);
+
+        let buffer = pool.take();
+        assert!(buffer.is_empty());
+        assert_eq!(buffer.bytes.as_ptr(), reused);
+        drop(buffer);
+
+        let mut buffer = pool.take();
+        buffer.bytes.reserve(MAX_POOLED_CAPACITY * 2);
+        drop(buffer);
+        assert!(pool.buffers.lock().unwrap().is_empty());
+
+        let state = serde_json::json!({ "challenge": "AQID" });
+        assert_eq!(
+            &*serialize_challenge(&state).unwrap(),
+            br#"{"challenge":"AQID"}"#
+        );
+    }
+}
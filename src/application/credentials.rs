@@ -0,0 +1,305 @@
+//! A user's passkeys: listing, deleting, and the per-user limit on how
+//! many they may hold.
+//!
+//! Each user may hold at most `AXUM_MAX_CREDENTIALS_PER_USER` active
+//! passkeys, unless an admin overrides the limit for them. Overrides are
+//! stored in Redis without expiry. A limit of 0 means unlimited.
+
+use super::ServiceError;
+use crate::app_state::AppState;
+use crate::domain::{Credential, OutboxWrite};
+use crate::events::DomainEvent;
+use crate::redact;
+use crate::redis_keys;
+use crate::session::SessionInfo;
+use crate::webhooks::{WebhookEvent, WebhookEventKind};
+use axum::http::StatusCode;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use redis::AsyncCommands;
+use uuid::Uuid;
+
+/// Page size used when the client does not specify `per_page`.
+const DEFAULT_PER_PAGE: u32 = 50;
+
+/// Upper bound on `per_page` to keep list responses bounded.
+const MAX_PER_PAGE: u32 = 100;
+
+/// One page of a user's credentials.
+pub(crate) struct CredentialPage {
+    // ---
+    pub credentials: Vec<Credential>,
+    pub page: u32,
+    pub per_page: u32,
+    pub total: u64,
+}
+
+/// Decodes a credential ID from its URL-safe, unpadded base64 form, as
+/// returned when listing credentials.
+pub(crate) fn decode_credential_id(encoded: &str) -> Result<Vec<u8>, base64::DecodeError> {
+    // ---
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.decode(encoded)
+}
+
+/// Whether a user holding `held` credentials may register another.
+pub(super) fn within_limit(limit: u32, held: usize) -> bool {
+    // ---
+    limit == 0 || held < limit as usize
+}
+
+/// Credential operations shared by the HTTP handlers and other transports.
+/// Cheap to clone.
+#[derive(Clone)]
+pub(crate) struct CredentialService {
+    // ---
+    state: AppState,
+}
+
+impl CredentialService {
+    // ---
+
+    pub(crate) fn new(state: AppState) -> Self {
+        // ---
+        Self { state }
+    }
+
+    /// Page `page` (1-based, default 1) of the credentials of `session`'s
+    /// user, `per_page` (default 50, at most 100) at a time.
+    ///
+    /// # Errors
+    /// `500` if the database query fails.
+    pub(crate) async fn list(
+        &self,
+        session: &SessionInfo,
+        page: Option<u32>,
+        per_page: Option<u32>,
+    ) -> Result<CredentialPage, ServiceError> {
+        // ---
+        tracing::info!(
+            "Listing credentials for user: {} ({})",
+            redact::username(&session.username),
+            session.user_id
+        );
+
+        let credentials = self
+            .state
+            .repository()
+            .get_credentials_by_user(session.user_id)
+            .await
+            .map_err(|e| {
+                // ---
+                tracing::error!(
+                    "Failed to fetch credentials for user {}: {}",
+                    session.user_id,
+                    e
+                );
+                ServiceError::internal("Failed to fetch credentials")
+            })?;
+
+        let page = page.unwrap_or(1).max(1);
+        let per_page = per_page.unwrap_or(DEFAULT_PER_PAGE).clamp(1, MAX_PER_PAGE);
+        let total = credentials.len() as u64;
+        let offset = (page as usize - 1).saturating_mul(per_page as usize);
+        let credentials: Vec<_> = credentials
+            .into_iter()
+            .skip(offset)
+            .take(per_page as usize)
+            .collect();
+
+        tracing::info!(
+            "Found {} credentials for user: {} (page {} of {} total)",
+            credentials.len(),
+            redact::username(&session.username),
+            page,
+            total
+        );
+
+        Ok(CredentialPage {
+            credentials,
+            page,
+            per_page,
+            total,
+        })
+    }
+
+    /// When `session`'s user's credentials last changed (see
+    /// [`Repository::credentials_modified_at`]), for conditional listing.
+    ///
+    /// # Errors
+    /// `500` if the database query fails.
+    ///
+    /// [`Repository::credentials_modified_at`]: crate::domain::Repository::credentials_modified_at
+    pub(crate) async fn modified_at(
+        &self,
+        session: &SessionInfo,
+    ) -> Result<Option<DateTime<Utc>>, ServiceError> {
+        // ---
+        self.state
+            .repository()
+            .credentials_modified_at(session.user_id)
+            .await
+            .map_err(|e| {
+                // ---
+                tracing::error!(
+                    "Failed to check credentials of user {}: {}",
+                    session.user_id,
+                    e
+                );
+                ServiceError::internal("Failed to fetch credentials")
+            })
+    }
+
+    /// Soft-deletes one of `session`'s user's credentials, given its base64
+    /// ID, with its `credential.deleted` webhook event. The credential stops
+    /// working immediately and is purged once the retention window passes.
+    ///
+    /// # Errors
+    /// - `400` if the ID is not valid base64
+    /// - `404` if there is no such credential
+    /// - `403` if it belongs to another user
+    /// - `500` if the database fails
+    pub(crate) async fn delete(
+        &self,
+        session: &SessionInfo,
+        credential_id_base64: &str,
+    ) -> Result<(), ServiceError> {
+        // ---
+        tracing::info!(
+            "Deleting credential {} for user: {} ({})",
+            credential_id_base64,
+            redact::username(&session.username),
+            session.user_id
+        );
+
+        let credential_id = decode_credential_id(credential_id_base64).map_err(|e| {
+            // ---
+            tracing::warn!("Invalid base64 credential ID: {}", e);
+            ServiceError::new(StatusCode::BAD_REQUEST, "Invalid credential ID format")
+        })?;
+
+        // Verify credential exists and belongs to this user
+        let repository = self.state.repository();
+        let credential = repository
+            .get_credential_by_id(&credential_id)
+            .await
+            .map_err(|e| {
+                // ---
+                tracing::error!("Failed to query credential: {}", e);
+                ServiceError::internal("Failed to query credential")
+            })?
+            .ok_or_else(|| {
+                // ---
+                tracing::warn!("Credential not found: {}", credential_id_base64);
+                ServiceError::new(StatusCode::NOT_FOUND, "Credential not found")
+            })?;
+
+        // Prevent deletion of other users' credentials
+        if credential.user_id != session.user_id {
+            // ---
+            tracing::warn!(
+                "User {} attempted to delete credential belonging to user {}",
+                session.user_id,
+                credential.user_id
+            );
+            return Err(ServiceError::new(
+                StatusCode::FORBIDDEN,
+                "Cannot delete credential belonging to another user",
+            ));
+        }
+
+        // Soft-delete credential (purged later by the retention job), storing
+        // its `credential.deleted` webhook event in the same transaction
+        let event = WebhookEvent::new(
+            WebhookEventKind::CredentialDeleted,
+            session.user_id,
+            &session.username,
+            credential_id_base64,
+        );
+        self.state
+            .webhooks()
+            .emit_with(
+                &**repository,
+                OutboxWrite::SoftDeleteCredential(credential_id),
+                event,
+            )
+            .await
+            .map_err(|e| {
+                // ---
+                tracing::error!("Failed to delete credential: {}", e);
+                ServiceError::internal("Failed to delete credential")
+            })?;
+
+        tracing::info!(
+            "Successfully deleted credential {} for user {}",
+            credential_id_base64,
+            redact::username(&session.username)
+        );
+
+        self.state
+            .domain_events()
+            .publish(DomainEvent::CredentialDeleted {
+                user_id: session.user_id,
+                username: session.username.clone(),
+                credential_id: credential_id_base64.to_string(),
+            });
+        Ok(())
+    }
+
+    /// The user's credential limit (0 = unlimited), and whether it is a
+    /// per-user override rather than the default.
+    pub(crate) async fn limit(&self, user_id: Uuid) -> redis::RedisResult<(u32, bool)> {
+        // ---
+        let key = &redis_keys::credential_limit(user_id);
+        let stored: Option<u32> = self
+            .state
+            .redis_read(move |mut conn| async move { conn.get(key).await })
+            .await?;
+        Ok(match stored {
+            Some(limit) => (limit, true),
+            None => (self.state.credential_policy().max_per_user, false),
+        })
+    }
+
+    /// Overrides the user's limit with `max_credentials`, or restores the
+    /// default if `None`. Existing credentials above a lowered limit are
+    /// kept; only new registrations are refused.
+    ///
+    /// # Errors
+    /// `500` if Redis fails.
+    pub(crate) async fn set_limit(
+        &self,
+        user_id: Uuid,
+        max_credentials: Option<u32>,
+    ) -> Result<(), ServiceError> {
+        // ---
+        let mut conn = self
+            .state
+            .get_conn()
+            .await
+            .map_err(|status| ServiceError::new(status, "Internal server error"))?;
+        let key = redis_keys::credential_limit(user_id);
+        match max_credentials {
+            Some(limit) => conn.set::<_, _, ()>(key, limit).await,
+            None => conn.del::<_, ()>(key).await,
+        }
+        .map_err(|e| {
+            tracing::error!("Credential limit storage error: {e}");
+            ServiceError::internal("Internal server error")
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+
+    #[test]
+    fn zero_limit_is_unlimited() {
+        // ---
+        assert!(within_limit(10, 9));
+        assert!(!within_limit(10, 10));
+        assert!(!within_limit(1, 3));
+        assert!(within_limit(0, 1_000));
+    }
+}
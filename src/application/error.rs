@@ -0,0 +1,30 @@
+//! Errors of the application services.
+
+use axum::http::StatusCode;
+
+/// Why an operation failed: the status it maps to and a message that is
+/// safe to show the client. Details are logged where the failure happens.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ServiceError {
+    // ---
+    pub status: StatusCode,
+    pub message: String,
+}
+
+impl ServiceError {
+    // ---
+
+    pub(crate) fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        // ---
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+
+    /// A `500 Internal Server Error` with `message`.
+    pub(crate) fn internal(message: impl Into<String>) -> Self {
+        // ---
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, message)
+    }
+}
@@ -0,0 +1,27 @@
+// Gateway module - business logic shared by the HTTP handlers and the gRPC server
+// Modules are private, only exported symbols are public
+
+mod auth;
+mod challenge;
+mod credentials;
+mod error;
+mod movies;
+#[cfg(feature = "password")]
+mod password;
+mod quotas;
+mod stats;
+mod subscribers;
+
+pub(crate) use auth::{AuthService, USER_ROLE};
+#[cfg(feature = "fuzzing")]
+pub(crate) use credentials::decode_credential_id;
+pub(crate) use credentials::CredentialService;
+pub(crate) use error::ServiceError;
+#[cfg(feature = "test-utils")]
+pub(crate) use movies::{remove_movie, save_movie};
+pub(crate) use movies::{Movie, MovieError, MovieService, StoredMovie};
+#[cfg(feature = "password")]
+pub(crate) use password::PasswordService;
+pub(crate) use quotas::{QuotaPeriod, QuotaService, QuotaWindow};
+pub(crate) use stats::{AdminStats, StatsService};
+pub(crate) use subscribers::spawn_subscribers;
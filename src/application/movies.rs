@@ -0,0 +1,775 @@
+//! Movie catalogue: validation, storage in Redis, and the title and genre
+//! indexes.
+//!
+//! Movies are stored as JSON under their ID, tagged with the
+//! `schema_version` of their layout; older layouts are upgraded when read
+//! and rewritten in the current one on their next save. A title index maps
+//! each normalized title and year to the movie's ID so duplicates are
+//! detected, and sets hold the IDs of every movie and of the movies in each
+//! genre.
+//!
+//! Claiming a title and writing the movie happen in one Lua script, so two
+//! concurrent adds of the same title cannot both succeed, and a save takes
+//! one round trip unless it has stale index entries to clean up.
+
+use crate::app_state::AppState;
+use crate::events::DomainEvent;
+use crate::redis_keys;
+use crate::redis_source::RedisConnection;
+use axum::http::StatusCode;
+use chrono::{Datelike, Utc};
+use once_cell::sync::Lazy;
+use redis::{AsyncCommands, Script};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+use uuid::Uuid;
+
+/// Most genres one movie may have.
+const MAX_GENRES: usize = 10;
+
+/// Longest genre name, in bytes.
+const MAX_GENRE_LEN: usize = 32;
+
+/// Default page size for `GET /movies/list`.
+const DEFAULT_PER_PAGE: u32 = 50;
+
+/// Upper bound on `per_page` to keep list responses bounded.
+const MAX_PER_PAGE: u32 = 100;
+
+/// Layout of the stored movie JSON. Movies stored without a
+/// `schema_version` are version 1.
+///
+/// - 1: `title`, `year`, `stars`
+/// - 2: adds `genres`
+const MOVIE_SCHEMA_VERSION: u64 = 2;
+
+/// Claims the title index for a movie and writes the movie and its set
+/// memberships, unless another movie owns the title.
+///
+/// `KEYS`: title index, movie, set of all movies, then the sets of the
+/// movie's genres. `ARGV`: movie ID, movie JSON. Returns `{"conflict"}`, or
+/// `{"ok", previous}` with the JSON it replaced (nil for a new movie).
+static SAVE_MOVIE: Lazy<Script> = Lazy::new(|| {
+    Script::new(
+        r"
+local owner = redis.call('GET', KEYS[1])
+if owner and owner ~= ARGV[1] then
+    return {'conflict'}
+end
+local previous = redis.call('GET', KEYS[2])
+redis.call('SET', KEYS[2], ARGV[2])
+redis.call('SET', KEYS[1], ARGV[1])
+for i = 3, #KEYS do
+    redis.call('SADD', KEYS[i], ARGV[1])
+end
+return {'ok', previous}
+",
+    )
+});
+
+/// Deletes a title index entry (`KEYS[1]`) only if it still points at the
+/// movie `ARGV[1]`.
+static RELEASE_TITLE: Lazy<Script> = Lazy::new(|| {
+    Script::new(
+        r"
+if redis.call('GET', KEYS[1]) == ARGV[1] then
+    return redis.call('DEL', KEYS[1])
+end
+return 0
+",
+    )
+});
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+pub struct Movie {
+    pub(crate) title: String,
+    pub(crate) year: u16,
+    pub(crate) stars: f32,
+
+    /// Lowercase genre names such as `scifi`, sorted and without
+    /// duplicates once sanitized. Optional in request bodies.
+    #[serde(default)]
+    pub(crate) genres: Vec<String>,
+}
+
+/// A movie as stored in Redis.
+#[derive(Serialize)]
+struct MovieRecord<'a> {
+    // ---
+    schema_version: u64,
+    #[serde(flatten)]
+    movie: &'a Movie,
+}
+
+/// The stored JSON of `movie`, in the current layout.
+fn encode_movie(movie: &Movie) -> serde_json::Result<String> {
+    // ---
+    serde_json::to_string(&MovieRecord {
+        schema_version: MOVIE_SCHEMA_VERSION,
+        movie,
+    })
+}
+
+/// Reads stored movie JSON of any layout, upgrading older ones. A movie
+/// written by a newer server (rolling deploys) is read as far as this
+/// layout goes; fields it does not know are ignored.
+fn decode_movie(json: &str) -> serde_json::Result<Movie> {
+    // ---
+    let mut record: serde_json::Map<String, serde_json::Value> = serde_json::from_str(json)?;
+    let version = record
+        .remove("schema_version")
+        .and_then(|version| version.as_u64())
+        .unwrap_or(1);
+
+    if version < 2 {
+        record
+            .entry("genres")
+            .or_insert_with(|| serde_json::Value::Array(Vec::new()));
+    }
+
+    serde_json::from_value(serde_json::Value::Object(record))
+}
+
+#[derive(Debug, Clone)]
+pub struct HashKey {
+    pub value: String,
+}
+
+impl HashKey {
+    // ---
+
+    /// The Redis key mapping this title and year to a movie ID.
+    pub fn index_key(&self) -> String {
+        // ---
+        redis_keys::movie_title(&self.value)
+    }
+}
+
+/// Lowercases and trims a genre name, as stored and as matched by the
+/// list filter.
+fn normalize_genre(genre: &str) -> String {
+    // ---
+    genre.trim().to_lowercase()
+}
+
+/// Whether `genre` (already normalized) is a valid genre name: 1 to
+/// [`MAX_GENRE_LEN`] ASCII letters, digits, and hyphens, so it can be used
+/// in a URL as is.
+fn valid_genre(genre: &str) -> bool {
+    // ---
+    !genre.is_empty()
+        && genre.len() <= MAX_GENRE_LEN
+        && genre.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+/// Error from a movie operation. Handlers send it as an `ApiError` body,
+/// the gRPC server as a status.
+///
+/// For `Status` the HTTP error code follows from the status: `invalid_movie`,
+/// `movie_not_found`, `movie_exists`, `service_unavailable`, or
+/// `internal_error`.
+#[derive(Debug)]
+pub enum MovieError {
+    // ---
+    Status(StatusCode),
+
+    /// The `genre` filter is not a valid genre name (400 `invalid_genre`).
+    InvalidGenre,
+}
+
+impl From<StatusCode> for MovieError {
+    fn from(status: StatusCode) -> Self {
+        // ---
+        Self::Status(status)
+    }
+}
+
+impl MovieError {
+    // ---
+
+    /// HTTP status the error is answered with.
+    pub fn status(&self) -> StatusCode {
+        // ---
+        match self {
+            Self::Status(status) => *status,
+            Self::InvalidGenre => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+impl Movie {
+    // ---
+
+    /// Sanitizes the Movie instance by trimming whitespace,
+    /// collapsing multiple spaces, normalizing genres, validating fields,
+    /// and generating
+    /// a HashKey based on normalized title and year. The key detects
+    /// duplicates; it is not the movie's ID.
+    pub fn sanitize(&mut self) -> Result<HashKey, StatusCode> {
+        // ---
+
+        let re = Regex::new(r"\s+").unwrap();
+
+        // Trim leading/trailing and collapse internal spaces
+        let trimmed = self.title.trim();
+        let squeezed = re.replace_all(trimmed, " ");
+        self.title = squeezed.to_string();
+
+        // Validation
+        if self.title.is_empty() {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+
+        let current_year = Utc::now().year() as u16;
+        if self.year < 1880 || self.year > current_year + 5 {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+
+        if !(0.0..=5.0).contains(&self.stars) {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+
+        let mut genres: Vec<String> = self.genres.iter().map(|g| normalize_genre(g)).collect();
+        genres.sort();
+        genres.dedup();
+        if genres.len() > MAX_GENRES || !genres.iter().all(|g| valid_genre(g)) {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+        self.genres = genres;
+
+        // Now generate the lookup key
+        let combined = format!("{}:{}", self.title.to_lowercase(), self.year);
+        let mut hasher = Sha1::new();
+        hasher.update(combined.as_bytes());
+        let result = hasher.finalize();
+        let key = hex::encode(result);
+
+        Ok(HashKey { value: key })
+    }
+}
+
+/// A stored movie and its ID, as returned by `POST /movies/add` and
+/// `GET /movies/list`.
+#[derive(Serialize)]
+pub struct StoredMovie {
+    pub(crate) id: String,
+    #[serde(flatten)]
+    pub(crate) movie: Movie,
+}
+
+/// One page of movies, sorted by title, then year.
+pub(crate) struct MoviePage {
+    // ---
+    pub movies: Vec<StoredMovie>,
+
+    /// The genre filter, normalized.
+    pub genre: Option<String>,
+
+    /// 1-based.
+    pub page: u32,
+    pub per_page: u32,
+
+    /// Movies on all pages.
+    pub total: u64,
+}
+
+/// Movie operations shared by the HTTP handlers and the gRPC server.
+///
+/// Publishes a domain event for each change, from which the movie metrics
+/// (`movies_created_total`, ...) are recorded; request metrics belong to
+/// the transport. Cheap to clone.
+#[derive(Clone)]
+pub(crate) struct MovieService {
+    // ---
+    state: AppState,
+}
+
+impl MovieService {
+    // ---
+
+    pub(crate) fn new(state: AppState) -> Self {
+        // ---
+        Self { state }
+    }
+
+    /// The movie with ID `id`.
+    ///
+    /// # Errors
+    /// `404` if there is none, `500` if Redis fails.
+    pub(crate) async fn get(&self, id: &str) -> Result<Movie, MovieError> {
+        // ---
+        tracing::debug!("get movie: {id}");
+
+        let key = &redis_keys::movie(id);
+        let result: Option<String> = self
+            .state
+            .redis_read(move |mut conn| async move { conn.get(key).await })
+            .await
+            .map_err(|err| {
+                tracing::info!("Got internal server error: {:?}", &err);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+
+        let Some(json_string) = result else {
+            tracing::trace!("Movie not found: {id}");
+            return Err(StatusCode::NOT_FOUND.into());
+        };
+
+        let movie = decode_movie(&json_string).map_err(|err| {
+            tracing::info!("Error parsing JSON: {:?}", &err);
+            StatusCode::BAD_REQUEST
+        })?;
+
+        tracing::trace!("Movie return: {}/{:?}", id, &movie);
+        Ok(movie)
+    }
+
+    /// Page `page` (1-based, default 1) of `per_page` movies (default 50,
+    /// at most 100), only those in `genre` if given.
+    ///
+    /// Movies stored before genres and listing existed are not listed until
+    /// they are updated.
+    ///
+    /// # Errors
+    /// [`MovieError::InvalidGenre`] for a malformed genre, `500` if Redis
+    /// fails.
+    pub(crate) async fn list(
+        &self,
+        genre: Option<&str>,
+        page: Option<u32>,
+        per_page: Option<u32>,
+    ) -> Result<MoviePage, MovieError> {
+        // ---
+        let genre = genre.map(normalize_genre);
+        let set_key = match &genre {
+            Some(genre) if !valid_genre(genre) => return Err(MovieError::InvalidGenre),
+            Some(genre) => redis_keys::movie_genre(genre),
+            None => redis_keys::all_movies(),
+        };
+
+        let set_key = &set_key;
+        let stored: Vec<(String, Option<String>)> = self
+            .state
+            .redis_read(move |mut conn| async move {
+                let ids: Vec<String> = conn.smembers(set_key).await?;
+                if ids.is_empty() {
+                    return Ok(Vec::new());
+                }
+                let keys: Vec<String> = ids.iter().map(|id| redis_keys::movie(id)).collect();
+                let values: Vec<Option<String>> = conn.mget(&keys).await?;
+                Ok(ids.into_iter().zip(values).collect())
+            })
+            .await
+            .map_err(|err| {
+                tracing::info!("Got internal server error: {:?}", &err);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+
+        // Skip IDs whose movie was deleted between the two reads
+        let mut movies: Vec<StoredMovie> = stored
+            .into_iter()
+            .filter_map(|(id, json)| {
+                let movie = decode_movie(&json?).ok()?;
+                Some(StoredMovie { id, movie })
+            })
+            .collect();
+        movies.sort_by(|a, b| {
+            (a.movie.title.to_lowercase(), a.movie.year, &a.id).cmp(&(
+                b.movie.title.to_lowercase(),
+                b.movie.year,
+                &b.id,
+            ))
+        });
+
+        // Select the requested page
+        let page = page.unwrap_or(1).max(1);
+        let per_page = per_page.unwrap_or(DEFAULT_PER_PAGE).clamp(1, MAX_PER_PAGE);
+        let total = movies.len() as u64;
+        let offset = (page as usize - 1).saturating_mul(per_page as usize);
+        let movies = movies
+            .into_iter()
+            .skip(offset)
+            .take(per_page as usize)
+            .collect();
+
+        Ok(MoviePage {
+            movies,
+            genre,
+            page,
+            per_page,
+            total,
+        })
+    }
+
+    /// Stores `movie` under a new ID.
+    ///
+    /// # Errors
+    /// `400` if the movie is invalid, `409` if one with the same title and
+    /// year (compared case-insensitively, ignoring extra whitespace)
+    /// exists, `500` if Redis fails.
+    pub(crate) async fn add(&self, mut movie: Movie) -> Result<StoredMovie, MovieError> {
+        // ---
+        // Sanitize the movie and get the key that detects duplicates
+        let hash_key = movie.sanitize()?;
+        let mut conn = self.state.get_conn().await?;
+
+        let movie_id = Uuid::new_v4().to_string();
+
+        // Create a span with movie details for tracing
+        let span = tracing::info_span!(
+            "add_movie",
+            title = %movie.title,
+            year = movie.year,
+            id = %movie_id
+        );
+        let _enter = span.enter();
+
+        // The ID is new, so any owner of the title is a duplicate
+        tracing::debug!("Inserting new movie, id:{movie_id}");
+        save_movie(&mut conn, &movie_id, &movie, &hash_key)
+            .await
+            .inspect_err(|status| {
+                if *status == StatusCode::CONFLICT {
+                    tracing::debug!("Duplicate detected: {}", &hash_key.value);
+                }
+            })?;
+
+        self.state
+            .domain_events()
+            .publish(DomainEvent::MovieCreated {
+                id: movie_id.clone(),
+            });
+
+        Ok(StoredMovie {
+            id: movie_id,
+            movie,
+        })
+    }
+
+    /// Stores `movie` under `id`, overwriting any movie stored there.
+    ///
+    /// # Errors
+    /// `400` if the movie is invalid, `409` if a different movie already
+    /// has its title and year, `500` if Redis fails.
+    pub(crate) async fn update(&self, id: &str, mut movie: Movie) -> Result<(), MovieError> {
+        // ---
+        let hash_key = movie.sanitize()?;
+        let mut conn = self.state.get_conn().await?;
+
+        save_movie(&mut conn, id, &movie, &hash_key).await?;
+        self.state
+            .domain_events()
+            .publish(DomainEvent::MovieUpdated { id: id.to_string() });
+        Ok(())
+    }
+
+    /// Deletes the movie with ID `id`.
+    ///
+    /// # Errors
+    /// `404` if there is none, `500` if Redis fails.
+    pub(crate) async fn delete(&self, id: &str) -> Result<(), MovieError> {
+        // ---
+        let mut conn = self.state.get_conn().await?;
+
+        if !remove_movie(&mut conn, id).await? {
+            return Err(StatusCode::NOT_FOUND.into());
+        }
+        self.state
+            .domain_events()
+            .publish(DomainEvent::MovieDeleted { id: id.to_string() });
+        Ok(())
+    }
+}
+
+/// Stores `movie` under `movie_id`, overwriting any previous version, and
+/// updates the indexes: the title index points at it, and its ID is in the
+/// set of every movie and in the set of each of its genres.
+///
+/// Fails with `409 Conflict` if another movie already has the same title
+/// and year; the check and the write are atomic ([`SAVE_MOVIE`]). Index
+/// entries for a replaced title and year, or for genres the movie no longer
+/// has, are removed afterwards.
+pub(crate) async fn save_movie(
+    conn: &mut RedisConnection,
+    movie_id: &str,
+    movie: &Movie,
+    hash_key: &HashKey,
+) -> Result<(), StatusCode> {
+    // ---
+
+    tracing::trace!("save_movie {}/{:?}", &movie_id, &movie);
+
+    let movie_json = encode_movie(movie).map_err(|err| {
+        tracing::info!("Serialization error: {:?}", &err);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    tracing::trace!("Writing movie: {:?}", &movie_json);
+
+    let mut invocation = SAVE_MOVIE.prepare_invoke();
+    invocation
+        .key(hash_key.index_key())
+        .key(redis_keys::movie(movie_id))
+        .key(redis_keys::all_movies());
+    for genre in &movie.genres {
+        invocation.key(redis_keys::movie_genre(genre));
+    }
+    let reply: Vec<Option<String>> = invocation
+        .arg(movie_id)
+        .arg(movie_json)
+        .invoke_async(conn)
+        .await
+        .map_err(|err| {
+            tracing::info!("SAVE_MOVIE script failed for {movie_id}: {err}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    let (outcome, previous) = match reply.as_slice() {
+        [outcome] => (outcome.as_deref(), None),
+        [outcome, previous] => (outcome.as_deref(), previous.clone()),
+        _ => (None, None),
+    };
+    match outcome {
+        Some("ok") => {}
+        Some("conflict") => {
+            tracing::trace!("Conflict");
+            return Err(StatusCode::CONFLICT);
+        }
+        _ => {
+            tracing::info!("Unexpected save_movie reply: {:?}", &reply);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    }
+
+    // Drop the index entries of the version being replaced
+    if let Some(mut previous) = previous.and_then(|json| decode_movie(&json).ok()) {
+        if let Ok(old_key) = previous.sanitize() {
+            if old_key.value != hash_key.value {
+                remove_index(conn, &old_key, movie_id).await?;
+            }
+        }
+        previous
+            .genres
+            .retain(|genre| !movie.genres.contains(genre));
+        if !previous.genres.is_empty() {
+            remove_from_genres(conn, movie_id, &previous.genres).await?;
+        }
+    }
+
+    tracing::debug!("save movie OK");
+    Ok(())
+}
+
+/// Deletes the title index entry for `hash_key` if it still points at
+/// `movie_id`.
+async fn remove_index(
+    conn: &mut RedisConnection,
+    hash_key: &HashKey,
+    movie_id: &str,
+) -> Result<(), StatusCode> {
+    // ---
+    RELEASE_TITLE
+        .key(hash_key.index_key())
+        .arg(movie_id)
+        .invoke_async::<()>(conn)
+        .await
+        .map_err(|err| {
+            tracing::info!("RELEASE_TITLE script failed for {movie_id}: {err}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+/// Removes `movie_id` from the sets of `genres`.
+async fn remove_from_genres(
+    conn: &mut RedisConnection,
+    movie_id: &str,
+    genres: &[String],
+) -> Result<(), StatusCode> {
+    // ---
+    let mut pipe = redis::pipe();
+    for genre in genres {
+        pipe.srem(redis_keys::movie_genre(genre), movie_id).ignore();
+    }
+    pipe.query_async::<()>(conn).await.map_err(|err| {
+        tracing::info!("Got internal server error: {:?}", &err);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+/// Deletes the movie `movie_id` and its index entries, freeing its title
+/// and year. Returns whether it existed.
+pub(crate) async fn remove_movie(
+    conn: &mut RedisConnection,
+    movie_id: &str,
+) -> Result<bool, StatusCode> {
+    // ---
+    let deleted: Option<String> =
+        conn.get_del(redis_keys::movie(movie_id))
+            .await
+            .map_err(|err| {
+                tracing::info!("Got internal server error: {:?}", &err);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+
+    let mut genres = Vec::new();
+    if let Some(mut movie) = deleted.as_deref().and_then(|json| decode_movie(json).ok()) {
+        if let Ok(hash_key) = movie.sanitize() {
+            remove_index(conn, &hash_key, movie_id).await?;
+        }
+        genres = movie.genres;
+    }
+
+    let mut pipe = redis::pipe();
+    pipe.srem(redis_keys::all_movies(), movie_id).ignore();
+    for genre in &genres {
+        pipe.srem(redis_keys::movie_genre(genre), movie_id).ignore();
+    }
+    pipe.query_async::<()>(conn).await.map_err(|err| {
+        tracing::info!("Got internal server error: {:?}", &err);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(deleted.is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+
+    use super::*;
+    use axum::http::StatusCode;
+
+    fn sanitize_ok(title: &str, year: u16, stars: f32) -> HashKey {
+        let mut movie = Movie {
+            title: title.to_string(),
+            year,
+            stars,
+            ..Movie::default()
+        };
+        movie.sanitize().expect("Expected sanitize to succeed")
+    }
+
+    fn sanitize_err(title: &str, year: u16, stars: f32) -> StatusCode {
+        let mut movie = Movie {
+            title: title.to_string(),
+            year,
+            stars,
+            ..Movie::default()
+        };
+        movie.sanitize().unwrap_err()
+    }
+
+    #[test]
+    fn test_normal_title_sanitization() {
+        let key = sanitize_ok("The Shawshank Redemption", 1994, 4.5);
+        assert_eq!(key.value.len(), 40); // SHA1 hex = 40 characters
+    }
+
+    #[test]
+    fn test_title_with_extra_spaces() {
+        let key = sanitize_ok(" The    Shawshank    Redemption ", 1994, 4.5);
+        let key2 = sanitize_ok("The Shawshank Redemption", 1994, 4.5);
+        assert_eq!(
+            key.value, key2.value,
+            "Key must be the same after collapsing spaces"
+        );
+    }
+
+    #[test]
+    fn test_title_mixed_case() {
+        let key = sanitize_ok("The SHAWshank Redemption", 1994, 4.5);
+        let key2 = sanitize_ok("the shawshank redemption", 1994, 4.5);
+        assert_eq!(key.value, key2.value, "Key must be case-insensitive");
+    }
+
+    #[test]
+    fn test_empty_title_rejected() {
+        let status = sanitize_err("   ", 1994, 4.5);
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_bad_year_rejected() {
+        let status = sanitize_err("Test Movie", 1700, 4.5);
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let future_year = (chrono::Utc::now().year() as u16) + 10;
+        let status = sanitize_err("Test Movie", future_year, 4.5);
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_invalid_stars_rejected() {
+        let status = sanitize_err("Test Movie", 1994, -1.0);
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let status = sanitize_err("Test Movie", 1994, 6.0);
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    fn with_genres(genres: &[&str]) -> Movie {
+        Movie {
+            title: "Test Movie".to_string(),
+            year: 1994,
+            stars: 4.5,
+            genres: genres.iter().map(|g| g.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_stored_movies_are_versioned() {
+        let movie = with_genres(&["drama"]);
+        let json = encode_movie(&movie).unwrap();
+        let stored: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(stored["schema_version"], MOVIE_SCHEMA_VERSION);
+        assert_eq!(stored["genres"], serde_json::json!(["drama"]));
+
+        let decoded = decode_movie(&json).unwrap();
+        assert_eq!(decoded.title, "Test Movie");
+        assert_eq!(decoded.genres, ["drama"]);
+    }
+
+    #[test]
+    fn test_older_and_newer_layouts_are_read() {
+        // Version 1: no schema_version, no genres
+        let movie = decode_movie(r#"{"title":"Alien","year":1979,"stars":4.5}"#).unwrap();
+        assert_eq!(movie.title, "Alien");
+        assert!(movie.genres.is_empty());
+
+        // A newer layout with fields this server does not know
+        let movie = decode_movie(
+            r#"{"schema_version":9,"title":"Alien","year":1979,"stars":4.5,
+                "genres":["scifi"],"runtime_minutes":117}"#,
+        )
+        .unwrap();
+        assert_eq!(movie.genres, ["scifi"]);
+
+        assert!(decode_movie(r#"{"schema_version":2,"title":"Alien"}"#).is_err());
+    }
+
+    #[test]
+    fn test_genres_normalized() {
+        let mut movie = with_genres(&[" SciFi ", "drama", "scifi", "film-noir"]);
+        movie.sanitize().unwrap();
+        assert_eq!(movie.genres, ["drama", "film-noir", "scifi"]);
+    }
+
+    #[test]
+    fn test_invalid_genres_rejected() {
+        for genres in [
+            &["sci fi"][..],
+            &[""],
+            &["drämä"],
+            &["x".repeat(33).as_str()],
+        ] {
+            let status = with_genres(genres).sanitize().unwrap_err();
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{genres:?}");
+        }
+
+        let many: Vec<String> = (0..=MAX_GENRES).map(|n| format!("g{n}")).collect();
+        let many: Vec<&str> = many.iter().map(String::as_str).collect();
+        assert_eq!(
+            with_genres(&many).sanitize().unwrap_err(),
+            StatusCode::BAD_REQUEST
+        );
+    }
+}
@@ -0,0 +1,377 @@
+//! Password registration and sign-in, a fallback next to passkeys for
+//! deployments migrating to them (`--features password`).
+//!
+//! Passwords are hashed with argon2id using the `argon2` crate's defaults
+//! (19 MiB, 2 passes, 1 lane; the OWASP baseline) and stored as PHC strings,
+//! which carry their parameters, so stored hashes stay verifiable if the
+//! defaults change. Hashing runs on the blocking thread pool. A sign-in
+//! creates the same session as a passkey sign-in.
+
+use super::{ServiceError, StatsService};
+use crate::app_state::AppState;
+use crate::config::PasswordPolicy;
+use crate::events::DomainEvent;
+use crate::redact;
+use crate::session::ClientFingerprint;
+use crate::tenant::Tenant;
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use axum::http::StatusCode;
+use once_cell::sync::Lazy;
+use uuid::Uuid;
+
+/// Verified in place of a missing hash, so signing in as an unknown user
+/// or a user without a password takes as long as a wrong password.
+static DUMMY_HASH: Lazy<String> = Lazy::new(|| {
+    // ---
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(Uuid::new_v4().as_bytes(), &salt)
+        .expect("argon2 accepts a 16-byte password")
+        .to_string()
+});
+
+/// Password operations behind the `/auth/password` routes. Cheap to clone.
+#[derive(Clone)]
+pub(crate) struct PasswordService {
+    // ---
+    state: AppState,
+}
+
+impl PasswordService {
+    // ---
+
+    pub(crate) fn new(state: AppState) -> Self {
+        // ---
+        Self { state }
+    }
+
+    /// Creates the user `username` in `tenant`, signing in with `password`.
+    /// Returns the new user's ID. Existing users, including passkey users,
+    /// cannot add a password this way.
+    ///
+    /// # Errors
+    /// - `400` if `password` breaks the password policy
+    /// - `409` if the username is taken
+    /// - `500` if the database fails
+    pub(crate) async fn register(
+        &self,
+        tenant: &Tenant,
+        username: &str,
+        password: &str,
+    ) -> Result<Uuid, ServiceError> {
+        // ---
+        let state = &self.state;
+        if let Some(violation) = policy_violation(&state.password_policy(), username, password) {
+            return Err(ServiceError::new(StatusCode::BAD_REQUEST, violation));
+        }
+
+        let taken = || ServiceError::new(StatusCode::CONFLICT, "Username is already taken");
+        if self.find_user(tenant, username).await?.is_some() {
+            return Err(taken());
+        }
+
+        let password_hash = hash_password(password.to_string()).await?;
+        let user = match state
+            .repository()
+            .create_user_in(tenant.id(), username)
+            .await
+        {
+            Ok(user) => user,
+            Err(e) => {
+                // Lost a race for the name, or the database failed
+                if self.find_user(tenant, username).await?.is_some() {
+                    return Err(taken());
+                }
+                tracing::error!("Failed to create user: {}", e);
+                return Err(ServiceError::internal("Failed to create user"));
+            }
+        };
+
+        // A user left without a password is removed as an abandoned
+        // registration by the orphaned-user cleanup
+        let stored = state
+            .repository()
+            .set_password_hash(user.id, &password_hash)
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to store password: {}", e);
+                ServiceError::internal("Failed to store password")
+            })?;
+        if !stored {
+            return Err(ServiceError::internal("Failed to store password"));
+        }
+
+        tracing::info!(
+            "Password registration completed for user: {}",
+            redact::username(username)
+        );
+        state.domain_events().publish(DomainEvent::UserRegistered {
+            user_id: user.id,
+            username: user.username,
+            credential_id: None,
+        });
+        Ok(user.id)
+    }
+
+    /// Signs in as `username` with `password` and creates a session for
+    /// `client`. Returns the session token.
+    ///
+    /// Successes and wrong passwords are counted in the sign-in statistics,
+    /// like passkey sign-ins.
+    ///
+    /// # Errors
+    /// - `401` if the user does not exist, has no password, or the password
+    ///   is wrong; the same generic message in every case, to prevent
+    ///   username enumeration
+    /// - `500` if the database or Redis fails
+    pub(crate) async fn login(
+        &self,
+        tenant: &Tenant,
+        username: &str,
+        password: &str,
+        client: &ClientFingerprint,
+    ) -> Result<String, ServiceError> {
+        // ---
+        let result = self.sign_in(tenant, username, password, client).await;
+
+        let stats = StatsService::new(self.state.clone());
+        match &result {
+            Ok(_) => stats.record_auth(true).await,
+            Err(e) if e.status.is_client_error() => stats.record_auth(false).await,
+            Err(_) => {}
+        }
+        result
+    }
+
+    /// [`login`](Self::login) without the statistics.
+    async fn sign_in(
+        &self,
+        tenant: &Tenant,
+        username: &str,
+        password: &str,
+        client: &ClientFingerprint,
+    ) -> Result<String, ServiceError> {
+        // ---
+        let state = &self.state;
+        let failed = || ServiceError::new(StatusCode::UNAUTHORIZED, "Authentication failed");
+
+        // Longer passwords were never accepted, and would only cost hashing
+        if password.chars().count() > state.password_policy().max_length {
+            return Err(failed());
+        }
+
+        let user = self.find_user(tenant, username).await?;
+        let password_hash = match &user {
+            Some(user) => state
+                .repository()
+                .get_password_hash(user.id)
+                .await
+                .map_err(|e| {
+                    tracing::error!("Failed to fetch password hash: {}", e);
+                    ServiceError::internal("Internal server error")
+                })?,
+            None => None,
+        };
+
+        let verified = verify_password(password.to_string(), password_hash).await?;
+        let user = match user {
+            Some(user) if verified => user,
+            _ => {
+                tracing::warn!(
+                    "Password sign-in failed for user: {}",
+                    redact::username(username)
+                );
+                return Err(failed());
+            }
+        };
+
+        let mut conn = state
+            .get_conn()
+            .await
+            .map_err(|status| ServiceError::new(status, "Internal server error"))?;
+        let session_token = state
+            .sessions()
+            .create(
+                &mut conn,
+                user.id,
+                user.username.clone(),
+                &user.tenant_id,
+                client,
+            )
+            .await
+            .map_err(|status| {
+                tracing::error!(
+                    "Failed to create session for user: {}",
+                    redact::username(&user.username)
+                );
+                ServiceError::new(status, "Internal server error")
+            })?;
+
+        tracing::info!(
+            "User '{}' signed in with a password",
+            redact::username(username)
+        );
+        state.domain_events().publish(DomainEvent::AuthSucceeded {
+            user_id: user.id,
+            username: user.username,
+            credential_id: None,
+            client_ip: client.ip,
+        });
+        Ok(session_token)
+    }
+
+    async fn find_user(
+        &self,
+        tenant: &Tenant,
+        username: &str,
+    ) -> Result<Option<crate::domain::User>, ServiceError> {
+        // ---
+        self.state
+            .repository()
+            .get_user_by_username_in(tenant.id(), username)
+            .await
+            .map_err(|e| {
+                tracing::error!(
+                    "Database error fetching user '{}': {:?}",
+                    redact::username(username),
+                    e
+                );
+                ServiceError::internal("Internal server error")
+            })
+    }
+}
+
+/// Why `password` breaks `policy`, if it does.
+fn policy_violation(policy: &PasswordPolicy, username: &str, password: &str) -> Option<String> {
+    // ---
+    let length = password.chars().count();
+    if length < policy.min_length {
+        return Some(format!(
+            "Password must be at least {} characters",
+            policy.min_length
+        ));
+    }
+    if length > policy.max_length {
+        return Some(format!(
+            "Password must be at most {} characters",
+            policy.max_length
+        ));
+    }
+
+    let classes = [
+        password.chars().any(char::is_lowercase),
+        password.chars().any(char::is_uppercase),
+        password.chars().any(|c| c.is_ascii_digit()),
+        password
+            .chars()
+            .any(|c| !c.is_lowercase() && !c.is_uppercase() && !c.is_ascii_digit()),
+    ];
+    if classes.iter().filter(|&&present| present).count() < policy.min_classes {
+        return Some(format!(
+            "Password must mix at least {} of lowercase letters, uppercase letters, digits, and other characters",
+            policy.min_classes
+        ));
+    }
+
+    if password.to_lowercase() == username.to_lowercase() {
+        return Some("Password must not be the username".to_string());
+    }
+    None
+}
+
+/// Hashes `password` into a PHC string with a new random salt.
+async fn hash_password(password: String) -> Result<String, ServiceError> {
+    // ---
+    tokio::task::spawn_blocking(move || {
+        let salt = SaltString::generate(&mut OsRng);
+        Argon2::default()
+            .hash_password(password.as_bytes(), &salt)
+            .map(|hash| hash.to_string())
+    })
+    .await
+    .map_err(|e| ServiceError::internal(format!("password hashing task failed: {e}")))?
+    .map_err(|e| {
+        tracing::error!("Failed to hash password: {}", e);
+        ServiceError::internal("Failed to hash password")
+    })
+}
+
+/// Whether `password` matches `password_hash`. Without a hash, a dummy one
+/// is verified instead and the answer is `false`.
+async fn verify_password(
+    password: String,
+    password_hash: Option<String>,
+) -> Result<bool, ServiceError> {
+    // ---
+    tokio::task::spawn_blocking(move || {
+        let present = password_hash.is_some();
+        let stored = password_hash.unwrap_or_else(|| DUMMY_HASH.clone());
+        let matches = match PasswordHash::new(&stored) {
+            Ok(parsed) => Argon2::default()
+                .verify_password(password.as_bytes(), &parsed)
+                .is_ok(),
+            Err(e) => {
+                tracing::error!("Stored password hash does not parse: {}", e);
+                false
+            }
+        };
+        present && matches
+    })
+    .await
+    .map_err(|e| ServiceError::internal(format!("password verification task failed: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+
+    #[test]
+    fn policy_checks_length_classes_and_username() {
+        // ---
+        let policy = PasswordPolicy {
+            min_length: 8,
+            max_length: 16,
+            min_classes: 3,
+        };
+        let ok = |password| policy_violation(&policy, "alice", password).is_none();
+
+        assert!(ok("Correct-horse1"));
+        assert!(ok("ÄÖÜäöü12"), "lengths count characters, not bytes");
+        assert!(!ok("Short-1"));
+        assert!(!ok("Much-too-long-password-1"));
+        assert!(!ok("alllowercase1"));
+        assert!(ok("alllowercase1!"));
+
+        let policy = PasswordPolicy {
+            min_length: 5,
+            ..PasswordPolicy::default()
+        };
+        assert!(policy_violation(&policy, "Alice", "aLiCe").is_some());
+        assert!(policy_violation(&policy, "Alice", "alice2").is_none());
+    }
+
+    #[tokio::test]
+    async fn hashes_verify_only_their_password() {
+        // ---
+        let hash = hash_password("correct horse battery".into()).await.unwrap();
+        assert!(hash.starts_with("$argon2id$"), "{hash}");
+        assert_ne!(
+            hash,
+            hash_password("correct horse battery".into()).await.unwrap(),
+            "each hash has its own salt"
+        );
+
+        let verify = |password: &str, hash: Option<String>| verify_password(password.into(), hash);
+        assert!(verify("correct horse battery", Some(hash.clone()))
+            .await
+            .unwrap());
+        assert!(!verify("wrong horse battery", Some(hash)).await.unwrap());
+        assert!(!verify("correct horse battery", None).await.unwrap());
+        assert!(!verify("anything", Some("not a phc string".into()))
+            .await
+            .unwrap());
+    }
+}
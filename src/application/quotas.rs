@@ -0,0 +1,349 @@
+//! Daily and monthly request quotas per caller.
+//!
+//! A caller is a signed-in user, known by their session, or a service
+//! holding one of the service or admin tokens, known by a fingerprint of
+//! the token. Each request increments the caller's counters for the
+//! current UTC day and calendar month in Redis, which expire once their
+//! window has passed. A request that would go over a limit is refused and
+//! not counted.
+//!
+//! Counting only happens while the caller's kind has a limit configured, so
+//! usage is not tracked with quotas turned off.
+
+use super::{AuthService, ServiceError};
+use crate::app_state::AppState;
+use crate::redis_keys;
+use crate::tenant::Tenant;
+use chrono::{DateTime, Datelike, Days, Months, NaiveDate, Utc};
+use redis::AsyncCommands;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+/// Hex digits of the token fingerprint that names a service caller.
+const KEY_FINGERPRINT_LEN: usize = 16;
+
+/// Counters are kept this long after their window ends, so a request racing
+/// the reset still finds its key.
+const EXPIRY_GRACE_SECS: i64 = 60;
+
+/// Who a request is counted against.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum QuotaCaller {
+    // ---
+    User(Uuid),
+
+    /// A service or admin token, by fingerprint.
+    Key(String),
+}
+
+impl QuotaCaller {
+    // ---
+
+    /// `"user"` or `"key"`.
+    pub(crate) fn kind(&self) -> &'static str {
+        // ---
+        match self {
+            Self::User(_) => "user",
+            Self::Key(_) => "key",
+        }
+    }
+
+    fn redis_name(&self) -> String {
+        // ---
+        match self {
+            Self::User(id) => format!("user:{id}"),
+            Self::Key(fingerprint) => format!("key:{fingerprint}"),
+        }
+    }
+}
+
+/// The period a quota applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum QuotaPeriod {
+    // ---
+    Daily,
+    Monthly,
+}
+
+impl QuotaPeriod {
+    // ---
+
+    /// Label of the window containing `now` (`2026-10-15` or `2026-10`),
+    /// and when it ends.
+    fn window(self, now: DateTime<Utc>) -> (String, DateTime<Utc>) {
+        // ---
+        let today = now.date_naive();
+        match self {
+            Self::Daily => {
+                let end = today + Days::new(1);
+                (today.format("%Y-%m-%d").to_string(), midnight(end))
+            }
+            Self::Monthly => {
+                let first = NaiveDate::from_ymd_opt(today.year(), today.month(), 1)
+                    .expect("the first of a month is a valid date");
+                let end = first + Months::new(1);
+                (today.format("%Y-%m").to_string(), midnight(end))
+            }
+        }
+    }
+}
+
+fn midnight(date: NaiveDate) -> DateTime<Utc> {
+    // ---
+    date.and_hms_opt(0, 0, 0)
+        .expect("midnight is a valid time")
+        .and_utc()
+}
+
+/// A caller's consumption in one window.
+#[derive(Debug, Clone)]
+pub(crate) struct QuotaWindow {
+    // ---
+    pub period: QuotaPeriod,
+    pub used: u64,
+    /// `None` if unlimited.
+    pub limit: Option<u64>,
+    pub resets_at: DateTime<Utc>,
+}
+
+impl QuotaWindow {
+    // ---
+
+    /// Requests left in the window, or `None` if unlimited.
+    pub(crate) fn remaining(&self) -> Option<u64> {
+        // ---
+        self.limit.map(|limit| limit.saturating_sub(self.used))
+    }
+}
+
+/// The outcome of counting one request.
+#[derive(Debug)]
+pub(crate) struct QuotaDecision {
+    // ---
+    pub allowed: bool,
+    pub windows: Vec<QuotaWindow>,
+}
+
+impl QuotaDecision {
+    // ---
+
+    /// The limited window closest to running out (the earlier to reset on a
+    /// tie), which is the one to report in rate limit headers.
+    pub(crate) fn binding(&self) -> Option<&QuotaWindow> {
+        // ---
+        self.windows
+            .iter()
+            .filter(|window| window.limit.is_some())
+            .min_by_key(|window| (window.remaining(), window.resets_at))
+    }
+}
+
+/// Quota accounting shared by the middleware and the usage endpoint.
+#[derive(Clone)]
+pub(crate) struct QuotaService {
+    // ---
+    state: AppState,
+}
+
+impl QuotaService {
+    // ---
+
+    pub(crate) fn new(state: AppState) -> Self {
+        // ---
+        Self { state }
+    }
+
+    /// The caller presenting `token` to `tenant`: a service if it is a
+    /// service or admin token, else the user of the session it names in
+    /// `tenant`. `None` if it is neither, or the session cannot be checked.
+    pub(crate) async fn caller(&self, token: &str, tenant: &Tenant) -> Option<QuotaCaller> {
+        // ---
+        let auth = AuthService::new(self.state.clone());
+        if auth.is_service_token(token) {
+            let digest = hex::encode(Sha256::digest(token.as_bytes()));
+            return Some(QuotaCaller::Key(digest[..KEY_FINGERPRINT_LEN].to_string()));
+        }
+
+        match auth.introspect(token).await {
+            Ok(session) => session
+                .filter(|session| session.belongs_to(tenant))
+                .map(|session| QuotaCaller::User(session.user_id)),
+            Err(_) => None,
+        }
+    }
+
+    /// The caller's daily and monthly limits; 0 means unlimited.
+    fn limits(&self, caller: &QuotaCaller) -> [(QuotaPeriod, u64); 2] {
+        // ---
+        let quotas = self.state.quotas();
+        match caller {
+            QuotaCaller::User(_) => [
+                (QuotaPeriod::Daily, quotas.user_daily),
+                (QuotaPeriod::Monthly, quotas.user_monthly),
+            ],
+            QuotaCaller::Key(_) => [
+                (QuotaPeriod::Daily, quotas.key_daily),
+                (QuotaPeriod::Monthly, quotas.key_monthly),
+            ],
+        }
+    }
+
+    /// Counts one request against the caller's quotas. If that takes any
+    /// window over its limit, the count is undone and the request refused.
+    /// `None` if the caller has no limits.
+    ///
+    /// # Errors
+    /// `500` (or `503` past the request deadline) if Redis fails.
+    pub(crate) async fn consume(
+        &self,
+        caller: &QuotaCaller,
+    ) -> Result<Option<QuotaDecision>, ServiceError> {
+        // ---
+        let limits = self.limits(caller);
+        if limits.iter().all(|&(_, limit)| limit == 0) {
+            return Ok(None);
+        }
+
+        let now = Utc::now();
+        let windows = limits.map(|(period, limit)| {
+            let (label, resets_at) = period.window(now);
+            let key = redis_keys::quota_usage(&caller.redis_name(), &label);
+            (period, limit, key, resets_at)
+        });
+
+        let mut conn = self
+            .state
+            .get_conn()
+            .await
+            .map_err(|status| ServiceError::new(status, "Quota check failed"))?;
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        for (_, _, key, resets_at) in &windows {
+            let ttl = (*resets_at - now).num_seconds() + EXPIRY_GRACE_SECS;
+            pipe.incr(key, 1).expire(key, ttl).ignore();
+        }
+        let mut counts: Vec<u64> = pipe.query_async(&mut conn).await.map_err(storage_error)?;
+
+        let allowed = windows
+            .iter()
+            .zip(&counts)
+            .all(|((_, limit, _, _), &used)| *limit == 0 || used <= *limit);
+        if !allowed {
+            let mut undo = redis::pipe();
+            for (_, _, key, _) in &windows {
+                undo.incr(key, -1).ignore();
+            }
+            undo.query_async::<()>(&mut conn)
+                .await
+                .map_err(storage_error)?;
+            counts.iter_mut().for_each(|used| *used -= 1);
+        }
+
+        let windows = windows
+            .into_iter()
+            .zip(counts)
+            .map(|((period, limit, _, resets_at), used)| QuotaWindow {
+                period,
+                used,
+                limit: (limit > 0).then_some(limit),
+                resets_at,
+            })
+            .collect();
+        Ok(Some(QuotaDecision { allowed, windows }))
+    }
+
+    /// The caller's consumption in the current day and month, without
+    /// counting a request.
+    ///
+    /// # Errors
+    /// `500` if Redis fails.
+    pub(crate) async fn usage(
+        &self,
+        caller: &QuotaCaller,
+    ) -> Result<Vec<QuotaWindow>, ServiceError> {
+        // ---
+        let now = Utc::now();
+        let limits = self.limits(caller);
+        let windows = limits.map(|(period, limit)| {
+            let (label, resets_at) = period.window(now);
+            (period, limit, label, resets_at)
+        });
+        let keys: Vec<String> = windows
+            .iter()
+            .map(|(_, _, label, _)| redis_keys::quota_usage(&caller.redis_name(), label))
+            .collect();
+
+        let keys = &keys;
+        let counts: Vec<Option<u64>> = self
+            .state
+            .redis_read(move |mut conn| async move { conn.mget(keys).await })
+            .await
+            .map_err(storage_error)?;
+
+        Ok(windows
+            .into_iter()
+            .zip(counts)
+            .map(|((period, limit, _, resets_at), used)| QuotaWindow {
+                period,
+                used: used.unwrap_or(0),
+                limit: (limit > 0).then_some(limit),
+                resets_at,
+            })
+            .collect())
+    }
+}
+
+fn storage_error(e: redis::RedisError) -> ServiceError {
+    // ---
+    tracing::error!("Quota storage error: {e}");
+    ServiceError::internal("Quota check failed")
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn windows_end_at_the_next_utc_day_and_month() {
+        // ---
+        let now = Utc.with_ymd_and_hms(2026, 12, 31, 23, 59, 59).unwrap();
+
+        let (label, end) = QuotaPeriod::Daily.window(now);
+        assert_eq!(label, "2026-12-31");
+        assert_eq!(end, Utc.with_ymd_and_hms(2027, 1, 1, 0, 0, 0).unwrap());
+
+        let (label, end) = QuotaPeriod::Monthly.window(now);
+        assert_eq!(label, "2026-12");
+        assert_eq!(end, Utc.with_ymd_and_hms(2027, 1, 1, 0, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn binding_window_has_the_fewest_requests_left() {
+        // ---
+        let at = |day| Utc.with_ymd_and_hms(2026, 10, day, 0, 0, 0).unwrap();
+        let window = |period, used, limit, resets_at| QuotaWindow {
+            period,
+            used,
+            limit,
+            resets_at,
+        };
+        let decision = |windows| QuotaDecision {
+            allowed: true,
+            windows,
+        };
+
+        let both = decision(vec![
+            window(QuotaPeriod::Daily, 5, Some(100), at(16)),
+            window(QuotaPeriod::Monthly, 995, Some(1000), at(31)),
+        ]);
+        assert_eq!(both.binding().unwrap().period, QuotaPeriod::Monthly);
+
+        let daily_only = decision(vec![
+            window(QuotaPeriod::Daily, 5, Some(100), at(16)),
+            window(QuotaPeriod::Monthly, 995, None, at(31)),
+        ]);
+        assert_eq!(daily_only.binding().unwrap().period, QuotaPeriod::Daily);
+    }
+}
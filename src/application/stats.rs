@@ -0,0 +1,318 @@
+//! Aggregate counts and trends for operators.
+//!
+//! User and credential counts come from the repository, active sessions
+//! and movies from Redis. Sign-in outcomes are counted per UTC hour in
+//! Redis as they happen, which gives the last-24-hours figures. A computed
+//! result is cached in Redis for [`CACHE_TTL_SECS`], so dashboards polling
+//! the stats do not each query the database and scan Redis.
+
+use super::ServiceError;
+use crate::app_state::AppState;
+use crate::redis_keys;
+use chrono::{DateTime, Duration, DurationRound, Utc};
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// How long a computed result is served from the cache.
+pub(crate) const CACHE_TTL_SECS: u64 = 60;
+
+/// Hours of sign-in outcomes reported, including the current one.
+const AUTH_WINDOW_HOURS: i64 = 24;
+
+/// Hourly outcome counters are kept an hour past the window.
+const AUTH_BUCKET_TTL_SECS: i64 = (AUTH_WINDOW_HOURS + 1) * 3600;
+
+/// Users with this many credentials or more are counted together.
+const CREDENTIAL_BUCKET_MAX: u64 = 5;
+
+const SUCCEEDED: &str = "succeeded";
+const FAILED: &str = "failed";
+
+/// Snapshot of the deployment's usage.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct AdminStats {
+    // ---
+    pub generated_at: DateTime<Utc>,
+    pub users: UserCounts,
+    pub credentials: CredentialCounts,
+    pub sessions: SessionCounts,
+    pub movies: MovieCounts,
+    pub auth_last_24h: AuthStats,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct UserCounts {
+    // ---
+    pub total: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct CredentialCounts {
+    // ---
+    pub total: u64,
+
+    /// Number of users by how many passkeys they have: `"0"` to `"4"`,
+    /// then `"5+"`.
+    pub per_user: BTreeMap<String, u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct SessionCounts {
+    // ---
+    /// Redis-backed sessions not yet expired. Signed sessions are not
+    /// stored, so they are not counted.
+    pub active: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct MovieCounts {
+    // ---
+    pub total: u64,
+}
+
+/// Passkey sign-ins finished in the last 24 hours.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct AuthStats {
+    // ---
+    pub succeeded: u64,
+    pub failed: u64,
+
+    /// `succeeded / (succeeded + failed)`, or `None` without sign-ins.
+    pub success_rate: Option<f64>,
+
+    /// Counts per UTC hour, oldest first, ending with the current hour.
+    pub hourly: Vec<AuthHour>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct AuthHour {
+    // ---
+    /// Start of the hour.
+    pub hour: DateTime<Utc>,
+    pub succeeded: u64,
+    pub failed: u64,
+}
+
+/// Computes [`AdminStats`] and records sign-in outcomes.
+#[derive(Clone)]
+pub(crate) struct StatsService {
+    // ---
+    state: AppState,
+}
+
+impl StatsService {
+    // ---
+
+    pub(crate) fn new(state: AppState) -> Self {
+        // ---
+        Self { state }
+    }
+
+    /// Counts a finished sign-in in the current hour. Failures to record
+    /// are logged and otherwise ignored.
+    pub(crate) async fn record_auth(&self, succeeded: bool) {
+        // ---
+        let key = redis_keys::auth_outcomes(&hour_label(Utc::now()));
+        let field = if succeeded { SUCCEEDED } else { FAILED };
+
+        let Ok(mut conn) = self.state.get_conn().await else {
+            tracing::warn!("Sign-in outcome not recorded: Redis unavailable");
+            return;
+        };
+        let recorded: redis::RedisResult<()> = redis::pipe()
+            .hincr(&key, field, 1)
+            .ignore()
+            .expire(&key, AUTH_BUCKET_TTL_SECS)
+            .ignore()
+            .query_async(&mut conn)
+            .await;
+        if let Err(e) = recorded {
+            tracing::warn!("Sign-in outcome not recorded: {e}");
+        }
+    }
+
+    /// The current statistics, from the cache if they were computed less
+    /// than [`CACHE_TTL_SECS`] ago, unless `refresh` is set.
+    ///
+    /// # Errors
+    /// `500` if the database or Redis fails.
+    pub(crate) async fn stats(&self, refresh: bool) -> Result<AdminStats, ServiceError> {
+        // ---
+        let cache_key = redis_keys::admin_stats();
+        if !refresh {
+            let key = &cache_key;
+            let cached: redis::RedisResult<Option<String>> = self
+                .state
+                .redis_read(move |mut conn| async move { conn.get(key).await })
+                .await;
+            match cached.map(|json| json.map(|json| serde_json::from_str(&json))) {
+                Ok(Some(Ok(stats))) => return Ok(stats),
+                Ok(Some(Err(e))) => tracing::warn!("Ignoring unreadable cached stats: {e}"),
+                Ok(None) => {}
+                Err(e) => tracing::warn!("Stats cache unavailable: {e}"),
+            }
+        }
+
+        let stats = self.compute().await?;
+        match serde_json::to_string(&stats) {
+            Ok(json) => {
+                if let Ok(mut conn) = self.state.get_conn().await {
+                    let stored: redis::RedisResult<()> =
+                        conn.set_ex(&cache_key, json, CACHE_TTL_SECS).await;
+                    if let Err(e) = stored {
+                        tracing::warn!("Failed to cache stats: {e}");
+                    }
+                }
+            }
+            Err(e) => tracing::warn!("Failed to cache stats: {e}"),
+        }
+        Ok(stats)
+    }
+
+    async fn compute(&self) -> Result<AdminStats, ServiceError> {
+        // ---
+        let failed = || ServiceError::internal("Failed to compute statistics");
+        let now = Utc::now();
+
+        let counts = self
+            .state
+            .repository()
+            .credential_stats()
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to count users and credentials: {e:#}");
+                failed()
+            })?;
+        let mut per_user: BTreeMap<String, u64> = (0..CREDENTIAL_BUCKET_MAX)
+            .map(|n| (n.to_string(), 0))
+            .chain([(format!("{CREDENTIAL_BUCKET_MAX}+"), 0)])
+            .collect();
+        for (&credentials, &users) in &counts.users_by_credential_count {
+            let bucket = match credentials {
+                n if n < CREDENTIAL_BUCKET_MAX => n.to_string(),
+                _ => format!("{CREDENTIAL_BUCKET_MAX}+"),
+            };
+            *per_user.entry(bucket).or_default() += users;
+        }
+
+        let hours: Vec<DateTime<Utc>> = (0..AUTH_WINDOW_HOURS)
+            .rev()
+            .map(|ago| start_of_hour(now) - Duration::hours(ago))
+            .collect();
+        let (active_sessions, movies, outcomes) = self.redis_counts(&hours).await.map_err(|e| {
+            tracing::error!("Failed to read stats from Redis: {e}");
+            failed()
+        })?;
+
+        let hourly: Vec<AuthHour> = hours
+            .into_iter()
+            .zip(outcomes)
+            .map(|(hour, (succeeded, failed))| AuthHour {
+                hour,
+                succeeded: succeeded.unwrap_or(0),
+                failed: failed.unwrap_or(0),
+            })
+            .collect();
+        let succeeded: u64 = hourly.iter().map(|hour| hour.succeeded).sum();
+        let failed: u64 = hourly.iter().map(|hour| hour.failed).sum();
+
+        Ok(AdminStats {
+            generated_at: now,
+            users: UserCounts {
+                total: counts.users,
+            },
+            credentials: CredentialCounts {
+                total: counts.credentials,
+                per_user,
+            },
+            sessions: SessionCounts {
+                active: active_sessions,
+            },
+            movies: MovieCounts { total: movies },
+            auth_last_24h: AuthStats {
+                succeeded,
+                failed,
+                success_rate: success_rate(succeeded, failed),
+                hourly,
+            },
+        })
+    }
+
+    /// Active sessions, movies, and the sign-in outcomes of each of `hours`.
+    #[allow(clippy::type_complexity)]
+    async fn redis_counts(
+        &self,
+        hours: &[DateTime<Utc>],
+    ) -> redis::RedisResult<(u64, u64, Vec<(Option<u64>, Option<u64>)>)> {
+        // ---
+        // The movie set is in another cluster slot than the hourly counts
+        let mut pipe = redis::pipe();
+        for hour in hours {
+            pipe.hget(
+                redis_keys::auth_outcomes(&hour_label(*hour)),
+                &[SUCCEEDED, FAILED],
+            );
+        }
+        let pipe = &pipe;
+
+        self.state
+            .redis_read(move |mut conn| async move {
+                let outcomes = pipe.query_async(&mut conn).await?;
+                let movies: u64 = conn.scard(redis_keys::all_movies()).await?;
+
+                let mut active_sessions = 0;
+                conn.for_each_key(&redis_keys::session_pattern(), |key| {
+                    if !redis_keys::is_revoked_session(key) {
+                        active_sessions += 1;
+                    }
+                })
+                .await?;
+                Ok((active_sessions, movies, outcomes))
+            })
+            .await
+    }
+}
+
+fn start_of_hour(at: DateTime<Utc>) -> DateTime<Utc> {
+    // ---
+    at.duration_trunc(Duration::hours(1))
+        .expect("an hour divides any UTC timestamp")
+}
+
+/// `2026-10-15T09`
+fn hour_label(at: DateTime<Utc>) -> String {
+    // ---
+    at.format("%Y-%m-%dT%H").to_string()
+}
+
+fn success_rate(succeeded: u64, failed: u64) -> Option<f64> {
+    // ---
+    let total = succeeded + failed;
+    (total > 0).then(|| succeeded as f64 / total as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn hours_are_labelled_in_utc() {
+        // ---
+        let at = Utc.with_ymd_and_hms(2026, 10, 15, 9, 41, 7).unwrap();
+        assert_eq!(hour_label(at), "2026-10-15T09");
+        assert_eq!(
+            start_of_hour(at),
+            Utc.with_ymd_and_hms(2026, 10, 15, 9, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn success_rate_needs_sign_ins() {
+        // ---
+        assert_eq!(success_rate(0, 0), None);
+        assert_eq!(success_rate(3, 1), Some(0.75));
+    }
+}
@@ -0,0 +1,176 @@
+//! Subscribers acting on the domain events the services publish.
+//!
+//! | Subscriber | Acts on                            | By                                             |
+//! |:-----------|:-----------------------------------|:-----------------------------------------------|
+//! | metrics    | Movie changes                      | Counting them (`movies_created_total`, ...)    |
+//! | audit log  | Every event                        | Logging it under the `audit` target            |
+//! | SSE        | Registrations, sign-ins, deletions | Publishing it on the server event bus          |
+//! | webhooks   | Passkey sign-ins                   | `auth.new_device` on a passkey's first sign-in |
+//!
+//! Each runs in its own task, so one waiting on Redis or the database does
+//! not hold up the others, and none of them delays the response to the
+//! request that caused the event. They stop at shutdown.
+
+use crate::app_state::AppState;
+use crate::domain::OutboxWrite;
+use crate::events::DomainEvent;
+use crate::redact;
+use crate::redis_keys;
+use crate::webhooks::{WebhookEvent, WebhookEventKind};
+use redis::AsyncCommands;
+use std::future::Future;
+use tokio::sync::broadcast::error::RecvError;
+use uuid::Uuid;
+
+/// Starts every subscriber on the current Tokio runtime.
+///
+/// # Panics
+/// Panics if called outside a Tokio runtime.
+pub(crate) fn spawn_subscribers(state: &AppState) {
+    // ---
+    spawn_subscriber(state, "metrics", |state, event| async move {
+        record_metrics(&state, &event);
+    });
+    spawn_subscriber(state, "audit log", |_, event| async move {
+        log_event(&event);
+    });
+    spawn_subscriber(state, "SSE", |state, event| async move {
+        if let Some(server_event) = event.to_server_event() {
+            state.events().publish(server_event);
+        }
+    });
+    spawn_subscriber(state, "webhooks", |state, event| async move {
+        if let DomainEvent::AuthSucceeded {
+            user_id,
+            username,
+            credential_id: Some(credential_id),
+            ..
+        } = event
+        {
+            notify_if_new_device(&state, user_id, &username, &credential_id).await;
+        }
+    });
+}
+
+/// Calls `handle` with each domain event published from now on, one at a
+/// time, until shutdown.
+fn spawn_subscriber<F, Fut>(state: &AppState, name: &'static str, handle: F)
+where
+    F: Fn(AppState, DomainEvent) -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send,
+{
+    // ---
+    // Subscribe now, so events published while the task starts are seen
+    let mut receiver = state.domain_events().subscribe();
+    let state = state.clone();
+
+    tokio::spawn(async move {
+        // ---
+        let stopped = state.shutdown().triggered();
+        tokio::pin!(stopped);
+
+        loop {
+            let event = tokio::select! {
+                received = receiver.recv() => received,
+                _ = &mut stopped => break,
+            };
+            match event {
+                Ok(event) => handle(state.clone(), event).await,
+                Err(RecvError::Lagged(missed)) => {
+                    tracing::warn!(
+                        "{name} subscriber fell behind; {missed} domain events were not seen"
+                    );
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    });
+}
+
+fn record_metrics(state: &AppState, event: &DomainEvent) {
+    // ---
+    let metrics = state.metrics();
+    match event {
+        DomainEvent::MovieCreated { .. } => metrics.record_movie_created(),
+        DomainEvent::MovieUpdated { .. } => metrics.record_movie_updated(),
+        DomainEvent::MovieDeleted { .. } => metrics.record_movie_deleted(),
+        _ => {}
+    }
+}
+
+fn log_event(event: &DomainEvent) {
+    // ---
+    let user = match event {
+        DomainEvent::UserRegistered { username, .. }
+        | DomainEvent::CredentialAdded { username, .. }
+        | DomainEvent::AuthSucceeded { username, .. }
+        | DomainEvent::CredentialDeleted { username, .. } => redact::username(username).to_string(),
+        _ => String::new(),
+    };
+    let movie = match event {
+        DomainEvent::MovieCreated { id }
+        | DomainEvent::MovieUpdated { id }
+        | DomainEvent::MovieDeleted { id } => id.as_str(),
+        _ => "",
+    };
+
+    tracing::info!(
+        target: "audit",
+        event = event.as_str(),
+        user_id = ?event.user_id(),
+        user = %user,
+        movie_id = movie,
+        "{}",
+        event.as_str()
+    );
+}
+
+/// Emits an `auth.new_device` webhook the first time a credential is used to sign in.
+///
+/// Credentials used for sign-in are remembered per user in a Redis set.
+/// Failures are logged and otherwise ignored.
+async fn notify_if_new_device(
+    state: &AppState,
+    user_id: Uuid,
+    username: &str,
+    credential_id: &str,
+) {
+    // ---
+    let mut conn = match state.get_conn().await {
+        Ok(conn) => conn,
+        Err(_) => {
+            tracing::warn!(
+                "Failed to record credential use for {}: Redis unavailable",
+                redact::username(username)
+            );
+            return;
+        }
+    };
+    let seen_key = redis_keys::seen_credentials(user_id);
+
+    match conn.sadd::<_, _, u32>(&seen_key, credential_id).await {
+        Ok(1) => {
+            let event = WebhookEvent::new(
+                WebhookEventKind::NewDeviceLogin,
+                user_id,
+                username,
+                credential_id,
+            );
+            let emitted = state
+                .webhooks()
+                .emit_with(&**state.repository(), OutboxWrite::EventOnly, event)
+                .await;
+            if let Err(e) = emitted {
+                tracing::warn!(
+                    "Failed to store auth.new_device event for {}: {e}",
+                    redact::username(username)
+                );
+            }
+        }
+        Ok(_) => {}
+        Err(e) => tracing::warn!(
+            "Failed to record credential use for {}: {e}",
+            redact::username(username)
+        ),
+    }
+}
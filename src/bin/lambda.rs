@@ -0,0 +1,44 @@
+//! AWS Lambda entry point: the same API as the server binary, invoked by
+//! API Gateway or a load balancer instead of listening on a port.
+//!
+//! Build with `cargo lambda build --release --features lambda --bin lambda`.
+//! Configuration comes from the function's environment, as for the server.
+//! Background jobs (orphan cleanup, pushgateway) are not started; run them
+//! from a long-lived process or a schedule.
+
+use anyhow::Result;
+use axum_quickstart::{
+    create_metrics_from_env, create_repository, log_startup_banner, metrics_type_from_env,
+    run_lambda, wait_for_dependencies, AppBuilder, AppConfig,
+};
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    // ---
+    // CloudWatch timestamps every line and does not render colours
+    let level = std::env::var("AXUM_LOG_LEVEL")
+        .ok()
+        .and_then(|level| level.parse().ok())
+        .unwrap_or(tracing::Level::INFO);
+    tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_ansi(false)
+        .without_time()
+        .with_target(true)
+        .init();
+
+    let config = AppConfig::from_env()?;
+    log_startup_banner(&config, metrics_type_from_env(), "lambda");
+
+    // Cold starts wait for the database and Redis like the server does
+    wait_for_dependencies(&config).await?;
+    let repository = create_repository(&config.database).await?;
+    let metrics = create_metrics_from_env()?;
+
+    let service = AppBuilder::new()
+        .config(config)
+        .repository(repository)
+        .metrics(metrics)
+        .build_service()?;
+    run_lambda(service).await
+}
@@ -0,0 +1,235 @@
+//! Percentage-based failures and latency for Redis and the database.
+//!
+//! Every Redis connect and every repository call first consults the
+//! [`Fault`] configured for its dependency: it may be delayed, and it may
+//! then fail as if the dependency were down. Faults can be changed while
+//! the server runs, so a test can break a dependency, watch the retries and
+//! health reporting, and then let it recover.
+
+use anyhow::anyhow;
+use arc_swap::ArcSwap;
+use rand::Rng;
+use redis::RedisResult;
+use std::io;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How one dependency misbehaves. The default injects nothing.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Fault {
+    // ---
+    /// Percentage (0-100) of calls that fail.
+    pub failure_percent: u8,
+
+    /// Delay added to a delayed call, before it may fail.
+    pub latency: Duration,
+
+    /// Percentage (0-100) of calls delayed by `latency`.
+    pub latency_percent: u8,
+}
+
+impl Fault {
+    // ---
+
+    /// Fails `percent` of calls.
+    pub fn failing(percent: u8) -> Self {
+        // ---
+        Self {
+            failure_percent: percent.min(100),
+            ..Self::default()
+        }
+    }
+
+    /// Also delays `percent` of calls by `latency`.
+    pub fn with_latency(mut self, latency: Duration, percent: u8) -> Self {
+        // ---
+        self.latency = latency;
+        self.latency_percent = percent.min(100);
+        self
+    }
+
+    /// Whether this fault never delays or fails a call.
+    pub fn is_none(&self) -> bool {
+        // ---
+        self.failure_percent == 0 && (self.latency_percent == 0 || self.latency.is_zero())
+    }
+
+    /// Reads `AXUM_CHAOS_<prefix>_FAILURE_PCT`, `..._LATENCY_MS`, and
+    /// `..._LATENCY_PCT` (default 100). Missing or invalid values count as 0.
+    fn from_env(prefix: &str) -> Self {
+        // ---
+        let var = |name: &str| std::env::var(format!("AXUM_CHAOS_{prefix}_{name}")).ok();
+        let failure_percent = var("FAILURE_PCT").and_then(|v| v.parse().ok());
+        let latency_ms = var("LATENCY_MS").and_then(|v| v.parse().ok());
+        let latency_percent = var("LATENCY_PCT").and_then(|v| v.parse().ok());
+
+        Self::failing(failure_percent.unwrap_or(0)).with_latency(
+            Duration::from_millis(latency_ms.unwrap_or(0)),
+            latency_percent.unwrap_or(100),
+        )
+    }
+
+    /// Sleeps if this call is delayed, then returns whether it should fail.
+    async fn strike(self) -> bool {
+        // ---
+        let (delayed, failed) = {
+            let mut rng = rand::thread_rng();
+            (
+                rng.gen_range(0..100) < self.latency_percent,
+                rng.gen_range(0..100) < self.failure_percent,
+            )
+        };
+        if delayed && !self.latency.is_zero() {
+            tokio::time::sleep(self.latency).await;
+        }
+        failed
+    }
+}
+
+/// Handle to the faults injected into Redis and the database.
+///
+/// Clones share the same settings. Pass one to
+/// [`AppBuilder::chaos`](crate::AppBuilder::chaos) and keep a clone to
+/// change the faults while the server runs:
+///
+/// ```no_run
+/// # fn demo(repository: axum_quickstart::domain::RepositoryPtr) -> anyhow::Result<()> {
+/// use axum_quickstart::{AppBuilder, Chaos, Fault};
+///
+/// let chaos = Chaos::new();
+/// let router = AppBuilder::new()
+///     .repository(repository)
+///     .chaos(chaos.clone())
+///     .build()?;
+///
+/// chaos.set_database(Fault::failing(100)); // every query fails
+/// chaos.clear(); // back to normal
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Default)]
+pub struct Chaos {
+    // ---
+    redis: Arc<ArcSwap<Fault>>,
+    database: Arc<ArcSwap<Fault>>,
+}
+
+impl Chaos {
+    // ---
+
+    /// A handle that injects nothing until faults are set.
+    pub fn new() -> Self {
+        // ---
+        Self::default()
+    }
+
+    /// Reads the faults from `AXUM_CHAOS_REDIS_*` and `AXUM_CHAOS_DB_*`.
+    pub fn from_env() -> Self {
+        // ---
+        let chaos = Self::new();
+        chaos.set_redis(Fault::from_env("REDIS"));
+        chaos.set_database(Fault::from_env("DB"));
+        chaos
+    }
+
+    /// Applies `fault` to every Redis connect.
+    pub fn set_redis(&self, fault: Fault) {
+        // ---
+        self.redis.store(Arc::new(fault));
+    }
+
+    /// Applies `fault` to every repository call.
+    pub fn set_database(&self, fault: Fault) {
+        // ---
+        self.database.store(Arc::new(fault));
+    }
+
+    /// Stops injecting faults.
+    pub fn clear(&self) {
+        // ---
+        self.set_redis(Fault::default());
+        self.set_database(Fault::default());
+    }
+
+    /// The current Redis fault.
+    pub fn redis(&self) -> Fault {
+        // ---
+        **self.redis.load()
+    }
+
+    /// The current database fault.
+    pub fn database(&self) -> Fault {
+        // ---
+        **self.database.load()
+    }
+
+    /// Applies the Redis fault to a connect. The injected error is a
+    /// refused connection, which the Redis retry policy treats as transient.
+    pub(crate) async fn before_redis_connect(&self) -> RedisResult<()> {
+        // ---
+        if self.redis().strike().await {
+            tracing::debug!("Chaos: failing Redis connect");
+            return Err(io::Error::new(
+                io::ErrorKind::ConnectionRefused,
+                "chaos: injected Redis failure",
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Applies the database fault to the repository call `operation`.
+    pub(crate) async fn before_database_call(&self, operation: &str) -> anyhow::Result<()> {
+        // ---
+        if self.database().strike().await {
+            tracing::debug!("Chaos: failing database call {operation}");
+            return Err(anyhow!("chaos: injected database failure in {operation}"));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+
+    #[tokio::test]
+    async fn percentages_of_zero_and_hundred_are_exact() {
+        // ---
+        let chaos = Chaos::new();
+        for _ in 0..100 {
+            assert!(chaos.before_redis_connect().await.is_ok());
+            assert!(chaos.before_database_call("ping").await.is_ok());
+        }
+
+        chaos.set_redis(Fault::failing(100));
+        chaos.set_database(Fault::failing(100));
+        for _ in 0..100 {
+            assert!(chaos.before_redis_connect().await.is_err());
+            assert!(chaos.before_database_call("ping").await.is_err());
+        }
+
+        chaos.clear();
+        assert!(chaos.redis().is_none() && chaos.database().is_none());
+    }
+
+    #[tokio::test]
+    async fn latency_delays_the_call() {
+        // ---
+        let chaos = Chaos::new();
+        chaos.set_database(Fault::default().with_latency(Duration::from_millis(50), 100));
+
+        let started = std::time::Instant::now();
+        chaos.before_database_call("ping").await.unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(50));
+    }
+
+    #[test]
+    fn injected_redis_failures_are_retried() {
+        // ---
+        let err: redis::RedisError =
+            io::Error::new(io::ErrorKind::ConnectionRefused, "chaos").into();
+        assert!(matches!(err.retry_method(), redis::RetryMethod::Reconnect));
+    }
+}
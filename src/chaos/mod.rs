@@ -0,0 +1,8 @@
+// Gateway module - fault injection for resilience tests (`chaos` feature)
+// Modules are private, only exported symbols are public
+
+mod fault;
+mod repository;
+
+pub use fault::{Chaos, Fault};
+pub(crate) use repository::ChaosRepository;
@@ -0,0 +1,263 @@
+//! Repository decorator that injects database faults.
+
+use super::fault::Chaos;
+use crate::domain::{
+    Credential, CredentialStats, OutboxEvent, OutboxWrite, PurgeSummary, ReencryptSummary,
+    Repository, RepositoryPtr, User,
+};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use std::future::Future;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Wraps any [`Repository`] so each call is delayed or fails according to
+/// the database [`Fault`](super::Fault) currently set on [`Chaos`]. A
+/// failed call never reaches the inner repository.
+pub(crate) struct ChaosRepository {
+    // ---
+    inner: RepositoryPtr,
+    chaos: Chaos,
+}
+
+impl ChaosRepository {
+    // ---
+
+    pub fn new(inner: RepositoryPtr, chaos: Chaos) -> Self {
+        // ---
+        Self { inner, chaos }
+    }
+
+    /// Applies the database fault, then awaits the repository call.
+    async fn within<T>(
+        &self,
+        operation: &'static str,
+        call: impl Future<Output = Result<T>>,
+    ) -> Result<T> {
+        // ---
+        self.chaos.before_database_call(operation).await?;
+        call.await
+    }
+}
+
+#[async_trait::async_trait]
+impl Repository for ChaosRepository {
+    // ---
+    async fn ping(&self) -> Result<()> {
+        self.within("ping", self.inner.ping()).await
+    }
+
+    async fn create_user_in(&self, tenant_id: &str, username: &str) -> Result<User> {
+        self.within(
+            "create_user",
+            self.inner.create_user_in(tenant_id, username),
+        )
+        .await
+    }
+
+    async fn get_user_by_username_in(
+        &self,
+        tenant_id: &str,
+        username: &str,
+    ) -> Result<Option<User>> {
+        self.within(
+            "get_user_by_username",
+            self.inner.get_user_by_username_in(tenant_id, username),
+        )
+        .await
+    }
+
+    async fn get_or_create_user_in(&self, tenant_id: &str, username: &str) -> Result<User> {
+        self.within(
+            "get_or_create_user",
+            self.inner.get_or_create_user_in(tenant_id, username),
+        )
+        .await
+    }
+
+    async fn get_user_by_id(&self, user_id: Uuid) -> Result<Option<User>> {
+        self.within("get_user_by_id", self.inner.get_user_by_id(user_id))
+            .await
+    }
+
+    async fn set_password_hash(&self, user_id: Uuid, password_hash: &str) -> Result<bool> {
+        self.within(
+            "set_password_hash",
+            self.inner.set_password_hash(user_id, password_hash),
+        )
+        .await
+    }
+
+    async fn get_password_hash(&self, user_id: Uuid) -> Result<Option<String>> {
+        self.within("get_password_hash", self.inner.get_password_hash(user_id))
+            .await
+    }
+
+    async fn save_credential(&self, credential: Credential) -> Result<()> {
+        self.within("save_credential", self.inner.save_credential(credential))
+            .await
+    }
+
+    async fn save_credentials(&self, credentials: Vec<Credential>) -> Result<()> {
+        self.within("save_credentials", self.inner.save_credentials(credentials))
+            .await
+    }
+
+    async fn get_credentials_by_user(&self, user_id: Uuid) -> Result<Vec<Credential>> {
+        self.within(
+            "get_credentials_by_user",
+            self.inner.get_credentials_by_user(user_id),
+        )
+        .await
+    }
+
+    async fn credentials_modified_at(&self, user_id: Uuid) -> Result<Option<DateTime<Utc>>> {
+        self.within(
+            "credentials_modified_at",
+            self.inner.credentials_modified_at(user_id),
+        )
+        .await
+    }
+
+    async fn get_credential_by_id(&self, credential_id: &[u8]) -> Result<Option<Credential>> {
+        self.within(
+            "get_credential_by_id",
+            self.inner.get_credential_by_id(credential_id),
+        )
+        .await
+    }
+
+    async fn update_credential(&self, credential: Credential) -> Result<()> {
+        self.within(
+            "update_credential",
+            self.inner.update_credential(credential),
+        )
+        .await
+    }
+
+    async fn delete_credential(&self, credential_id: &[u8]) -> Result<()> {
+        self.within(
+            "delete_credential",
+            self.inner.delete_credential(credential_id),
+        )
+        .await
+    }
+
+    async fn delete_credentials_by_user(&self, user_id: Uuid) -> Result<u64> {
+        self.within(
+            "delete_credentials_by_user",
+            self.inner.delete_credentials_by_user(user_id),
+        )
+        .await
+    }
+
+    async fn delete_user(&self, user_id: Uuid) -> Result<bool> {
+        self.within("delete_user", self.inner.delete_user(user_id))
+            .await
+    }
+
+    async fn soft_delete_user(&self, user_id: Uuid) -> Result<bool> {
+        self.within("soft_delete_user", self.inner.soft_delete_user(user_id))
+            .await
+    }
+
+    async fn restore_user(&self, user_id: Uuid) -> Result<bool> {
+        self.within("restore_user", self.inner.restore_user(user_id))
+            .await
+    }
+
+    async fn soft_delete_credential(&self, credential_id: &[u8]) -> Result<bool> {
+        self.within(
+            "soft_delete_credential",
+            self.inner.soft_delete_credential(credential_id),
+        )
+        .await
+    }
+
+    async fn restore_credential(&self, credential_id: &[u8]) -> Result<bool> {
+        self.within(
+            "restore_credential",
+            self.inner.restore_credential(credential_id),
+        )
+        .await
+    }
+
+    async fn mark_credential_compromised(&self, credential_id: &[u8]) -> Result<bool> {
+        self.within(
+            "mark_credential_compromised",
+            self.inner.mark_credential_compromised(credential_id),
+        )
+        .await
+    }
+
+    async fn purge_deleted(&self, cutoff: DateTime<Utc>, dry_run: bool) -> Result<PurgeSummary> {
+        self.within("purge_deleted", self.inner.purge_deleted(cutoff, dry_run))
+            .await
+    }
+
+    async fn delete_orphaned_users(
+        &self,
+        created_before: DateTime<Utc>,
+        dry_run: bool,
+    ) -> Result<u64> {
+        self.within(
+            "delete_orphaned_users",
+            self.inner.delete_orphaned_users(created_before, dry_run),
+        )
+        .await
+    }
+
+    async fn credential_stats(&self) -> Result<CredentialStats> {
+        self.within("credential_stats", self.inner.credential_stats())
+            .await
+    }
+
+    async fn list_all_credentials(
+        &self,
+        after: Option<&[u8]>,
+        limit: u32,
+    ) -> Result<Vec<Credential>> {
+        self.within(
+            "list_all_credentials",
+            self.inner.list_all_credentials(after, limit),
+        )
+        .await
+    }
+
+    async fn replace_public_key(
+        &self,
+        credential_id: &[u8],
+        expected: &[u8],
+        public_key: &[u8],
+    ) -> Result<bool> {
+        self.within(
+            "replace_public_key",
+            self.inner
+                .replace_public_key(credential_id, expected, public_key),
+        )
+        .await
+    }
+
+    async fn write_with_event(&self, write: OutboxWrite, event: &OutboxEvent) -> Result<bool> {
+        self.within(
+            "write_with_event",
+            self.inner.write_with_event(write, event),
+        )
+        .await
+    }
+
+    async fn claim_events(&self, limit: u32, lease: Duration) -> Result<Vec<OutboxEvent>> {
+        self.within("claim_events", self.inner.claim_events(limit, lease))
+            .await
+    }
+
+    async fn delete_event(&self, event_id: Uuid) -> Result<()> {
+        self.within("delete_event", self.inner.delete_event(event_id))
+            .await
+    }
+
+    async fn reencrypt_credentials(&self) -> Result<Option<ReencryptSummary>> {
+        self.within("reencrypt_credentials", self.inner.reencrypt_credentials())
+            .await
+    }
+}
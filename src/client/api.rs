@@ -0,0 +1,318 @@
+//! Typed calls to the HTTP API.
+//!
+//! [`Client`] sends the same JSON bodies a browser or script would, to the
+//! `/api/v1` routes of a running server, and decodes the responses into
+//! the types in this module. Error responses become [`ClientError::Api`]
+//! with the status and the body's `code` and message.
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), axum_quickstart::client::ClientError> {
+//! use axum_quickstart::client::{Client, Movie};
+//!
+//! let client = Client::new("http://localhost:8080");
+//! let stored = client
+//!     .add_movie(&Movie {
+//!         title: "Alien".into(),
+//!         year: 1979,
+//!         stars: 4.5,
+//!         genres: vec!["scifi".into()],
+//!     })
+//!     .await?;
+//! assert_eq!(client.get_movie(&stored.id).await?.title, "Alien");
+//! # Ok(())
+//! # }
+//! ```
+
+use super::error::ClientError;
+use super::types::{
+    AuthenticationFinished, AuthenticationStart, CredentialList, CredentialPage, ListMovies, Movie,
+    MovieList, MoviePage, RegistrationFinished, RegistrationStart, StoredMovie,
+};
+use crate::handlers::ApiResponse;
+use reqwest::{Method, RequestBuilder, Response};
+use serde::de::DeserializeOwned;
+use serde_json::json;
+use uuid::Uuid;
+use webauthn_rs::prelude::{PublicKeyCredential, RegisterPublicKeyCredential};
+
+/// Client of one server. Cheap to clone; clones share connections, and
+/// each keeps its own session token.
+#[derive(Clone)]
+pub struct Client {
+    // ---
+    http: reqwest::Client,
+
+    /// `scheme://host[:port]` of the server, without a trailing `/`.
+    base_url: String,
+
+    /// Sent as `Authorization: Bearer` on calls that need a session.
+    session_token: Option<String>,
+}
+
+impl Client {
+    // ---
+
+    /// A client of the server at `base_url` (`http://localhost:8080`).
+    pub fn new(base_url: impl Into<String>) -> Self {
+        // ---
+        Self::with_http_client(base_url, reqwest::Client::new())
+    }
+
+    /// Like [`new`](Self::new), sending through `http` (for its timeouts,
+    /// proxy, or TLS roots).
+    pub fn with_http_client(base_url: impl Into<String>, http: reqwest::Client) -> Self {
+        // ---
+        let base_url = base_url.into().trim_end_matches('/').to_string();
+        Self {
+            http,
+            base_url,
+            session_token: None,
+        }
+    }
+
+    /// Uses `token` as the session, e.g. one kept from an earlier sign-in.
+    pub fn with_session_token(mut self, token: impl Into<String>) -> Self {
+        // ---
+        self.session_token = Some(token.into());
+        self
+    }
+
+    /// The session token of the last sign-in, if any.
+    pub fn session_token(&self) -> Option<&str> {
+        // ---
+        self.session_token.as_deref()
+    }
+
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        // ---
+        let url = format!("{}{}{path}", self.base_url, crate::API_V1_PREFIX);
+        self.http.request(method, url)
+    }
+
+    /// A request carrying the session token.
+    fn authorized(&self, method: Method, path: &str) -> Result<RequestBuilder, ClientError> {
+        // ---
+        let token = self.session_token.as_ref().ok_or(ClientError::NoSession)?;
+        Ok(self.request(method, path).bearer_auth(token))
+    }
+
+    /// Sends `request`; error statuses become [`ClientError::Api`].
+    async fn send(request: RequestBuilder) -> Result<Response, ClientError> {
+        // ---
+        let response = request.send().await?;
+        match response.status().is_success() {
+            true => Ok(response),
+            false => Err(ClientError::from_response(response).await),
+        }
+    }
+
+    /// Sends `request` and decodes its JSON body.
+    async fn fetch<T: DeserializeOwned>(request: RequestBuilder) -> Result<T, ClientError> {
+        // ---
+        Ok(Self::send(request).await?.json().await?)
+    }
+
+    // ------------------------------------------------------------------
+    // Movies
+    // ------------------------------------------------------------------
+
+    /// `POST /movies/add`: stores `movie` under a new ID.
+    ///
+    /// # Errors
+    /// `409` (`movie_exists`) if a movie has its title and year, `400`
+    /// (`invalid_movie`) if it is invalid.
+    pub async fn add_movie(&self, movie: &Movie) -> Result<StoredMovie, ClientError> {
+        // ---
+        let request = self.request(Method::POST, "/movies/add").json(movie);
+        let envelope: ApiResponse<StoredMovie> = Self::fetch(request).await?;
+        Ok(envelope.data)
+    }
+
+    /// `GET /movies/get/{id}`.
+    ///
+    /// # Errors
+    /// `404` (`movie_not_found`) if there is no such movie.
+    pub async fn get_movie(&self, id: &str) -> Result<Movie, ClientError> {
+        // ---
+        let request = self.request(Method::GET, &format!("/movies/get/{id}"));
+        let envelope: ApiResponse<Movie> = Self::fetch(request).await?;
+        Ok(envelope.data)
+    }
+
+    /// `GET /movies/list`: one page of movies, filtered by `query`.
+    ///
+    /// # Errors
+    /// `400` (`invalid_genre`) for a malformed genre.
+    pub async fn list_movies(&self, query: &ListMovies) -> Result<MoviePage, ClientError> {
+        // ---
+        let request = self.request(Method::GET, "/movies/list").query(query);
+        let envelope: ApiResponse<MovieList> = Self::fetch(request).await?;
+        Ok(MoviePage {
+            movies: envelope.data.movies,
+            pagination: pagination(envelope.meta),
+            links: envelope.links.unwrap_or_default(),
+        })
+    }
+
+    /// `PUT /movies/update/{id}`: stores `movie` under `id`, replacing
+    /// any movie there.
+    ///
+    /// # Errors
+    /// `409` (`movie_exists`) if a different movie has its title and year.
+    pub async fn update_movie(&self, id: &str, movie: &Movie) -> Result<(), ClientError> {
+        // ---
+        let request = self
+            .request(Method::PUT, &format!("/movies/update/{id}"))
+            .json(movie);
+        Self::send(request).await.map(drop)
+    }
+
+    /// `DELETE /movies/delete/{id}`.
+    ///
+    /// # Errors
+    /// `404` (`movie_not_found`) if there is no such movie.
+    pub async fn delete_movie(&self, id: &str) -> Result<(), ClientError> {
+        // ---
+        let request = self.request(Method::DELETE, &format!("/movies/delete/{id}"));
+        Self::send(request).await.map(drop)
+    }
+
+    // ------------------------------------------------------------------
+    // WebAuthn
+    // ------------------------------------------------------------------
+
+    /// `POST /webauthn/register/start`: begins registering a passkey for
+    /// `username`, creating the user if needed.
+    pub async fn register_start(&self, username: &str) -> Result<RegistrationStart, ClientError> {
+        // ---
+        let request = self
+            .request(Method::POST, "/webauthn/register/start")
+            .json(&json!({ "username": username }));
+        Self::fetch(request).await
+    }
+
+    /// `POST /webauthn/register/finish`: stores the passkey `credential`
+    /// created for the `flow_id` of [`register_start`](Self::register_start).
+    /// Returns the new credential's ID, hex-encoded as the server sends it
+    /// (the credential listing and `delete_credential` use base64url).
+    ///
+    /// # Errors
+    /// `400` if the flow expired or the credential does not verify, `409`
+    /// once the user holds the most passkeys allowed.
+    pub async fn register_finish(
+        &self,
+        username: &str,
+        flow_id: Uuid,
+        credential: &RegisterPublicKeyCredential,
+    ) -> Result<String, ClientError> {
+        // ---
+        let request = self
+            .request(Method::POST, "/webauthn/register/finish")
+            .json(&json!({
+                "username": username,
+                "flow_id": flow_id,
+                "credential": credential,
+            }));
+        let finished: RegistrationFinished = Self::fetch(request).await?;
+        Ok(finished.credential_id)
+    }
+
+    /// `POST /webauthn/auth/start`: begins signing in as `username`.
+    pub async fn auth_start(&self, username: &str) -> Result<AuthenticationStart, ClientError> {
+        // ---
+        let request = self
+            .request(Method::POST, "/webauthn/auth/start")
+            .json(&json!({ "username": username }));
+        Self::fetch(request).await
+    }
+
+    /// `POST /webauthn/auth/finish`: signs in with the assertion
+    /// `credential` for the `flow_id` of [`auth_start`](Self::auth_start).
+    /// The session token is kept for later calls, and returned.
+    ///
+    /// # Errors
+    /// `401` if the assertion does not verify or the flow expired.
+    pub async fn auth_finish(
+        &mut self,
+        username: &str,
+        flow_id: Uuid,
+        credential: &PublicKeyCredential,
+    ) -> Result<String, ClientError> {
+        // ---
+        let request = self
+            .request(Method::POST, "/webauthn/auth/finish")
+            .json(&json!({
+                "username": username,
+                "flow_id": flow_id,
+                "credential": credential,
+            }));
+        let finished: AuthenticationFinished = Self::fetch(request).await?;
+        self.session_token = Some(finished.session_token.clone());
+        Ok(finished.session_token)
+    }
+
+    /// `POST /webauthn/logout`: ends the session and forgets its token.
+    ///
+    /// # Errors
+    /// [`ClientError::NoSession`] without a session, `401` if it already
+    /// ended.
+    pub async fn logout(&mut self) -> Result<(), ClientError> {
+        // ---
+        let request = self.authorized(Method::POST, "/webauthn/logout")?;
+        Self::send(request).await?;
+        self.session_token = None;
+        Ok(())
+    }
+
+    /// `GET /webauthn/credentials`: one page (`None`: the server default)
+    /// of the signed-in user's passkeys.
+    ///
+    /// # Errors
+    /// [`ClientError::NoSession`] without a session, `401` if it expired.
+    pub async fn list_credentials(
+        &self,
+        page: Option<u32>,
+        per_page: Option<u32>,
+    ) -> Result<CredentialPage, ClientError> {
+        // ---
+        let query: Vec<(&str, u32)> = [("page", page), ("per_page", per_page)]
+            .into_iter()
+            .filter_map(|(name, value)| Some((name, value?)))
+            .collect();
+        let request = self
+            .authorized(Method::GET, "/webauthn/credentials")?
+            .query(&query);
+        let envelope: ApiResponse<CredentialList> = Self::fetch(request).await?;
+        Ok(CredentialPage {
+            credentials: envelope.data.credentials,
+            pagination: pagination(envelope.meta),
+            links: envelope.links.unwrap_or_default(),
+        })
+    }
+
+    /// `DELETE /webauthn/credentials/{id}`: revokes one of the signed-in
+    /// user's passkeys, by the `id` of its [`CredentialInfo`].
+    ///
+    /// # Errors
+    /// [`ClientError::NoSession`] without a session, `404` if there is no
+    /// such passkey, `403` if it belongs to another user.
+    ///
+    /// [`CredentialInfo`]: super::CredentialInfo
+    pub async fn delete_credential(&self, id: &str) -> Result<(), ClientError> {
+        // ---
+        let path = format!("/webauthn/credentials/{id}");
+        let request = self.authorized(Method::DELETE, &path)?;
+        Self::send(request).await.map(drop)
+    }
+}
+
+/// The pagination of a list envelope; list responses always carry it.
+fn pagination(meta: Option<crate::handlers::ResponseMeta>) -> crate::handlers::Pagination {
+    // ---
+    meta.and_then(|meta| meta.pagination)
+        .unwrap_or(crate::handlers::Pagination {
+            page: 1,
+            per_page: 0,
+            total: 0,
+        })
+}
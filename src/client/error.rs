@@ -0,0 +1,113 @@
+//! Errors of [`Client`](super::Client) calls.
+
+use reqwest::StatusCode;
+use serde::Deserialize;
+use std::fmt;
+
+/// Why a [`Client`](super::Client) call failed.
+#[derive(Debug)]
+pub enum ClientError {
+    // ---
+    /// The server answered with an error status.
+    Api {
+        status: StatusCode,
+
+        /// Machine-readable code such as `movie_not_found`; absent for
+        /// endpoints whose error body only has a message.
+        code: Option<String>,
+
+        /// The `error` message of the body, or the status reason if the
+        /// body has none.
+        message: String,
+    },
+
+    /// The request could not be sent, or the response not read or decoded.
+    Transport(reqwest::Error),
+
+    /// The call needs a session and the client has no session token.
+    NoSession,
+}
+
+impl ClientError {
+    // ---
+
+    /// The status of an [`Api`](Self::Api) error.
+    pub fn status(&self) -> Option<StatusCode> {
+        // ---
+        match self {
+            Self::Api { status, .. } => Some(*status),
+            Self::Transport(e) => e.status(),
+            Self::NoSession => None,
+        }
+    }
+
+    /// The error code of an [`Api`](Self::Api) error, if the body had one.
+    pub fn code(&self) -> Option<&str> {
+        // ---
+        match self {
+            Self::Api { code, .. } => code.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Reads the error body of `response`, a non-success response.
+    pub(super) async fn from_response(response: reqwest::Response) -> Self {
+        // ---
+
+        /// Both the `ApiError` body and the older `{ "error": ... }` ones.
+        #[derive(Deserialize)]
+        struct ErrorBody {
+            error: String,
+            code: Option<String>,
+        }
+
+        let status = response.status();
+        match response.json::<ErrorBody>().await {
+            Ok(body) => Self::Api {
+                status,
+                code: body.code,
+                message: body.error,
+            },
+            Err(_) => Self::Api {
+                status,
+                code: None,
+                message: status.canonical_reason().unwrap_or("").to_string(),
+            },
+        }
+    }
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // ---
+        match self {
+            Self::Api {
+                status,
+                code: Some(code),
+                message,
+            } => write!(f, "{status} ({code}): {message}"),
+            Self::Api {
+                status, message, ..
+            } => write!(f, "{status}: {message}"),
+            Self::Transport(e) => write!(f, "request failed: {e}"),
+            Self::NoSession => f.write_str("no session token; sign in first"),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        // ---
+        match self {
+            Self::Transport(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for ClientError {
+    fn from(e: reqwest::Error) -> Self {
+        // ---
+        Self::Transport(e)
+    }
+}
@@ -0,0 +1,20 @@
+// Gateway module - typed client for the HTTP API (`client` feature)
+// Modules are private, only exported symbols are public
+
+mod api;
+mod error;
+mod types;
+
+pub use api::Client;
+pub use error::ClientError;
+pub use types::{
+    AuthenticationStart,
+    CredentialInfo,
+    CredentialPage,
+    ListMovies,
+    Movie,
+    MoviePage,
+    PasskeyKind,
+    RegistrationStart,
+    StoredMovie, // ---
+};
@@ -0,0 +1,145 @@
+//! Request and response bodies of the API, as the client sends and reads
+//! them. They mirror the server's handler types field for field; the
+//! client tests run them against the real routes.
+
+use crate::handlers::{Pagination, ResponseLinks};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use webauthn_rs::prelude::{CreationChallengeResponse, RequestChallengeResponse};
+
+/// A movie, as sent to `add` and `update` and returned by `get`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Movie {
+    // ---
+    pub title: String,
+    pub year: u16,
+
+    /// 0.0 to 5.0.
+    pub stars: f32,
+
+    /// Up to 10 names of letters, digits, and hyphens; stored lowercase.
+    #[serde(default)]
+    pub genres: Vec<String>,
+}
+
+/// A stored movie and its server-assigned ID.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoredMovie {
+    // ---
+    pub id: String,
+    #[serde(flatten)]
+    pub movie: Movie,
+}
+
+/// Filter and page of [`Client::list_movies`](super::Client::list_movies).
+/// Unset fields take the server defaults (page 1 of 50, all genres).
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ListMovies {
+    // ---
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub genre: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub per_page: Option<u32>,
+}
+
+/// One page of movies, sorted by title, then year.
+#[derive(Debug, Clone)]
+pub struct MoviePage {
+    // ---
+    pub movies: Vec<StoredMovie>,
+    pub pagination: Pagination,
+
+    /// Paths of the neighbouring pages, if any.
+    pub links: ResponseLinks,
+}
+
+/// The data of a `GET /movies/list` envelope.
+#[derive(Deserialize)]
+pub(super) struct MovieList {
+    // ---
+    pub movies: Vec<StoredMovie>,
+}
+
+/// `register/start`: options for the authenticator and the flow to finish.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegistrationStart {
+    // ---
+    /// Pass to `navigator.credentials.create()` (or a test authenticator).
+    pub challenge: CreationChallengeResponse,
+    pub flow_id: Uuid,
+}
+
+/// `auth/start`: options for the authenticator and the flow to finish.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthenticationStart {
+    // ---
+    /// Pass to `navigator.credentials.get()` (or a test authenticator).
+    pub options: RequestChallengeResponse,
+    pub flow_id: Uuid,
+}
+
+/// The `register/finish` response.
+#[derive(Deserialize)]
+pub(super) struct RegistrationFinished {
+    // ---
+    pub credential_id: String,
+}
+
+/// The `auth/finish` response.
+#[derive(Deserialize)]
+pub(super) struct AuthenticationFinished {
+    // ---
+    pub session_token: String,
+}
+
+/// Whether a passkey can leave the authenticator it was created on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PasskeyKind {
+    // ---
+    Synced,
+    DeviceBound,
+}
+
+/// A registered passkey of the signed-in user.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CredentialInfo {
+    // ---
+    /// Base64url credential ID, as taken by `delete_credential`.
+    pub id: String,
+
+    /// RFC 3339 timestamps.
+    pub created_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_used_at: Option<String>,
+
+    /// Set once a sign-in suggested the authenticator was cloned.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compromised_at: Option<String>,
+
+    /// Absent for passkeys registered before this was recorded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<PasskeyKind>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backup_eligible: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backup_state: Option<bool>,
+}
+
+/// One page of the signed-in user's passkeys.
+#[derive(Debug, Clone)]
+pub struct CredentialPage {
+    // ---
+    pub credentials: Vec<CredentialInfo>,
+    pub pagination: Pagination,
+    pub links: ResponseLinks,
+}
+
+/// The data of a `GET /webauthn/credentials` envelope.
+#[derive(Deserialize)]
+pub(super) struct CredentialList {
+    // ---
+    pub credentials: Vec<CredentialInfo>,
+}
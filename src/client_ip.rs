@@ -0,0 +1,443 @@
+//! Client IP resolution behind trusted reverse proxies.
+//!
+//! The client address is the TCP peer from `ConnectInfo<SocketAddr>`, unless
+//! the peer is listed in `AXUM_TRUSTED_PROXIES`. Then the forwarding chain is
+//! walked from the right (nearest hop first) and the first hop that is not
+//! itself a trusted proxy is the client. Hops left of it were supplied by the
+//! client and are ignored, so they cannot be spoofed.
+//!
+//! The chain is read from the RFC 7239 `Forwarded` header (`for=` parameters)
+//! when present, otherwise from `X-Forwarded-For`. The two are never mixed.
+//!
+//! The scheme and host the client used ([`ExternalOrigin`]) are taken from
+//! the same hop: its `proto=` and `host=` parameters, or the matching
+//! `X-Forwarded-Proto` and `X-Forwarded-Host` entries (the last entry when
+//! those lists are shorter, as when a proxy overwrites them). Without a
+//! trusted proxy they come from the `Host` header, over plain HTTP.
+
+use crate::app_state::AppState;
+use crate::config::AccessConfig;
+use axum::{
+    extract::{ConnectInfo, FromRequestParts, OptionalFromRequestParts},
+    http::{header, request::Parts, uri::Authority, HeaderMap, StatusCode},
+};
+use std::convert::Infallible;
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+
+/// The resolved address of the client that made the request.
+///
+/// Requires the router to be served with
+/// `into_make_service_with_connect_info::<SocketAddr>()`. Extracting
+/// `ClientIp` directly fails with 500 when the peer address is unavailable;
+/// extract `Option<ClientIp>` where that should be tolerated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ClientIp(pub IpAddr);
+
+impl fmt::Display for ClientIp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // ---
+        self.0.fmt(f)
+    }
+}
+
+impl OptionalFromRequestParts<AppState> for ClientIp {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Option<Self>, Self::Rejection> {
+        // ---
+        let peer = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip());
+
+        Ok(peer.map(|peer| ClientIp(resolve(&state.access(), peer, &parts.headers))))
+    }
+}
+
+impl FromRequestParts<AppState> for ClientIp {
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, StatusCode> {
+        // ---
+        let client =
+            <Self as OptionalFromRequestParts<AppState>>::from_request_parts(parts, state).await;
+
+        client.ok().flatten().ok_or_else(|| {
+            tracing::error!(
+                "Client IP unavailable; serve with into_make_service_with_connect_info"
+            );
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+    }
+}
+
+/// The scheme and host the client used to reach the service, which differ
+/// from what this server sees when a proxy terminates TLS.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ExternalOrigin {
+    // ---
+    /// `http` or `https`.
+    pub scheme: String,
+
+    /// Host name, with the port if not the default.
+    pub host: String,
+}
+
+impl fmt::Display for ExternalOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // ---
+        write!(f, "{}://{}", self.scheme, self.host)
+    }
+}
+
+impl OptionalFromRequestParts<AppState> for ExternalOrigin {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Option<Self>, Self::Rejection> {
+        // ---
+        let peer = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip());
+        let authority = parts.uri.authority().map(|a| a.as_str());
+
+        Ok(resolve_origin(
+            &state.access(),
+            peer,
+            &parts.headers,
+            authority,
+        ))
+    }
+}
+
+/// Resolves the client address for a request received from `peer`.
+pub(crate) fn resolve(access: &AccessConfig, peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+    // ---
+    client_hop(access, peer, headers).0
+}
+
+/// Resolves the external origin of a request received from `peer`, falling
+/// back to the request's own `authority` (HTTP/2) when there is no `Host`
+/// header. `None` if no host is known.
+pub(crate) fn resolve_origin(
+    access: &AccessConfig,
+    peer: Option<IpAddr>,
+    headers: &HeaderMap,
+    authority: Option<&str>,
+) -> Option<ExternalOrigin> {
+    // ---
+    let (mut proto, mut host) = (None, None);
+    if let Some(peer) = peer.filter(|peer| access.is_trusted_proxy(peer.to_canonical())) {
+        let (_, hop) = client_hop(access, peer, headers);
+        (proto, host) = forwarded_origin(headers, hop);
+    }
+
+    let host = host.or_else(|| {
+        headers
+            .get(header::HOST)
+            .and_then(|v| v.to_str().ok())
+            .or(authority)
+            .filter(|h| is_host(h))
+            .map(str::to_owned)
+    })?;
+
+    Some(ExternalOrigin {
+        scheme: proto.unwrap_or_else(|| "http".to_string()),
+        host,
+    })
+}
+
+/// Walks the forwarding chain for a request from `peer`. Returns the client
+/// address and the index of the hop it came from, or `None` when the peer
+/// itself is the client.
+fn client_hop(access: &AccessConfig, peer: IpAddr, headers: &HeaderMap) -> (IpAddr, Option<usize>) {
+    // ---
+    let mut client = peer.to_canonical();
+    if !access.is_trusted_proxy(client) {
+        return (client, None);
+    }
+
+    let mut hop = None;
+    for (index, node) in forwarding_chain(headers).into_iter().enumerate().rev() {
+        let Some(ip) = node else {
+            break;
+        };
+        client = ip.to_canonical();
+        hop = Some(index);
+        if !access.is_trusted_proxy(client) {
+            break;
+        }
+    }
+
+    (client, hop)
+}
+
+/// Header values, split on commas. Multiple header lines are equivalent to
+/// one comma-joined line, in order.
+fn header_values<'a>(headers: &'a HeaderMap, name: &str) -> Vec<&'a str> {
+    // ---
+    headers
+        .get_all(name)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(str::trim)
+        .collect()
+}
+
+/// The forwarded scheme and host for the chain entry at `hop` (the last
+/// entry if `None`). Values that are not a valid scheme or host are ignored.
+fn forwarded_origin(headers: &HeaderMap, hop: Option<usize>) -> (Option<String>, Option<String>) {
+    // ---
+    let pick = |values: Vec<&str>| -> Option<String> {
+        hop.and_then(|i| values.get(i))
+            .or(values.last())
+            .map(|v| v.trim_matches('"').to_owned())
+    };
+    let param = |element: &str, name: &str| -> Option<String> {
+        element
+            .split(';')
+            .filter_map(|pair| pair.trim().split_once('='))
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.trim().trim_matches('"').to_owned())
+    };
+
+    let forwarded = header_values(headers, "forwarded");
+    let (proto, host) = match pick(forwarded) {
+        Some(element) => (param(&element, "proto"), param(&element, "host")),
+        None => (
+            pick(header_values(headers, "x-forwarded-proto")),
+            pick(header_values(headers, "x-forwarded-host")),
+        ),
+    };
+
+    let proto = proto
+        .map(|p| p.to_ascii_lowercase())
+        .filter(|p| p == "http" || p == "https");
+    (proto, host.filter(|h| is_host(h)))
+}
+
+/// Whether `host` is a bare `host[:port]` authority (no user info).
+fn is_host(host: &str) -> bool {
+    // ---
+    !host.contains('@') && host.parse::<Authority>().is_ok()
+}
+
+/// Returns the forwarding chain, left (original client) to right (nearest
+/// proxy). Unparseable or obfuscated hops are `None`.
+fn forwarding_chain(headers: &HeaderMap) -> Vec<Option<IpAddr>> {
+    // ---
+    let forwarded = header_values(headers, "forwarded");
+    if !forwarded.is_empty() {
+        return forwarded
+            .into_iter()
+            .map(|element| {
+                element
+                    .split(';')
+                    .filter_map(|pair| pair.trim().split_once('='))
+                    .find(|(name, _)| name.eq_ignore_ascii_case("for"))
+                    .and_then(|(_, node)| parse_node(node))
+            })
+            .collect();
+    }
+
+    header_values(headers, "x-forwarded-for")
+        .into_iter()
+        .map(|hop| hop.parse().ok())
+        .collect()
+}
+
+/// Parses a `Forwarded` node: `192.0.2.1`, `"192.0.2.1:8080"`, or
+/// `"[2001:db8::1]:4711"`. Obfuscated identifiers (`unknown`, `_hidden`)
+/// yield `None`.
+fn parse_node(node: &str) -> Option<IpAddr> {
+    // ---
+    let node = node.trim().trim_matches('"');
+
+    if let Some(rest) = node.strip_prefix('[') {
+        return rest.split_once(']')?.0.parse().ok();
+    }
+
+    if let Ok(ip) = node.parse() {
+        return Some(ip);
+    }
+    node.rsplit_once(':')?.0.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn access(trusted: &[&str]) -> AccessConfig {
+        // ---
+        AccessConfig {
+            trusted_proxies: trusted.iter().map(|c| c.parse().unwrap()).collect(),
+            ..AccessConfig::default()
+        }
+    }
+
+    fn headers(name: &'static str, values: &[&str]) -> HeaderMap {
+        // ---
+        let mut map = HeaderMap::new();
+        for value in values {
+            map.append(name, HeaderValue::from_str(value).unwrap());
+        }
+        map
+    }
+
+    fn ip(s: &str) -> IpAddr {
+        // ---
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn forwarded_for_ignored_from_untrusted_peer() {
+        // ---
+        let access = access(&["10.0.0.0/8"]);
+        let xff = headers("x-forwarded-for", &["127.0.0.1"]);
+        assert_eq!(resolve(&access, ip("203.0.113.7"), &xff), ip("203.0.113.7"));
+    }
+
+    #[test]
+    fn forwarded_for_walked_past_trusted_hops() {
+        // ---
+        let access = access(&["10.0.0.0/8"]);
+
+        // Spoofed left-most entry is ignored; the hop our proxy saw wins.
+        let xff = headers("x-forwarded-for", &["127.0.0.1, 198.51.100.4, 10.0.0.2"]);
+        assert_eq!(resolve(&access, ip("10.0.0.1"), &xff), ip("198.51.100.4"));
+
+        let split = headers(
+            "x-forwarded-for",
+            &["127.0.0.1", "198.51.100.4", "10.0.0.2"],
+        );
+        assert_eq!(resolve(&access, ip("10.0.0.1"), &split), ip("198.51.100.4"));
+
+        // No header: the proxy itself is the client.
+        assert_eq!(
+            resolve(&access, ip("10.0.0.1"), &HeaderMap::new()),
+            ip("10.0.0.1")
+        );
+
+        // Garbage stops the walk at the last hop that parsed.
+        let garbage = headers("x-forwarded-for", &["198.51.100.4, nonsense, 10.0.0.2"]);
+        assert_eq!(resolve(&access, ip("10.0.0.1"), &garbage), ip("10.0.0.2"));
+    }
+
+    #[test]
+    fn forwarded_header_preferred_over_x_forwarded_for() {
+        // ---
+        let access = access(&["10.0.0.0/8"]);
+        let mut map = headers(
+            "forwarded",
+            &[r#"for=127.0.0.1, for="[2001:db8::7]:4711";proto=https, For=10.0.0.2:80"#],
+        );
+        map.append("x-forwarded-for", HeaderValue::from_static("198.51.100.4"));
+
+        assert_eq!(resolve(&access, ip("10.0.0.1"), &map), ip("2001:db8::7"));
+
+        let hidden = headers("forwarded", &["for=198.51.100.4, for=_hidden"]);
+        assert_eq!(resolve(&access, ip("10.0.0.1"), &hidden), ip("10.0.0.1"));
+    }
+
+    fn origin(access: &AccessConfig, peer: &str, headers: &HeaderMap) -> Option<String> {
+        // ---
+        resolve_origin(access, Some(ip(peer)), headers, None).map(|o| o.to_string())
+    }
+
+    #[test]
+    fn origin_from_host_without_trusted_proxy() {
+        // ---
+        let access = access(&["10.0.0.0/8"]);
+        let mut map = headers("host", &["api.example.com:8080"]);
+        map.append("x-forwarded-proto", HeaderValue::from_static("https"));
+        map.append("x-forwarded-host", HeaderValue::from_static("evil.example"));
+
+        assert_eq!(
+            origin(&access, "203.0.113.7", &map).as_deref(),
+            Some("http://api.example.com:8080")
+        );
+        assert_eq!(origin(&access, "203.0.113.7", &HeaderMap::new()), None);
+        assert_eq!(
+            resolve_origin(&access, None, &HeaderMap::new(), Some("h2.example"))
+                .map(|o| o.to_string())
+                .as_deref(),
+            Some("http://h2.example")
+        );
+    }
+
+    #[test]
+    fn origin_from_trusted_proxy_headers() {
+        // ---
+        let access = access(&["10.0.0.0/8"]);
+
+        // The edge proxy overwrote X-Forwarded-Proto; the inner one passed it on.
+        let mut map = headers("host", &["backend:8080"]);
+        map.append(
+            "x-forwarded-for",
+            HeaderValue::from_static("198.51.100.4, 10.0.0.2"),
+        );
+        map.append("x-forwarded-proto", HeaderValue::from_static("HTTPS"));
+        map.append(
+            "x-forwarded-host",
+            HeaderValue::from_static("app.example.com"),
+        );
+        assert_eq!(
+            origin(&access, "10.0.0.1", &map).as_deref(),
+            Some("https://app.example.com")
+        );
+
+        // Entries aligned with X-Forwarded-For: the spoofed first one is skipped.
+        let mut map = headers("host", &["backend:8080"]);
+        map.append(
+            "x-forwarded-for",
+            HeaderValue::from_static("127.0.0.1, 198.51.100.4"),
+        );
+        map.append("x-forwarded-proto", HeaderValue::from_static("http, https"));
+        map.append(
+            "x-forwarded-host",
+            HeaderValue::from_static("evil.example, app.example.com"),
+        );
+        assert_eq!(
+            origin(&access, "10.0.0.1", &map).as_deref(),
+            Some("https://app.example.com")
+        );
+
+        // Invalid values fall back to Host and plain HTTP.
+        let mut map = headers("host", &["backend:8080"]);
+        map.append("x-forwarded-proto", HeaderValue::from_static("gopher"));
+        map.append(
+            "x-forwarded-host",
+            HeaderValue::from_static("user@evil.example"),
+        );
+        assert_eq!(
+            origin(&access, "10.0.0.1", &map).as_deref(),
+            Some("http://backend:8080")
+        );
+    }
+
+    #[test]
+    fn origin_from_forwarded_element_of_client_hop() {
+        // ---
+        let access = access(&["10.0.0.0/8"]);
+        let map = headers(
+            "forwarded",
+            &[
+                r#"for=127.0.0.1;proto=http;host=evil.example, for=198.51.100.4;proto=https;host="app.example.com", for=10.0.0.2;proto=http;host=backend"#,
+            ],
+        );
+        assert_eq!(
+            origin(&access, "10.0.0.1", &map).as_deref(),
+            Some("https://app.example.com")
+        );
+    }
+}
@@ -0,0 +1,118 @@
+//! Per-request time budgets.
+//!
+//! The `request_deadline` middleware gives each request a [`Deadline`],
+//! stored in the request extensions and in a task-local for the duration of
+//! the handler. Repository and Redis calls made from the handler's task are
+//! bounded by it with [`bounded`], so a slow dependency fails that call
+//! (and is logged as the culprit) instead of holding the request open.
+//!
+//! Work outside a request (background jobs, spawned tasks) has no deadline
+//! and is not bounded.
+
+use std::fmt;
+use std::future::Future;
+use std::time::Duration;
+use tokio::time::Instant;
+
+tokio::task_local! {
+    static DEADLINE: Deadline;
+}
+
+/// The instant by which a request must be answered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Deadline(Instant);
+
+impl Deadline {
+    // ---
+
+    /// A deadline `budget` from now.
+    pub fn after(budget: Duration) -> Self {
+        // ---
+        Self(Instant::now() + budget)
+    }
+
+    /// The deadline of the request being handled on this task, if any.
+    pub fn current() -> Option<Self> {
+        // ---
+        DEADLINE.try_with(|deadline| *deadline).ok()
+    }
+
+    pub fn instant(&self) -> Instant {
+        // ---
+        self.0
+    }
+
+    /// Time left before the deadline; zero once it has passed.
+    pub fn remaining(&self) -> Duration {
+        // ---
+        self.0.saturating_duration_since(Instant::now())
+    }
+
+    /// Runs `fut` with this deadline visible to [`Deadline::current`].
+    pub async fn scope<F: Future>(self, fut: F) -> F::Output {
+        // ---
+        DEADLINE.scope(self, fut).await
+    }
+}
+
+/// A dependency call did not finish before the request deadline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct DeadlineExceeded;
+
+impl fmt::Display for DeadlineExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // ---
+        f.write_str("request deadline exceeded")
+    }
+}
+
+impl std::error::Error for DeadlineExceeded {}
+
+impl From<DeadlineExceeded> for redis::RedisError {
+    fn from(e: DeadlineExceeded) -> Self {
+        // ---
+        std::io::Error::new(std::io::ErrorKind::TimedOut, e).into()
+    }
+}
+
+/// Awaits `fut`, giving up at the current request's deadline. Without a
+/// deadline (outside a request) this is a plain `.await`.
+pub(crate) async fn bounded<F: Future>(fut: F) -> Result<F::Output, DeadlineExceeded> {
+    // ---
+    match Deadline::current() {
+        Some(deadline) => tokio::time::timeout_at(deadline.instant(), fut)
+            .await
+            .map_err(|_| DeadlineExceeded),
+        None => Ok(fut.await),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+
+    #[tokio::test]
+    async fn bounded_only_inside_a_deadline_scope() {
+        // ---
+        let slow = || tokio::time::sleep(Duration::from_millis(200));
+
+        assert_eq!(Deadline::current(), None);
+        assert!(bounded(slow()).await.is_ok());
+
+        let deadline = Deadline::after(Duration::from_millis(20));
+        let result = deadline
+            .scope(async {
+                assert_eq!(Deadline::current(), Some(deadline));
+                bounded(slow()).await
+            })
+            .await;
+        assert_eq!(result, Err(DeadlineExceeded));
+        assert_eq!(deadline.remaining(), Duration::ZERO);
+
+        let fast = Deadline::after(Duration::from_secs(10))
+            .scope(bounded(async { 7 }))
+            .await;
+        assert_eq!(fast, Ok(7));
+    }
+}
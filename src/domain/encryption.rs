@@ -0,0 +1,37 @@
+use anyhow::Result;
+use std::sync::Arc;
+
+/// A data key encrypted ("wrapped") by a key encryption key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WrappedKey {
+    // ---
+    /// Identifies the key encryption key, so the data key can be unwrapped
+    /// after the current key has been rotated.
+    pub key_id: String,
+
+    /// Provider-specific ciphertext of the data key.
+    pub ciphertext: Vec<u8>,
+}
+
+/// Source of key encryption keys for envelope encryption at rest.
+///
+/// Each stored value is encrypted with its own random data key, and only
+/// the data key is sent to the provider to be wrapped. The built-in
+/// provider holds keys in memory (`AXUM_DATA_ENCRYPTION_KEY`); implement
+/// this trait to delegate wrapping to a KMS instead.
+#[async_trait::async_trait]
+pub trait KeyProvider: Send + Sync {
+    // ---
+    /// ID of the key that new data keys are wrapped with.
+    fn current_key_id(&self) -> &str;
+
+    /// Wrap a data key with the current key encryption key.
+    async fn wrap_key(&self, data_key: &[u8]) -> Result<WrappedKey>;
+
+    /// Unwrap a data key. Must accept keys wrapped by any key that has not
+    /// been retired, not only the current one.
+    async fn unwrap_key(&self, wrapped: &WrappedKey) -> Result<Vec<u8>>;
+}
+
+/// Type alias for any key provider implementation.
+pub type KeyProviderPtr = Arc<dyn KeyProvider>;
@@ -1,5 +1,35 @@
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
+
+/// A snapshot of async runtime load, taken periodically.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RuntimeSample {
+    // ---
+    /// Worker threads driving async tasks.
+    pub workers: usize,
+
+    /// Tasks spawned and not yet finished.
+    pub alive_tasks: usize,
+
+    /// Tasks waiting in the shared queue for a free worker.
+    pub global_queue_depth: usize,
+
+    /// Fraction of time the workers spent running tasks since the previous
+    /// sample, from 0.0 (idle) to 1.0 (saturated).
+    pub worker_busy_ratio: f64,
+
+    /// Threads in the blocking pool (`spawn_blocking`, file I/O).
+    /// Only available when built with `--cfg tokio_unstable`.
+    pub blocking_threads: Option<usize>,
+
+    /// Blocking pool threads with nothing to do.
+    /// Only available when built with `--cfg tokio_unstable`.
+    pub idle_blocking_threads: Option<usize>,
+
+    /// Tasks waiting for a blocking pool thread.
+    /// Only available when built with `--cfg tokio_unstable`.
+    pub blocking_queue_depth: Option<usize>,
+}
 
 /// Abstraction for application metrics (counters, histograms).
 pub trait Metrics: Send + Sync + 'static {
@@ -10,8 +40,64 @@ pub trait Metrics: Send + Sync + 'static {
     /// Record a "movie created" event.
     fn record_movie_created(&self);
 
+    /// Record a "movie updated" event.
+    fn record_movie_updated(&self);
+
+    /// Record a "movie deleted" event.
+    fn record_movie_deleted(&self);
+
     /// Record HTTP request duration and labels.
     fn record_http_request(&self, start: Instant, path: &str, method: &str, status: u16);
+
+    /// Record users removed (or, in dry-run mode, found) by the orphan cleanup job.
+    fn record_orphan_users_removed(&self, count: u64, dry_run: bool);
+
+    /// Record records removed (or, in dry-run mode, found) by one rule of
+    /// the retention job.
+    fn record_retention_purged(&self, rule: &str, count: u64, dry_run: bool);
+
+    /// Record domain events forwarded to the event stream broker, by
+    /// outcome: `delivered`, `failed` (to be retried), or `dropped`.
+    fn record_event_stream(&self, outcome: &str, count: u64);
+
+    /// Record how many events wait to be sent to the event stream broker.
+    fn record_event_stream_buffered(&self, buffered: usize);
+
+    /// Record a webhook delivered successfully to one endpoint.
+    fn record_webhook_delivered(&self);
+
+    /// Record a webhook abandoned after all retries (or dropped because the queue was full).
+    fn record_webhook_dead_letter(&self);
+
+    /// Record a sign-in whose signature counter did not increase, and
+    /// whether the sign-in was rejected because of it.
+    fn record_sign_count_anomaly(&self, rejected: bool);
+
+    /// Record a Redis operation that hit a transient error and was retried,
+    /// and whether a retry then succeeded.
+    fn record_redis_retry(&self, recovered: bool);
+
+    /// Record a request starting (`true`) or finishing (`false`), tracking
+    /// how many are in flight.
+    fn record_in_flight_request(&self, started: bool);
+
+    /// Record a snapshot of async runtime load.
+    fn record_runtime_sample(&self, sample: &RuntimeSample);
+
+    /// Record an outbound HTTP request to `host`, with the response status,
+    /// or `None` if no response arrived.
+    fn record_outbound_request(&self, host: &str, status: Option<u16>);
+
+    /// Record how long a repository call took, by repository method.
+    fn record_db_query(&self, query_name: &str, elapsed: Duration);
+
+    /// Record a WebAuthn ceremony (`reg` or `auth`) whose finish call could
+    /// not take its challenge: expired or unknown, or (`redis_error`) Redis
+    /// failed.
+    fn record_challenge_failure(&self, ceremony: &str, redis_error: bool);
+
+    /// Record a handler panic that was caught and answered with a 500.
+    fn record_panic(&self);
 }
 
 /// Type alias for any backend that implements Metrics.
@@ -1,13 +1,19 @@
+mod encryption;
 mod metrics;
+mod outbox;
 mod repository;
 mod webauthn_models;
 
 // Publicly expose the Metrics abstraction
-pub use metrics::{Metrics, MetricsPtr};
+pub use metrics::{Metrics, MetricsPtr, RuntimeSample};
+
+// Publicly expose the encryption-at-rest key abstraction
+pub use encryption::{KeyProvider, KeyProviderPtr, WrappedKey};
 
 // Publicly expose WebAuthn abstractions
-pub use repository::{Repository, RepositoryPtr};
-pub use webauthn_models::{Credential, User};
+pub use outbox::{OutboxEvent, OutboxWrite};
+pub use repository::{CredentialStats, PurgeSummary, ReencryptSummary, Repository, RepositoryPtr};
+pub use webauthn_models::{normalize_username, Credential, User, DEFAULT_TENANT};
 
 pub async fn init_database_with_retry_from_env() -> anyhow::Result<()> {
     // ---
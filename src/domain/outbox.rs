@@ -0,0 +1,50 @@
+use super::webauthn_models::Credential;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+/// An event waiting in the transactional outbox for delivery.
+///
+/// Written in the same transaction as the change it describes (see
+/// [`Repository::write_with_event`](super::Repository::write_with_event)),
+/// so it is stored exactly when the change is, and removed once delivered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutboxEvent {
+    // ---
+    /// Event ID, stable across delivery attempts.
+    pub id: Uuid,
+
+    /// Event type, e.g. `user.registered`.
+    pub kind: String,
+
+    /// Serialized event, as delivered.
+    pub payload: String,
+
+    pub created_at: DateTime<Utc>,
+
+    /// Times the event has been claimed for delivery, including the
+    /// current claim.
+    pub attempts: i32,
+
+    /// W3C `traceparent` of the request that emitted the event, if any.
+    pub traceparent: Option<String>,
+
+    /// W3C `tracestate` accompanying `traceparent`.
+    pub tracestate: Option<String>,
+}
+
+/// A change stored together with an [`OutboxEvent`].
+#[derive(Debug, Clone)]
+pub enum OutboxWrite {
+    // ---
+    /// [`Repository::save_credential`](super::Repository::save_credential).
+    SaveCredential(Credential),
+
+    /// [`Repository::soft_delete_credential`](super::Repository::soft_delete_credential).
+    SoftDeleteCredential(Vec<u8>),
+
+    /// [`Repository::mark_credential_compromised`](super::Repository::mark_credential_compromised).
+    MarkCredentialCompromised(Vec<u8>),
+
+    /// Nothing besides the event, for events about changes kept elsewhere.
+    EventOnly,
+}
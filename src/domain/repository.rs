@@ -1,35 +1,255 @@
-use super::webauthn_models::{Credential, User};
+use super::outbox::{OutboxEvent, OutboxWrite};
+use super::webauthn_models::{Credential, User, DEFAULT_TENANT};
 use anyhow::Result;
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
 use std::sync::Arc;
+use std::time::Duration;
 use uuid::Uuid;
 
 /// Abstraction for WebAuthn data persistence.
+///
+/// Users and credentials can be soft-deleted: they are hidden from every
+/// lookup but kept until [`Repository::purge_deleted`] removes them, so a
+/// deletion can be undone within a grace period. A soft-deleted user still
+/// reserves their username until purged.
+///
+/// Usernames are matched after
+/// [`normalize_username`](super::normalize_username): a tenant cannot hold
+/// two users whose names differ only in case or Unicode normalization.
 #[async_trait::async_trait]
 pub trait Repository: Send + Sync {
     // ---
-    /// Create a new user.
-    async fn create_user(&self, username: &str) -> Result<User>;
+    /// Check that the backing store is reachable and answering queries.
+    async fn ping(&self) -> Result<()>;
 
-    /// Get user by username.
-    async fn get_user_by_username(&self, username: &str) -> Result<Option<User>>;
+    /// Create a new user in `tenant_id`.
+    async fn create_user_in(&self, tenant_id: &str, username: &str) -> Result<User>;
+
+    /// Get a user of `tenant_id` by username.
+    async fn get_user_by_username_in(
+        &self,
+        tenant_id: &str,
+        username: &str,
+    ) -> Result<Option<User>>;
+
+    /// Get the user of `tenant_id` named `username`, creating it if there
+    /// is none. Atomic: concurrent calls for a new username all return the
+    /// same user. Fails if a soft-deleted user reserves the name.
+    async fn get_or_create_user_in(&self, tenant_id: &str, username: &str) -> Result<User>;
+
+    /// Create a new user in the default tenant.
+    async fn create_user(&self, username: &str) -> Result<User> {
+        // ---
+        self.create_user_in(DEFAULT_TENANT, username).await
+    }
+
+    /// Get or create a user of the default tenant.
+    async fn get_or_create_user(&self, username: &str) -> Result<User> {
+        // ---
+        self.get_or_create_user_in(DEFAULT_TENANT, username).await
+    }
+
+    /// Get a user of the default tenant by username.
+    async fn get_user_by_username(&self, username: &str) -> Result<Option<User>> {
+        // ---
+        self.get_user_by_username_in(DEFAULT_TENANT, username).await
+    }
 
     /// Get user by ID.
     async fn get_user_by_id(&self, user_id: Uuid) -> Result<Option<User>>;
 
+    /// Store `password_hash` (a PHC string) as the user's password,
+    /// replacing any previous one.
+    ///
+    /// Returns `false` if there is no active user with this ID.
+    async fn set_password_hash(&self, user_id: Uuid, password_hash: &str) -> Result<bool>;
+
+    /// The password hash of an active user, if they have a password.
+    async fn get_password_hash(&self, user_id: Uuid) -> Result<Option<String>>;
+
     /// Save a new credential for a user.
     async fn save_credential(&self, credential: Credential) -> Result<()>;
 
+    /// Save several new credentials, possibly for different users, in one
+    /// round trip. Either all are saved or, on error, none are.
+    async fn save_credentials(&self, credentials: Vec<Credential>) -> Result<()>;
+
     /// Get all credentials for a user.
     async fn get_credentials_by_user(&self, user_id: Uuid) -> Result<Vec<Credential>>;
 
+    /// When a user's credentials last changed: the latest creation, sign-in,
+    /// clone flag, or soft delete among them, soft-deleted ones included.
+    /// `None` if the user has no credentials. Restores and hard deletes of
+    /// active credentials do not advance it.
+    async fn credentials_modified_at(&self, user_id: Uuid) -> Result<Option<DateTime<Utc>>>;
+
     /// Get a specific credential by its ID.
     async fn get_credential_by_id(&self, credential_id: &[u8]) -> Result<Option<Credential>>;
 
     /// Update an existing credential (typically to increment counter).
     async fn update_credential(&self, credential: Credential) -> Result<()>;
 
-    /// Delete a credential by its ID.
+    /// Permanently delete a credential by its ID.
     async fn delete_credential(&self, credential_id: &[u8]) -> Result<()>;
+
+    /// Permanently delete all of a user's credentials, including
+    /// soft-deleted ones, keeping the user.
+    ///
+    /// Returns the number of credentials deleted.
+    async fn delete_credentials_by_user(&self, user_id: Uuid) -> Result<u64>;
+
+    /// Permanently delete a user and all of their credentials, including
+    /// soft-deleted ones.
+    ///
+    /// Returns `false` if there is no user with this ID.
+    async fn delete_user(&self, user_id: Uuid) -> Result<bool>;
+
+    /// Soft-delete a user together with their active credentials.
+    ///
+    /// Returns `false` if there is no active user with this ID.
+    async fn soft_delete_user(&self, user_id: Uuid) -> Result<bool>;
+
+    /// Restore a soft-deleted user and the credentials deleted along with them.
+    ///
+    /// Returns `false` if there is no soft-deleted user with this ID.
+    async fn restore_user(&self, user_id: Uuid) -> Result<bool>;
+
+    /// Soft-delete a single credential.
+    ///
+    /// Returns `false` if there is no active credential with this ID.
+    async fn soft_delete_credential(&self, credential_id: &[u8]) -> Result<bool>;
+
+    /// Restore a soft-deleted credential.
+    ///
+    /// Returns `false` if there is no soft-deleted credential with this ID,
+    /// or its owner is itself soft-deleted (restore the user instead).
+    async fn restore_credential(&self, credential_id: &[u8]) -> Result<bool>;
+
+    /// Record that a credential may have been cloned.
+    ///
+    /// Returns `false` if there is no such credential or it was already
+    /// flagged, so callers can notify only once.
+    async fn mark_credential_compromised(&self, credential_id: &[u8]) -> Result<bool>;
+
+    /// Permanently delete users and credentials soft-deleted before `cutoff`.
+    ///
+    /// With `dry_run`, nothing is deleted and the returned summary counts
+    /// what would have been.
+    async fn purge_deleted(&self, cutoff: DateTime<Utc>, dry_run: bool) -> Result<PurgeSummary>;
+
+    /// Delete active users created before `created_before` that have no
+    /// credentials at all and no password (registration was started but
+    /// never finished).
+    ///
+    /// With `dry_run` nothing is deleted. Returns the number of users
+    /// deleted, or that would have been deleted.
+    async fn delete_orphaned_users(
+        &self,
+        created_before: DateTime<Utc>,
+        dry_run: bool,
+    ) -> Result<u64>;
+
+    /// Count active users and credentials, and how many users have each
+    /// number of active credentials.
+    async fn credential_stats(&self) -> Result<CredentialStats>;
+
+    /// List stored credentials ordered by ID, starting after `after`,
+    /// including soft-deleted ones. Intended for maintenance jobs that must
+    /// visit every row, such as re-encryption.
+    async fn list_all_credentials(
+        &self,
+        after: Option<&[u8]>,
+        limit: u32,
+    ) -> Result<Vec<Credential>>;
+
+    /// Overwrite a credential's stored key material, even if it is
+    /// soft-deleted, provided it still equals `expected`. The counter and
+    /// timestamps are left unchanged.
+    ///
+    /// Returns `false` if the credential is gone or was changed
+    /// concurrently, so a maintenance job never overwrites a newer write.
+    async fn replace_public_key(
+        &self,
+        credential_id: &[u8],
+        expected: &[u8],
+        public_key: &[u8],
+    ) -> Result<bool>;
+
+    /// Apply `write` and queue `event` in the outbox in one transaction.
+    ///
+    /// Returns `false`, queuing nothing, if `write` changed nothing (the
+    /// credential was already deleted or flagged), so an event is only
+    /// ever stored for a change that happened.
+    async fn write_with_event(&self, write: OutboxWrite, event: &OutboxEvent) -> Result<bool>;
+
+    /// Claim up to `limit` queued events, oldest first, for `lease`.
+    ///
+    /// Events claimed by another worker whose lease has not expired are
+    /// skipped; an event whose delivery never finished (the worker died)
+    /// is claimed again once its lease runs out.
+    async fn claim_events(&self, limit: u32, lease: Duration) -> Result<Vec<OutboxEvent>>;
+
+    /// Remove an event from the outbox once it has been delivered.
+    async fn delete_event(&self, event_id: Uuid) -> Result<()>;
+
+    /// Re-encrypt stored credentials under the current data encryption key.
+    ///
+    /// Returns `None` if this repository does not encrypt credentials,
+    /// which is the default for plain database backends.
+    async fn reencrypt_credentials(&self) -> Result<Option<ReencryptSummary>> {
+        // ---
+        Ok(None)
+    }
+}
+
+/// Outcome of [`Repository::reencrypt_credentials`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReencryptSummary {
+    // ---
+    /// Credentials rewritten under the current key.
+    pub reencrypted: u64,
+
+    /// Credentials already encrypted under the current key.
+    pub unchanged: u64,
+}
+
+/// Aggregate counts from [`Repository::credential_stats`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CredentialStats {
+    // ---
+    pub users: u64,
+    pub credentials: u64,
+
+    /// Number of users by their number of credentials, including users
+    /// with none.
+    pub users_by_credential_count: BTreeMap<u64, u64>,
+}
+
+impl CredentialStats {
+    // ---
+    /// Builds the totals from `(credentials, users)` pairs.
+    pub fn from_distribution(rows: impl IntoIterator<Item = (u64, u64)>) -> Self {
+        // ---
+        let users_by_credential_count: BTreeMap<u64, u64> = rows.into_iter().collect();
+        Self {
+            users: users_by_credential_count.values().sum(),
+            credentials: users_by_credential_count
+                .iter()
+                .map(|(credentials, users)| credentials * users)
+                .sum(),
+            users_by_credential_count,
+        }
+    }
+}
+
+/// Number of rows removed (or, in a dry run, found) by
+/// [`Repository::purge_deleted`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PurgeSummary {
+    // ---
+    pub users: u64,
+    pub credentials: u64,
 }
 
 /// Type alias for any backend that implements Repository.
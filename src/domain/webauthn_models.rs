@@ -1,22 +1,40 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use unicode_normalization::UnicodeNormalization;
 use uuid::Uuid;
 
+/// Tenant that owns users when multi-tenancy is not configured, including
+/// every user created before it was.
+pub const DEFAULT_TENANT: &str = "default";
+
+/// Folds `username` to the form usernames are compared in: lowercase, then
+/// Unicode NFC, so `Alice@Example.com` and `alice@example.com` (or a
+/// precomposed and a decomposed `é`) name the same account.
+pub fn normalize_username(username: &str) -> String {
+    // ---
+    username.to_lowercase().nfc().collect()
+}
+
 /// Represents a user in the WebAuthn system.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct User {
     // ---
     pub id: Uuid,
+    /// Tenant the user belongs to; usernames are unique per tenant.
+    pub tenant_id: String,
+    /// Username as first registered. Lookups ignore case and Unicode
+    /// normalization; see [`normalize_username`].
     pub username: String,
     pub created_at: DateTime<Utc>,
 }
 
 impl User {
     // ---
-    pub fn new(username: String) -> Self {
+    pub fn new(tenant_id: String, username: String) -> Self {
         // ---
         Self {
             id: Uuid::new_v4(),
+            tenant_id,
             username,
             created_at: Utc::now(),
         }
@@ -36,16 +54,30 @@ pub struct Credential {
     /// Public key for signature verification
     pub public_key: Vec<u8>,
 
-    /// Signature counter (for replay attack prevention)
-    pub counter: i32,
+    /// Signature counter (for replay attack prevention). The authenticator
+    /// reports a `u32`; `i64` holds its full range.
+    pub counter: i64,
 
     /// When this credential was created
     pub created_at: DateTime<Utc>,
+
+    /// When a sign-in first suggested the authenticator was cloned
+    pub compromised_at: Option<DateTime<Utc>>,
+
+    /// When this credential last signed in, if ever
+    pub last_used_at: Option<DateTime<Utc>>,
+
+    /// Backup eligible (BE flag): a synced, multi-device passkey rather than
+    /// one bound to a single authenticator. `None` if not yet recorded.
+    pub backup_eligible: Option<bool>,
+
+    /// Backed up (BS flag) as of the last registration or sign-in.
+    pub backup_state: Option<bool>,
 }
 
 impl Credential {
     // ---
-    pub fn new(id: Vec<u8>, user_id: Uuid, public_key: Vec<u8>, counter: i32) -> Self {
+    pub fn new(id: Vec<u8>, user_id: Uuid, public_key: Vec<u8>, counter: i64) -> Self {
         // ---
         Self {
             id,
@@ -53,6 +85,10 @@ impl Credential {
             public_key,
             counter,
             created_at: Utc::now(),
+            compromised_at: None,
+            last_used_at: None,
+            backup_eligible: None,
+            backup_state: None,
         }
     }
 }
@@ -0,0 +1,104 @@
+//! Broadcast bus connecting event producers (handlers) to SSE subscribers.
+
+use super::event::{ServerEvent, ServerEventKind};
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+/// Events buffered per subscriber before it starts lagging.
+const DEFAULT_CAPACITY: usize = 256;
+
+const HEALTH_UNKNOWN: u8 = 0;
+const HEALTH_OK: u8 = 1;
+const HEALTH_ERROR: u8 = 2;
+
+/// Fan-out of [`ServerEvent`]s to any number of subscribers.
+///
+/// Publishing never blocks and is a no-op when nobody is subscribed.
+/// Subscribers that fall more than the buffer capacity behind skip the
+/// oldest events. Cheap to clone.
+#[derive(Clone)]
+pub struct EventBus {
+    // ---
+    sender: broadcast::Sender<ServerEvent>,
+
+    /// Last observed health state, for publishing transitions only.
+    health: Arc<AtomicU8>,
+}
+
+impl EventBus {
+    // ---
+    /// Creates a bus buffering up to `capacity` events per subscriber.
+    pub fn with_capacity(capacity: usize) -> Self {
+        // ---
+        let (sender, _) = broadcast::channel(capacity);
+        Self {
+            sender,
+            health: Arc::new(AtomicU8::new(HEALTH_UNKNOWN)),
+        }
+    }
+
+    /// Publishes `event` to all current subscribers.
+    pub fn publish(&self, event: ServerEvent) {
+        // ---
+        // Err only means there are no subscribers
+        let _ = self.sender.send(event);
+    }
+
+    /// Subscribes to events published from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<ServerEvent> {
+        // ---
+        self.sender.subscribe()
+    }
+
+    /// Records a health check result, publishing `health.changed` when it
+    /// differs from the previous one. The first result is only published if
+    /// it is a failure.
+    pub fn record_health(&self, healthy: bool) {
+        // ---
+        let current = if healthy { HEALTH_OK } else { HEALTH_ERROR };
+        let previous = self.health.swap(current, Ordering::Relaxed);
+
+        if previous == current || (previous == HEALTH_UNKNOWN && healthy) {
+            return;
+        }
+
+        self.publish(ServerEvent::new(
+            ServerEventKind::HealthChanged,
+            None,
+            serde_json::json!({ "status": if healthy { "ok" } else { "error" } }),
+        ));
+    }
+}
+
+impl Default for EventBus {
+    // ---
+    fn default() -> Self {
+        // ---
+        Self::with_capacity(DEFAULT_CAPACITY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+
+    #[test]
+    fn health_publishes_transitions_only() {
+        // ---
+        let bus = EventBus::default();
+        let mut rx = bus.subscribe();
+
+        bus.record_health(true);
+        bus.record_health(true);
+        bus.record_health(false);
+        bus.record_health(false);
+        bus.record_health(true);
+
+        let statuses: Vec<_> = std::iter::from_fn(|| rx.try_recv().ok())
+            .map(|e| e.data["status"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(statuses, ["error", "ok"]);
+    }
+}
@@ -0,0 +1,261 @@
+//! Domain events: what the service layer did, for the side effects that
+//! follow from it.
+//!
+//! Services publish a [`DomainEvent`] on the [`DomainEventBus`] once a change
+//! is stored, and subscribers turn it into metrics, audit log lines, SSE
+//! events, and webhooks. A service only describes what happened; it does
+//! not know who reacts.
+//!
+//! Webhook events that must not be lost with their database change (a
+//! registration, a deleted passkey, ...) are still stored by the service in
+//! the same transaction, through the outbox.
+
+use super::event::{ServerEvent, ServerEventKind};
+use std::net::IpAddr;
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+/// Events buffered per subscriber before it starts lagging.
+const DEFAULT_CAPACITY: usize = 1024;
+
+/// Something that happened in the service layer.
+///
+/// Passkey credential IDs are base64url encoded. A `credential_id` of
+/// `None` on a sign-in or registration means a password was used.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum DomainEvent {
+    // ---
+    /// A user registered their first passkey, or a password.
+    UserRegistered {
+        user_id: Uuid,
+        username: String,
+        credential_id: Option<String>,
+    },
+
+    /// A user who already had a passkey registered another.
+    CredentialAdded {
+        user_id: Uuid,
+        username: String,
+        credential_id: String,
+    },
+
+    /// A user signed in and was given a session.
+    AuthSucceeded {
+        user_id: Uuid,
+        username: String,
+        credential_id: Option<String>,
+        client_ip: Option<IpAddr>,
+    },
+
+    /// A user deleted one of their passkeys.
+    CredentialDeleted {
+        user_id: Uuid,
+        username: String,
+        credential_id: String,
+    },
+
+    MovieCreated {
+        id: String,
+    },
+
+    MovieUpdated {
+        id: String,
+    },
+
+    MovieDeleted {
+        id: String,
+    },
+}
+
+impl DomainEvent {
+    // ---
+
+    /// Name of the event in the audit log (`user.registered`, ...).
+    pub fn as_str(&self) -> &'static str {
+        // ---
+        match self {
+            Self::UserRegistered { .. } => "user.registered",
+            Self::CredentialAdded { .. } => "credential.added",
+            Self::AuthSucceeded { .. } => "auth.succeeded",
+            Self::CredentialDeleted { .. } => "credential.deleted",
+            Self::MovieCreated { .. } => "movie.created",
+            Self::MovieUpdated { .. } => "movie.updated",
+            Self::MovieDeleted { .. } => "movie.deleted",
+        }
+    }
+
+    /// The user the event concerns, if any.
+    pub fn user_id(&self) -> Option<Uuid> {
+        // ---
+        match self {
+            Self::UserRegistered { user_id, .. }
+            | Self::CredentialAdded { user_id, .. }
+            | Self::AuthSucceeded { user_id, .. }
+            | Self::CredentialDeleted { user_id, .. } => Some(*user_id),
+            Self::MovieCreated { .. } | Self::MovieUpdated { .. } | Self::MovieDeleted { .. } => {
+                None
+            }
+        }
+    }
+
+    /// The event streamed to SSE subscribers, if there is one. A passkey
+    /// added to an account is streamed as `user.registered`, as it always
+    /// has been.
+    pub fn to_server_event(&self) -> Option<ServerEvent> {
+        // ---
+        let (kind, username, credential_id) = match self {
+            Self::UserRegistered {
+                username,
+                credential_id,
+                ..
+            } => (
+                ServerEventKind::UserRegistered,
+                username,
+                credential_id.as_deref(),
+            ),
+            Self::CredentialAdded {
+                username,
+                credential_id,
+                ..
+            } => (
+                ServerEventKind::UserRegistered,
+                username,
+                Some(credential_id.as_str()),
+            ),
+            Self::AuthSucceeded {
+                username,
+                credential_id,
+                ..
+            } => (ServerEventKind::Login, username, credential_id.as_deref()),
+            Self::CredentialDeleted {
+                username,
+                credential_id,
+                ..
+            } => (
+                ServerEventKind::CredentialDeleted,
+                username,
+                Some(credential_id.as_str()),
+            ),
+            Self::MovieCreated { .. } | Self::MovieUpdated { .. } | Self::MovieDeleted { .. } => {
+                return None
+            }
+        };
+
+        let data = match credential_id {
+            Some(id) => serde_json::json!({ "username": username, "credential_id": id }),
+            None => serde_json::json!({ "username": username, "method": "password" }),
+        };
+        let event = ServerEvent::new(kind, self.user_id(), data);
+        Some(match self {
+            Self::AuthSucceeded { client_ip, .. } => event.with_client_ip(*client_ip),
+            _ => event,
+        })
+    }
+}
+
+/// Fan-out of [`DomainEvent`]s from the service layer to its subscribers.
+///
+/// Like the [`EventBus`](super::EventBus), publishing never blocks and is a
+/// no-op when nobody is subscribed, and a subscriber that falls behind by
+/// more than the buffer skips the oldest events. Cheap to clone.
+#[derive(Clone)]
+pub(crate) struct DomainEventBus {
+    // ---
+    sender: broadcast::Sender<DomainEvent>,
+}
+
+impl DomainEventBus {
+    // ---
+
+    /// Publishes `event` to all current subscribers.
+    pub fn publish(&self, event: DomainEvent) {
+        // ---
+        // Err only means there are no subscribers
+        let _ = self.sender.send(event);
+    }
+
+    /// Subscribes to events published from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<DomainEvent> {
+        // ---
+        self.sender.subscribe()
+    }
+}
+
+impl Default for DomainEventBus {
+    // ---
+    fn default() -> Self {
+        // ---
+        let (sender, _) = broadcast::channel(DEFAULT_CAPACITY);
+        Self { sender }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+
+    #[test]
+    fn server_events_keep_their_wire_format() {
+        // ---
+        let user_id = Uuid::new_v4();
+        let ip: IpAddr = "198.51.100.4".parse().unwrap();
+
+        let login = DomainEvent::AuthSucceeded {
+            user_id,
+            username: "alice".into(),
+            credential_id: Some("AQID".into()),
+            client_ip: Some(ip),
+        }
+        .to_server_event()
+        .unwrap();
+        assert_eq!(login.kind, ServerEventKind::Login);
+        assert_eq!(login.user_id, Some(user_id));
+        assert_eq!(
+            login.data,
+            serde_json::json!({
+                "username": "alice",
+                "credential_id": "AQID",
+                "client_ip": "198.51.100.4",
+            })
+        );
+
+        let registered = DomainEvent::UserRegistered {
+            user_id,
+            username: "alice".into(),
+            credential_id: None,
+        }
+        .to_server_event()
+        .unwrap();
+        assert_eq!(registered.kind, ServerEventKind::UserRegistered);
+        assert_eq!(registered.data["method"], "password");
+
+        let added = DomainEvent::CredentialAdded {
+            user_id,
+            username: "alice".into(),
+            credential_id: "BAUG".into(),
+        }
+        .to_server_event()
+        .unwrap();
+        assert_eq!(added.kind, ServerEventKind::UserRegistered);
+
+        let movie = DomainEvent::MovieCreated { id: "m1".into() };
+        assert!(movie.to_server_event().is_none());
+    }
+
+    #[test]
+    fn subscribers_receive_published_events() {
+        // ---
+        let bus = DomainEventBus::default();
+        bus.publish(DomainEvent::MovieDeleted { id: "lost".into() });
+
+        let mut rx = bus.subscribe();
+        bus.publish(DomainEvent::MovieDeleted { id: "m1".into() });
+
+        assert_eq!(
+            rx.try_recv().unwrap(),
+            DomainEvent::MovieDeleted { id: "m1".into() }
+        );
+        assert!(rx.try_recv().is_err());
+    }
+}
@@ -0,0 +1,139 @@
+//! Events published on the in-process bus and streamed over SSE.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::net::IpAddr;
+use uuid::Uuid;
+
+/// Type of server event, used as the SSE `event:` field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum ServerEventKind {
+    // ---
+    /// A passkey registration completed.
+    #[serde(rename = "user.registered")]
+    UserRegistered,
+
+    /// A user signed in with a passkey.
+    #[serde(rename = "auth.login")]
+    Login,
+
+    /// A user deleted one of their passkeys.
+    #[serde(rename = "credential.deleted")]
+    CredentialDeleted,
+
+    /// The full health check changed between `ok` and `error`.
+    #[serde(rename = "health.changed")]
+    HealthChanged,
+
+    /// An administrative action (purge, webhook changes, ...).
+    #[serde(rename = "audit")]
+    Audit,
+}
+
+impl ServerEventKind {
+    // ---
+    /// Wire name of the event (`user.registered`, ...).
+    pub fn as_str(&self) -> &'static str {
+        // ---
+        match self {
+            Self::UserRegistered => "user.registered",
+            Self::Login => "auth.login",
+            Self::CredentialDeleted => "credential.deleted",
+            Self::HealthChanged => "health.changed",
+            Self::Audit => "audit",
+        }
+    }
+}
+
+/// A single event on the bus.
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerEvent {
+    // ---
+    pub id: Uuid,
+
+    #[serde(rename = "type")]
+    pub kind: ServerEventKind,
+
+    pub created_at: DateTime<Utc>,
+
+    /// User the event concerns. Non-admin subscribers only see their own
+    /// events plus health changes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<Uuid>,
+
+    pub data: serde_json::Value,
+}
+
+impl ServerEvent {
+    // ---
+    /// Creates an event of `kind`, optionally scoped to a user.
+    pub fn new(kind: ServerEventKind, user_id: Option<Uuid>, data: serde_json::Value) -> Self {
+        // ---
+        Self {
+            id: Uuid::new_v4(),
+            kind,
+            created_at: Utc::now(),
+            user_id,
+            data,
+        }
+    }
+
+    /// Creates an admin-only audit event for `action` (e.g. `admin.purge`).
+    pub fn audit(action: &str, details: serde_json::Value) -> Self {
+        // ---
+        Self::new(
+            ServerEventKind::Audit,
+            None,
+            serde_json::json!({ "action": action, "details": details }),
+        )
+    }
+
+    /// Records the requesting client's address in the event data, if known.
+    pub fn with_client_ip(mut self, client_ip: Option<IpAddr>) -> Self {
+        // ---
+        if let (Some(ip), Some(data)) = (client_ip, self.data.as_object_mut()) {
+            data.insert("client_ip".to_string(), ip.to_string().into());
+        }
+        self
+    }
+
+    /// Whether a subscriber authenticated as `user_id` (not an admin) may see this event.
+    pub fn visible_to(&self, user_id: Uuid) -> bool {
+        // ---
+        self.kind == ServerEventKind::HealthChanged || self.user_id == Some(user_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+
+    #[test]
+    fn users_see_own_events_and_health_only() {
+        // ---
+        let me = Uuid::new_v4();
+        let other = Uuid::new_v4();
+        let own = ServerEvent::new(ServerEventKind::Login, Some(me), serde_json::json!({}));
+        let theirs = ServerEvent::new(ServerEventKind::Login, Some(other), serde_json::json!({}));
+        let health = ServerEvent::new(ServerEventKind::HealthChanged, None, serde_json::json!({}));
+        let audit = ServerEvent::audit("admin.purge", serde_json::json!({}));
+
+        assert!(own.visible_to(me));
+        assert!(!theirs.visible_to(me));
+        assert!(health.visible_to(me));
+        assert!(!audit.visible_to(me));
+    }
+
+    #[test]
+    fn client_ip_added_to_object_data() {
+        // ---
+        let ip: IpAddr = "198.51.100.4".parse().unwrap();
+        let audit =
+            ServerEvent::audit("admin.purge", serde_json::json!({})).with_client_ip(Some(ip));
+        assert_eq!(audit.data["client_ip"], "198.51.100.4");
+
+        let unknown = ServerEvent::audit("admin.purge", serde_json::json!({})).with_client_ip(None);
+        assert!(unknown.data.get("client_ip").is_none());
+    }
+}
@@ -0,0 +1,11 @@
+// Gateway module - in-process event buses: server events (feeds the SSE
+// stream) and domain events published by the service layer
+// Modules are private, only exported symbols are public
+
+mod bus;
+mod domain;
+mod event;
+
+pub use bus::EventBus;
+pub(crate) use domain::{DomainEvent, DomainEventBus};
+pub use event::{ServerEvent, ServerEventKind};
@@ -0,0 +1,311 @@
+//! Batches audit events and metrics snapshots into NDJSON files in S3.
+//!
+//! Audit events are collected from the [`EventBus`] as they are published
+//! and held in memory until the next export. Each export uploads up to two
+//! objects, named by the time of the export:
+//!
+//! | Object                                          | One line per      |
+//! |:------------------------------------------------|:------------------|
+//! | `{prefix}audit/YYYY/MM/DD/{time}-{id}.ndjson`   | Audit event       |
+//! | `{prefix}metrics/YYYY/MM/DD/{time}.ndjson`      | Metric sample     |
+//!
+//! An audit file is only written when there are new events. If its upload
+//! fails, the events are kept for the next attempt.
+//!
+//! Audit files are removed again by the retention job once the day they
+//! were written is older than `AXUM_RETENTION_AUDIT_LOG_DAYS`.
+
+use super::s3::S3Client;
+use super::snapshot::parse_samples;
+use crate::config::ExportConfig;
+use crate::domain::MetricsPtr;
+use crate::events::{EventBus, ServerEvent, ServerEventKind};
+use crate::infrastructure::HttpClient;
+use crate::shutdown::ShutdownSignal;
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate, Utc};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, PoisonError};
+use tokio::sync::broadcast::error::RecvError;
+
+const CONTENT_TYPE: &str = "application/x-ndjson";
+
+/// What one export uploaded.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ExportSummary {
+    // ---
+    pub audit_events: usize,
+    pub metrics_samples: usize,
+
+    /// Keys of the uploaded objects.
+    pub objects: Vec<String>,
+}
+
+/// Exports audit events and metrics to the configured bucket, periodically
+/// and on demand. Cheap to clone.
+#[derive(Clone)]
+pub(crate) struct AuditExporter {
+    // ---
+    inner: Option<Arc<Inner>>,
+}
+
+struct Inner {
+    // ---
+    s3: S3Client,
+    prefix: String,
+    metrics: MetricsPtr,
+    max_buffered: usize,
+
+    /// Audit events not yet exported, oldest first.
+    pending: Mutex<VecDeque<ServerEvent>>,
+
+    /// Held for the duration of an export, so that periodic and on-demand
+    /// exports do not interleave.
+    running: tokio::sync::Mutex<()>,
+}
+
+impl AuditExporter {
+    // ---
+
+    /// Starts collecting audit events from `events` and exporting every
+    /// `cfg.interval` through `http` on the current Tokio runtime. A last
+    /// export runs when `shutdown` is triggered.
+    ///
+    /// # Errors
+    /// Returns an error if no bucket is configured.
+    ///
+    /// # Panics
+    /// Panics if called outside a Tokio runtime.
+    pub(crate) fn spawn(
+        cfg: &ExportConfig,
+        events: &EventBus,
+        http: HttpClient,
+        metrics: MetricsPtr,
+        shutdown: ShutdownSignal,
+    ) -> Result<Self> {
+        // ---
+        let inner = Arc::new(Inner {
+            s3: S3Client::new(cfg, http)?,
+            prefix: cfg.prefix.clone(),
+            metrics,
+            max_buffered: cfg.max_buffered,
+            pending: Mutex::new(VecDeque::new()),
+            running: tokio::sync::Mutex::new(()),
+        });
+        tracing::info!(
+            "Exporting audit events and metrics to {}/{} every {}s",
+            cfg.endpoint,
+            cfg.bucket.as_deref().unwrap_or_default(),
+            cfg.interval.as_secs()
+        );
+
+        // Subscribe now, so events published while the task starts are kept
+        let mut receiver = events.subscribe();
+        let collector = inner.clone();
+        tokio::spawn(async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) if event.kind == ServerEventKind::Audit => collector.buffer(event),
+                    Ok(_) => {}
+                    Err(RecvError::Lagged(missed)) => {
+                        tracing::warn!("Audit export fell behind; {missed} events were not seen");
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        });
+
+        let exporter = inner.clone();
+        let period = cfg.interval;
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            interval.tick().await; // first tick completes immediately
+            let stopped = shutdown.triggered();
+            tokio::pin!(stopped);
+
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {
+                        if let Err(e) = exporter.export().await {
+                            tracing::error!("Audit export failed: {e:#}");
+                        }
+                    }
+                    _ = &mut stopped => break,
+                }
+            }
+            if let Err(e) = exporter.export().await {
+                tracing::error!("Final audit export failed: {e:#}");
+            }
+        });
+
+        Ok(Self { inner: Some(inner) })
+    }
+
+    /// An exporter that does nothing.
+    pub(crate) fn disabled() -> Self {
+        // ---
+        Self { inner: None }
+    }
+
+    /// Whether exporting is configured.
+    pub(crate) fn is_enabled(&self) -> bool {
+        // ---
+        self.inner.is_some()
+    }
+
+    /// Exports the events collected so far and a metrics snapshot now.
+    /// Returns `None` if exporting is not configured.
+    ///
+    /// # Errors
+    /// Returns an error if an upload fails.
+    pub(crate) async fn export_now(&self) -> Result<Option<ExportSummary>> {
+        // ---
+        match &self.inner {
+            Some(inner) => inner.export().await.map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Deletes the audit files written on days before `cutoff`, or with
+    /// `dry_run` only counts them. Returns `None` if exporting is not
+    /// configured.
+    ///
+    /// # Errors
+    /// Returns an error if listing the bucket or a deletion fails.
+    pub(crate) async fn expire_audit_logs(
+        &self,
+        cutoff: DateTime<Utc>,
+        dry_run: bool,
+    ) -> Result<Option<u64>> {
+        // ---
+        let Some(inner) = &self.inner else {
+            return Ok(None);
+        };
+        let audit_prefix = format!("{}audit/", inner.prefix);
+        let expired: Vec<String> = inner
+            .s3
+            .list_objects(&audit_prefix)
+            .await?
+            .into_iter()
+            .filter(|key| {
+                written_on(&key[audit_prefix.len()..]).is_some_and(|day| day < cutoff.date_naive())
+            })
+            .collect();
+
+        if !dry_run {
+            for key in &expired {
+                inner.s3.delete_object(key).await?;
+            }
+        }
+        Ok(Some(expired.len() as u64))
+    }
+}
+
+impl Inner {
+    // ---
+
+    fn pending(&self) -> std::sync::MutexGuard<'_, VecDeque<ServerEvent>> {
+        // ---
+        self.pending.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn buffer(&self, event: ServerEvent) {
+        // ---
+        let mut pending = self.pending();
+        if pending.len() >= self.max_buffered {
+            tracing::warn!("Audit export buffer full; dropping the oldest event");
+            pending.pop_front();
+        }
+        pending.push_back(event);
+    }
+
+    async fn export(&self) -> Result<ExportSummary> {
+        // ---
+        let _running = self.running.lock().await;
+        let now = Utc::now();
+        let day = now.format("%Y/%m/%d");
+        let time = now.format("%Y%m%dT%H%M%SZ");
+        let mut summary = ExportSummary::default();
+
+        let events: Vec<ServerEvent> = self.pending().drain(..).collect();
+        if !events.is_empty() {
+            let key = format!(
+                "{}audit/{day}/{time}-{}.ndjson",
+                self.prefix,
+                &uuid::Uuid::new_v4().simple().to_string()[..8]
+            );
+            let body = ndjson(&events)?;
+            if let Err(e) = self.s3.put_object(&key, body, CONTENT_TYPE).await {
+                self.restore(events);
+                return Err(e);
+            }
+            summary.audit_events = events.len();
+            summary.objects.push(key);
+        }
+
+        let samples = parse_samples(&self.metrics.render(), now);
+        if !samples.is_empty() {
+            let key = format!("{}metrics/{day}/{time}.ndjson", self.prefix);
+            self.s3
+                .put_object(&key, ndjson(&samples)?, CONTENT_TYPE)
+                .await?;
+            summary.metrics_samples = samples.len();
+            summary.objects.push(key);
+        }
+
+        tracing::info!(
+            "Exported {} audit events and {} metric samples",
+            summary.audit_events,
+            summary.metrics_samples
+        );
+        Ok(summary)
+    }
+
+    /// Puts events whose upload failed back in front of any collected
+    /// since, keeping the newest if that overflows the buffer.
+    fn restore(&self, events: Vec<ServerEvent>) {
+        // ---
+        let mut pending = self.pending();
+        for event in events.into_iter().rev() {
+            if pending.len() >= self.max_buffered {
+                break;
+            }
+            pending.push_front(event);
+        }
+    }
+}
+
+/// The day in an audit file name below the `audit/` prefix, such as
+/// `2026/10/15/20261015T120000Z-1a2b3c4d.ndjson`.
+fn written_on(name: &str) -> Option<NaiveDate> {
+    // ---
+    NaiveDate::parse_from_str(name.get(..10)?, "%Y/%m/%d").ok()
+}
+
+fn ndjson<T: serde::Serialize>(items: &[T]) -> Result<Vec<u8>> {
+    // ---
+    let mut body = Vec::new();
+    for item in items {
+        serde_json::to_writer(&mut body, item)?;
+        body.push(b'\n');
+    }
+    Ok(body)
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+
+    #[test]
+    fn audit_files_are_dated_by_their_path() {
+        // ---
+        assert_eq!(
+            written_on("2026/10/15/20261015T120000Z-1a2b3c4d.ndjson"),
+            NaiveDate::from_ymd_opt(2026, 10, 15)
+        );
+        assert_eq!(written_on("notes.txt"), None);
+        assert_eq!(written_on("2026/1"), None);
+    }
+}
@@ -0,0 +1,8 @@
+// Gateway module - audit log and metrics export to S3-compatible storage
+// Modules are private, only exported symbols are public
+
+mod exporter;
+mod s3;
+mod snapshot;
+
+pub(crate) use exporter::AuditExporter;
@@ -0,0 +1,106 @@
+//! Conversion of the Prometheus text exposition into NDJSON samples.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// One metric sample as exported: a line of the snapshot file.
+#[derive(Debug, Serialize)]
+pub(super) struct Sample {
+    // ---
+    pub taken_at: DateTime<Utc>,
+    pub name: String,
+    pub labels: BTreeMap<String, String>,
+
+    /// `null` for NaN and infinities, which JSON cannot represent.
+    pub value: Option<f64>,
+}
+
+/// Parses every sample in `text`, as rendered by `Metrics::render`.
+/// Comments and lines that do not parse are skipped.
+pub(super) fn parse_samples(text: &str, taken_at: DateTime<Utc>) -> Vec<Sample> {
+    // ---
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| parse_line(line, taken_at))
+        .collect()
+}
+
+/// `name{label="value",...} value [timestamp]`
+fn parse_line(line: &str, taken_at: DateTime<Utc>) -> Option<Sample> {
+    // ---
+    let (name, labels, rest) = match line.find(['{', ' ']) {
+        Some(i) if line.as_bytes()[i] == b'{' => {
+            let (labels, rest) = parse_labels(&line[i + 1..])?;
+            (&line[..i], labels, rest)
+        }
+        Some(i) => (&line[..i], BTreeMap::new(), &line[i..]),
+        None => return None,
+    };
+    let value: f64 = rest.split_whitespace().next()?.parse().ok()?;
+
+    Some(Sample {
+        taken_at,
+        name: name.to_string(),
+        labels,
+        value: value.is_finite().then_some(value),
+    })
+}
+
+/// Parses labels up to the closing `}`, returning them and the remainder
+/// of the line.
+fn parse_labels(mut s: &str) -> Option<(BTreeMap<String, String>, &str)> {
+    // ---
+    let mut labels = BTreeMap::new();
+    loop {
+        s = s.trim_start_matches([' ', ',']);
+        if let Some(rest) = s.strip_prefix('}') {
+            return Some((labels, rest));
+        }
+
+        let (name, rest) = s.split_once("=\"")?;
+        let mut value = String::new();
+        let mut chars = rest.char_indices();
+        let end = loop {
+            match chars.next()? {
+                (i, '"') => break i,
+                (_, '\\') => match chars.next()?.1 {
+                    'n' => value.push('\n'),
+                    c => value.push(c),
+                },
+                (_, c) => value.push(c),
+            }
+        };
+        labels.insert(name.trim().to_string(), value);
+        s = &rest[end + 1..];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+
+    #[test]
+    fn parses_labels_and_values() {
+        // ---
+        let text = "\
+# HELP http_requests_total Requests
+# TYPE http_requests_total counter
+http_requests_total{method=\"GET\",path=\"/a \\\"b\\\"\"} 3
+in_flight_requests 0
+duration_bucket{le=\"+Inf\"} +Inf
+";
+        let samples = parse_samples(text, Utc::now());
+
+        assert_eq!(samples.len(), 3);
+        assert_eq!(samples[0].name, "http_requests_total");
+        assert_eq!(samples[0].labels["method"], "GET");
+        assert_eq!(samples[0].labels["path"], "/a \"b\"");
+        assert_eq!(samples[0].value, Some(3.0));
+        assert!(samples[1].labels.is_empty());
+        assert_eq!(samples[2].labels["le"], "+Inf");
+        assert_eq!(samples[2].value, None);
+    }
+}
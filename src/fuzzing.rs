@@ -0,0 +1,47 @@
+//! Entry points for the cargo-fuzz targets in `fuzz/`.
+//!
+//! Only built with the `fuzzing` feature. Each function feeds untrusted
+//! bytes through one input path the same way its handler does. None of them
+//! may panic, whatever the input; the fuzzer reports any panic as a crash.
+
+use crate::application::{decode_credential_id, Movie};
+use crate::handlers::{AuthFinishRequest, RegistrationFinishRequest};
+use base64::Engine;
+
+/// The body of `POST /webauthn/register/finish`.
+pub fn registration_finish_request(data: &[u8]) {
+    // ---
+    let _ = serde_json::from_slice::<RegistrationFinishRequest>(data);
+}
+
+/// The body of `POST /webauthn/auth/finish`.
+pub fn auth_finish_request(data: &[u8]) {
+    // ---
+    let _ = serde_json::from_slice::<AuthFinishRequest>(data);
+}
+
+/// The body of `POST /movies/add` and `PUT /movies/update/{id}`, including
+/// validation and ID derivation.
+pub fn movie(data: &[u8]) {
+    // ---
+    if let Ok(mut movie) = serde_json::from_slice::<Movie>(data) {
+        let _ = movie.sanitize();
+    }
+}
+
+/// The `{id}` of `DELETE /webauthn/credentials/{id}`.
+///
+/// Also checks that a decoded ID encodes back to the same text, so the IDs
+/// `list_credentials` returns and `delete_credential` accepts agree.
+pub fn credential_id(data: &[u8]) {
+    // ---
+    let Ok(encoded) = std::str::from_utf8(data) else {
+        return;
+    };
+    if let Ok(id) = decode_credential_id(encoded) {
+        assert_eq!(
+            base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(id),
+            encoded
+        );
+    }
+}
@@ -0,0 +1,104 @@
+//! Messages of `proto/quickstart.proto`, package `quickstart.v1`.
+//!
+//! Written by hand rather than generated, so building needs no `protoc`.
+//! Keep field tags in sync with the `.proto` file.
+
+// ---
+// Movies
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct Movie {
+    #[prost(string, tag = "1")]
+    pub title: String,
+    #[prost(uint32, tag = "2")]
+    pub year: u32,
+    #[prost(float, tag = "3")]
+    pub stars: f32,
+    #[prost(string, repeated, tag = "4")]
+    pub genres: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct StoredMovie {
+    #[prost(string, tag = "1")]
+    pub id: String,
+    #[prost(message, optional, tag = "2")]
+    pub movie: Option<Movie>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct GetMovieRequest {
+    #[prost(string, tag = "1")]
+    pub id: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct ListMoviesRequest {
+    #[prost(string, optional, tag = "1")]
+    pub genre: Option<String>,
+    #[prost(uint32, optional, tag = "2")]
+    pub page: Option<u32>,
+    #[prost(uint32, optional, tag = "3")]
+    pub per_page: Option<u32>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct ListMoviesResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub movies: Vec<StoredMovie>,
+    #[prost(uint32, tag = "2")]
+    pub page: u32,
+    #[prost(uint32, tag = "3")]
+    pub per_page: u32,
+    #[prost(uint64, tag = "4")]
+    pub total: u64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct AddMovieRequest {
+    #[prost(message, optional, tag = "1")]
+    pub movie: Option<Movie>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct UpdateMovieRequest {
+    #[prost(string, tag = "1")]
+    pub id: String,
+    #[prost(message, optional, tag = "2")]
+    pub movie: Option<Movie>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct UpdateMovieResponse {}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct DeleteMovieRequest {
+    #[prost(string, tag = "1")]
+    pub id: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct DeleteMovieResponse {}
+
+// ---
+// Sessions
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct IntrospectRequest {
+    #[prost(string, tag = "1")]
+    pub token: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct IntrospectResponse {
+    #[prost(bool, tag = "1")]
+    pub active: bool,
+    #[prost(string, tag = "2")]
+    pub user_id: String,
+    #[prost(string, tag = "3")]
+    pub username: String,
+    #[prost(int64, tag = "4")]
+    pub expires_at: i64,
+    #[prost(string, tag = "5")]
+    pub role: String,
+}
@@ -0,0 +1,7 @@
+// Gateway module - gRPC server for movies and session introspection
+// Modules are private, only exported symbols are public
+
+mod messages;
+mod server;
+
+pub(crate) use server::grpc_router;
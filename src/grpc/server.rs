@@ -0,0 +1,288 @@
+//! gRPC services `quickstart.v1.Movies` and `quickstart.v1.Sessions`.
+//!
+//! Each RPC is an axum route on its own path, decoded and encoded by tonic,
+//! so the gRPC server shares its HTTP/2 stack with the REST API while
+//! listening on a port of its own. The RPCs call the same application
+//! services as the REST handlers.
+
+use super::messages::{
+    AddMovieRequest, DeleteMovieRequest, DeleteMovieResponse, GetMovieRequest, IntrospectRequest,
+    IntrospectResponse, ListMoviesRequest, ListMoviesResponse, Movie as MovieMessage, StoredMovie,
+    UpdateMovieRequest, UpdateMovieResponse,
+};
+use crate::app_state::AppState;
+use crate::application::{AuthService, Movie, MovieError, MovieService, USER_ROLE};
+use axum::{
+    extract::{Request, State},
+    http::StatusCode,
+    response::IntoResponse,
+    routing::{post, MethodRouter},
+    Router,
+};
+use std::future::Future;
+use tonic::server::Grpc;
+use tonic::Status;
+use tonic_prost::ProstCodec;
+
+/// Router serving every RPC with `state`. Unknown methods are answered
+/// with `UNIMPLEMENTED`.
+pub(crate) fn grpc_router(state: AppState) -> Router {
+    // ---
+    Router::new()
+        .route("/quickstart.v1.Movies/GetMovie", rpc(get_movie))
+        .route("/quickstart.v1.Movies/ListMovies", rpc(list_movies))
+        .route("/quickstart.v1.Movies/AddMovie", rpc(add_movie))
+        .route("/quickstart.v1.Movies/UpdateMovie", rpc(update_movie))
+        .route("/quickstart.v1.Movies/DeleteMovie", rpc(delete_movie))
+        .route("/quickstart.v1.Sessions/Introspect", rpc(introspect))
+        .fallback(|| async {
+            Status::unimplemented("Unknown method").into_http::<axum::body::Body>()
+        })
+        .with_state(state)
+}
+
+/// A route for the unary RPC `method`.
+fn rpc<Req, Res, F, Fut>(method: F) -> MethodRouter<AppState>
+where
+    Req: prost::Message + Default + Send + 'static,
+    Res: prost::Message + Send + 'static,
+    F: Fn(AppState, tonic::Request<Req>) -> Fut + Clone + Send + Sync + 'static,
+    Fut: Future<Output = Result<Res, Status>> + Send + 'static,
+{
+    // ---
+    post(
+        move |State(state): State<AppState>, req: Request| async move {
+            let service = tower::service_fn(move |request| {
+                let response = method(state.clone(), request);
+                async move { response.await.map(tonic::Response::new) }
+            });
+            let mut grpc = Grpc::new(ProstCodec::<Res, Req>::default());
+            grpc.unary(service, req).await.into_response()
+        },
+    )
+}
+
+// ---
+// Movies
+
+async fn get_movie(
+    state: AppState,
+    request: tonic::Request<GetMovieRequest>,
+) -> Result<StoredMovie, Status> {
+    // ---
+    let id = request.into_inner().id;
+    let movie = MovieService::new(state)
+        .get(&id)
+        .await
+        .map_err(movie_status)?;
+    Ok(StoredMovie {
+        id,
+        movie: Some(movie.into()),
+    })
+}
+
+async fn list_movies(
+    state: AppState,
+    request: tonic::Request<ListMoviesRequest>,
+) -> Result<ListMoviesResponse, Status> {
+    // ---
+    let request = request.into_inner();
+    let page = MovieService::new(state)
+        .list(request.genre.as_deref(), request.page, request.per_page)
+        .await
+        .map_err(movie_status)?;
+    Ok(ListMoviesResponse {
+        movies: page.movies.into_iter().map(Into::into).collect(),
+        page: page.page,
+        per_page: page.per_page,
+        total: page.total,
+    })
+}
+
+async fn add_movie(
+    state: AppState,
+    request: tonic::Request<AddMovieRequest>,
+) -> Result<StoredMovie, Status> {
+    // ---
+    let movie = movie_field(request.into_inner().movie)?;
+    let stored = MovieService::new(state)
+        .add(movie)
+        .await
+        .map_err(movie_status)?;
+    Ok(stored.into())
+}
+
+async fn update_movie(
+    state: AppState,
+    request: tonic::Request<UpdateMovieRequest>,
+) -> Result<UpdateMovieResponse, Status> {
+    // ---
+    let request = request.into_inner();
+    let movie = movie_field(request.movie)?;
+    MovieService::new(state)
+        .update(&request.id, movie)
+        .await
+        .map_err(movie_status)?;
+    Ok(UpdateMovieResponse {})
+}
+
+async fn delete_movie(
+    state: AppState,
+    request: tonic::Request<DeleteMovieRequest>,
+) -> Result<DeleteMovieResponse, Status> {
+    // ---
+    MovieService::new(state)
+        .delete(&request.into_inner().id)
+        .await
+        .map_err(movie_status)?;
+    Ok(DeleteMovieResponse {})
+}
+
+/// The movie of a request; it is required.
+fn movie_field(movie: Option<MovieMessage>) -> Result<Movie, Status> {
+    // ---
+    let movie = movie.ok_or_else(|| Status::invalid_argument("movie is required"))?;
+    Ok(Movie {
+        title: movie.title,
+        // Out-of-range years fail validation like any other invalid year
+        year: u16::try_from(movie.year).unwrap_or(0),
+        stars: movie.stars,
+        genres: movie.genres,
+    })
+}
+
+impl From<Movie> for MovieMessage {
+    fn from(movie: Movie) -> Self {
+        // ---
+        Self {
+            title: movie.title,
+            year: movie.year.into(),
+            stars: movie.stars,
+            genres: movie.genres,
+        }
+    }
+}
+
+impl From<crate::application::StoredMovie> for StoredMovie {
+    fn from(stored: crate::application::StoredMovie) -> Self {
+        // ---
+        Self {
+            id: stored.id,
+            movie: Some(stored.movie.into()),
+        }
+    }
+}
+
+/// The gRPC status for a movie error, with the messages of the REST API.
+fn movie_status(err: MovieError) -> Status {
+    // ---
+    match err {
+        MovieError::InvalidGenre => {
+            Status::invalid_argument("Genre must be 1 to 32 letters, digits, or hyphens")
+        }
+        MovieError::Status(StatusCode::BAD_REQUEST) => Status::invalid_argument(
+            "Movie needs a non-empty title, a year from 1880 to five years from now, \
+             0 to 5 stars, and at most 10 genres of up to 32 letters, digits, or hyphens",
+        ),
+        MovieError::Status(StatusCode::NOT_FOUND) => Status::not_found("Movie not found"),
+        MovieError::Status(StatusCode::CONFLICT) => {
+            Status::already_exists("A movie with this title and year already exists")
+        }
+        MovieError::Status(StatusCode::SERVICE_UNAVAILABLE) => {
+            Status::unavailable("Service temporarily unavailable")
+        }
+        MovieError::Status(_) => Status::internal("Internal server error"),
+    }
+}
+
+// ---
+// Sessions
+
+/// Checks a session token for another service. Requires a service token
+/// (or the admin token) as `authorization: Bearer` metadata.
+async fn introspect(
+    state: AppState,
+    request: tonic::Request<IntrospectRequest>,
+) -> Result<IntrospectResponse, Status> {
+    // ---
+    let service = AuthService::new(state);
+    let bearer = request
+        .metadata()
+        .get("authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .unwrap_or_default();
+    service
+        .authorize_service(bearer)
+        .map_err(|err| match err.status {
+            StatusCode::FORBIDDEN => Status::permission_denied(err.message),
+            _ => Status::unauthenticated(err.message),
+        })?;
+
+    let token = request.into_inner().token;
+    let session = service
+        .introspect(&token)
+        .await
+        .map_err(|_| Status::unavailable("Cannot check sessions"))?;
+
+    Ok(match session {
+        Some(session) => IntrospectResponse {
+            active: true,
+            user_id: session.user_id.to_string(),
+            username: session.username,
+            expires_at: session.expires_at.timestamp(),
+            role: USER_ROLE.to_string(),
+        },
+        None => IntrospectResponse::default(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+    use tonic::Code;
+
+    #[test]
+    fn movie_errors_map_to_grpc_codes() {
+        // ---
+        let code = |err| movie_status(err).code();
+        assert_eq!(code(MovieError::InvalidGenre), Code::InvalidArgument);
+        assert_eq!(
+            code(MovieError::Status(StatusCode::BAD_REQUEST)),
+            Code::InvalidArgument
+        );
+        assert_eq!(
+            code(MovieError::Status(StatusCode::NOT_FOUND)),
+            Code::NotFound
+        );
+        assert_eq!(
+            code(MovieError::Status(StatusCode::CONFLICT)),
+            Code::AlreadyExists
+        );
+        assert_eq!(
+            code(MovieError::Status(StatusCode::SERVICE_UNAVAILABLE)),
+            Code::Unavailable
+        );
+        assert_eq!(
+            code(MovieError::Status(StatusCode::INTERNAL_SERVER_ERROR)),
+            Code::Internal
+        );
+    }
+
+    #[test]
+    fn a_movie_is_required_and_years_are_range_checked() {
+        // ---
+        let err = movie_field(None).unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+
+        let movie = movie_field(Some(MovieMessage {
+            title: "Heat".to_string(),
+            year: 70_000,
+            stars: 4.5,
+            genres: Vec::new(),
+        }))
+        .unwrap();
+        assert_eq!(movie.year, 0);
+    }
+}
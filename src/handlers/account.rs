@@ -0,0 +1,128 @@
+//! The caller's own account.
+//!
+//! `GET /account/usage` reports how much of their request quotas a user
+//! (by session) or service (by service or admin token) has consumed. Usage
+//! is tracked by [`QuotaService`] while quotas are configured.
+
+use super::ApiResponse;
+use crate::app_state::AppState;
+use crate::application::{QuotaPeriod, QuotaService, QuotaWindow, ServiceError};
+use crate::session::session_cookie;
+use crate::tenant::Tenant;
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use serde::Serialize;
+
+// ============================================================================
+// Response Types
+// ============================================================================
+
+/// Consumption of the caller's daily and monthly quotas.
+#[derive(Debug, Serialize)]
+pub struct UsageResponse {
+    // ---
+    /// `user` or `key`.
+    pub caller: &'static str,
+
+    pub daily: WindowUsage,
+
+    pub monthly: WindowUsage,
+}
+
+/// Consumption in one quota window.
+#[derive(Debug, Serialize)]
+pub struct WindowUsage {
+    // ---
+    /// Requests counted in the window so far.
+    pub used: u64,
+
+    /// `null` if unlimited.
+    pub limit: Option<u64>,
+
+    /// `null` if unlimited.
+    pub remaining: Option<u64>,
+
+    /// RFC 3339 timestamp at which the count starts again from zero.
+    pub resets_at: String,
+}
+
+impl From<&QuotaWindow> for WindowUsage {
+    fn from(window: &QuotaWindow) -> Self {
+        // ---
+        Self {
+            used: window.used,
+            limit: window.limit,
+            remaining: window.remaining(),
+            resets_at: window.resets_at.to_rfc3339(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    // ---
+    pub error: String,
+}
+
+// ============================================================================
+// Usage Handler
+// ============================================================================
+
+/// GET /account/usage
+///
+/// Reports the caller's quota consumption for the current UTC day and
+/// month. The request itself is counted.
+///
+/// # Request Headers
+/// ```text
+/// Authorization: Bearer <session_token | service_token>
+/// ```
+/// A session cookie is accepted in place of the header.
+///
+/// # Errors
+/// - 401 Unauthorized if the token is missing, or is neither a valid
+///   session nor a service token
+/// - 500 Internal Server Error if Redis fails
+pub async fn account_usage(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    tenant: Tenant,
+) -> Result<ApiResponse<UsageResponse>, (StatusCode, Json<ErrorResponse>)> {
+    // ---
+    let reject = |err: ServiceError| (err.status, Json(ErrorResponse { error: err.message }));
+
+    let token = headers
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .or_else(|| session_cookie(&headers));
+    let service = QuotaService::new(state);
+    let caller = match token {
+        Some(token) => service.caller(token, &tenant).await,
+        None => None,
+    }
+    .ok_or_else(|| {
+        reject(ServiceError::new(
+            StatusCode::UNAUTHORIZED,
+            "Authentication required",
+        ))
+    })?;
+
+    let windows = service.usage(&caller).await.map_err(reject)?;
+    let window = |period| {
+        windows
+            .iter()
+            .find(|window| window.period == period)
+            .map(WindowUsage::from)
+            .expect("usage covers every period")
+    };
+
+    Ok(ApiResponse::new(UsageResponse {
+        caller: caller.kind(),
+        daily: window(QuotaPeriod::Daily),
+        monthly: window(QuotaPeriod::Monthly),
+    }))
+}
@@ -0,0 +1,519 @@
+//! Operator-only administration handlers.
+//!
+//! Admin endpoints are authenticated with a static bearer token
+//! (`AXUM_ADMIN_TOKEN`) rather than a user session. When no token is
+//! configured the endpoints are disabled.
+//!
+//! 1. `purge_deleted` - Permanently remove soft-deleted users and credentials
+//! 2. `reencrypt_credentials` - Rewrite stored passkeys under the current key
+//! 3. `export_audit_log` - Upload pending audit events and metrics to S3 now
+//! 4. `retention_schedule` - Show the retention rules and the next purge
+//!
+//! Webhook endpoint management lives in `admin_webhooks`.
+
+use super::{ApiResponse, ResponseMeta};
+use crate::app_state::AppState;
+use crate::client_ip::ClientIp;
+use crate::events::ServerEvent;
+use axum::{
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+// ============================================================================
+// Request/Response Types
+// ============================================================================
+
+/// Query parameters for a purge.
+#[derive(Debug, Default, Deserialize)]
+pub struct PurgeQuery {
+    // ---
+    /// Purge records soft-deleted more than this many days ago.
+    /// Defaults to `AXUM_SOFT_DELETE_RETENTION_DAYS`.
+    pub older_than_days: Option<u32>,
+}
+
+// ---
+
+/// Result of a purge.
+#[derive(Debug, Serialize)]
+pub struct PurgeResponse {
+    // ---
+    /// Records soft-deleted before this instant were removed.
+    pub cutoff: String,
+    pub users_purged: u64,
+    pub credentials_purged: u64,
+}
+
+// ---
+
+/// Result of a re-encryption pass.
+#[derive(Debug, Serialize)]
+pub struct ReencryptResponse {
+    // ---
+    pub reencrypted: u64,
+    pub unchanged: u64,
+}
+
+// ---
+
+/// Result of an on-demand export.
+#[derive(Debug, Serialize)]
+pub struct ExportResponse {
+    // ---
+    pub audit_events: usize,
+    pub metrics_samples: usize,
+
+    /// Keys of the objects written to the bucket.
+    pub objects: Vec<String>,
+}
+
+// ---
+
+/// The retention job's schedule and rules.
+#[derive(Debug, Serialize)]
+pub struct RetentionResponse {
+    // ---
+    /// `false` if `AXUM_RETENTION_INTERVAL_SEC=0`; the other fields are
+    /// then empty.
+    pub enabled: bool,
+    pub dry_run: bool,
+    pub interval_secs: Option<u64>,
+
+    /// When the next purge starts.
+    pub next_run: Option<String>,
+    pub rules: Vec<RetentionRuleInfo>,
+    pub last_run: Option<RetentionRunInfo>,
+}
+
+// ---
+
+/// One retention rule as the next purge will apply it.
+#[derive(Debug, Serialize)]
+pub struct RetentionRuleInfo {
+    // ---
+    pub rule: &'static str,
+    pub max_age_days: u64,
+
+    /// Data older than this will be removed by the next purge.
+    pub cutoff: String,
+
+    /// `false` if the rule has nothing to act on, such as audit logs
+    /// without an export bucket.
+    pub enabled: bool,
+}
+
+// ---
+
+/// What the last purge did.
+#[derive(Debug, Serialize)]
+pub struct RetentionRunInfo {
+    // ---
+    pub started_at: String,
+    pub dry_run: bool,
+    pub rules: Vec<RetentionOutcomeInfo>,
+}
+
+// ---
+
+/// What one rule did in the last purge.
+#[derive(Debug, Serialize)]
+pub struct RetentionOutcomeInfo {
+    // ---
+    pub rule: &'static str,
+    pub cutoff: String,
+
+    /// Records removed, or found in a dry run. Absent if the rule failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub purged: Option<u64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+// ---
+
+/// Error response for admin operations.
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    // ---
+    pub error: String,
+}
+
+// ============================================================================
+// Helper Functions
+// ============================================================================
+
+/// Verifies the `Authorization: Bearer <token>` header against the configured admin token.
+///
+/// # Errors
+///
+/// - 403 Forbidden if the admin API is disabled (no token configured)
+/// - 401 Unauthorized if the header is missing or the token does not match
+pub(super) fn require_admin(
+    headers: &HeaderMap,
+    state: &AppState,
+) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
+    // ---
+    let error = |status, message: &str| {
+        (
+            status,
+            Json(ErrorResponse {
+                error: message.to_string(),
+            }),
+        )
+    };
+
+    let admin = state.admin();
+    let Some(expected) = admin.api_token.as_deref() else {
+        tracing::warn!("Admin request rejected: AXUM_ADMIN_TOKEN is not set");
+        return Err(error(StatusCode::FORBIDDEN, "Admin API is disabled"));
+    };
+
+    if !constant_time_eq(bearer_token(headers).as_bytes(), expected.as_bytes()) {
+        tracing::warn!("Admin request rejected: invalid token");
+        return Err(error(StatusCode::UNAUTHORIZED, "Invalid admin token"));
+    }
+
+    Ok(())
+}
+
+/// Whether the request carries the admin token. Always `false` when the
+/// admin API is disabled. Unlike [`require_admin`], does not log.
+pub(super) fn is_admin(headers: &HeaderMap, state: &AppState) -> bool {
+    // ---
+    state.admin().api_token.as_deref().is_some_and(|expected| {
+        constant_time_eq(bearer_token(headers).as_bytes(), expected.as_bytes())
+    })
+}
+
+/// The `Bearer` token from the `Authorization` header, or `""`.
+fn bearer_token(headers: &HeaderMap) -> &str {
+    // ---
+    headers
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .unwrap_or_default()
+}
+
+/// Compares two byte strings without short-circuiting on the first mismatch.
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    // ---
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+// ============================================================================
+// Purge Handler
+// ============================================================================
+
+/// POST /admin/purge
+///
+/// Permanently deletes users and credentials that were soft-deleted longer
+/// ago than the retention window. Until then a soft-deleted record can be
+/// restored, which gives users a grace period after asking for deletion.
+///
+/// # Request Headers
+/// ```text
+/// Authorization: Bearer <AXUM_ADMIN_TOKEN>
+/// ```
+///
+/// # Query Parameters
+/// - `older_than_days` - Override the configured retention window
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The admin API is disabled (403 Forbidden)
+/// - The admin token is missing or wrong (401 Unauthorized)
+/// - Database deletion fails (500 Internal Server Error)
+pub async fn purge_deleted(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    client: Option<ClientIp>,
+    Query(query): Query<PurgeQuery>,
+) -> Result<ApiResponse<PurgeResponse>, (StatusCode, Json<ErrorResponse>)> {
+    // ---
+    let start = Instant::now();
+
+    require_admin(&headers, &state)?;
+
+    let retention = match query.older_than_days {
+        Some(days) => chrono::Duration::days(days.into()),
+        None => chrono::Duration::from_std(state.admin().soft_delete_retention)
+            .unwrap_or(chrono::Duration::MAX),
+    };
+    let cutoff: DateTime<Utc> = Utc::now()
+        .checked_sub_signed(retention)
+        .unwrap_or(DateTime::<Utc>::MIN_UTC);
+
+    let summary = state
+        .repository()
+        .purge_deleted(cutoff, false)
+        .await
+        .map_err(|e| {
+            // ---
+            tracing::error!("Failed to purge soft-deleted records: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "Failed to purge deleted records".to_string(),
+                }),
+            )
+        })?;
+
+    tracing::info!(
+        "Purged {} users and {} credentials deleted before {}",
+        summary.users,
+        summary.credentials,
+        cutoff
+    );
+
+    state.events().publish(
+        ServerEvent::audit(
+            "admin.purge",
+            serde_json::json!({
+                "cutoff": cutoff.to_rfc3339(),
+                "users_purged": summary.users,
+                "credentials_purged": summary.credentials,
+            }),
+        )
+        .with_client_ip(client.map(|c| c.0)),
+    );
+    Ok(ApiResponse::new(PurgeResponse {
+        cutoff: cutoff.to_rfc3339(),
+        users_purged: summary.users,
+        credentials_purged: summary.credentials,
+    })
+    .with_meta(ResponseMeta::new(&headers, start)))
+}
+
+// ============================================================================
+// Re-encryption Handler
+// ============================================================================
+
+/// POST /admin/credentials/reencrypt
+///
+/// Rewrites every stored passkey, including soft-deleted ones, under the
+/// current data encryption key. Run after rotating `AXUM_DATA_ENCRYPTION_KEY`
+/// and before removing the old key from `AXUM_DATA_ENCRYPTION_PREVIOUS_KEYS`.
+/// Legacy plaintext rows are encrypted too. Safe to repeat.
+///
+/// # Request Headers
+/// ```text
+/// Authorization: Bearer <AXUM_ADMIN_TOKEN>
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The admin API is disabled (403 Forbidden)
+/// - The admin token is missing or wrong (401 Unauthorized)
+/// - Encryption at rest is not configured (409 Conflict)
+/// - A credential cannot be decrypted or saved (500 Internal Server Error)
+pub async fn reencrypt_credentials(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    client: Option<ClientIp>,
+) -> Result<ApiResponse<ReencryptResponse>, (StatusCode, Json<ErrorResponse>)> {
+    // ---
+    let start = Instant::now();
+
+    require_admin(&headers, &state)?;
+
+    let summary = state
+        .repository()
+        .reencrypt_credentials()
+        .await
+        .map_err(|e| {
+            // ---
+            tracing::error!("Failed to re-encrypt credentials: {:#}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "Failed to re-encrypt credentials".to_string(),
+                }),
+            )
+        })?
+        .ok_or_else(|| {
+            (
+                StatusCode::CONFLICT,
+                Json(ErrorResponse {
+                    error: "Encryption at rest is not configured".to_string(),
+                }),
+            )
+        })?;
+
+    state.events().publish(
+        ServerEvent::audit(
+            "admin.reencrypt",
+            serde_json::json!({
+                "reencrypted": summary.reencrypted,
+                "unchanged": summary.unchanged,
+            }),
+        )
+        .with_client_ip(client.map(|c| c.0)),
+    );
+    Ok(ApiResponse::new(ReencryptResponse {
+        reencrypted: summary.reencrypted,
+        unchanged: summary.unchanged,
+    })
+    .with_meta(ResponseMeta::new(&headers, start)))
+}
+
+/// POST /admin/export
+///
+/// Uploads the audit events collected since the last export, and a metrics
+/// snapshot, to the export bucket without waiting for the next interval.
+/// Events recorded by this request are part of the following export.
+///
+/// # Request Headers
+/// ```text
+/// Authorization: Bearer <AXUM_ADMIN_TOKEN>
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The admin API is disabled (403 Forbidden)
+/// - The admin token is missing or wrong (401 Unauthorized)
+/// - Export is not configured (409 Conflict)
+/// - An upload fails (500 Internal Server Error); audit events are kept
+///   for the next attempt
+pub async fn export_audit_log(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    client: Option<ClientIp>,
+) -> Result<ApiResponse<ExportResponse>, (StatusCode, Json<ErrorResponse>)> {
+    // ---
+    let start = Instant::now();
+
+    require_admin(&headers, &state)?;
+
+    let summary = state
+        .export()
+        .export_now()
+        .await
+        .map_err(|e| {
+            // ---
+            tracing::error!("Failed to export audit log: {:#}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "Failed to export audit log".to_string(),
+                }),
+            )
+        })?
+        .ok_or_else(|| {
+            (
+                StatusCode::CONFLICT,
+                Json(ErrorResponse {
+                    error: "Export is not configured".to_string(),
+                }),
+            )
+        })?;
+
+    state.events().publish(
+        ServerEvent::audit(
+            "admin.export",
+            serde_json::json!({
+                "audit_events": summary.audit_events,
+                "metrics_samples": summary.metrics_samples,
+            }),
+        )
+        .with_client_ip(client.map(|c| c.0)),
+    );
+    Ok(ApiResponse::new(ExportResponse {
+        audit_events: summary.audit_events,
+        metrics_samples: summary.metrics_samples,
+        objects: summary.objects,
+    })
+    .with_meta(ResponseMeta::new(&headers, start)))
+}
+
+/// GET /admin/retention
+///
+/// Shows the retention job's rules as the next scheduled purge will apply
+/// them, when that purge starts, and what the last one removed.
+///
+/// # Request Headers
+/// ```text
+/// Authorization: Bearer <AXUM_ADMIN_TOKEN>
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The admin API is disabled (403 Forbidden)
+/// - The admin token is missing or wrong (401 Unauthorized)
+pub async fn retention_schedule(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<ApiResponse<RetentionResponse>, (StatusCode, Json<ErrorResponse>)> {
+    // ---
+    let start = Instant::now();
+
+    require_admin(&headers, &state)?;
+
+    let response = match state.retention().status() {
+        Some(status) => RetentionResponse {
+            enabled: true,
+            dry_run: status.dry_run,
+            interval_secs: Some(status.interval.as_secs()),
+            next_run: status.next_run.map(|at| at.to_rfc3339()),
+            rules: status
+                .rules
+                .into_iter()
+                .map(|plan| RetentionRuleInfo {
+                    rule: plan.rule.as_str(),
+                    max_age_days: plan.max_age.as_secs() / 86_400,
+                    cutoff: plan.cutoff.to_rfc3339(),
+                    enabled: plan.enabled,
+                })
+                .collect(),
+            last_run: status.last_run.map(|run| RetentionRunInfo {
+                started_at: run.started_at.to_rfc3339(),
+                dry_run: run.dry_run,
+                rules: run
+                    .outcomes
+                    .into_iter()
+                    .map(|outcome| RetentionOutcomeInfo {
+                        rule: outcome.rule.as_str(),
+                        cutoff: outcome.cutoff.to_rfc3339(),
+                        purged: outcome.purged,
+                        error: outcome.error,
+                    })
+                    .collect(),
+            }),
+        },
+        None => RetentionResponse {
+            enabled: false,
+            dry_run: false,
+            interval_secs: None,
+            next_run: None,
+            rules: Vec::new(),
+            last_run: None,
+        },
+    };
+
+    Ok(ApiResponse::new(response).with_meta(ResponseMeta::new(&headers, start)))
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+
+    #[test]
+    fn constant_time_eq_matches_only_identical() {
+        // ---
+        assert!(constant_time_eq(b"token", b"token"));
+        assert!(!constant_time_eq(b"token", b"tokem"));
+        assert!(!constant_time_eq(b"token", b"token2"));
+        assert!(!constant_time_eq(b"", b"token"));
+    }
+}
@@ -0,0 +1,224 @@
+//! Per-user credential limits.
+//!
+//! Each user may hold at most `AXUM_MAX_CREDENTIALS_PER_USER` active
+//! passkeys; `register_finish` rejects further registrations with 409.
+//! Admins can raise or lower the limit for one user. A limit of 0 means
+//! unlimited. Limits are kept by [`CredentialService`].
+//!
+//! Usernames are looked up in the tenant of the request's host.
+//!
+//! 1. `get_credential_limit`    - GET    /admin/users/{username}/credential-limit
+//! 2. `set_credential_limit`    - PUT    /admin/users/{username}/credential-limit
+//! 3. `delete_credential_limit` - DELETE /admin/users/{username}/credential-limit
+
+use super::admin::{require_admin, ErrorResponse};
+use super::ApiResponse;
+use crate::app_state::AppState;
+use crate::application::{CredentialService, ServiceError};
+use crate::client_ip::ClientIp;
+use crate::domain::User;
+use crate::events::ServerEvent;
+use crate::redact;
+use crate::tenant::Tenant;
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+type HandlerError = (StatusCode, Json<ErrorResponse>);
+
+// ============================================================================
+// Request/Response Types
+// ============================================================================
+
+/// Body for setting a per-user limit.
+#[derive(Debug, Deserialize)]
+pub struct CredentialLimitRequest {
+    // ---
+    /// Most active passkeys the user may hold; 0 for unlimited.
+    pub max_credentials: u32,
+}
+
+// ---
+
+/// A user's effective credential limit.
+#[derive(Debug, Serialize)]
+pub struct CredentialLimitInfo {
+    // ---
+    pub username: String,
+
+    /// 0 means unlimited.
+    pub max_credentials: u32,
+
+    /// Whether `max_credentials` is a per-user override rather than the default.
+    pub overridden: bool,
+
+    /// Active passkeys the user currently holds.
+    pub credentials: usize,
+}
+
+// ============================================================================
+// Helper Functions
+// ============================================================================
+
+fn error(status: StatusCode, message: &str) -> HandlerError {
+    // ---
+    (
+        status,
+        Json(ErrorResponse {
+            error: message.to_string(),
+        }),
+    )
+}
+
+fn reject(err: ServiceError) -> HandlerError {
+    // ---
+    error(err.status, &err.message)
+}
+
+fn storage_error(e: impl std::fmt::Display) -> HandlerError {
+    // ---
+    tracing::error!("Credential limit storage error: {e}");
+    error(StatusCode::INTERNAL_SERVER_ERROR, "Internal server error")
+}
+
+async fn find_user(
+    state: &AppState,
+    tenant: &Tenant,
+    username: &str,
+) -> Result<User, HandlerError> {
+    // ---
+    state
+        .repository()
+        .get_user_by_username_in(tenant.id(), username)
+        .await
+        .map_err(storage_error)?
+        .ok_or_else(|| error(StatusCode::NOT_FOUND, "User not found"))
+}
+
+async fn limit_info(state: &AppState, user: User) -> Result<CredentialLimitInfo, HandlerError> {
+    // ---
+    let (max_credentials, overridden) = CredentialService::new(state.clone())
+        .limit(user.id)
+        .await
+        .map_err(storage_error)?;
+    let credentials = state
+        .repository()
+        .get_credentials_by_user(user.id)
+        .await
+        .map_err(storage_error)?
+        .len();
+
+    Ok(CredentialLimitInfo {
+        username: user.username,
+        max_credentials,
+        overridden,
+        credentials,
+    })
+}
+
+// ============================================================================
+// Handlers
+// ============================================================================
+
+/// GET /admin/users/{username}/credential-limit
+///
+/// # Errors
+/// - 404 Not Found if the user does not exist
+pub async fn get_credential_limit(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    headers: HeaderMap,
+    Path(username): Path<String>,
+) -> Result<ApiResponse<CredentialLimitInfo>, HandlerError> {
+    // ---
+    require_admin(&headers, &state)?;
+    let user = find_user(&state, &tenant, &username).await?;
+
+    Ok(ApiResponse::new(limit_info(&state, user).await?))
+}
+
+/// PUT /admin/users/{username}/credential-limit
+///
+/// Overrides the default limit for one user. Existing credentials above a
+/// lowered limit are kept; only new registrations are refused.
+///
+/// # Errors
+/// - 404 Not Found if the user does not exist
+pub async fn set_credential_limit(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    headers: HeaderMap,
+    client: Option<ClientIp>,
+    Path(username): Path<String>,
+    Json(req): Json<CredentialLimitRequest>,
+) -> Result<ApiResponse<CredentialLimitInfo>, HandlerError> {
+    // ---
+    require_admin(&headers, &state)?;
+    let user = find_user(&state, &tenant, &username).await?;
+    CredentialService::new(state.clone())
+        .set_limit(user.id, Some(req.max_credentials))
+        .await
+        .map_err(reject)?;
+
+    tracing::info!(
+        "Set credential limit for {} to {}",
+        redact::username(&user.username),
+        req.max_credentials
+    );
+    state.events().publish(
+        ServerEvent::audit(
+            "admin.credential_limit",
+            serde_json::json!({
+                "user_id": user.id,
+                "username": user.username,
+                "max_credentials": req.max_credentials,
+            }),
+        )
+        .with_client_ip(client.map(|c| c.0)),
+    );
+
+    Ok(ApiResponse::new(limit_info(&state, user).await?))
+}
+
+/// DELETE /admin/users/{username}/credential-limit
+///
+/// Removes a per-user override, restoring the default limit.
+///
+/// # Errors
+/// - 404 Not Found if the user does not exist
+pub async fn delete_credential_limit(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    headers: HeaderMap,
+    client: Option<ClientIp>,
+    Path(username): Path<String>,
+) -> Result<ApiResponse<CredentialLimitInfo>, HandlerError> {
+    // ---
+    require_admin(&headers, &state)?;
+    let user = find_user(&state, &tenant, &username).await?;
+    CredentialService::new(state.clone())
+        .set_limit(user.id, None)
+        .await
+        .map_err(reject)?;
+
+    tracing::info!(
+        "Reset credential limit for {}",
+        redact::username(&user.username)
+    );
+    state.events().publish(
+        ServerEvent::audit(
+            "admin.credential_limit",
+            serde_json::json!({
+                "user_id": user.id,
+                "username": user.username,
+                "max_credentials": null,
+            }),
+        )
+        .with_client_ip(client.map(|c| c.0)),
+    );
+
+    Ok(ApiResponse::new(limit_info(&state, user).await?))
+}
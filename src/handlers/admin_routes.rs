@@ -0,0 +1,41 @@
+//! Route introspection for operators.
+//!
+//! `GET /admin/routes` lists the registered routes with their methods,
+//! credentials, and middleware, from the [route registry](crate::route_table).
+
+use super::admin::{require_admin, ErrorResponse};
+use super::{ApiResponse, ResponseMeta};
+use crate::app_state::AppState;
+use crate::route_registry::{route_table, RouteTable};
+use axum::{extract::State, http::HeaderMap, http::StatusCode, Json};
+use std::time::Instant;
+
+/// GET /admin/routes
+///
+/// Returns every route of this build, below the base path: method, path
+/// and legacy alias, the credentials it takes, its route-level middleware,
+/// and whether it moves to the admin listener, plus the middleware in
+/// front of all routes.
+///
+/// # Request Headers
+/// ```text
+/// Authorization: Bearer <AXUM_ADMIN_TOKEN>
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The admin API is disabled (403 Forbidden)
+/// - The admin token is missing or wrong (401 Unauthorized)
+pub async fn list_routes(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<ApiResponse<RouteTable>, (StatusCode, Json<ErrorResponse>)> {
+    // ---
+    let start = Instant::now();
+
+    require_admin(&headers, &state)?;
+
+    let routes = route_table(state.base_path());
+    Ok(ApiResponse::new(routes).with_meta(ResponseMeta::new(&headers, start)))
+}
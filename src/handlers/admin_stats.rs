@@ -0,0 +1,68 @@
+//! Deployment statistics for operator dashboards.
+//!
+//! `GET /admin/stats` reports totals (users, credentials and their
+//! distribution per user, active sessions, movies) and passkey sign-in
+//! outcomes over the last 24 hours, as computed by [`StatsService`].
+
+use super::admin::{require_admin, ErrorResponse};
+use super::{ApiResponse, ResponseMeta};
+use crate::app_state::AppState;
+use crate::application::{AdminStats, StatsService};
+use axum::{
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use serde::Deserialize;
+use std::time::Instant;
+
+// ============================================================================
+// Request Types
+// ============================================================================
+
+/// Query parameters for the stats.
+#[derive(Debug, Default, Deserialize)]
+pub struct StatsQuery {
+    // ---
+    /// Recompute instead of serving a result cached up to a minute ago.
+    #[serde(default)]
+    pub refresh: bool,
+}
+
+// ============================================================================
+// Stats Handler
+// ============================================================================
+
+/// GET /admin/stats
+///
+/// Returns aggregate counts and sign-in trends. Results are cached for a
+/// minute; pass `?refresh=true` to recompute.
+///
+/// # Request Headers
+/// ```text
+/// Authorization: Bearer <AXUM_ADMIN_TOKEN>
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The admin API is disabled (403 Forbidden)
+/// - The admin token is missing or wrong (401 Unauthorized)
+/// - The database or Redis fails (500 Internal Server Error)
+pub async fn admin_stats(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<StatsQuery>,
+) -> Result<ApiResponse<AdminStats>, (StatusCode, Json<ErrorResponse>)> {
+    // ---
+    let start = Instant::now();
+
+    require_admin(&headers, &state)?;
+
+    let stats = StatsService::new(state)
+        .stats(query.refresh)
+        .await
+        .map_err(|err| (err.status, Json(ErrorResponse { error: err.message })))?;
+
+    Ok(ApiResponse::new(stats).with_meta(ResponseMeta::new(&headers, start)))
+}
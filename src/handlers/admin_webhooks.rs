@@ -0,0 +1,320 @@
+//! Admin CRUD for outbound webhook endpoints.
+//!
+//! All handlers require the admin bearer token (see `admin::require_admin`).
+//! Endpoint secrets are write-only: they are returned once, when created or
+//! rotated, and never listed.
+//!
+//! 1. `list_webhooks`  - GET    /admin/webhooks
+//! 2. `create_webhook` - POST   /admin/webhooks
+//! 3. `get_webhook`    - GET    /admin/webhooks/{id}
+//! 4. `update_webhook` - PUT    /admin/webhooks/{id}
+//! 5. `delete_webhook` - DELETE /admin/webhooks/{id}
+
+use super::admin::{require_admin, ErrorResponse};
+use super::ApiResponse;
+use crate::app_state::AppState;
+use crate::client_ip::ClientIp;
+use crate::events::ServerEvent;
+use crate::redis_source::RedisConnection;
+use crate::webhooks::{self, WebhookEndpoint};
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+type HandlerError = (StatusCode, Json<ErrorResponse>);
+
+// ============================================================================
+// Request/Response Types
+// ============================================================================
+
+/// Body for creating or updating an endpoint.
+#[derive(Debug, Deserialize)]
+pub struct WebhookRequest {
+    // ---
+    /// `http` or `https` URL to POST events to.
+    pub url: String,
+
+    /// Signing secret. Generated when omitted on create; kept when omitted on update.
+    pub secret: Option<String>,
+}
+
+// ---
+
+/// Public view of an endpoint.
+#[derive(Debug, Serialize)]
+pub struct WebhookInfo {
+    // ---
+    pub id: Uuid,
+    pub url: String,
+    pub created_at: String,
+
+    /// Only present in the response that set it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+}
+
+impl WebhookInfo {
+    // ---
+    fn new(endpoint: &WebhookEndpoint, reveal_secret: bool) -> Self {
+        // ---
+        Self {
+            id: endpoint.id,
+            url: endpoint.url.clone(),
+            created_at: endpoint.created_at.to_rfc3339(),
+            secret: reveal_secret.then(|| endpoint.secret.clone()),
+        }
+    }
+}
+
+// ============================================================================
+// Helper Functions
+// ============================================================================
+
+fn error(status: StatusCode, message: &str) -> HandlerError {
+    // ---
+    (
+        status,
+        Json(ErrorResponse {
+            error: message.to_string(),
+        }),
+    )
+}
+
+fn storage_error(e: anyhow::Error) -> HandlerError {
+    // ---
+    tracing::error!("Webhook endpoint storage error: {e}");
+    error(StatusCode::INTERNAL_SERVER_ERROR, "Webhook storage error")
+}
+
+async fn redis_conn(state: &AppState) -> Result<RedisConnection, HandlerError> {
+    // ---
+    state
+        .get_conn()
+        .await
+        .map_err(|status| error(status, "Internal server error"))
+}
+
+/// Accepts absolute `http`/`https` URLs only.
+fn validate_url(url: &str) -> Result<(), HandlerError> {
+    // ---
+    match Url::parse(url) {
+        Ok(parsed) if matches!(parsed.scheme(), "http" | "https") => Ok(()),
+        _ => Err(error(
+            StatusCode::BAD_REQUEST,
+            "url must be an absolute http or https URL",
+        )),
+    }
+}
+
+fn validate_secret(secret: &Option<String>) -> Result<(), HandlerError> {
+    // ---
+    match secret {
+        Some(s) if s.is_empty() => Err(error(StatusCode::BAD_REQUEST, "secret must not be empty")),
+        _ => Ok(()),
+    }
+}
+
+/// 256 bits of randomness, hex-encoded.
+fn generate_secret() -> String {
+    // ---
+    format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
+
+// ============================================================================
+// Handlers
+// ============================================================================
+
+/// GET /admin/webhooks
+///
+/// Lists all registered endpoints (without secrets).
+pub async fn list_webhooks(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<ApiResponse<Vec<WebhookInfo>>, HandlerError> {
+    // ---
+    require_admin(&headers, &state)?;
+    let mut conn = redis_conn(&state).await?;
+
+    let mut endpoints = webhooks::list_endpoints(&mut conn)
+        .await
+        .map_err(storage_error)?;
+    endpoints.sort_by_key(|e| e.created_at);
+
+    Ok(ApiResponse::new(
+        endpoints
+            .iter()
+            .map(|e| WebhookInfo::new(e, false))
+            .collect(),
+    ))
+}
+
+/// POST /admin/webhooks
+///
+/// Registers an endpoint. Responds 201 with the endpoint, including its
+/// secret; store it, as it is not shown again.
+///
+/// # Errors
+/// - 400 Bad Request for an invalid URL or empty secret
+pub async fn create_webhook(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    client: Option<ClientIp>,
+    Json(req): Json<WebhookRequest>,
+) -> Result<(StatusCode, ApiResponse<WebhookInfo>), HandlerError> {
+    // ---
+    require_admin(&headers, &state)?;
+    validate_url(&req.url)?;
+    validate_secret(&req.secret)?;
+
+    let endpoint = WebhookEndpoint {
+        id: Uuid::new_v4(),
+        url: req.url,
+        secret: req.secret.unwrap_or_else(generate_secret),
+        created_at: chrono::Utc::now(),
+    };
+
+    let mut conn = redis_conn(&state).await?;
+    webhooks::save_endpoint(&mut conn, &endpoint)
+        .await
+        .map_err(storage_error)?;
+
+    tracing::info!("Registered webhook {} -> {}", endpoint.id, endpoint.url);
+    state.events().publish(
+        ServerEvent::audit(
+            "webhook.created",
+            serde_json::json!({ "id": endpoint.id, "url": endpoint.url }),
+        )
+        .with_client_ip(client.map(|c| c.0)),
+    );
+
+    Ok((
+        StatusCode::CREATED,
+        ApiResponse::new(WebhookInfo::new(&endpoint, true)),
+    ))
+}
+
+/// GET /admin/webhooks/{id}
+///
+/// # Errors
+/// - 404 Not Found if no endpoint has this ID
+pub async fn get_webhook(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+) -> Result<ApiResponse<WebhookInfo>, HandlerError> {
+    // ---
+    require_admin(&headers, &state)?;
+    let mut conn = redis_conn(&state).await?;
+
+    let endpoint = webhooks::get_endpoint(&mut conn, id)
+        .await
+        .map_err(storage_error)?
+        .ok_or_else(|| error(StatusCode::NOT_FOUND, "Webhook not found"))?;
+
+    Ok(ApiResponse::new(WebhookInfo::new(&endpoint, false)))
+}
+
+/// PUT /admin/webhooks/{id}
+///
+/// Replaces the URL and, if `secret` is supplied, rotates the secret (the
+/// new secret is echoed back).
+///
+/// # Errors
+/// - 400 Bad Request for an invalid URL or empty secret
+/// - 404 Not Found if no endpoint has this ID
+pub async fn update_webhook(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    client: Option<ClientIp>,
+    Path(id): Path<Uuid>,
+    Json(req): Json<WebhookRequest>,
+) -> Result<ApiResponse<WebhookInfo>, HandlerError> {
+    // ---
+    require_admin(&headers, &state)?;
+    validate_url(&req.url)?;
+    validate_secret(&req.secret)?;
+
+    let mut conn = redis_conn(&state).await?;
+
+    let mut endpoint = webhooks::get_endpoint(&mut conn, id)
+        .await
+        .map_err(storage_error)?
+        .ok_or_else(|| error(StatusCode::NOT_FOUND, "Webhook not found"))?;
+
+    let rotated = req.secret.is_some();
+    endpoint.url = req.url;
+    if let Some(secret) = req.secret {
+        endpoint.secret = secret;
+    }
+
+    webhooks::save_endpoint(&mut conn, &endpoint)
+        .await
+        .map_err(storage_error)?;
+
+    tracing::info!("Updated webhook {} -> {}", endpoint.id, endpoint.url);
+    state.events().publish(ServerEvent::audit(
+        "webhook.updated",
+        serde_json::json!({ "id": endpoint.id, "url": endpoint.url, "secret_rotated": rotated }),
+    ).with_client_ip(client.map(|c| c.0)));
+
+    Ok(ApiResponse::new(WebhookInfo::new(&endpoint, rotated)))
+}
+
+/// DELETE /admin/webhooks/{id}
+///
+/// Responds 204 No Content. Deliveries already in flight are not cancelled.
+///
+/// # Errors
+/// - 404 Not Found if no endpoint has this ID
+pub async fn delete_webhook(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    client: Option<ClientIp>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, HandlerError> {
+    // ---
+    require_admin(&headers, &state)?;
+    let mut conn = redis_conn(&state).await?;
+
+    if !webhooks::delete_endpoint(&mut conn, id)
+        .await
+        .map_err(storage_error)?
+    {
+        return Err(error(StatusCode::NOT_FOUND, "Webhook not found"));
+    }
+
+    tracing::info!("Deleted webhook {id}");
+    state.events().publish(
+        ServerEvent::audit("webhook.deleted", serde_json::json!({ "id": id }))
+            .with_client_ip(client.map(|c| c.0)),
+    );
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+
+    #[test]
+    fn only_http_urls_accepted() {
+        // ---
+        assert!(validate_url("https://example.com/hooks").is_ok());
+        assert!(validate_url("http://127.0.0.1:9000/").is_ok());
+        assert!(validate_url("ftp://example.com/").is_err());
+        assert!(validate_url("/relative").is_err());
+    }
+
+    #[test]
+    fn generated_secret_is_64_hex_chars() {
+        // ---
+        let secret = generate_secret();
+        assert_eq!(secret.len(), 64);
+        assert!(secret.chars().all(|c| c.is_ascii_hexdigit()));
+    }
+}
@@ -0,0 +1,31 @@
+//! Origin checks for passkey ceremonies.
+
+use crate::client_ip::ExternalOrigin;
+use crate::tenant::Tenant;
+use axum::http::{header, HeaderMap};
+
+/// Warns when a ceremony starts from an origin the tenant's WebAuthn does
+/// not allow, so the `*/finish` call that follows will fail. The browser's `Origin`
+/// header is used when sent; otherwise the origin the request reached us
+/// at, which honours `X-Forwarded-Proto`/`-Host` from trusted proxies.
+pub(super) fn check_origin(
+    tenant: &Tenant,
+    headers: &HeaderMap,
+    external: Option<&ExternalOrigin>,
+) {
+    // ---
+    let origin = headers
+        .get(header::ORIGIN)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_owned)
+        .or_else(|| external.map(ExternalOrigin::to_string));
+
+    if let Some(origin) = origin.filter(|o| !tenant.allows_origin(o)) {
+        tracing::warn!(
+            "Passkey ceremony from {origin}, which is not an allowed WebAuthn origin of tenant {}; \
+             check AXUM_WEBAUTHN_ORIGIN, AXUM_WEBAUTHN_ADDITIONAL_ORIGINS, AXUM_TENANTS, \
+             and AXUM_TRUSTED_PROXIES",
+            tenant.id()
+        );
+    }
+}
@@ -0,0 +1,83 @@
+//! CSRF token issuance for cookie sessions.
+//!
+//! 1. `csrf_token` - GET /csrf
+//!
+//! See `middleware::csrf` for how the token is enforced.
+
+use super::admin::ErrorResponse;
+use super::ApiResponse;
+use crate::app_state::AppState;
+use crate::application::AuthService;
+use crate::middleware::issue_csrf_token;
+use crate::session::{session_cookie, ClientFingerprint};
+use crate::tenant::Tenant;
+use axum::{extract::State, http::HeaderMap, http::StatusCode, Json};
+use serde::Serialize;
+
+/// A freshly issued CSRF token.
+#[derive(Debug, Serialize)]
+pub struct CsrfTokenResponse {
+    // ---
+    pub csrf_token: String,
+
+    /// Header to send the token in on state-changing requests.
+    pub header: &'static str,
+}
+
+/// GET /csrf
+///
+/// Issues a CSRF token bound to the session cookie. Browser clients using
+/// cookie sessions send it back as `X-CSRF-Token` on every POST, PUT, PATCH,
+/// and DELETE. Calling again rotates the token. Bearer-token clients do not
+/// need one.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The session cookie is missing, invalid, or expired (401 Unauthorized)
+/// - Redis is unavailable (500 Internal Server Error)
+pub async fn csrf_token(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    client: ClientFingerprint,
+    tenant: Tenant,
+) -> Result<ApiResponse<CsrfTokenResponse>, (StatusCode, Json<ErrorResponse>)> {
+    // ---
+    let error = |status, message: &str| {
+        (
+            status,
+            Json(ErrorResponse {
+                error: message.to_string(),
+            }),
+        )
+    };
+
+    let session_token = session_cookie(&headers)
+        .ok_or_else(|| error(StatusCode::UNAUTHORIZED, "Missing session cookie"))?;
+
+    let mut conn = state
+        .get_conn()
+        .await
+        .map_err(|status| error(status, "Internal server error"))?;
+
+    AuthService::new(state)
+        .authenticate(session_token, &client, &tenant)
+        .await
+        .map_err(|status| match status.is_server_error() {
+            true => error(status, "Internal server error"),
+            false => error(status, "Invalid or expired session"),
+        })?;
+
+    let csrf_token = issue_csrf_token(&mut conn, session_token)
+        .await
+        .map_err(|e| {
+            // ---
+            tracing::error!("Failed to store CSRF token: {e}");
+            error(StatusCode::INTERNAL_SERVER_ERROR, "Internal server error")
+        })?;
+
+    Ok(ApiResponse::new(CsrfTokenResponse {
+        csrf_token,
+        header: "X-CSRF-Token",
+    }))
+}
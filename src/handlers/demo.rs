@@ -0,0 +1,52 @@
+//! Browser demo for the WebAuthn flows.
+//!
+//! 1. `demo_index`  - GET /app, /app/
+//! 2. `demo_script` - GET /app/webauthn.js
+//!
+//! The page and script live in `static/demo/` and are embedded at compile
+//! time, so the binary serves them without any files on disk. They call
+//! the versioned API from the browser with `navigator.credentials`. Their
+//! absolute paths are rewritten below `AXUM_BASE_PATH` when it is set.
+
+use crate::AppState;
+use axum::{
+    extract::State,
+    http::header,
+    response::{Html, IntoResponse},
+};
+use std::borrow::Cow;
+
+const INDEX_HTML: &str = include_str!("../../static/demo/index.html");
+const WEBAUTHN_JS: &str = include_str!("../../static/demo/webauthn.js");
+
+/// GET /app
+///
+/// Serves the demo page.
+pub async fn demo_index(State(state): State<AppState>) -> impl IntoResponse {
+    // ---
+    Html(rebase(INDEX_HTML, "src=\"", state.base_path()))
+}
+
+/// GET /app/webauthn.js
+///
+/// Serves the demo client, including the base64url encode/decode helpers
+/// for the challenge and credential JSON.
+pub async fn demo_script(State(state): State<AppState>) -> impl IntoResponse {
+    // ---
+    (
+        [(header::CONTENT_TYPE, "text/javascript; charset=utf-8")],
+        rebase(WEBAUTHN_JS, "const API = \"", state.base_path()),
+    )
+}
+
+/// `text` with the absolute path following `before` moved below
+/// `base_path`.
+fn rebase(text: &'static str, before: &str, base_path: &str) -> Cow<'static, str> {
+    // ---
+    match base_path {
+        "" => Cow::Borrowed(text),
+        base_path => {
+            Cow::Owned(text.replacen(&format!("{before}/"), &format!("{before}{base_path}/"), 1))
+        }
+    }
+}
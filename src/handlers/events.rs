@@ -0,0 +1,149 @@
+//! Server-Sent Events stream of live server events.
+//!
+//! 1. `event_stream` - GET /events
+//!
+//! Dashboards subscribe once instead of polling. Events come from the
+//! in-process [`EventBus`](crate::events::EventBus), so each replica only
+//! streams what happened on that replica.
+
+use super::admin::{is_admin, ErrorResponse};
+use super::webauthn_credentials::extract_session;
+use crate::app_state::AppState;
+use crate::events::ServerEvent;
+use crate::session::ClientFingerprint;
+use crate::tenant::Tenant;
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::sse::{Event, KeepAlive, Sse},
+    Json,
+};
+use futures::stream::{self, Stream};
+use std::convert::Infallible;
+use tokio::sync::broadcast::{error::RecvError, Receiver};
+use uuid::Uuid;
+
+/// Who is subscribed, which decides the events they receive.
+#[derive(Clone, Copy)]
+enum Audience {
+    // ---
+    /// Admin token: every event.
+    All,
+
+    /// Session token: the user's own events plus health changes.
+    User(Uuid),
+}
+
+impl Audience {
+    // ---
+    fn can_see(&self, event: &ServerEvent) -> bool {
+        // ---
+        match self {
+            Self::All => true,
+            Self::User(user_id) => event.visible_to(*user_id),
+        }
+    }
+}
+
+/// Converts a bus event into an SSE frame (`id`, `event`, JSON `data`).
+fn to_sse(event: &ServerEvent) -> Event {
+    // ---
+    Event::default()
+        .id(event.id.to_string())
+        .event(event.kind.as_str())
+        .json_data(event)
+        .unwrap_or_else(|e| Event::default().comment(format!("unserializable event: {e}")))
+}
+
+/// Turns a bus receiver into an SSE stream filtered for `audience`.
+///
+/// A subscriber that falls behind gets a `lagged` event carrying the number
+/// of skipped events, so it can refetch state if needed.
+fn filtered_stream(
+    receiver: Receiver<ServerEvent>,
+    audience: Audience,
+) -> impl Stream<Item = Result<Event, Infallible>> {
+    // ---
+    stream::unfold(receiver, move |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(event) if audience.can_see(&event) => {
+                    return Some((Ok(to_sse(&event)), receiver));
+                }
+                Ok(_) => continue,
+                Err(RecvError::Lagged(skipped)) => {
+                    let frame = Event::default().event("lagged").data(skipped.to_string());
+                    return Some((Ok(frame), receiver));
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    })
+}
+
+/// GET /events
+///
+/// Streams live server events as `text/event-stream`. Each frame has the
+/// event ID as `id`, the type (`user.registered`, `auth.login`,
+/// `credential.deleted`, `health.changed`, `audit`) as `event`, and the
+/// JSON-encoded event as `data`.
+///
+/// # Request Headers
+/// ```text
+/// Authorization: Bearer <AXUM_ADMIN_TOKEN | session_token>
+/// ```
+///
+/// With the admin token every event is streamed. With a session token only
+/// events about that user, plus health changes, are streamed.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - Neither the admin token nor a valid session token is supplied (401 Unauthorized)
+pub async fn event_stream(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    client: ClientFingerprint,
+    tenant: Tenant,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, (StatusCode, Json<ErrorResponse>)> {
+    // ---
+    let audience = if is_admin(&headers, &state) {
+        Audience::All
+    } else {
+        let session = extract_session(&headers, &state, &client, &tenant)
+            .await
+            .map_err(|(status, Json(e))| (status, Json(ErrorResponse { error: e.error })))?;
+        Audience::User(session.user_id)
+    };
+
+    // Subscribe before returning so no event published after the response
+    // starts is missed.
+    let receiver = state.events().subscribe();
+
+    Ok(Sse::new(filtered_stream(receiver, audience)).keep_alive(KeepAlive::default()))
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+    use crate::events::{EventBus, ServerEventKind};
+    use futures::StreamExt;
+
+    #[tokio::test]
+    async fn user_stream_skips_other_users_events() {
+        // ---
+        let bus = EventBus::default();
+        let me = Uuid::new_v4();
+        let mut stream = Box::pin(filtered_stream(bus.subscribe(), Audience::User(me)));
+
+        let event =
+            |user| ServerEvent::new(ServerEventKind::Login, Some(user), serde_json::json!({}));
+        bus.publish(event(Uuid::new_v4()));
+        bus.publish(event(me));
+        drop(bus);
+
+        assert!(stream.next().await.is_some());
+        assert!(stream.next().await.is_none());
+    }
+}
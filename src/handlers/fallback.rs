@@ -0,0 +1,25 @@
+use super::ApiError;
+use axum::{http::StatusCode, response::IntoResponse, Json};
+
+/// Fallback for paths no route matches (404).
+pub async fn not_found() -> impl IntoResponse {
+    // ---
+    (
+        StatusCode::NOT_FOUND,
+        Json(ApiError::new("not_found", "No such endpoint")),
+    )
+}
+
+/// Fallback for an existing path requested with a method it does not
+/// support (405). The router adds the `Allow` header listing the methods
+/// the path does support.
+pub async fn method_not_allowed() -> impl IntoResponse {
+    // ---
+    (
+        StatusCode::METHOD_NOT_ALLOWED,
+        Json(ApiError::new(
+            "method_not_allowed",
+            "Method not allowed for this endpoint",
+        )),
+    )
+}
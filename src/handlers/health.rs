@@ -1,16 +1,81 @@
+use crate::client_ip::ExternalOrigin;
+use crate::health_history::HealthTransition;
+use crate::tenant::Tenant;
 use crate::AppState;
 use axum::{
     extract::{Query, State},
     http::StatusCode,
     Json,
 };
+use chrono::{DateTime, Utc};
 use redis::AsyncCommands;
-use serde::Deserialize;
-use std::time::Instant;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::time::{Duration, Instant};
 
-#[derive(serde::Serialize)]
+/// How long each dependency check may take before it counts as failed.
+const DEPENDENCY_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Serialize)]
 pub struct HealthResponse {
     status: &'static str,
+
+    /// Per-dependency results, only in full mode.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dependencies: Option<BTreeMap<&'static str, DependencyHealth>>,
+
+    /// The origin the request reached the service at, only in full mode.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    origin: Option<OriginHealth>,
+}
+
+/// The external origin of a health request, to check reverse proxy setup.
+#[derive(Serialize)]
+pub struct OriginHealth {
+    /// `scheme://host[:port]`, honouring `X-Forwarded-Proto`/`-Host` (or
+    /// `Forwarded`) from trusted proxies.
+    url: String,
+
+    /// The tenant this host is served as.
+    tenant: String,
+
+    /// Whether passkey ceremonies can run from this origin.
+    webauthn_allowed: bool,
+}
+
+/// Result of checking one backing service.
+#[derive(Serialize)]
+pub struct DependencyHealth {
+    /// `ok`, or `degraded` if the check failed or timed out.
+    status: &'static str,
+
+    /// Time the check took, in milliseconds.
+    latency_ms: f64,
+
+    /// Why the check failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl DependencyHealth {
+    fn is_ok(&self) -> bool {
+        // ---
+        self.error.is_none()
+    }
+}
+
+/// Recent dependency health changes, for `GET /health/history`.
+#[derive(Serialize)]
+pub struct HealthHistoryResponse {
+    /// When the server started.
+    started_at: DateTime<Utc>,
+
+    /// Seconds since the server started.
+    uptime_seconds: u64,
+
+    /// Changes of dependency status seen by full health checks, newest first.
+    transitions: Vec<HealthTransition>,
 }
 
 #[derive(Deserialize)]
@@ -18,26 +83,72 @@ pub struct HealthQuery {
     mode: Option<String>,
 }
 
+/// Runs one dependency check under [`DEPENDENCY_TIMEOUT`] and times it.
+async fn check<F>(check: F) -> DependencyHealth
+where
+    F: Future<Output = Result<(), String>>,
+{
+    // ---
+    let start = Instant::now();
+    let result = tokio::time::timeout(DEPENDENCY_TIMEOUT, check)
+        .await
+        .unwrap_or_else(|_| Err(format!("timed out after {DEPENDENCY_TIMEOUT:?}")));
+
+    DependencyHealth {
+        status: if result.is_ok() { "ok" } else { "degraded" },
+        latency_ms: start.elapsed().as_secs_f64() * 1000.0,
+        error: result.err(),
+    }
+}
+
+async fn check_redis(state: &AppState) -> DependencyHealth {
+    // ---
+    check(async {
+        let mut conn = state
+            .get_conn()
+            .await
+            .map_err(|_| "connection failed".to_string())?;
+        conn.ping::<String>().await.map_err(|e| e.to_string())?;
+        Ok(())
+    })
+    .await
+}
+
+async fn check_database(state: &AppState) -> DependencyHealth {
+    // ---
+    check(async { state.repository().ping().await.map_err(|e| e.to_string()) }).await
+}
+
 /// Responds with the health status of the server.
 ///
 /// - By default (no query parameters), performs a light check to confirm the web server
 ///   is running.
 ///
-/// - If `mode=full` is passed as a query parameter, also pings the Redis backend to
-///   verify database connectivity.
+/// - If `mode=full` is passed as a query parameter, also pings Redis and runs
+///   `SELECT 1` against the database, concurrently and each with a 2 second
+///   timeout. The body reports each dependency's status and latency. Changes
+///   in the overall result are published as `health.changed` on the event stream.
 ///
 /// # Query Parameters
 /// - `mode`: Optional. Accepts `"light"` (default) or `"full"`.
 ///
 /// # Responses
-/// - `200 OK` with `{ "status": "ok" }` if server (and Redis, in full mode) are healthy.
-/// - `500 INTERNAL SERVER ERROR` with `{ "status": "error" }` if Redis connection or ping fails in full mode.
+/// - `200 OK` with `{ "status": "ok" }` if the server (and, in full mode, every dependency) is healthy.
+/// - `500 INTERNAL SERVER ERROR` with `{ "status": "error" }` if any dependency check fails in full mode.
+///
+/// In full mode the body also carries `dependencies`, e.g.
+/// `{ "redis": { "status": "ok", "latency_ms": 0.4 }, "database": { "status": "degraded", ... } }`,
+/// and the `origin` the request arrived at (as seen through trusted proxies),
+/// the tenant it is served as, and whether passkeys work there, e.g.
+/// `{ "url": "https://app.example.com", "tenant": "default", "webauthn_allowed": true }`.
 ///
 /// # Examples
 /// - `GET /health` → 200 OK
 /// - `GET /health?mode=full` → 200 OK or 500 INTERNAL SERVER ERROR
 pub async fn health_check(
     State(state): State<AppState>,
+    origin: Option<ExternalOrigin>,
+    tenant: Tenant,
     Query(params): Query<HealthQuery>,
 ) -> (StatusCode, Json<HealthResponse>) {
     // ---
@@ -46,37 +157,51 @@ pub async fn health_check(
 
     match params.mode.as_deref() {
         Some("full") => {
-            // Full health check: Ping Redis
-            let mut conn = match state.get_conn().await {
-                Ok(conn) => conn,
-                Err(_) => {
-                    state
-                        .metrics()
-                        .record_http_request(start, "/health", "GET", 500);
-                    return (
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        Json(HealthResponse { status: "error" }),
-                    );
-                }
-            };
-
-            let ping_result: redis::RedisResult<String> = conn.ping().await;
-            match ping_result {
-                Ok(_) => {
-                    state
-                        .metrics()
-                        .record_http_request(start, "/health", "GET", 200);
-                    (StatusCode::OK, Json(HealthResponse { status: "ok" }))
-                }
-                Err(_) => {
-                    state
-                        .metrics()
-                        .record_http_request(start, "/health", "GET", 500);
-                    (
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        Json(HealthResponse { status: "error" }),
-                    )
+            // Full health check: Redis and the database
+            let (redis, database) = tokio::join!(check_redis(&state), check_database(&state));
+            for (name, dependency) in [("redis", &redis), ("database", &database)] {
+                state
+                    .health_history()
+                    .record(name, dependency.status, dependency.error.as_deref());
+            }
+            let healthy = redis.is_ok() && database.is_ok();
+            let dependencies = Some(BTreeMap::from([("redis", redis), ("database", database)]));
+            let origin = origin.map(|origin| {
+                let url = origin.to_string();
+                OriginHealth {
+                    tenant: tenant.id().to_string(),
+                    webauthn_allowed: tenant.allows_origin(&url),
+                    url,
                 }
+            });
+
+            // Publishes `health.changed` to SSE subscribers on transitions
+            state.events().record_health(healthy);
+
+            if healthy {
+                state
+                    .metrics()
+                    .record_http_request(start, "/health", "GET", 200);
+                (
+                    StatusCode::OK,
+                    Json(HealthResponse {
+                        status: "ok",
+                        dependencies,
+                        origin,
+                    }),
+                )
+            } else {
+                state
+                    .metrics()
+                    .record_http_request(start, "/health", "GET", 500);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(HealthResponse {
+                        status: "error",
+                        dependencies,
+                        origin,
+                    }),
+                )
             }
         }
         _ => {
@@ -84,7 +209,35 @@ pub async fn health_check(
             state
                 .metrics()
                 .record_http_request(start, "/health", "GET", 200);
-            (StatusCode::OK, Json(HealthResponse { status: "ok" }))
+            (
+                StatusCode::OK,
+                Json(HealthResponse {
+                    status: "ok",
+                    dependencies: None,
+                    origin: None,
+                }),
+            )
         }
     }
 }
+
+/// Responds with the dependency status changes seen by recent full health
+/// checks and the server's uptime.
+///
+/// Each transition names the dependency, its new and previous status, when
+/// the change was seen, and the error if it failed. At most the last 100
+/// are kept, in memory, so the history starts empty after a restart.
+///
+/// # Examples
+/// - `GET /health/history` → 200 OK with
+///   `{ "started_at": "...", "uptime_seconds": 3600, "transitions": [{ "dependency": "redis",
+///   "status": "degraded", "previous": "ok", "at": "...", "error": "connection failed" }] }`
+pub async fn health_history(State(state): State<AppState>) -> Json<HealthHistoryResponse> {
+    // ---
+    let history = state.health_history();
+    Json(HealthHistoryResponse {
+        started_at: history.started_at(),
+        uptime_seconds: history.uptime().as_secs(),
+        transitions: history.transitions(),
+    })
+}
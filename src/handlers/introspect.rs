@@ -0,0 +1,117 @@
+//! Session introspection for downstream services.
+//!
+//! `POST /auth/introspect` lets other services check a session token
+//! without access to Redis or the signing key, in the manner of OAuth
+//! token introspection (RFC 7662). Callers authenticate with one of
+//! `AXUM_SERVICE_TOKENS` (or the admin token), and over mutual TLS may
+//! also be required to present a client certificate.
+
+use super::ApiResponse;
+use crate::app_state::AppState;
+use crate::application::{AuthService, ServiceError, USER_ROLE};
+use crate::service_identity::ServiceIdentity;
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+// ============================================================================
+// Request/Response Types
+// ============================================================================
+
+#[derive(Debug, Deserialize)]
+pub struct IntrospectRequest {
+    // ---
+    /// Session token to check, as issued by `/webauthn/auth/finish`.
+    pub token: String,
+}
+
+/// Whether a token is an active session and, if so, whose. Inactive tokens
+/// (unknown, expired, or revoked) carry only `active: false`.
+#[derive(Debug, Default, Serialize)]
+pub struct IntrospectResponse {
+    // ---
+    pub active: bool,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub role: Option<&'static str>,
+
+    /// RFC 3339 timestamp after which the session is no longer valid.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    // ---
+    pub error: String,
+}
+
+// ============================================================================
+// Introspect Handler
+// ============================================================================
+
+/// POST /auth/introspect
+///
+/// Reports whether a session token is active. An invalid token is an
+/// answer (`200` with `active: false`), not an error.
+///
+/// # Request Headers
+/// ```text
+/// Authorization: Bearer <service_token>
+/// ```
+///
+/// # Request Body
+/// ```json
+/// { "token": "<session_token>" }
+/// ```
+///
+/// # Errors
+/// - 403 Forbidden if no service or admin token is configured, or a client
+///   certificate is required and none was presented
+/// - 401 Unauthorized if the bearer token is not one of them
+/// - 500 Internal Server Error if Redis fails
+pub async fn introspect_session(
+    State(state): State<AppState>,
+    identity: Option<ServiceIdentity>,
+    headers: HeaderMap,
+    Json(req): Json<IntrospectRequest>,
+) -> Result<ApiResponse<IntrospectResponse>, (StatusCode, Json<ErrorResponse>)> {
+    // ---
+    let reject = |err: ServiceError| (err.status, Json(ErrorResponse { error: err.message }));
+
+    let service = AuthService::new(state);
+    let bearer = headers
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .unwrap_or_default();
+    service.authorize_service(bearer).map_err(reject)?;
+    if let Some(identity) = &identity {
+        tracing::debug!("Session introspection by service {identity}");
+    }
+
+    let session = service
+        .introspect(&req.token)
+        .await
+        .map_err(|status| reject(ServiceError::new(status, "Cannot check sessions")))?;
+
+    Ok(ApiResponse::new(match session {
+        Some(session) => IntrospectResponse {
+            active: true,
+            user_id: Some(session.user_id.to_string()),
+            username: Some(session.username),
+            role: Some(USER_ROLE),
+            expires_at: Some(session.expires_at.to_rfc3339()),
+        },
+        None => IntrospectResponse::default(),
+    }))
+}
@@ -1,30 +1,86 @@
 // Gateway module - controls public API for handlers
 // Modules are private, only exported symbols are public
 
+mod account;
+mod admin;
+mod admin_credential_limits;
+mod admin_routes;
+mod admin_stats;
+mod admin_webhooks;
+mod challenge;
+mod csrf;
+mod demo;
+mod events;
+mod fallback;
 mod health;
+mod introspect;
 mod metrics;
 mod movies;
+#[cfg(feature = "password")]
+mod password;
+#[cfg(feature = "pprof")]
+mod pprof;
 mod root;
 mod shared_types;
 mod webauthn_authenticate;
 mod webauthn_credentials;
 mod webauthn_register;
+mod websocket;
 
-use shared_types::ApiResponse;
+use shared_types::{vary_on_accept, CsvBody, Format, Negotiated};
+pub use shared_types::{ApiError, ApiResponse, Pagination, ResponseLinks, ResponseMeta};
 
 // Core handlers
-pub use health::health_check;
+pub use csrf::csrf_token;
+pub use demo::{demo_index, demo_script};
+pub use events::event_stream;
+pub use fallback::{method_not_allowed, not_found};
+pub use health::{health_check, health_history};
 pub use metrics::metrics_handler;
 pub use root::root_handler;
+pub use websocket::ws_handler;
 
 // Movie CRUD handlers
-pub use movies::{add_movie, delete_movie, get_movie, update_movie};
+pub use movies::{add_movie, delete_movie, get_movie, list_movies, update_movie};
 
 // WebAuthn registration handlers
+#[cfg(feature = "fuzzing")]
+pub(crate) use webauthn_register::RegistrationFinishRequest;
 pub use webauthn_register::{register_finish, register_start};
 
 // WebAuthn authentication handlers
-pub use webauthn_authenticate::{auth_finish, auth_start};
+#[cfg(feature = "fuzzing")]
+pub(crate) use webauthn_authenticate::AuthFinishRequest;
+pub use webauthn_authenticate::{auth_finish, auth_start, logout};
 
 // WebAuthn credential management handlers
 pub use webauthn_credentials::{delete_credential, list_credentials};
+
+// Password authentication handlers
+#[cfg(feature = "password")]
+pub use password::{password_login, password_register};
+
+// Account handlers
+pub use account::account_usage;
+
+// Service-to-service handlers
+pub use introspect::introspect_session;
+
+// Admin handlers
+pub(crate) use admin::constant_time_eq;
+
+// Correlation ID of a request, for responses built outside the handlers
+pub use admin::{export_audit_log, purge_deleted, reencrypt_credentials, retention_schedule};
+pub use admin_credential_limits::{
+    delete_credential_limit, get_credential_limit, set_credential_limit,
+};
+pub use admin_routes::list_routes;
+pub use admin_stats::admin_stats;
+pub use admin_webhooks::{
+    create_webhook, delete_webhook, get_webhook, list_webhooks, update_webhook,
+};
+pub(crate) use shared_types::request_id;
+
+// Profiling handlers
+#[cfg(feature = "pprof")]
+pub use pprof::pprof_profile;
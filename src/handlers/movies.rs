@@ -1,263 +1,269 @@
-use super::ApiResponse;
+use super::{
+    ApiError, ApiResponse, CsvBody, Format, Negotiated, Pagination, ResponseLinks, ResponseMeta,
+};
+use crate::application::{Movie, MovieError, MovieService, StoredMovie};
 use crate::AppState;
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
     Json,
 };
-use chrono::{Datelike, Utc};
-use redis::AsyncCommands;
-use regex::Regex;
 use serde::{Deserialize, Serialize};
-use sha1::{Digest, Sha1};
 use std::time::Instant;
 
-#[derive(Debug, Default, Serialize, Deserialize, Clone)]
-pub struct Movie {
-    title: String,
-    year: u16,
-    stars: f32,
-}
-
-#[derive(Debug, Clone)]
-pub struct HashKey {
-    pub value: String,
+impl IntoResponse for MovieError {
+    fn into_response(self) -> Response {
+        // ---
+        let status = match self {
+            Self::Status(status) => status,
+            Self::InvalidGenre => {
+                let error = ApiError::new(
+                    "invalid_genre",
+                    "Genre must be 1 to 32 letters, digits, or hyphens",
+                );
+                return (StatusCode::BAD_REQUEST, Json(error)).into_response();
+            }
+        };
+        let error = match status {
+            StatusCode::BAD_REQUEST => ApiError::new(
+                "invalid_movie",
+                "Movie needs a non-empty title, a year from 1880 to five years from now, \
+                 0 to 5 stars, and at most 10 genres of up to 32 letters, digits, or hyphens",
+            ),
+            StatusCode::NOT_FOUND => ApiError::new("movie_not_found", "Movie not found"),
+            StatusCode::CONFLICT => ApiError::new(
+                "movie_exists",
+                "A movie with this title and year already exists",
+            ),
+            StatusCode::SERVICE_UNAVAILABLE => {
+                ApiError::new("service_unavailable", "Service temporarily unavailable")
+            }
+            _ => ApiError::new("internal_error", "Internal server error"),
+        };
+        (status, Json(error)).into_response()
+    }
 }
 
-impl Movie {
+/// Records a movies request answered with `result`.
+fn record<T>(
+    state: &AppState,
+    start: Instant,
+    route: &str,
+    method: &str,
+    result: &Result<T, MovieError>,
+    success: StatusCode,
+) {
     // ---
-
-    /// Sanitizes the Movie instance by trimming whitespace,
-    /// collapsing multiple spaces, validating fields, and generating
-    /// a HashKey based on normalized title and year.
-    pub fn sanitize(&mut self) -> Result<HashKey, StatusCode> {
-        // ---
-
-        let re = Regex::new(r"\s+").unwrap();
-
-        // Trim leading/trailing and collapse internal spaces
-        let trimmed = self.title.trim();
-        let squeezed = re.replace_all(trimmed, " ");
-        self.title = squeezed.to_string();
-
-        // Validation
-        if self.title.is_empty() {
-            return Err(StatusCode::BAD_REQUEST);
-        }
-
-        let current_year = Utc::now().year() as u16;
-        if self.year < 1880 || self.year > current_year + 5 {
-            return Err(StatusCode::BAD_REQUEST);
-        }
-
-        if !(0.0..=5.0).contains(&self.stars) {
-            return Err(StatusCode::BAD_REQUEST);
-        }
-
-        // Now generate the lookup key
-        let combined = format!("{}:{}", self.title.to_lowercase(), self.year);
-        let mut hasher = Sha1::new();
-        hasher.update(combined.as_bytes());
-        let result = hasher.finalize();
-        let key = hex::encode(result);
-
-        Ok(HashKey { value: key })
-    }
+    let status = match result {
+        Ok(_) => success,
+        Err(err) => err.status(),
+    };
+    state
+        .metrics()
+        .record_http_request(start, route, method, status.as_u16());
 }
 
 /// Handler for fetching a movie entry by ID (GET /get/{id}).
 ///
 /// Looks up a movie by its unique ID in the database.
 ///
-/// - If the movie exists, responds with `200 OK` and the full `Movie` object as JSON.
+/// - If the movie exists, responds with `200 OK` and the full `Movie` object,
+///   wrapped in the standard envelope with request metadata, as JSON or
+///   (with `Accept: application/msgpack`) MessagePack.
 /// - If the movie does not exist, responds with `404 Not Found` and an empty body.
 ///
 /// This endpoint enforces correct HTTP semantics for missing resources.
-#[tracing::instrument(skip(state, id))]
+#[tracing::instrument(skip(state, headers, id))]
 pub async fn get_movie(
     State(state): State<AppState>,
+    headers: HeaderMap,
     Path(id): Path<String>,
-) -> Result<(StatusCode, ApiResponse<Movie>), StatusCode> {
+) -> Result<Negotiated<Movie>, MovieError> {
     // ---
 
     let start = Instant::now();
-    let mut conn = state.get_conn().await?;
-
-    tracing::debug!("get movie: {id}");
-
-    let result: Option<String> = conn.get(&id).await.map_err(|err| {
-        tracing::info!("Got internal server error: {:?}", &err);
-        state
-            .metrics()
-            .record_http_request(start, "/movies/get", "GET", 500);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
-
-    let json_string = match result {
-        Some(val) => val,
-        None => {
-            tracing::trace!("Movie not found: {id}");
-            state
-                .metrics()
-                .record_http_request(start, "/movies/get", "GET", 404);
-            return Err(StatusCode::NOT_FOUND);
-        }
-    };
+    let format = Format::negotiate(&headers, &[Format::Json, Format::MsgPack]);
 
-    let movie: Movie = serde_json::from_str(&json_string).map_err(|err| {
-        tracing::info!("Error parsing JSON: {:?}", &err);
-        state
-            .metrics()
-            .record_http_request(start, "/movies/get", "GET", 400);
-        StatusCode::BAD_REQUEST
-    })?;
+    let result = MovieService::new(state.clone()).get(&id).await;
+    record(&state, start, "/movies/get", "GET", &result, StatusCode::OK);
 
-    tracing::trace!("Movie return: {}/{:?}", &id, &movie);
-    state
-        .metrics()
-        .record_http_request(start, "/movies/get", "GET", 200);
+    let body = ApiResponse::new(result?).with_meta(ResponseMeta::new(&headers, start));
 
-    Ok((StatusCode::OK, ApiResponse { data: movie }))
+    Ok(Negotiated::new(format, body))
 }
 
-async fn save_movie(
-    conn: &mut redis::aio::MultiplexedConnection,
-    movie_id: &str,
-    movie: &Movie,
-    allow_overwrite: bool,
-) -> Result<StatusCode, StatusCode> {
+/// Query parameters for `GET /movies/list`.
+#[derive(Debug, Deserialize)]
+pub struct ListMoviesQuery {
     // ---
+    /// Only list movies with this genre (case-insensitive).
+    pub genre: Option<String>,
+    pub page: Option<u32>,
+    pub per_page: Option<u32>,
+}
 
-    tracing::trace!("save_movie {}/{:?}", &movie_id, &movie);
+/// Response containing one page of movies.
+#[derive(Serialize)]
+pub struct ListMoviesResponse {
+    // ---
+    pub movies: Vec<StoredMovie>,
+}
 
-    if !allow_overwrite {
-        let exists: bool = conn.exists(movie_id).await.map_err(|err| {
-            tracing::info!("Got internal server error (1): {:?}", &err);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-        if exists {
-            tracing::trace!("Conflict");
-            return Err(StatusCode::CONFLICT);
+/// A movie as a CSV row; genres are joined with `;`.
+#[derive(Serialize)]
+struct MovieCsvRow<'a> {
+    // ---
+    id: &'a str,
+    title: &'a str,
+    year: u16,
+    stars: f32,
+    genres: String,
+}
+
+impl<'a> From<&'a StoredMovie> for MovieCsvRow<'a> {
+    fn from(stored: &'a StoredMovie) -> Self {
+        // ---
+        Self {
+            id: &stored.id,
+            title: &stored.movie.title,
+            year: stored.movie.year,
+            stars: stored.movie.stars,
+            genres: stored.movie.genres.join(";"),
         }
     }
+}
 
-    let movie_json = serde_json::to_string(movie).map_err(|err| {
-        tracing::info!("Serialization error: {:?}", &err);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
-    tracing::trace!("Writing movie: {:?}", &movie_json);
-
-    let _: () = conn.set(movie_id, movie_json).await.map_err(|err| {
-        tracing::info!("Got internal server error (2): {:?}", &err);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
+/// Handler for listing movies (GET /list), optionally filtered by genre.
+///
+/// Movies are sorted by title, then year, and paginated with `page`
+/// (1-based, default 1) and `per_page` (default 50, at most 100). The
+/// envelope carries pagination metadata and `next`/`prev` links.
+///
+/// - `?genre=scifi` lists only movies with that genre; an unknown genre
+///   gives an empty list and a malformed one `400 Bad Request`.
+/// - Movies stored before genres and listing existed are not listed until
+///   they are updated.
+/// - `Accept: application/msgpack` returns the envelope as MessagePack, and
+///   `Accept: text/csv` the page's movies as CSV rows
+///   (`id,title,year,stars,genres`), without the envelope.
+#[tracing::instrument(skip(state, headers))]
+pub async fn list_movies(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<ListMoviesQuery>,
+) -> Result<Response, MovieError> {
+    // ---
 
-    tracing::warn!("save movie OK");
+    let start = Instant::now();
+    let format = Format::negotiate(&headers, &[Format::Json, Format::MsgPack, Format::Csv]);
+
+    let result = MovieService::new(state.clone())
+        .list(query.genre.as_deref(), query.page, query.per_page)
+        .await;
+    record(
+        &state,
+        start,
+        "/movies/list",
+        "GET",
+        &result,
+        StatusCode::OK,
+    );
+    let page = result?;
 
-    if allow_overwrite {
-        Ok(StatusCode::OK)
-    } else {
-        Ok(StatusCode::CREATED)
+    if format == Format::Csv {
+        let rows = page.movies.iter().map(MovieCsvRow::from).collect();
+        return Ok(CsvBody::<MovieCsvRow>(rows).into_response());
     }
+
+    let meta = ResponseMeta::new(&headers, start).with_pagination(Pagination {
+        page: page.page,
+        per_page: page.per_page,
+        total: page.total,
+    });
+    let links = page_links(
+        &state.api_v1_prefix(),
+        page.genre.as_deref(),
+        page.page,
+        page.per_page,
+        page.total,
+    );
+
+    let body = ApiResponse::new(ListMoviesResponse {
+        movies: page.movies,
+    })
+    .with_meta(meta)
+    .with_links(links);
+    Ok(Negotiated::new(format, body).into_response())
 }
 
-// Response for add_movie
-#[derive(Serialize)]
-pub struct CreatedResponse {
-    id: String,
+/// Builds `next`/`prev` links for a movies page, keeping the (validated)
+/// genre filter. `api` is the API prefix clients see.
+///
+/// `next` is omitted on the last page and `prev` on the first.
+fn page_links(
+    api: &str,
+    genre: Option<&str>,
+    page: u32,
+    per_page: u32,
+    total: u64,
+) -> ResponseLinks {
+    // ---
+    let genre = genre
+        .map(|genre| format!("genre={genre}&"))
+        .unwrap_or_default();
+    let link = |p: u32| format!("{api}/movies/list?{genre}page={p}&per_page={per_page}");
+
+    let has_next = (page as u64).saturating_mul(per_page as u64) < total;
+
+    ResponseLinks {
+        next: has_next.then(|| link(page + 1)),
+        prev: (page > 1).then(|| link(page - 1)),
+    }
 }
 
+/// `201 Created` with a `Location` header and the created resource.
+type Created<T> = (
+    StatusCode,
+    [(header::HeaderName, String); 1],
+    ApiResponse<T>,
+);
+
 /// Handler for creating a new movie entry (POST /add).
 ///
-/// Expects a complete `Movie` object in the request body.
+/// Expects a `Movie` object (`title`, `year`, `stars`) in the request body.
+/// The server assigns the movie a new UUID; an `id` in the body is ignored.
 ///
-/// - If the movie ID already exists in the database, responds with `409 Conflict`.
-/// - On success, responds with `201 Created`.
-///
-/// This endpoint enforces uniqueness of movie IDs.
-#[tracing::instrument(skip(state, movie))]
+/// - If a movie with the same title and year (compared case-insensitively,
+///   ignoring extra whitespace) exists, responds with `409 Conflict`.
+/// - On success, responds with `201 Created`, a `Location` header pointing
+///   at `GET /api/v1/movies/get/{id}` (below the base path), and the stored movie with its `id`
+///   in the standard envelope.
+#[tracing::instrument(skip(state, headers, movie))]
 pub async fn add_movie(
     State(state): State<AppState>,
-    Json(mut movie): Json<Movie>,
-) -> Result<(StatusCode, Json<CreatedResponse>), StatusCode> {
+    headers: HeaderMap,
+    Json(movie): Json<Movie>,
+) -> Result<Created<StoredMovie>, MovieError> {
     // ---
 
     let start = Instant::now();
 
-    // Sanitize the movie and get a hash key for it
-    let hash_key = movie.sanitize().inspect_err(|_err| {
-        state
-            .metrics()
-            .record_http_request(start, "/movies/add", "POST", 400);
-    })?;
-
-    let mut conn = state.get_conn().await.map_err(|_| {
-        state
-            .metrics()
-            .record_http_request(start, "/movies/add", "POST", 500);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
-
-    let redis_key = hash_key.value;
-
-    // Create a span with movie details for tracing
-    let span = tracing::info_span!(
-        "add_movie",
-        title = %movie.title,
-        year = movie.year,
-        key = %redis_key
+    let result = MovieService::new(state.clone()).add(movie).await;
+    record(
+        &state,
+        start,
+        "/movies/add",
+        "POST",
+        &result,
+        StatusCode::CREATED,
     );
-    let _enter = span.enter();
-
-    // Check if movie already exists
-    if redis::cmd("EXISTS")
-        .arg(&redis_key)
-        .query_async::<i32>(&mut conn)
-        .await
-        .map_err(|_| {
-            state
-                .metrics()
-                .record_http_request(start, "/movies/add", "POST", 500);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?
-        != 0
-    {
-        tracing::debug!("Duplicate detected: {}", &redis_key);
-        state
-            .metrics()
-            .record_http_request(start, "/movies/add", "POST", 409);
-        return Err(StatusCode::CONFLICT);
-    }
+    let stored = result?;
 
-    tracing::debug!("Inserting new movie, key:{redis_key}");
-
-    // Insert new movie
-    let serialized = serde_json::to_string(&movie).map_err(|_| {
-        state
-            .metrics()
-            .record_http_request(start, "/movies/add", "POST", 500);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
-
-    redis::cmd("SET")
-        .arg(&redis_key)
-        .arg(&serialized)
-        .query_async::<()>(&mut conn)
-        .await
-        .map_err(|_| {
-            state
-                .metrics()
-                .record_http_request(start, "/movies/add", "POST", 500);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-
-    // Record successful movie creation
-    state.metrics().record_movie_created();
-    state
-        .metrics()
-        .record_http_request(start, "/movies/add", "POST", 201);
+    let location = format!("{}/movies/get/{}", state.api_v1_prefix(), stored.id);
+    let body = ApiResponse::new(stored).with_meta(ResponseMeta::new(&headers, start));
 
-    Ok((StatusCode::CREATED, Json(CreatedResponse { id: redis_key })))
+    Ok((StatusCode::CREATED, [(header::LOCATION, location)], body))
 }
 
 /// Handler for updating an existing movie entry (PUT /update/{id}).
@@ -266,46 +272,31 @@ pub async fn add_movie(
 ///
 /// - Always overwrites any existing movie with the provided ID.
 /// - Responds with `200 OK` regardless of whether the movie previously existed.
+/// - Responds with `409 Conflict` if a different movie already has the new
+///   title and year.
 ///
 /// This endpoint allows overwriting or creating movies freely.
 #[tracing::instrument(skip(state, movie))]
 pub async fn update_movie(
     State(state): State<AppState>,
     Path(id): Path<String>,
-    Json(mut movie): Json<Movie>,
-) -> Result<StatusCode, StatusCode> {
+    Json(movie): Json<Movie>,
+) -> Result<StatusCode, MovieError> {
     // ---
 
     let start = Instant::now();
 
-    movie.sanitize().inspect_err(|_err| {
-        state
-            .metrics()
-            .record_http_request(start, "/movies/update", "PUT", 400);
-    })?;
-
-    let mut conn = state.get_conn().await.inspect_err(|_err| {
-        state
-            .metrics()
-            .record_http_request(start, "/movies/update", "PUT", 500);
-    })?;
-
-    let result = save_movie(&mut conn, &id, &movie, true).await;
-
-    match &result {
-        Ok(status) => {
-            state
-                .metrics()
-                .record_http_request(start, "/movies/update", "PUT", status.as_u16());
-        }
-        Err(status) => {
-            state
-                .metrics()
-                .record_http_request(start, "/movies/update", "PUT", status.as_u16());
-        }
-    }
+    let result = MovieService::new(state.clone()).update(&id, movie).await;
+    record(
+        &state,
+        start,
+        "/movies/update",
+        "PUT",
+        &result,
+        StatusCode::OK,
+    );
 
-    result
+    result.map(|()| StatusCode::OK)
 }
 
 /// Delete a movie from the Redis database by its ID.
@@ -324,107 +315,20 @@ pub async fn update_movie(
 pub async fn delete_movie(
     State(state): State<AppState>,
     Path(id): Path<String>,
-) -> Result<StatusCode, StatusCode> {
+) -> Result<StatusCode, MovieError> {
     // ---
 
     let start = Instant::now();
 
-    let mut conn = state.get_conn().await.inspect_err(|_err| {
-        state
-            .metrics()
-            .record_http_request(start, "/movies/delete", "DELETE", 500);
-    })?;
-
-    let deleted: u64 = conn.del(&id).await.map_err(|_| {
-        state
-            .metrics()
-            .record_http_request(start, "/movies/delete", "DELETE", 500);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
-
-    if deleted == 0 {
-        state
-            .metrics()
-            .record_http_request(start, "/movies/delete", "DELETE", 404);
-        Err(StatusCode::NOT_FOUND)
-    } else {
-        state
-            .metrics()
-            .record_http_request(start, "/movies/delete", "DELETE", 204);
-        Ok(StatusCode::NO_CONTENT)
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    // ---
-
-    use super::*;
-    use axum::http::StatusCode;
-
-    fn sanitize_ok(title: &str, year: u16, stars: f32) -> HashKey {
-        let mut movie = Movie {
-            title: title.to_string(),
-            year,
-            stars,
-        };
-        movie.sanitize().expect("Expected sanitize to succeed")
-    }
-
-    fn sanitize_err(title: &str, year: u16, stars: f32) -> StatusCode {
-        let mut movie = Movie {
-            title: title.to_string(),
-            year,
-            stars,
-        };
-        movie.sanitize().unwrap_err()
-    }
-
-    #[test]
-    fn test_normal_title_sanitization() {
-        let key = sanitize_ok("The Shawshank Redemption", 1994, 4.5);
-        assert_eq!(key.value.len(), 40); // SHA1 hex = 40 characters
-    }
-
-    #[test]
-    fn test_title_with_extra_spaces() {
-        let key = sanitize_ok(" The    Shawshank    Redemption ", 1994, 4.5);
-        let key2 = sanitize_ok("The Shawshank Redemption", 1994, 4.5);
-        assert_eq!(
-            key.value, key2.value,
-            "Key must be the same after collapsing spaces"
-        );
-    }
-
-    #[test]
-    fn test_title_mixed_case() {
-        let key = sanitize_ok("The SHAWshank Redemption", 1994, 4.5);
-        let key2 = sanitize_ok("the shawshank redemption", 1994, 4.5);
-        assert_eq!(key.value, key2.value, "Key must be case-insensitive");
-    }
-
-    #[test]
-    fn test_empty_title_rejected() {
-        let status = sanitize_err("   ", 1994, 4.5);
-        assert_eq!(status, StatusCode::BAD_REQUEST);
-    }
-
-    #[test]
-    fn test_bad_year_rejected() {
-        let status = sanitize_err("Test Movie", 1700, 4.5);
-        assert_eq!(status, StatusCode::BAD_REQUEST);
-
-        let future_year = (chrono::Utc::now().year() as u16) + 10;
-        let status = sanitize_err("Test Movie", future_year, 4.5);
-        assert_eq!(status, StatusCode::BAD_REQUEST);
-    }
-
-    #[test]
-    fn test_invalid_stars_rejected() {
-        let status = sanitize_err("Test Movie", 1994, -1.0);
-        assert_eq!(status, StatusCode::BAD_REQUEST);
+    let result = MovieService::new(state.clone()).delete(&id).await;
+    record(
+        &state,
+        start,
+        "/movies/delete",
+        "DELETE",
+        &result,
+        StatusCode::NO_CONTENT,
+    );
 
-        let status = sanitize_err("Test Movie", 1994, 6.0);
-        assert_eq!(status, StatusCode::BAD_REQUEST);
-    }
+    result.map(|()| StatusCode::NO_CONTENT)
 }
@@ -0,0 +1,124 @@
+//! Password authentication handlers (`--features password`).
+//!
+//! A fallback for deployments migrating to passkeys:
+//! 1. `password_register` - Create a user who signs in with a password
+//! 2. `password_login` - Verify the password and create a session token
+//!
+//! Sessions are the same as after a passkey sign-in, so `/webauthn/logout`
+//! and the session-authenticated routes work with either. Hashing and the
+//! password policy are handled by [`PasswordService`].
+
+use super::ApiError;
+use crate::app_state::AppState;
+use crate::application::{PasswordService, ServiceError};
+use crate::session::ClientFingerprint;
+use crate::tenant::Tenant;
+use axum::{extract::State, http::StatusCode, Json};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+// ============================================================================
+// Request/Response Types
+// ============================================================================
+
+/// Body of both password endpoints. Deliberately not `Debug`, so the
+/// password cannot end up in a log line.
+#[derive(Deserialize)]
+pub struct PasswordRequest {
+    //
+    pub username: String,
+    pub password: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PasswordRegisterResponse {
+    //
+    pub user_id: Uuid,
+    pub success: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PasswordLoginResponse {
+    //
+    pub session_token: String,
+    pub success: bool,
+}
+
+// ============================================================================
+// Helper Functions
+// ============================================================================
+
+/// The response for a failed password request, with a code by status.
+fn reject(err: ServiceError) -> (StatusCode, Json<ApiError>) {
+    // ---
+    let code = match err.status {
+        StatusCode::BAD_REQUEST => "invalid_password",
+        StatusCode::UNAUTHORIZED => "authentication_failed",
+        StatusCode::CONFLICT => "username_taken",
+        StatusCode::SERVICE_UNAVAILABLE => "service_unavailable",
+        _ => "internal_error",
+    };
+    (err.status, Json(ApiError::new(code, err.message)))
+}
+
+// ============================================================================
+// Handlers
+// ============================================================================
+
+/// POST /auth/password/register
+///
+/// Creates a user who signs in with a password. Responds 201 with the new
+/// user's ID; sign in with [`password_login`] afterwards.
+///
+/// # Errors
+/// - 400 (`invalid_password`) if the password breaks the password policy
+///   (`AXUM_PASSWORD_*`)
+/// - 409 (`username_taken`) if the username is taken, by a password or a
+///   passkey user
+/// - 500 if the database fails
+pub async fn password_register(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Json(req): Json<PasswordRequest>,
+) -> Result<(StatusCode, Json<PasswordRegisterResponse>), (StatusCode, Json<ApiError>)> {
+    // ---
+    let user_id = PasswordService::new(state)
+        .register(&tenant, &req.username, &req.password)
+        .await
+        .map_err(reject)?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(PasswordRegisterResponse {
+            user_id,
+            success: true,
+        }),
+    ))
+}
+
+/// POST /auth/password/login
+///
+/// Verifies the password and returns a session token, as
+/// `/webauthn/auth/finish` does for passkeys.
+///
+/// # Errors
+/// - 401 (`authentication_failed`) for an unknown user, a user without a
+///   password, or a wrong password; the same body in every case
+/// - 500 if the database or Redis fails
+pub async fn password_login(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    client: ClientFingerprint,
+    Json(req): Json<PasswordRequest>,
+) -> Result<Json<PasswordLoginResponse>, (StatusCode, Json<ApiError>)> {
+    // ---
+    let session_token = PasswordService::new(state)
+        .login(&tenant, &req.username, &req.password, &client)
+        .await
+        .map_err(reject)?;
+
+    Ok(Json(PasswordLoginResponse {
+        session_token,
+        success: true,
+    }))
+}
@@ -0,0 +1,193 @@
+//! CPU profiling for diagnosing latency in a running server.
+//!
+//! Only built with the `pprof` feature. Like the admin endpoints, it
+//! requires `AXUM_ADMIN_TOKEN` and honours the operator IP lists.
+//!
+//! 1. `pprof_profile` - GET /debug/pprof/profile?seconds=N&format=flamegraph|pprof
+
+use super::admin::{require_admin, ErrorResponse};
+use crate::app_state::AppState;
+use crate::deadline::Deadline;
+use axum::{
+    extract::{Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use pprof::protos::Message;
+use serde::Deserialize;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+type HandlerError = (StatusCode, Json<ErrorResponse>);
+
+/// Samples per second, prime so sampling does not line up with periodic work.
+const SAMPLE_FREQUENCY: i32 = 99;
+
+const DEFAULT_SECONDS: u64 = 10;
+const MAX_SECONDS: u64 = 60;
+
+/// Only one profiler can run per process.
+static PROFILING: Mutex<()> = Mutex::const_new(());
+
+// ============================================================================
+// Request Types
+// ============================================================================
+
+/// Output format for a profile.
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProfileFormat {
+    // ---
+    /// Interactive flamegraph SVG. The default.
+    #[default]
+    Flamegraph,
+
+    /// Uncompressed pprof protobuf, for `go tool pprof` and similar tools.
+    Pprof,
+}
+
+// ---
+
+/// Query parameters for a profile.
+#[derive(Debug, Default, Deserialize)]
+pub struct ProfileQuery {
+    // ---
+    /// How long to sample, 1 to 60 seconds. Defaults to 10.
+    pub seconds: Option<u64>,
+
+    #[serde(default)]
+    pub format: ProfileFormat,
+}
+
+// ============================================================================
+// Helper Functions
+// ============================================================================
+
+fn error(status: StatusCode, message: &str) -> HandlerError {
+    // ---
+    (
+        status,
+        Json(ErrorResponse {
+            error: message.to_string(),
+        }),
+    )
+}
+
+fn profile_error(e: impl std::fmt::Display) -> HandlerError {
+    // ---
+    tracing::error!("CPU profile failed: {e}");
+    error(StatusCode::INTERNAL_SERVER_ERROR, "Internal server error")
+}
+
+/// Samples every thread for `duration` and renders the report.
+///
+/// Blocks the calling thread; run it with `spawn_blocking`.
+fn capture(duration: Duration, format: ProfileFormat) -> Result<Vec<u8>, HandlerError> {
+    // ---
+    let guard = pprof::ProfilerGuardBuilder::default()
+        .frequency(SAMPLE_FREQUENCY)
+        .build()
+        .map_err(profile_error)?;
+    std::thread::sleep(duration);
+    let report = guard.report().build().map_err(profile_error)?;
+
+    let mut body = Vec::new();
+    match format {
+        ProfileFormat::Flamegraph => report.flamegraph(&mut body).map_err(profile_error)?,
+        ProfileFormat::Pprof => report
+            .pprof()
+            .map_err(profile_error)?
+            .encode(&mut body)
+            .map_err(profile_error)?,
+    }
+    Ok(body)
+}
+
+// ============================================================================
+// Handlers
+// ============================================================================
+
+/// GET /debug/pprof/profile
+///
+/// Samples the CPU for `seconds` and returns the profile as a flamegraph
+/// SVG or, with `format=pprof`, a pprof protobuf. An idle server may
+/// collect no samples, in which case the flamegraph response is
+/// 204 No Content.
+///
+/// # Request Headers
+/// ```text
+/// Authorization: Bearer <AXUM_ADMIN_TOKEN>
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The admin API is disabled (403 Forbidden)
+/// - The admin token is missing or wrong (401 Unauthorized)
+/// - `seconds` is out of range or longer than the request timeout allows
+///   (400 Bad Request)
+/// - Another profile is already running (409 Conflict)
+pub async fn pprof_profile(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<ProfileQuery>,
+) -> Result<Response, HandlerError> {
+    // ---
+    require_admin(&headers, &state)?;
+
+    let seconds = query.seconds.unwrap_or(DEFAULT_SECONDS);
+    if !(1..=MAX_SECONDS).contains(&seconds) {
+        return Err(error(
+            StatusCode::BAD_REQUEST,
+            "seconds must be between 1 and 60",
+        ));
+    }
+    let duration = Duration::from_secs(seconds);
+
+    // Leave time to render the report before AXUM_REQUEST_TIMEOUT_SEC
+    if Deadline::current().is_some_and(|deadline| deadline.remaining() <= duration) {
+        return Err(error(
+            StatusCode::BAD_REQUEST,
+            "seconds must be shorter than the request timeout",
+        ));
+    }
+
+    let Ok(running) = PROFILING.try_lock() else {
+        return Err(error(
+            StatusCode::CONFLICT,
+            "A profile is already being captured",
+        ));
+    };
+
+    tracing::info!("Capturing {seconds}s CPU profile ({:?})", query.format);
+    // The lock moves with the capture, which outlives a dropped request
+    let body = tokio::task::spawn_blocking(move || {
+        let _running = running;
+        capture(duration, query.format)
+    })
+    .await
+    .map_err(profile_error)??;
+
+    if body.is_empty() {
+        return Ok(StatusCode::NO_CONTENT.into_response());
+    }
+
+    let response = match query.format {
+        ProfileFormat::Flamegraph => {
+            ([(header::CONTENT_TYPE, "image/svg+xml")], body).into_response()
+        }
+        ProfileFormat::Pprof => (
+            [
+                (header::CONTENT_TYPE, "application/octet-stream"),
+                (
+                    header::CONTENT_DISPOSITION,
+                    "attachment; filename=\"profile.pb\"",
+                ),
+            ],
+            body,
+        )
+            .into_response(),
+    };
+    Ok(response)
+}
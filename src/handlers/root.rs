@@ -1,24 +1,109 @@
+use super::{vary_on_accept, ApiResponse, Format, Negotiated, ResponseMeta};
+use crate::route_registry::{route_table, RouteAuth, RouteInfo};
 use crate::AppState;
 use axum::{
     extract::State,
-    response::{Html, IntoResponse},
+    http::{header, HeaderMap},
+    response::{Html, IntoResponse, Response},
 };
+use serde::Serialize;
+use std::fmt::Write;
 use std::time::Instant;
 
+/// What the service is, on the landing page and in its JSON variant.
+const DESCRIPTION: &str = "Rust Movie API demonstrating clean architecture, observability, \
+CRUD operations, and WebAuthn passwordless authentication.";
+
+/// The landing page as data, for clients that ask for JSON.
+#[derive(Debug, Serialize)]
+pub struct ServiceInfo {
+    // ---
+    pub name: &'static str,
+    pub version: &'static str,
+    pub description: &'static str,
+
+    /// Prefix of API version 1, below the base path if one is configured.
+    pub api_prefix: String,
+
+    /// Every route outside the admin API, from the route registry.
+    pub routes: Vec<RouteInfo>,
+}
+
 /// Handler for the root endpoint (GET /).
 ///
 /// Returns an HTML page with information about the API, including:
 /// - Application version from Cargo.toml
-/// - List of available endpoints
+/// - The endpoints outside the admin API, generated from the
+///   [route registry](crate::route_table), below the base path if one is
+///   configured
 /// - Basic styling for a clean presentation
 ///
 /// This serves as both a landing page and API documentation for users
-/// accessing the service through a web browser.
-pub async fn root_handler(State(state): State<AppState>) -> impl IntoResponse {
+/// accessing the service through a web browser. Clients whose `Accept`
+/// header prefers `application/json` get the same content as a
+/// [`ServiceInfo`]; without an `Accept` header the page is HTML.
+pub async fn root_handler(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    // ---
     let start = Instant::now();
+    let format = match headers.contains_key(header::ACCEPT) {
+        true => Format::negotiate(&headers, &[Format::Html, Format::Json]),
+        false => Format::Html,
+    };
+
+    let routes: Vec<RouteInfo> = route_table(state.base_path())
+        .routes
+        .into_iter()
+        .filter(|route| route.auth != RouteAuth::Admin)
+        .collect();
+
+    let response = match format {
+        Format::Html => {
+            let mut response = landing_page(&state, &routes).into_response();
+            vary_on_accept(&mut response);
+            response
+        }
+        _ => {
+            let info = ServiceInfo {
+                name: env!("CARGO_PKG_NAME"),
+                version: env!("CARGO_PKG_VERSION"),
+                description: DESCRIPTION,
+                api_prefix: state.api_v1_prefix(),
+                routes,
+            };
+            let body = ApiResponse::new(info).with_meta(ResponseMeta::new(&headers, start));
+            Negotiated::new(Format::Json, body).into_response()
+        }
+    };
+
+    // Record metrics for the root handler
+    state.metrics().record_http_request(start, "/", "GET", 200);
+
+    response
+}
+
+fn landing_page(state: &AppState, routes: &[RouteInfo]) -> Html<String> {
+    // ---
     let version = env!("CARGO_PKG_VERSION");
+    let (base, api) = (state.base_path(), state.api_v1_prefix());
 
-    let html = Html(format!(
+    let mut rows = String::new();
+    for route in routes {
+        let query = match route.query {
+            [] => String::new(),
+            params => format!("?{}", params.join("&")),
+        };
+        let _ = writeln!(
+            rows,
+            "      <tr><td>{}</td><td><code>{}{}</code></td><td>{}</td><td>{}</td></tr>",
+            route.method,
+            escape(&route.path),
+            escape(&query),
+            route.auth.as_str(),
+            escape(route.summary),
+        );
+    }
+
+    Html(format!(
         r#"
 <!DOCTYPE html>
 <html lang="en">
@@ -56,6 +141,15 @@ pub async fn root_handler(State(state): State<AppState>) -> impl IntoResponse {
     code {{
       font-family: monospace;
     }}
+    table {{
+      border-collapse: collapse;
+      width: 100%;
+    }}
+    th, td {{
+      text-align: left;
+      padding: 0.25em 0.75em 0.25em 0;
+      vertical-align: top;
+    }}
   </style>
 </head>
 <body>
@@ -63,40 +157,27 @@ pub async fn root_handler(State(state): State<AppState>) -> impl IntoResponse {
     <h1>AXUM Quickstart — Movie API 👋</h1>
     <p class="version">Version: {version}</p>
     <p>
-      Rust Movie API demonstrating clean architecture, observability,
-      CRUD operations, and WebAuthn passwordless authentication.
+      {DESCRIPTION}
     </p>
-    <pre><code>
-Available endpoints:
-
-Core:
-  - GET    /                            This landing page
-  - GET    /health                      Light health check
-  - GET    /health?mode=full            Full health check (includes Redis)
-  - GET    /metrics                     Prometheus metrics endpoint
-
-Movies (CRUD):
-  - GET    /movies/get/{{id}}             Fetch a movie by ID
-  - POST   /movies/add                  Add a new movie entry
-  - PUT    /movies/update/{{id}}          Update a movie entry by ID
-  - DELETE /movies/delete/{{id}}          Delete a movie entry by ID
-
-WebAuthn (Passwordless Auth):
-  - POST   /webauthn/register/start     Begin passkey registration
-  - POST   /webauthn/register/finish    Complete passkey registration
-  - POST   /webauthn/auth/start         Begin passkey authentication
-  - POST   /webauthn/auth/finish        Complete passkey authentication
-  - GET    /webauthn/credentials        List registered passkeys
-  - DELETE /webauthn/credentials/{{id}}   Delete a passkey
-    </code></pre>
+    <p>
+      API version 1 is served below <code>{api}</code>. Legacy unversioned
+      paths (e.g. <code>{base}/movies/add</code>) still work but are
+      deprecated and respond with a <code>Deprecation</code> header.
+    </p>
+    <table>
+      <tr><th>Method</th><th>Path</th><th>Credentials</th><th>Description</th></tr>
+{rows}    </table>
   </div>
 </body>
 </html>
 "#
-    ));
-
-    // Record metrics for the root handler
-    state.metrics().record_http_request(start, "/", "GET", 200);
+    ))
+}
 
-    html
+/// Escapes text for HTML element content.
+fn escape(text: &str) -> String {
+    // ---
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
 }
@@ -1,12 +1,53 @@
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
 use axum::response::{IntoResponse, Response};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+use uuid::Uuid;
 
 /// Wrapper type for successful API responses.
 ///
 /// Encapsulates the data payload and prepares it for JSON serialization.
-#[derive(Serialize)]
+/// This is the single envelope definition for every JSON success body, so
+/// `meta` and `links` look the same wherever they appear. Both are omitted
+/// from the serialized output when not set, keeping simple responses as
+/// `{ "data": ... }`. Clients can read it back (`Deserialize`).
+#[derive(Serialize, Deserialize)]
 pub struct ApiResponse<T> {
+    // ---
     pub data: T,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub meta: Option<ResponseMeta>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub links: Option<ResponseLinks>,
+}
+
+impl<T> ApiResponse<T> {
+    // ---
+    /// Wraps `data` with no metadata or links.
+    pub fn new(data: T) -> Self {
+        // ---
+        Self {
+            data,
+            meta: None,
+            links: None,
+        }
+    }
+
+    /// Attaches response metadata.
+    pub fn with_meta(mut self, meta: ResponseMeta) -> Self {
+        // ---
+        self.meta = Some(meta);
+        self
+    }
+
+    /// Attaches navigation links.
+    pub fn with_links(mut self, links: ResponseLinks) -> Self {
+        // ---
+        self.links = Some(links);
+        self
+    }
 }
 
 impl<T> IntoResponse for ApiResponse<T>
@@ -17,3 +58,365 @@ where
         axum::Json(self).into_response()
     }
 }
+
+/// Per-response metadata carried in the `meta` field of the envelope.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ResponseMeta {
+    // ---
+    /// Correlation ID for this request (echoed from `X-Request-Id` when supplied).
+    pub request_id: String,
+
+    /// Server-side handling time in milliseconds, measured up to envelope creation.
+    pub elapsed_ms: u64,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pagination: Option<Pagination>,
+}
+
+impl ResponseMeta {
+    // ---
+    /// Builds metadata for a request that started at `start`.
+    ///
+    /// The request ID is taken from the `X-Request-Id` header if the client
+    /// (or an upstream proxy) supplied one; otherwise a fresh UUID is issued.
+    pub fn new(headers: &HeaderMap, start: Instant) -> Self {
+        // ---
+        Self {
+            request_id: request_id(headers),
+            elapsed_ms: start.elapsed().as_millis() as u64,
+            pagination: None,
+        }
+    }
+
+    /// Attaches pagination details.
+    pub fn with_pagination(mut self, pagination: Pagination) -> Self {
+        // ---
+        self.pagination = Some(pagination);
+        self
+    }
+}
+
+/// Pagination details for list responses.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Pagination {
+    // ---
+    /// 1-based page number of this response.
+    pub page: u32,
+
+    /// Maximum number of items per page.
+    pub per_page: u32,
+
+    /// Total number of items across all pages.
+    pub total: u64,
+}
+
+/// Navigation links carried in the `links` field of the envelope.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResponseLinks {
+    // ---
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prev: Option<String>,
+}
+
+/// Body of an error response: a human-readable message and a stable,
+/// machine-readable code for clients to branch on.
+///
+/// `error` matches the older per-handler error bodies, so clients that only
+/// read the message keep working.
+#[derive(Debug, Serialize)]
+pub struct ApiError {
+    // ---
+    pub error: String,
+
+    /// Snake-case identifier such as `movie_not_found`.
+    pub code: &'static str,
+}
+
+impl ApiError {
+    // ---
+    pub fn new(code: &'static str, error: impl Into<String>) -> Self {
+        // ---
+        Self {
+            error: error.into(),
+            code,
+        }
+    }
+}
+
+/// Response body formats a client can ask for with the `Accept` header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    // ---
+    /// `application/json`, the default.
+    Json,
+
+    /// `application/msgpack`: the same envelope as JSON, in MessagePack.
+    MsgPack,
+
+    /// `text/csv`: one row per item, for lists.
+    Csv,
+
+    /// `text/html`: pages for browsers, offered only by the landing page.
+    Html,
+}
+
+impl Format {
+    // ---
+    fn media_type(self) -> &'static str {
+        // ---
+        match self {
+            Self::Json => "application/json",
+            Self::MsgPack => "application/msgpack",
+            Self::Csv => "text/csv",
+            Self::Html => "text/html",
+        }
+    }
+
+    /// Picks the format of `offered` that `Accept` prefers: the highest
+    /// `q`, then the earliest in `offered`. Each format takes the `q` of
+    /// the most specific media range matching it (`type/subtype`, then
+    /// `type/*`, then `*/*`). Without an `Accept` header, or if it accepts
+    /// none of `offered`, the answer is [`Format::Json`].
+    pub fn negotiate(headers: &HeaderMap, offered: &[Format]) -> Format {
+        // ---
+        let Some(accept) = headers
+            .get(header::ACCEPT)
+            .and_then(|value| value.to_str().ok())
+        else {
+            return Format::Json;
+        };
+
+        let ranges: Vec<(&str, f32)> = accept
+            .split(',')
+            .filter_map(|range| {
+                let mut parts = range.split(';').map(str::trim);
+                let media_range = parts.next().filter(|m| !m.is_empty())?;
+                let q = parts
+                    .filter_map(|param| param.strip_prefix("q="))
+                    .find_map(|q| q.parse::<f32>().ok())
+                    .unwrap_or(1.0);
+                Some((media_range, q))
+            })
+            .collect();
+
+        let quality = |format: Format| {
+            let media_type = format.media_type();
+            let (kind, _) = media_type.split_once('/').unwrap_or_default();
+            let specificity = |range: &str| match range {
+                _ if range.eq_ignore_ascii_case(media_type) => Some(3),
+                "*/*" => Some(1),
+                _ => match range.split_once('/') {
+                    Some((range_kind, "*")) if range_kind.eq_ignore_ascii_case(kind) => Some(2),
+                    _ => None,
+                },
+            };
+            ranges
+                .iter()
+                .filter_map(|&(range, q)| specificity(range).map(|s| (s, q)))
+                .max_by_key(|&(specificity, _)| specificity)
+                .map_or(0.0, |(_, q)| q)
+        };
+
+        let mut best = (Format::Json, 0.0);
+        for &format in offered {
+            let q = quality(format);
+            if q > best.1 {
+                best = (format, q);
+            }
+        }
+        best.0
+    }
+}
+
+/// An [`ApiResponse`] written as JSON or MessagePack, as negotiated with
+/// [`Format::negotiate`]. [`Format::Csv`] and [`Format::Html`] have no
+/// envelope; handlers that offer them answer with a [`CsvBody`] or a page
+/// instead, and here they fall back to JSON.
+pub struct Negotiated<T> {
+    // ---
+    format: Format,
+    body: ApiResponse<T>,
+}
+
+impl<T> Negotiated<T> {
+    // ---
+    pub fn new(format: Format, body: ApiResponse<T>) -> Self {
+        // ---
+        Self { format, body }
+    }
+}
+
+impl<T> IntoResponse for Negotiated<T>
+where
+    T: Serialize,
+{
+    fn into_response(self) -> Response {
+        // ---
+        let mut response = match self.format {
+            Format::MsgPack => match rmp_serde::to_vec_named(&self.body) {
+                Ok(bytes) => (
+                    [(header::CONTENT_TYPE, Format::MsgPack.media_type())],
+                    bytes,
+                )
+                    .into_response(),
+                Err(e) => serialization_failed(Format::MsgPack, e),
+            },
+            Format::Json | Format::Csv | Format::Html => self.body.into_response(),
+        };
+        vary_on_accept(&mut response);
+        response
+    }
+}
+
+/// Rows written as `text/csv` with a header line taken from the field
+/// names of `R`, which must be flat (no nested structs or lists).
+pub struct CsvBody<R>(pub Vec<R>);
+
+impl<R> IntoResponse for CsvBody<R>
+where
+    R: Serialize,
+{
+    fn into_response(self) -> Response {
+        // ---
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        let written = self
+            .0
+            .iter()
+            .try_for_each(|row| writer.serialize(row))
+            .map_err(anyhow::Error::from)
+            .and_then(|()| writer.into_inner().map_err(|e| anyhow::anyhow!("{e}")));
+
+        let mut response = match written {
+            Ok(bytes) => {
+                ([(header::CONTENT_TYPE, "text/csv; charset=utf-8")], bytes).into_response()
+            }
+            Err(e) => serialization_failed(Format::Csv, e),
+        };
+        vary_on_accept(&mut response);
+        response
+    }
+}
+
+/// Responses that depend on `Accept` say so, for caches.
+pub(crate) fn vary_on_accept(response: &mut Response) {
+    // ---
+    response
+        .headers_mut()
+        .append(header::VARY, HeaderValue::from_static("accept"));
+}
+
+fn serialization_failed(format: Format, err: impl std::fmt::Display) -> Response {
+    // ---
+    tracing::error!("Failed to write {} response: {err}", format.media_type());
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        axum::Json(ApiError::new("internal_error", "Internal server error")),
+    )
+        .into_response()
+}
+
+/// Returns the caller-supplied `X-Request-Id`, or a new UUID if absent or not valid UTF-8.
+pub(crate) fn request_id(headers: &HeaderMap) -> String {
+    // ---
+    headers
+        .get("x-request-id")
+        .and_then(|v| v.to_str().ok())
+        .filter(|v| !v.is_empty())
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+
+    #[test]
+    fn accept_header_negotiates_the_format() {
+        // ---
+        let offered = [Format::Json, Format::MsgPack, Format::Csv];
+        let negotiate = |accept: Option<&'static str>| {
+            let mut headers = HeaderMap::new();
+            if let Some(accept) = accept {
+                headers.insert(header::ACCEPT, HeaderValue::from_static(accept));
+            }
+            Format::negotiate(&headers, &offered)
+        };
+
+        assert_eq!(negotiate(None), Format::Json);
+        assert_eq!(negotiate(Some("*/*")), Format::Json);
+        assert_eq!(negotiate(Some("text/html")), Format::Json);
+        assert_eq!(negotiate(Some("application/msgpack")), Format::MsgPack);
+        assert_eq!(negotiate(Some("Text/CSV; charset=utf-8")), Format::Csv);
+        assert_eq!(negotiate(Some("text/*")), Format::Csv);
+        assert_eq!(
+            negotiate(Some("application/json;q=0.5, text/csv")),
+            Format::Csv
+        );
+        assert_eq!(negotiate(Some("text/csv;q=0.2, */*;q=0.8")), Format::Json);
+        assert_eq!(
+            negotiate(Some("application/*;q=0, text/csv;q=0.1")),
+            Format::Csv
+        );
+
+        // Formats an endpoint does not offer are never chosen
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ACCEPT, HeaderValue::from_static("text/csv"));
+        assert_eq!(
+            Format::negotiate(&headers, &[Format::Json, Format::MsgPack]),
+            Format::Json
+        );
+    }
+
+    #[test]
+    fn envelope_omits_unset_meta_and_links() {
+        // ---
+        let json = serde_json::to_value(ApiResponse::new(42)).unwrap();
+        assert_eq!(json, serde_json::json!({ "data": 42 }));
+    }
+
+    #[test]
+    fn envelope_includes_meta_and_links() {
+        // ---
+        let mut headers = HeaderMap::new();
+        headers.insert("x-request-id", HeaderValue::from_static("req-123"));
+
+        let meta = ResponseMeta::new(&headers, Instant::now()).with_pagination(Pagination {
+            page: 2,
+            per_page: 10,
+            total: 35,
+        });
+        let links = ResponseLinks {
+            next: Some("/items?page=3".to_string()),
+            prev: Some("/items?page=1".to_string()),
+        };
+
+        let json =
+            serde_json::to_value(ApiResponse::new("x").with_meta(meta).with_links(links)).unwrap();
+
+        assert_eq!(json["meta"]["request_id"], "req-123");
+        assert_eq!(json["meta"]["pagination"]["total"], 35);
+        assert_eq!(json["links"]["next"], "/items?page=3");
+        assert_eq!(json["links"]["prev"], "/items?page=1");
+    }
+
+    #[test]
+    fn error_carries_message_and_code() {
+        // ---
+        let json = serde_json::to_value(ApiError::new("thing_missing", "Thing not found")).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "error": "Thing not found", "code": "thing_missing" })
+        );
+    }
+
+    #[test]
+    fn request_id_generated_when_missing() {
+        // ---
+        let meta = ResponseMeta::new(&HeaderMap::new(), Instant::now());
+        assert!(Uuid::parse_str(&meta.request_id).is_ok());
+    }
+}
@@ -3,16 +3,24 @@
 //! Implements credential management operations (Phase 4):
 //! 1. `list_credentials` - List all passkeys for authenticated user
 //! 2. `delete_credential` - Remove a specific passkey
+//!
+//! Both call [`CredentialService`].
 
+use super::{ApiResponse, Pagination, ResponseLinks, ResponseMeta};
 use crate::app_state::AppState;
-use crate::session;
+use crate::application::{AuthService, CredentialService, ServiceError};
+use crate::session::{self, ClientFingerprint};
+use crate::tenant::Tenant;
 use axum::{
-    extract::{Path, State},
-    http::{HeaderMap, StatusCode},
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
     Json,
 };
 use base64::Engine;
-use serde::Serialize;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
 
 // ============================================================================
 // Request/Response Types
@@ -27,6 +35,19 @@ pub struct ListCredentialsResponse {
 
 // ---
 
+/// Query parameters for paging through a user's credentials.
+///
+/// Both parameters are optional. `page` is 1-based and defaults to 1;
+/// `per_page` defaults to 50 and is clamped to the range 1..=100.
+#[derive(Debug, Default, Deserialize)]
+pub struct ListCredentialsQuery {
+    // ---
+    pub page: Option<u32>,
+    pub per_page: Option<u32>,
+}
+
+// ---
+
 /// Information about a registered credential (passkey).
 ///
 /// This is a sanitized view of credential data suitable for display to users.
@@ -38,6 +59,46 @@ pub struct CredentialInfo {
     pub id: String,
     /// When this credential was registered
     pub created_at: String,
+    /// When this credential last signed in, if it has since this was recorded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_used_at: Option<String>,
+    /// When a sign-in suggested the authenticator was cloned, if ever
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compromised_at: Option<String>,
+    /// Synced across devices or bound to one authenticator; omitted for
+    /// passkeys registered before this was recorded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<PasskeyKind>,
+    /// Backup eligible (WebAuthn BE flag)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backup_eligible: Option<bool>,
+    /// Currently backed up (WebAuthn BS flag)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backup_state: Option<bool>,
+}
+
+// ---
+
+/// Whether a passkey can leave the authenticator it was created on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PasskeyKind {
+    // ---
+    /// Multi-device passkey, e.g. in iCloud Keychain or Google Password Manager.
+    Synced,
+    /// Single-device passkey, e.g. on a security key.
+    DeviceBound,
+}
+
+impl PasskeyKind {
+    // ---
+    fn from_backup_eligible(backup_eligible: Option<bool>) -> Option<Self> {
+        // ---
+        backup_eligible.map(|eligible| match eligible {
+            true => Self::Synced,
+            false => Self::DeviceBound,
+        })
+    }
 }
 
 // ---
@@ -63,13 +124,22 @@ pub struct ErrorResponse {
 // Helper Functions
 // ============================================================================
 
+/// The response for a failed credential operation.
+fn reject(err: ServiceError) -> (StatusCode, Json<ErrorResponse>) {
+    // ---
+    (err.status, Json(ErrorResponse { error: err.message }))
+}
+
 /// Extracts and validates the session token from Authorization header.
 ///
 /// Expects header format: "Authorization: Bearer <token>"
 ///
 /// # Security
 ///
-/// - Validates token exists in Redis
+/// - Validates the token (Redis lookup or signature check, per session mode)
+/// - Compares `client` with the client the session was created by
+///   (`AXUM_SESSION_FINGERPRINT`)
+/// - Rejects sessions created in another tenant than `tenant`
 /// - Returns authenticated user's ID for authorization checks
 ///
 /// # Errors
@@ -77,10 +147,12 @@ pub struct ErrorResponse {
 /// Returns UNAUTHORIZED if:
 /// - Authorization header is missing
 /// - Header format is invalid (not "Bearer <token>")
-/// - Token is invalid or expired
-async fn extract_session(
+/// - Token is invalid or expired, or was issued by another tenant
+pub(super) async fn extract_session(
     headers: &HeaderMap,
     state: &AppState,
+    client: &ClientFingerprint,
+    tenant: &Tenant,
 ) -> Result<session::SessionInfo, (StatusCode, Json<ErrorResponse>)> {
     // ---
     // Extract Authorization header
@@ -120,25 +192,20 @@ async fn extract_session(
         )
     })?;
 
-    // Validate session with Redis
-    let mut redis_conn = state.get_conn().await.map_err(|status| {
-        // ---
-        (
-            status,
-            Json(ErrorResponse {
-                error: "Internal server error".to_string(),
-            }),
-        )
-    })?;
-
-    session::validate_session(&mut redis_conn, token)
+    // Validate session, checking the client against the one it was issued to
+    AuthService::new(state.clone())
+        .authenticate(token, client, tenant)
         .await
         .map_err(|status| {
             // ---
+            let error = match status.is_server_error() {
+                true => "Internal server error",
+                false => "Invalid or expired session",
+            };
             (
                 status,
                 Json(ErrorResponse {
-                    error: "Invalid or expired session".to_string(),
+                    error: error.to_string(),
                 }),
             )
         })
@@ -166,8 +233,19 @@ async fn extract_session(
 /// Authorization: Bearer <session_token>
 /// ```
 ///
+/// # Query Parameters
+/// - `page` - 1-based page number (default 1)
+/// - `per_page` - Items per page (default 50, max 100)
+///
 /// # Response
-/// Returns a list of credential IDs and creation timestamps.
+/// Returns a page of credential IDs and creation timestamps in the standard
+/// envelope, with pagination details in `meta` and `next`/`prev` links.
+///
+/// # Conditional Requests
+/// The response carries `Last-Modified`: the latest creation, sign-in,
+/// clone flag, or deletion among the user's credentials. When
+/// `If-Modified-Since` is at or after it, the answer is `304 Not Modified`
+/// without a body, so polling clients do not download the list again.
 ///
 /// # Errors
 ///
@@ -177,58 +255,108 @@ async fn extract_session(
 pub async fn list_credentials(
     State(state): State<AppState>,
     headers: HeaderMap,
-) -> Result<Json<ListCredentialsResponse>, (StatusCode, Json<ErrorResponse>)> {
+    client: ClientFingerprint,
+    tenant: Tenant,
+    Query(query): Query<ListCredentialsQuery>,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
     // ---
+    let start = Instant::now();
+
     // Validate session and extract user_id
-    let session_info = extract_session(&headers, &state).await?;
-
-    tracing::info!(
-        "Listing credentials for user: {} ({})",
-        session_info.username,
-        session_info.user_id
-    );
-
-    // Fetch user's credentials from database
-    let credentials = state
-        .repository()
-        .get_credentials_by_user(session_info.user_id)
+    let session_info = extract_session(&headers, &state, &client, &tenant).await?;
+
+    let api = state.api_v1_prefix();
+    let service = CredentialService::new(state);
+    let modified_at = service.modified_at(&session_info).await.map_err(reject)?;
+    if let Some(at) = modified_at.filter(|at| !modified_since(&headers, *at)) {
+        return Ok((
+            StatusCode::NOT_MODIFIED,
+            [(header::LAST_MODIFIED, http_date(at))],
+        )
+            .into_response());
+    }
+
+    let page = service
+        .list(&session_info, query.page, query.per_page)
         .await
-        .map_err(|e| {
-            // ---
-            tracing::error!(
-                "Failed to fetch credentials for user {}: {}",
-                session_info.user_id,
-                e
-            );
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse {
-                    error: "Failed to fetch credentials".to_string(),
-                }),
-            )
-        })?;
+        .map_err(reject)?;
+    let (page_number, per_page, total) = (page.page, page.per_page, page.total);
 
     // Convert to response format (sanitized view)
-    let credential_list: Vec<CredentialInfo> = credentials
+    let credential_list: Vec<CredentialInfo> = page
+        .credentials
         .into_iter()
         .map(|cred| {
             // ---
             CredentialInfo {
                 id: base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(&cred.id),
                 created_at: cred.created_at.to_rfc3339(),
+                last_used_at: cred.last_used_at.map(|at| at.to_rfc3339()),
+                compromised_at: cred.compromised_at.map(|at| at.to_rfc3339()),
+                kind: PasskeyKind::from_backup_eligible(cred.backup_eligible),
+                backup_eligible: cred.backup_eligible,
+                backup_state: cred.backup_state,
             }
         })
         .collect();
 
-    tracing::info!(
-        "Found {} credentials for user: {}",
-        credential_list.len(),
-        session_info.username
-    );
+    let meta = ResponseMeta::new(&headers, start).with_pagination(Pagination {
+        page: page_number,
+        per_page,
+        total,
+    });
+    let links = page_links(&api, page_number, per_page, total);
 
-    Ok(Json(ListCredentialsResponse {
+    let mut response = ApiResponse::new(ListCredentialsResponse {
         credentials: credential_list,
-    }))
+    })
+    .with_meta(meta)
+    .with_links(links)
+    .into_response();
+    if let Some(at) = modified_at {
+        response
+            .headers_mut()
+            .insert(header::LAST_MODIFIED, http_date(at));
+    }
+    Ok(response)
+}
+
+/// `at` as an HTTP-date (`Sun, 06 Nov 1994 08:49:37 GMT`).
+fn http_date(at: DateTime<Utc>) -> HeaderValue {
+    // ---
+    let date = at.format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+    HeaderValue::from_str(&date).expect("HTTP-dates are valid header values")
+}
+
+/// Whether `modified_at` is after the request's `If-Modified-Since`, or
+/// the request has none (or an invalid one). HTTP-dates have whole
+/// seconds, so changes are compared by the second.
+fn modified_since(headers: &HeaderMap, modified_at: DateTime<Utc>) -> bool {
+    // ---
+    let since = headers
+        .get(header::IF_MODIFIED_SINCE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| DateTime::parse_from_rfc2822(value).ok());
+    match since {
+        Some(since) => modified_at.timestamp() > since.timestamp(),
+        None => true,
+    }
+}
+
+/// Builds `next`/`prev` links for a credentials page. `api` is the API
+/// prefix clients see.
+///
+/// `next` is omitted on the last page and `prev` on the first.
+fn page_links(api: &str, page: u32, per_page: u32, total: u64) -> ResponseLinks {
+    // ---
+    let link = |p: u32| format!("{api}/webauthn/credentials?page={p}&per_page={per_page}");
+
+    let has_next = (page as u64).saturating_mul(per_page as u64) < total;
+
+    ResponseLinks {
+        next: has_next.then(|| link(page + 1)),
+        prev: (page > 1).then(|| link(page - 1)),
+    }
 }
 
 // ============================================================================
@@ -240,6 +368,8 @@ pub async fn list_credentials(
 /// Deletes a specific WebAuthn credential (passkey) for the authenticated user.
 ///
 /// This allows users to revoke access for lost devices or remove old authenticators.
+/// The credential stops working immediately but is only soft-deleted; it is
+/// permanently removed by the admin purge once the retention window passes.
 ///
 /// # Security
 ///
@@ -266,99 +396,81 @@ pub async fn list_credentials(
 pub async fn delete_credential(
     State(state): State<AppState>,
     headers: HeaderMap,
+    client: ClientFingerprint,
+    tenant: Tenant,
     Path(credential_id_base64): Path<String>,
 ) -> Result<Json<DeleteCredentialResponse>, (StatusCode, Json<ErrorResponse>)> {
     // ---
     // Validate session and extract user_id
-    let session_info = extract_session(&headers, &state).await?;
-
-    tracing::info!(
-        "Deleting credential {} for user: {} ({})",
-        credential_id_base64,
-        session_info.username,
-        session_info.user_id
-    );
-
-    // Decode credential ID from base64
-    let credential_id = base64::engine::general_purpose::URL_SAFE_NO_PAD
-        .decode(&credential_id_base64)
-        .map_err(|e| {
-            // ---
-            tracing::warn!("Invalid base64 credential ID: {}", e);
-            (
-                StatusCode::BAD_REQUEST,
-                Json(ErrorResponse {
-                    error: "Invalid credential ID format".to_string(),
-                }),
-            )
-        })?;
+    let session_info = extract_session(&headers, &state, &client, &tenant).await?;
 
-    // Verify credential exists and belongs to this user
-    let credential = state
-        .repository()
-        .get_credential_by_id(&credential_id)
+    CredentialService::new(state)
+        .delete(&session_info, &credential_id_base64)
         .await
-        .map_err(|e| {
-            // ---
-            tracing::error!("Failed to query credential: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse {
-                    error: "Failed to query credential".to_string(),
-                }),
-            )
-        })?
-        .ok_or_else(|| {
-            // ---
-            tracing::warn!("Credential not found: {}", credential_id_base64);
-            (
-                StatusCode::NOT_FOUND,
-                Json(ErrorResponse {
-                    error: "Credential not found".to_string(),
-                }),
-            )
-        })?;
+        .map_err(reject)?;
 
-    // Verify ownership (prevent deletion of other users' credentials)
-    if credential.user_id != session_info.user_id {
+    Ok(Json(DeleteCredentialResponse {
+        success: true,
+        message: "Credential deleted successfully".to_string(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+
+    #[test]
+    fn page_links_first_middle_last() {
         // ---
-        tracing::warn!(
-            "User {} attempted to delete credential belonging to user {}",
-            session_info.user_id,
-            credential.user_id
+        let first = page_links(crate::API_V1_PREFIX, 1, 10, 25);
+        assert_eq!(
+            first.next.as_deref(),
+            Some("/api/v1/webauthn/credentials?page=2&per_page=10")
         );
-        return Err((
-            StatusCode::FORBIDDEN,
-            Json(ErrorResponse {
-                error: "Cannot delete credential belonging to another user".to_string(),
-            }),
-        ));
-    }
+        assert!(first.prev.is_none());
 
-    // Delete credential from database
-    state
-        .repository()
-        .delete_credential(&credential_id)
-        .await
-        .map_err(|e| {
-            // ---
-            tracing::error!("Failed to delete credential: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse {
-                    error: "Failed to delete credential".to_string(),
-                }),
-            )
-        })?;
+        let middle = page_links(crate::API_V1_PREFIX, 2, 10, 25);
+        assert!(middle.next.is_some());
+        assert_eq!(
+            middle.prev.as_deref(),
+            Some("/api/v1/webauthn/credentials?page=1&per_page=10")
+        );
 
-    tracing::info!(
-        "Successfully deleted credential {} for user {}",
-        credential_id_base64,
-        session_info.username
-    );
+        let last = page_links(crate::API_V1_PREFIX, 3, 10, 25);
+        assert!(last.next.is_none());
+        assert!(last.prev.is_some());
 
-    Ok(Json(DeleteCredentialResponse {
-        success: true,
-        message: "Credential deleted successfully".to_string(),
-    }))
+        // Behind a base path the links keep it
+        let prefixed = page_links("/edge/api/v1", 1, 10, 25);
+        assert_eq!(
+            prefixed.next.as_deref(),
+            Some("/edge/api/v1/webauthn/credentials?page=2&per_page=10")
+        );
+    }
+
+    #[test]
+    fn page_links_exact_fit_has_no_next() {
+        // ---
+        let links = page_links(crate::API_V1_PREFIX, 2, 10, 20);
+        assert!(links.next.is_none());
+    }
+
+    #[test]
+    fn passkey_kind_follows_backup_eligibility() {
+        // ---
+        assert_eq!(
+            PasskeyKind::from_backup_eligible(Some(true)),
+            Some(PasskeyKind::Synced)
+        );
+        assert_eq!(
+            PasskeyKind::from_backup_eligible(Some(false)),
+            Some(PasskeyKind::DeviceBound)
+        );
+        assert_eq!(PasskeyKind::from_backup_eligible(None), None);
+        assert_eq!(
+            serde_json::to_value(PasskeyKind::DeviceBound).unwrap(),
+            "device_bound"
+        );
+    }
 }
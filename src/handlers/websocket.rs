@@ -0,0 +1,209 @@
+//! WebSocket endpoint for per-user notifications.
+//!
+//! 1. `ws_handler` - GET /ws (upgrade)
+//!
+//! An example long-lived subsystem built on the same session auth and event
+//! bus as the rest of the API:
+//!
+//! - Text and binary frames from the client are echoed back unchanged.
+//! - Events about the signed-in user (e.g. `user.registered`,
+//!   `credential.deleted`) and health changes are pushed as JSON text frames.
+//! - The server pings every [`PING_INTERVAL`] and drops clients that stay
+//!   silent for two intervals.
+//! - On shutdown every socket receives a `1001 Going Away` close frame.
+
+use super::admin::ErrorResponse;
+use crate::app_state::AppState;
+use crate::application::AuthService;
+use crate::events::{EventBus, ServerEvent};
+use crate::redact;
+use crate::session::{ClientFingerprint, SessionInfo};
+use crate::tenant::Tenant;
+use axum::{
+    extract::{
+        ws::{close_code, CloseFrame, Message, WebSocket, WebSocketUpgrade},
+        Query, State,
+    },
+    http::{HeaderMap, StatusCode},
+    response::Response,
+    Json,
+};
+use futures::{SinkExt, StreamExt};
+use serde::Deserialize;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast::error::RecvError;
+
+/// How often the server pings an idle client.
+const PING_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Optional query parameters for the upgrade request.
+#[derive(Debug, Default, Deserialize)]
+pub struct WsQuery {
+    // ---
+    /// Session token, for clients that cannot set headers on the upgrade
+    /// request (browsers). The `Authorization` header takes precedence.
+    pub token: Option<String>,
+}
+
+// ============================================================================
+// Handler
+// ============================================================================
+
+/// GET /ws
+///
+/// Upgrades to a WebSocket after validating the session token.
+///
+/// # Request Headers
+/// ```text
+/// Authorization: Bearer <session_token>
+/// ```
+/// or `GET /ws?token=<session_token>` when headers cannot be set.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - No token is supplied, or it is invalid or expired (401 Unauthorized)
+/// - The request is not a WebSocket upgrade (400 Bad Request, from axum)
+pub async fn ws_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    client: ClientFingerprint,
+    tenant: Tenant,
+    Query(query): Query<WsQuery>,
+    ws: WebSocketUpgrade,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    // ---
+    let error = |status, message: &str| {
+        (
+            status,
+            Json(ErrorResponse {
+                error: message.to_string(),
+            }),
+        )
+    };
+
+    let token = headers
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::to_string)
+        .or(query.token)
+        .ok_or_else(|| error(StatusCode::UNAUTHORIZED, "Missing session token"))?;
+
+    let session = AuthService::new(state.clone())
+        .authenticate(&token, &client, &tenant)
+        .await
+        .map_err(|status| match status.is_server_error() {
+            true => error(status, "Internal server error"),
+            false => error(status, "Invalid or expired session"),
+        })?;
+
+    tracing::info!(
+        "WebSocket opened for user {}",
+        redact::username(&session.username)
+    );
+
+    let events = state.events().clone();
+    let shutdown = state.shutdown().triggered();
+
+    Ok(ws.on_upgrade(move |socket| run_socket(socket, session, events, shutdown)))
+}
+
+// ============================================================================
+// Connection Loop
+// ============================================================================
+
+/// Drives one connection until the client leaves, goes silent, or the
+/// server shuts down.
+async fn run_socket(
+    socket: WebSocket,
+    session: SessionInfo,
+    events: EventBus,
+    shutdown: impl std::future::Future<Output = ()>,
+) {
+    // ---
+    let (mut sender, mut receiver) = socket.split();
+    let mut notifications = events.subscribe();
+    let mut ping =
+        tokio::time::interval_at(tokio::time::Instant::now() + PING_INTERVAL, PING_INTERVAL);
+    let mut last_seen = Instant::now();
+    tokio::pin!(shutdown);
+
+    loop {
+        tokio::select! {
+            incoming = receiver.next() => {
+                let message = match incoming {
+                    Some(Ok(message)) => message,
+                    Some(Err(e)) => {
+                        tracing::debug!("WebSocket error for {}: {e}", redact::username(&session.username));
+                        break;
+                    }
+                    None => break,
+                };
+                last_seen = Instant::now();
+
+                // Pings are answered automatically; pongs only refresh `last_seen`
+                let reply = match message {
+                    Message::Text(text) => Message::Text(text),
+                    Message::Binary(data) => Message::Binary(data),
+                    Message::Close(_) => break,
+                    Message::Ping(_) | Message::Pong(_) => continue,
+                };
+                if sender.send(reply).await.is_err() {
+                    break;
+                }
+            }
+
+            event = notifications.recv() => {
+                let event = match event {
+                    Ok(event) => event,
+                    Err(RecvError::Lagged(skipped)) => {
+                        tracing::warn!("WebSocket for {} skipped {skipped} events", redact::username(&session.username));
+                        continue;
+                    }
+                    Err(RecvError::Closed) => break,
+                };
+                if !event.visible_to(session.user_id) {
+                    continue;
+                }
+                if sender.send(notification(&event)).await.is_err() {
+                    break;
+                }
+            }
+
+            _ = ping.tick() => {
+                if last_seen.elapsed() > PING_INTERVAL * 2 {
+                    tracing::info!("WebSocket for {} timed out", redact::username(&session.username));
+                    break;
+                }
+                if sender.send(Message::Ping(Default::default())).await.is_err() {
+                    break;
+                }
+            }
+
+            _ = &mut shutdown => {
+                let frame = CloseFrame {
+                    code: close_code::AWAY,
+                    reason: "server shutting down".into(),
+                };
+                let _ = sender.send(Message::Close(Some(frame))).await;
+                break;
+            }
+        }
+    }
+
+    tracing::info!(
+        "WebSocket closed for user {}",
+        redact::username(&session.username)
+    );
+}
+
+/// Encodes an event as a JSON text frame.
+fn notification(event: &ServerEvent) -> Message {
+    // ---
+    let json = serde_json::to_string(event).unwrap_or_else(|e| {
+        tracing::error!("Cannot serialize event {}: {e}", event.id);
+        "{}".to_string()
+    });
+    Message::Text(json.into())
+}
@@ -0,0 +1,171 @@
+//! Dependency health history.
+//!
+//! Every full health check (`GET /health?mode=full`) records each
+//! dependency's result here. Changes of state are kept in a ring buffer of
+//! the last [`HISTORY_CAPACITY`] transitions, which `GET /health/history`
+//! serves together with the server's uptime, so a flapping dependency shows
+//! up without searching the logs.
+//!
+//! Only checks that run are seen: a dependency that fails and recovers
+//! between two checks leaves no trace.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Transitions kept; older ones are dropped.
+pub(crate) const HISTORY_CAPACITY: usize = 100;
+
+/// A dependency changing state between two health checks.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct HealthTransition {
+    // ---
+    /// `redis` or `database`.
+    pub dependency: &'static str,
+
+    /// `ok` or `degraded`.
+    pub status: &'static str,
+
+    /// The status before, or `unknown` for a dependency failing its first
+    /// check.
+    pub previous: &'static str,
+
+    /// When the check that saw the change ran.
+    pub at: DateTime<Utc>,
+
+    /// Why the check failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Default)]
+struct History {
+    // ---
+    /// Latest status of each dependency.
+    current: BTreeMap<&'static str, &'static str>,
+
+    /// Oldest first.
+    transitions: VecDeque<HealthTransition>,
+}
+
+/// Recent dependency state changes, and when the server started.
+pub(crate) struct HealthHistory {
+    // ---
+    started: Instant,
+    started_at: DateTime<Utc>,
+    history: Mutex<History>,
+}
+
+impl HealthHistory {
+    // ---
+
+    /// An empty history, counting uptime from now.
+    pub(crate) fn new() -> Self {
+        // ---
+        Self {
+            started: Instant::now(),
+            started_at: Utc::now(),
+            history: Mutex::default(),
+        }
+    }
+
+    /// Records the result of checking `dependency`. Kept as a transition
+    /// when the status differs from the previous check; a first check is
+    /// only kept if it failed.
+    pub(crate) fn record(
+        &self,
+        dependency: &'static str,
+        status: &'static str,
+        error: Option<&str>,
+    ) {
+        // ---
+        let mut history = self.history.lock().unwrap_or_else(|e| e.into_inner());
+        let previous = history.current.insert(dependency, status);
+        if previous == Some(status) || (previous.is_none() && status == "ok") {
+            return;
+        }
+
+        if history.transitions.len() == HISTORY_CAPACITY {
+            history.transitions.pop_front();
+        }
+        history.transitions.push_back(HealthTransition {
+            dependency,
+            status,
+            previous: previous.unwrap_or("unknown"),
+            at: Utc::now(),
+            error: error.map(str::to_owned),
+        });
+    }
+
+    /// Recorded transitions, newest first.
+    pub(crate) fn transitions(&self) -> Vec<HealthTransition> {
+        // ---
+        let history = self.history.lock().unwrap_or_else(|e| e.into_inner());
+        history.transitions.iter().rev().cloned().collect()
+    }
+
+    /// When the server started.
+    pub(crate) fn started_at(&self) -> DateTime<Utc> {
+        // ---
+        self.started_at
+    }
+
+    /// How long the server has been running.
+    pub(crate) fn uptime(&self) -> Duration {
+        // ---
+        self.started.elapsed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+
+    #[test]
+    fn keeps_transitions_newest_first() {
+        // ---
+        let history = HealthHistory::new();
+        history.record("redis", "ok", None);
+        history.record("database", "degraded", Some("timed out"));
+        history.record("redis", "ok", None);
+        history.record("redis", "degraded", Some("connection failed"));
+        history.record("database", "ok", None);
+
+        let seen: Vec<_> = history
+            .transitions()
+            .into_iter()
+            .map(|t| (t.dependency, t.previous, t.status))
+            .collect();
+        assert_eq!(
+            seen,
+            [
+                ("database", "degraded", "ok"),
+                ("redis", "ok", "degraded"),
+                ("database", "unknown", "degraded"),
+            ]
+        );
+        assert_eq!(
+            history.transitions()[1].error.as_deref(),
+            Some("connection failed")
+        );
+    }
+
+    #[test]
+    fn drops_the_oldest_beyond_capacity() {
+        // ---
+        let history = HealthHistory::new();
+        for i in 0..=HISTORY_CAPACITY {
+            let status = if i % 2 == 0 { "degraded" } else { "ok" };
+            history.record("redis", status, None);
+        }
+
+        // The first transition, from `unknown`, was dropped
+        let transitions = history.transitions();
+        assert_eq!(transitions.len(), HISTORY_CAPACITY);
+        assert_eq!(transitions.last().unwrap().previous, "degraded");
+        assert!(transitions.iter().all(|t| t.previous != "unknown"));
+    }
+}
@@ -0,0 +1,248 @@
+//! Repository decorator that bounds every call by the request deadline.
+
+use crate::deadline::bounded;
+use crate::domain::{
+    Credential, CredentialStats, OutboxEvent, OutboxWrite, PurgeSummary, ReencryptSummary,
+    Repository, RepositoryPtr, User,
+};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use std::future::Future;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Wraps any [`Repository`] so a call made while handling a request fails
+/// once that request's deadline passes, instead of waiting on a slow
+/// database. Calls made outside a request are passed through unbounded.
+pub(crate) struct DeadlineRepository {
+    // ---
+    inner: RepositoryPtr,
+}
+
+impl DeadlineRepository {
+    // ---
+
+    pub fn new(inner: RepositoryPtr) -> Self {
+        // ---
+        Self { inner }
+    }
+}
+
+/// Awaits a repository call under the current request deadline.
+async fn within<T>(operation: &'static str, call: impl Future<Output = Result<T>>) -> Result<T> {
+    // ---
+    bounded(call)
+        .await
+        .inspect_err(|_| tracing::warn!("Database call {operation} exceeded the request deadline"))
+        .with_context(|| format!("database call {operation}"))?
+}
+
+#[async_trait::async_trait]
+impl Repository for DeadlineRepository {
+    // ---
+    async fn ping(&self) -> Result<()> {
+        within("ping", self.inner.ping()).await
+    }
+
+    async fn create_user_in(&self, tenant_id: &str, username: &str) -> Result<User> {
+        within(
+            "create_user",
+            self.inner.create_user_in(tenant_id, username),
+        )
+        .await
+    }
+
+    async fn get_user_by_username_in(
+        &self,
+        tenant_id: &str,
+        username: &str,
+    ) -> Result<Option<User>> {
+        within(
+            "get_user_by_username",
+            self.inner.get_user_by_username_in(tenant_id, username),
+        )
+        .await
+    }
+
+    async fn get_or_create_user_in(&self, tenant_id: &str, username: &str) -> Result<User> {
+        within(
+            "get_or_create_user",
+            self.inner.get_or_create_user_in(tenant_id, username),
+        )
+        .await
+    }
+
+    async fn get_user_by_id(&self, user_id: Uuid) -> Result<Option<User>> {
+        within("get_user_by_id", self.inner.get_user_by_id(user_id)).await
+    }
+
+    async fn set_password_hash(&self, user_id: Uuid, password_hash: &str) -> Result<bool> {
+        within(
+            "set_password_hash",
+            self.inner.set_password_hash(user_id, password_hash),
+        )
+        .await
+    }
+
+    async fn get_password_hash(&self, user_id: Uuid) -> Result<Option<String>> {
+        within("get_password_hash", self.inner.get_password_hash(user_id)).await
+    }
+
+    async fn save_credential(&self, credential: Credential) -> Result<()> {
+        within("save_credential", self.inner.save_credential(credential)).await
+    }
+
+    async fn save_credentials(&self, credentials: Vec<Credential>) -> Result<()> {
+        within("save_credentials", self.inner.save_credentials(credentials)).await
+    }
+
+    async fn get_credentials_by_user(&self, user_id: Uuid) -> Result<Vec<Credential>> {
+        within(
+            "get_credentials_by_user",
+            self.inner.get_credentials_by_user(user_id),
+        )
+        .await
+    }
+
+    async fn credentials_modified_at(&self, user_id: Uuid) -> Result<Option<DateTime<Utc>>> {
+        within(
+            "credentials_modified_at",
+            self.inner.credentials_modified_at(user_id),
+        )
+        .await
+    }
+
+    async fn get_credential_by_id(&self, credential_id: &[u8]) -> Result<Option<Credential>> {
+        within(
+            "get_credential_by_id",
+            self.inner.get_credential_by_id(credential_id),
+        )
+        .await
+    }
+
+    async fn update_credential(&self, credential: Credential) -> Result<()> {
+        within(
+            "update_credential",
+            self.inner.update_credential(credential),
+        )
+        .await
+    }
+
+    async fn delete_credential(&self, credential_id: &[u8]) -> Result<()> {
+        within(
+            "delete_credential",
+            self.inner.delete_credential(credential_id),
+        )
+        .await
+    }
+
+    async fn delete_credentials_by_user(&self, user_id: Uuid) -> Result<u64> {
+        within(
+            "delete_credentials_by_user",
+            self.inner.delete_credentials_by_user(user_id),
+        )
+        .await
+    }
+
+    async fn delete_user(&self, user_id: Uuid) -> Result<bool> {
+        within("delete_user", self.inner.delete_user(user_id)).await
+    }
+
+    async fn soft_delete_user(&self, user_id: Uuid) -> Result<bool> {
+        within("soft_delete_user", self.inner.soft_delete_user(user_id)).await
+    }
+
+    async fn restore_user(&self, user_id: Uuid) -> Result<bool> {
+        within("restore_user", self.inner.restore_user(user_id)).await
+    }
+
+    async fn soft_delete_credential(&self, credential_id: &[u8]) -> Result<bool> {
+        within(
+            "soft_delete_credential",
+            self.inner.soft_delete_credential(credential_id),
+        )
+        .await
+    }
+
+    async fn restore_credential(&self, credential_id: &[u8]) -> Result<bool> {
+        within(
+            "restore_credential",
+            self.inner.restore_credential(credential_id),
+        )
+        .await
+    }
+
+    async fn mark_credential_compromised(&self, credential_id: &[u8]) -> Result<bool> {
+        within(
+            "mark_credential_compromised",
+            self.inner.mark_credential_compromised(credential_id),
+        )
+        .await
+    }
+
+    async fn purge_deleted(&self, cutoff: DateTime<Utc>, dry_run: bool) -> Result<PurgeSummary> {
+        within("purge_deleted", self.inner.purge_deleted(cutoff, dry_run)).await
+    }
+
+    async fn delete_orphaned_users(
+        &self,
+        created_before: DateTime<Utc>,
+        dry_run: bool,
+    ) -> Result<u64> {
+        within(
+            "delete_orphaned_users",
+            self.inner.delete_orphaned_users(created_before, dry_run),
+        )
+        .await
+    }
+
+    async fn credential_stats(&self) -> Result<CredentialStats> {
+        within("credential_stats", self.inner.credential_stats()).await
+    }
+
+    async fn list_all_credentials(
+        &self,
+        after: Option<&[u8]>,
+        limit: u32,
+    ) -> Result<Vec<Credential>> {
+        within(
+            "list_all_credentials",
+            self.inner.list_all_credentials(after, limit),
+        )
+        .await
+    }
+
+    async fn replace_public_key(
+        &self,
+        credential_id: &[u8],
+        expected: &[u8],
+        public_key: &[u8],
+    ) -> Result<bool> {
+        within(
+            "replace_public_key",
+            self.inner
+                .replace_public_key(credential_id, expected, public_key),
+        )
+        .await
+    }
+
+    async fn write_with_event(&self, write: OutboxWrite, event: &OutboxEvent) -> Result<bool> {
+        within(
+            "write_with_event",
+            self.inner.write_with_event(write, event),
+        )
+        .await
+    }
+
+    async fn claim_events(&self, limit: u32, lease: Duration) -> Result<Vec<OutboxEvent>> {
+        within("claim_events", self.inner.claim_events(limit, lease)).await
+    }
+
+    async fn delete_event(&self, event_id: Uuid) -> Result<()> {
+        within("delete_event", self.inner.delete_event(event_id)).await
+    }
+
+    async fn reencrypt_credentials(&self) -> Result<Option<ReencryptSummary>> {
+        within("reencrypt_credentials", self.inner.reencrypt_credentials()).await
+    }
+}
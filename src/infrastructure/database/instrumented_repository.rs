@@ -0,0 +1,375 @@
+//! Repository decorator that times every call and logs slow ones.
+
+use crate::domain::{
+    Credential, CredentialStats, MetricsPtr, OutboxEvent, OutboxWrite, PurgeSummary,
+    ReencryptSummary, Repository, RepositoryPtr, User,
+};
+use crate::redact;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use std::future::Future;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// Wraps any [`Repository`] so each call is recorded in
+/// `db_query_duration_seconds{query_name}`, named after the repository
+/// method. A call taking at least the slow-query threshold is logged with
+/// its parameters; usernames and credential IDs are redacted as in other
+/// log output.
+pub(crate) struct InstrumentedRepository {
+    // ---
+    inner: RepositoryPtr,
+    metrics: MetricsPtr,
+    slow_query_threshold: Option<Duration>,
+}
+
+impl InstrumentedRepository {
+    // ---
+
+    /// `slow_query_threshold` of `None` records timings without logging.
+    pub fn new(
+        inner: RepositoryPtr,
+        metrics: MetricsPtr,
+        slow_query_threshold: Option<Duration>,
+    ) -> Self {
+        // ---
+        Self {
+            inner,
+            metrics,
+            slow_query_threshold,
+        }
+    }
+
+    /// Awaits a repository call, recording how long it took. `params`
+    /// renders the call's parameters and is only evaluated for slow calls.
+    async fn timed<T>(
+        &self,
+        query_name: &'static str,
+        params: impl FnOnce() -> String,
+        call: impl Future<Output = Result<T>>,
+    ) -> Result<T> {
+        // ---
+        let start = Instant::now();
+        let result = call.await;
+        let elapsed = start.elapsed();
+
+        self.metrics.record_db_query(query_name, elapsed);
+        if self
+            .slow_query_threshold
+            .is_some_and(|threshold| elapsed >= threshold)
+        {
+            let params = params();
+            let ms = elapsed.as_millis();
+            match params.is_empty() {
+                true => tracing::warn!("Slow database query {query_name} took {ms}ms"),
+                false => tracing::warn!("Slow database query {query_name} took {ms}ms ({params})"),
+            }
+        }
+        result
+    }
+}
+
+fn no_params() -> String {
+    // ---
+    String::new()
+}
+
+fn username(tenant_id: &str, username: &str) -> String {
+    // ---
+    format!(
+        "tenant_id={tenant_id}, username={}",
+        redact::username(username)
+    )
+}
+
+fn credential_id(credential_id: &[u8]) -> String {
+    // ---
+    format!(
+        "credential_id={}",
+        redact::secret(&hex::encode(credential_id))
+    )
+}
+
+#[async_trait::async_trait]
+impl Repository for InstrumentedRepository {
+    // ---
+    async fn ping(&self) -> Result<()> {
+        self.timed("ping", no_params, self.inner.ping()).await
+    }
+
+    async fn create_user_in(&self, tenant_id: &str, name: &str) -> Result<User> {
+        self.timed(
+            "create_user",
+            || username(tenant_id, name),
+            self.inner.create_user_in(tenant_id, name),
+        )
+        .await
+    }
+
+    async fn get_user_by_username_in(&self, tenant_id: &str, name: &str) -> Result<Option<User>> {
+        self.timed(
+            "get_user_by_username",
+            || username(tenant_id, name),
+            self.inner.get_user_by_username_in(tenant_id, name),
+        )
+        .await
+    }
+
+    async fn get_or_create_user_in(&self, tenant_id: &str, name: &str) -> Result<User> {
+        self.timed(
+            "get_or_create_user",
+            || username(tenant_id, name),
+            self.inner.get_or_create_user_in(tenant_id, name),
+        )
+        .await
+    }
+
+    async fn get_user_by_id(&self, user_id: Uuid) -> Result<Option<User>> {
+        self.timed(
+            "get_user_by_id",
+            || format!("user_id={user_id}"),
+            self.inner.get_user_by_id(user_id),
+        )
+        .await
+    }
+
+    async fn set_password_hash(&self, user_id: Uuid, password_hash: &str) -> Result<bool> {
+        self.timed(
+            "set_password_hash",
+            || format!("user_id={user_id}"),
+            self.inner.set_password_hash(user_id, password_hash),
+        )
+        .await
+    }
+
+    async fn get_password_hash(&self, user_id: Uuid) -> Result<Option<String>> {
+        self.timed(
+            "get_password_hash",
+            || format!("user_id={user_id}"),
+            self.inner.get_password_hash(user_id),
+        )
+        .await
+    }
+
+    async fn save_credential(&self, credential: Credential) -> Result<()> {
+        let id = credential.id.clone();
+        self.timed(
+            "save_credential",
+            || credential_id(&id),
+            self.inner.save_credential(credential),
+        )
+        .await
+    }
+
+    async fn save_credentials(&self, credentials: Vec<Credential>) -> Result<()> {
+        let count = credentials.len();
+        self.timed(
+            "save_credentials",
+            || format!("credentials={count}"),
+            self.inner.save_credentials(credentials),
+        )
+        .await
+    }
+
+    async fn get_credentials_by_user(&self, user_id: Uuid) -> Result<Vec<Credential>> {
+        self.timed(
+            "get_credentials_by_user",
+            || format!("user_id={user_id}"),
+            self.inner.get_credentials_by_user(user_id),
+        )
+        .await
+    }
+
+    async fn credentials_modified_at(&self, user_id: Uuid) -> Result<Option<DateTime<Utc>>> {
+        self.timed(
+            "credentials_modified_at",
+            || format!("user_id={user_id}"),
+            self.inner.credentials_modified_at(user_id),
+        )
+        .await
+    }
+
+    async fn get_credential_by_id(&self, id: &[u8]) -> Result<Option<Credential>> {
+        self.timed(
+            "get_credential_by_id",
+            || credential_id(id),
+            self.inner.get_credential_by_id(id),
+        )
+        .await
+    }
+
+    async fn update_credential(&self, credential: Credential) -> Result<()> {
+        let id = credential.id.clone();
+        self.timed(
+            "update_credential",
+            || credential_id(&id),
+            self.inner.update_credential(credential),
+        )
+        .await
+    }
+
+    async fn delete_credential(&self, id: &[u8]) -> Result<()> {
+        self.timed(
+            "delete_credential",
+            || credential_id(id),
+            self.inner.delete_credential(id),
+        )
+        .await
+    }
+
+    async fn delete_credentials_by_user(&self, user_id: Uuid) -> Result<u64> {
+        self.timed(
+            "delete_credentials_by_user",
+            || format!("user_id={user_id}"),
+            self.inner.delete_credentials_by_user(user_id),
+        )
+        .await
+    }
+
+    async fn delete_user(&self, user_id: Uuid) -> Result<bool> {
+        self.timed(
+            "delete_user",
+            || format!("user_id={user_id}"),
+            self.inner.delete_user(user_id),
+        )
+        .await
+    }
+
+    async fn soft_delete_user(&self, user_id: Uuid) -> Result<bool> {
+        self.timed(
+            "soft_delete_user",
+            || format!("user_id={user_id}"),
+            self.inner.soft_delete_user(user_id),
+        )
+        .await
+    }
+
+    async fn restore_user(&self, user_id: Uuid) -> Result<bool> {
+        self.timed(
+            "restore_user",
+            || format!("user_id={user_id}"),
+            self.inner.restore_user(user_id),
+        )
+        .await
+    }
+
+    async fn soft_delete_credential(&self, id: &[u8]) -> Result<bool> {
+        self.timed(
+            "soft_delete_credential",
+            || credential_id(id),
+            self.inner.soft_delete_credential(id),
+        )
+        .await
+    }
+
+    async fn restore_credential(&self, id: &[u8]) -> Result<bool> {
+        self.timed(
+            "restore_credential",
+            || credential_id(id),
+            self.inner.restore_credential(id),
+        )
+        .await
+    }
+
+    async fn mark_credential_compromised(&self, id: &[u8]) -> Result<bool> {
+        self.timed(
+            "mark_credential_compromised",
+            || credential_id(id),
+            self.inner.mark_credential_compromised(id),
+        )
+        .await
+    }
+
+    async fn purge_deleted(&self, cutoff: DateTime<Utc>, dry_run: bool) -> Result<PurgeSummary> {
+        self.timed(
+            "purge_deleted",
+            || format!("cutoff={cutoff}, dry_run={dry_run}"),
+            self.inner.purge_deleted(cutoff, dry_run),
+        )
+        .await
+    }
+
+    async fn delete_orphaned_users(
+        &self,
+        created_before: DateTime<Utc>,
+        dry_run: bool,
+    ) -> Result<u64> {
+        self.timed(
+            "delete_orphaned_users",
+            || format!("created_before={created_before}, dry_run={dry_run}"),
+            self.inner.delete_orphaned_users(created_before, dry_run),
+        )
+        .await
+    }
+
+    async fn credential_stats(&self) -> Result<CredentialStats> {
+        self.timed("credential_stats", no_params, self.inner.credential_stats())
+            .await
+    }
+
+    async fn list_all_credentials(
+        &self,
+        after: Option<&[u8]>,
+        limit: u32,
+    ) -> Result<Vec<Credential>> {
+        self.timed(
+            "list_all_credentials",
+            || match after {
+                Some(id) => format!("after {}, limit={limit}", credential_id(id)),
+                None => format!("limit={limit}"),
+            },
+            self.inner.list_all_credentials(after, limit),
+        )
+        .await
+    }
+
+    async fn replace_public_key(
+        &self,
+        id: &[u8],
+        expected: &[u8],
+        public_key: &[u8],
+    ) -> Result<bool> {
+        self.timed(
+            "replace_public_key",
+            || credential_id(id),
+            self.inner.replace_public_key(id, expected, public_key),
+        )
+        .await
+    }
+
+    async fn write_with_event(&self, write: OutboxWrite, event: &OutboxEvent) -> Result<bool> {
+        self.timed(
+            "write_with_event",
+            || format!("event_id={}, kind={}", event.id, event.kind),
+            self.inner.write_with_event(write, event),
+        )
+        .await
+    }
+
+    async fn claim_events(&self, limit: u32, lease: Duration) -> Result<Vec<OutboxEvent>> {
+        self.timed(
+            "claim_events",
+            || format!("limit={limit}, lease={lease:?}"),
+            self.inner.claim_events(limit, lease),
+        )
+        .await
+    }
+
+    async fn delete_event(&self, event_id: Uuid) -> Result<()> {
+        self.timed(
+            "delete_event",
+            || format!("event_id={event_id}"),
+            self.inner.delete_event(event_id),
+        )
+        .await
+    }
+
+    async fn reencrypt_credentials(&self) -> Result<Option<ReencryptSummary>> {
+        self.timed(
+            "reencrypt_credentials",
+            no_params,
+            self.inner.reencrypt_credentials(),
+        )
+        .await
+    }
+}
@@ -1,4 +1,127 @@
+mod deadline_repository;
+mod instrumented_repository;
 pub mod postgres_repository;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_repository;
 
 #[cfg(test)]
 mod tests;
+
+pub(crate) use deadline_repository::DeadlineRepository;
+pub(crate) use instrumented_repository::InstrumentedRepository;
+
+use crate::domain::RepositoryPtr;
+use crate::{DatabaseConfig, RepositoryType};
+use anyhow::Result;
+use once_cell::sync::OnceCell;
+use std::sync::Arc;
+
+/// Connect to the backend selected by `cfg.repository_type`.
+///
+/// The returned repository owns its connection pool; dropping the last
+/// clone closes it. Calling this twice with different configurations
+/// yields two independent repositories, so several databases can be used
+/// in one process.
+///
+/// # Errors
+/// Returns an error if the database cannot be reached (after retries for
+/// PostgreSQL), or SQLite is selected in a build without the `sqlite` feature.
+pub async fn create_repository(cfg: &DatabaseConfig) -> Result<RepositoryPtr> {
+    // ---
+    match cfg.repository_type {
+        RepositoryType::Postgres => Ok(Arc::new(
+            postgres_repository::PostgresRepository::connect(cfg).await?,
+        )),
+        RepositoryType::Sqlite => connect_sqlite(cfg).await,
+    }
+}
+
+/// Opens and closes one connection to the database, without retrying.
+/// SQLite is a local file and always counts as reachable.
+///
+/// # Errors
+/// Returns an error if PostgreSQL cannot be reached within
+/// `cfg.acquire_timeout`.
+pub(crate) async fn probe_database(cfg: &DatabaseConfig) -> Result<()> {
+    // ---
+    use sqlx::Connection;
+
+    if cfg.repository_type == RepositoryType::Sqlite {
+        return Ok(());
+    }
+    let connect = sqlx::PgConnection::connect(&cfg.database_url);
+    let conn = tokio::time::timeout(cfg.acquire_timeout, connect)
+        .await
+        .map_err(|_| anyhow::anyhow!("Timed out connecting to the database"))??;
+    conn.close().await?;
+    Ok(())
+}
+
+#[cfg(feature = "sqlite")]
+async fn connect_sqlite(cfg: &DatabaseConfig) -> Result<RepositoryPtr> {
+    // ---
+    Ok(Arc::new(
+        sqlite_repository::SqliteRepository::connect(cfg).await?,
+    ))
+}
+
+#[cfg(not(feature = "sqlite"))]
+async fn connect_sqlite(_cfg: &DatabaseConfig) -> Result<RepositoryPtr> {
+    // ---
+    Err(anyhow::anyhow!(
+        "AXUM_REPOSITORY_TYPE=sqlite requires building with `--features sqlite`"
+    ))
+}
+
+// ============================================================
+// Compatibility shim for the env-driven entry points
+// ============================================================
+//
+// `create_router()` is synchronous and cannot open a pool itself, so the
+// original two-step API (`init_database_with_retry_from_env()` followed by
+// `create_postgres_repository()`) is kept on top of `create_repository()`.
+// New code should call `create_repository()` and pass the result to
+// `AppBuilder::repository()` instead.
+
+static DEFAULT_REPOSITORY: OnceCell<RepositoryPtr> = OnceCell::new();
+
+/// Connect using [`DatabaseConfig::from_env`] and remember the repository
+/// for [`default_repository`]. Idempotent once a connection succeeds.
+pub async fn init_database_with_retry_from_env() -> Result<()> {
+    // ---
+    if DEFAULT_REPOSITORY.get().is_some() {
+        tracing::debug!("init_database_with_retry_from_env: already initialized");
+        return Ok(());
+    }
+
+    let repository = create_repository(&DatabaseConfig::from_env()?).await?;
+
+    if DEFAULT_REPOSITORY.set(repository).is_err() {
+        // Concurrent initialization; keep the first and drop ours.
+        tracing::warn!("init_database_with_retry_from_env: already initialized");
+    }
+    Ok(())
+}
+
+/// Returns the repository created by [`init_database_with_retry_from_env`].
+///
+/// # Errors
+/// Returns an error if it has not been called (successfully) yet.
+pub fn default_repository() -> Result<RepositoryPtr> {
+    // ---
+    DEFAULT_REPOSITORY.get().cloned().ok_or_else(|| {
+        anyhow::anyhow!(
+            "No repository available: call init_database_with_retry_from_env() first, \
+             or supply one with AppBuilder::repository()"
+        )
+    })
+}
+
+/// Compatibility alias for [`default_repository`].
+///
+/// Despite the name this returns whichever backend `AXUM_REPOSITORY_TYPE`
+/// selected when the database was initialized.
+pub fn create_postgres_repository() -> Result<RepositoryPtr> {
+    // ---
+    default_repository()
+}
@@ -1,55 +1,64 @@
 use crate::DatabaseConfig;
 use anyhow::{anyhow, Result};
 use chrono::{DateTime, Utc};
-use once_cell::sync::OnceCell;
-use sqlx::{postgres::PgPoolOptions, PgPool};
-use std::sync::Arc;
-use std::time::Duration;
+use sqlx::{postgres::PgPoolOptions, PgExecutor, PgPool};
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 use uuid::Uuid;
 
-use crate::domain::{Credential, Repository, RepositoryPtr, User};
+use crate::domain::{
+    normalize_username, Credential, CredentialStats, OutboxEvent, OutboxWrite, PurgeSummary,
+    Repository, User,
+};
 
-#[derive(sqlx::FromRow)]
 struct UserRow {
     id: Uuid,
+    tenant_id: String,
     username: String,
     created_at: DateTime<Utc>,
 }
 
-#[derive(sqlx::FromRow)]
+struct OutboxRow {
+    id: Uuid,
+    kind: String,
+    payload: String,
+    created_at: DateTime<Utc>,
+    attempts: i32,
+    traceparent: Option<String>,
+    tracestate: Option<String>,
+}
+
 struct CredentialRow {
     id: Vec<u8>,
     user_id: Uuid,
     public_key: Vec<u8>,
-    counter: i32,
+    counter: i64,
     created_at: DateTime<Utc>,
+    compromised_at: Option<DateTime<Utc>>,
+    last_used_at: Option<DateTime<Utc>>,
+    backup_eligible: Option<bool>,
+    backup_state: Option<bool>,
 }
 
-static DB_POOL: OnceCell<PgPool> = OnceCell::new();
-
-/// Initialize the DB connection pool with retry logic.
+/// Connect to PostgreSQL with retry and exponential backoff.
 ///
-/// Respects env vars:
-/// - `AXUM_DB_RETRY_COUNT` (default: 50)
-/// - `AXUM_DB_RETRY_DELAY_SECS` (default: 1)
-pub async fn init_database_with_retry_from_env() -> Result<()> {
-    // ---
-
-    if DB_POOL.get().is_some() {
-        tracing::debug!("init_database_with_retry_from_env: Pool is already initialized");
-        return Ok(());
-    }
-
-    init_database_with_retry(&DatabaseConfig::from_env()?).await
-}
-
-async fn init_database_with_retry(cfg: &DatabaseConfig) -> Result<()> {
+/// Retries up to `cfg.retry_count` times, doubling the delay between
+/// attempts up to a cap of 8 seconds. Intended for startup, where the
+/// database container may still be coming up.
+///
+/// # Errors
+/// Returns an error if no connection could be established after all retries.
+pub async fn connect_with_retry(cfg: &DatabaseConfig) -> Result<PgPool> {
     // ---
-    let url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+    let url = &cfg.database_url;
 
-    let fname = "init_database_with_retry";
+    let fname = "connect_with_retry";
 
-    tracing::info!("🚨 axum-quickstart attaching to database at: {:?}", url);
+    tracing::info!(
+        "🚨 axum-quickstart attaching to database at: {:?}",
+        crate::redact::url(url)
+    );
 
     for attempt in 1..=cfg.retry_count {
         // ---
@@ -57,23 +66,10 @@ async fn init_database_with_retry(cfg: &DatabaseConfig) -> Result<()> {
             .max_connections(cfg.max_connections)
             .min_connections(cfg.min_connections)
             .acquire_timeout(cfg.acquire_timeout)
-            .connect(&url)
+            .connect(url)
             .await
         {
-            Ok(pool) => {
-                // ---
-                if DB_POOL.set(pool).is_err() {
-                    // ---
-
-                    // This would happen only if this function is called from multiple
-                    // threads concurrently which is not supposed to happen since it is
-                    // called early in main, but we handle if it does by just dropping the
-                    // new (2nd) one.
-
-                    tracing::warn!("{fname}: Pool is already initialized");
-                }
-                return Ok(());
-            }
+            Ok(pool) => return Ok(pool),
             Err(e) if attempt == cfg.retry_count => {
                 return Err(anyhow!(
                     "{fname}: Failed to connect to DB after {} retries: {e}",
@@ -92,26 +88,103 @@ async fn init_database_with_retry(cfg: &DatabaseConfig) -> Result<()> {
             }
         }
     }
-    unreachable!("Exhausted retries should already have returned above")
+    Err(anyhow!("{fname}: retry_count must be at least 1"))
 }
 
-pub fn create_postgres_repository() -> Result<RepositoryPtr> {
+/// Recomputes `username_normalized` for usernames with non-ASCII characters.
+///
+/// The migration adding the column filled it with PostgreSQL's `lower()` and
+/// `normalize()`, which only agree with [`normalize_username`] on a UTF-8
+/// database with a Unicode-aware collation. Elsewhere those users would not
+/// be found and signing in would create a second account. Rows that already
+/// match are skipped, so after the first start this only reads.
+///
+/// # Errors
+/// Returns an error if two users of one tenant collide once normalized;
+/// rename one of them first.
+async fn backfill_normalized_usernames(pool: &PgPool) -> Result<()> {
     // ---
-    let pool = DB_POOL
-        .get()
-        .expect("Pool not initialized. Call init_pool_with_retry() first.");
+    let rows = sqlx::query!(
+        "SELECT id, username, username_normalized FROM users WHERE username ~ '[^[:ascii:]]'"
+    )
+    .fetch_all(pool)
+    .await?;
 
-    let rep = PostgresRepository::new(pool.clone());
-    Ok(Arc::new(rep))
+    for row in rows {
+        let normalized = normalize_username(&row.username);
+        if normalized == row.username_normalized {
+            continue;
+        }
+        sqlx::query!(
+            "UPDATE users SET username_normalized = $1 WHERE id = $2",
+            normalized,
+            row.id,
+        )
+        .execute(pool)
+        .await
+        .map_err(|e| anyhow!("Failed to normalize username {:?}: {e}", row.username))?;
+    }
+    Ok(())
+}
+
+/// How long reads bypass the replica after it has failed.
+const REPLICA_COOLDOWN: Duration = Duration::from_secs(30);
+
+/// Upper bound on waiting for a replica connection before falling back.
+const REPLICA_ACQUIRE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Create a lazily-connected pool for the read replica at `url`.
+///
+/// The replica is optional, so it is never waited on at startup; a replica
+/// that is down is detected on first use and reads fall back to the primary.
+pub fn connect_replica(cfg: &DatabaseConfig, url: &str) -> Result<PgPool> {
+    // ---
+    tracing::info!("axum-quickstart using read replica at: {:?}", url);
+
+    PgPoolOptions::new()
+        .max_connections(cfg.max_connections)
+        .acquire_timeout(cfg.acquire_timeout.min(REPLICA_ACQUIRE_TIMEOUT))
+        .connect_lazy(url)
+        .map_err(|e| anyhow!("Invalid DATABASE_READ_URL: {e}"))
 }
 
+struct Replica {
+    // ---
+    pool: PgPool,
+
+    /// Reads skip the replica until this instant after a failure.
+    down_until: Mutex<Option<Instant>>,
+}
+
+/// PostgreSQL-backed [`Repository`]. Owns its connection pool(s).
+///
+/// Writes always go to the primary pool. When a read replica is configured,
+/// the read-only methods (`get_user_*`, `get_credential*`) are sent to it
+/// instead; if the replica cannot be reached the read is retried on the
+/// primary and the replica is skipped for [`REPLICA_COOLDOWN`].
 pub struct PostgresRepository {
     // ---
     pool: PgPool,
+    replica: Option<Replica>,
 }
 
 impl PostgresRepository {
     // ---
+    /// Connects using `cfg` (with retry) and returns a repository owning the pool.
+    ///
+    /// If `cfg.read_url` is set, a replica pool is attached as well.
+    pub async fn connect(cfg: &DatabaseConfig) -> Result<Self> {
+        // ---
+        let pool = connect_with_retry(cfg).await?;
+        backfill_normalized_usernames(&pool).await?;
+        let repository = Self::new(pool);
+
+        match &cfg.read_url {
+            Some(url) => Ok(repository.with_replica(connect_replica(cfg, url)?)),
+            None => Ok(repository),
+        }
+    }
+
     pub fn new(pool: PgPool) -> Self {
         // ---
         tracing::debug!(
@@ -120,104 +193,395 @@ impl PostgresRepository {
             pool.num_idle()
         );
 
-        Self { pool }
+        Self {
+            pool,
+            replica: None,
+        }
+    }
+
+    /// Routes read-only queries to `replica`.
+    pub fn with_replica(mut self, replica: PgPool) -> Self {
+        // ---
+        self.replica = Some(Replica {
+            pool: replica,
+            down_until: Mutex::new(None),
+        });
+        self
+    }
+
+    /// Returns the replica pool unless none is configured or it is cooling down.
+    fn replica_pool(&self) -> Option<&PgPool> {
+        // ---
+        let replica = self.replica.as_ref()?;
+        let mut down_until = replica.down_until.lock().unwrap();
+
+        match *down_until {
+            Some(until) if Instant::now() < until => None,
+            Some(_) => {
+                tracing::info!("Retrying read replica after cooldown");
+                *down_until = None;
+                Some(&replica.pool)
+            }
+            None => Some(&replica.pool),
+        }
+    }
+
+    fn mark_replica_down(&self) {
+        // ---
+        if let Some(replica) = &self.replica {
+            *replica.down_until.lock().unwrap() = Some(Instant::now() + REPLICA_COOLDOWN);
+        }
+    }
+
+    /// Runs a read-only query on the replica, falling back to the primary
+    /// if the replica is unavailable.
+    ///
+    /// Only connection-level failures trigger the fallback; query errors
+    /// from a reachable replica are returned as-is.
+    async fn read<T, F, Fut>(&self, query: F) -> Result<T>
+    where
+        F: Fn(PgPool) -> Fut,
+        Fut: Future<Output = std::result::Result<T, sqlx::Error>>,
+    {
+        // ---
+        if let Some(replica) = self.replica_pool() {
+            match query(replica.clone()).await {
+                Ok(value) => return Ok(value),
+                Err(e) if is_unavailable(&e) => {
+                    tracing::warn!("Read replica unavailable, falling back to primary: {e}");
+                    self.mark_replica_down();
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        Ok(query(self.pool.clone()).await?)
+    }
+}
+
+/// True for errors meaning the server could not be reached or is not
+/// accepting queries, as opposed to errors in the query itself.
+fn is_unavailable(e: &sqlx::Error) -> bool {
+    // ---
+    match e {
+        sqlx::Error::Io(_)
+        | sqlx::Error::Tls(_)
+        | sqlx::Error::PoolTimedOut
+        | sqlx::Error::PoolClosed
+        | sqlx::Error::WorkerCrashed => true,
+        // 57P03 cannot_connect_now: replica starting up or in recovery
+        sqlx::Error::Database(db) => db.code().as_deref() == Some("57P03"),
+        _ => false,
     }
 }
 
+// Writes that can also run inside an outbox transaction
+
+async fn insert_credential<'e>(
+    executor: impl PgExecutor<'e>,
+    credential: &Credential,
+) -> Result<()> {
+    // ---
+    sqlx::query!(
+        "INSERT INTO credentials
+             (id, user_id, public_key, counter, created_at, backup_eligible, backup_state)
+         VALUES ($1, $2, $3, $4, $5, $6, $7)",
+        credential.id,
+        credential.user_id,
+        credential.public_key,
+        credential.counter,
+        credential.created_at,
+        credential.backup_eligible,
+        credential.backup_state,
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+async fn soft_delete_credential_with<'e>(
+    executor: impl PgExecutor<'e>,
+    credential_id: &[u8],
+) -> Result<bool> {
+    // ---
+    let result = sqlx::query!(
+        "UPDATE credentials SET deleted_at = NOW() WHERE id = $1 AND deleted_at IS NULL",
+        credential_id,
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+async fn mark_compromised_with<'e>(
+    executor: impl PgExecutor<'e>,
+    credential_id: &[u8],
+) -> Result<bool> {
+    // ---
+    let result = sqlx::query!(
+        "UPDATE credentials SET compromised_at = NOW() WHERE id = $1 AND compromised_at IS NULL",
+        credential_id,
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
 #[async_trait::async_trait]
 impl Repository for PostgresRepository {
     // ---
-    async fn create_user(&self, username: &str) -> Result<User> {
+    async fn ping(&self) -> Result<()> {
         // ---
-        let user = User::new(username.to_string());
-
-        sqlx::query("INSERT INTO users (id, username, created_at) VALUES ($1, $2, $3)")
-            .bind(user.id)
-            .bind(&user.username)
-            .bind(user.created_at)
-            .execute(&self.pool)
+        sqlx::query!("SELECT 1 AS one")
+            .fetch_one(&self.pool)
             .await?;
-
-        Ok(user)
+        Ok(())
     }
 
-    async fn get_user_by_username(&self, username: &str) -> Result<Option<User>> {
+    async fn create_user_in(&self, tenant_id: &str, username: &str) -> Result<User> {
         // ---
-        let row = sqlx::query_as::<_, UserRow>(
-            "SELECT id, username, created_at FROM users WHERE username = $1",
+        let user = User::new(tenant_id.to_string(), username.to_string());
+
+        sqlx::query!(
+            "INSERT INTO users (id, tenant_id, username, username_normalized, created_at)
+             VALUES ($1, $2, $3, $4, $5)",
+            user.id,
+            user.tenant_id,
+            user.username,
+            normalize_username(username),
+            user.created_at,
         )
-        .bind(username)
-        .fetch_optional(&self.pool)
+        .execute(&self.pool)
         .await?;
 
+        Ok(user)
+    }
+
+    async fn get_user_by_username_in(
+        &self,
+        tenant_id: &str,
+        username: &str,
+    ) -> Result<Option<User>> {
+        // ---
+        let normalized = normalize_username(username);
+        let username = normalized.as_str();
+        let row = self
+            .read(|pool| async move {
+                sqlx::query_as!(
+                    UserRow,
+                    "SELECT id, tenant_id, username, created_at FROM users
+                     WHERE tenant_id = $1 AND username_normalized = $2 AND deleted_at IS NULL",
+                    tenant_id,
+                    username,
+                )
+                .fetch_optional(&pool)
+                .await
+            })
+            .await?;
+
         Ok(row.map(|r| User {
             id: r.id,
+            tenant_id: r.tenant_id,
             username: r.username,
             created_at: r.created_at,
         }))
     }
 
-    async fn get_user_by_id(&self, user_id: Uuid) -> Result<Option<User>> {
+    async fn get_or_create_user_in(&self, tenant_id: &str, username: &str) -> Result<User> {
         // ---
-        let row = sqlx::query_as::<_, UserRow>(
-            "SELECT id, username, created_at FROM users WHERE id = $1",
+        let user = User::new(tenant_id.to_string(), username.to_string());
+        let normalized = normalize_username(username);
+
+        let inserted = sqlx::query_as!(
+            UserRow,
+            "INSERT INTO users (id, tenant_id, username, username_normalized, created_at)
+             VALUES ($1, $2, $3, $4, $5)
+             ON CONFLICT (tenant_id, username_normalized) DO NOTHING
+             RETURNING id, tenant_id, username, created_at",
+            user.id,
+            user.tenant_id,
+            user.username,
+            normalized,
+            user.created_at,
         )
-        .bind(user_id)
         .fetch_optional(&self.pool)
         .await?;
 
+        // Lost the race, or the user already existed. Read the primary: a
+        // concurrent insert may not have reached the replica yet.
+        let row = match inserted {
+            Some(row) => row,
+            None => sqlx::query_as!(
+                UserRow,
+                "SELECT id, tenant_id, username, created_at FROM users
+                 WHERE tenant_id = $1 AND username_normalized = $2 AND deleted_at IS NULL",
+                tenant_id,
+                normalized,
+            )
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or_else(|| anyhow!("Username is reserved by a deleted user"))?,
+        };
+
+        Ok(User {
+            id: row.id,
+            tenant_id: row.tenant_id,
+            username: row.username,
+            created_at: row.created_at,
+        })
+    }
+
+    async fn get_user_by_id(&self, user_id: Uuid) -> Result<Option<User>> {
+        // ---
+        let row = self
+            .read(|pool| async move {
+                sqlx::query_as!(
+                    UserRow,
+                    "SELECT id, tenant_id, username, created_at FROM users
+                     WHERE id = $1 AND deleted_at IS NULL",
+                    user_id,
+                )
+                .fetch_optional(&pool)
+                .await
+            })
+            .await?;
+
         Ok(row.map(|r| User {
             id: r.id,
+            tenant_id: r.tenant_id,
             username: r.username,
             created_at: r.created_at,
         }))
     }
 
-    async fn save_credential(&self, credential: Credential) -> Result<()> {
+    async fn set_password_hash(&self, user_id: Uuid, password_hash: &str) -> Result<bool> {
         // ---
-        sqlx::query(
-            "INSERT INTO credentials (id, user_id, public_key, counter, created_at)
-             VALUES ($1, $2, $3, $4, $5)",
+        let result = sqlx::query!(
+            "INSERT INTO user_passwords (user_id, password_hash)
+             SELECT id, $2 FROM users WHERE id = $1 AND deleted_at IS NULL
+             ON CONFLICT (user_id)
+             DO UPDATE SET password_hash = EXCLUDED.password_hash, updated_at = NOW()",
+            user_id,
+            password_hash,
         )
-        .bind(&credential.id)
-        .bind(credential.user_id)
-        .bind(&credential.public_key)
-        .bind(credential.counter)
-        .bind(credential.created_at)
         .execute(&self.pool)
         .await?;
 
-        Ok(())
+        Ok(result.rows_affected() > 0)
     }
 
-    async fn get_credential_by_id(&self, credential_id: &[u8]) -> Result<Option<Credential>> {
+    async fn get_password_hash(&self, user_id: Uuid) -> Result<Option<String>> {
         // ---
-        let row = sqlx::query_as::<_, CredentialRow>(
-            "SELECT id, user_id, public_key, counter, created_at
-             FROM credentials WHERE id = $1",
+        // From the primary: a replica lagging behind a password change
+        // would still accept the old password
+        let hash = sqlx::query_scalar!(
+            "SELECT p.password_hash FROM user_passwords p
+             JOIN users u ON u.id = p.user_id
+             WHERE p.user_id = $1 AND u.deleted_at IS NULL",
+            user_id,
         )
-        .bind(credential_id)
         .fetch_optional(&self.pool)
         .await?;
 
+        Ok(hash)
+    }
+
+    async fn save_credential(&self, credential: Credential) -> Result<()> {
+        // ---
+        insert_credential(&self.pool, &credential).await
+    }
+
+    async fn save_credentials(&self, credentials: Vec<Credential>) -> Result<()> {
+        // ---
+        if credentials.is_empty() {
+            return Ok(());
+        }
+
+        // One statement for the whole batch, with each column sent as an array
+        let mut ids = Vec::with_capacity(credentials.len());
+        let mut user_ids = Vec::with_capacity(credentials.len());
+        let mut public_keys = Vec::with_capacity(credentials.len());
+        let mut counters = Vec::with_capacity(credentials.len());
+        let mut created_ats = Vec::with_capacity(credentials.len());
+        let mut backup_eligible = Vec::with_capacity(credentials.len());
+        let mut backup_state = Vec::with_capacity(credentials.len());
+        for credential in credentials {
+            ids.push(credential.id);
+            user_ids.push(credential.user_id);
+            public_keys.push(credential.public_key);
+            counters.push(credential.counter);
+            created_ats.push(credential.created_at);
+            backup_eligible.push(credential.backup_eligible);
+            backup_state.push(credential.backup_state);
+        }
+
+        sqlx::query!(
+            "INSERT INTO credentials
+                 (id, user_id, public_key, counter, created_at, backup_eligible, backup_state)
+             SELECT * FROM UNNEST($1::bytea[], $2::uuid[], $3::bytea[], $4::int8[],
+                                  $5::timestamptz[], $6::bool[], $7::bool[])",
+            &ids,
+            &user_ids,
+            &public_keys,
+            &counters,
+            &created_ats,
+            // Unknown backup flags are stored as NULL elements
+            &backup_eligible as &[Option<bool>],
+            &backup_state as &[Option<bool>],
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_credential_by_id(&self, credential_id: &[u8]) -> Result<Option<Credential>> {
+        // ---
+        let row = self
+            .read(|pool| async move {
+                sqlx::query_as!(
+                    CredentialRow,
+                    "SELECT id, user_id, public_key, counter, created_at, compromised_at, last_used_at,
+                            backup_eligible, backup_state
+                     FROM credentials WHERE id = $1 AND deleted_at IS NULL",
+                    credential_id,
+                )
+                .fetch_optional(&pool)
+                .await
+            })
+            .await?;
+
         Ok(row.map(|r| Credential {
             id: r.id,
             user_id: r.user_id,
             public_key: r.public_key,
             counter: r.counter,
             created_at: r.created_at,
+            compromised_at: r.compromised_at,
+            last_used_at: r.last_used_at,
+            backup_eligible: r.backup_eligible,
+            backup_state: r.backup_state,
         }))
     }
 
     async fn get_credentials_by_user(&self, user_id: Uuid) -> Result<Vec<Credential>> {
         // ---
-        let rows = sqlx::query_as::<_, CredentialRow>(
-            "SELECT id, user_id, public_key, counter, created_at
-             FROM credentials WHERE user_id = $1",
-        )
-        .bind(user_id)
-        .fetch_all(&self.pool)
-        .await?;
+        let rows = self
+            .read(|pool| async move {
+                sqlx::query_as!(
+                    CredentialRow,
+                    "SELECT id, user_id, public_key, counter, created_at, compromised_at, last_used_at,
+                            backup_eligible, backup_state
+                     FROM credentials WHERE user_id = $1 AND deleted_at IS NULL",
+                    user_id,
+                )
+                .fetch_all(&pool)
+                .await
+            })
+            .await?;
 
         Ok(rows
             .into_iter()
@@ -227,26 +591,394 @@ impl Repository for PostgresRepository {
                 public_key: r.public_key,
                 counter: r.counter,
                 created_at: r.created_at,
+                compromised_at: r.compromised_at,
+                last_used_at: r.last_used_at,
+                backup_eligible: r.backup_eligible,
+                backup_state: r.backup_state,
             })
             .collect())
     }
 
+    async fn credentials_modified_at(&self, user_id: Uuid) -> Result<Option<DateTime<Utc>>> {
+        // ---
+        // GREATEST skips NULLs, so each row counts its latest change
+        let modified_at = self
+            .read(|pool| async move {
+                sqlx::query_scalar!(
+                    "SELECT MAX(GREATEST(created_at, last_used_at, compromised_at, deleted_at))
+                     FROM credentials WHERE user_id = $1",
+                    user_id,
+                )
+                .fetch_one(&pool)
+                .await
+            })
+            .await?;
+
+        Ok(modified_at)
+    }
+
     async fn update_credential(&self, credential: Credential) -> Result<()> {
         // ---
-        sqlx::query("UPDATE credentials SET public_key = $1, counter = $2 WHERE id = $3")
-            .bind(&credential.public_key)
-            .bind(credential.counter)
-            .bind(&credential.id)
+        sqlx::query!(
+            "UPDATE credentials SET public_key = $1, counter = $2,
+                 backup_eligible = $3, backup_state = $4, last_used_at = $5
+             WHERE id = $6 AND deleted_at IS NULL",
+            credential.public_key,
+            credential.counter,
+            credential.backup_eligible,
+            credential.backup_state,
+            credential.last_used_at,
+            credential.id,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn delete_credential(&self, credential_id: &[u8]) -> Result<()> {
+        // ---
+        sqlx::query!("DELETE FROM credentials WHERE id = $1", credential_id)
             .execute(&self.pool)
             .await?;
 
         Ok(())
     }
 
-    async fn delete_credential(&self, credential_id: &[u8]) -> Result<()> {
+    async fn delete_credentials_by_user(&self, user_id: Uuid) -> Result<u64> {
+        // ---
+        let result = sqlx::query!("DELETE FROM credentials WHERE user_id = $1", user_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    async fn delete_user(&self, user_id: Uuid) -> Result<bool> {
+        // ---
+        // Credentials go with the user (ON DELETE CASCADE)
+        let result = sqlx::query!("DELETE FROM users WHERE id = $1", user_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn soft_delete_user(&self, user_id: Uuid) -> Result<bool> {
+        // ---
+        // Credentials share the user's timestamp so restore_user can tell
+        // them apart from ones the user had deleted individually.
+        let now = Utc::now();
+        let mut tx = self.pool.begin().await?;
+
+        let updated = sqlx::query!(
+            "UPDATE users SET deleted_at = $2 WHERE id = $1 AND deleted_at IS NULL",
+            user_id,
+            now,
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        if updated == 0 {
+            return Ok(false);
+        }
+
+        sqlx::query!(
+            "UPDATE credentials SET deleted_at = $2 WHERE user_id = $1 AND deleted_at IS NULL",
+            user_id,
+            now,
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    async fn restore_user(&self, user_id: Uuid) -> Result<bool> {
+        // ---
+        let mut tx = self.pool.begin().await?;
+
+        let deleted_at = sqlx::query_scalar!(
+            "SELECT deleted_at FROM users
+             WHERE id = $1 AND deleted_at IS NOT NULL FOR UPDATE",
+            user_id,
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .flatten();
+
+        let Some(deleted_at) = deleted_at else {
+            return Ok(false);
+        };
+
+        sqlx::query!("UPDATE users SET deleted_at = NULL WHERE id = $1", user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query!(
+            "UPDATE credentials SET deleted_at = NULL WHERE user_id = $1 AND deleted_at = $2",
+            user_id,
+            deleted_at,
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    async fn soft_delete_credential(&self, credential_id: &[u8]) -> Result<bool> {
+        // ---
+        soft_delete_credential_with(&self.pool, credential_id).await
+    }
+
+    async fn restore_credential(&self, credential_id: &[u8]) -> Result<bool> {
+        // ---
+        let result = sqlx::query!(
+            "UPDATE credentials SET deleted_at = NULL
+             WHERE id = $1 AND deleted_at IS NOT NULL
+               AND user_id IN (SELECT id FROM users WHERE deleted_at IS NULL)",
+            credential_id,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn mark_credential_compromised(&self, credential_id: &[u8]) -> Result<bool> {
+        // ---
+        mark_compromised_with(&self.pool, credential_id).await
+    }
+
+    async fn purge_deleted(&self, cutoff: DateTime<Utc>, dry_run: bool) -> Result<PurgeSummary> {
+        // ---
+        if dry_run {
+            let credentials = sqlx::query_scalar!(
+                r#"SELECT COUNT(*) AS "count!" FROM credentials WHERE deleted_at < $1"#,
+                cutoff,
+            )
+            .fetch_one(&self.pool)
+            .await?;
+            let users = sqlx::query_scalar!(
+                r#"SELECT COUNT(*) AS "count!" FROM users WHERE deleted_at < $1"#,
+                cutoff,
+            )
+            .fetch_one(&self.pool)
+            .await?;
+            return Ok(PurgeSummary {
+                users: users as u64,
+                credentials: credentials as u64,
+            });
+        }
+
+        let mut tx = self.pool.begin().await?;
+
+        // Credentials first so the count includes those deleted with their user
+        let credentials = sqlx::query!("DELETE FROM credentials WHERE deleted_at < $1", cutoff)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+
+        let users = sqlx::query!("DELETE FROM users WHERE deleted_at < $1", cutoff)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+
+        tx.commit().await?;
+        Ok(PurgeSummary { users, credentials })
+    }
+
+    async fn delete_orphaned_users(
+        &self,
+        created_before: DateTime<Utc>,
+        dry_run: bool,
+    ) -> Result<u64> {
+        // ---
+        // Soft-deleted credentials still count, so a user who removed their
+        // passkeys is not mistaken for an abandoned registration. Users
+        // with a password are not abandoned either.
+        if dry_run {
+            let count = sqlx::query_scalar!(
+                r#"SELECT COUNT(*) AS "count!" FROM users
+                 WHERE deleted_at IS NULL AND created_at < $1
+                   AND NOT EXISTS (SELECT 1 FROM credentials c WHERE c.user_id = users.id)
+                   AND NOT EXISTS (SELECT 1 FROM user_passwords p WHERE p.user_id = users.id)"#,
+                created_before,
+            )
+            .fetch_one(&self.pool)
+            .await?;
+            return Ok(count as u64);
+        }
+
+        let result = sqlx::query!(
+            "DELETE FROM users
+             WHERE deleted_at IS NULL AND created_at < $1
+               AND NOT EXISTS (SELECT 1 FROM credentials c WHERE c.user_id = users.id)
+               AND NOT EXISTS (SELECT 1 FROM user_passwords p WHERE p.user_id = users.id)",
+            created_before,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    async fn credential_stats(&self) -> Result<CredentialStats> {
+        // ---
+        let rows = self
+            .read(|pool| async move {
+                sqlx::query!(
+                    r#"SELECT n AS "n!", COUNT(*) AS "users!" FROM (
+                         SELECT COUNT(c.id) AS n FROM users u
+                         LEFT JOIN credentials c ON c.user_id = u.id AND c.deleted_at IS NULL
+                         WHERE u.deleted_at IS NULL GROUP BY u.id
+                     ) per_user
+                     GROUP BY n ORDER BY n"#,
+                )
+                .fetch_all(&pool)
+                .await
+            })
+            .await?;
+
+        Ok(CredentialStats::from_distribution(
+            rows.into_iter().map(|row| (row.n as u64, row.users as u64)),
+        ))
+    }
+
+    async fn list_all_credentials(
+        &self,
+        after: Option<&[u8]>,
+        limit: u32,
+    ) -> Result<Vec<Credential>> {
         // ---
-        sqlx::query("DELETE FROM credentials WHERE id = $1")
-            .bind(credential_id)
+        // Maintenance scans read the primary so they see every committed write.
+        let rows = sqlx::query_as!(
+            CredentialRow,
+            "SELECT id, user_id, public_key, counter, created_at, compromised_at, last_used_at,
+                    backup_eligible, backup_state
+             FROM credentials WHERE $1::bytea IS NULL OR id > $1
+             ORDER BY id LIMIT $2",
+            after,
+            i64::from(limit),
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|r| Credential {
+                id: r.id,
+                user_id: r.user_id,
+                public_key: r.public_key,
+                counter: r.counter,
+                created_at: r.created_at,
+                compromised_at: r.compromised_at,
+                last_used_at: r.last_used_at,
+                backup_eligible: r.backup_eligible,
+                backup_state: r.backup_state,
+            })
+            .collect())
+    }
+
+    async fn replace_public_key(
+        &self,
+        credential_id: &[u8],
+        expected: &[u8],
+        public_key: &[u8],
+    ) -> Result<bool> {
+        // ---
+        let result = sqlx::query!(
+            "UPDATE credentials SET public_key = $1 WHERE id = $2 AND public_key = $3",
+            public_key,
+            credential_id,
+            expected,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn write_with_event(&self, write: OutboxWrite, event: &OutboxEvent) -> Result<bool> {
+        // ---
+        let mut tx = self.pool.begin().await?;
+
+        let changed = match &write {
+            OutboxWrite::SaveCredential(credential) => {
+                insert_credential(&mut *tx, credential).await?;
+                true
+            }
+            OutboxWrite::SoftDeleteCredential(id) => {
+                soft_delete_credential_with(&mut *tx, id).await?
+            }
+            OutboxWrite::MarkCredentialCompromised(id) => {
+                mark_compromised_with(&mut *tx, id).await?
+            }
+            OutboxWrite::EventOnly => true,
+        };
+        if !changed {
+            return Ok(false);
+        }
+
+        sqlx::query!(
+            "INSERT INTO outbox (id, kind, payload, created_at, traceparent, tracestate)
+             VALUES ($1, $2, $3, $4, $5, $6)",
+            event.id,
+            event.kind,
+            event.payload,
+            event.created_at,
+            event.traceparent,
+            event.tracestate,
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    async fn claim_events(&self, limit: u32, lease: Duration) -> Result<Vec<OutboxEvent>> {
+        // ---
+        // SKIP LOCKED lets several instances drain the outbox concurrently
+        let rows = sqlx::query_as!(
+            OutboxRow,
+            "UPDATE outbox SET locked_until = NOW() + $2 * INTERVAL '1 millisecond',
+                               attempts = attempts + 1
+             WHERE id IN (
+                 SELECT id FROM outbox
+                 WHERE locked_until IS NULL OR locked_until < NOW()
+                 ORDER BY created_at LIMIT $1
+                 FOR UPDATE SKIP LOCKED
+             )
+             RETURNING id, kind, payload, created_at, attempts, traceparent, tracestate",
+            i64::from(limit),
+            lease.as_millis() as f64,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut events: Vec<OutboxEvent> = rows
+            .into_iter()
+            .map(|r| OutboxEvent {
+                id: r.id,
+                kind: r.kind,
+                payload: r.payload,
+                created_at: r.created_at,
+                attempts: r.attempts,
+                traceparent: r.traceparent,
+                tracestate: r.tracestate,
+            })
+            .collect();
+        events.sort_by_key(|event| event.created_at);
+        Ok(events)
+    }
+
+    async fn delete_event(&self, event_id: Uuid) -> Result<()> {
+        // ---
+        sqlx::query!("DELETE FROM outbox WHERE id = $1", event_id)
             .execute(&self.pool)
             .await?;
 
@@ -285,8 +1017,8 @@ mod schema_tests {
 
         // Insert user (raw SQL)
         sqlx::query(
-            "INSERT INTO users (id, username, created_at)
-             VALUES ($1, $2, NOW())",
+            "INSERT INTO users (id, username, username_normalized, created_at)
+             VALUES ($1, $2, $2, NOW())",
         )
         .bind(user_id)
         .bind("cascade_test_user")
@@ -302,7 +1034,7 @@ mod schema_tests {
         .bind(&credential_id)
         .bind(user_id)
         .bind(vec![9u8, 9, 9]) // ✅ Vec<u8> explicit
-        .bind(0_i32)
+        .bind(0_i64)
         .execute(&pool)
         .await
         .expect("Failed to insert credential");
@@ -336,4 +1068,39 @@ mod schema_tests {
             "credentials should be deleted via ON DELETE CASCADE"
         );
     }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn non_ascii_usernames_are_normalized_at_startup() {
+        // ---
+        let pool = test_pool().await;
+
+        // As the migration leaves it under a collation whose lower() is ASCII-only
+        let user_id = Uuid::new_v4();
+        let username = format!("\u{c9}owyn-{user_id}");
+        sqlx::query(
+            "INSERT INTO users (id, username, username_normalized, created_at)
+             VALUES ($1, $2, $2, NOW())",
+        )
+        .bind(user_id)
+        .bind(&username)
+        .execute(&pool)
+        .await
+        .expect("Failed to insert user");
+
+        super::backfill_normalized_usernames(&pool).await.unwrap();
+
+        let stored: String =
+            sqlx::query_scalar("SELECT username_normalized FROM users WHERE id = $1")
+                .bind(user_id)
+                .fetch_one(&pool)
+                .await
+                .expect("Failed to read user");
+        assert_eq!(stored, format!("\u{e9}owyn-{user_id}"));
+
+        sqlx::query("DELETE FROM users WHERE id = $1")
+            .bind(user_id)
+            .execute(&pool)
+            .await
+            .expect("Failed to delete user");
+    }
 }
@@ -0,0 +1,1290 @@
+//! SQLite implementation of the [`Repository`] trait.
+//!
+//! Intended for single-binary deployments and local development without
+//! Docker. Selected with `AXUM_REPOSITORY_TYPE=sqlite` and a
+//! `DATABASE_URL` such as `sqlite://axum.db`. The database file is created
+//! if missing and the SQLite migration set (`migrations/sqlite`) is
+//! embedded in the binary and applied at startup.
+
+use crate::DatabaseConfig;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+use sqlx::{QueryBuilder, Sqlite, SqliteExecutor};
+use std::str::FromStr;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::domain::{
+    normalize_username, Credential, CredentialStats, OutboxEvent, OutboxWrite, PurgeSummary,
+    Repository, User,
+};
+
+/// Rows per multi-row INSERT; 7 parameters each stays under the 999
+/// bound-parameter limit of older SQLite builds.
+const INSERT_BATCH_ROWS: usize = 128;
+
+#[derive(sqlx::FromRow)]
+struct UserRow {
+    id: Uuid,
+    tenant_id: String,
+    username: String,
+    created_at: DateTime<Utc>,
+}
+
+#[derive(sqlx::FromRow)]
+struct OutboxRow {
+    id: Uuid,
+    kind: String,
+    payload: String,
+    created_at: DateTime<Utc>,
+    attempts: i32,
+    traceparent: Option<String>,
+    tracestate: Option<String>,
+}
+
+#[derive(sqlx::FromRow)]
+struct CredentialRow {
+    id: Vec<u8>,
+    user_id: Uuid,
+    public_key: Vec<u8>,
+    counter: i64,
+    created_at: DateTime<Utc>,
+    compromised_at: Option<DateTime<Utc>>,
+    last_used_at: Option<DateTime<Utc>>,
+    backup_eligible: Option<bool>,
+    backup_state: Option<bool>,
+}
+
+/// The timestamps of a credential that change when it does.
+#[derive(sqlx::FromRow)]
+struct ModifiedRow {
+    created_at: DateTime<Utc>,
+    last_used_at: Option<DateTime<Utc>>,
+    compromised_at: Option<DateTime<Utc>>,
+    deleted_at: Option<DateTime<Utc>>,
+}
+
+/// Open (creating if missing) `cfg.database_url` and apply the embedded migrations.
+///
+/// Unlike PostgreSQL there is no server to wait for, so this does not retry.
+pub async fn connect(cfg: &DatabaseConfig) -> Result<SqlitePool> {
+    // ---
+    tracing::info!(
+        "axum-quickstart opening SQLite database at: {:?}",
+        cfg.database_url
+    );
+
+    let options = SqliteConnectOptions::from_str(&cfg.database_url)?
+        .create_if_missing(true)
+        .foreign_keys(true);
+
+    let pool = SqlitePoolOptions::new()
+        .max_connections(cfg.max_connections)
+        .min_connections(cfg.min_connections)
+        .acquire_timeout(cfg.acquire_timeout)
+        .connect_with(options)
+        .await
+        .map_err(|e| anyhow!("Failed to open SQLite database: {e}"))?;
+
+    migrate(&pool).await?;
+    backfill_normalized_usernames(&pool).await?;
+
+    Ok(pool)
+}
+
+/// Apply the embedded migrations with foreign keys switched off.
+///
+/// Changing a column constraint in SQLite means rebuilding the table, and
+/// dropping the old `users` table would otherwise cascade to `credentials`.
+/// The pragma has no effect inside the transaction each migration runs in,
+/// so it is set on the connection beforehand, and the result is checked
+/// before foreign keys are switched back on.
+async fn migrate(pool: &SqlitePool) -> Result<()> {
+    // ---
+    let mut conn = pool.acquire().await?;
+    sqlx::query("PRAGMA foreign_keys = OFF")
+        .execute(&mut *conn)
+        .await?;
+
+    let migrated = sqlx::migrate!("./migrations/sqlite").run(&mut *conn).await;
+    let violations = sqlx::query("PRAGMA foreign_key_check")
+        .fetch_all(&mut *conn)
+        .await;
+
+    sqlx::query("PRAGMA foreign_keys = ON")
+        .execute(&mut *conn)
+        .await?;
+    migrated?;
+    if !violations?.is_empty() {
+        return Err(anyhow!("SQLite migrations left foreign key violations"));
+    }
+    Ok(())
+}
+
+/// Completes `username_normalized` for usernames with non-ASCII characters.
+///
+/// The migration adding the column filled it with SQLite's `lower()`, which
+/// only folds ASCII, so those users would not be found by
+/// [`normalize_username`] and signing in would create a second account.
+/// Rows that already match are skipped, so after the first start this only
+/// reads.
+///
+/// # Errors
+/// Returns an error if two users of one tenant collide once normalized;
+/// rename one of them first.
+async fn backfill_normalized_usernames(pool: &SqlitePool) -> Result<()> {
+    // ---
+    let rows: Vec<(Uuid, String, String)> = sqlx::query_as(
+        "SELECT id, username, username_normalized FROM users WHERE username GLOB '*[^ -~]*'",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    for (id, username, stored) in rows {
+        let normalized = normalize_username(&username);
+        if normalized == stored {
+            continue;
+        }
+        sqlx::query("UPDATE users SET username_normalized = ? WHERE id = ?")
+            .bind(&normalized)
+            .bind(id)
+            .execute(pool)
+            .await
+            .map_err(|e| anyhow!("Failed to normalize username {username:?}: {e}"))?;
+    }
+    Ok(())
+}
+
+/// SQLite-backed [`Repository`]. Owns its connection pool.
+pub struct SqliteRepository {
+    // ---
+    pool: SqlitePool,
+}
+
+impl SqliteRepository {
+    // ---
+    /// Opens the database described by `cfg` and returns a repository owning the pool.
+    pub async fn connect(cfg: &DatabaseConfig) -> Result<Self> {
+        // ---
+        Ok(Self::new(connect(cfg).await?))
+    }
+
+    pub fn new(pool: SqlitePool) -> Self {
+        // ---
+        Self { pool }
+    }
+}
+
+impl From<CredentialRow> for Credential {
+    // ---
+    fn from(r: CredentialRow) -> Self {
+        // ---
+        Credential {
+            id: r.id,
+            user_id: r.user_id,
+            public_key: r.public_key,
+            counter: r.counter,
+            created_at: r.created_at,
+            compromised_at: r.compromised_at,
+            last_used_at: r.last_used_at,
+            backup_eligible: r.backup_eligible,
+            backup_state: r.backup_state,
+        }
+    }
+}
+
+impl From<OutboxRow> for OutboxEvent {
+    // ---
+    fn from(r: OutboxRow) -> Self {
+        // ---
+        OutboxEvent {
+            id: r.id,
+            kind: r.kind,
+            payload: r.payload,
+            created_at: r.created_at,
+            attempts: r.attempts,
+            traceparent: r.traceparent,
+            tracestate: r.tracestate,
+        }
+    }
+}
+
+impl From<UserRow> for User {
+    // ---
+    fn from(r: UserRow) -> Self {
+        // ---
+        User {
+            id: r.id,
+            tenant_id: r.tenant_id,
+            username: r.username,
+            created_at: r.created_at,
+        }
+    }
+}
+
+// Writes that can also run inside an outbox transaction
+
+async fn insert_credential<'e>(
+    executor: impl SqliteExecutor<'e>,
+    credential: &Credential,
+) -> Result<()> {
+    // ---
+    sqlx::query(
+        "INSERT INTO credentials
+             (id, user_id, public_key, counter, created_at, backup_eligible, backup_state)
+         VALUES (?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(&credential.id)
+    .bind(credential.user_id)
+    .bind(&credential.public_key)
+    .bind(credential.counter)
+    .bind(credential.created_at)
+    .bind(credential.backup_eligible)
+    .bind(credential.backup_state)
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+async fn soft_delete_credential_with<'e>(
+    executor: impl SqliteExecutor<'e>,
+    credential_id: &[u8],
+) -> Result<bool> {
+    // ---
+    let result =
+        sqlx::query("UPDATE credentials SET deleted_at = ? WHERE id = ? AND deleted_at IS NULL")
+            .bind(Utc::now())
+            .bind(credential_id)
+            .execute(executor)
+            .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+async fn mark_compromised_with<'e>(
+    executor: impl SqliteExecutor<'e>,
+    credential_id: &[u8],
+) -> Result<bool> {
+    // ---
+    let result = sqlx::query(
+        "UPDATE credentials SET compromised_at = ? WHERE id = ? AND compromised_at IS NULL",
+    )
+    .bind(Utc::now())
+    .bind(credential_id)
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+#[async_trait::async_trait]
+impl Repository for SqliteRepository {
+    // ---
+    async fn ping(&self) -> Result<()> {
+        // ---
+        sqlx::query("SELECT 1").execute(&self.pool).await?;
+        Ok(())
+    }
+
+    async fn create_user_in(&self, tenant_id: &str, username: &str) -> Result<User> {
+        // ---
+        let user = User::new(tenant_id.to_string(), username.to_string());
+
+        sqlx::query(
+            "INSERT INTO users (id, tenant_id, username, username_normalized, created_at)
+             VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(user.id)
+        .bind(&user.tenant_id)
+        .bind(&user.username)
+        .bind(normalize_username(username))
+        .bind(user.created_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(user)
+    }
+
+    async fn get_user_by_username_in(
+        &self,
+        tenant_id: &str,
+        username: &str,
+    ) -> Result<Option<User>> {
+        // ---
+        let row = sqlx::query_as::<_, UserRow>(
+            "SELECT id, tenant_id, username, created_at FROM users
+             WHERE tenant_id = ? AND username_normalized = ? AND deleted_at IS NULL",
+        )
+        .bind(tenant_id)
+        .bind(normalize_username(username))
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(User::from))
+    }
+
+    async fn get_or_create_user_in(&self, tenant_id: &str, username: &str) -> Result<User> {
+        // ---
+        let user = User::new(tenant_id.to_string(), username.to_string());
+        let normalized = normalize_username(username);
+
+        let inserted = sqlx::query_as::<_, UserRow>(
+            "INSERT INTO users (id, tenant_id, username, username_normalized, created_at)
+             VALUES (?, ?, ?, ?, ?)
+             ON CONFLICT (tenant_id, username_normalized) DO NOTHING
+             RETURNING id, tenant_id, username, created_at",
+        )
+        .bind(user.id)
+        .bind(&user.tenant_id)
+        .bind(&user.username)
+        .bind(&normalized)
+        .bind(user.created_at)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let row = match inserted {
+            Some(row) => row,
+            None => sqlx::query_as::<_, UserRow>(
+                "SELECT id, tenant_id, username, created_at FROM users
+                 WHERE tenant_id = ? AND username_normalized = ? AND deleted_at IS NULL",
+            )
+            .bind(tenant_id)
+            .bind(&normalized)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or_else(|| anyhow!("Username is reserved by a deleted user"))?,
+        };
+
+        Ok(User::from(row))
+    }
+
+    async fn get_user_by_id(&self, user_id: Uuid) -> Result<Option<User>> {
+        // ---
+        let row = sqlx::query_as::<_, UserRow>(
+            "SELECT id, tenant_id, username, created_at FROM users WHERE id = ? AND deleted_at IS NULL",
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(User::from))
+    }
+
+    async fn set_password_hash(&self, user_id: Uuid, password_hash: &str) -> Result<bool> {
+        // ---
+        let result = sqlx::query(
+            "INSERT INTO user_passwords (user_id, password_hash)
+             SELECT id, ? FROM users WHERE id = ? AND deleted_at IS NULL
+             ON CONFLICT (user_id)
+             DO UPDATE SET password_hash = excluded.password_hash, updated_at = ?",
+        )
+        .bind(password_hash)
+        .bind(user_id)
+        .bind(Utc::now())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn get_password_hash(&self, user_id: Uuid) -> Result<Option<String>> {
+        // ---
+        let hash = sqlx::query_scalar(
+            "SELECT p.password_hash FROM user_passwords p
+             JOIN users u ON u.id = p.user_id
+             WHERE p.user_id = ? AND u.deleted_at IS NULL",
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(hash)
+    }
+
+    async fn save_credential(&self, credential: Credential) -> Result<()> {
+        // ---
+        insert_credential(&self.pool, &credential).await
+    }
+
+    async fn save_credentials(&self, credentials: Vec<Credential>) -> Result<()> {
+        // ---
+        let mut tx = self.pool.begin().await?;
+
+        // Multi-row INSERTs, kept under SQLite's limit on bound parameters
+        for batch in credentials.chunks(INSERT_BATCH_ROWS) {
+            let mut insert = QueryBuilder::<Sqlite>::new(
+                "INSERT INTO credentials
+                     (id, user_id, public_key, counter, created_at, backup_eligible, backup_state) ",
+            );
+            insert.push_values(batch, |mut row, credential| {
+                row.push_bind(&credential.id)
+                    .push_bind(credential.user_id)
+                    .push_bind(&credential.public_key)
+                    .push_bind(credential.counter)
+                    .push_bind(credential.created_at)
+                    .push_bind(credential.backup_eligible)
+                    .push_bind(credential.backup_state);
+            });
+            insert.build().execute(&mut *tx).await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn get_credential_by_id(&self, credential_id: &[u8]) -> Result<Option<Credential>> {
+        // ---
+        let row = sqlx::query_as::<_, CredentialRow>(
+            "SELECT id, user_id, public_key, counter, created_at, compromised_at, last_used_at,
+                    backup_eligible, backup_state
+             FROM credentials WHERE id = ? AND deleted_at IS NULL",
+        )
+        .bind(credential_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(Credential::from))
+    }
+
+    async fn get_credentials_by_user(&self, user_id: Uuid) -> Result<Vec<Credential>> {
+        // ---
+        let rows = sqlx::query_as::<_, CredentialRow>(
+            "SELECT id, user_id, public_key, counter, created_at, compromised_at, last_used_at,
+                    backup_eligible, backup_state
+             FROM credentials WHERE user_id = ? AND deleted_at IS NULL",
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(Credential::from).collect())
+    }
+
+    async fn credentials_modified_at(&self, user_id: Uuid) -> Result<Option<DateTime<Utc>>> {
+        // ---
+        // Compared after decoding: the columns mix SQLite's and sqlx's
+        // timestamp text formats
+        let rows = sqlx::query_as::<_, ModifiedRow>(
+            "SELECT created_at, last_used_at, compromised_at, deleted_at
+             FROM credentials WHERE user_id = ?",
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .flat_map(|r| {
+                [
+                    Some(r.created_at),
+                    r.last_used_at,
+                    r.compromised_at,
+                    r.deleted_at,
+                ]
+            })
+            .flatten()
+            .max())
+    }
+
+    async fn update_credential(&self, credential: Credential) -> Result<()> {
+        // ---
+        sqlx::query(
+            "UPDATE credentials SET public_key = ?, counter = ?,
+                 backup_eligible = ?, backup_state = ?, last_used_at = ?
+             WHERE id = ? AND deleted_at IS NULL",
+        )
+        .bind(&credential.public_key)
+        .bind(credential.counter)
+        .bind(credential.backup_eligible)
+        .bind(credential.backup_state)
+        .bind(credential.last_used_at)
+        .bind(&credential.id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn delete_credential(&self, credential_id: &[u8]) -> Result<()> {
+        // ---
+        sqlx::query("DELETE FROM credentials WHERE id = ?")
+            .bind(credential_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn delete_credentials_by_user(&self, user_id: Uuid) -> Result<u64> {
+        // ---
+        let result = sqlx::query("DELETE FROM credentials WHERE user_id = ?")
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    async fn delete_user(&self, user_id: Uuid) -> Result<bool> {
+        // ---
+        // Credentials go with the user (ON DELETE CASCADE)
+        let result = sqlx::query("DELETE FROM users WHERE id = ?")
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn soft_delete_user(&self, user_id: Uuid) -> Result<bool> {
+        // ---
+        // Credentials share the user's timestamp so restore_user can tell
+        // them apart from ones the user had deleted individually.
+        let now = Utc::now();
+        let mut tx = self.pool.begin().await?;
+
+        let updated =
+            sqlx::query("UPDATE users SET deleted_at = ? WHERE id = ? AND deleted_at IS NULL")
+                .bind(now)
+                .bind(user_id)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+
+        if updated == 0 {
+            return Ok(false);
+        }
+
+        sqlx::query(
+            "UPDATE credentials SET deleted_at = ? WHERE user_id = ? AND deleted_at IS NULL",
+        )
+        .bind(now)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    async fn restore_user(&self, user_id: Uuid) -> Result<bool> {
+        // ---
+        let mut tx = self.pool.begin().await?;
+
+        let deleted_at: Option<String> = sqlx::query_scalar(
+            "SELECT deleted_at FROM users WHERE id = ? AND deleted_at IS NOT NULL",
+        )
+        .bind(user_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(deleted_at) = deleted_at else {
+            return Ok(false);
+        };
+
+        sqlx::query("UPDATE users SET deleted_at = NULL WHERE id = ?")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query(
+            "UPDATE credentials SET deleted_at = NULL WHERE user_id = ? AND deleted_at = ?",
+        )
+        .bind(user_id)
+        .bind(deleted_at)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    async fn soft_delete_credential(&self, credential_id: &[u8]) -> Result<bool> {
+        // ---
+        soft_delete_credential_with(&self.pool, credential_id).await
+    }
+
+    async fn restore_credential(&self, credential_id: &[u8]) -> Result<bool> {
+        // ---
+        let result = sqlx::query(
+            "UPDATE credentials SET deleted_at = NULL
+             WHERE id = ? AND deleted_at IS NOT NULL
+               AND user_id IN (SELECT id FROM users WHERE deleted_at IS NULL)",
+        )
+        .bind(credential_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn mark_credential_compromised(&self, credential_id: &[u8]) -> Result<bool> {
+        // ---
+        mark_compromised_with(&self.pool, credential_id).await
+    }
+
+    async fn purge_deleted(&self, cutoff: DateTime<Utc>, dry_run: bool) -> Result<PurgeSummary> {
+        // ---
+        if dry_run {
+            let credentials: i64 =
+                sqlx::query_scalar("SELECT COUNT(*) FROM credentials WHERE deleted_at < ?")
+                    .bind(cutoff)
+                    .fetch_one(&self.pool)
+                    .await?;
+            let users: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM users WHERE deleted_at < ?")
+                .bind(cutoff)
+                .fetch_one(&self.pool)
+                .await?;
+            return Ok(PurgeSummary {
+                users: users as u64,
+                credentials: credentials as u64,
+            });
+        }
+
+        let mut tx = self.pool.begin().await?;
+
+        // Credentials first so the count includes those deleted with their user
+        let credentials = sqlx::query("DELETE FROM credentials WHERE deleted_at < ?")
+            .bind(cutoff)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+
+        let users = sqlx::query("DELETE FROM users WHERE deleted_at < ?")
+            .bind(cutoff)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+
+        tx.commit().await?;
+        Ok(PurgeSummary { users, credentials })
+    }
+
+    async fn delete_orphaned_users(
+        &self,
+        created_before: DateTime<Utc>,
+        dry_run: bool,
+    ) -> Result<u64> {
+        // ---
+        // Soft-deleted credentials still count, so a user who removed their
+        // passkeys is not mistaken for an abandoned registration. Users
+        // with a password are not abandoned either.
+        if dry_run {
+            let count: i64 = sqlx::query_scalar(
+                "SELECT COUNT(*) FROM users
+                 WHERE deleted_at IS NULL AND created_at < ?
+                   AND NOT EXISTS (SELECT 1 FROM credentials c WHERE c.user_id = users.id)
+                   AND NOT EXISTS (SELECT 1 FROM user_passwords p WHERE p.user_id = users.id)",
+            )
+            .bind(created_before)
+            .fetch_one(&self.pool)
+            .await?;
+            return Ok(count as u64);
+        }
+
+        let result = sqlx::query(
+            "DELETE FROM users
+             WHERE deleted_at IS NULL AND created_at < ?
+               AND NOT EXISTS (SELECT 1 FROM credentials c WHERE c.user_id = users.id)
+               AND NOT EXISTS (SELECT 1 FROM user_passwords p WHERE p.user_id = users.id)",
+        )
+        .bind(created_before)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    async fn credential_stats(&self) -> Result<CredentialStats> {
+        // ---
+        let rows: Vec<(i64, i64)> = sqlx::query_as(
+            "SELECT n, COUNT(*) FROM (
+                 SELECT COUNT(c.id) AS n FROM users u
+                 LEFT JOIN credentials c ON c.user_id = u.id AND c.deleted_at IS NULL
+                 WHERE u.deleted_at IS NULL GROUP BY u.id
+             ) per_user
+             GROUP BY n ORDER BY n",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(CredentialStats::from_distribution(
+            rows.into_iter().map(|(n, users)| (n as u64, users as u64)),
+        ))
+    }
+
+    async fn list_all_credentials(
+        &self,
+        after: Option<&[u8]>,
+        limit: u32,
+    ) -> Result<Vec<Credential>> {
+        // ---
+        let rows = sqlx::query_as::<_, CredentialRow>(
+            "SELECT id, user_id, public_key, counter, created_at, compromised_at, last_used_at,
+                    backup_eligible, backup_state
+             FROM credentials WHERE ?1 IS NULL OR id > ?1
+             ORDER BY id LIMIT ?2",
+        )
+        .bind(after)
+        .bind(i64::from(limit))
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(Credential::from).collect())
+    }
+
+    async fn replace_public_key(
+        &self,
+        credential_id: &[u8],
+        expected: &[u8],
+        public_key: &[u8],
+    ) -> Result<bool> {
+        // ---
+        let result =
+            sqlx::query("UPDATE credentials SET public_key = ? WHERE id = ? AND public_key = ?")
+                .bind(public_key)
+                .bind(credential_id)
+                .bind(expected)
+                .execute(&self.pool)
+                .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn write_with_event(&self, write: OutboxWrite, event: &OutboxEvent) -> Result<bool> {
+        // ---
+        let mut tx = self.pool.begin().await?;
+
+        let changed = match &write {
+            OutboxWrite::SaveCredential(credential) => {
+                insert_credential(&mut *tx, credential).await?;
+                true
+            }
+            OutboxWrite::SoftDeleteCredential(id) => {
+                soft_delete_credential_with(&mut *tx, id).await?
+            }
+            OutboxWrite::MarkCredentialCompromised(id) => {
+                mark_compromised_with(&mut *tx, id).await?
+            }
+            OutboxWrite::EventOnly => true,
+        };
+        if !changed {
+            return Ok(false);
+        }
+
+        sqlx::query(
+            "INSERT INTO outbox (id, kind, payload, created_at, traceparent, tracestate)
+             VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(event.id)
+        .bind(&event.kind)
+        .bind(&event.payload)
+        .bind(event.created_at)
+        .bind(&event.traceparent)
+        .bind(&event.tracestate)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    async fn claim_events(&self, limit: u32, lease: Duration) -> Result<Vec<OutboxEvent>> {
+        // ---
+        // SQLite serializes writers, so the claim cannot race another one
+        let now = Utc::now();
+        let rows = sqlx::query_as::<_, OutboxRow>(
+            "UPDATE outbox SET locked_until = ?, attempts = attempts + 1
+             WHERE id IN (
+                 SELECT id FROM outbox
+                 WHERE locked_until IS NULL OR locked_until < ?
+                 ORDER BY created_at LIMIT ?
+             )
+             RETURNING id, kind, payload, created_at, attempts, traceparent, tracestate",
+        )
+        .bind(now + lease)
+        .bind(now)
+        .bind(i64::from(limit))
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut events: Vec<OutboxEvent> = rows.into_iter().map(OutboxEvent::from).collect();
+        events.sort_by_key(|event| event.created_at);
+        Ok(events)
+    }
+
+    async fn delete_event(&self, event_id: Uuid) -> Result<()> {
+        // ---
+        sqlx::query("DELETE FROM outbox WHERE id = ?")
+            .bind(event_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+    use crate::RepositoryType;
+
+    /// Fresh in-memory database per test. A single connection is required
+    /// because every `sqlite::memory:` connection is its own database.
+    async fn memory_repo() -> SqliteRepository {
+        // ---
+        let cfg = DatabaseConfig {
+            repository_type: RepositoryType::Sqlite,
+            database_url: "sqlite::memory:".to_string(),
+            read_url: None,
+            retry_count: 1,
+            acquire_timeout: Duration::from_secs(5),
+            min_connections: 1,
+            max_connections: 1,
+            slow_query_threshold: None,
+        };
+        SqliteRepository::connect(&cfg)
+            .await
+            .expect("sqlite connect failed")
+    }
+
+    #[tokio::test]
+    async fn create_and_get_user() {
+        // ---
+        let repo = memory_repo().await;
+
+        let user = repo.create_user("Bilbo").await.unwrap();
+
+        let by_name = repo.get_user_by_username("Bilbo").await.unwrap().unwrap();
+        assert_eq!(by_name.id, user.id);
+
+        let by_id = repo.get_user_by_id(user.id).await.unwrap().unwrap();
+        assert_eq!(by_id.username, "Bilbo");
+
+        assert!(repo.get_user_by_username("Smaug").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn username_must_be_unique() {
+        // ---
+        let repo = memory_repo().await;
+
+        repo.create_user("Frodo").await.unwrap();
+        assert!(repo.create_user("Frodo").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn usernames_match_ignoring_case_and_normalization() {
+        // ---
+        let repo = memory_repo().await;
+
+        let user = repo.create_user("\u{c9}owyn").await.unwrap();
+        let found = repo
+            .get_user_by_username("e\u{301}OWYN")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.id, user.id);
+        assert_eq!(found.username, "\u{c9}owyn");
+        assert!(repo.create_user("éowyn").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn non_ascii_usernames_are_normalized_after_migrating() {
+        // ---
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+
+        // A user created before usernames were normalized
+        let migrations = sqlx::migrate!("./migrations/sqlite");
+        let before = sqlx::migrate::Migrator {
+            migrations: migrations
+                .iter()
+                .filter(|migration| migration.version < 20250101000010)
+                .cloned()
+                .collect::<Vec<_>>()
+                .into(),
+            ..sqlx::migrate::Migrator::DEFAULT
+        };
+        before.run(&pool).await.unwrap();
+        let id = Uuid::new_v4();
+        sqlx::query("INSERT INTO users (id, username) VALUES (?, ?)")
+            .bind(id)
+            .bind("\u{c9}owyn")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        migrate(&pool).await.unwrap();
+        backfill_normalized_usernames(&pool).await.unwrap();
+        let repo = SqliteRepository::new(pool);
+
+        let found = repo.get_user_by_username("e\u{301}OWYN").await.unwrap();
+        assert_eq!(found.map(|user| user.id), Some(id));
+        assert_eq!(repo.get_or_create_user("\u{e9}owyn").await.unwrap().id, id);
+    }
+
+    #[tokio::test]
+    async fn get_or_create_user_returns_the_existing_user() {
+        // ---
+        let repo = memory_repo().await;
+
+        let created = repo.get_or_create_user("Pippin").await.unwrap();
+        let again = repo.get_or_create_user("PIPPIN").await.unwrap();
+        assert_eq!(again.id, created.id);
+        assert_eq!(again.username, "Pippin");
+
+        assert!(repo.soft_delete_user(created.id).await.unwrap());
+        assert!(repo.get_or_create_user("Pippin").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn usernames_are_scoped_to_a_tenant() {
+        // ---
+        let repo = memory_repo().await;
+
+        let shire = repo.create_user_in("shire", "Merry").await.unwrap();
+        let bree = repo.create_user_in("bree", "Merry").await.unwrap();
+        assert_ne!(shire.id, bree.id);
+        assert!(repo.create_user_in("shire", "Merry").await.is_err());
+
+        let found = repo
+            .get_user_by_username_in("bree", "Merry")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.id, bree.id);
+        assert_eq!(found.tenant_id, "bree");
+        assert!(repo.get_user_by_username("Merry").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn batch_save_and_delete_by_user() {
+        // ---
+        let repo = memory_repo().await;
+        let user = repo.create_user("Gaffer").await.unwrap();
+
+        // More rows than one INSERT takes
+        let credentials: Vec<Credential> = (0..INSERT_BATCH_ROWS as u32 + 2)
+            .map(|i| Credential::new(i.to_be_bytes().to_vec(), user.id, vec![1], 0))
+            .collect();
+        repo.save_credentials(credentials).await.unwrap();
+        assert_eq!(
+            repo.get_credentials_by_user(user.id).await.unwrap().len(),
+            INSERT_BATCH_ROWS + 2
+        );
+
+        // A duplicate ID rejects the whole batch
+        let batch = vec![
+            Credential::new(vec![0xff], user.id, vec![1], 0),
+            Credential::new(0u32.to_be_bytes().to_vec(), user.id, vec![1], 0),
+        ];
+        assert!(repo.save_credentials(batch).await.is_err());
+        assert!(repo.get_credential_by_id(&[0xff]).await.unwrap().is_none());
+
+        assert!(repo
+            .soft_delete_credential(&0u32.to_be_bytes())
+            .await
+            .unwrap());
+        assert_eq!(
+            repo.delete_credentials_by_user(user.id).await.unwrap(),
+            INSERT_BATCH_ROWS as u64 + 2
+        );
+        assert!(repo.get_user_by_id(user.id).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn credential_round_trip_and_delete() {
+        // ---
+        let repo = memory_repo().await;
+        let user = repo.create_user("Sam").await.unwrap();
+
+        let mut credential = Credential::new(vec![1, 2, 3], user.id, vec![9, 9, 9], 0);
+        repo.save_credential(credential.clone()).await.unwrap();
+
+        credential.counter = u32::MAX.into();
+        credential.backup_eligible = Some(true);
+        credential.backup_state = Some(false);
+        repo.update_credential(credential.clone()).await.unwrap();
+
+        let found = repo
+            .get_credential_by_id(&[1, 2, 3])
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.user_id, user.id);
+        assert_eq!(found.counter, i64::from(u32::MAX));
+        assert_eq!(found.backup_eligible, Some(true));
+        assert_eq!(found.backup_state, Some(false));
+
+        assert_eq!(
+            repo.get_credentials_by_user(user.id).await.unwrap().len(),
+            1
+        );
+
+        repo.delete_credential(&[1, 2, 3]).await.unwrap();
+        assert!(repo
+            .get_credential_by_id(&[1, 2, 3])
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn credentials_modified_at_follows_sign_ins_and_deletes() {
+        // ---
+        let repo = memory_repo().await;
+        let user = repo.create_user("Rosie").await.unwrap();
+        assert!(repo
+            .credentials_modified_at(user.id)
+            .await
+            .unwrap()
+            .is_none());
+
+        let mut credential = Credential::new(vec![4], user.id, vec![1], 0);
+        repo.save_credential(credential.clone()).await.unwrap();
+        let created = repo
+            .credentials_modified_at(user.id)
+            .await
+            .unwrap()
+            .unwrap();
+
+        let used_at = created + chrono::Duration::minutes(5);
+        credential.last_used_at = Some(used_at);
+        repo.update_credential(credential).await.unwrap();
+        assert_eq!(
+            repo.credentials_modified_at(user.id).await.unwrap(),
+            Some(used_at)
+        );
+        let found = repo.get_credential_by_id(&[4]).await.unwrap().unwrap();
+        assert_eq!(found.last_used_at, Some(used_at));
+
+        assert!(repo.soft_delete_credential(&[4]).await.unwrap());
+        assert!(repo.credentials_modified_at(user.id).await.unwrap() >= Some(created));
+    }
+
+    #[tokio::test]
+    async fn delete_user_removes_credentials() {
+        // ---
+        let repo = memory_repo().await;
+        let user = repo.create_user("Pippin").await.unwrap();
+        repo.save_credential(Credential::new(vec![7], user.id, vec![1], 0))
+            .await
+            .unwrap();
+        repo.save_credential(Credential::new(vec![8], user.id, vec![1], 0))
+            .await
+            .unwrap();
+        assert!(repo.soft_delete_credential(&[8]).await.unwrap());
+
+        assert!(repo.delete_user(user.id).await.unwrap());
+        assert!(!repo.delete_user(user.id).await.unwrap());
+        assert!(repo
+            .list_all_credentials(None, 10)
+            .await
+            .unwrap()
+            .is_empty());
+
+        // The username is free again
+        repo.create_user("Pippin").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn soft_delete_restore_and_purge() {
+        // ---
+        let repo = memory_repo().await;
+        let user = repo.create_user("Merry").await.unwrap();
+        repo.save_credential(Credential::new(vec![5], user.id, vec![1], 0))
+            .await
+            .unwrap();
+        repo.save_credential(Credential::new(vec![6], user.id, vec![1], 0))
+            .await
+            .unwrap();
+
+        // Individually deleted credential stays deleted when the user is restored
+        assert!(repo.soft_delete_credential(&[5]).await.unwrap());
+        assert!(repo.soft_delete_user(user.id).await.unwrap());
+        assert!(repo.get_user_by_id(user.id).await.unwrap().is_none());
+        assert!(repo.get_credential_by_id(&[6]).await.unwrap().is_none());
+        assert!(!repo.restore_credential(&[6]).await.unwrap());
+
+        assert!(repo.restore_user(user.id).await.unwrap());
+        assert!(repo.get_user_by_username("Merry").await.unwrap().is_some());
+        assert!(repo.get_credential_by_id(&[6]).await.unwrap().is_some());
+        assert!(repo.get_credential_by_id(&[5]).await.unwrap().is_none());
+
+        // Nothing is older than a cutoff in the past
+        let past = Utc::now() - chrono::Duration::days(1);
+        assert_eq!(
+            repo.purge_deleted(past, false).await.unwrap(),
+            PurgeSummary::default()
+        );
+
+        // A dry run counts without deleting
+        repo.soft_delete_user(user.id).await.unwrap();
+        let counted = repo.purge_deleted(Utc::now(), true).await.unwrap();
+        assert_eq!(counted.users, 1);
+        assert_eq!(counted.credentials, 2);
+
+        let summary = repo.purge_deleted(Utc::now(), false).await.unwrap();
+        assert_eq!(summary, counted);
+        assert_eq!(summary.users, 1);
+        assert_eq!(summary.credentials, 2);
+        assert!(!repo.restore_user(user.id).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn orphaned_users_deleted_unless_dry_run() {
+        // ---
+        let repo = memory_repo().await;
+        let orphan = repo.create_user("Pippin").await.unwrap();
+        let owner = repo.create_user("Rosie").await.unwrap();
+        repo.save_credential(Credential::new(vec![7], owner.id, vec![1], 0))
+            .await
+            .unwrap();
+
+        // Too young to be removed
+        let past = Utc::now() - chrono::Duration::hours(1);
+        assert_eq!(repo.delete_orphaned_users(past, false).await.unwrap(), 0);
+
+        let cutoff = Utc::now() + chrono::Duration::seconds(1);
+        assert_eq!(repo.delete_orphaned_users(cutoff, true).await.unwrap(), 1);
+        assert!(repo.get_user_by_id(orphan.id).await.unwrap().is_some());
+
+        assert_eq!(repo.delete_orphaned_users(cutoff, false).await.unwrap(), 1);
+        assert!(repo.get_user_by_id(orphan.id).await.unwrap().is_none());
+        assert!(repo.get_user_by_id(owner.id).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn credential_stats_count_active_rows() {
+        // ---
+        let repo = memory_repo().await;
+        repo.create_user("Pippin").await.unwrap();
+        let owner = repo.create_user("Rosie").await.unwrap();
+        for id in [7, 8, 9] {
+            repo.save_credential(Credential::new(vec![id], owner.id, vec![1], 0))
+                .await
+                .unwrap();
+        }
+        repo.soft_delete_credential(&[9]).await.unwrap();
+
+        let stats = repo.credential_stats().await.unwrap();
+        assert_eq!(stats.users, 2);
+        assert_eq!(stats.credentials, 2);
+        assert_eq!(
+            stats.users_by_credential_count,
+            [(0, 1), (2, 1)].into_iter().collect()
+        );
+    }
+
+    #[tokio::test]
+    async fn list_all_and_replace_public_key() {
+        // ---
+        let repo = memory_repo().await;
+        let user = repo.create_user("Lobelia").await.unwrap();
+        for id in [3u8, 1, 2] {
+            repo.save_credential(Credential::new(vec![id], user.id, vec![id], 0))
+                .await
+                .unwrap();
+        }
+        repo.soft_delete_credential(&[2]).await.unwrap();
+
+        let first = repo.list_all_credentials(None, 2).await.unwrap();
+        let ids: Vec<_> = first.iter().map(|c| c.id.clone()).collect();
+        assert_eq!(ids, vec![vec![1], vec![2]]);
+        let rest = repo.list_all_credentials(Some(&[2]), 2).await.unwrap();
+        assert_eq!(rest.len(), 1);
+        assert_eq!(rest[0].id, vec![3]);
+
+        // Only replaced while the stored value is still the expected one
+        assert!(repo.replace_public_key(&[2], &[2], &[20]).await.unwrap());
+        assert!(!repo.replace_public_key(&[2], &[2], &[21]).await.unwrap());
+        assert!(!repo.replace_public_key(&[9], &[9], &[9]).await.unwrap());
+        assert!(repo.restore_credential(&[2]).await.unwrap());
+        let found = repo.get_credential_by_id(&[2]).await.unwrap().unwrap();
+        assert_eq!(found.public_key, vec![20]);
+    }
+
+    #[tokio::test]
+    async fn credential_flagged_compromised_once() {
+        // ---
+        let repo = memory_repo().await;
+        let user = repo.create_user("Gollum").await.unwrap();
+        repo.save_credential(Credential::new(vec![5], user.id, vec![1], 3))
+            .await
+            .unwrap();
+
+        assert!(repo.mark_credential_compromised(&[5]).await.unwrap());
+        let first = repo.get_credential_by_id(&[5]).await.unwrap().unwrap();
+        assert!(first.compromised_at.is_some());
+
+        assert!(!repo.mark_credential_compromised(&[5]).await.unwrap());
+        assert!(!repo.mark_credential_compromised(&[6]).await.unwrap());
+        let again = repo.get_credential_by_id(&[5]).await.unwrap().unwrap();
+        assert_eq!(again.compromised_at, first.compromised_at);
+    }
+
+    #[tokio::test]
+    async fn outbox_events_are_stored_with_their_change() {
+        // ---
+        let repo = memory_repo().await;
+        let user = repo.create_user("Pippin").await.unwrap();
+        let event = |kind: &str| OutboxEvent {
+            id: Uuid::new_v4(),
+            kind: kind.to_string(),
+            payload: "{}".to_string(),
+            created_at: Utc::now(),
+            attempts: 0,
+            traceparent: Some("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01".into()),
+            tracestate: None,
+        };
+
+        let saved = event("user.registered");
+        let credential = Credential::new(vec![7], user.id, vec![1], 0);
+        let write = OutboxWrite::SaveCredential(credential);
+        assert!(repo.write_with_event(write, &saved).await.unwrap());
+
+        // A no-op change stores no event; a failed one rolls it back
+        let write = OutboxWrite::MarkCredentialCompromised(vec![8]);
+        assert!(!repo.write_with_event(write, &event("x")).await.unwrap());
+        let orphan = Credential::new(vec![9], Uuid::new_v4(), vec![1], 0);
+        let write = OutboxWrite::SaveCredential(orphan);
+        assert!(repo.write_with_event(write, &event("x")).await.is_err());
+
+        let lease = Duration::from_secs(60);
+        let claimed = repo.claim_events(10, lease).await.unwrap();
+        assert_eq!(claimed.len(), 1);
+        assert_eq!(claimed[0].id, saved.id);
+        assert_eq!(claimed[0].attempts, 1);
+        assert_eq!(claimed[0].traceparent, saved.traceparent);
+        assert!(repo.get_credential_by_id(&[7]).await.unwrap().is_some());
+
+        // Leased events are not claimed again until the lease expires
+        assert!(repo.claim_events(10, lease).await.unwrap().is_empty());
+        repo.delete_event(saved.id).await.unwrap();
+
+        let expired = event("auth.new_device");
+        repo.write_with_event(OutboxWrite::EventOnly, &expired)
+            .await
+            .unwrap();
+        repo.claim_events(10, Duration::ZERO).await.unwrap();
+        let again = repo.claim_events(10, lease).await.unwrap();
+        assert_eq!(again.len(), 1);
+        assert_eq!(again[0].attempts, 2);
+    }
+
+    #[tokio::test]
+    async fn credential_without_user_fails() {
+        // ---
+        let repo = memory_repo().await;
+
+        let credential = Credential::new(vec![4, 4], Uuid::new_v4(), vec![1], 0);
+        assert!(repo.save_credential(credential).await.is_err());
+    }
+}
@@ -1,5 +1,6 @@
-use super::postgres_repository::*;
-use crate::domain::Credential; // {Credential, Repository, User};
+use super::create_postgres_repository;
+use super::postgres_repository::PostgresRepository;
+use crate::domain::{Credential, Repository}; // {Credential, Repository, User};
 use once_cell::sync::Lazy;
 use tokio::runtime::Runtime;
 use uuid::Uuid;
@@ -141,6 +142,68 @@ fn test_username_must_be_unique() {
     });
 }
 
+#[test]
+fn test_usernames_match_ignoring_case_and_normalization() {
+    // ---
+    RUNTIME.block_on(async {
+        // ---
+        init().await;
+        let repo = setup_repo().await;
+
+        // Precomposed "é" at registration
+        let user = repo
+            .create_user("Ren\u{e9}e@Example.com")
+            .await
+            .expect("User should be created");
+        assert_eq!(user.username, "Ren\u{e9}e@Example.com");
+
+        // Lowercase with a combining accent at sign-in
+        let found = repo
+            .get_user_by_username("rene\u{301}e@example.com")
+            .await
+            .expect("Query should succeed")
+            .expect("User should be found");
+        assert_eq!(found.id, user.id);
+        assert_eq!(found.username, "Ren\u{e9}e@Example.com");
+
+        assert!(repo.create_user("RENÉE@EXAMPLE.COM").await.is_err());
+    });
+}
+
+#[test]
+fn test_concurrent_get_or_create_user_agree() {
+    // ---
+    RUNTIME.block_on(async {
+        // ---
+        init().await;
+        let repo = setup_repo().await;
+        let username = format!("Race_{}", Uuid::new_v4());
+
+        // Each call races to insert; every one must get the same user
+        let calls = (0..8).map(|i| {
+            let repo = repo.clone();
+            let username = match i % 2 {
+                0 => username.clone(),
+                _ => username.to_uppercase(),
+            };
+            tokio::spawn(async move { repo.get_or_create_user(&username).await })
+        });
+        let users: Vec<_> = futures::future::join_all(calls)
+            .await
+            .into_iter()
+            .map(|joined| joined.unwrap().expect("get_or_create should not fail"))
+            .collect();
+
+        assert!(users.iter().all(|user| user.id == users[0].id));
+        let stored = repo.get_user_by_username(&username).await.unwrap().unwrap();
+        assert_eq!(stored.id, users[0].id);
+
+        // A soft-deleted user keeps the name reserved
+        assert!(repo.soft_delete_user(stored.id).await.unwrap());
+        assert!(repo.get_or_create_user(&username).await.is_err());
+    });
+}
+
 #[test]
 fn test_save_and_get_credential() {
     // ---
@@ -260,7 +323,7 @@ fn test_update_credential_counter() {
 
         // Update counter again
         credential.counter = 5;
-        repo.update_credential(credential)
+        repo.update_credential(credential.clone())
             .await
             .expect("Failed to update credential");
 
@@ -271,6 +334,23 @@ fn test_update_credential_counter() {
             .expect("Credential not found");
 
         assert_eq!(found.counter, 5);
+
+        // Counters above i32::MAX (authenticators report a u32)
+        credential.counter = u32::MAX.into();
+        credential.backup_eligible = Some(false);
+        credential.backup_state = Some(false);
+        repo.update_credential(credential)
+            .await
+            .expect("Failed to update credential");
+
+        let found = repo
+            .get_credential_by_id(&credential_id)
+            .await
+            .expect("Failed to get credential")
+            .expect("Credential not found");
+
+        assert_eq!(found.counter, i64::from(u32::MAX));
+        assert_eq!(found.backup_eligible, Some(false));
     });
 }
 
@@ -370,3 +450,410 @@ fn test_multiple_credentials_per_user() {
         assert_eq!(creds.len(), 3);
     });
 }
+
+#[test]
+fn test_reads_use_replica() {
+    // ---
+    RUNTIME.block_on(async {
+        // ---
+        init().await;
+        let cfg = crate::DatabaseConfig::from_env().expect("config");
+
+        // Point the "replica" at the primary so reads after writes are visible
+        let repo = PostgresRepository::connect(&crate::DatabaseConfig {
+            read_url: Some(cfg.database_url.clone()),
+            ..cfg
+        })
+        .await
+        .expect("connect failed");
+
+        let user = repo.create_user("Bifur").await.expect("create failed");
+
+        let found = repo
+            .get_user_by_id(user.id)
+            .await
+            .expect("replica read failed")
+            .expect("User not found");
+        assert_eq!(found.username, "Bifur");
+    });
+}
+
+#[test]
+fn test_reads_fall_back_when_replica_down() {
+    // ---
+    RUNTIME.block_on(async {
+        // ---
+        init().await;
+        let cfg = crate::DatabaseConfig::from_env().expect("config");
+
+        // Nothing listens on port 1, so every replica connection fails
+        let repo = PostgresRepository::connect(&crate::DatabaseConfig {
+            read_url: Some("postgres://postgres@127.0.0.1:1/axum_db".to_string()),
+            ..cfg
+        })
+        .await
+        .expect("connect failed");
+
+        let user = repo.create_user("Bofur").await.expect("create failed");
+
+        let found = repo
+            .get_user_by_username("Bofur")
+            .await
+            .expect("read should fall back to primary")
+            .expect("User not found");
+        assert_eq!(found.id, user.id);
+
+        // Replica is now cooling down; reads go straight to the primary
+        let creds = repo
+            .get_credentials_by_user(user.id)
+            .await
+            .expect("read should use primary");
+        assert!(creds.is_empty());
+    });
+}
+
+// Purging removes every soft-deleted row past the cutoff, so tests that
+// soft-delete must not run concurrently with the purge test.
+#[test]
+#[serial_test::serial(soft_delete)]
+fn test_soft_delete_and_restore() {
+    // ---
+    RUNTIME.block_on(async {
+        // ---
+        init().await;
+        let repo = setup_repo().await;
+
+        let user = repo.create_user("Gloin").await.expect("create failed");
+        let credential = Credential::new(vec![21, 0, 0], user.id, vec![1, 1], 0);
+        repo.save_credential(credential).await.expect("save failed");
+
+        // Soft-deleted rows are hidden from every lookup
+        assert!(repo.soft_delete_user(user.id).await.unwrap());
+        assert!(!repo.soft_delete_user(user.id).await.unwrap());
+        assert!(repo.get_user_by_username("Gloin").await.unwrap().is_none());
+        assert!(repo.get_user_by_id(user.id).await.unwrap().is_none());
+        assert!(repo
+            .get_credential_by_id(&[21, 0, 0])
+            .await
+            .unwrap()
+            .is_none());
+        assert!(repo
+            .get_credentials_by_user(user.id)
+            .await
+            .unwrap()
+            .is_empty());
+
+        // Restoring the user brings back the credentials deleted with them
+        assert!(repo.restore_user(user.id).await.unwrap());
+        assert!(repo.get_user_by_id(user.id).await.unwrap().is_some());
+        assert!(repo
+            .get_credential_by_id(&[21, 0, 0])
+            .await
+            .unwrap()
+            .is_some());
+
+        // Credentials can be deleted and restored individually
+        assert!(repo.soft_delete_credential(&[21, 0, 0]).await.unwrap());
+        assert!(repo
+            .get_credentials_by_user(user.id)
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(repo.restore_credential(&[21, 0, 0]).await.unwrap());
+        assert!(!repo.restore_credential(&[21, 0, 0]).await.unwrap());
+    });
+}
+
+#[test]
+#[serial_test::serial(soft_delete)]
+fn test_purge_soft_deleted() {
+    // ---
+    RUNTIME.block_on(async {
+        // ---
+        init().await;
+        let repo = setup_repo().await;
+
+        let user = repo.create_user("Oin").await.expect("create failed");
+        let credential = Credential::new(vec![22, 0, 0], user.id, vec![1, 1], 0);
+        repo.save_credential(credential).await.expect("save failed");
+        repo.soft_delete_user(user.id).await.unwrap();
+
+        // Within the retention window nothing of ours is purged
+        let past = chrono::Utc::now() - chrono::Duration::days(1);
+        repo.purge_deleted(past, false).await.unwrap();
+        assert!(repo.restore_user(user.id).await.unwrap());
+        repo.soft_delete_user(user.id).await.unwrap();
+
+        let summary = repo.purge_deleted(chrono::Utc::now(), false).await.unwrap();
+        assert!(summary.users >= 1);
+        assert!(summary.credentials >= 1);
+
+        // Purged rows are gone for good
+        assert!(!repo.restore_user(user.id).await.unwrap());
+    });
+}
+
+#[test]
+fn test_orphan_cleanup_respects_age() {
+    // ---
+    RUNTIME.block_on(async {
+        // ---
+        init().await;
+        let repo = setup_repo().await;
+
+        // Every user in the test database is newer than this, so even a real
+        // run must not delete anything other tests depend on.
+        let long_ago = chrono::Utc::now() - chrono::Duration::days(365 * 30);
+        assert_eq!(repo.delete_orphaned_users(long_ago, true).await.unwrap(), 0);
+        assert_eq!(
+            repo.delete_orphaned_users(long_ago, false).await.unwrap(),
+            0
+        );
+    });
+}
+
+#[test]
+fn test_mark_credential_compromised() {
+    // ---
+    RUNTIME.block_on(async {
+        // ---
+        init().await;
+        let repo = setup_repo().await;
+
+        let username = format!("cloned_{}", Uuid::new_v4());
+        let user = repo.create_user(&username).await.unwrap();
+        let credential_id = Uuid::new_v4().as_bytes().to_vec();
+        repo.save_credential(Credential::new(credential_id.clone(), user.id, vec![1], 3))
+            .await
+            .unwrap();
+
+        assert!(repo
+            .mark_credential_compromised(&credential_id)
+            .await
+            .unwrap());
+        let found = repo
+            .get_credential_by_id(&credential_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(found.compromised_at.is_some());
+
+        // Flagging is reported only once
+        assert!(!repo
+            .mark_credential_compromised(&credential_id)
+            .await
+            .unwrap());
+    });
+}
+
+#[test]
+fn test_credentials_modified_at() {
+    // ---
+    RUNTIME.block_on(async {
+        // ---
+        init().await;
+        let repo = setup_repo().await;
+
+        let username = format!("modified_{}", Uuid::new_v4());
+        let user = repo.create_user(&username).await.unwrap();
+        assert!(repo
+            .credentials_modified_at(user.id)
+            .await
+            .unwrap()
+            .is_none());
+
+        let credential_id = Uuid::new_v4().as_bytes().to_vec();
+        let mut credential = Credential::new(credential_id.clone(), user.id, vec![1], 0);
+        repo.save_credential(credential.clone()).await.unwrap();
+        let created = repo
+            .credentials_modified_at(user.id)
+            .await
+            .unwrap()
+            .unwrap();
+
+        // A sign-in advances it
+        credential.last_used_at = Some(created + chrono::Duration::minutes(5));
+        repo.update_credential(credential).await.unwrap();
+        let used = repo
+            .credentials_modified_at(user.id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(used > created + chrono::Duration::minutes(4));
+
+        // So does a deletion, although the credential is no longer listed
+        assert!(repo.soft_delete_credential(&credential_id).await.unwrap());
+        let deleted = repo.credentials_modified_at(user.id).await.unwrap();
+        assert!(deleted.is_some_and(|at| at >= created));
+        assert!(repo
+            .get_credentials_by_user(user.id)
+            .await
+            .unwrap()
+            .is_empty());
+    });
+}
+
+#[test]
+fn test_credential_stats() {
+    // ---
+    RUNTIME.block_on(async {
+        // ---
+        init().await;
+        let repo = setup_repo().await;
+
+        let user = repo
+            .create_user(&format!("stats_{}", Uuid::new_v4()))
+            .await
+            .unwrap();
+        for _ in 0..2 {
+            let credential_id = Uuid::new_v4().as_bytes().to_vec();
+            repo.save_credential(Credential::new(credential_id, user.id, vec![1], 0))
+                .await
+                .unwrap();
+        }
+
+        // Other tests share the database, so only check what this one added
+        let stats = repo.credential_stats().await.unwrap();
+        assert!(
+            stats
+                .users_by_credential_count
+                .get(&2)
+                .copied()
+                .unwrap_or(0)
+                >= 1
+        );
+        assert_eq!(
+            stats.users,
+            stats.users_by_credential_count.values().sum::<u64>()
+        );
+        assert!(stats.credentials >= 2);
+    });
+}
+
+#[test]
+fn test_batch_save_and_delete_credentials() {
+    // ---
+    RUNTIME.block_on(async {
+        let repo = setup_repo().await;
+        let user = repo
+            .create_user(&format!("batch_{}", Uuid::new_v4()))
+            .await
+            .unwrap();
+        let ids: Vec<Vec<u8>> = (0..3).map(|_| Uuid::new_v4().as_bytes().to_vec()).collect();
+
+        let mut credentials: Vec<Credential> = ids
+            .iter()
+            .map(|id| Credential::new(id.clone(), user.id, vec![1], 0))
+            .collect();
+        credentials[0].backup_eligible = Some(true);
+        repo.save_credentials(credentials).await.unwrap();
+        repo.save_credentials(Vec::new()).await.unwrap();
+
+        let saved = repo.get_credentials_by_user(user.id).await.unwrap();
+        assert_eq!(saved.len(), 3);
+        assert!(saved
+            .iter()
+            .any(|c| c.id == ids[0] && c.backup_eligible == Some(true)));
+
+        // A duplicate ID rejects the whole batch
+        let fresh = Uuid::new_v4().as_bytes().to_vec();
+        let batch = vec![
+            Credential::new(fresh.clone(), user.id, vec![1], 0),
+            Credential::new(ids[1].clone(), user.id, vec![1], 0),
+        ];
+        assert!(repo.save_credentials(batch).await.is_err());
+        assert!(repo.get_credential_by_id(&fresh).await.unwrap().is_none());
+
+        // Soft-deleted credentials are removed too; the user stays
+        assert!(repo.soft_delete_credential(&ids[2]).await.unwrap());
+        assert_eq!(repo.delete_credentials_by_user(user.id).await.unwrap(), 3);
+        assert!(repo.get_user_by_id(user.id).await.unwrap().is_some());
+        assert_eq!(repo.delete_credentials_by_user(user.id).await.unwrap(), 0);
+    });
+}
+
+#[test]
+fn test_warm_up_runs_the_sign_in_queries_per_connection() {
+    // ---
+    RUNTIME.block_on(async {
+        init().await;
+        let metrics = std::sync::Arc::new(QueryRecorder::default());
+        let repo: crate::domain::RepositoryPtr = std::sync::Arc::new(
+            super::InstrumentedRepository::new(setup_repo().await, metrics.clone(), None),
+        );
+
+        crate::startup::warm_up_repository(&repo, 3).await.unwrap();
+
+        let queries = metrics.queries.lock().unwrap();
+        for query in [
+            "ping",
+            "get_user_by_username",
+            "get_credentials_by_user",
+            "get_credential_by_id",
+        ] {
+            assert_eq!(queries.iter().filter(|q| *q == query).count(), 3, "{query}");
+        }
+    });
+}
+
+/// Records the query name of each timed repository call.
+#[derive(Default)]
+struct QueryRecorder {
+    // ---
+    queries: std::sync::Mutex<Vec<String>>,
+}
+
+impl crate::domain::Metrics for QueryRecorder {
+    // ---
+    fn render(&self) -> String {
+        String::new()
+    }
+    fn record_movie_created(&self) {}
+    fn record_movie_updated(&self) {}
+    fn record_movie_deleted(&self) {}
+    fn record_http_request(&self, _: std::time::Instant, _: &str, _: &str, _: u16) {}
+    fn record_orphan_users_removed(&self, _: u64, _: bool) {}
+    fn record_retention_purged(&self, _: &str, _: u64, _: bool) {}
+    fn record_event_stream(&self, _: &str, _: u64) {}
+    fn record_event_stream_buffered(&self, _: usize) {}
+    fn record_webhook_delivered(&self) {}
+    fn record_webhook_dead_letter(&self) {}
+    fn record_sign_count_anomaly(&self, _: bool) {}
+    fn record_redis_retry(&self, _: bool) {}
+    fn record_in_flight_request(&self, _: bool) {}
+    fn record_runtime_sample(&self, _: &crate::domain::RuntimeSample) {}
+    fn record_outbound_request(&self, _: &str, _: Option<u16>) {}
+    fn record_db_query(&self, query_name: &str, _: std::time::Duration) {
+        self.queries.lock().unwrap().push(query_name.to_string());
+    }
+    fn record_challenge_failure(&self, _: &str, _: bool) {}
+    fn record_panic(&self) {}
+}
+
+#[test]
+fn test_instrumented_repository_times_each_call() {
+    // ---
+    RUNTIME.block_on(async {
+        init().await;
+        let metrics = std::sync::Arc::new(QueryRecorder::default());
+
+        // A zero threshold logs every call, exercising the slow-query path
+        let repo = super::InstrumentedRepository::new(
+            setup_repo().await,
+            metrics.clone(),
+            Some(std::time::Duration::ZERO),
+        );
+
+        let user = repo
+            .create_user(&format!("timed_{}", Uuid::new_v4()))
+            .await
+            .unwrap();
+        repo.get_user_by_id(user.id).await.unwrap();
+        repo.get_credential_by_id(b"missing").await.unwrap();
+
+        assert_eq!(
+            *metrics.queries.lock().unwrap(),
+            ["create_user", "get_user_by_id", "get_credential_by_id"]
+        );
+    });
+}
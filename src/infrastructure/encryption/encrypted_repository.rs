@@ -0,0 +1,361 @@
+//! Repository decorator that encrypts credential key material at rest.
+
+use super::envelope::{open, seal, sealed_key_id};
+use crate::domain::{
+    Credential, CredentialStats, KeyProviderPtr, OutboxEvent, OutboxWrite, PurgeSummary,
+    ReencryptSummary, Repository, RepositoryPtr, User,
+};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Credentials visited per page during re-encryption.
+const REENCRYPT_BATCH: u32 = 100;
+
+/// Wraps any [`Repository`] so each credential's serialized passkey is
+/// stored envelope-encrypted with AES-256-GCM. The credential ID is bound
+/// as associated data.
+///
+/// Plaintext rows written before encryption was enabled are still readable
+/// and are encrypted on their next update, or all at once by
+/// [`Repository::reencrypt_credentials`].
+pub struct EncryptedRepository {
+    // ---
+    inner: RepositoryPtr,
+    keys: KeyProviderPtr,
+}
+
+impl EncryptedRepository {
+    // ---
+
+    pub fn new(inner: RepositoryPtr, keys: KeyProviderPtr) -> Self {
+        // ---
+        Self { inner, keys }
+    }
+
+    async fn encrypt(&self, mut credential: Credential) -> Result<Credential> {
+        // ---
+        credential.public_key = seal(&*self.keys, &credential.id, &credential.public_key).await?;
+        Ok(credential)
+    }
+
+    async fn decrypt(&self, mut credential: Credential) -> Result<Credential> {
+        // ---
+        credential.public_key = open(&*self.keys, &credential.id, &credential.public_key)
+            .await
+            .with_context(|| format!("credential {}", hex::encode(&credential.id)))?;
+        Ok(credential)
+    }
+
+    async fn decrypt_all(&self, credentials: Vec<Credential>) -> Result<Vec<Credential>> {
+        // ---
+        let mut out = Vec::with_capacity(credentials.len());
+        for credential in credentials {
+            out.push(self.decrypt(credential).await?);
+        }
+        Ok(out)
+    }
+}
+
+#[async_trait::async_trait]
+impl Repository for EncryptedRepository {
+    // ---
+    async fn ping(&self) -> Result<()> {
+        self.inner.ping().await
+    }
+
+    async fn create_user_in(&self, tenant_id: &str, username: &str) -> Result<User> {
+        self.inner.create_user_in(tenant_id, username).await
+    }
+
+    async fn get_user_by_username_in(
+        &self,
+        tenant_id: &str,
+        username: &str,
+    ) -> Result<Option<User>> {
+        self.inner
+            .get_user_by_username_in(tenant_id, username)
+            .await
+    }
+
+    async fn get_or_create_user_in(&self, tenant_id: &str, username: &str) -> Result<User> {
+        self.inner.get_or_create_user_in(tenant_id, username).await
+    }
+
+    async fn get_user_by_id(&self, user_id: Uuid) -> Result<Option<User>> {
+        self.inner.get_user_by_id(user_id).await
+    }
+
+    // Password hashes are one-way already; they are stored as they are
+    async fn set_password_hash(&self, user_id: Uuid, password_hash: &str) -> Result<bool> {
+        self.inner.set_password_hash(user_id, password_hash).await
+    }
+
+    async fn get_password_hash(&self, user_id: Uuid) -> Result<Option<String>> {
+        self.inner.get_password_hash(user_id).await
+    }
+
+    async fn save_credential(&self, credential: Credential) -> Result<()> {
+        // ---
+        let credential = self.encrypt(credential).await?;
+        self.inner.save_credential(credential).await
+    }
+
+    async fn save_credentials(&self, credentials: Vec<Credential>) -> Result<()> {
+        // ---
+        let mut sealed = Vec::with_capacity(credentials.len());
+        for credential in credentials {
+            sealed.push(self.encrypt(credential).await?);
+        }
+        self.inner.save_credentials(sealed).await
+    }
+
+    async fn get_credentials_by_user(&self, user_id: Uuid) -> Result<Vec<Credential>> {
+        // ---
+        let credentials = self.inner.get_credentials_by_user(user_id).await?;
+        self.decrypt_all(credentials).await
+    }
+
+    async fn credentials_modified_at(&self, user_id: Uuid) -> Result<Option<DateTime<Utc>>> {
+        self.inner.credentials_modified_at(user_id).await
+    }
+
+    async fn get_credential_by_id(&self, credential_id: &[u8]) -> Result<Option<Credential>> {
+        // ---
+        match self.inner.get_credential_by_id(credential_id).await? {
+            Some(credential) => Ok(Some(self.decrypt(credential).await?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn update_credential(&self, credential: Credential) -> Result<()> {
+        // ---
+        let credential = self.encrypt(credential).await?;
+        self.inner.update_credential(credential).await
+    }
+
+    async fn delete_credential(&self, credential_id: &[u8]) -> Result<()> {
+        self.inner.delete_credential(credential_id).await
+    }
+
+    async fn delete_credentials_by_user(&self, user_id: Uuid) -> Result<u64> {
+        self.inner.delete_credentials_by_user(user_id).await
+    }
+
+    async fn delete_user(&self, user_id: Uuid) -> Result<bool> {
+        self.inner.delete_user(user_id).await
+    }
+
+    async fn soft_delete_user(&self, user_id: Uuid) -> Result<bool> {
+        self.inner.soft_delete_user(user_id).await
+    }
+
+    async fn restore_user(&self, user_id: Uuid) -> Result<bool> {
+        self.inner.restore_user(user_id).await
+    }
+
+    async fn soft_delete_credential(&self, credential_id: &[u8]) -> Result<bool> {
+        self.inner.soft_delete_credential(credential_id).await
+    }
+
+    async fn restore_credential(&self, credential_id: &[u8]) -> Result<bool> {
+        self.inner.restore_credential(credential_id).await
+    }
+
+    async fn mark_credential_compromised(&self, credential_id: &[u8]) -> Result<bool> {
+        self.inner.mark_credential_compromised(credential_id).await
+    }
+
+    async fn purge_deleted(&self, cutoff: DateTime<Utc>, dry_run: bool) -> Result<PurgeSummary> {
+        self.inner.purge_deleted(cutoff, dry_run).await
+    }
+
+    async fn delete_orphaned_users(
+        &self,
+        created_before: DateTime<Utc>,
+        dry_run: bool,
+    ) -> Result<u64> {
+        self.inner
+            .delete_orphaned_users(created_before, dry_run)
+            .await
+    }
+
+    async fn credential_stats(&self) -> Result<CredentialStats> {
+        self.inner.credential_stats().await
+    }
+
+    async fn list_all_credentials(
+        &self,
+        after: Option<&[u8]>,
+        limit: u32,
+    ) -> Result<Vec<Credential>> {
+        // ---
+        let credentials = self.inner.list_all_credentials(after, limit).await?;
+        self.decrypt_all(credentials).await
+    }
+
+    async fn replace_public_key(
+        &self,
+        credential_id: &[u8],
+        expected: &[u8],
+        public_key: &[u8],
+    ) -> Result<bool> {
+        // ---
+        // `expected` is compared against what is stored, so it must be the
+        // stored (encrypted) form; only the new value is sealed here.
+        let sealed = seal(&*self.keys, credential_id, public_key).await?;
+        self.inner
+            .replace_public_key(credential_id, expected, &sealed)
+            .await
+    }
+
+    async fn write_with_event(&self, write: OutboxWrite, event: &OutboxEvent) -> Result<bool> {
+        // ---
+        let write = match write {
+            OutboxWrite::SaveCredential(credential) => {
+                OutboxWrite::SaveCredential(self.encrypt(credential).await?)
+            }
+            other => other,
+        };
+        self.inner.write_with_event(write, event).await
+    }
+
+    async fn claim_events(&self, limit: u32, lease: Duration) -> Result<Vec<OutboxEvent>> {
+        // ---
+        self.inner.claim_events(limit, lease).await
+    }
+
+    async fn delete_event(&self, event_id: Uuid) -> Result<()> {
+        // ---
+        self.inner.delete_event(event_id).await
+    }
+
+    async fn reencrypt_credentials(&self) -> Result<Option<ReencryptSummary>> {
+        // ---
+        let current = self.keys.current_key_id();
+        let mut summary = ReencryptSummary::default();
+        let mut after: Option<Vec<u8>> = None;
+
+        loop {
+            let page = self
+                .inner
+                .list_all_credentials(after.as_deref(), REENCRYPT_BATCH)
+                .await?;
+
+            for credential in &page {
+                let stored = &credential.public_key;
+                if sealed_key_id(stored) == Some(current) {
+                    summary.unchanged += 1;
+                    continue;
+                }
+
+                let plaintext = open(&*self.keys, &credential.id, stored)
+                    .await
+                    .with_context(|| format!("credential {}", hex::encode(&credential.id)))?;
+                let sealed = seal(&*self.keys, &credential.id, &plaintext).await?;
+
+                // A concurrent update has already written it under the
+                // current key, so losing the race is fine.
+                if self
+                    .inner
+                    .replace_public_key(&credential.id, stored, &sealed)
+                    .await?
+                {
+                    summary.reencrypted += 1;
+                } else {
+                    summary.unchanged += 1;
+                }
+            }
+
+            if page.len() < REENCRYPT_BATCH as usize {
+                break;
+            }
+            after = page.last().map(|c| c.id.clone());
+        }
+
+        tracing::info!(
+            "Re-encrypted {} credentials under key {} ({} unchanged)",
+            summary.reencrypted,
+            current,
+            summary.unchanged
+        );
+        Ok(Some(summary))
+    }
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+    // ---
+    use super::*;
+    use crate::config::{DatabaseConfig, EncryptionConfig, RepositoryType};
+    use crate::infrastructure::{LocalKeyProvider, SqliteRepository};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    async fn memory_repo() -> RepositoryPtr {
+        // ---
+        let cfg = DatabaseConfig {
+            repository_type: RepositoryType::Sqlite,
+            database_url: "sqlite::memory:".to_string(),
+            read_url: None,
+            retry_count: 1,
+            acquire_timeout: Duration::from_secs(5),
+            min_connections: 1,
+            max_connections: 1,
+            slow_query_threshold: None,
+        };
+        Arc::new(SqliteRepository::connect(&cfg).await.unwrap())
+    }
+
+    fn keys(current: u8, previous: &[u8]) -> KeyProviderPtr {
+        // ---
+        Arc::new(
+            LocalKeyProvider::from_config(&EncryptionConfig {
+                current_key: Some([current; 32]),
+                previous_keys: previous.iter().map(|&b| [b; 32]).collect(),
+            })
+            .unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn rotation_reencrypts_every_credential() {
+        // ---
+        let plain = memory_repo().await;
+        let user = plain.create_user("Merry").await.unwrap();
+
+        // Written before encryption was enabled
+        plain
+            .save_credential(Credential::new(vec![1], user.id, b"legacy".to_vec(), 0))
+            .await
+            .unwrap();
+
+        let first = EncryptedRepository::new(plain.clone(), keys(1, &[]));
+        first
+            .save_credential(Credential::new(vec![2], user.id, b"new".to_vec(), 0))
+            .await
+            .unwrap();
+        first.soft_delete_credential(&[2]).await.unwrap();
+
+        let stored = plain.list_all_credentials(None, 10).await.unwrap();
+        assert_eq!(stored[0].public_key, b"legacy");
+        assert_ne!(stored[1].public_key, b"new");
+
+        let rotated = EncryptedRepository::new(plain.clone(), keys(2, &[1]));
+        let summary = rotated.reencrypt_credentials().await.unwrap().unwrap();
+        assert_eq!(summary.reencrypted, 2);
+        assert_eq!(summary.unchanged, 0);
+
+        // Readable with only the new key, including the soft-deleted row
+        let retired = EncryptedRepository::new(plain.clone(), keys(2, &[]));
+        let found = retired.get_credential_by_id(&[1]).await.unwrap().unwrap();
+        assert_eq!(found.public_key, b"legacy");
+        let all = retired.list_all_credentials(None, 10).await.unwrap();
+        assert_eq!(all[1].public_key, b"new");
+
+        let again = retired.reencrypt_credentials().await.unwrap().unwrap();
+        assert_eq!(again.reencrypted, 0);
+        assert_eq!(again.unchanged, 2);
+    }
+}
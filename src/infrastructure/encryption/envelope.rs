@@ -0,0 +1,178 @@
+//! Binary envelope for values encrypted at rest.
+//!
+//! Layout, all lengths big-endian:
+//!
+//! ```text
+//! "ENC1" | key_id_len: u8 | key_id | wrapped_len: u16 | wrapped data key
+//!        | nonce: 12 bytes | AES-256-GCM ciphertext + tag
+//! ```
+//!
+//! Values without the magic prefix are legacy plaintext, written before
+//! encryption was enabled, and are returned unchanged by [`open`].
+
+use crate::domain::{KeyProvider, WrappedKey};
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use anyhow::{anyhow, Context, Result};
+
+const MAGIC: &[u8] = b"ENC1";
+const NONCE_LEN: usize = 12;
+
+/// Returns the ID of the key that wrapped the envelope's data key, or
+/// `None` for plaintext or malformed values.
+pub(super) fn sealed_key_id(stored: &[u8]) -> Option<&str> {
+    // ---
+    let rest = stored.strip_prefix(MAGIC)?;
+    let (&len, rest) = rest.split_first()?;
+    std::str::from_utf8(rest.get(..len as usize)?).ok()
+}
+
+/// Encrypts `plaintext` under a fresh data key wrapped by `keys`.
+///
+/// `aad` is authenticated but not stored; the same value must be passed to
+/// [`open`], which stops an envelope being copied to another record.
+pub(super) async fn seal(keys: &dyn KeyProvider, aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>> {
+    // ---
+    let data_key = Aes256Gcm::generate_key(OsRng);
+    let wrapped = keys.wrap_key(&data_key).await?;
+
+    let key_id = wrapped.key_id.as_bytes();
+    let key_id_len = u8::try_from(key_id.len()).map_err(|_| anyhow!("key ID too long"))?;
+    let wrapped_len =
+        u16::try_from(wrapped.ciphertext.len()).map_err(|_| anyhow!("wrapped key too long"))?;
+
+    let nonce = Aes256Gcm::generate_nonce(OsRng);
+    let ciphertext = Aes256Gcm::new(&data_key)
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: plaintext,
+                aad,
+            },
+        )
+        .map_err(|_| anyhow!("failed to encrypt value"))?;
+
+    let mut out = Vec::with_capacity(
+        MAGIC.len() + 3 + key_id.len() + wrapped.ciphertext.len() + NONCE_LEN + ciphertext.len(),
+    );
+    out.extend_from_slice(MAGIC);
+    out.push(key_id_len);
+    out.extend_from_slice(key_id);
+    out.extend_from_slice(&wrapped_len.to_be_bytes());
+    out.extend_from_slice(&wrapped.ciphertext);
+    out.extend_from_slice(&nonce);
+    out.extend_from_slice(&ciphertext);
+    Ok(out)
+}
+
+/// Decrypts an envelope produced by [`seal`]. Plaintext is passed through.
+///
+/// # Errors
+/// Returns an error if the envelope is truncated, its key cannot be
+/// unwrapped, or authentication fails (tampering or the wrong `aad`).
+pub(super) async fn open(keys: &dyn KeyProvider, aad: &[u8], stored: &[u8]) -> Result<Vec<u8>> {
+    // ---
+    let Some(rest) = stored.strip_prefix(MAGIC) else {
+        return Ok(stored.to_vec());
+    };
+
+    let mut reader = Reader(rest);
+    let key_id_len = reader.take(1)?[0] as usize;
+    let key_id = std::str::from_utf8(reader.take(key_id_len)?).context("key ID is not UTF-8")?;
+    let wrapped_len = u16::from_be_bytes(reader.take(2)?.try_into()?) as usize;
+    let wrapped = reader.take(wrapped_len)?;
+    let nonce = Nonce::from_slice(reader.take(NONCE_LEN)?);
+
+    let data_key = keys
+        .unwrap_key(&WrappedKey {
+            key_id: key_id.to_string(),
+            ciphertext: wrapped.to_vec(),
+        })
+        .await?;
+
+    Aes256Gcm::new_from_slice(&data_key)
+        .map_err(|_| anyhow!("unwrapped data key has the wrong length"))?
+        .decrypt(nonce, Payload { msg: reader.0, aad })
+        .map_err(|_| anyhow!("failed to decrypt value under key {key_id}"))
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    // ---
+    fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        // ---
+        if self.0.len() < n {
+            return Err(anyhow!("encrypted value is truncated"));
+        }
+        let (head, tail) = self.0.split_at(n);
+        self.0 = tail;
+        Ok(head)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+    use crate::config::EncryptionConfig;
+    use crate::infrastructure::LocalKeyProvider;
+
+    fn provider(current: u8, previous: &[u8]) -> LocalKeyProvider {
+        // ---
+        LocalKeyProvider::from_config(&EncryptionConfig {
+            current_key: Some([current; 32]),
+            previous_keys: previous.iter().map(|&b| [b; 32]).collect(),
+        })
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn seal_and_open_round_trip() {
+        // ---
+        let keys = provider(1, &[]);
+        let sealed = seal(&keys, b"cred-1", b"passkey json").await.unwrap();
+
+        assert_eq!(sealed_key_id(&sealed), Some(keys.current_key_id()));
+        assert!(!sealed.windows(7).any(|w| w == b"passkey"));
+        assert_eq!(
+            open(&keys, b"cred-1", &sealed).await.unwrap(),
+            b"passkey json"
+        );
+
+        // Bound to the record it was written for
+        assert!(open(&keys, b"cred-2", &sealed).await.is_err());
+
+        let mut tampered = sealed.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(open(&keys, b"cred-1", &tampered).await.is_err());
+        assert!(open(&keys, b"cred-1", &sealed[..sealed.len() - 20])
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn plaintext_passes_through() {
+        // ---
+        let keys = provider(1, &[]);
+        assert_eq!(sealed_key_id(b"{\"cred\":{}}"), None);
+        assert_eq!(
+            open(&keys, b"id", b"{\"cred\":{}}").await.unwrap(),
+            b"{\"cred\":{}}"
+        );
+    }
+
+    #[tokio::test]
+    async fn previous_keys_still_decrypt() {
+        // ---
+        let old = provider(1, &[]);
+        let sealed = seal(&old, b"id", b"secret").await.unwrap();
+
+        let rotated = provider(2, &[1]);
+        assert_ne!(rotated.current_key_id(), old.current_key_id());
+        assert_eq!(open(&rotated, b"id", &sealed).await.unwrap(), b"secret");
+
+        let retired = provider(2, &[]);
+        assert!(open(&retired, b"id", &sealed).await.is_err());
+    }
+}
@@ -0,0 +1,86 @@
+//! In-process key provider backed by keys from the environment.
+
+use crate::config::{EncryptionConfig, DATA_KEY_LEN};
+use crate::domain::{KeyProvider, WrappedKey};
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Nonce};
+use anyhow::{anyhow, Result};
+use sha2::{Digest, Sha256};
+
+const NONCE_LEN: usize = 12;
+
+/// Wraps data keys with AES-256-GCM using `AXUM_DATA_ENCRYPTION_KEY`.
+///
+/// Retired keys from `AXUM_DATA_ENCRYPTION_PREVIOUS_KEYS` can still unwrap.
+/// Key IDs are derived from a hash of the key, so they stay stable across
+/// restarts without being configured separately.
+pub struct LocalKeyProvider {
+    // ---
+    /// Current key first, then previous keys.
+    keys: Vec<(String, Aes256Gcm)>,
+}
+
+impl LocalKeyProvider {
+    // ---
+
+    /// Creates a provider from the configured keys, or `None` if encryption
+    /// is not enabled.
+    pub fn from_config(config: &EncryptionConfig) -> Option<Self> {
+        // ---
+        let current = config.current_key?;
+        let keys = std::iter::once(&current)
+            .chain(&config.previous_keys)
+            .map(|key| (key_id(key), Aes256Gcm::new(key.into())))
+            .collect();
+        Some(Self { keys })
+    }
+}
+
+/// First four bytes of the key's SHA-256, hex-encoded.
+fn key_id(key: &[u8; DATA_KEY_LEN]) -> String {
+    // ---
+    hex::encode(&Sha256::digest(key)[..4])
+}
+
+#[async_trait::async_trait]
+impl KeyProvider for LocalKeyProvider {
+    // ---
+    fn current_key_id(&self) -> &str {
+        // ---
+        &self.keys[0].0
+    }
+
+    async fn wrap_key(&self, data_key: &[u8]) -> Result<WrappedKey> {
+        // ---
+        let (key_id, cipher) = &self.keys[0];
+        let nonce = Aes256Gcm::generate_nonce(OsRng);
+        let mut ciphertext = nonce.to_vec();
+        ciphertext.extend(
+            cipher
+                .encrypt(&nonce, data_key)
+                .map_err(|_| anyhow!("failed to wrap data key"))?,
+        );
+
+        Ok(WrappedKey {
+            key_id: key_id.clone(),
+            ciphertext,
+        })
+    }
+
+    async fn unwrap_key(&self, wrapped: &WrappedKey) -> Result<Vec<u8>> {
+        // ---
+        let (_, cipher) = self
+            .keys
+            .iter()
+            .find(|(id, _)| *id == wrapped.key_id)
+            .ok_or_else(|| anyhow!("unknown data encryption key {}", wrapped.key_id))?;
+
+        if wrapped.ciphertext.len() < NONCE_LEN {
+            return Err(anyhow!("wrapped data key is truncated"));
+        }
+        let (nonce, ciphertext) = wrapped.ciphertext.split_at(NONCE_LEN);
+        cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| anyhow!("failed to unwrap data key {}", wrapped.key_id))
+    }
+}
@@ -0,0 +1,9 @@
+// Gateway module - envelope encryption of stored credentials
+// Modules are private, only exported symbols are public
+
+mod encrypted_repository;
+mod envelope;
+mod local_key_provider;
+
+pub use encrypted_repository::EncryptedRepository;
+pub use local_key_provider::LocalKeyProvider;
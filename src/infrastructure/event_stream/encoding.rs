@@ -0,0 +1,229 @@
+//! Wire encoding of domain events for the event stream.
+//!
+//! Every event is sent as the same envelope:
+//!
+//! | Field         | Content                                                   |
+//! |:--------------|:----------------------------------------------------------|
+//! | `id`          | UUID of this message, for deduplication by consumers      |
+//! | `type`        | Event name (`user.registered`, `movie.deleted`, ...)      |
+//! | `occurred_at` | When the event was published                              |
+//! | `user_id`     | The user the event concerns, or null                      |
+//! | `data`        | Event fields: username, credential ID, client IP, movie ID |
+//!
+//! In JSON, `occurred_at` is RFC 3339 and `data` an object. In Avro the
+//! envelope follows `schemas/domain-event.avsc`: `occurred_at` is in
+//! milliseconds since the epoch and `data` is the same object as JSON text,
+//! so adding a field to an event does not change the schema. Avro messages
+//! are plain binary datums, without a schema registry header.
+
+use crate::config::EventFormat;
+use crate::events::DomainEvent;
+use chrono::{DateTime, SecondsFormat, Utc};
+use uuid::Uuid;
+
+/// An encoded event, ready to send.
+#[derive(Debug, Clone)]
+pub(crate) struct Message {
+    // ---
+    pub id: Uuid,
+
+    /// Event name, used for the NATS subject and a Kafka header.
+    pub kind: &'static str,
+
+    /// The user or movie ID, so a Kafka consumer sees one entity's events
+    /// in order.
+    pub key: Option<String>,
+
+    pub occurred_at: DateTime<Utc>,
+    pub payload: Vec<u8>,
+}
+
+/// The `Content-Type` of messages in `format`.
+pub(crate) fn content_type(format: EventFormat) -> &'static str {
+    // ---
+    match format {
+        EventFormat::Json => "application/json",
+        EventFormat::Avro => "avro/binary",
+    }
+}
+
+/// Encodes `event` as published at `occurred_at`.
+pub(crate) fn encode(
+    event: &DomainEvent,
+    format: EventFormat,
+    occurred_at: DateTime<Utc>,
+) -> Message {
+    // ---
+    let id = Uuid::new_v4();
+    let user_id = event.user_id();
+    let data = data(event);
+
+    let payload = match format {
+        EventFormat::Json => serde_json::json!({
+            "id": id,
+            "type": event.as_str(),
+            "occurred_at": occurred_at.to_rfc3339_opts(SecondsFormat::Millis, true),
+            "user_id": user_id,
+            "data": data,
+        })
+        .to_string()
+        .into_bytes(),
+        EventFormat::Avro => {
+            let mut out = Vec::with_capacity(128);
+            write_string(&mut out, &id.to_string());
+            write_string(&mut out, event.as_str());
+            write_long(&mut out, occurred_at.timestamp_millis());
+            match user_id {
+                None => write_long(&mut out, 0),
+                Some(user_id) => {
+                    write_long(&mut out, 1);
+                    write_string(&mut out, &user_id.to_string());
+                }
+            }
+            write_string(&mut out, &data.to_string());
+            out
+        }
+    };
+
+    Message {
+        id,
+        kind: event.as_str(),
+        key: key(event),
+        occurred_at,
+        payload,
+    }
+}
+
+fn key(event: &DomainEvent) -> Option<String> {
+    // ---
+    match event {
+        DomainEvent::MovieCreated { id }
+        | DomainEvent::MovieUpdated { id }
+        | DomainEvent::MovieDeleted { id } => Some(id.clone()),
+        _ => event.user_id().map(|user_id| user_id.to_string()),
+    }
+}
+
+fn data(event: &DomainEvent) -> serde_json::Value {
+    // ---
+    match event {
+        DomainEvent::UserRegistered {
+            username,
+            credential_id,
+            ..
+        } => serde_json::json!({ "username": username, "credential_id": credential_id }),
+        DomainEvent::CredentialAdded {
+            username,
+            credential_id,
+            ..
+        }
+        | DomainEvent::CredentialDeleted {
+            username,
+            credential_id,
+            ..
+        } => serde_json::json!({ "username": username, "credential_id": credential_id }),
+        DomainEvent::AuthSucceeded {
+            username,
+            credential_id,
+            client_ip,
+            ..
+        } => serde_json::json!({
+            "username": username,
+            "credential_id": credential_id,
+            "client_ip": client_ip,
+        }),
+        DomainEvent::MovieCreated { id }
+        | DomainEvent::MovieUpdated { id }
+        | DomainEvent::MovieDeleted { id } => serde_json::json!({ "movie_id": id }),
+    }
+}
+
+/// Avro `long`: zig-zag encoded, then as a variable-length integer.
+fn write_long(out: &mut Vec<u8>, value: i64) {
+    // ---
+    let mut zigzag = ((value << 1) ^ (value >> 63)) as u64;
+    while zigzag >= 0x80 {
+        out.push((zigzag as u8 & 0x7f) | 0x80);
+        zigzag >>= 7;
+    }
+    out.push(zigzag as u8);
+}
+
+/// Avro `string`: the length in bytes as a `long`, then UTF-8.
+fn write_string(out: &mut Vec<u8>, value: &str) {
+    // ---
+    write_long(out, value.len() as i64);
+    out.extend_from_slice(value.as_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn avro_primitives_follow_the_spec() {
+        // ---
+        let encoded = |value| {
+            let mut out = Vec::new();
+            write_long(&mut out, value);
+            out
+        };
+        assert_eq!(encoded(0), [0x00]);
+        assert_eq!(encoded(-1), [0x01]);
+        assert_eq!(encoded(1), [0x02]);
+        assert_eq!(encoded(-64), [0x7f]);
+        assert_eq!(encoded(64), [0x80, 0x01]);
+
+        let mut out = Vec::new();
+        write_string(&mut out, "foo");
+        assert_eq!(out, [0x06, b'f', b'o', b'o']);
+    }
+
+    #[test]
+    fn envelopes_carry_the_event() {
+        // ---
+        let at = Utc.with_ymd_and_hms(2026, 10, 15, 12, 0, 0).unwrap();
+        let user_id = Uuid::new_v4();
+        let event = DomainEvent::AuthSucceeded {
+            user_id,
+            username: "alice".into(),
+            credential_id: None,
+            client_ip: Some("198.51.100.4".parse().unwrap()),
+        };
+
+        let message = encode(&event, EventFormat::Json, at);
+        let json: serde_json::Value = serde_json::from_slice(&message.payload).unwrap();
+        assert_eq!(json["id"], message.id.to_string());
+        assert_eq!(json["type"], "auth.succeeded");
+        assert_eq!(json["occurred_at"], "2026-10-15T12:00:00.000Z");
+        assert_eq!(json["user_id"], user_id.to_string());
+        assert_eq!(json["data"]["client_ip"], "198.51.100.4");
+        assert_eq!(message.key, Some(user_id.to_string()));
+
+        let movie = DomainEvent::MovieDeleted { id: "m1".into() };
+        let message = encode(&movie, EventFormat::Avro, at);
+        assert_eq!(message.key.as_deref(), Some("m1"));
+
+        // id, type, occurred_at, null user_id (union branch 0), data
+        let mut expected = Vec::new();
+        write_string(&mut expected, &message.id.to_string());
+        write_string(&mut expected, "movie.deleted");
+        write_long(&mut expected, at.timestamp_millis());
+        expected.push(0x00);
+        write_string(&mut expected, r#"{"movie_id":"m1"}"#);
+        assert_eq!(message.payload, expected);
+
+        // The published schema lists the fields in the order written
+        let schema: serde_json::Value =
+            serde_json::from_str(include_str!("../../../schemas/domain-event.avsc")).unwrap();
+        let fields: Vec<_> = schema["fields"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|field| field["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(fields, ["id", "type", "occurred_at", "user_id", "data"]);
+    }
+}
@@ -0,0 +1,89 @@
+//! Kafka producer for the event stream, speaking the Kafka protocol
+//! directly (rskafka), so no librdkafka is needed.
+//!
+//! Events are produced to partition 0 of the topic, which keeps them in
+//! publish order. Each record is keyed by the user or movie ID and carries
+//! `content-type`, `event-type`, and `message-id` headers.
+
+use super::encoding::{content_type, Message};
+use super::publisher::EventSink;
+use crate::config::EventStreamConfig;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use rskafka::client::partition::{Compression, PartitionClient, UnknownTopicHandling};
+use rskafka::client::ClientBuilder;
+use rskafka::record::Record;
+use std::collections::BTreeMap;
+
+const PARTITION: i32 = 0;
+
+pub(crate) struct KafkaSink {
+    // ---
+    brokers: Vec<String>,
+    topic: String,
+    content_type: &'static str,
+    client: Option<PartitionClient>,
+}
+
+impl KafkaSink {
+    // ---
+    pub fn new(cfg: &EventStreamConfig) -> Self {
+        // ---
+        Self {
+            brokers: cfg.brokers.clone(),
+            topic: cfg.topic.clone(),
+            content_type: content_type(cfg.format),
+            client: None,
+        }
+    }
+
+    async fn connect(&self) -> Result<PartitionClient> {
+        // ---
+        let client = ClientBuilder::new(self.brokers.clone())
+            .build()
+            .await
+            .context("Failed to connect to Kafka")?;
+        client
+            .partition_client(self.topic.clone(), PARTITION, UnknownTopicHandling::Error)
+            .await
+            .with_context(|| format!("Kafka topic {} unavailable", self.topic))
+    }
+
+    fn record(&self, message: &Message) -> Record {
+        // ---
+        let headers = BTreeMap::from([
+            ("content-type".to_string(), self.content_type.into()),
+            ("event-type".to_string(), message.kind.into()),
+            (
+                "message-id".to_string(),
+                message.id.to_string().into_bytes(),
+            ),
+        ]);
+        Record {
+            key: message.key.clone().map(String::into_bytes),
+            value: Some(message.payload.clone()),
+            headers,
+            timestamp: message.occurred_at,
+        }
+    }
+}
+
+#[async_trait]
+impl EventSink for KafkaSink {
+    // ---
+    async fn send(&mut self, batch: &[Message]) -> Result<()> {
+        // ---
+        // Taken out while in use, so a failed or cancelled send reconnects
+        let client = match self.client.take() {
+            Some(client) => client,
+            None => self.connect().await?,
+        };
+        let records = batch.iter().map(|message| self.record(message)).collect();
+        client
+            .produce(records, Compression::NoCompression)
+            .await
+            .context("Kafka produce failed")?;
+        self.client = Some(client);
+        Ok(())
+    }
+}
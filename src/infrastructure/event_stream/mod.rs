@@ -0,0 +1,60 @@
+// Gateway module - forwarding of domain events to Kafka or NATS
+// Modules are private, only exported symbols are public
+
+#[cfg(any(feature = "kafka", feature = "nats"))]
+mod encoding;
+#[cfg(feature = "kafka")]
+mod kafka;
+#[cfg(feature = "nats")]
+mod nats;
+#[cfg(any(feature = "kafka", feature = "nats"))]
+mod publisher;
+
+use crate::config::EventStreamConfig;
+use crate::domain::MetricsPtr;
+use crate::events::DomainEventBus;
+use crate::shutdown::ShutdownSignal;
+use anyhow::Result;
+
+/// Starts forwarding the domain events published on `bus` to the
+/// configured broker, on the current Tokio runtime, until `shutdown` is
+/// triggered. Does nothing if no broker is configured.
+///
+/// # Errors
+/// Returns an error if the binary was built without the broker's feature.
+///
+/// # Panics
+/// Panics if called outside a Tokio runtime.
+pub(crate) fn spawn_event_stream(
+    cfg: &EventStreamConfig,
+    bus: &DomainEventBus,
+    metrics: MetricsPtr,
+    shutdown: ShutdownSignal,
+) -> Result<()> {
+    // ---
+    let Some(broker) = cfg.broker else {
+        return Ok(());
+    };
+
+    match broker {
+        #[cfg(feature = "kafka")]
+        crate::config::EventBroker::Kafka => {
+            publisher::spawn(kafka::KafkaSink::new(cfg), cfg, bus, metrics, shutdown);
+            Ok(())
+        }
+        #[cfg(feature = "nats")]
+        crate::config::EventBroker::Nats => {
+            publisher::spawn(nats::NatsSink::new(cfg), cfg, bus, metrics, shutdown);
+            Ok(())
+        }
+        #[allow(unreachable_patterns)]
+        _ => {
+            // Only used when the broker's feature is built in
+            let _ = (bus, metrics, shutdown);
+            anyhow::bail!(
+                "AXUM_EVENT_STREAM={0} requires building with the {0} feature",
+                broker.as_str()
+            )
+        }
+    }
+}
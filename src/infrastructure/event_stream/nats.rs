@@ -0,0 +1,65 @@
+//! NATS publisher for the event stream.
+//!
+//! Each event is published on `{topic}.{event type}`, e.g.
+//! `axum-quickstart.events.user.registered`, so subscribers can pick events
+//! with subject wildcards. Messages carry `Content-Type` and a `Nats-Msg-Id`
+//! header, which JetStream uses to drop a message sent twice.
+
+use super::encoding::{content_type, Message};
+use super::publisher::EventSink;
+use crate::config::EventStreamConfig;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+
+pub(crate) struct NatsSink {
+    // ---
+    servers: String,
+    subject_prefix: String,
+    content_type: &'static str,
+    client: Option<async_nats::Client>,
+}
+
+impl NatsSink {
+    // ---
+    pub fn new(cfg: &EventStreamConfig) -> Self {
+        // ---
+        Self {
+            servers: cfg.brokers.join(","),
+            subject_prefix: cfg.topic.clone(),
+            content_type: content_type(cfg.format),
+            client: None,
+        }
+    }
+}
+
+#[async_trait]
+impl EventSink for NatsSink {
+    // ---
+    async fn send(&mut self, batch: &[Message]) -> Result<()> {
+        // ---
+        // Taken out while in use, so a failed or cancelled send reconnects
+        let client = match self.client.take() {
+            Some(client) => client,
+            None => async_nats::connect(self.servers.as_str())
+                .await
+                .context("Failed to connect to NATS")?,
+        };
+        for message in batch {
+            let mut headers = async_nats::HeaderMap::new();
+            headers.insert("Content-Type", self.content_type);
+            headers.insert("Nats-Msg-Id", message.id.to_string().as_str());
+            client
+                .publish_with_headers(
+                    format!("{}.{}", self.subject_prefix, message.kind),
+                    headers,
+                    message.payload.clone().into(),
+                )
+                .await
+                .context("NATS publish failed")?;
+        }
+        // Publishing only buffers; a message is out once flushed
+        client.flush().await.context("NATS flush failed")?;
+        self.client = Some(client);
+        Ok(())
+    }
+}
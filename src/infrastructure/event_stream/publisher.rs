@@ -0,0 +1,256 @@
+//! Forwarding of domain events to a message broker.
+//!
+//! Events are encoded as they are published and queued in memory, then
+//! sent in batches of up to [`BATCH_SIZE`]. While the broker is unreachable
+//! the queue holds up to `AXUM_EVENT_STREAM_MAX_BUFFERED` events, dropping
+//! the oldest beyond that, and sending is retried after
+//! `AXUM_EVENT_STREAM_RETRY_MS`, doubling up to [`MAX_RETRY_DELAY`] while
+//! the broker stays down. Delivery is at least once: a batch that failed
+//! partway is sent again whole, and consumers deduplicate on the `id`.
+//!
+//! Counted in `event_stream_messages_total{outcome}`: `delivered`,
+//! `failed` (a send that will be retried), and `dropped` (lost to a full
+//! queue, to a lagging subscription, or at shutdown). `event_stream_buffered`
+//! is the queue length. At shutdown one last attempt sends what is queued.
+
+use super::encoding::{self, Message};
+use crate::config::{EventFormat, EventStreamConfig};
+use crate::domain::MetricsPtr;
+use crate::events::{DomainEvent, DomainEventBus};
+use crate::shutdown::ShutdownSignal;
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::Utc;
+use std::collections::VecDeque;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::time::Instant;
+
+/// Most events sent in one request to the broker.
+const BATCH_SIZE: usize = 100;
+
+/// A send slower than this counts as failed.
+const SEND_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Longest wait between attempts while the broker is down.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// A connection to one broker.
+#[async_trait]
+pub(crate) trait EventSink: Send + 'static {
+    // ---
+
+    /// Sends `batch` in order, connecting first if not connected.
+    ///
+    /// # Errors
+    /// Returns an error if the broker cannot be reached or refuses a
+    /// message. The connection is then dropped, and made again on the next
+    /// call; so is one whose send was cancelled.
+    async fn send(&mut self, batch: &[Message]) -> Result<()>;
+}
+
+/// Starts forwarding the events published on `bus` through `sink`.
+///
+/// # Panics
+/// Panics if called outside a Tokio runtime.
+pub(crate) fn spawn(
+    sink: impl EventSink,
+    cfg: &EventStreamConfig,
+    bus: &DomainEventBus,
+    metrics: MetricsPtr,
+    shutdown: ShutdownSignal,
+) {
+    // ---
+    // Subscribe now, so events published while the task starts are sent
+    let mut receiver = bus.subscribe();
+    let mut forwarder = Forwarder {
+        sink,
+        format: cfg.format,
+        queue: VecDeque::new(),
+        max_buffered: cfg.max_buffered,
+        retry_delay: cfg.retry_delay,
+        backoff: cfg.retry_delay,
+        retry_at: None,
+        metrics,
+    };
+    tracing::info!(
+        "Forwarding domain events to {} topic {} as {:?}",
+        cfg.broker.map_or("-", |broker| broker.as_str()),
+        cfg.topic,
+        cfg.format
+    );
+
+    tokio::spawn(async move {
+        // ---
+        let stopped = shutdown.triggered();
+        tokio::pin!(stopped);
+
+        loop {
+            let retry_at = forwarder.retry_at;
+            tokio::select! {
+                received = receiver.recv() => match received {
+                    Ok(event) => forwarder.enqueue(&event),
+                    Err(RecvError::Lagged(missed)) => {
+                        tracing::warn!("Event stream fell behind; {missed} domain events dropped");
+                        forwarder.metrics.record_event_stream("dropped", missed);
+                    }
+                    Err(RecvError::Closed) => break,
+                },
+                _ = tokio::time::sleep_until(retry_at.unwrap_or_else(Instant::now)),
+                    if retry_at.is_some() => forwarder.retry_at = None,
+                _ = &mut stopped => break,
+            }
+            forwarder.flush().await;
+        }
+
+        forwarder.retry_at = None;
+        forwarder.flush().await;
+        if !forwarder.queue.is_empty() {
+            tracing::warn!(
+                "Event stream stopped with {} events unsent",
+                forwarder.queue.len()
+            );
+            forwarder
+                .metrics
+                .record_event_stream("dropped", forwarder.queue.len() as u64);
+        }
+    });
+}
+
+struct Forwarder<S> {
+    // ---
+    sink: S,
+    format: EventFormat,
+    queue: VecDeque<Message>,
+    max_buffered: usize,
+    retry_delay: Duration,
+
+    /// Wait after the next failure.
+    backoff: Duration,
+
+    /// Set after a failure; nothing is sent before then.
+    retry_at: Option<Instant>,
+    metrics: MetricsPtr,
+}
+
+impl<S: EventSink> Forwarder<S> {
+    // ---
+
+    fn enqueue(&mut self, event: &DomainEvent) {
+        // ---
+        if self.queue.len() >= self.max_buffered {
+            self.queue.pop_front();
+            self.metrics.record_event_stream("dropped", 1);
+        }
+        self.queue
+            .push_back(encoding::encode(event, self.format, Utc::now()));
+        self.metrics.record_event_stream_buffered(self.queue.len());
+    }
+
+    /// Sends the queue in batches, unless waiting to retry. Stops at the
+    /// first failure and schedules the retry.
+    async fn flush(&mut self) {
+        // ---
+        while self.retry_at.is_none() && !self.queue.is_empty() {
+            let len = self.queue.len().min(BATCH_SIZE);
+            let batch = &self.queue.make_contiguous()[..len];
+
+            let sent = match tokio::time::timeout(SEND_TIMEOUT, self.sink.send(batch)).await {
+                Ok(sent) => sent,
+                Err(_) => Err(anyhow::anyhow!(
+                    "timed out after {}s",
+                    SEND_TIMEOUT.as_secs()
+                )),
+            };
+            match sent {
+                Ok(()) => {
+                    self.queue.drain(..len);
+                    self.backoff = self.retry_delay;
+                    self.metrics.record_event_stream("delivered", len as u64);
+                }
+                Err(e) => {
+                    tracing::warn!(
+                        "Event stream send failed, retrying in {}ms with {} events queued: {e:#}",
+                        self.backoff.as_millis(),
+                        self.queue.len()
+                    );
+                    self.metrics.record_event_stream("failed", len as u64);
+                    self.retry_at = Some(Instant::now() + self.backoff);
+                    self.backoff = (self.backoff * 2).min(MAX_RETRY_DELAY);
+                }
+            }
+        }
+        self.metrics.record_event_stream_buffered(self.queue.len());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+    use crate::infrastructure::create_noop_metrics;
+    use std::sync::{Arc, Mutex};
+
+    /// Records what it sends, and fails while `down` is set.
+    #[derive(Clone, Default)]
+    struct Recorder {
+        sent: Arc<Mutex<Vec<&'static str>>>,
+        down: Arc<Mutex<bool>>,
+    }
+
+    #[async_trait]
+    impl EventSink for Recorder {
+        async fn send(&mut self, batch: &[Message]) -> Result<()> {
+            if *self.down.lock().unwrap() {
+                anyhow::bail!("broker down");
+            }
+            self.sent
+                .lock()
+                .unwrap()
+                .extend(batch.iter().map(|message| message.kind));
+            Ok(())
+        }
+    }
+
+    fn forwarder(sink: Recorder, max_buffered: usize) -> Forwarder<Recorder> {
+        Forwarder {
+            sink,
+            format: EventFormat::Json,
+            queue: VecDeque::new(),
+            max_buffered,
+            retry_delay: Duration::from_millis(100),
+            backoff: Duration::from_millis(100),
+            retry_at: None,
+            metrics: create_noop_metrics().unwrap(),
+        }
+    }
+
+    #[tokio::test]
+    async fn events_are_buffered_while_the_broker_is_down() {
+        // ---
+        let sink = Recorder::default();
+        let mut forwarder = forwarder(sink.clone(), 2);
+        *sink.down.lock().unwrap() = true;
+
+        forwarder.enqueue(&DomainEvent::MovieCreated { id: "m1".into() });
+        forwarder.flush().await;
+        assert!(forwarder.retry_at.is_some());
+        assert_eq!(forwarder.backoff, Duration::from_millis(200));
+
+        // Waiting to retry: queued, oldest dropped beyond the limit
+        forwarder.enqueue(&DomainEvent::MovieUpdated { id: "m1".into() });
+        forwarder.enqueue(&DomainEvent::MovieDeleted { id: "m1".into() });
+        forwarder.flush().await;
+        assert_eq!(forwarder.queue.len(), 2);
+
+        *sink.down.lock().unwrap() = false;
+        forwarder.retry_at = None;
+        forwarder.flush().await;
+        assert!(forwarder.queue.is_empty());
+        assert_eq!(forwarder.backoff, Duration::from_millis(100));
+        assert_eq!(
+            *sink.sent.lock().unwrap(),
+            ["movie.updated", "movie.deleted"]
+        );
+    }
+}
@@ -0,0 +1,264 @@
+//! Outbound HTTP calls with shared timeouts, retries, proxy, and metrics.
+//!
+//! Every outbound request made by the server goes through one
+//! [`HttpClient`], so they all get the same connect and request timeouts
+//! and proxy ([`HttpClientConfig`]), and are counted in
+//! `outbound_requests_total{host,status}` (`status="error"` when no
+//! response arrived). Requests made while handling an inbound request
+//! carry its W3C trace as `traceparent` unless the caller set one.
+
+use crate::config::HttpClientConfig;
+use crate::domain::MetricsPtr;
+use crate::trace_context::{TraceContext, TRACEPARENT};
+use anyhow::{Context, Result};
+use reqwest::{IntoUrl, Method, Request, RequestBuilder, Response, StatusCode};
+use std::time::Duration;
+
+/// Upper bound on the delay between retries.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(10);
+
+/// Outbound HTTP client. Cheap to clone; clones share connections.
+#[derive(Clone)]
+pub struct HttpClient {
+    // ---
+    inner: reqwest::Client,
+    metrics: MetricsPtr,
+    max_retries: u32,
+    retry_base: Duration,
+}
+
+impl HttpClient {
+    // ---
+
+    /// A client with the timeouts, retry policy, and proxy in `cfg`,
+    /// recording requests in `metrics`.
+    ///
+    /// # Errors
+    /// Returns an error if the proxy URL is invalid or the TLS backend
+    /// cannot be initialized.
+    pub fn new(cfg: &HttpClientConfig, metrics: MetricsPtr) -> Result<Self> {
+        // ---
+        let mut builder = reqwest::Client::builder()
+            .timeout(cfg.timeout)
+            .connect_timeout(cfg.connect_timeout);
+        if let Some(proxy) = &cfg.proxy {
+            builder = builder.proxy(reqwest::Proxy::all(proxy).context("Invalid AXUM_HTTP_PROXY")?);
+        }
+
+        Ok(Self {
+            inner: builder
+                .build()
+                .context("Failed to create the outbound HTTP client")?,
+            metrics,
+            max_retries: cfg.max_retries,
+            retry_base: cfg.retry_base,
+        })
+    }
+
+    /// Starts a request; send it with [`send`](Self::send) or
+    /// [`send_once`](Self::send_once). A timeout set on the builder
+    /// replaces the default for that request.
+    pub fn request(&self, method: Method, url: impl IntoUrl) -> RequestBuilder {
+        // ---
+        self.inner.request(method, url)
+    }
+
+    pub fn get(&self, url: impl IntoUrl) -> RequestBuilder {
+        // ---
+        self.request(Method::GET, url)
+    }
+
+    pub fn post(&self, url: impl IntoUrl) -> RequestBuilder {
+        // ---
+        self.request(Method::POST, url)
+    }
+
+    pub fn put(&self, url: impl IntoUrl) -> RequestBuilder {
+        // ---
+        self.request(Method::PUT, url)
+    }
+
+    /// Sends `request`, retrying idempotent requests (`GET`, `HEAD`, `PUT`,
+    /// `DELETE`, `OPTIONS`) after a connection error, timeout, or a 429,
+    /// 502, 503, or 504 response, with exponential backoff. Other requests,
+    /// and requests with a streaming body, are sent once.
+    ///
+    /// Returns the last response, whatever its status.
+    ///
+    /// # Errors
+    /// Returns an error if the request cannot be built or the last attempt
+    /// got no response.
+    pub async fn send(&self, request: RequestBuilder) -> reqwest::Result<Response> {
+        // ---
+        let request = request.build()?;
+        let retries = match is_idempotent(request.method()) {
+            true => self.max_retries,
+            false => 0,
+        };
+
+        let mut delay = self.retry_base;
+        let mut pending = request;
+        for _ in 0..retries {
+            // Streaming bodies cannot be replayed
+            let Some(next) = pending.try_clone() else {
+                break;
+            };
+            match self.execute(pending).await {
+                Ok(response) if !is_retryable_status(response.status()) => return Ok(response),
+                Err(e) if !(e.is_connect() || e.is_timeout()) => return Err(e),
+                Ok(response) => tracing::debug!(
+                    "Retrying {} {}: HTTP {}",
+                    next.method(),
+                    host(&next),
+                    response.status()
+                ),
+                Err(e) => tracing::debug!("Retrying {} {}: {e}", next.method(), host(&next)),
+            }
+            tokio::time::sleep(delay).await;
+            delay = (delay * 2).min(MAX_RETRY_DELAY);
+            pending = next;
+        }
+        self.execute(pending).await
+    }
+
+    /// Sends `request` once, for callers with a retry schedule of their own.
+    ///
+    /// # Errors
+    /// Returns an error if the request cannot be built or got no response.
+    pub async fn send_once(&self, request: RequestBuilder) -> reqwest::Result<Response> {
+        // ---
+        self.execute(request.build()?).await
+    }
+
+    /// One attempt, counted in `outbound_requests_total`.
+    async fn execute(&self, mut request: Request) -> reqwest::Result<Response> {
+        // ---
+        if !request.headers().contains_key(TRACEPARENT) {
+            if let Some(trace) = TraceContext::current() {
+                trace.child().inject(request.headers_mut());
+            }
+        }
+
+        let host = host(&request);
+        let result = self.inner.execute(request).await;
+        let status = result
+            .as_ref()
+            .ok()
+            .map(|response| response.status().as_u16());
+        self.metrics.record_outbound_request(&host, status);
+        result
+    }
+}
+
+fn host(request: &Request) -> String {
+    // ---
+    request.url().host_str().unwrap_or("-").to_string()
+}
+
+fn is_idempotent(method: &Method) -> bool {
+    // ---
+    [
+        Method::GET,
+        Method::HEAD,
+        Method::PUT,
+        Method::DELETE,
+        Method::OPTIONS,
+    ]
+    .contains(method)
+}
+
+fn is_retryable_status(status: StatusCode) -> bool {
+    // ---
+    [
+        StatusCode::TOO_MANY_REQUESTS,
+        StatusCode::BAD_GATEWAY,
+        StatusCode::SERVICE_UNAVAILABLE,
+        StatusCode::GATEWAY_TIMEOUT,
+    ]
+    .contains(&status)
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+    use crate::infrastructure::create_noop_metrics;
+    use axum::{extract::State, http::HeaderMap, routing::any, Router};
+    use std::sync::{Arc, Mutex};
+
+    /// Requests seen by the fake upstream, as their `traceparent` if any.
+    type Seen = Arc<Mutex<Vec<Option<String>>>>;
+
+    /// An upstream answering 503 to the first request and 204 after.
+    async fn spawn_flaky_upstream() -> (String, Seen) {
+        // ---
+        async fn handle(State(seen): State<Seen>, headers: HeaderMap) -> StatusCode {
+            // ---
+            let mut seen = seen.lock().unwrap();
+            seen.push(
+                headers
+                    .get(TRACEPARENT)
+                    .map(|value| value.to_str().unwrap().to_string()),
+            );
+            match seen.len() {
+                1 => StatusCode::SERVICE_UNAVAILABLE,
+                _ => StatusCode::NO_CONTENT,
+            }
+        }
+
+        let seen = Seen::default();
+        let app = Router::new()
+            .route("/", any(handle))
+            .with_state(seen.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (url, seen)
+    }
+
+    fn client() -> HttpClient {
+        // ---
+        let cfg = HttpClientConfig {
+            retry_base: Duration::from_millis(1),
+            ..Default::default()
+        };
+        HttpClient::new(&cfg, create_noop_metrics().unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn retries_idempotent_requests_only() {
+        // ---
+        let http = client();
+
+        let (url, seen) = spawn_flaky_upstream().await;
+        let response = http.send(http.put(&url).body("x")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert_eq!(seen.lock().unwrap().len(), 2);
+
+        let (url, seen) = spawn_flaky_upstream().await;
+        let response = http.send(http.post(&url).body("x")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(seen.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn requests_carry_the_current_trace() {
+        // ---
+        let http = client();
+        let (url, seen) = spawn_flaky_upstream().await;
+        let trace = TraceContext::new_root();
+
+        trace
+            .clone()
+            .scope(http.send_once(http.get(&url)))
+            .await
+            .unwrap();
+        http.send_once(http.get(&url)).await.unwrap();
+
+        let seen = seen.lock().unwrap();
+        let sent = seen[0].as_deref().expect("traceparent is injected");
+        assert!(sent.contains(&trace.trace_id()));
+        assert!(!sent.contains(&trace.span_id()));
+        assert_eq!(seen[1], None);
+    }
+}
@@ -0,0 +1,6 @@
+// Gateway module - shared outbound HTTP client
+// Modules are private, only exported symbols are public
+
+mod client;
+
+pub use client::HttpClient;
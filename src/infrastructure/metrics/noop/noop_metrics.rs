@@ -1,5 +1,5 @@
-use crate::domain::Metrics;
-use std::time::Instant;
+use crate::domain::{Metrics, RuntimeSample};
+use std::time::{Duration, Instant};
 
 /// No-op metrics implementation for testing.
 pub struct NoopMetrics;
@@ -16,5 +16,21 @@ impl Metrics for NoopMetrics {
         String::new()
     }
     fn record_movie_created(&self) {}
+    fn record_movie_updated(&self) {}
+    fn record_movie_deleted(&self) {}
     fn record_http_request(&self, _: Instant, _: &str, _: &str, _: u16) {}
+    fn record_orphan_users_removed(&self, _: u64, _: bool) {}
+    fn record_retention_purged(&self, _: &str, _: u64, _: bool) {}
+    fn record_event_stream(&self, _: &str, _: u64) {}
+    fn record_event_stream_buffered(&self, _: usize) {}
+    fn record_webhook_delivered(&self) {}
+    fn record_webhook_dead_letter(&self) {}
+    fn record_sign_count_anomaly(&self, _: bool) {}
+    fn record_redis_retry(&self, _: bool) {}
+    fn record_in_flight_request(&self, _: bool) {}
+    fn record_runtime_sample(&self, _: &RuntimeSample) {}
+    fn record_outbound_request(&self, _: &str, _: Option<u16>) {}
+    fn record_db_query(&self, _: &str, _: Duration) {}
+    fn record_challenge_failure(&self, _: &str, _: bool) {}
+    fn record_panic(&self) {}
 }
@@ -1,13 +1,142 @@
-use metrics::{counter, histogram};
-use std::time::Instant;
+use crate::domain::RuntimeSample;
+use metrics::{counter, gauge, histogram};
+use std::time::{Duration, Instant};
 
 /// Increment a counter for created movies.
 pub fn increment_movie_created() {
     counter!("movies_created_total").increment(1);
 }
 
+/// Increment a counter for updated movies.
+pub fn increment_movie_updated() {
+    counter!("movies_updated_total").increment(1);
+}
+
+/// Increment a counter for deleted movies.
+pub fn increment_movie_deleted() {
+    counter!("movies_deleted_total").increment(1);
+}
+
+/// Count users deleted by the orphan cleanup job.
+///
+/// Dry runs are counted under `dry_run="true"` so they can be compared with
+/// real runs before enabling deletion.
+pub fn increment_orphan_users_removed(count: u64, dry_run: bool) {
+    counter!("orphan_users_removed_total", "dry_run" => dry_run.to_string()).increment(count);
+}
+
+/// Increment a counter for records removed by a retention rule, labelled
+/// by rule and, like orphan cleanup, by dry run.
+pub fn increment_retention_purged(rule: &str, count: u64, dry_run: bool) {
+    counter!(
+        "retention_purged_total",
+        "rule" => rule.to_string(),
+        "dry_run" => dry_run.to_string()
+    )
+    .increment(count);
+}
+
+/// Increment a counter for domain events forwarded to the event stream,
+/// labelled by outcome.
+pub fn increment_event_stream(outcome: &str, count: u64) {
+    counter!("event_stream_messages_total", "outcome" => outcome.to_string()).increment(count);
+}
+
+/// Set the number of events waiting for the event stream broker.
+pub fn set_event_stream_buffered(buffered: usize) {
+    gauge!("event_stream_buffered").set(buffered as f64);
+}
+
+/// Increment a counter for webhooks delivered to an endpoint.
+pub fn increment_webhook_delivered() {
+    counter!("webhook_deliveries_total").increment(1);
+}
+
+/// Increment a counter for webhooks that could not be delivered.
+pub fn increment_webhook_dead_letter() {
+    counter!("webhook_dead_letters_total").increment(1);
+}
+
+/// Count sign-ins whose signature counter did not increase, labelled by
+/// whether the sign-in was rejected (`action="rejected"`) or allowed.
+pub fn increment_sign_count_anomaly(rejected: bool) {
+    let action = if rejected { "rejected" } else { "allowed" };
+    counter!("sign_count_anomalies_total", "action" => action).increment(1);
+}
+
+/// Count Redis operations that were retried after a transient error,
+/// labelled `outcome="recovered"` if a retry succeeded, else `"exhausted"`.
+pub fn increment_redis_retry(recovered: bool) {
+    let outcome = if recovered { "recovered" } else { "exhausted" };
+    counter!("redis_retries_total", "outcome" => outcome).increment(1);
+}
+
+/// Count ceremony finishes whose challenge could not be taken, by ceremony
+/// and `reason="expired"` or `"redis_error"`.
+pub fn increment_challenge_failure(ceremony: &str, redis_error: bool) {
+    let reason = if redis_error {
+        "redis_error"
+    } else {
+        "expired"
+    };
+    counter!(
+        "webauthn_challenge_failures_total",
+        "ceremony" => ceremony.to_string(),
+        "reason" => reason
+    )
+    .increment(1);
+}
+
+/// Increment a counter for handler panics answered with a 500.
+pub fn increment_panic() {
+    counter!("panics_total").increment(1);
+}
+
+/// Count outbound HTTP requests by host and response status, or
+/// `status="error"` when no response arrived.
+pub fn increment_outbound_request(host: &str, status: Option<u16>) {
+    let status = status.map_or_else(|| "error".to_string(), |status| status.to_string());
+    counter!("outbound_requests_total", "host" => host.to_string(), "status" => status)
+        .increment(1);
+}
+
+/// Track requests currently being handled in `http_requests_in_flight`.
+pub fn track_in_flight_request(started: bool) {
+    let in_flight = gauge!("http_requests_in_flight");
+    if started {
+        in_flight.increment(1.0);
+    } else {
+        in_flight.decrement(1.0);
+    }
+}
+
+/// Publish a runtime snapshot as `tokio_*` gauges. Blocking pool gauges
+/// are only set when the sample includes them.
+pub fn set_runtime_gauges(sample: &RuntimeSample) {
+    gauge!("tokio_workers").set(sample.workers as f64);
+    gauge!("tokio_alive_tasks").set(sample.alive_tasks as f64);
+    gauge!("tokio_global_queue_depth").set(sample.global_queue_depth as f64);
+    gauge!("tokio_worker_busy_ratio").set(sample.worker_busy_ratio);
+
+    let blocking = [
+        ("tokio_blocking_threads", sample.blocking_threads),
+        ("tokio_idle_blocking_threads", sample.idle_blocking_threads),
+        ("tokio_blocking_queue_depth", sample.blocking_queue_depth),
+    ];
+    for (name, value) in blocking {
+        if let Some(value) = value {
+            gauge!(name).set(value as f64);
+        }
+    }
+}
+
 /// Track HTTP request latency using a histogram.
 pub fn track_http_request(start: Instant) {
     let elapsed = start.elapsed();
     histogram!("http_request_duration_seconds").record(elapsed);
 }
+
+/// Track repository call latency by query name using a histogram.
+pub fn track_db_query(query_name: &str, elapsed: Duration) {
+    histogram!("db_query_duration_seconds", "query_name" => query_name.to_string()).record(elapsed);
+}
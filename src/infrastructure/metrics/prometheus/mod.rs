@@ -6,7 +6,14 @@ pub use prometheus_metrics::PrometheusMetrics;
 use std::sync::Arc;
 
 // Re-export utilities for internal use within this module
-pub(crate) use counters::{increment_movie_created, track_http_request};
+pub(crate) use counters::{
+    increment_challenge_failure, increment_event_stream, increment_movie_created,
+    increment_movie_deleted, increment_movie_updated, increment_orphan_users_removed,
+    increment_outbound_request, increment_panic, increment_redis_retry, increment_retention_purged,
+    increment_sign_count_anomaly, increment_webhook_dead_letter, increment_webhook_delivered,
+    set_event_stream_buffered, set_runtime_gauges, track_db_query, track_http_request,
+    track_in_flight_request,
+};
 pub(crate) use recorder::{init_metrics, render_metrics};
 
 /// Creates a new Prometheus metrics implementation.
@@ -15,10 +22,14 @@ pub(crate) use recorder::{init_metrics, render_metrics};
 /// expose them via HTTP endpoint for scraping.
 ///
 /// Returns a fully initialized metrics instance ready for use.
+///
+/// # Errors
+/// Returns an error if the Prometheus recorder cannot be installed as the
+/// global `metrics` recorder, e.g. because another one was installed first.
 pub fn create() -> anyhow::Result<crate::domain::MetricsPtr> {
     tracing::info!("Initializing Prometheus metrics");
     // TODO: Start HTTP server for /metrics endpoint, initialize registry, etc.
-    init_metrics();
+    init_metrics()?;
 
     Ok(Arc::new(PrometheusMetrics::new()))
 }
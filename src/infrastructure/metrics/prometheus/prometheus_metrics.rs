@@ -9,8 +9,8 @@
 //! automatically registered when first used, and a single global handle
 //! manages rendering all collected metrics in Prometheus text format.
 
-use crate::domain::Metrics;
-use std::time::Instant;
+use crate::domain::{Metrics, RuntimeSample};
+use std::time::{Duration, Instant};
 
 /// Prometheus-based metrics implementation.
 ///
@@ -42,8 +42,84 @@ impl Metrics for PrometheusMetrics {
         super::increment_movie_created();
     }
 
+    fn record_movie_updated(&self) {
+        tracing::debug!("Recording movie updated event");
+        super::increment_movie_updated();
+    }
+
+    fn record_movie_deleted(&self) {
+        tracing::debug!("Recording movie deleted event");
+        super::increment_movie_deleted();
+    }
+
     fn record_http_request(&self, start: Instant, _path: &str, _method: &str, _status: u16) {
         tracing::debug!("Recording HTTP request duration");
         super::track_http_request(start);
     }
+
+    fn record_orphan_users_removed(&self, count: u64, dry_run: bool) {
+        tracing::debug!("Recording {count} orphaned users (dry_run={dry_run})");
+        super::increment_orphan_users_removed(count, dry_run);
+    }
+
+    fn record_retention_purged(&self, rule: &str, count: u64, dry_run: bool) {
+        tracing::debug!(
+            "Recording {count} records purged by retention rule {rule} (dry_run={dry_run})"
+        );
+        super::increment_retention_purged(rule, count, dry_run);
+    }
+
+    fn record_event_stream(&self, outcome: &str, count: u64) {
+        super::increment_event_stream(outcome, count);
+    }
+
+    fn record_event_stream_buffered(&self, buffered: usize) {
+        super::set_event_stream_buffered(buffered);
+    }
+
+    fn record_webhook_delivered(&self) {
+        tracing::debug!("Recording webhook delivered");
+        super::increment_webhook_delivered();
+    }
+
+    fn record_webhook_dead_letter(&self) {
+        tracing::debug!("Recording webhook dead letter");
+        super::increment_webhook_dead_letter();
+    }
+
+    fn record_sign_count_anomaly(&self, rejected: bool) {
+        tracing::debug!("Recording sign count anomaly (rejected={rejected})");
+        super::increment_sign_count_anomaly(rejected);
+    }
+
+    fn record_redis_retry(&self, recovered: bool) {
+        tracing::debug!("Recording Redis retry (recovered={recovered})");
+        super::increment_redis_retry(recovered);
+    }
+
+    fn record_in_flight_request(&self, started: bool) {
+        super::track_in_flight_request(started);
+    }
+
+    fn record_runtime_sample(&self, sample: &RuntimeSample) {
+        super::set_runtime_gauges(sample);
+    }
+
+    fn record_outbound_request(&self, host: &str, status: Option<u16>) {
+        super::increment_outbound_request(host, status);
+    }
+
+    fn record_db_query(&self, query_name: &str, elapsed: Duration) {
+        super::track_db_query(query_name, elapsed);
+    }
+
+    fn record_challenge_failure(&self, ceremony: &str, redis_error: bool) {
+        tracing::debug!("Recording challenge failure ({ceremony}, redis_error={redis_error})");
+        super::increment_challenge_failure(ceremony, redis_error);
+    }
+
+    fn record_panic(&self) {
+        tracing::debug!("Recording handler panic");
+        super::increment_panic();
+    }
 }
@@ -1,24 +1,63 @@
+use anyhow::{anyhow, Result};
 use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
 use std::sync::OnceLock;
 
-static HANDLE: OnceLock<PrometheusHandle> = OnceLock::new();
+/// The installed recorder's handle, or why it could not be installed. Only
+/// one global recorder can ever be installed, so a failure is final too.
+static HANDLE: OnceLock<Result<PrometheusHandle, String>> = OnceLock::new();
 
-/// Initialize the Prometheus recorder globally and store the handle.
-/// This function is safe to call multiple times - it will only initialize once.
-/// Returns true if initialization was successful, false if already initialized.
-pub fn init_metrics() -> bool {
-    HANDLE.get_or_init(|| {
+/// Install the Prometheus recorder globally and store the handle.
+///
+/// Safe to call concurrently and more than once: the first call installs
+/// the recorder, and every call returns its outcome.
+///
+/// # Errors
+/// Returns an error if the recorder could not be installed, e.g. because
+/// the embedding application installed its own `metrics` recorder first.
+pub fn init_metrics() -> Result<()> {
+    // ---
+    let installed = HANDLE.get_or_init(|| {
         PrometheusBuilder::new()
             .install_recorder()
-            .expect("failed to install Prometheus recorder")
+            .map_err(|e| e.to_string())
     });
-    true
+    match installed {
+        Ok(_) => Ok(()),
+        Err(e) => Err(anyhow!("Failed to install Prometheus recorder: {e}")),
+    }
 }
 
 /// Render the current metrics in Prometheus text format.
+///
+/// Without an installed recorder this is a single comment line saying so,
+/// which scrapers read as an empty exposition, rather than a panic.
 pub fn render_metrics() -> String {
-    HANDLE
-        .get()
-        .expect("metrics recorder not initialized")
-        .render()
+    // ---
+    match HANDLE.get() {
+        Some(Ok(handle)) => handle.render(),
+        Some(Err(e)) => format!("# Metrics unavailable: Prometheus recorder not installed ({e})\n"),
+        None => "# Metrics unavailable: Prometheus recorder not initialized\n".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+
+    #[test]
+    fn concurrent_inits_agree() {
+        // ---
+        let outcomes: Vec<bool> = std::thread::scope(|scope| {
+            let inits: Vec<_> = (0..8)
+                .map(|_| scope.spawn(|| init_metrics().is_ok()))
+                .collect();
+            inits.into_iter().map(|init| init.join().unwrap()).collect()
+        });
+        assert!(outcomes.iter().all(|ok| *ok == outcomes[0]));
+
+        // Either way rendering answers rather than panicking
+        let rendered = render_metrics();
+        assert!(outcomes[0] || rendered.starts_with("# Metrics unavailable"));
+    }
 }
@@ -1,12 +1,23 @@
 mod database;
+mod encryption;
+mod event_stream;
+mod http_client;
 mod webauthn;
 
 pub mod metrics;
 
 // Re-export the factory functions for easy access
-pub use database::postgres_repository::{
-    create_postgres_repository, init_database_with_retry_from_env,
+pub use database::postgres_repository::PostgresRepository;
+#[cfg(feature = "sqlite")]
+pub use database::sqlite_repository::SqliteRepository;
+pub use database::{
+    create_postgres_repository, create_repository, default_repository,
+    init_database_with_retry_from_env,
 };
+pub(crate) use database::{probe_database, DeadlineRepository, InstrumentedRepository};
+pub use encryption::{EncryptedRepository, LocalKeyProvider};
+pub(crate) use event_stream::spawn_event_stream;
+pub use http_client::HttpClient;
 pub use metrics::{create_noop_metrics, create_prom_metrics};
 
 pub use webauthn::*;
@@ -13,25 +13,57 @@ use webauthn_rs::{Webauthn, WebauthnBuilder};
 /// Creates a configured WebAuthn instance from application config.
 ///
 /// # Parameters
-/// - `config`: WebAuthn configuration (RP ID, origin, etc.)
+/// - `config`: WebAuthn configuration (RP ID, origins, etc.)
 ///
 /// # Returns
 /// A configured `Webauthn` instance ready for registration/authentication flows.
 ///
 /// # Errors
 /// Returns an error if the WebAuthn builder fails to construct a valid instance.
-/// This typically happens if the origin URL or RP ID are malformed.
+/// This typically happens if an origin URL or the RP ID are malformed, or a
+/// web origin is not the RP ID or one of its subdomains.
 pub fn create_webauthn(config: &WebAuthnConfig) -> Result<Webauthn> {
     // ---
     tracing::debug!("Creating with config:{:?}", config);
 
     let url = Url::from_str(config.origin.as_str())?;
-    let builder = WebauthnBuilder::new(&config.rp_id, &url)?;
+    let mut builder = WebauthnBuilder::new(&config.rp_id, &url)?;
+
+    for origin in &config.additional_origins {
+        let url = Url::from_str(origin)
+            .map_err(|e| anyhow::anyhow!("Invalid WebAuthn origin '{origin}': {e}"))?;
+        ensure_origin_matches_rp(&url, &config.rp_id)?;
+        builder = builder.append_allowed_origin(&url);
+    }
+
     let webauthn = builder.rp_name(&config.rp_name).build()?;
 
     Ok(webauthn)
 }
 
+/// Checks that a web origin's host is the RP ID or a subdomain of it, the
+/// same rule `WebauthnBuilder::new` applies to the primary origin.
+///
+/// Native app origins (`android:`, `ios:`, ...) have no host and are not
+/// checked; the platform binds them to the RP through its own association
+/// files.
+fn ensure_origin_matches_rp(origin: &Url, rp_id: &str) -> Result<()> {
+    // ---
+    if !matches!(origin.scheme(), "http" | "https") {
+        return Ok(());
+    }
+
+    let matches = origin
+        .domain()
+        .is_some_and(|domain| domain == rp_id || domain.ends_with(&format!(".{rp_id}")));
+    anyhow::ensure!(
+        matches,
+        "WebAuthn origin '{origin}' is not within RP ID '{rp_id}'"
+    );
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -42,6 +74,7 @@ mod tests {
             rp_id: "localhost".to_string(),
             rp_name: "Test App".to_string(),
             origin: "http://localhost:8080".to_string(),
+            additional_origins: Vec::new(),
         };
 
         let result = create_webauthn(&config);
@@ -54,9 +87,31 @@ mod tests {
             rp_id: "localhost".to_string(),
             rp_name: "Test App".to_string(),
             origin: "not-a-valid-url".to_string(),
+            additional_origins: Vec::new(),
         };
 
         let result = create_webauthn(&config);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn create_webauthn_additional_origins() {
+        let config = |origins: &[&str]| WebAuthnConfig {
+            rp_id: "example.com".to_string(),
+            rp_name: "Test App".to_string(),
+            origin: "https://example.com".to_string(),
+            additional_origins: origins.iter().map(|o| o.to_string()).collect(),
+        };
+
+        let allowed = config(&[
+            "https://staging.example.com",
+            "http://localhost.example.com:8080",
+            "android:apk-key-hash:abc123",
+        ]);
+        assert!(create_webauthn(&allowed).is_ok());
+
+        assert!(create_webauthn(&config(&["https://example.org"])).is_err());
+        assert!(create_webauthn(&config(&["https://notexample.com"])).is_err());
+        assert!(create_webauthn(&config(&["not a url"])).is_err());
+    }
 }
@@ -0,0 +1,12 @@
+// Gateway module - background maintenance jobs
+// Modules are private, only exported symbols are public
+
+mod orphan_cleanup;
+mod push_gateway;
+mod retention;
+mod runtime_metrics;
+
+pub use orphan_cleanup::{cleanup_orphaned_users, spawn_orphan_cleanup};
+pub use push_gateway::spawn_push_gateway;
+pub(crate) use retention::RetentionEngine;
+pub use runtime_metrics::spawn_runtime_metrics;
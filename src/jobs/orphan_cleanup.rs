@@ -0,0 +1,80 @@
+//! Periodic removal of users who never finished registration.
+//!
+//! `register_start` creates the user row before a passkey exists. If the
+//! client never calls `register_finish`, that user has no credentials and
+//! would otherwise stay in the database forever.
+
+use crate::config::CleanupConfig;
+use crate::domain::{MetricsPtr, RepositoryPtr};
+use anyhow::Result;
+use chrono::Utc;
+use tokio::task::JoinHandle;
+
+/// Runs one cleanup pass.
+///
+/// Deletes users with no credentials that are older than
+/// `cfg.orphan_user_max_age`, or only counts them when `cfg.dry_run` is set.
+/// The count is logged and recorded in metrics either way.
+///
+/// # Errors
+/// Returns an error if the repository query fails.
+pub async fn cleanup_orphaned_users(
+    repository: &RepositoryPtr,
+    metrics: &MetricsPtr,
+    cfg: &CleanupConfig,
+) -> Result<u64> {
+    // ---
+    let max_age = chrono::Duration::from_std(cfg.orphan_user_max_age)?;
+    let created_before = Utc::now() - max_age;
+
+    let count = repository
+        .delete_orphaned_users(created_before, cfg.dry_run)
+        .await?;
+
+    if cfg.dry_run {
+        tracing::info!("Orphan cleanup (dry run): {count} users created before {created_before} have no credentials");
+    } else {
+        tracing::info!("Orphan cleanup: removed {count} users created before {created_before}");
+    }
+    metrics.record_orphan_users_removed(count, cfg.dry_run);
+
+    Ok(count)
+}
+
+/// Spawns the cleanup job on the current Tokio runtime.
+///
+/// Runs once per `cfg.orphan_cleanup_interval`, starting one interval after
+/// startup. Errors are logged and the job keeps running. Returns `None`
+/// without spawning anything if the job is disabled.
+pub fn spawn_orphan_cleanup(
+    repository: RepositoryPtr,
+    metrics: MetricsPtr,
+    cfg: CleanupConfig,
+) -> Option<JoinHandle<()>> {
+    // ---
+    let Some(period) = cfg.orphan_cleanup_interval else {
+        tracing::info!("Orphan cleanup job disabled");
+        return None;
+    };
+
+    tracing::info!(
+        "Orphan cleanup job every {}s (max age {}s, dry_run={})",
+        period.as_secs(),
+        cfg.orphan_user_max_age.as_secs(),
+        cfg.dry_run
+    );
+
+    Some(tokio::spawn(async move {
+        // ---
+        let mut interval = tokio::time::interval(period);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        interval.tick().await; // first tick completes immediately
+
+        loop {
+            interval.tick().await;
+            if let Err(e) = cleanup_orphaned_users(&repository, &metrics, &cfg).await {
+                tracing::error!("Orphan cleanup failed: {e}");
+            }
+        }
+    }))
+}
@@ -0,0 +1,207 @@
+//! Periodic push of metrics to a Prometheus pushgateway.
+//!
+//! Short-lived or unreachable processes cannot be scraped, so instead the
+//! rendered metrics are sent to a pushgateway, which Prometheus scrapes in
+//! their place. Each push replaces the whole group
+//! (`/metrics/job/{job}[/instance/{instance}]`), so metrics that are no
+//! longer rendered disappear with it. A last push runs at shutdown so the
+//! final values of a batch run are kept.
+
+use crate::config::PushGatewayConfig;
+use crate::domain::MetricsPtr;
+use crate::infrastructure::HttpClient;
+use crate::shutdown::ShutdownSignal;
+use anyhow::{Context, Result};
+use reqwest::Url;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+/// A push slower than this is abandoned; the next one replaces it anyway.
+const PUSH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Pushes rendered metrics to one pushgateway group.
+pub(crate) struct PushGateway {
+    // ---
+    http: HttpClient,
+    url: Url,
+    metrics: MetricsPtr,
+}
+
+impl PushGateway {
+    // ---
+
+    /// Returns `Ok(None)` if no pushgateway is configured.
+    ///
+    /// # Errors
+    /// Returns an error if the URL does not parse.
+    pub fn new(
+        cfg: &PushGatewayConfig,
+        http: HttpClient,
+        metrics: MetricsPtr,
+    ) -> Result<Option<Self>> {
+        // ---
+        let Some(base) = &cfg.url else {
+            return Ok(None);
+        };
+        let mut url = Url::parse(base).context("Invalid AXUM_PUSHGATEWAY_URL")?;
+        {
+            let mut segments = url
+                .path_segments_mut()
+                .map_err(|()| anyhow::anyhow!("Invalid AXUM_PUSHGATEWAY_URL: {base}"))?;
+            segments.pop_if_empty().extend(["metrics", "job", &cfg.job]);
+            if let Some(instance) = &cfg.instance {
+                segments.extend(["instance", instance]);
+            }
+        }
+
+        Ok(Some(Self { http, url, metrics }))
+    }
+
+    /// Replaces the group's metrics with the current ones.
+    ///
+    /// # Errors
+    /// Returns an error if the pushgateway cannot be reached or rejects the
+    /// push.
+    pub async fn push(&self) -> Result<()> {
+        // ---
+        let request = self
+            .http
+            .put(self.url.clone())
+            .timeout(PUSH_TIMEOUT)
+            .header(reqwest::header::CONTENT_TYPE, CONTENT_TYPE)
+            .body(self.metrics.render());
+        self.http.send(request).await?.error_for_status()?;
+        Ok(())
+    }
+}
+
+/// Spawns the pusher on the current Tokio runtime, pushing through `http`.
+///
+/// Pushes every `cfg.interval`, and once more when `shutdown` is triggered.
+/// Returns `Ok(None)` without spawning anything if no pushgateway is
+/// configured.
+///
+/// # Errors
+/// Returns an error if the pushgateway URL is invalid.
+pub fn spawn_push_gateway(
+    http: HttpClient,
+    metrics: MetricsPtr,
+    cfg: &PushGatewayConfig,
+    shutdown: ShutdownSignal,
+) -> Result<Option<JoinHandle<()>>> {
+    // ---
+    let Some(gateway) = PushGateway::new(cfg, http, metrics)? else {
+        return Ok(None);
+    };
+
+    // The URL is not logged, as it may carry basic auth credentials
+    tracing::info!(
+        "Pushing metrics to the pushgateway as job {} every {}s",
+        cfg.job,
+        cfg.interval.as_secs()
+    );
+
+    let period = cfg.interval;
+    Ok(Some(tokio::spawn(async move {
+        // ---
+        let mut interval = tokio::time::interval(period);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        let stopped = shutdown.triggered();
+        tokio::pin!(stopped);
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {
+                    if let Err(e) = gateway.push().await {
+                        tracing::warn!("Metrics push failed: {e:#}");
+                    }
+                }
+                _ = &mut stopped => break,
+            }
+        }
+        if let Err(e) = gateway.push().await {
+            tracing::error!("Final metrics push failed: {e:#}");
+        }
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+    use crate::create_noop_metrics;
+    use axum::{extract::State, http::HeaderMap, http::Uri, routing::put, Router};
+    use std::sync::{Arc, Mutex};
+
+    type Pushes = Arc<Mutex<Vec<(String, String)>>>;
+
+    /// A pushgateway stand-in recording each push as (path, content type).
+    async fn spawn_fake_gateway() -> (String, Pushes) {
+        // ---
+        async fn store(State(pushes): State<Pushes>, uri: Uri, headers: HeaderMap) {
+            // ---
+            let content_type = headers["content-type"].to_str().unwrap().to_string();
+            pushes
+                .lock()
+                .unwrap()
+                .push((uri.path().to_string(), content_type));
+        }
+
+        let pushes = Pushes::default();
+        let app = Router::new()
+            .route("/{*path}", put(store))
+            .with_state(pushes.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (url, pushes)
+    }
+
+    #[tokio::test]
+    async fn pushes_on_start_and_at_shutdown() {
+        // ---
+        let (url, pushes) = spawn_fake_gateway().await;
+        let cfg = PushGatewayConfig {
+            url: Some(url),
+            job: "batch".to_string(),
+            instance: Some("worker-1".to_string()),
+            interval: Duration::from_secs(3600),
+        };
+        let shutdown = ShutdownSignal::new();
+
+        let metrics = create_noop_metrics().unwrap();
+        let http = HttpClient::new(&Default::default(), metrics.clone()).unwrap();
+        let task = spawn_push_gateway(http, metrics, &cfg, shutdown.clone())
+            .unwrap()
+            .expect("pushgateway is configured");
+
+        // The first interval tick completes immediately
+        for _ in 0..100 {
+            if !pushes.lock().unwrap().is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        shutdown.trigger();
+        task.await.unwrap();
+
+        let pushes = pushes.lock().unwrap();
+        assert_eq!(pushes.len(), 2);
+        for (path, content_type) in pushes.iter() {
+            assert_eq!(path, "/metrics/job/batch/instance/worker-1");
+            assert_eq!(content_type, CONTENT_TYPE);
+        }
+    }
+
+    #[test]
+    fn disabled_without_url() {
+        // ---
+        let cfg = PushGatewayConfig::default();
+        let metrics = create_noop_metrics().unwrap();
+        let http = HttpClient::new(&Default::default(), metrics.clone()).unwrap();
+        let gateway = PushGateway::new(&cfg, http, metrics).unwrap();
+        assert!(gateway.is_none());
+    }
+}
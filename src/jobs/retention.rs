@@ -0,0 +1,350 @@
+//! Scheduled removal of data that is past its retention period.
+//!
+//! Each rule removes one kind of data once it is older than configured:
+//!
+//! | Rule                 | Removes                                  | Kept for                          |
+//! |:---------------------|:-----------------------------------------|:----------------------------------|
+//! | `soft_deleted_users` | Soft-deleted users and credentials       | `AXUM_SOFT_DELETE_RETENTION_DAYS` |
+//! | `audit_logs`         | Audit files exported to S3               | `AXUM_RETENTION_AUDIT_LOG_DAYS`   |
+//!
+//! Sessions, their revocation entries, and the other Redis data carry a TTL
+//! and expire on their own, so no rule covers them.
+//!
+//! With `AXUM_RETENTION_DRY_RUN` each rule only counts what it would remove.
+//! Counts are logged and recorded per rule either way, and
+//! `GET /admin/retention` shows when the next pass is due.
+
+use crate::config::RetentionConfig;
+use crate::domain::{MetricsPtr, RepositoryPtr};
+use crate::export::AuditExporter;
+use crate::reload::{Live, LiveConfigPtr};
+use crate::shutdown::ShutdownSignal;
+use chrono::{DateTime, Utc};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+/// A kind of data the retention job removes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RetentionRule {
+    // ---
+    /// Soft-deleted users and their credentials.
+    SoftDeletedUsers,
+
+    /// Audit log files exported to S3.
+    AuditLogs,
+}
+
+impl RetentionRule {
+    // ---
+
+    /// The name used in logs, metrics, and `GET /admin/retention`.
+    pub fn as_str(&self) -> &'static str {
+        // ---
+        match self {
+            Self::SoftDeletedUsers => "soft_deleted_users",
+            Self::AuditLogs => "audit_logs",
+        }
+    }
+}
+
+/// A rule as the next pass will apply it.
+#[derive(Debug, Clone)]
+pub(crate) struct RulePlan {
+    // ---
+    pub rule: RetentionRule,
+    pub max_age: Duration,
+
+    /// Data older than this is removed, if the pass started now.
+    pub cutoff: DateTime<Utc>,
+
+    /// Whether the rule has anything to act on; the audit log rule needs
+    /// the audit export to be configured.
+    pub enabled: bool,
+}
+
+/// What one rule did in a pass.
+#[derive(Debug, Clone)]
+pub(crate) struct RuleOutcome {
+    // ---
+    pub rule: RetentionRule,
+    pub cutoff: DateTime<Utc>,
+
+    /// Records removed, or found in a dry run. `None` if the rule was
+    /// skipped or failed.
+    pub purged: Option<u64>,
+    pub error: Option<String>,
+}
+
+/// One pass over all rules.
+#[derive(Debug, Clone)]
+pub(crate) struct RetentionRun {
+    // ---
+    pub started_at: DateTime<Utc>,
+    pub dry_run: bool,
+    pub outcomes: Vec<RuleOutcome>,
+}
+
+/// The schedule and rules of the retention job, for inspection.
+#[derive(Debug, Clone)]
+pub(crate) struct RetentionStatus {
+    // ---
+    pub dry_run: bool,
+    pub interval: Duration,
+    pub next_run: Option<DateTime<Utc>>,
+    pub rules: Vec<RulePlan>,
+    pub last_run: Option<RetentionRun>,
+}
+
+/// Applies the retention rules periodically. Cheap to clone.
+#[derive(Clone)]
+pub(crate) struct RetentionEngine {
+    // ---
+    inner: Option<Arc<Inner>>,
+}
+
+struct Inner {
+    // ---
+    repository: RepositoryPtr,
+    export: AuditExporter,
+    metrics: MetricsPtr,
+
+    /// Source of the soft-delete window, which can be reloaded.
+    live: LiveConfigPtr,
+    audit_log_max_age: Duration,
+    dry_run: bool,
+    period: Duration,
+    schedule: Mutex<Schedule>,
+}
+
+#[derive(Default)]
+struct Schedule {
+    // ---
+    next_run: Option<DateTime<Utc>>,
+    last_run: Option<RetentionRun>,
+}
+
+impl RetentionEngine {
+    // ---
+
+    /// Starts applying the rules every `cfg.interval` on the current Tokio
+    /// runtime, starting one interval after startup, until `shutdown` is
+    /// triggered. Returns a disabled engine if the job is disabled.
+    ///
+    /// # Panics
+    /// Panics if called outside a Tokio runtime.
+    pub(crate) fn spawn(
+        cfg: &RetentionConfig,
+        repository: RepositoryPtr,
+        export: AuditExporter,
+        metrics: MetricsPtr,
+        live: LiveConfigPtr,
+        shutdown: ShutdownSignal,
+    ) -> Self {
+        // ---
+        let Some(period) = cfg.interval else {
+            tracing::info!("Retention job disabled");
+            return Self::disabled();
+        };
+
+        let inner = Arc::new(Inner {
+            repository,
+            export,
+            metrics,
+            live,
+            audit_log_max_age: cfg.audit_log_max_age,
+            dry_run: cfg.dry_run,
+            period,
+            schedule: Mutex::new(Schedule::default()),
+        });
+        inner.schedule().next_run = Some(inner.due_after(Utc::now()));
+        tracing::info!(
+            "Retention job every {}s (dry_run={})",
+            period.as_secs(),
+            cfg.dry_run
+        );
+
+        let job = inner.clone();
+        tokio::spawn(async move {
+            // ---
+            let mut interval = tokio::time::interval(period);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            interval.tick().await; // first tick completes immediately
+            let stopped = shutdown.triggered();
+            tokio::pin!(stopped);
+
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => job.run().await,
+                    _ = &mut stopped => break,
+                }
+            }
+        });
+
+        Self { inner: Some(inner) }
+    }
+
+    /// An engine that never runs.
+    pub(crate) fn disabled() -> Self {
+        // ---
+        Self { inner: None }
+    }
+
+    /// The schedule, the rules as the next pass would apply them, and the
+    /// result of the last pass. Returns `None` if the job is disabled.
+    pub(crate) fn status(&self) -> Option<RetentionStatus> {
+        // ---
+        let inner = self.inner.as_ref()?;
+        let (next_run, last_run) = {
+            let schedule = inner.schedule();
+            (schedule.next_run, schedule.last_run.clone())
+        };
+        let at = next_run.unwrap_or_else(Utc::now);
+
+        Some(RetentionStatus {
+            dry_run: inner.dry_run,
+            interval: inner.period,
+            next_run,
+            rules: inner.plan(at),
+            last_run,
+        })
+    }
+}
+
+impl Inner {
+    // ---
+
+    fn schedule(&self) -> std::sync::MutexGuard<'_, Schedule> {
+        // ---
+        self.schedule.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn due_after(&self, at: DateTime<Utc>) -> DateTime<Utc> {
+        // ---
+        at + chrono::Duration::from_std(self.period).unwrap_or(chrono::Duration::MAX)
+    }
+
+    fn plan(&self, at: DateTime<Utc>) -> Vec<RulePlan> {
+        // ---
+        let soft_deleted = Live::load(&self.live, |live| &live.admin).soft_delete_retention;
+
+        vec![
+            RulePlan {
+                rule: RetentionRule::SoftDeletedUsers,
+                max_age: soft_deleted,
+                cutoff: cutoff(at, soft_deleted),
+                enabled: true,
+            },
+            RulePlan {
+                rule: RetentionRule::AuditLogs,
+                max_age: self.audit_log_max_age,
+                cutoff: cutoff(at, self.audit_log_max_age),
+                enabled: self.export.is_enabled(),
+            },
+        ]
+    }
+
+    /// Applies every enabled rule once. A failing rule is logged and does
+    /// not stop the others.
+    async fn run(&self) {
+        // ---
+        let started_at = Utc::now();
+        let mut outcomes = Vec::new();
+
+        for plan in self
+            .plan(started_at)
+            .into_iter()
+            .filter(|plan| plan.enabled)
+        {
+            let result = match plan.rule {
+                RetentionRule::SoftDeletedUsers => self.purge_soft_deleted(plan.cutoff).await,
+                RetentionRule::AuditLogs => self
+                    .export
+                    .expire_audit_logs(plan.cutoff, self.dry_run)
+                    .await
+                    .map(Option::unwrap_or_default),
+            };
+
+            let outcome = match result {
+                Ok(purged) => {
+                    let verb = if self.dry_run {
+                        "would remove"
+                    } else {
+                        "removed"
+                    };
+                    tracing::info!(
+                        "Retention rule {}: {verb} {purged} records older than {}",
+                        plan.rule.as_str(),
+                        plan.cutoff
+                    );
+                    self.metrics
+                        .record_retention_purged(plan.rule.as_str(), purged, self.dry_run);
+                    RuleOutcome {
+                        rule: plan.rule,
+                        cutoff: plan.cutoff,
+                        purged: Some(purged),
+                        error: None,
+                    }
+                }
+                Err(e) => {
+                    tracing::error!("Retention rule {} failed: {e:#}", plan.rule.as_str());
+                    RuleOutcome {
+                        rule: plan.rule,
+                        cutoff: plan.cutoff,
+                        purged: None,
+                        error: Some(e.to_string()),
+                    }
+                }
+            };
+            outcomes.push(outcome);
+        }
+
+        let mut schedule = self.schedule();
+        schedule.next_run = Some(self.due_after(started_at));
+        schedule.last_run = Some(RetentionRun {
+            started_at,
+            dry_run: self.dry_run,
+            outcomes,
+        });
+    }
+
+    /// Purges soft-deleted users, counting them; their credentials go with
+    /// them and are logged.
+    async fn purge_soft_deleted(&self, cutoff: DateTime<Utc>) -> anyhow::Result<u64> {
+        // ---
+        let summary = self.repository.purge_deleted(cutoff, self.dry_run).await?;
+        tracing::debug!(
+            "Retention purge of soft-deleted users included {} credentials",
+            summary.credentials
+        );
+        Ok(summary.users)
+    }
+}
+
+/// `max_age` before `at`, or the earliest representable time if that is
+/// out of range.
+fn cutoff(at: DateTime<Utc>, max_age: Duration) -> DateTime<Utc> {
+    // ---
+    chrono::Duration::from_std(max_age)
+        .ok()
+        .and_then(|age| at.checked_sub_signed(age))
+        .unwrap_or(DateTime::<Utc>::MIN_UTC)
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn cutoffs_saturate() {
+        // ---
+        let at = Utc.with_ymd_and_hms(2026, 10, 15, 0, 0, 0).unwrap();
+
+        assert_eq!(
+            cutoff(at, Duration::from_secs(14 * 86_400)),
+            Utc.with_ymd_and_hms(2026, 10, 1, 0, 0, 0).unwrap()
+        );
+        assert_eq!(cutoff(at, Duration::MAX), DateTime::<Utc>::MIN_UTC);
+    }
+}
@@ -0,0 +1,135 @@
+//! Periodic sampling of Tokio runtime metrics.
+//!
+//! A saturated runtime (every worker busy, tasks piling up in the queue)
+//! shows up as latency on every route at once. Sampling the runtime next to
+//! the HTTP metrics makes that cause visible.
+//!
+//! Blocking pool figures need Tokio's unstable metrics; they are included
+//! only when built with `RUSTFLAGS="--cfg tokio_unstable"`.
+
+use crate::domain::{MetricsPtr, RuntimeSample};
+use std::time::{Duration, Instant};
+use tokio::runtime::Handle;
+use tokio::task::JoinHandle;
+
+/// Takes [`RuntimeSample`]s from one runtime, remembering the previous
+/// sample so busy time can be reported as a ratio.
+pub(crate) struct RuntimeSampler {
+    // ---
+    handle: Handle,
+    last_busy: Duration,
+    last_at: Instant,
+}
+
+impl RuntimeSampler {
+    // ---
+
+    pub fn new(handle: Handle) -> Self {
+        // ---
+        let last_busy = total_busy(&handle);
+        Self {
+            handle,
+            last_busy,
+            last_at: Instant::now(),
+        }
+    }
+
+    pub fn sample(&mut self) -> RuntimeSample {
+        // ---
+        let metrics = self.handle.metrics();
+        let workers = metrics.num_workers();
+
+        let busy = total_busy(&self.handle);
+        let now = Instant::now();
+        let capacity = now.duration_since(self.last_at).as_secs_f64() * workers as f64;
+        let worker_busy_ratio = if capacity > 0.0 {
+            (busy.saturating_sub(self.last_busy).as_secs_f64() / capacity).min(1.0)
+        } else {
+            0.0
+        };
+        self.last_busy = busy;
+        self.last_at = now;
+
+        #[cfg(tokio_unstable)]
+        let (blocking_threads, idle_blocking_threads, blocking_queue_depth) = (
+            Some(metrics.num_blocking_threads()),
+            Some(metrics.num_idle_blocking_threads()),
+            Some(metrics.blocking_queue_depth()),
+        );
+        #[cfg(not(tokio_unstable))]
+        let (blocking_threads, idle_blocking_threads, blocking_queue_depth) = (None, None, None);
+
+        RuntimeSample {
+            workers,
+            alive_tasks: metrics.num_alive_tasks(),
+            global_queue_depth: metrics.global_queue_depth(),
+            worker_busy_ratio,
+            blocking_threads,
+            idle_blocking_threads,
+            blocking_queue_depth,
+        }
+    }
+}
+
+/// Time all workers have spent running tasks since the runtime started.
+fn total_busy(handle: &Handle) -> Duration {
+    // ---
+    let metrics = handle.metrics();
+    (0..metrics.num_workers())
+        .map(|worker| metrics.worker_total_busy_duration(worker))
+        .sum()
+}
+
+/// Spawns the sampler on the current Tokio runtime.
+///
+/// Records a sample every `period`. Returns `None` without spawning
+/// anything if `period` is `None`.
+pub fn spawn_runtime_metrics(
+    metrics: MetricsPtr,
+    period: Option<Duration>,
+) -> Option<JoinHandle<()>> {
+    // ---
+    let Some(period) = period else {
+        tracing::info!("Runtime metrics sampling disabled");
+        return None;
+    };
+
+    tracing::info!("Sampling runtime metrics every {}s", period.as_secs());
+
+    let mut sampler = RuntimeSampler::new(Handle::current());
+    Some(tokio::spawn(async move {
+        // ---
+        let mut interval = tokio::time::interval(period);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        loop {
+            interval.tick().await;
+            metrics.record_runtime_sample(&sampler.sample());
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn sample_reports_workers_and_tasks() {
+        // ---
+        let mut sampler = RuntimeSampler::new(Handle::current());
+        let (tx, rx) = tokio::sync::oneshot::channel::<()>();
+        let waiting = tokio::spawn(async move {
+            let _ = rx.await;
+        });
+
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        let sample = sampler.sample();
+        assert_eq!(sample.workers, 2);
+        assert!(sample.alive_tasks >= 1);
+        assert!((0.0..=1.0).contains(&sample.worker_busy_ratio));
+
+        tx.send(()).unwrap();
+        waiting.await.unwrap();
+    }
+}
@@ -2,106 +2,365 @@
 use anyhow::Result;
 use app_state::AppState;
 use axum::{
-    routing::{delete, get, post, put},
+    middleware::from_fn_with_state,
+    routing::{delete, get, post, put, MethodRouter},
     Router,
 };
 use handlers::{
+    account_usage,
     //
     add_movie,
+    admin_stats,
     auth_finish,
     auth_start,
+    create_webhook,
+    csrf_token,
     delete_credential,
+    delete_credential_limit,
     delete_movie,
+    delete_webhook,
+    demo_index,
+    demo_script,
+    event_stream,
+    export_audit_log,
+    get_credential_limit,
     get_movie,
+    get_webhook,
     health_check,
+    health_history,
+    introspect_session,
     list_credentials,
+    list_movies,
+    list_routes,
+    list_webhooks,
+    logout,
+    method_not_allowed,
     metrics_handler,
+    not_found,
+    purge_deleted,
+    reencrypt_credentials,
     register_finish,
     register_start,
+    retention_schedule,
     root_handler,
+    set_credential_limit,
     update_movie,
+    update_webhook,
+    ws_handler,
 };
-use redis::Client;
+use route_registry::{Route, ROUTES};
 use std::env;
 
 // Public exports (visible outside this module)
+#[cfg(feature = "client")]
+pub mod client;
 pub mod domain;
+#[cfg(feature = "fuzzing")]
+#[doc(hidden)]
+pub mod fuzzing;
+pub mod prelude;
+#[cfg(feature = "test-utils")]
+pub mod test_utils;
 
 // Internal-only exports (sibling access within this module)
+mod app_builder;
 mod app_state;
+mod application;
+#[cfg(feature = "chaos")]
+mod chaos;
+mod client_ip;
 mod config;
+mod deadline;
+mod events;
+mod export;
+#[cfg(feature = "grpc")]
+mod grpc;
 mod handlers;
+mod health_history;
 mod infrastructure;
+mod jobs;
+mod listener;
+mod middleware;
+mod redact;
+mod redis_keys;
+mod redis_retry;
+mod redis_source;
+mod reload;
+mod route_registry;
+mod serverless;
+mod service_identity;
 mod session;
+mod shutdown;
+mod startup;
+mod tenant;
+#[cfg(feature = "tls")]
+mod tls;
+mod trace_context;
+mod webhooks;
 
 // Hoist up only the public symbol(s)
-pub use session::{create_session, validate_session, SessionInfo};
+pub use session::{create_session, validate_session, SessionInfo, SESSION_COOKIE};
 
+pub use app_builder::{AppBuilder, AppRouters};
+#[cfg(feature = "chaos")]
+pub use chaos::{Chaos, Fault};
 pub use config::*;
+pub use events::{EventBus, ServerEvent, ServerEventKind};
+pub use jobs::{
+    cleanup_orphaned_users, spawn_orphan_cleanup, spawn_push_gateway, spawn_runtime_metrics,
+};
+pub use listener::{ServerConnection, ServerListener};
+pub use middleware::CSRF_HEADER;
+pub use redis_keys::redis_key;
+pub use redis_source::verify_redis;
+pub use reload::ConfigReloader;
+pub use route_registry::{route_table, RouteAuth, RouteInfo, RouteTable, GLOBAL_LAYERS};
+#[cfg(feature = "lambda")]
+pub use serverless::run_lambda;
+pub use serverless::AppService;
+pub use service_identity::ServiceIdentity;
+pub use shutdown::ShutdownSignal;
+pub use startup::{log_startup_banner, wait_for_dependencies, warm_up};
+#[cfg(feature = "tls")]
+pub use tls::{
+    tls_server_config, ConnectionService, LiveTls, MakeServiceWithIdentity, TlsConnection,
+    TlsListener,
+};
+pub use trace_context::TraceContext;
+pub use webhooks::{WebhookDispatcher, WebhookEvent, WebhookEventData, WebhookEventKind};
 
 // Publicly expose the infrastructure creation functions
+#[cfg(feature = "sqlite")]
+pub use infrastructure::SqliteRepository;
 pub use infrastructure::{
     create_noop_metrics, // ---
     create_postgres_repository,
     create_prom_metrics,
+    create_repository,
     create_webauthn,
+    EncryptedRepository,
+    HttpClient,
+    LocalKeyProvider,
+    PostgresRepository,
 };
 
+/// Path prefix for version 1 of the HTTP API.
+///
+/// All API routes are served under this prefix. The same routes are also
+/// reachable at their legacy unversioned paths, which respond with
+/// deprecation headers pointing here.
+pub const API_V1_PREFIX: &str = "/api/v1";
+
 /// Build the HTTP router with metrics implementation determined by environment variables.
+///
+/// This is the env-driven convenience wrapper around [`AppBuilder`]. It uses
+/// the repository created by `init_database_with_retry_from_env()`, which
+/// must have been awaited first. Use the builder directly to inject
+/// dependencies when embedding the app.
 pub fn create_router() -> Result<Router> {
     // ---
     // Load all configuration from environment
     let config = AppConfig::from_env()?;
 
-    // Determine metrics implementation from environment
-    let metrics_type = env::var("AXUM_METRICS_TYPE").unwrap_or_else(|_| "noop".to_string());
-    let metrics = if metrics_type == "prom" {
-        create_prom_metrics()?
-    } else {
-        create_noop_metrics()?
+    tracing_subscriber::fmt::try_init().ok(); // ✅ Ignores if already initialized
+
+    AppBuilder::new()
+        .config(config)
+        .metrics(create_metrics_from_env()?)
+        .repository(infrastructure::default_repository()?)
+        .build()
+}
+
+/// Create the metrics backend selected by `AXUM_METRICS_TYPE`.
+///
+/// `prom` selects Prometheus; anything else (including unset) selects no-op.
+pub fn create_metrics_from_env() -> Result<domain::MetricsPtr> {
+    // ---
+    match metrics_type_from_env() {
+        "prom" => create_prom_metrics(),
+        _ => create_noop_metrics(),
+    }
+}
+
+/// The metrics backend selected by `AXUM_METRICS_TYPE`: `"prom"`, or
+/// `"noop"` for anything else.
+pub fn metrics_type_from_env() -> &'static str {
+    // ---
+    match env::var("AXUM_METRICS_TYPE").as_deref() {
+        Ok("prom") => "prom",
+        _ => "noop",
+    }
+}
+
+/// Attaches all routes to the given application state.
+///
+/// The routes are those of the registry in [`route_registry`], each behind
+/// the route-level middleware it lists. With `separate_admin`, the operator
+/// routes are left out of the first router and returned in a second one,
+/// to be served on a port of its own. Both are nested under the state's
+/// base path, if it has one.
+fn build_routes(app_state: AppState, separate_admin: bool) -> (Router, Option<Router>) {
+    // ---
+    let api = registered_routes(&app_state, |route| route.versioned && !route.operator);
+    let operator = registered_routes(&app_state, |route| route.versioned && route.operator);
+    let (api, admin) = match separate_admin {
+        true => (api, Some(operator)),
+        false => (api.merge(operator), None),
     };
 
-    tracing_subscriber::fmt::try_init().ok(); // ✅ Ignores if already initialized
+    let app = registered_routes(&app_state, |route| !route.versioned);
+    let app = with_fallbacks(app.merge(versioned(api, &app_state)), &app_state);
+    let admin = admin.map(|operator| with_fallbacks(versioned(operator, &app_state), &app_state));
+    (app, admin)
+}
 
-    // Create infrastructure dependencies
-    let redis_client = Client::open(config.redis.url.clone())?;
-    let repository = create_postgres_repository()?;
-    let webauthn = std::sync::Arc::new(create_webauthn(&config.webauthn)?);
-
-    // Build application state with all dependencies
-    let app_state = AppState::new(
-        redis_client,
-        metrics,
-        repository,
-        webauthn,
-        config.redis.webauthn_challenge_ttl,
-    );
-
-    // Build router (Phase 2 WebAuthn routes will be added next)
+/// `routes` nested under [`API_V1_PREFIX`], and at their legacy paths.
+fn versioned(routes: Router<AppState>, app_state: &AppState) -> Router<AppState> {
+    // ---
+    // Each API version is nested under its own prefix so a future `/api/v2`
+    // can be added alongside v1 without disturbing it. Legacy unversioned
+    // paths alias v1 and are marked deprecated.
     //
-    let router = Router::new()
-        .route("/", get(root_handler))
-        .route("/health", get(health_check))
-        .route("/metrics", get(metrics_handler))
-        .nest(
-            "/movies",
-            Router::new()
-                .route("/get/{id}", get(get_movie))
-                .route("/add", post(add_movie))
-                .route("/update/{id}", put(update_movie))
-                .route("/delete/{id}", delete(delete_movie)),
-        )
-        .nest(
-            "/webauthn",
-            Router::new()
-                .route("/register/start", post(register_start))
-                .route("/register/finish", post(register_finish))
-                .route("/auth/start", post(auth_start))
-                .route("/auth/finish", post(auth_finish))
-                .route("/credentials", get(list_credentials))
-                .route("/credentials/{id}", delete(delete_credential)),
-        )
-        .with_state(app_state);
-
-    Ok(router)
+    let legacy = routes.clone().layer(from_fn_with_state(
+        app_state.clone(),
+        middleware::deprecated_alias,
+    ));
+    Router::new().nest(API_V1_PREFIX, routes).merge(legacy)
+}
+
+/// Finishes a route table: nested under the base path, with JSON errors
+/// for what it does not route, CSRF protection, and the state.
+fn with_fallbacks(routes: Router<AppState>, app_state: &AppState) -> Router {
+    // ---
+    let routes = match app_state.base_path() {
+        "" => routes,
+        base_path => Router::new().nest(base_path, routes),
+    };
+    routes
+        // JSON error bodies for unknown paths and unsupported methods; the
+        // latter applies to the routes registered above, so it comes last
+        .fallback(not_found)
+        .method_not_allowed_fallback(method_not_allowed)
+        .layer(from_fn_with_state(
+            app_state.clone(),
+            middleware::csrf_protect,
+        ))
+        .with_state(app_state.clone())
+}
+
+/// The registered routes `include` selects, at their registry paths
+/// (relative to [`API_V1_PREFIX`] for versioned routes), each behind its
+/// route-level middleware.
+///
+/// `app_state` is only used by route-level middleware; the caller attaches
+/// the state to the finished router.
+fn registered_routes(app_state: &AppState, include: impl Fn(&Route) -> bool) -> Router<AppState> {
+    // ---
+    ROUTES
+        .iter()
+        .filter(|route| include(route))
+        .fold(Router::new(), |router, route| {
+            // Innermost first, so the first listed layer runs first
+            let endpoint = route
+                .layers
+                .iter()
+                .rev()
+                .fold(endpoint(route), |endpoint, layer| {
+                    route_layer(endpoint, layer, app_state)
+                });
+            router.route(route.path, endpoint)
+        })
+}
+
+/// The handler serving a registered route.
+///
+/// # Panics
+/// If `route` has no handler here, so a registry entry cannot go unrouted.
+fn endpoint(route: &Route) -> MethodRouter<AppState> {
+    // ---
+    match (route.method, route.path) {
+        // Pages
+        ("GET", "/") => get(root_handler),
+        ("GET", "/app" | "/app/") => get(demo_index),
+        ("GET", "/app/webauthn.js") => get(demo_script),
+
+        // Core
+        ("GET", "/health") => get(health_check),
+        ("GET", "/health/history") => get(health_history),
+        ("GET", "/metrics") => get(metrics_handler),
+        ("GET", "/csrf") => get(csrf_token),
+        ("GET", "/account/usage") => get(account_usage),
+        ("GET", "/events") => get(event_stream),
+        ("GET", "/ws") => get(ws_handler),
+
+        // Movies
+        ("GET", "/movies/get/{id}") => get(get_movie),
+        ("GET", "/movies/list") => get(list_movies),
+        ("POST", "/movies/add") => post(add_movie),
+        ("PUT", "/movies/update/{id}") => put(update_movie),
+        ("DELETE", "/movies/delete/{id}") => delete(delete_movie),
+
+        // WebAuthn
+        ("POST", "/webauthn/register/start") => post(register_start),
+        ("POST", "/webauthn/register/finish") => post(register_finish),
+        ("POST", "/webauthn/auth/start") => post(auth_start),
+        ("POST", "/webauthn/auth/finish") => post(auth_finish),
+        ("POST", "/webauthn/logout") => post(logout),
+        ("GET", "/webauthn/credentials") => get(list_credentials),
+        ("DELETE", "/webauthn/credentials/{id}") => delete(delete_credential),
+        #[cfg(feature = "password")]
+        ("POST", "/auth/password/register") => post(handlers::password_register),
+        #[cfg(feature = "password")]
+        ("POST", "/auth/password/login") => post(handlers::password_login),
+
+        // Service-to-service
+        ("POST", "/auth/introspect") => post(introspect_session),
+
+        // Admin
+        ("POST", "/admin/purge") => post(purge_deleted),
+        ("POST", "/admin/credentials/reencrypt") => post(reencrypt_credentials),
+        ("POST", "/admin/export") => post(export_audit_log),
+        ("GET", "/admin/retention") => get(retention_schedule),
+        ("GET", "/admin/routes") => get(list_routes),
+        ("GET", "/admin/stats") => get(admin_stats),
+        ("GET", "/admin/users/{username}/credential-limit") => get(get_credential_limit),
+        ("PUT", "/admin/users/{username}/credential-limit") => put(set_credential_limit),
+        ("DELETE", "/admin/users/{username}/credential-limit") => delete(delete_credential_limit),
+        ("GET", "/admin/webhooks") => get(list_webhooks),
+        ("POST", "/admin/webhooks") => post(create_webhook),
+        ("GET", "/admin/webhooks/{id}") => get(get_webhook),
+        ("PUT", "/admin/webhooks/{id}") => put(update_webhook),
+        ("DELETE", "/admin/webhooks/{id}") => delete(delete_webhook),
+        #[cfg(feature = "pprof")]
+        ("GET", "/debug/pprof/profile") => get(handlers::pprof_profile),
+
+        (method, path) => panic!("No handler for the registered route {method} {path}"),
+    }
+}
+
+/// `endpoint` behind the route-level middleware named `layer` in the
+/// registry.
+///
+/// # Panics
+/// If `layer` is not a route-level middleware.
+fn route_layer(
+    endpoint: MethodRouter<AppState>,
+    layer: &str,
+    app_state: &AppState,
+) -> MethodRouter<AppState> {
+    // ---
+    let state = app_state.clone();
+    match layer {
+        // Counts authenticated requests against the caller's quotas
+        "enforce_quota" => {
+            endpoint.route_layer(from_fn_with_state(state, middleware::enforce_quota))
+        }
+        "admin_ip_filter" => {
+            endpoint.route_layer(from_fn_with_state(state, middleware::admin_ip_filter))
+        }
+        "require_client_cert" => {
+            endpoint.route_layer(from_fn_with_state(state, middleware::require_client_cert))
+        }
+        layer => panic!("Unknown route-level middleware {layer}"),
+    }
 }
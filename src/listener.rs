@@ -0,0 +1,223 @@
+//! TCP listener tuned by [`ServerConfig`].
+//!
+//! `axum::serve` accepts HTTP/1.1 and, on the same port, HTTP/2 over
+//! cleartext (prior knowledge, as gRPC-style clients use). This listener
+//! adds what `TcpListener::bind` does not expose: the listen backlog, a cap
+//! on open connections, and per-connection `TCP_NODELAY` and keep-alive.
+//!
+//! Socket options are applied with [`ListenerExt::tap_io`], which also lets
+//! the router extract `ConnectInfo<SocketAddr>`:
+//!
+//! ```no_run
+//! # async fn run(router: axum::Router) -> anyhow::Result<()> {
+//! use axum::serve::ListenerExt;
+//! use axum_quickstart::{ServerConfig, ServerListener};
+//! use std::net::SocketAddr;
+//!
+//! let server = ServerConfig::from_env()?;
+//! let listener = ServerListener::bind("127.0.0.1:8080", &server)
+//!     .await?
+//!     .tap_io(move |conn| conn.configure(&server));
+//!
+//! axum::serve(listener, router.into_make_service_with_connect_info::<SocketAddr>()).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`ListenerExt::tap_io`]: axum::serve::ListenerExt::tap_io
+
+use crate::config::ServerConfig;
+use axum::serve::Listener;
+use socket2::{SockRef, TcpKeepalive};
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::{TcpListener, TcpSocket, TcpStream};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Accepts connections up to `max_connections` at a time.
+#[derive(Debug)]
+pub struct ServerListener {
+    // ---
+    listener: TcpListener,
+    connections: Option<Arc<Semaphore>>,
+}
+
+impl ServerListener {
+    // ---
+
+    /// Binds to `addr` (`host:port`) with the configured backlog.
+    ///
+    /// # Errors
+    /// Returns an error if the address does not resolve or cannot be bound.
+    pub async fn bind(addr: &str, config: &ServerConfig) -> io::Result<Self> {
+        // ---
+        let addr = tokio::net::lookup_host(addr).await?.next().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("no address for {addr}"),
+            )
+        })?;
+
+        let socket = match addr {
+            SocketAddr::V4(_) => TcpSocket::new_v4()?,
+            SocketAddr::V6(_) => TcpSocket::new_v6()?,
+        };
+        // Same as `TcpListener::bind`, so restarts can rebind immediately
+        #[cfg(unix)]
+        socket.set_reuseaddr(true)?;
+        socket.bind(addr)?;
+
+        Ok(Self {
+            listener: socket.listen(config.listen_backlog)?,
+            connections: config
+                .max_connections
+                .map(|max| Arc::new(Semaphore::new(max))),
+        })
+    }
+}
+
+impl Listener for ServerListener {
+    // ---
+    type Io = ServerConnection;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        // ---
+        // Wait for a free slot first, so excess clients queue in the backlog
+        let permit = match &self.connections {
+            Some(connections) => Some(
+                connections
+                    .clone()
+                    .acquire_owned()
+                    .await
+                    .expect("connection semaphore is never closed"),
+            ),
+            None => None,
+        };
+
+        // Retries and logs accept errors
+        let (stream, addr) = Listener::accept(&mut self.listener).await;
+        let conn = ServerConnection {
+            stream,
+            _permit: permit,
+        };
+        (conn, addr)
+    }
+
+    fn local_addr(&self) -> io::Result<Self::Addr> {
+        // ---
+        self.listener.local_addr()
+    }
+}
+
+/// An accepted connection, holding its slot until dropped.
+#[derive(Debug)]
+pub struct ServerConnection {
+    // ---
+    stream: TcpStream,
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+impl ServerConnection {
+    // ---
+
+    /// Applies the configured `TCP_NODELAY` and keep-alive settings.
+    /// Failures are logged; the connection is still served.
+    pub fn configure(&self, config: &ServerConfig) {
+        // ---
+        if let Err(e) = self.stream.set_nodelay(config.tcp_nodelay) {
+            tracing::debug!("Failed to set TCP_NODELAY: {e}");
+        }
+
+        if let Some(idle) = config.tcp_keepalive {
+            let keepalive = TcpKeepalive::new().with_time(idle);
+            if let Err(e) = SockRef::from(&self.stream).set_tcp_keepalive(&keepalive) {
+                tracing::debug!("Failed to enable TCP keep-alive: {e}");
+            }
+        }
+    }
+}
+
+impl AsyncRead for ServerConnection {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        // ---
+        Pin::new(&mut self.stream).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for ServerConnection {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        // ---
+        Pin::new(&mut self.stream).poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        // ---
+        Pin::new(&mut self.stream).poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        // ---
+        self.stream.is_write_vectored()
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        // ---
+        Pin::new(&mut self.stream).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        // ---
+        Pin::new(&mut self.stream).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn connections_beyond_the_limit_wait_for_a_slot() {
+        // ---
+        let config = ServerConfig {
+            max_connections: Some(1),
+            ..ServerConfig::default()
+        };
+        let mut listener = ServerListener::bind("127.0.0.1:0", &config).await.unwrap();
+        let addr = Listener::local_addr(&listener).unwrap();
+
+        let _first_client = TcpStream::connect(addr).await.unwrap();
+        let (first, _) = listener.accept().await;
+        first.configure(&config);
+        assert!(first.stream.nodelay().unwrap());
+
+        let _second_client = TcpStream::connect(addr).await.unwrap();
+        let waiting = tokio::time::timeout(Duration::from_millis(50), listener.accept()).await;
+        assert!(
+            waiting.is_err(),
+            "second connection accepted over the limit"
+        );
+
+        drop(first);
+        let second = tokio::time::timeout(Duration::from_secs(1), listener.accept()).await;
+        assert!(second.is_ok());
+    }
+}
@@ -1,46 +1,159 @@
 use anyhow::Result;
-use axum_quickstart::create_router;
+use axum::serve::ListenerExt;
+use axum::Router;
+use axum_quickstart::{
+    create_metrics_from_env, create_repository, log_startup_banner, metrics_type_from_env,
+    route_table, spawn_orphan_cleanup, spawn_push_gateway, spawn_runtime_metrics,
+    wait_for_dependencies, warm_up, AppBuilder, AppConfig, ConfigReloader, HttpClient,
+    ServerConfig, ServerListener, ShutdownSignal,
+};
+#[cfg(feature = "tls")]
+use axum_quickstart::{LiveTls, MakeServiceWithIdentity, TlsListener};
 use futures::FutureExt;
 use std::env;
-use tracing::Level;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tracing_subscriber::filter::LevelFilter;
 use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::{reload, Registry};
 
-use axum_quickstart::domain::init_database_with_retry_from_env;
+/// Changes the log level of the running subscriber.
+type LogLevelHandle = reload::Handle<LevelFilter, Registry>;
 
-// Initialize tracing subscriber
-fn init_tracing() {
+// Determine log level from env, default to DEBUG
+fn log_level() -> LevelFilter {
+    match env::var("AXUM_LOG_LEVEL").ok().as_deref() {
+        Some("trace") => LevelFilter::TRACE,
+        Some("debug") => LevelFilter::DEBUG,
+        Some("info") => LevelFilter::INFO,
+        Some("warn") => LevelFilter::WARN,
+        Some("error") => LevelFilter::ERROR,
+        _ => LevelFilter::DEBUG, // Default
+    }
+}
+
+// Initialize tracing subscriber; the level can be changed later on SIGHUP
+fn init_tracing() -> LogLevelHandle {
     let span_events = match env::var("AXUM_SPAN_EVENTS").as_deref() {
         Ok("full") => FmtSpan::FULL, // ENTER, EXIT, CLOSE with timing
         Ok("enter_exit") => FmtSpan::ENTER | FmtSpan::EXIT, // Only ENTER and EXIT
         _ => FmtSpan::CLOSE,         // Default: only CLOSE timing
     };
 
-    // Determine log level from env, default to DEBUG
-    let level = match env::var("AXUM_LOG_LEVEL").ok().as_deref() {
-        Some("trace") => Level::TRACE,
-        Some("debug") => Level::DEBUG,
-        Some("info") => Level::INFO,
-        Some("warn") => Level::WARN,
-        Some("error") => Level::ERROR,
-        _ => Level::DEBUG, // Default
-    };
+    let (level, handle) = reload::Layer::new(log_level());
 
-    tracing_subscriber::fmt()
-        .with_target(true)
-        .with_file(true)
-        .with_line_number(true)
-        .with_span_events(span_events)
-        .with_max_level(level)
-        .compact()
+    tracing_subscriber::registry()
+        .with(level)
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_target(true)
+                .with_file(true)
+                .with_line_number(true)
+                .with_span_events(span_events)
+                .compact(),
+        )
         .init();
+
+    handle
+}
+
+const USAGE: &str = "usage: axum-quickstart [--wait-timeout <SECS>]
+       axum-quickstart print-routes [--json]";
+
+/// What the command line asks for.
+enum Command {
+    /// Run the server. `--wait-timeout <SECS>` (or `--wait-timeout=<SECS>`)
+    /// limits waiting for dependencies at startup, overriding
+    /// `AXUM_STARTUP_WAIT_TIMEOUT_SEC`; `Some(None)` (from `0`) removes the
+    /// limit.
+    Serve {
+        wait_timeout: Option<Option<Duration>>,
+    },
+
+    /// List the registered routes, as a table or, with `--json`, as
+    /// `GET /admin/routes` returns them.
+    PrintRoutes { json: bool },
+}
+
+fn parse_args() -> Result<Command> {
+    // ---
+    let args: Vec<String> = env::args().skip(1).collect();
+    let value = match args.as_slice() {
+        [] => return Ok(Command::Serve { wait_timeout: None }),
+        [command] if command == "print-routes" => return Ok(Command::PrintRoutes { json: false }),
+        [command, flag] if command == "print-routes" && flag == "--json" => {
+            return Ok(Command::PrintRoutes { json: true })
+        }
+        [flag, value] if flag == "--wait-timeout" => value.as_str(),
+        [arg] => match arg.strip_prefix("--wait-timeout=") {
+            Some(value) => value,
+            None => anyhow::bail!(USAGE),
+        },
+        _ => anyhow::bail!(USAGE),
+    };
+    let secs: u64 = value
+        .parse()
+        .map_err(|_| anyhow::anyhow!("--wait-timeout takes whole seconds, got {value:?}"))?;
+    Ok(Command::Serve {
+        wait_timeout: Some((secs > 0).then(|| Duration::from_secs(secs))),
+    })
+}
+
+/// Prints the route registry below `AXUM_BASE_PATH` (read from `.env` too),
+/// without starting anything.
+fn print_routes(json: bool) -> Result<()> {
+    // ---
+    let _ = dotenvy::dotenv();
+    let table = route_table(&ServerConfig::from_env()?.base_path);
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&table)?);
+        return Ok(());
+    }
+
+    let width = table
+        .routes
+        .iter()
+        .map(|route| route.path.len())
+        .max()
+        .unwrap_or(0);
+    println!(
+        "{:<7} {:<width$} {:<18} {:<8} {:<52} SUMMARY",
+        "METHOD", "PATH", "AUTH", "LISTENER", "LAYERS"
+    );
+    for route in &table.routes {
+        let listener = match route.operator {
+            true => "admin",
+            false => "app",
+        };
+        println!(
+            "{:<7} {:<width$} {:<18} {:<8} {:<52} {}",
+            route.method,
+            route.path,
+            route.auth.as_str(),
+            listener,
+            route.layers.join(","),
+            route.summary
+        );
+    }
+    println!();
+    println!("All routes: {}", table.global_layers.join(" > "));
+    println!("Versioned routes are also served at their legacy path (see --json).");
+    Ok(())
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     // ---
+    let wait_timeout = match parse_args()? {
+        Command::Serve { wait_timeout } => wait_timeout,
+        Command::PrintRoutes { json } => return print_routes(json),
+    };
 
     // Initialize tracing subscriber to log to stdout
-    init_tracing();
+    let log_level_handle = init_tracing();
 
     // Load .env file if present (development convenience)
     match dotenvy::dotenv() {
@@ -51,25 +164,232 @@ async fn main() -> Result<()> {
         Err(e) => tracing::warn!("Failed to parse .env file: {e}"),
     }
 
-    init_database_with_retry_from_env().await?;
-
-    // Create router with metrics determined by environment variables
-    let router = create_router()?;
+    // Load configuration; --wait-timeout overrides the configured limit
+    let mut config = AppConfig::from_env()?;
+    if let Some(wait_timeout) = wait_timeout {
+        config.startup.wait_timeout = wait_timeout;
+    }
 
     // Get optional bind endpoint from environment
     let endpoint = env::var("API_BIND_ADDR").unwrap_or_else(|_| "127.0.0.1:8080".to_string());
 
+    // Everything support needs to know about this deployment, in one event
+    log_startup_banner(&config, metrics_type_from_env(), &endpoint);
+
+    // Fail now on unreadable certificates, before anything else starts
+    #[cfg(feature = "tls")]
+    let tls = match config.tls.is_enabled() {
+        true => Some(LiveTls::load(&config.tls)?),
+        false => None,
+    };
+    #[cfg(not(feature = "tls"))]
+    if config.tls.is_enabled() {
+        anyhow::bail!("AXUM_TLS_CERT_FILE requires building with the tls feature");
+    }
+
+    // Wait for the database and Redis as configured; a wrong Redis
+    // address, certificate, or password fails here too
+    wait_for_dependencies(&config).await?;
+
+    // The repository owns its pool and is handed to the router explicitly
+    let repository = create_repository(&config.database).await?;
+
+    // Optionally open the pool's connections and prepare the hot queries
+    // now, rather than during the first requests (AXUM_WARMUP)
+    warm_up(&config, &repository).await;
+    let metrics = create_metrics_from_env()?;
+
+    // Background removal of users who never finished registration
+    spawn_orphan_cleanup(repository.clone(), metrics.clone(), config.cleanup.clone());
+
+    // Worker load and queue depths alongside the HTTP metrics
+    spawn_runtime_metrics(metrics.clone(), config.server.runtime_metrics_interval);
+
+    // One outbound client, so webhooks, exports, and pushes share its
+    // connections, timeouts, and proxy
+    let http = HttpClient::new(&config.http, metrics.clone())?;
+
+    // Triggered once the OS signal arrives so WebSocket clients get a close frame
+    let shutdown = ShutdownSignal::new();
+
+    // Metrics for a pushgateway when this process cannot be scraped, with a
+    // last push at shutdown
+    spawn_push_gateway(
+        http.clone(),
+        metrics.clone(),
+        &config.push_gateway,
+        shutdown.clone(),
+    )?;
+
+    // Listener tuning; the rest of the config moves into the builder
+    let server = config.server.clone();
+
+    // Reloadable settings, and the certificates, are re-read on SIGHUP
+    let reloader = ConfigReloader::new(&config);
+    #[cfg(feature = "tls")]
+    let reloader = match &tls {
+        Some(tls) => reloader.with_tls(tls.clone()),
+        None => reloader,
+    };
+    tokio::spawn(reload_on_sighup(reloader.clone(), log_level_handle));
+
+    // Health, metrics, and admin routes, if given a port of their own,
+    // leave the public one
+    let admin_endpoint = env::var("AXUM_ADMIN_BIND_ADDR").ok();
+
+    // Create router with metrics determined by environment variables
+    let routers = AppBuilder::new()
+        .config(config)
+        .repository(repository)
+        .metrics(metrics)
+        .http_client(http)
+        .shutdown(shutdown.clone())
+        .reloader(reloader)
+        .separate_admin(admin_endpoint.is_some())
+        .build_routers()?;
+
+    // gRPC, if enabled, listens on a port of its own
+    #[cfg(feature = "grpc")]
+    if let Ok(grpc_endpoint) = env::var("AXUM_GRPC_BIND_ADDR") {
+        let listener = tokio::net::TcpListener::bind(&grpc_endpoint).await?;
+        tracing::info!("Starting gRPC server on endpoint:{grpc_endpoint}");
+
+        let (grpc, stopped) = (routers.grpc, shutdown.triggered());
+        tokio::spawn(async move {
+            let serve = axum::serve(listener, grpc).with_graceful_shutdown(stopped);
+            if let Err(e) = serve.await {
+                tracing::error!("gRPC server failed: {e}");
+            }
+        });
+    }
+
+    // Bound before the public port, so a taken admin port fails startup
+    if let (Some(admin_endpoint), Some(admin)) = (admin_endpoint, routers.admin) {
+        let listener = ServerListener::bind(&admin_endpoint, &server).await?;
+        tracing::info!("Starting admin server on endpoint:{admin_endpoint}");
+
+        let served = serve(
+            listener,
+            server.clone(),
+            admin,
+            #[cfg(feature = "tls")]
+            tls.clone(),
+            shutdown.triggered(),
+        );
+        tokio::spawn(async move {
+            if let Err(e) = served.await {
+                tracing::error!("Admin server failed: {e}");
+            }
+        });
+    }
+
     let version = env!("CARGO_PKG_VERSION");
     tracing::info!("Starting axum server {version} on endpoint:{}", endpoint);
 
-    let listener = tokio::net::TcpListener::bind(&endpoint).await?;
-    axum::serve(listener, router)
-        .with_graceful_shutdown(shutdown_signal())
-        .await?;
+    // Backlog and connection limit at bind, socket options per connection
+    let listener = ServerListener::bind(&endpoint, &server).await?;
+    let trigger = shutdown.clone();
+    let stopped = async move {
+        shutdown_signal().await;
+        trigger.trigger();
+    };
+    #[cfg(feature = "tls")]
+    if tls.is_some() {
+        tracing::info!("Serving HTTPS");
+    }
+    serve(
+        listener,
+        server,
+        routers.app,
+        #[cfg(feature = "tls")]
+        tls,
+        stopped,
+    )
+    .await?;
+
+    // Upgraded connections are not tracked by axum; give them, the final
+    // audit export, and the last metrics push time to finish
+    if !shutdown.drain(Duration::from_secs(5)).await {
+        tracing::warn!(
+            "Timed out waiting for WebSocket connections, the audit export, and the metrics push"
+        );
+    }
 
     Ok(())
 }
 
+/// Serves `router` on `listener`, over TLS when configured, until `stopped`
+/// completes. Peer addresses feed the admin IP allow/deny lists, and client
+/// certificates the service identity.
+async fn serve(
+    listener: ServerListener,
+    server: ServerConfig,
+    router: Router,
+    #[cfg(feature = "tls")] tls: Option<LiveTls>,
+    stopped: impl Future<Output = ()> + Send + 'static,
+) -> std::io::Result<()> {
+    // ---
+    let listener = listener.tap_io(move |conn| conn.configure(&server));
+
+    #[cfg(feature = "tls")]
+    if let Some(tls) = tls {
+        return axum::serve(
+            TlsListener::new(listener, tls)?,
+            MakeServiceWithIdentity::new(router),
+        )
+        .with_graceful_shutdown(stopped)
+        .await;
+    }
+    axum::serve(
+        listener,
+        router.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(stopped)
+    .await
+}
+
+/// Re-reads `.env` (overriding variables set from it before) and applies
+/// the log level and reloadable settings each time SIGHUP arrives. An
+/// invalid configuration is logged and the current one kept.
+async fn reload_on_sighup(reloader: ConfigReloader, log_level_handle: LogLevelHandle) {
+    // ---
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangup = match signal(SignalKind::hangup()) {
+        Ok(hangup) => hangup,
+        Err(e) => {
+            tracing::warn!("Failed to install SIGHUP handler; config reload disabled: {e}");
+            return;
+        }
+    };
+
+    while hangup.recv().await.is_some() {
+        tracing::info!("Caught SIGHUP. Reloading configuration...");
+
+        match dotenvy::dotenv_override() {
+            Ok(_) => {}
+            Err(e) if e.not_found() => {}
+            Err(e) => tracing::warn!("Failed to parse .env file: {e}"),
+        }
+
+        let level = log_level();
+        let previous = log_level_handle.clone_current();
+        if previous != Some(level) {
+            match log_level_handle.modify(|filter| *filter = level) {
+                Ok(()) => tracing::info!("Config reload: log level -> {level}"),
+                Err(e) => tracing::warn!("Failed to change log level: {e}"),
+            }
+        }
+
+        match AppConfig::from_env() {
+            Ok(config) => {
+                reloader.reload(config);
+            }
+            Err(e) => tracing::error!("Config reload failed; keeping current settings: {e:#}"),
+        }
+    }
+}
+
 fn shutdown_signal() -> impl std::future::Future<Output = ()> {
     // ---
 
@@ -0,0 +1,221 @@
+//! Access log.
+//!
+//! Every request produces one `access_log` event at info level with the
+//! method, matched route, path, status, duration, response size, and the
+//! authenticated user, if the handler validated a session. Sensitive query
+//! parameters (session tokens passed as `?token=`, for example) are
+//! redacted.
+//!
+//! Requests are sampled per route (see [`AccessLogConfig`]) so that health
+//! checks and metrics scrapes can be thinned out; server errors are always
+//! logged.
+
+use crate::config::AccessLogConfig;
+use crate::redact;
+use axum::{
+    body::HttpBody,
+    extract::{MatchedPath, Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::Response,
+};
+use rand::Rng;
+use std::cell::Cell;
+use std::sync::Arc;
+use std::time::Instant;
+use uuid::Uuid;
+
+tokio::task_local! {
+    static USER: Cell<Option<Uuid>>;
+}
+
+/// Query parameters whose values are never logged. Matched as substrings
+/// of the lowercased parameter name.
+const SENSITIVE_PARAMS: &[&str] = &["token", "secret", "password", "key", "session", "code"];
+
+/// Notes the user whose session authenticated the current request, for its
+/// access log event. Does nothing outside a logged request.
+pub(crate) fn record_access_user(user_id: Uuid) {
+    // ---
+    let _ = USER.try_with(|user| user.set(Some(user_id)));
+}
+
+/// Whether a request to `route` answered with `status` is logged.
+fn sampled(config: &AccessLogConfig, route: Option<&str>, status: StatusCode) -> bool {
+    // ---
+    if status.is_server_error() {
+        return true;
+    }
+    let rate = config.sample_rate_for(route);
+    rate >= 1.0 || (rate > 0.0 && rand::thread_rng().gen_bool(rate))
+}
+
+/// `query` with the values of sensitive parameters fingerprinted.
+fn redact_query(query: &str) -> String {
+    // ---
+    query
+        .split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some((name, value)) if is_sensitive(name) => {
+                format!("{name}={}", redact::secret(value))
+            }
+            _ => pair.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+fn is_sensitive(name: &str) -> bool {
+    // ---
+    let name = name.to_ascii_lowercase();
+    SENSITIVE_PARAMS.iter().any(|param| name.contains(param))
+}
+
+/// Logs the request passed through it, subject to sampling.
+pub(crate) async fn access_log(
+    State(config): State<Arc<AccessLogConfig>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    // ---
+    let start = Instant::now();
+    let method = req.method().clone();
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_owned());
+    let path = req.uri().path().to_owned();
+    let query = req.uri().query().map(redact_query);
+
+    let (response, user_id) = USER
+        .scope(Cell::new(None), async {
+            let response = next.run(req).await;
+            (response, USER.with(Cell::get))
+        })
+        .await;
+
+    let status = response.status();
+    if sampled(&config, route.as_deref(), status) {
+        tracing::info!(
+            target: "access_log",
+            method = %method,
+            route = route.as_deref().unwrap_or("-"),
+            path = %path,
+            query = query.as_deref(),
+            status = status.as_u16(),
+            duration_ms = start.elapsed().as_secs_f64() * 1000.0,
+            bytes = response.body().size_hint().exact(),
+            user_id = user_id.map(tracing::field::display),
+        );
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+    use axum::{body::Body, middleware::from_fn_with_state, routing::get, Router};
+    use std::io;
+    use std::sync::Mutex;
+    use tower::ServiceExt;
+
+    /// Collects formatted log output.
+    #[derive(Clone, Default)]
+    struct Captured(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for Captured {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            // ---
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            // ---
+            Ok(())
+        }
+    }
+
+    impl Captured {
+        fn text(&self) -> String {
+            // ---
+            String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+        }
+    }
+
+    #[test]
+    fn sensitive_query_values_are_redacted() {
+        // ---
+        let query = redact_query("token=tok-1&page=2&API_KEY=key-2&flag");
+        assert_eq!(
+            query,
+            format!(
+                "token={}&page=2&API_KEY={}&flag",
+                redact::secret("tok-1"),
+                redact::secret("key-2")
+            )
+        );
+        assert!(!query.contains("tok-1") && !query.contains("key-2"));
+    }
+
+    #[test]
+    fn server_errors_bypass_sampling() {
+        // ---
+        let config = AccessLogConfig {
+            sample_rate: 0.0,
+            ..AccessLogConfig::default()
+        };
+        assert!(!sampled(&config, Some("/health"), StatusCode::OK));
+        assert!(!sampled(&config, None, StatusCode::NOT_FOUND));
+        assert!(sampled(&config, Some("/health"), StatusCode::BAD_GATEWAY));
+        assert!(sampled(
+            &AccessLogConfig::default(),
+            Some("/health"),
+            StatusCode::OK
+        ));
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn logs_route_user_and_redacted_query() {
+        // ---
+        let captured = Captured::default();
+        let writer = captured.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(move || writer.clone())
+            .with_ansi(false)
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let user_id = Uuid::new_v4();
+        let config = Arc::new(AccessLogConfig {
+            route_sample_rates: vec![("/quiet".to_string(), 0.0)],
+            ..AccessLogConfig::default()
+        });
+        let items = Router::new().route(
+            "/items/{id}",
+            get(move || async move {
+                record_access_user(user_id);
+                "ok"
+            }),
+        );
+        let app = Router::new()
+            .nest("/api", items)
+            .route("/quiet", get(|| async {}))
+            .layer(from_fn_with_state(config, access_log));
+
+        for uri in ["/api/items/7?token=secret-value&page=1", "/quiet"] {
+            let request = Request::get(uri).body(Body::empty()).unwrap();
+            app.clone().oneshot(request).await.unwrap();
+        }
+
+        let logged = captured.text();
+        assert_eq!(logged.lines().count(), 1, "{logged}");
+        assert!(logged.contains("route=\"/api/items/{id}\""), "{logged}");
+        assert!(logged.contains("status=200"), "{logged}");
+        assert!(logged.contains("bytes=2"), "{logged}");
+        assert!(logged.contains(&format!("user_id={user_id}")), "{logged}");
+        assert!(logged.contains("&page=1"), "{logged}");
+        assert!(!logged.contains("secret-value"), "{logged}");
+    }
+}
@@ -0,0 +1,169 @@
+//! Handler panics.
+//!
+//! A panic while handling a request is caught by tower-http's
+//! `CatchPanicLayer` and answered with the standard error body (500,
+//! `internal_error`) and the request's `X-Request-Id`, instead of the
+//! connection being dropped. It is counted in `panics_total` and logged
+//! once, with the request ID, where it happened, and its backtrace.
+//!
+//! The backtrace has to be taken before the stack unwinds, so a panic hook
+//! captures it for panics inside a request. Other panics (background tasks,
+//! tasks spawned by handlers) still go to the previous hook.
+
+use crate::domain::MetricsPtr;
+use crate::handlers::request_id;
+use axum::{
+    extract::Request,
+    http::{HeaderValue, StatusCode},
+    middleware::{from_fn, Next},
+    response::{IntoResponse, Response},
+    Json, Router,
+};
+use std::any::Any;
+use std::backtrace::Backtrace;
+use std::cell::RefCell;
+use std::sync::Once;
+use tower_http::catch_panic::CatchPanicLayer;
+
+/// Where a panic happened, captured by the panic hook.
+struct PanicReport {
+    // ---
+    location: Option<String>,
+    backtrace: Backtrace,
+}
+
+/// The request being handled on this task.
+struct PanicScope {
+    // ---
+    request_id: String,
+    report: RefCell<Option<PanicReport>>,
+}
+
+tokio::task_local! {
+    static PANIC_SCOPE: PanicScope;
+}
+
+/// Makes the panic hook capture panics of requests, once per process.
+fn install_panic_hook() {
+    // ---
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            let captured = PANIC_SCOPE.try_with(|scope| {
+                *scope.report.borrow_mut() = Some(PanicReport {
+                    location: info.location().map(ToString::to_string),
+                    backtrace: Backtrace::force_capture(),
+                });
+            });
+            if captured.is_err() {
+                previous(info);
+            }
+        }));
+    });
+}
+
+async fn panic_scope(req: Request, next: Next) -> Response {
+    // ---
+    let scope = PanicScope {
+        request_id: request_id(req.headers()),
+        report: RefCell::new(None),
+    };
+    PANIC_SCOPE.scope(scope, next.run(req)).await
+}
+
+fn panic_response(metrics: &MetricsPtr, payload: Box<dyn Any + Send>) -> Response {
+    // ---
+    metrics.record_panic();
+
+    let message = payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("<non-string payload>");
+    let (request_id, report) = PANIC_SCOPE
+        .try_with(|scope| (scope.request_id.clone(), scope.report.take()))
+        .unwrap_or_default();
+    match report {
+        Some(PanicReport {
+            location,
+            backtrace,
+        }) => tracing::error!(
+            request_id,
+            location,
+            "Handler panicked: {message}\n{backtrace}"
+        ),
+        None => tracing::error!(request_id, "Handler panicked: {message}"),
+    }
+
+    let mut response = (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(serde_json::json!({
+            "error": "Internal server error",
+            "code": "internal_error",
+        })),
+    )
+        .into_response();
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert("x-request-id", value);
+    }
+    response
+}
+
+/// Answers panics in `router` with a 500, counting and logging them.
+pub(crate) fn catch_panic(router: Router, metrics: MetricsPtr) -> Router {
+    // ---
+    install_panic_hook();
+    router
+        .layer(CatchPanicLayer::custom(move |payload| {
+            panic_response(&metrics, payload)
+        }))
+        .layer(from_fn(panic_scope))
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+    use crate::infrastructure::create_noop_metrics;
+    use axum::{body::Body, routing::get};
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn panics_become_a_json_500_with_the_request_id() {
+        // ---
+        async fn boom() -> &'static str {
+            panic!("boom")
+        }
+        let app = Router::new()
+            .route("/boom", get(boom))
+            .route("/fine", get(|| async { "fine" }));
+        let app = catch_panic(app, create_noop_metrics().unwrap());
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::get("/boom")
+                    .header("x-request-id", "req-123")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(response.headers()["x-request-id"], "req-123");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["code"], "internal_error");
+        assert_eq!(json["error"], "Internal server error");
+
+        // The server keeps serving
+        let response = app
+            .oneshot(Request::get("/fine").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
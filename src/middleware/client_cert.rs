@@ -0,0 +1,51 @@
+//! Mutual TLS requirement for service-to-service and operator routes.
+//!
+//! Applied to the `/admin` router and `/auth/introspect`. With
+//! `AXUM_REQUIRE_CLIENT_CERT` unset it lets everything through; bearer
+//! tokens are checked by the handlers either way.
+
+use crate::app_state::AppState;
+use crate::events::ServerEvent;
+use crate::service_identity::ServiceIdentity;
+use axum::{
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+
+/// Rejects requests made without a verified client certificate with 403
+/// Forbidden, when client certificates are required. Each denial is logged
+/// and published as an `access.denied` audit event.
+pub(crate) async fn require_client_cert(
+    State(state): State<AppState>,
+    identity: Option<ServiceIdentity>,
+    req: Request,
+    next: Next,
+) -> Response {
+    // ---
+    if !state.access().require_client_cert || identity.is_some() {
+        return next.run(req).await;
+    }
+
+    tracing::warn!(
+        "Client certificate missing on {} {}",
+        req.method(),
+        req.uri().path()
+    );
+    state.events().publish(ServerEvent::audit(
+        "access.denied",
+        serde_json::json!({
+            "reason": "client_certificate_required",
+            "method": req.method().as_str(),
+            "path": req.uri().path(),
+        }),
+    ));
+
+    (
+        StatusCode::FORBIDDEN,
+        Json(serde_json::json!({ "error": "Client certificate required" })),
+    )
+        .into_response()
+}
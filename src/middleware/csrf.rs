@@ -0,0 +1,158 @@
+//! CSRF protection for cookie-authenticated requests.
+//!
+//! Uses the synchronizer-token pattern: `GET /csrf` issues a random token
+//! bound to the caller's session cookie and stores it in Redis. Every
+//! state-changing request that carries the session cookie must echo that
+//! token in the `X-CSRF-Token` header.
+//!
+//! Requests are exempt when they:
+//! - use a safe method (`GET`, `HEAD`, `OPTIONS`, `TRACE`),
+//! - authenticate with `Authorization: Bearer` (browsers never attach that
+//!   header on their own, so it cannot be forged cross-site), or
+//! - carry no session cookie (there is no ambient credential to abuse).
+
+use crate::app_state::AppState;
+use crate::handlers::constant_time_eq;
+use crate::redis_keys;
+use crate::redis_source::RedisConnection;
+use crate::session::{session_cookie, SESSION_TTL_SECONDS};
+use axum::{
+    extract::{Request, State},
+    http::{HeaderMap, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use redis::AsyncCommands;
+use uuid::Uuid;
+
+/// Request header carrying the CSRF token.
+pub const CSRF_HEADER: &str = "x-csrf-token";
+
+/// Issues (or rotates) the CSRF token for `session_token`.
+///
+/// The token lives as long as a session, so one fetch per page load is enough.
+pub(crate) async fn issue_csrf_token(
+    conn: &mut RedisConnection,
+    session_token: &str,
+) -> redis::RedisResult<String> {
+    // ---
+    let token = format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+    conn.set_ex::<_, _, ()>(
+        redis_keys::csrf(session_token),
+        &token,
+        SESSION_TTL_SECONDS as u64,
+    )
+    .await?;
+
+    Ok(token)
+}
+
+/// Whether the request must present a CSRF token, returning the session
+/// cookie it is bound to if so.
+fn requires_csrf<'a>(method: &Method, headers: &'a HeaderMap) -> Option<&'a str> {
+    // ---
+    if matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE
+    ) {
+        return None;
+    }
+
+    let bearer = headers
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("Bearer "));
+    if bearer {
+        return None;
+    }
+
+    session_cookie(headers)
+}
+
+fn reject(status: StatusCode, message: &str) -> Response {
+    // ---
+    (status, Json(serde_json::json!({ "error": message }))).into_response()
+}
+
+/// Rejects cookie-authenticated, state-changing requests without a valid
+/// `X-CSRF-Token` header with 403 Forbidden.
+pub(crate) async fn csrf_protect(
+    State(state): State<AppState>,
+    req: Request,
+    next: Next,
+) -> Response {
+    // ---
+    let Some(session_token) = requires_csrf(req.method(), req.headers()) else {
+        return next.run(req).await;
+    };
+
+    let Some(supplied) = req.headers().get(CSRF_HEADER).and_then(|v| v.to_str().ok()) else {
+        tracing::warn!(
+            "CSRF token missing on {} {}",
+            req.method(),
+            req.uri().path()
+        );
+        return reject(StatusCode::FORBIDDEN, "Missing CSRF token");
+    };
+
+    let key = &redis_keys::csrf(session_token);
+    let expected: Option<String> = match state
+        .redis_read(move |mut conn| async move { conn.get(key).await })
+        .await
+    {
+        Ok(expected) => expected,
+        Err(e) => {
+            tracing::error!("Failed to read CSRF token from Redis: {e}");
+            return reject(StatusCode::INTERNAL_SERVER_ERROR, "Internal server error");
+        }
+    };
+
+    match expected {
+        Some(expected) if constant_time_eq(supplied.as_bytes(), expected.as_bytes()) => {
+            next.run(req).await
+        }
+        _ => {
+            tracing::warn!(
+                "CSRF token invalid on {} {}",
+                req.method(),
+                req.uri().path()
+            );
+            reject(StatusCode::FORBIDDEN, "Invalid CSRF token")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        // ---
+        let mut map = HeaderMap::new();
+        for (name, value) in pairs {
+            map.append(*name, HeaderValue::from_str(value).unwrap());
+        }
+        map
+    }
+
+    #[test]
+    fn only_unsafe_cookie_requests_need_a_token() {
+        // ---
+        let cookie = headers(&[("cookie", "theme=dark; axum_session=abc")]);
+        let cookie_and_bearer = headers(&[
+            ("cookie", "axum_session=abc"),
+            ("authorization", "Bearer xyz"),
+        ]);
+        let other_cookie = headers(&[("cookie", "session=abc")]);
+
+        assert_eq!(requires_csrf(&Method::POST, &cookie), Some("abc"));
+        assert_eq!(requires_csrf(&Method::DELETE, &cookie), Some("abc"));
+        assert_eq!(requires_csrf(&Method::GET, &cookie), None);
+        assert_eq!(requires_csrf(&Method::POST, &cookie_and_bearer), None);
+        assert_eq!(requires_csrf(&Method::POST, &other_cookie), None);
+        assert_eq!(requires_csrf(&Method::POST, &HeaderMap::new()), None);
+    }
+}
@@ -0,0 +1,120 @@
+//! Request time budget.
+//!
+//! Every request gets a [`Deadline`] `AXUM_REQUEST_TIMEOUT_SEC` from its
+//! arrival, or the override in `AXUM_ROUTE_TIMEOUTS` for its path prefix
+//! (see [`ServerConfig::timeout_for`]). Repository and Redis calls honour
+//! it (see [`crate::deadline`]), and a handler still running when it passes
+//! is dropped and answered with 503 Service Unavailable.
+//!
+//! Streaming responses (`/events`, `/ws`) are only bounded until their
+//! headers are sent; the stream itself is not cut off.
+
+use crate::deadline::Deadline;
+use crate::ServerConfig;
+use axum::{
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use std::sync::Arc;
+
+/// Applies the budget `server` gives the request's path, if any.
+pub(crate) async fn request_deadline(
+    State(server): State<Arc<ServerConfig>>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    // ---
+    let Some(budget) = server.timeout_for(req.uri().path()) else {
+        return next.run(req).await;
+    };
+    let deadline = Deadline::after(budget);
+    req.extensions_mut().insert(deadline);
+
+    let method = req.method().clone();
+    let path = req.uri().path().to_owned();
+
+    let response = deadline.scope(tokio::time::timeout_at(deadline.instant(), next.run(req)));
+    match response.await {
+        Ok(response) => response,
+        Err(_) => {
+            tracing::warn!(
+                "Request {} {} exceeded its {:?} budget",
+                method,
+                path,
+                budget
+            );
+            (
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(serde_json::json!({ "error": "Request timed out" })),
+            )
+                .into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+    use axum::{body::Body, routing::get, Router};
+    use std::time::Duration;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn slow_handlers_are_cut_off_at_the_budget() {
+        // ---
+        let server = ServerConfig {
+            request_timeout: Some(Duration::from_millis(50)),
+            route_timeouts: vec![
+                ("/patient".to_string(), Some(Duration::from_secs(10))),
+                ("/unbounded".to_string(), None),
+            ],
+            ..ServerConfig::default()
+        };
+        let report = || async {
+            Deadline::current()
+                .map(|_| "bounded")
+                .unwrap_or("unbounded")
+        };
+        let app = Router::new()
+            .route("/fast", get(report))
+            .route("/unbounded", get(report))
+            .route("/slow", get(|| tokio::time::sleep(Duration::from_secs(5))))
+            .route(
+                "/patient/slow",
+                get(|| tokio::time::sleep(Duration::from_millis(100))),
+            )
+            .layer(axum::middleware::from_fn_with_state(
+                Arc::new(server),
+                request_deadline,
+            ));
+
+        let get_path = |path: &str| Request::get(path).body(Body::empty()).unwrap();
+
+        let fast = app.clone().oneshot(get_path("/fast")).await.unwrap();
+        assert_eq!(fast.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(fast.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], b"bounded");
+
+        let slow = app.clone().oneshot(get_path("/slow")).await.unwrap();
+        assert_eq!(slow.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        // Route overrides lengthen or lift the budget
+        let patient = app
+            .clone()
+            .oneshot(get_path("/patient/slow"))
+            .await
+            .unwrap();
+        assert_eq!(patient.status(), StatusCode::OK);
+        let unbounded = app.oneshot(get_path("/unbounded")).await.unwrap();
+        let body = axum::body::to_bytes(unbounded.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], b"unbounded");
+    }
+}
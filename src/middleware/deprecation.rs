@@ -0,0 +1,45 @@
+//! Deprecation signalling for legacy (unversioned) route aliases.
+
+use crate::app_state::AppState;
+use axum::{
+    extract::{Request, State},
+    http::{HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+
+/// Adds deprecation headers to responses served from a legacy route alias.
+///
+/// Legacy unversioned paths (e.g. `/movies/get/{id}`) keep working, but every
+/// response carries:
+/// - `Deprecation: true` to flag the route as deprecated
+/// - `Link: </api/v1/...>; rel="successor-version"` pointing at the versioned
+///   path, below the base path if one is configured
+///
+/// Clients and proxies can use these to find and migrate remaining callers
+/// before the aliases are removed.
+pub(crate) async fn deprecated_alias(
+    State(state): State<AppState>,
+    req: Request,
+    next: Next,
+) -> Response {
+    // ---
+    let successor = format!(
+        "<{}{}>; rel=\"successor-version\"",
+        state.api_v1_prefix(),
+        req.uri().path()
+    );
+
+    let mut response = next.run(req).await;
+
+    let headers = response.headers_mut();
+    headers.insert(
+        HeaderName::from_static("deprecation"),
+        HeaderValue::from_static("true"),
+    );
+    if let Ok(link) = HeaderValue::from_str(&successor) {
+        headers.insert(axum::http::header::LINK, link);
+    }
+
+    response
+}
@@ -0,0 +1,58 @@
+//! Client IP allow/deny lists for operator endpoints.
+//!
+//! Applied to the `/admin` router and `/metrics`. The client address is
+//! resolved by [`ClientIp`], which only believes forwarding headers from
+//! trusted proxies.
+//!
+//! The peer address comes from `ConnectInfo<SocketAddr>`, so the router must
+//! be served with `into_make_service_with_connect_info`. Without it every
+//! request is denied while a list is configured.
+
+use crate::app_state::AppState;
+use crate::client_ip::ClientIp;
+use crate::events::ServerEvent;
+use axum::{
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+
+/// Rejects requests to operator endpoints from clients outside the configured
+/// allow list, or inside the deny list, with 403 Forbidden. Each denial is
+/// logged and published as an `access.denied` audit event.
+pub(crate) async fn admin_ip_filter(
+    State(state): State<AppState>,
+    client: Option<ClientIp>,
+    req: Request,
+    next: Next,
+) -> Response {
+    // ---
+    let access = state.access();
+    if access.is_unrestricted() || client.is_some_and(|ClientIp(ip)| access.admin_allows(ip)) {
+        return next.run(req).await;
+    }
+
+    let client = client.map_or_else(|| "unknown".to_string(), |ip| ip.to_string());
+    tracing::warn!(
+        "Access denied for client {} on {} {}",
+        client,
+        req.method(),
+        req.uri().path()
+    );
+    state.events().publish(ServerEvent::audit(
+        "access.denied",
+        serde_json::json!({
+            "client_ip": client,
+            "method": req.method().as_str(),
+            "path": req.uri().path(),
+        }),
+    ));
+
+    (
+        StatusCode::FORBIDDEN,
+        Json(serde_json::json!({ "error": "Access denied" })),
+    )
+        .into_response()
+}
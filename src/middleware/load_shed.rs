@@ -0,0 +1,135 @@
+//! Load shedding.
+//!
+//! At most `AXUM_MAX_IN_FLIGHT_REQUESTS` requests are handled at once,
+//! across all routes. Requests arriving while every slot is taken are
+//! rejected immediately with 503 Service Unavailable instead of queueing,
+//! so a traffic spike cannot pile up waiters on the Redis and database
+//! pools. Admitted requests are counted in `http_requests_in_flight`.
+
+use crate::domain::MetricsPtr;
+use axum::{
+    error_handling::HandleErrorLayer,
+    extract::{Request, State},
+    http::{header, StatusCode},
+    middleware::{from_fn_with_state, Next},
+    response::{IntoResponse, Response},
+    BoxError, Json, Router,
+};
+use tower::limit::GlobalConcurrencyLimitLayer;
+use tower::load_shed::error::Overloaded;
+use tower::ServiceBuilder;
+
+/// Seconds a shed client is asked to wait before retrying.
+const RETRY_AFTER_SECS: &str = "1";
+
+/// Counts a request in flight until it finishes or is dropped.
+struct InFlight(MetricsPtr);
+
+impl InFlight {
+    // ---
+
+    fn start(metrics: MetricsPtr) -> Self {
+        // ---
+        metrics.record_in_flight_request(true);
+        Self(metrics)
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        // ---
+        self.0.record_in_flight_request(false);
+    }
+}
+
+async fn track_in_flight(State(metrics): State<MetricsPtr>, req: Request, next: Next) -> Response {
+    // ---
+    let _in_flight = InFlight::start(metrics);
+    next.run(req).await
+}
+
+async fn overloaded(err: BoxError) -> Response {
+    // ---
+    if err.is::<Overloaded>() {
+        tracing::debug!("Shedding request: too many in flight");
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            [(header::RETRY_AFTER, RETRY_AFTER_SECS)],
+            Json(serde_json::json!({ "error": "Server is busy, retry later" })),
+        )
+            .into_response();
+    }
+
+    tracing::error!("Unhandled middleware error: {err}");
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(serde_json::json!({ "error": "Internal server error" })),
+    )
+        .into_response()
+}
+
+/// Limits `router` to `max_in_flight` concurrent requests, shedding the
+/// excess, and tracks admitted requests in the in-flight gauge. With no
+/// limit, requests are only tracked.
+pub(crate) fn load_shed(
+    router: Router,
+    max_in_flight: Option<usize>,
+    metrics: MetricsPtr,
+) -> Router {
+    // ---
+    let router = router.layer(from_fn_with_state(metrics, track_in_flight));
+    match max_in_flight {
+        Some(max) => router.layer(
+            ServiceBuilder::new()
+                .layer(HandleErrorLayer::new(overloaded))
+                .load_shed()
+                .layer(GlobalConcurrencyLimitLayer::new(max)),
+        ),
+        None => router,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+    use crate::infrastructure::create_noop_metrics;
+    use axum::{body::Body, routing::get};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::sync::Notify;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn requests_over_the_limit_are_shed() {
+        // ---
+        let gate = Arc::new(Notify::new());
+        let held_gate = gate.clone();
+
+        let app = Router::new()
+            .route(
+                "/hold",
+                get(move || {
+                    let gate = held_gate.clone();
+                    async move { gate.notified().await }
+                }),
+            )
+            .route("/quick", get(|| async {}));
+        let app = load_shed(app, Some(1), create_noop_metrics().unwrap());
+
+        let get_path = |path: &str| Request::get(path).body(Body::empty()).unwrap();
+
+        let held = tokio::spawn(app.clone().oneshot(get_path("/hold")));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let shed = app.clone().oneshot(get_path("/quick")).await.unwrap();
+        assert_eq!(shed.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(shed.headers()[header::RETRY_AFTER], "1");
+
+        gate.notify_one();
+        assert_eq!(held.await.unwrap().unwrap().status(), StatusCode::OK);
+
+        let admitted = app.oneshot(get_path("/quick")).await.unwrap();
+        assert_eq!(admitted.status(), StatusCode::OK);
+    }
+}
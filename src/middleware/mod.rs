@@ -0,0 +1,44 @@
+// Gateway module - controls public API for middleware
+// Modules are private, only exported symbols are public
+
+mod access_log;
+mod catch_panic;
+mod client_cert;
+mod csrf;
+mod deadline;
+mod deprecation;
+mod ip_filter;
+mod load_shed;
+mod quota;
+mod trace_context;
+
+// Legacy route aliasing
+pub(crate) use deprecation::deprecated_alias;
+
+// CSRF protection for cookie sessions
+pub use csrf::CSRF_HEADER;
+pub(crate) use csrf::{csrf_protect, issue_csrf_token};
+
+// Per-request time budget
+pub(crate) use deadline::request_deadline;
+
+// Concurrency limit with load shedding
+pub(crate) use load_shed::load_shed;
+
+// Handler panics answered with a 500 instead of a dropped connection
+pub(crate) use catch_panic::catch_panic;
+
+// Daily and monthly request quotas per user or service token
+pub(crate) use quota::enforce_quota;
+
+// Client IP allow/deny lists for operator endpoints
+pub(crate) use ip_filter::admin_ip_filter;
+
+// Mutual TLS requirement for service-to-service and operator endpoints
+pub(crate) use client_cert::require_client_cert;
+
+// Sampled, structured per-request logging
+pub(crate) use access_log::{access_log, record_access_user};
+
+// W3C traceparent / tracestate on inbound requests
+pub(crate) use trace_context::propagate_trace;
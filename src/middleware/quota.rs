@@ -0,0 +1,97 @@
+//! Per-caller request quotas (`AXUM_USER_QUOTA_*`, `AXUM_KEY_QUOTA_*`).
+//!
+//! Applied to every API route. Requests carrying a session or service token
+//! are counted against the caller's daily and monthly quotas; anonymous
+//! requests and tokens that do not authenticate pass through uncounted.
+//! Responses to counted requests carry `X-RateLimit-Limit`,
+//! `X-RateLimit-Remaining`, and `X-RateLimit-Reset` (seconds until the
+//! window resets) for the window closest to running out.
+
+use crate::app_state::AppState;
+use crate::application::{QuotaService, QuotaWindow};
+use crate::session::session_cookie;
+use crate::tenant::Tenant;
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::Utc;
+
+/// Counts the request against its caller's quotas, refusing it with 429
+/// Too Many Requests once a quota is used up. If Redis is unavailable the
+/// request is let through uncounted.
+pub(crate) async fn enforce_quota(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    req: Request,
+    next: Next,
+) -> Response {
+    // ---
+    if !state.quotas().is_enabled() {
+        return next.run(req).await;
+    }
+
+    let token = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .or_else(|| session_cookie(req.headers()));
+    let service = QuotaService::new(state);
+    let caller = match token {
+        Some(token) => service.caller(token, &tenant).await,
+        None => None,
+    };
+    let Some(caller) = caller else {
+        return next.run(req).await;
+    };
+
+    let decision = match service.consume(&caller).await {
+        Ok(Some(decision)) => decision,
+        Ok(None) => return next.run(req).await,
+        Err(e) => {
+            tracing::warn!("Quota not checked, allowing request: {}", e.message);
+            return next.run(req).await;
+        }
+    };
+
+    let mut response = if decision.allowed {
+        next.run(req).await
+    } else {
+        tracing::info!(
+            "Quota exceeded for {} caller on {} {}",
+            caller.kind(),
+            req.method(),
+            req.uri().path()
+        );
+        (
+            StatusCode::TOO_MANY_REQUESTS,
+            Json(serde_json::json!({ "error": "Quota exceeded" })),
+        )
+            .into_response()
+    };
+
+    if let Some(window) = decision.binding() {
+        rate_limit_headers(response.headers_mut(), window, !decision.allowed);
+    }
+    response
+}
+
+/// Adds the `X-RateLimit-*` headers for `window`, and `Retry-After` if the
+/// request was refused.
+fn rate_limit_headers(headers: &mut HeaderMap, window: &QuotaWindow, refused: bool) {
+    // ---
+    let reset = (window.resets_at - Utc::now()).num_seconds().max(0);
+    let limit = window.limit.unwrap_or_default();
+    let remaining = window.remaining().unwrap_or_default();
+
+    headers.insert("x-ratelimit-limit", HeaderValue::from(limit));
+    headers.insert("x-ratelimit-remaining", HeaderValue::from(remaining));
+    headers.insert("x-ratelimit-reset", HeaderValue::from(reset));
+    if refused {
+        headers.insert(header::RETRY_AFTER, HeaderValue::from(reset));
+    }
+}
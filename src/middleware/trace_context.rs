@@ -0,0 +1,72 @@
+//! Inbound W3C Trace Context.
+//!
+//! Wraps every request in a `request` span carrying its `trace_id`,
+//! `span_id`, and the caller's `parent_id`, so everything logged while it
+//! is handled (the access log included) can be joined with the caller's
+//! trace. The [`TraceContext`] is also inserted as a request extension and
+//! made [current](TraceContext::current) for outbound calls.
+
+use crate::trace_context::TraceContext;
+use axum::{extract::Request, middleware::Next, response::Response};
+use tracing::Instrument;
+
+/// Continues the caller's trace, or starts one, for the request passed
+/// through it.
+pub(crate) async fn propagate_trace(mut req: Request, next: Next) -> Response {
+    // ---
+    let ctx = TraceContext::from_headers_or_root(req.headers());
+    let span = tracing::info_span!(
+        "request",
+        trace_id = %ctx.trace_id(),
+        span_id = %ctx.span_id(),
+        parent_id = ctx.parent_id().map(tracing::field::display),
+    );
+    req.extensions_mut().insert(ctx.clone());
+
+    ctx.scope(next.run(req)).instrument(span).await
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+    use crate::trace_context::TRACEPARENT;
+    use axum::{body::Body, middleware::from_fn, routing::get, Extension, Router};
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn handlers_see_the_inbound_trace() {
+        // ---
+        async fn handler(Extension(ctx): Extension<TraceContext>) -> String {
+            // ---
+            let current = TraceContext::current().expect("context is set for the request");
+            assert_eq!(current, ctx);
+            format!("{} {}", ctx.trace_id(), ctx.parent_id().unwrap_or_default())
+        }
+        let app = Router::new()
+            .route("/", get(handler))
+            .layer(from_fn(propagate_trace));
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/")
+                    .header(
+                        TRACEPARENT,
+                        "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+                    )
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            body,
+            "4bf92f3577b34da6a3ce929d0e0e4736 00f067aa0ba902b7".as_bytes()
+        );
+    }
+}
@@ -0,0 +1,54 @@
+//! The library surface, in one import.
+//!
+//! ```no_run
+//! use axum_quickstart::prelude::*;
+//!
+//! # async fn run() -> anyhow::Result<()> {
+//! let config = AppConfig::from_env()?;
+//! let repository: RepositoryPtr = create_repository(&config.database).await?;
+//! let metrics: MetricsPtr = create_prom_metrics()?;
+//!
+//! let router = AppBuilder::new()
+//!     .config(config)
+//!     .repository(repository)
+//!     .metrics(metrics)
+//!     .build()?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Everything here follows semver: it changes incompatibly only in a new
+//! major version. That covers the storage, metrics, and key abstractions
+//! downstream crates implement ([`Repository`], [`Metrics`],
+//! [`KeyProvider`]) and the types they exchange, the builder and runtime
+//! handles of the server, sessions, and the JSON envelope and error body
+//! of the API, for handlers mounted next to this crate's routes.
+//!
+//! The application services (movies, credentials, sign-in) are not part of
+//! it: they run on the server's internal state and are reached through
+//! the router. Challenges and sessions are stored by concrete Redis code,
+//! not behind a trait, so there is no store to swap in yet.
+
+#[cfg(feature = "sqlite")]
+pub use crate::SqliteRepository;
+pub use crate::{
+    create_noop_metrics, create_prom_metrics, create_repository, EncryptedRepository,
+    LocalKeyProvider, PostgresRepository,
+};
+
+// Abstractions and the types they exchange
+pub use crate::domain::{
+    Credential, CredentialStats, KeyProvider, KeyProviderPtr, Metrics, MetricsPtr, PurgeSummary,
+    ReencryptSummary, Repository, RepositoryPtr, RuntimeSample, User, WrappedKey,
+};
+
+// Building and running the server
+pub use crate::{
+    AppBuilder, AppConfig, AppService, ConfigReloader, EventBus, ServerEvent, ShutdownSignal,
+};
+
+// Sessions
+pub use crate::{create_session, validate_session, SessionInfo, SESSION_COOKIE};
+
+// Response envelope and error body
+pub use crate::handlers::{ApiError, ApiResponse, Pagination, ResponseLinks, ResponseMeta};
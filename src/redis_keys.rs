@@ -0,0 +1,364 @@
+//! Names of the Redis keys the server uses.
+//!
+//! Every key is built here so that `AXUM_REDIS_KEY_PREFIX` applies to all
+//! of them, letting several environments share one Redis instance
+//! (`staging:session:…` next to `prod:session:…`). A `:` is appended to the
+//! prefix if it does not end with one. Without a prefix the keys are the
+//! ones used before prefixes existed, so no data needs migrating.
+//!
+//! The prefix is read once, on first use; changing it means restarting,
+//! and keys written under the old prefix are no longer seen.
+//!
+//! With Redis Cluster (`AXUM_REDIS_CLUSTER_NODES`), keys that one Lua
+//! script or pipeline touches together must be in one slot. Those keys get
+//! a hash tag, the part in braces that alone decides the slot: the movie
+//! keys share `{movies}`, a quota caller's windows share the caller, and
+//! the sign-in outcome hours share `{auth}`. Outside a cluster the tags are
+//! left out, so the names stay as before. Moving existing data into a
+//! cluster therefore means renaming these keys.
+
+use crate::domain::DEFAULT_TENANT;
+use once_cell::sync::Lazy;
+use std::fmt::Display;
+use uuid::Uuid;
+
+/// Prepended to every key, with its trailing `:`; empty without a prefix.
+static PREFIX: Lazy<String> =
+    Lazy::new(|| normalize_prefix(&std::env::var("AXUM_REDIS_KEY_PREFIX").unwrap_or_default()));
+
+fn normalize_prefix(prefix: &str) -> String {
+    // ---
+    let prefix = prefix.trim();
+    match prefix {
+        "" => String::new(),
+        _ if prefix.ends_with(':') => prefix.to_string(),
+        _ => format!("{prefix}:"),
+    }
+}
+
+/// Names of the keys that carry a hash tag in cluster mode, read once like
+/// the prefix.
+static SLOTTED: Lazy<SlottedKeys> = Lazy::new(|| SlottedKeys {
+    cluster: std::env::var("AXUM_REDIS_CLUSTER_NODES").is_ok_and(|nodes| !nodes.trim().is_empty()),
+});
+
+/// Builds the keys that must share a slot with others in a cluster.
+#[derive(Clone, Copy)]
+struct SlottedKeys {
+    // ---
+    cluster: bool,
+}
+
+impl SlottedKeys {
+    // ---
+
+    /// `{tag}` in a cluster, else `tag`.
+    fn tag(self, tag: &str) -> String {
+        // ---
+        match self.cluster {
+            true => format!("{{{tag}}}"),
+            false => tag.to_string(),
+        }
+    }
+
+    fn movie_key(self, key: impl Display) -> String {
+        // ---
+        match self.cluster {
+            true => redis_key(format_args!("{{movies}}:{key}")),
+            false => redis_key(key),
+        }
+    }
+
+    fn movie(self, id: &str) -> String {
+        // ---
+        self.movie_key(id)
+    }
+
+    fn movie_title(self, hash: &str) -> String {
+        // ---
+        self.movie_key(format_args!("movie:title:{hash}"))
+    }
+
+    fn movie_genre(self, genre: &str) -> String {
+        // ---
+        self.movie_key(format_args!("movie:genre:{genre}"))
+    }
+
+    fn all_movies(self) -> String {
+        // ---
+        self.movie_key("movie:ids")
+    }
+
+    fn quota_usage(self, caller: &str, window: &str) -> String {
+        // ---
+        redis_key(format_args!("quota:{}:{window}", self.tag(caller)))
+    }
+
+    fn auth_outcomes(self, hour: &str) -> String {
+        // ---
+        redis_key(format_args!("stats:{}:{hour}", self.tag("auth")))
+    }
+}
+
+/// `key` in this server's namespace, i.e. with `AXUM_REDIS_KEY_PREFIX`
+/// applied. For tests and tools that inspect Redis directly.
+pub fn redis_key(key: impl Display) -> String {
+    // ---
+    format!("{}{key}", *PREFIX)
+}
+
+/// Session data of a Redis-backed session token.
+pub(crate) fn session(token: &str) -> String {
+    // ---
+    redis_key(format_args!("session:{token}"))
+}
+
+/// [`session`] built on the stack, for the lookup on every authenticated
+/// request.
+pub(crate) fn session_key(token: &str) -> StackKey {
+    // ---
+    let mut key = StackKey::default();
+    key.push_str(&PREFIX);
+    key.push_str("session:");
+    key.push_str(token);
+    key
+}
+
+/// Denylist entry of a revoked signed session.
+pub(crate) fn revoked_session(jti: Uuid) -> String {
+    // ---
+    redis_key(format_args!("session:revoked:{jti}"))
+}
+
+/// Marks a session whose fingerprint change has been reported, by a
+/// digest of its token. Outside `session:*`, so it is not counted as a
+/// session.
+pub(crate) fn session_fingerprint_reported(token_digest: &str) -> String {
+    // ---
+    redis_key(format_args!("session_fingerprint:{token_digest}"))
+}
+
+/// CSRF token bound to a session.
+pub(crate) fn csrf(session_token: &str) -> String {
+    // ---
+    redis_key(format_args!("csrf:{session_token}"))
+}
+
+/// State of one WebAuthn ceremony (`reg` or `auth`) for `username`. Keys of
+/// the default tenant carry no tenant ID.
+pub(crate) fn challenge(tenant_id: &str, ceremony: &str, username: &str, flow_id: Uuid) -> String {
+    // ---
+    match tenant_id {
+        DEFAULT_TENANT => redis_key(format_args!("webauthn:{ceremony}:{username}:{flow_id}")),
+        _ => redis_key(format_args!(
+            "webauthn:{tenant_id}:{ceremony}:{username}:{flow_id}"
+        )),
+    }
+}
+
+/// Credential IDs that have signed in for a user.
+pub(crate) fn seen_credentials(user_id: Uuid) -> String {
+    // ---
+    redis_key(format_args!("webauthn:seen_credentials:{user_id}"))
+}
+
+/// Admin override of a user's passkey limit.
+pub(crate) fn credential_limit(user_id: Uuid) -> String {
+    // ---
+    redis_key(format_args!("webauthn:credential_limit:{user_id}"))
+}
+
+/// Hash of registered webhook endpoints, by ID.
+pub(crate) fn webhook_endpoints() -> String {
+    // ---
+    redis_key("webhooks:endpoints")
+}
+
+/// A movie's JSON. Movie keys are bare IDs.
+pub(crate) fn movie(id: &str) -> String {
+    // ---
+    SLOTTED.movie(id)
+}
+
+/// ID of the movie with a normalized title and year (`hash`).
+pub(crate) fn movie_title(hash: &str) -> String {
+    // ---
+    SLOTTED.movie_title(hash)
+}
+
+/// Set of the IDs of the movies in `genre`.
+pub(crate) fn movie_genre(genre: &str) -> String {
+    // ---
+    SLOTTED.movie_genre(genre)
+}
+
+/// Set of the ID of every movie.
+pub(crate) fn all_movies() -> String {
+    // ---
+    SLOTTED.all_movies()
+}
+
+/// Request count of a quota caller (`user:{id}` or `key:{fingerprint}`)
+/// in one window (`2026-10-15` or `2026-10`).
+pub(crate) fn quota_usage(caller: &str, window: &str) -> String {
+    // ---
+    SLOTTED.quota_usage(caller, window)
+}
+
+/// Sign-in outcome counts (hash of `succeeded` and `failed`) for one UTC
+/// hour (`2026-10-15T09`).
+pub(crate) fn auth_outcomes(hour: &str) -> String {
+    // ---
+    SLOTTED.auth_outcomes(hour)
+}
+
+/// Cached `GET /admin/stats` result.
+pub(crate) fn admin_stats() -> String {
+    // ---
+    redis_key("stats:admin")
+}
+
+/// Pattern matching the keys of Redis-backed sessions, and of the signed
+/// session denylist; see [`is_revoked_session`].
+pub(crate) fn session_pattern() -> String {
+    // ---
+    redis_key("session:*")
+}
+
+/// Whether `key` is a denylist entry rather than a session.
+pub(crate) fn is_revoked_session(key: &str) -> bool {
+    // ---
+    key.starts_with(&redis_key("session:revoked:"))
+}
+
+/// Keys up to this long are built without a heap allocation.
+const STACK_KEY_LEN: usize = 128;
+
+/// A key in a fixed buffer, moved to the heap only if it outgrows it.
+pub(crate) struct StackKey {
+    // ---
+    buf: [u8; STACK_KEY_LEN],
+    len: usize,
+    spilled: Option<String>,
+}
+
+impl Default for StackKey {
+    fn default() -> Self {
+        // ---
+        Self {
+            buf: [0; STACK_KEY_LEN],
+            len: 0,
+            spilled: None,
+        }
+    }
+}
+
+impl StackKey {
+    // ---
+
+    fn push_str(&mut self, s: &str) {
+        // ---
+        if let Some(spilled) = &mut self.spilled {
+            spilled.push_str(s);
+        } else if let Some(free) = self.buf.get_mut(self.len..self.len + s.len()) {
+            free.copy_from_slice(s.as_bytes());
+            self.len += s.len();
+        } else {
+            let mut spilled = String::with_capacity(self.len + s.len());
+            spilled.push_str(self.as_str());
+            spilled.push_str(s);
+            self.spilled = Some(spilled);
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        // ---
+        match &self.spilled {
+            Some(spilled) => spilled,
+            // Only whole `&str`s are copied in, so this is valid UTF-8
+            None => std::str::from_utf8(&self.buf[..self.len]).unwrap_or_default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+
+    #[test]
+    fn prefixes_end_with_a_colon() {
+        // ---
+        assert_eq!(normalize_prefix(""), "");
+        assert_eq!(normalize_prefix("  "), "");
+        assert_eq!(normalize_prefix("staging"), "staging:");
+        assert_eq!(normalize_prefix("app:staging:"), "app:staging:");
+    }
+
+    #[test]
+    fn keys_are_unchanged_without_a_prefix() {
+        // ---
+        // AXUM_REDIS_KEY_PREFIX is not set for unit tests
+        assert_eq!(session("tok"), "session:tok");
+        assert_eq!(
+            revoked_session(Uuid::nil()),
+            format!("session:revoked:{}", Uuid::nil())
+        );
+        assert_eq!(webhook_endpoints(), "webhooks:endpoints");
+        assert_eq!(movie("42"), "42");
+        assert_eq!(all_movies(), "movie:ids");
+        assert_eq!(quota_usage("user:7", "2026-10"), "quota:user:7:2026-10");
+        assert_eq!(auth_outcomes("2026-10-15T09"), "stats:auth:2026-10-15T09");
+    }
+
+    #[test]
+    fn keys_used_together_share_a_cluster_slot() {
+        // ---
+        let slot = |key: String| redis::cluster_routing::get_slot(key.as_bytes());
+        let keys = SlottedKeys { cluster: true };
+
+        // Everything SAVE_MOVIE and RELEASE_TITLE touch, and the removal pipelines
+        let movies = [
+            keys.movie_title("5f1d"),
+            keys.movie("8a0c51e2-56b9-4b8e-9ef3-2d5d7c3e4f10"),
+            keys.all_movies(),
+            keys.movie_genre("scifi"),
+            keys.movie_genre("horror"),
+        ];
+        assert!(movies
+            .iter()
+            .all(|key| slot(key.clone()) == slot(keys.all_movies())));
+
+        // The quota windows of one caller, counted in one transaction
+        assert_eq!(
+            slot(keys.quota_usage("user:7", "2026-10-15")),
+            slot(keys.quota_usage("user:7", "2026-10"))
+        );
+
+        // The sign-in outcome hours, read in one pipeline
+        assert_eq!(
+            slot(keys.auth_outcomes("2026-10-15T09")),
+            slot(keys.auth_outcomes("2026-10-14T10"))
+        );
+
+        // Without the tags they spread over the cluster
+        let untagged = SlottedKeys { cluster: false };
+        assert_ne!(
+            slot(untagged.movie_genre("scifi")),
+            slot(untagged.all_movies())
+        );
+        assert_eq!(untagged.movie_genre("scifi"), "movie:genre:scifi");
+    }
+
+    #[test]
+    fn stack_keys_match_heap_keys() {
+        // ---
+        assert_eq!(session_key("tok").as_str(), session("tok"));
+
+        // Longer than the buffer: moved to the heap, unchanged
+        let token = "t".repeat(STACK_KEY_LEN * 2);
+        let key = session_key(&token);
+        assert!(key.spilled.is_some());
+        assert_eq!(key.as_str(), session(&token));
+    }
+}
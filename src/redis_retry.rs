@@ -0,0 +1,206 @@
+//! Retries for transient Redis failures.
+//!
+//! A dropped connection, a refused connect during failover, or a server
+//! still loading its dataset usually clears within milliseconds. Idempotent
+//! operations (connecting, `GET`, `EXISTS`) are retried a few times with
+//! "full jitter" backoff: each delay is uniformly random between zero and an
+//! exponentially growing cap, so concurrent requests do not retry in step.
+//!
+//! Commands with side effects (`SET`, `GETDEL`, `INCR`, ...) must not go
+//! through here, as a retry after a lost reply could apply them twice.
+
+use crate::config::RedisConfig;
+use crate::domain::MetricsPtr;
+use rand::Rng;
+use redis::{ErrorKind, RedisError, RedisResult, RetryMethod};
+use std::future::Future;
+use std::time::Duration;
+
+/// Longest delay between two attempts, whatever the base delay.
+const MAX_DELAY: Duration = Duration::from_secs(1);
+
+/// Retry policy for idempotent Redis operations.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct RedisRetry {
+    // ---
+    attempts: u32,
+    base_delay: Duration,
+}
+
+impl Default for RedisRetry {
+    fn default() -> Self {
+        // ---
+        Self {
+            attempts: 1,
+            base_delay: Duration::ZERO,
+        }
+    }
+}
+
+impl RedisRetry {
+    // ---
+
+    pub fn from_config(config: &RedisConfig) -> Self {
+        // ---
+        Self {
+            attempts: config.retry_attempts.max(1),
+            base_delay: config.retry_base_delay,
+        }
+    }
+
+    /// Upper bound of the delay before retry number `retry` (0-based).
+    fn delay_cap(&self, retry: u32) -> Duration {
+        // ---
+        self.base_delay
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(MAX_DELAY)
+    }
+
+    /// Runs `op` until it succeeds, fails with a permanent error, or the
+    /// attempts are used up.
+    ///
+    /// When at least one retry was needed, the outcome is recorded in
+    /// `redis_retries_total` as `recovered` or `exhausted`.
+    pub async fn run<T, F, Fut>(&self, metrics: &MetricsPtr, mut op: F) -> RedisResult<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = RedisResult<T>>,
+    {
+        // ---
+        let mut retry = 0;
+        loop {
+            match op().await {
+                Ok(value) => {
+                    if retry > 0 {
+                        metrics.record_redis_retry(true);
+                    }
+                    return Ok(value);
+                }
+                Err(e) if is_transient(&e) && retry + 1 < self.attempts => {
+                    let delay =
+                        rand::thread_rng().gen_range(Duration::ZERO..=self.delay_cap(retry));
+                    tracing::debug!("Transient Redis error, retrying in {delay:?}: {e}");
+                    tokio::time::sleep(delay).await;
+                    retry += 1;
+                }
+                Err(e) => {
+                    if retry > 0 {
+                        metrics.record_redis_retry(false);
+                    }
+                    return Err(e);
+                }
+            }
+        }
+    }
+}
+
+/// Whether retrying `e` may succeed: connection loss, timeouts, and
+/// "try again later" replies. Command errors and bad credentials are final.
+fn is_transient(e: &RedisError) -> bool {
+    // ---
+    if e.kind() == ErrorKind::AuthenticationFailed {
+        return false;
+    }
+    e.is_timeout()
+        || matches!(
+            e.retry_method(),
+            RetryMethod::Reconnect | RetryMethod::RetryImmediately | RetryMethod::WaitAndRetry
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+    use crate::infrastructure::create_noop_metrics;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn policy(attempts: u32) -> RedisRetry {
+        // ---
+        RedisRetry {
+            attempts,
+            base_delay: Duration::from_millis(1),
+        }
+    }
+
+    fn metrics() -> MetricsPtr {
+        // ---
+        create_noop_metrics().unwrap()
+    }
+
+    fn dropped() -> RedisError {
+        // ---
+        std::io::Error::from(std::io::ErrorKind::ConnectionReset).into()
+    }
+
+    #[test]
+    fn transient_errors_are_classified() {
+        // ---
+        assert!(is_transient(&dropped()));
+        assert!(is_transient(&RedisError::from((
+            ErrorKind::TryAgain,
+            "busy"
+        ))));
+        assert!(!is_transient(&RedisError::from((
+            ErrorKind::TypeError,
+            "wrong type"
+        ))));
+        assert!(!is_transient(&RedisError::from((
+            ErrorKind::AuthenticationFailed,
+            "bad password"
+        ))));
+    }
+
+    #[test]
+    fn delay_cap_grows_and_is_bounded() {
+        // ---
+        let retry = RedisRetry {
+            attempts: 10,
+            base_delay: Duration::from_millis(100),
+        };
+        assert_eq!(retry.delay_cap(0), Duration::from_millis(100));
+        assert_eq!(retry.delay_cap(2), Duration::from_millis(400));
+        assert_eq!(retry.delay_cap(30), MAX_DELAY);
+    }
+
+    #[tokio::test]
+    async fn retries_transient_errors_until_success() {
+        // ---
+        let calls = AtomicU32::new(0);
+        let result = policy(3)
+            .run(&metrics(), || async {
+                match calls.fetch_add(1, Ordering::SeqCst) {
+                    0 => Err(dropped()),
+                    n => Ok(n),
+                }
+            })
+            .await;
+
+        assert_eq!(result.unwrap(), 1);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_attempts_or_on_permanent_errors() {
+        // ---
+        let calls = AtomicU32::new(0);
+        let result: RedisResult<()> = policy(3)
+            .run(&metrics(), || async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Err(dropped())
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        calls.store(0, Ordering::SeqCst);
+        let result: RedisResult<()> = policy(3)
+            .run(&metrics(), || async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Err(RedisError::from((ErrorKind::TypeError, "wrong type")))
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,466 @@
+//! Where Redis connections come from: a fixed address, the primary
+//! currently reported by Redis Sentinel, or a Redis Cluster.
+//!
+//! With Sentinel (`AXUM_REDIS_SENTINEL_MASTER`), every connect first asks
+//! the sentinels for the primary's address. Connections are opened per
+//! operation, so after a failover the next operation reaches the new
+//! primary; a connect that fails mid-failover is retried like any other
+//! transient error (see [`RedisRetry`](crate::redis_retry::RedisRetry)),
+//! asking the sentinels again. Challenges and sessions written to the old
+//! primary survive if they were replicated before it went down.
+//!
+//! `rediss://` URLs connect over TLS, verified against the system roots or
+//! the CA bundle in `AXUM_REDIS_TLS_CA_FILE`. `AXUM_REDIS_USERNAME` and
+//! `AXUM_REDIS_PASSWORD` override the credentials in the URL, so they can
+//! come from a secret store rather than being spelled out in `REDIS_URL`.
+//!
+//! With Redis Cluster (`AXUM_REDIS_CLUSTER_NODES`), one connection is
+//! opened on first use and shared by every operation; it learns which node
+//! serves each slot and follows slots as they move. Commands sent together
+//! (pipelines, Lua scripts) must stay within one slot, so the keys written
+//! together carry a hash tag in cluster mode; see
+//! [`redis_keys`](crate::redis_keys).
+
+use crate::config::RedisConfig;
+use anyhow::{Context, Result};
+use redis::aio::{ConnectionLike, MultiplexedConnection};
+use redis::cluster::{ClusterClient, ClusterClientBuilder};
+use redis::cluster_async::ClusterConnection;
+use redis::cluster_routing::{
+    MultipleNodeRoutingInfo, ResponsePolicy, RoutingInfo, SingleNodeRoutingInfo,
+};
+use redis::sentinel::{Sentinel, SentinelNodeConnectionInfo};
+use redis::{
+    AsyncCommands, AsyncConnectionConfig, Client, Cmd, ConnectionAddr, ConnectionInfo,
+    IntoConnectionInfo, Pipeline, RedisFuture, RedisResult, TlsCertificates, TlsMode, Value,
+};
+use std::sync::Arc;
+use tokio::sync::{Mutex, OnceCell};
+
+/// Keys asked for per `SCAN` call.
+const SCAN_COUNT: usize = 1000;
+
+/// Opens Redis connections. Cheap to clone.
+#[derive(Clone)]
+pub(crate) enum RedisSource {
+    // ---
+    /// A single server.
+    Direct(Client),
+
+    /// The primary of a Sentinel-monitored group.
+    Sentinel(Arc<SentinelSource>),
+
+    /// The nodes of a Redis Cluster.
+    Cluster(Arc<ClusterSource>),
+}
+
+/// Sentinels and how to connect to the primary they report.
+pub(crate) struct SentinelSource {
+    // ---
+    /// Keeps a connection to each sentinel; queries need exclusive access.
+    sentinel: Mutex<Sentinel>,
+    master_name: String,
+    node: SentinelNodeConnectionInfo,
+
+    /// CA bundle (PEM) the primary's certificate is verified against.
+    root_cert: Option<Vec<u8>>,
+}
+
+/// A Redis Cluster and the connection shared by every operation on it.
+pub(crate) struct ClusterSource {
+    // ---
+    client: ClusterClient,
+
+    /// Opened on first use. It reconnects to nodes by itself, so it is
+    /// kept for the life of the server.
+    connection: OnceCell<ClusterConnection>,
+}
+
+/// A connection from a [`RedisSource`]. Cheap to clone; clones share the
+/// underlying connection.
+#[derive(Clone)]
+pub(crate) enum RedisConnection {
+    // ---
+    /// To a single server (also a Sentinel primary).
+    Single(MultiplexedConnection),
+
+    /// To every node of a Redis Cluster, routing each command by its keys.
+    Cluster(ClusterConnection),
+}
+
+impl RedisSource {
+    // ---
+
+    /// A fixed server (`config.url`), the Sentinel primary when
+    /// `config.sentinel_master` is set, or the cluster seeded by
+    /// `config.cluster_nodes`.
+    ///
+    /// # Errors
+    /// Returns an error if a URL is invalid or the CA bundle cannot be read.
+    pub(crate) fn from_config(config: &RedisConfig) -> Result<Self> {
+        // ---
+        let mut info = config.url.as_str().into_connection_info()?;
+        if let Some(username) = &config.username {
+            info.redis.username = Some(username.clone());
+        }
+        if let Some(password) = &config.password {
+            info.redis.password = Some(password.clone());
+        }
+        let root_cert = match &config.tls_ca_file {
+            Some(path) => Some(std::fs::read(path).with_context(|| {
+                format!("Cannot read AXUM_REDIS_TLS_CA_FILE {}", path.display())
+            })?),
+            None => None,
+        };
+
+        if !config.cluster_nodes.is_empty() {
+            return Ok(Self::Cluster(Arc::new(ClusterSource::new(
+                &config.cluster_nodes,
+                info,
+                root_cert,
+            )?)));
+        }
+        let Some(master_name) = &config.sentinel_master else {
+            return Ok(Self::Direct(open_client(info, &root_cert)?));
+        };
+
+        // The primary inherits the scheme, database, and credentials of REDIS_URL
+        let tls_mode = match info.addr {
+            ConnectionAddr::TcpTls { insecure: true, .. } => Some(TlsMode::Insecure),
+            ConnectionAddr::TcpTls { .. } => Some(TlsMode::Secure),
+            _ => None,
+        };
+        let node = SentinelNodeConnectionInfo {
+            tls_mode,
+            redis_connection_info: Some(info.redis),
+        };
+        let sentinels = config.sentinels.iter().map(String::as_str).collect();
+
+        Ok(Self::Sentinel(Arc::new(SentinelSource {
+            sentinel: Mutex::new(Sentinel::build(sentinels)?),
+            master_name: master_name.clone(),
+            node,
+            root_cert,
+        })))
+    }
+
+    /// Opens a multiplexed connection with default settings.
+    pub(crate) async fn connect(&self) -> RedisResult<RedisConnection> {
+        // ---
+        self.connect_with_config(&AsyncConnectionConfig::new())
+            .await
+    }
+
+    /// Opens a multiplexed connection with `config`'s timeouts. The shared
+    /// cluster connection keeps the default ones.
+    pub(crate) async fn connect_with_config(
+        &self,
+        config: &AsyncConnectionConfig,
+    ) -> RedisResult<RedisConnection> {
+        // ---
+        let client = match self {
+            Self::Direct(client) => client.clone(),
+            Self::Sentinel(source) => source.primary().await?,
+            Self::Cluster(source) => return source.connect().await.map(RedisConnection::Cluster),
+        };
+        client
+            .get_multiplexed_async_connection_with_config(config)
+            .await
+            .map(RedisConnection::Single)
+    }
+}
+
+impl ClusterSource {
+    // ---
+
+    /// A cluster found through `nodes`, authenticating with the
+    /// credentials of `info` (`REDIS_URL` and its overrides) if it has any.
+    fn new(nodes: &[String], info: ConnectionInfo, root_cert: Option<Vec<u8>>) -> Result<Self> {
+        // ---
+        let mut builder = ClusterClientBuilder::new(nodes.iter().map(String::as_str));
+        if let Some(username) = info.redis.username {
+            builder = builder.username(username);
+        }
+        if let Some(password) = info.redis.password {
+            builder = builder.password(password);
+        }
+        if let Some(pem) = root_cert {
+            builder = builder.certs(TlsCertificates {
+                client_tls: None,
+                root_cert: Some(pem),
+            });
+        }
+
+        Ok(Self {
+            client: builder
+                .build()
+                .context("Invalid AXUM_REDIS_CLUSTER_NODES")?,
+            connection: OnceCell::new(),
+        })
+    }
+
+    /// The shared connection, opened now if this is the first use or
+    /// opening it failed before.
+    async fn connect(&self) -> RedisResult<ClusterConnection> {
+        // ---
+        self.connection
+            .get_or_try_init(|| self.client.get_async_connection())
+            .await
+            .cloned()
+    }
+}
+
+impl RedisConnection {
+    // ---
+
+    /// Calls `f` with every key matching `pattern`, scanning each primary
+    /// of a cluster in turn. Keys added or removed meanwhile may or may
+    /// not be seen.
+    pub(crate) async fn for_each_key(
+        &mut self,
+        pattern: &str,
+        mut f: impl FnMut(&str) + Send,
+    ) -> RedisResult<()> {
+        // ---
+        let conn = match self {
+            Self::Single(conn) => {
+                let mut keys = conn.scan_match::<_, String>(pattern).await?;
+                while let Some(key) = keys.next_item().await {
+                    f(&key);
+                }
+                return Ok(());
+            }
+            Self::Cluster(conn) => conn,
+        };
+
+        // SCAN reads one node, so ask each primary, by the address it answers PING from
+        let primaries =
+            RoutingInfo::MultiNode((MultipleNodeRoutingInfo::AllMasters, None::<ResponsePolicy>));
+        let addresses: Vec<(String, Value)> =
+            redis::from_redis_value(&conn.route_command(&redis::cmd("PING"), primaries).await?)?;
+
+        for (address, _) in addresses {
+            let (host, port) = address
+                .rsplit_once(':')
+                .and_then(|(host, port)| Some((host.to_string(), port.parse().ok()?)))
+                .ok_or((redis::ErrorKind::ClientError, "Invalid node address"))?;
+            let node = RoutingInfo::SingleNode(SingleNodeRoutingInfo::ByAddress { host, port });
+
+            let mut cursor = 0u64;
+            loop {
+                let mut scan = redis::cmd("SCAN");
+                scan.arg(cursor)
+                    .arg("MATCH")
+                    .arg(pattern)
+                    .arg("COUNT")
+                    .arg(SCAN_COUNT);
+                let reply = conn.route_command(&scan, node.clone()).await?;
+                let (next, keys): (u64, Vec<String>) = redis::from_redis_value(&reply)?;
+                keys.iter().for_each(|key| f(key));
+                if next == 0 {
+                    break;
+                }
+                cursor = next;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ConnectionLike for RedisConnection {
+    fn req_packed_command<'a>(&'a mut self, cmd: &'a Cmd) -> RedisFuture<'a, Value> {
+        // ---
+        match self {
+            Self::Single(conn) => conn.req_packed_command(cmd),
+            Self::Cluster(conn) => conn.req_packed_command(cmd),
+        }
+    }
+
+    fn req_packed_commands<'a>(
+        &'a mut self,
+        pipeline: &'a Pipeline,
+        offset: usize,
+        count: usize,
+    ) -> RedisFuture<'a, Vec<Value>> {
+        // ---
+        match self {
+            Self::Single(conn) => conn.req_packed_commands(pipeline, offset, count),
+            Self::Cluster(conn) => conn.req_packed_commands(pipeline, offset, count),
+        }
+    }
+
+    fn get_db(&self) -> i64 {
+        // ---
+        match self {
+            Self::Single(conn) => conn.get_db(),
+            Self::Cluster(conn) => conn.get_db(),
+        }
+    }
+}
+
+impl From<MultiplexedConnection> for RedisConnection {
+    fn from(conn: MultiplexedConnection) -> Self {
+        // ---
+        Self::Single(conn)
+    }
+}
+
+impl SentinelSource {
+    // ---
+
+    /// A client for the current primary, as reported by the first sentinel
+    /// that answers.
+    async fn primary(&self) -> RedisResult<Client> {
+        // ---
+        let primary = {
+            let mut sentinel = self.sentinel.lock().await;
+            sentinel
+                .async_master_for(&self.master_name, Some(&self.node))
+                .await?
+        };
+        match &self.root_cert {
+            Some(_) => open_client(primary.get_connection_info().clone(), &self.root_cert),
+            None => Ok(primary),
+        }
+    }
+}
+
+/// A client for `info`, trusting `root_cert` instead of the system roots
+/// if given.
+fn open_client(info: ConnectionInfo, root_cert: &Option<Vec<u8>>) -> RedisResult<Client> {
+    // ---
+    match root_cert {
+        Some(pem) => Client::build_with_tls(
+            info,
+            TlsCertificates {
+                client_tls: None,
+                root_cert: Some(pem.clone()),
+            },
+        ),
+        None => Client::open(info),
+    }
+}
+
+/// Connects to Redis as configured and checks that it answers `PING`, so a
+/// wrong address, certificate, or password stops the server at startup
+/// instead of failing its first request.
+///
+/// # Errors
+/// Returns an error describing why Redis could not be reached.
+pub async fn verify_redis(config: &RedisConfig) -> Result<()> {
+    // ---
+    let source = RedisSource::from_config(config)?;
+    let mut conn = source.connect().await.context("Cannot connect to Redis")?;
+    redis::cmd("PING")
+        .query_async::<()>(&mut conn)
+        .await
+        .context("Redis did not answer PING")?;
+    Ok(())
+}
+
+impl From<Client> for RedisSource {
+    fn from(client: Client) -> Self {
+        // ---
+        Self::Direct(client)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+    use std::time::Duration;
+
+    fn config(sentinel_master: Option<&str>) -> RedisConfig {
+        // ---
+        RedisConfig {
+            url: "redis://:hunter2@127.0.0.1:6379/3".to_string(),
+            username: None,
+            password: None,
+            tls_ca_file: None,
+            sentinel_master: sentinel_master.map(str::to_string),
+            sentinels: vec!["redis://127.0.0.1:1".to_string()],
+            cluster_nodes: Vec::new(),
+            webauthn_challenge_ttl: Duration::from_secs(300),
+            retry_attempts: 1,
+            retry_base_delay: Duration::ZERO,
+        }
+    }
+
+    #[test]
+    fn sentinel_primary_uses_redis_url_credentials() {
+        // ---
+        let RedisSource::Sentinel(source) =
+            RedisSource::from_config(&config(Some("main"))).unwrap()
+        else {
+            panic!("expected a Sentinel source");
+        };
+        let redis = source.node.redis_connection_info.as_ref().unwrap();
+        assert_eq!(redis.db, 3);
+        assert_eq!(redis.password.as_deref(), Some("hunter2"));
+        assert_eq!(source.master_name, "main");
+
+        assert!(matches!(
+            RedisSource::from_config(&config(None)).unwrap(),
+            RedisSource::Direct(_)
+        ));
+    }
+
+    #[test]
+    fn explicit_credentials_override_the_url() {
+        // ---
+        let mut config = config(Some("main"));
+        config.url = "rediss://:hunter2@redis.internal:6380/1".to_string();
+        config.username = Some("app".to_string());
+        config.password = Some("s3cret".to_string());
+        let RedisSource::Sentinel(source) = RedisSource::from_config(&config).unwrap() else {
+            panic!("expected a Sentinel source");
+        };
+        let redis = source.node.redis_connection_info.as_ref().unwrap();
+        assert_eq!(redis.username.as_deref(), Some("app"));
+        assert_eq!(redis.password.as_deref(), Some("s3cret"));
+        assert!(matches!(source.node.tls_mode, Some(TlsMode::Secure)));
+
+        config.tls_ca_file = Some("/nonexistent/ca.pem".into());
+        let err = RedisSource::from_config(&config).err().unwrap();
+        assert!(err.to_string().contains("AXUM_REDIS_TLS_CA_FILE"), "{err}");
+    }
+
+    #[test]
+    fn cluster_nodes_make_a_cluster_source() {
+        // ---
+        let mut config = config(None);
+        config.cluster_nodes = vec![
+            "redis://127.0.0.1:7000".to_string(),
+            "redis://127.0.0.1:7001".to_string(),
+        ];
+        assert!(matches!(
+            RedisSource::from_config(&config).unwrap(),
+            RedisSource::Cluster(_)
+        ));
+
+        config.cluster_nodes = vec!["not a url".to_string()];
+        let err = RedisSource::from_config(&config).err().unwrap();
+        assert!(
+            err.to_string().contains("AXUM_REDIS_CLUSTER_NODES"),
+            "{err}"
+        );
+    }
+
+    #[tokio::test]
+    async fn verify_fails_when_redis_is_unreachable() {
+        // ---
+        let mut config = config(None);
+        config.url = "redis://127.0.0.1:1".to_string();
+        let err = verify_redis(&config).await.unwrap_err();
+        assert!(err.to_string().contains("Cannot connect to Redis"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn unreachable_sentinels_fail_transiently() {
+        // ---
+        let source = RedisSource::from_config(&config(Some("main"))).unwrap();
+        let err = source.connect().await.err().unwrap();
+        assert!(matches!(err.retry_method(), redis::RetryMethod::Reconnect));
+    }
+}
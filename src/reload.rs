@@ -0,0 +1,258 @@
+//! Configuration that can change while the server runs.
+//!
+//! [`ConfigReloader`] holds the reloadable sections of [`AppConfig`] (admin
+//! API settings, client IP access lists, the credential and password
+//! policies, and request quotas) behind
+//! an `ArcSwap`, so a reload replaces all of them at once and requests never
+//! see a mix of old and new values. Each request reads a snapshot.
+//!
+//! When serving HTTPS, a reload also re-reads the TLS certificate, key, and
+//! client CA files (see [`LiveTls`](crate::LiveTls)); connections made
+//! afterwards use them. Switching HTTPS on or off still needs a restart.
+//!
+//! Everything else (database, Redis, WebAuthn relying party, sessions,
+//! encryption keys, background jobs, listener) is fixed at startup; changes
+//! to it are logged and ignored until the next restart.
+
+#[cfg(feature = "password")]
+use crate::config::PasswordPolicy;
+use crate::config::{
+    AccessConfig, AdminConfig, AppConfig, CredentialPolicy, QuotaConfig, TlsConfig,
+};
+#[cfg(feature = "tls")]
+use crate::tls::LiveTls;
+use arc_swap::ArcSwap;
+use std::fmt::Debug;
+use std::ops::Deref;
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// The reloadable part of the configuration.
+#[derive(Debug, Clone)]
+pub(crate) struct LiveConfig {
+    // ---
+    pub admin: AdminConfig,
+    pub access: AccessConfig,
+    pub credentials: CredentialPolicy,
+    #[cfg(feature = "password")]
+    pub passwords: PasswordPolicy,
+    pub quotas: QuotaConfig,
+}
+
+impl LiveConfig {
+    // ---
+
+    fn from_config(config: &AppConfig) -> Self {
+        // ---
+        Self {
+            admin: config.admin.clone(),
+            access: config.access.clone(),
+            credentials: config.credentials.clone(),
+            #[cfg(feature = "password")]
+            passwords: config.passwords.clone(),
+            quotas: config.quotas.clone(),
+        }
+    }
+}
+
+/// Shared, atomically replaceable [`LiveConfig`].
+pub(crate) type LiveConfigPtr = Arc<ArcSwap<LiveConfig>>;
+
+/// A snapshot of one section of the live configuration.
+///
+/// Holds the snapshot it was taken from, so a reload in the meantime does
+/// not change what the holder sees.
+pub(crate) struct Live<T: 'static> {
+    // ---
+    config: Arc<LiveConfig>,
+    section: fn(&LiveConfig) -> &T,
+}
+
+impl<T> Live<T> {
+    // ---
+
+    /// Snapshots the current value of `section`.
+    pub fn load(live: &LiveConfigPtr, section: fn(&LiveConfig) -> &T) -> Self {
+        // ---
+        Self {
+            config: live.load_full(),
+            section,
+        }
+    }
+}
+
+impl<T> Deref for Live<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // ---
+        (self.section)(&self.config)
+    }
+}
+
+/// Applies configuration reloads to a running server.
+///
+/// Cloning is cheap; clones share the same live configuration. Pass one to
+/// [`AppBuilder::reloader`](crate::AppBuilder::reloader) and call
+/// [`reload`](Self::reload) when the configuration changes (the server
+/// binary does so on `SIGHUP`).
+#[derive(Clone)]
+pub struct ConfigReloader {
+    // ---
+    live: LiveConfigPtr,
+
+    /// The configuration in effect, for comparing against the next reload.
+    current: Arc<Mutex<AppConfig>>,
+
+    /// The certificates being served, if HTTPS is on.
+    #[cfg(feature = "tls")]
+    tls: Option<LiveTls>,
+}
+
+impl ConfigReloader {
+    // ---
+
+    pub fn new(config: &AppConfig) -> Self {
+        // ---
+        Self {
+            live: Arc::new(ArcSwap::from_pointee(LiveConfig::from_config(config))),
+            current: Arc::new(Mutex::new(config.clone())),
+            #[cfg(feature = "tls")]
+            tls: None,
+        }
+    }
+
+    /// Re-reads the certificates `tls` serves on each reload.
+    #[cfg(feature = "tls")]
+    pub fn with_tls(mut self, tls: LiveTls) -> Self {
+        // ---
+        self.tls = Some(tls);
+        self
+    }
+
+    /// The live configuration this reloader updates.
+    pub(crate) fn live(&self) -> LiveConfigPtr {
+        // ---
+        self.live.clone()
+    }
+
+    /// Applies the reloadable sections of `config` and returns a description
+    /// of each setting that changed. Secret values are not included.
+    ///
+    /// Changes to sections that cannot be reloaded are logged as warnings
+    /// and otherwise ignored.
+    pub fn reload(&self, config: AppConfig) -> Vec<String> {
+        // ---
+        let mut current = self.current.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let fixed = [
+            ("server", differs(&current.server, &config.server)),
+            ("database", differs(&current.database, &config.database)),
+            ("redis", differs(&current.redis, &config.redis)),
+            ("webauthn", differs(&current.webauthn, &config.webauthn)),
+            ("cleanup", differs(&current.cleanup, &config.cleanup)),
+            ("retention", differs(&current.retention, &config.retention)),
+            ("webhooks", differs(&current.webhooks, &config.webhooks)),
+            ("export", differs(&current.export, &config.export)),
+            (
+                "push_gateway",
+                differs(&current.push_gateway, &config.push_gateway),
+            ),
+            (
+                "event_stream",
+                differs(&current.event_stream, &config.event_stream),
+            ),
+            ("http", differs(&current.http, &config.http)),
+            ("session", differs(&current.session, &config.session)),
+            (
+                "encryption",
+                differs(&current.encryption, &config.encryption),
+            ),
+        ];
+        for (section, _) in fixed.iter().filter(|(_, changed)| *changed) {
+            tracing::warn!("Config reload: {section} settings changed but need a restart; ignored");
+        }
+
+        let tls_change = self.reload_tls(&mut current.tls, config.tls);
+        let changes: Vec<String> = [
+            secret_change(
+                "admin.api_token",
+                &current.admin.api_token,
+                &config.admin.api_token,
+            ),
+            secret_change(
+                "admin.service_tokens",
+                &current.admin.service_tokens,
+                &config.admin.service_tokens,
+            ),
+            change(
+                "admin.soft_delete_retention",
+                &current.admin.soft_delete_retention,
+                &config.admin.soft_delete_retention,
+            ),
+            change("access", &current.access, &config.access),
+            change("credentials", &current.credentials, &config.credentials),
+            change("passwords", &current.passwords, &config.passwords),
+            change("quotas", &current.quotas, &config.quotas),
+            tls_change,
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+
+        current.admin = config.admin;
+        current.access = config.access;
+        current.credentials = config.credentials;
+        current.passwords = config.passwords;
+        current.quotas = config.quotas;
+        self.live.store(Arc::new(LiveConfig::from_config(&current)));
+
+        if changes.is_empty() {
+            tracing::info!("Config reload: no reloadable settings changed");
+        }
+        for change in &changes {
+            tracing::info!("Config reload: {change}");
+        }
+        changes
+    }
+
+    /// Re-reads the TLS files when serving HTTPS, keeping the current
+    /// certificates if they are invalid. Returns a change description if
+    /// their contents changed.
+    fn reload_tls(&self, current: &mut TlsConfig, config: TlsConfig) -> Option<String> {
+        // ---
+        #[cfg(feature = "tls")]
+        if let Some(tls) = self.tls.as_ref().filter(|_| config.is_enabled()) {
+            let reloaded = match tls.reload(&config) {
+                Ok(reloaded) => reloaded,
+                Err(e) => {
+                    tracing::warn!("Config reload: keeping the current TLS certificates: {e:#}");
+                    return None;
+                }
+            };
+            *current = config;
+            return reloaded.then(|| "tls: certificates reloaded".to_string());
+        }
+
+        if differs(current, &config) {
+            tracing::warn!("Config reload: tls settings changed but need a restart; ignored");
+        }
+        None
+    }
+}
+
+fn differs<T: Debug>(old: &T, new: &T) -> bool {
+    // ---
+    format!("{old:?}") != format!("{new:?}")
+}
+
+/// `name: old -> new`, if the value changed.
+fn change<T: Debug>(name: &str, old: &T, new: &T) -> Option<String> {
+    // ---
+    differs(old, new).then(|| format!("{name}: {old:?} -> {new:?}"))
+}
+
+/// Like [`change`], without the values.
+fn secret_change<T: Debug>(name: &str, old: &T, new: &T) -> Option<String> {
+    // ---
+    differs(old, new).then(|| format!("{name} changed"))
+}
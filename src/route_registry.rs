@@ -0,0 +1,427 @@
+//! Registry of the HTTP routes.
+//!
+//! One entry per method and path, with what it does, the credentials it
+//! takes, and the middleware in front of it. `axum-quickstart print-routes`
+//! and `GET /admin/routes` list it, and `build_routes` in `lib.rs` routes
+//! exactly these entries, behind the route-level middleware they list.
+//!
+//! Paths are those of the router: versioned routes are relative to
+//! [`API_V1_PREFIX`] and are also served at their legacy unversioned path,
+//! behind `deprecated_alias`.
+
+use crate::API_V1_PREFIX;
+use serde::Serialize;
+
+/// Middleware every request passes through, outermost first. `access_log`
+/// only runs when enabled, and `load_shed` is skipped on the admin listener.
+pub const GLOBAL_LAYERS: &[&str] = &[
+    "propagate_trace",
+    "access_log",
+    "load_shed",
+    "request_deadline",
+    "catch_panic",
+    "csrf_protect",
+];
+
+/// Credentials a route takes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RouteAuth {
+    // ---
+    /// Open to anyone.
+    None,
+
+    /// A session token, as a bearer token or the session cookie.
+    Session,
+
+    /// A session token or a service token.
+    SessionOrService,
+
+    /// A session token or `AXUM_ADMIN_TOKEN`.
+    SessionOrAdmin,
+
+    /// A service token or `AXUM_ADMIN_TOKEN`.
+    Service,
+
+    /// `AXUM_ADMIN_TOKEN`.
+    Admin,
+}
+
+impl RouteAuth {
+    // ---
+
+    /// Name as serialized (`session_or_admin`, ...).
+    pub fn as_str(&self) -> &'static str {
+        // ---
+        match self {
+            Self::None => "none",
+            Self::Session => "session",
+            Self::SessionOrService => "session_or_service",
+            Self::SessionOrAdmin => "session_or_admin",
+            Self::Service => "service",
+            Self::Admin => "admin",
+        }
+    }
+}
+
+/// A registered route, with its paths as clients see them.
+#[derive(Debug, Clone, Serialize)]
+pub struct RouteInfo {
+    // ---
+    pub method: &'static str,
+    pub path: String,
+
+    /// The deprecated unversioned alias, for versioned routes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub legacy_path: Option<String>,
+
+    /// Query parameters it reads.
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    pub query: &'static [&'static str],
+
+    pub summary: &'static str,
+    pub auth: RouteAuth,
+
+    /// Route-level middleware, outermost first, inside [`GLOBAL_LAYERS`].
+    pub layers: &'static [&'static str],
+
+    /// Served on `AXUM_ADMIN_BIND_ADDR` instead of `API_BIND_ADDR` when
+    /// that is set.
+    pub operator: bool,
+}
+
+/// Every registered route, and the middleware in front of all of them.
+#[derive(Debug, Clone, Serialize)]
+pub struct RouteTable {
+    // ---
+    pub global_layers: &'static [&'static str],
+    pub routes: Vec<RouteInfo>,
+}
+
+/// The routes of this build, below `base_path` (`AXUM_BASE_PATH`, or `""`).
+pub fn route_table(base_path: &str) -> RouteTable {
+    // ---
+    let routes = ROUTES
+        .iter()
+        .map(|route| RouteInfo {
+            method: route.method,
+            path: match route.versioned {
+                true => format!("{base_path}{API_V1_PREFIX}{}", route.path),
+                false => format!("{base_path}{}", route.path),
+            },
+            legacy_path: route
+                .versioned
+                .then(|| format!("{base_path}{}", route.path)),
+            query: route.query,
+            summary: route.summary,
+            auth: route.auth,
+            layers: route.layers,
+            operator: route.operator,
+        })
+        .collect();
+
+    RouteTable {
+        global_layers: GLOBAL_LAYERS,
+        routes,
+    }
+}
+
+/// A registry entry.
+pub(crate) struct Route {
+    // ---
+    pub(crate) method: &'static str,
+
+    /// Relative to [`API_V1_PREFIX`] when `versioned`.
+    pub(crate) path: &'static str,
+    pub(crate) versioned: bool,
+    query: &'static [&'static str],
+    summary: &'static str,
+    auth: RouteAuth,
+    pub(crate) layers: &'static [&'static str],
+    pub(crate) operator: bool,
+}
+
+impl Route {
+    // ---
+
+    /// An API route, counted against the caller's quotas.
+    const fn api(
+        method: &'static str,
+        path: &'static str,
+        auth: RouteAuth,
+        summary: &'static str,
+    ) -> Self {
+        // ---
+        Self {
+            method,
+            path,
+            versioned: true,
+            query: &[],
+            summary,
+            auth,
+            layers: QUOTA,
+            operator: false,
+        }
+    }
+
+    /// An admin API route, limited to the admin IP lists and, when
+    /// required, client certificates.
+    const fn admin(method: &'static str, path: &'static str, summary: &'static str) -> Self {
+        // ---
+        Self {
+            method,
+            path,
+            versioned: true,
+            query: &[],
+            summary,
+            auth: RouteAuth::Admin,
+            layers: ADMIN,
+            operator: true,
+        }
+    }
+
+    /// A page outside the API.
+    const fn page(path: &'static str, summary: &'static str) -> Self {
+        // ---
+        Self {
+            method: "GET",
+            path,
+            versioned: false,
+            query: &[],
+            summary,
+            auth: RouteAuth::None,
+            layers: &[],
+            operator: false,
+        }
+    }
+
+    const fn with_query(mut self, query: &'static [&'static str]) -> Self {
+        // ---
+        self.query = query;
+        self
+    }
+
+    const fn with_layers(mut self, layers: &'static [&'static str]) -> Self {
+        // ---
+        self.layers = layers;
+        self
+    }
+
+    const fn operator(mut self) -> Self {
+        // ---
+        self.operator = true;
+        self
+    }
+}
+
+const QUOTA: &[&str] = &["enforce_quota"];
+const IP_FILTERED: &[&str] = &["enforce_quota", "admin_ip_filter"];
+const CLIENT_CERT: &[&str] = &["enforce_quota", "require_client_cert"];
+const ADMIN: &[&str] = &["enforce_quota", "admin_ip_filter", "require_client_cert"];
+
+/// Every route served. Each entry needs a handler in `endpoint` in `lib.rs`.
+pub(crate) const ROUTES: &[Route] = {
+    use RouteAuth::*;
+    &[
+        Route::page("/", "This landing page"),
+        Route::page("/app", "Passkey demo (register and sign in)"),
+        Route::page("/app/", "Passkey demo (register and sign in)"),
+        Route::page("/app/webauthn.js", "Passkey demo script"),
+        // Core
+        Route::api(
+            "GET",
+            "/health",
+            None,
+            "Health check; mode=full includes Redis",
+        )
+        .with_query(&["mode"])
+        .operator(),
+        Route::api(
+            "GET",
+            "/health/history",
+            None,
+            "Recent dependency health changes and uptime",
+        )
+        .operator(),
+        Route::api("GET", "/metrics", None, "Prometheus metrics")
+            .with_layers(IP_FILTERED)
+            .operator(),
+        Route::api("GET", "/csrf", Session, "CSRF token for the session cookie"),
+        Route::api(
+            "GET",
+            "/account/usage",
+            SessionOrService,
+            "Caller's quota consumption",
+        ),
+        Route::api("GET", "/events", SessionOrAdmin, "Live server events (SSE)"),
+        Route::api("GET", "/ws", Session, "WebSocket echo and notifications")
+            .with_query(&["token"]),
+        // Movies
+        Route::api("GET", "/movies/get/{id}", None, "Fetch a movie by ID"),
+        Route::api(
+            "GET",
+            "/movies/list",
+            None,
+            "List movies, by page and genre",
+        )
+        .with_query(&["genre", "page", "per_page"]),
+        Route::api("POST", "/movies/add", None, "Add a movie"),
+        Route::api("PUT", "/movies/update/{id}", None, "Update a movie by ID"),
+        Route::api(
+            "DELETE",
+            "/movies/delete/{id}",
+            None,
+            "Delete a movie by ID",
+        ),
+        // WebAuthn
+        Route::api(
+            "POST",
+            "/webauthn/register/start",
+            None,
+            "Begin passkey registration",
+        ),
+        Route::api(
+            "POST",
+            "/webauthn/register/finish",
+            None,
+            "Complete passkey registration",
+        ),
+        Route::api(
+            "POST",
+            "/webauthn/auth/start",
+            None,
+            "Begin passkey authentication",
+        ),
+        Route::api(
+            "POST",
+            "/webauthn/auth/finish",
+            None,
+            "Complete passkey authentication",
+        ),
+        Route::api(
+            "POST",
+            "/webauthn/logout",
+            Session,
+            "End the current session",
+        ),
+        Route::api(
+            "GET",
+            "/webauthn/credentials",
+            Session,
+            "List registered passkeys",
+        )
+        .with_query(&["page", "per_page"]),
+        Route::api(
+            "DELETE",
+            "/webauthn/credentials/{id}",
+            Session,
+            "Delete a passkey",
+        ),
+        #[cfg(feature = "password")]
+        Route::api(
+            "POST",
+            "/auth/password/register",
+            None,
+            "Register with a password",
+        ),
+        #[cfg(feature = "password")]
+        Route::api(
+            "POST",
+            "/auth/password/login",
+            None,
+            "Sign in with a password",
+        ),
+        // Service-to-service
+        Route::api(
+            "POST",
+            "/auth/introspect",
+            Service,
+            "Report whether a session token is active",
+        )
+        .with_layers(CLIENT_CERT),
+        // Admin
+        Route::admin("POST", "/admin/purge", "Remove soft-deleted users now")
+            .with_query(&["older_than_days"]),
+        Route::admin(
+            "POST",
+            "/admin/credentials/reencrypt",
+            "Re-encrypt stored passkeys with the current key",
+        ),
+        Route::admin("POST", "/admin/export", "Export the audit log now"),
+        Route::admin(
+            "GET",
+            "/admin/retention",
+            "Retention rules and the next purge",
+        ),
+        Route::admin("GET", "/admin/routes", "This route table"),
+        Route::admin("GET", "/admin/stats", "Deployment statistics").with_query(&["refresh"]),
+        Route::admin(
+            "GET",
+            "/admin/users/{username}/credential-limit",
+            "A user's passkey limit",
+        ),
+        Route::admin(
+            "PUT",
+            "/admin/users/{username}/credential-limit",
+            "Set a user's passkey limit",
+        ),
+        Route::admin(
+            "DELETE",
+            "/admin/users/{username}/credential-limit",
+            "Reset a user's passkey limit to the default",
+        ),
+        Route::admin("GET", "/admin/webhooks", "List webhook endpoints"),
+        Route::admin("POST", "/admin/webhooks", "Register a webhook endpoint"),
+        Route::admin("GET", "/admin/webhooks/{id}", "Fetch a webhook endpoint"),
+        Route::admin("PUT", "/admin/webhooks/{id}", "Update a webhook endpoint"),
+        Route::admin(
+            "DELETE",
+            "/admin/webhooks/{id}",
+            "Delete a webhook endpoint",
+        ),
+        #[cfg(feature = "pprof")]
+        Route::api(
+            "GET",
+            "/debug/pprof/profile",
+            Admin,
+            "CPU profile as a flamegraph or pprof",
+        )
+        .with_query(&["seconds", "format"])
+        .with_layers(IP_FILTERED)
+        .operator(),
+    ]
+};
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+
+    #[test]
+    fn routes_are_listed_below_the_base_path() {
+        // ---
+        let table = route_table("/edge");
+        let health = table
+            .routes
+            .iter()
+            .find(|route| route.path == "/edge/api/v1/health")
+            .unwrap();
+        assert_eq!(health.legacy_path.as_deref(), Some("/edge/health"));
+        assert!(health.operator);
+        assert_eq!(health.query, ["mode"]);
+
+        let root = &table.routes[0];
+        assert_eq!((root.method, root.path.as_str()), ("GET", "/edge/"));
+        assert!(root.legacy_path.is_none());
+
+        // One entry per method and path
+        let mut seen = std::collections::HashSet::new();
+        for route in &table.routes {
+            assert!(seen.insert((route.method, &route.path)), "{}", route.path);
+        }
+
+        let json = serde_json::to_value(route_table("")).unwrap();
+        assert_eq!(json["routes"][0]["path"], "/");
+        assert_eq!(json["global_layers"][0], "propagate_trace");
+    }
+}
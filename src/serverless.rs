@@ -0,0 +1,99 @@
+//! The application as a plain `tower::Service`, for runtimes that hand over
+//! requests instead of accepting TCP connections.
+//!
+//! [`AppBuilder::build_service`](crate::AppBuilder::build_service) returns
+//! an [`AppService`]: the routed application, boxed, which any tower-based
+//! host can call. Nothing supplies a peer address then, so an embedder
+//! that knows the client should insert `ConnectInfo<SocketAddr>` into each
+//! request's extensions; without it, the admin IP lists deny every request
+//! and routes that need the client address answer 500.
+//!
+//! With the `lambda` feature, [`run_lambda`] serves an [`AppService`] on AWS
+//! Lambda behind API Gateway (REST or HTTP API) or an Application Load
+//! Balancer. The client address is taken from the API Gateway request
+//! context; behind a load balancer the peer is unknown (`0.0.0.0`), so list
+//! it in `AXUM_TRUSTED_PROXIES` to read `X-Forwarded-For`.
+//!
+//! Responses are buffered, so the SSE stream and WebSockets do not work on
+//! Lambda, and background work (webhook deliveries, exports) only makes
+//! progress while an invocation runs.
+
+use axum::{extract::Request, response::Response};
+use std::convert::Infallible;
+use tower::util::BoxCloneService;
+
+/// The routed application as a cloneable `tower::Service`.
+pub type AppService = BoxCloneService<Request, Response, Infallible>;
+
+#[cfg(feature = "lambda")]
+pub use lambda::run_lambda;
+
+#[cfg(feature = "lambda")]
+mod lambda {
+    // ---
+    use super::AppService;
+    use axum::body::Body;
+    use axum::extract::ConnectInfo;
+    use lambda_http::request::RequestContext;
+    use lambda_http::{service_fn, RequestExt};
+    use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+    use tower::ServiceExt;
+
+    /// Largest response Lambda returns synchronously.
+    const MAX_RESPONSE_BYTES: usize = 6 * 1024 * 1024;
+
+    /// Serves `service` as an AWS Lambda function until the runtime stops
+    /// it.
+    ///
+    /// # Errors
+    /// Returns an error if the Lambda runtime API cannot be reached, as
+    /// when not running on Lambda.
+    pub async fn run_lambda(service: AppService) -> anyhow::Result<()> {
+        // ---
+        lambda_http::run(service_fn(move |request| handle(service.clone(), request)))
+            .await
+            .map_err(|e| anyhow::anyhow!("Lambda runtime failed: {e}"))
+    }
+
+    async fn handle(
+        service: AppService,
+        request: lambda_http::Request,
+    ) -> Result<axum::http::Response<lambda_http::Body>, lambda_http::Error> {
+        // ---
+        let peer = source_ip(&request).unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+        let (mut parts, body) = request.into_parts();
+        parts
+            .extensions
+            .insert(ConnectInfo(SocketAddr::new(peer, 0)));
+        let body = match body {
+            lambda_http::Body::Empty => Body::empty(),
+            lambda_http::Body::Text(text) => Body::from(text),
+            lambda_http::Body::Binary(bytes) => Body::from(bytes),
+        };
+
+        let Ok(response) = service
+            .oneshot(axum::http::Request::from_parts(parts, body))
+            .await;
+        let (parts, body) = response.into_parts();
+        let bytes = axum::body::to_bytes(body, MAX_RESPONSE_BYTES).await?;
+
+        // Text stays readable in API Gateway logs; the rest is sent base64
+        let body = match String::from_utf8(bytes.to_vec()) {
+            Ok(text) if text.is_empty() => lambda_http::Body::Empty,
+            Ok(text) => lambda_http::Body::Text(text),
+            Err(e) => lambda_http::Body::Binary(e.into_bytes()),
+        };
+        Ok(axum::http::Response::from_parts(parts, body))
+    }
+
+    /// The client address API Gateway saw. Load balancers do not report it.
+    fn source_ip(request: &lambda_http::Request) -> Option<IpAddr> {
+        // ---
+        let ip = match request.request_context_ref()? {
+            RequestContext::ApiGatewayV1(context) => context.identity.source_ip.as_deref(),
+            RequestContext::ApiGatewayV2(context) => context.http.source_ip.as_deref(),
+            _ => None,
+        };
+        ip?.parse().ok()
+    }
+}
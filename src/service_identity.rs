@@ -0,0 +1,51 @@
+//! The calling service, as named by its verified client certificate.
+//!
+//! With `AXUM_TLS_CLIENT_CA_FILE` set (`tls` feature), clients may present a
+//! certificate issued by one of the trusted CAs. Its subject Common Name, or
+//! failing that its first DNS or URI subject alternative name (e.g. a SPIFFE
+//! ID), becomes the connection's [`ServiceIdentity`]. Plain HTTP connections
+//! and TLS clients without a certificate have none.
+
+use axum::{
+    extract::{FromRequestParts, OptionalFromRequestParts},
+    http::{request::Parts, StatusCode},
+    Json,
+};
+use std::convert::Infallible;
+use std::fmt;
+
+/// Identity of the service on the other end of a mutual TLS connection.
+///
+/// Extracting `ServiceIdentity` directly rejects requests without a
+/// verified client certificate with 403; extract
+/// `Option<ServiceIdentity>` where one is optional.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServiceIdentity(pub String);
+
+impl fmt::Display for ServiceIdentity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // ---
+        self.0.fmt(f)
+    }
+}
+
+impl<S: Send + Sync> OptionalFromRequestParts<S> for ServiceIdentity {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Option<Self>, Infallible> {
+        // ---
+        Ok(parts.extensions.get::<ServiceIdentity>().cloned())
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for ServiceIdentity {
+    type Rejection = (StatusCode, Json<serde_json::Value>);
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        // ---
+        parts.extensions.get::<ServiceIdentity>().cloned().ok_or((
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({ "error": "Client certificate required" })),
+        ))
+    }
+}
@@ -0,0 +1,181 @@
+//! Client fingerprints recorded with sessions.
+//!
+//! A session remembers the network and user agent it was created from. A
+//! token later presented from another network *and* with another user
+//! agent is reported as possibly stolen (see `AXUM_SESSION_FINGERPRINT`).
+//! Either changing alone is common (mobile networks, browser updates) and
+//! is not reported.
+//!
+//! Networks are coarse on purpose, roughly a provider and region: the /16
+//! of an IPv4 address and the /32 of an IPv6 address. User agents are
+//! compared without their version numbers, and only a digest is stored.
+
+use crate::app_state::AppState;
+use crate::client_ip::ClientIp;
+use axum::extract::{FromRequestParts, OptionalFromRequestParts};
+use axum::http::{header, request::Parts};
+use sha2::{Digest, Sha256};
+use std::convert::Infallible;
+use std::net::IpAddr;
+
+/// Prefix length of the network an IPv4 client is placed in.
+const IPV4_NETWORK_BITS: u32 = 16;
+
+/// Prefix length of the network an IPv6 client is placed in.
+const IPV6_NETWORK_BITS: u32 = 32;
+
+/// Hex characters kept of a user agent digest.
+const AGENT_DIGEST_LEN: usize = 16;
+
+/// Where a request came from, as far as sessions are concerned: the
+/// resolved client IP and a digest of its `User-Agent`.
+///
+/// Extracting it never fails; parts that are unavailable are `None`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct ClientFingerprint {
+    // ---
+    pub ip: Option<IpAddr>,
+
+    /// Digest of the user agent without version numbers.
+    pub agent: Option<String>,
+}
+
+/// How a client differs from the one a session was created by.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct FingerprintChange {
+    // ---
+    /// Network the session was created from.
+    pub from_network: String,
+
+    /// Network the token is now used from.
+    pub to_network: String,
+}
+
+impl ClientFingerprint {
+    // ---
+
+    pub fn new(ip: Option<IpAddr>, user_agent: Option<&str>) -> Self {
+        // ---
+        Self {
+            ip,
+            agent: user_agent.map(agent_digest),
+        }
+    }
+
+    /// How `self` differs from `issued`, the client the session was created
+    /// by, if it is on another network and has another user agent. Parts
+    /// unknown on either side never count as different.
+    pub fn change_from(&self, issued: &ClientFingerprint) -> Option<FingerprintChange> {
+        // ---
+        let (from, to) = (network(issued.ip?), network(self.ip?));
+        let agent_changed = issued.agent.as_ref()? != self.agent.as_ref()?;
+
+        (from != to && agent_changed).then_some(FingerprintChange {
+            from_network: from,
+            to_network: to,
+        })
+    }
+}
+
+impl FromRequestParts<AppState> for ClientFingerprint {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Infallible> {
+        // ---
+        let client =
+            <ClientIp as OptionalFromRequestParts<AppState>>::from_request_parts(parts, state)
+                .await?;
+        let user_agent = parts
+            .headers
+            .get(header::USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .filter(|value| !value.is_empty());
+
+        Ok(Self::new(client.map(|ClientIp(ip)| ip), user_agent))
+    }
+}
+
+/// The coarse network `ip` is placed in, such as `198.51.0.0/16`.
+fn network(ip: IpAddr) -> String {
+    // ---
+    match ip {
+        IpAddr::V4(v4) => {
+            let bits = u32::from(v4) & (u32::MAX << (32 - IPV4_NETWORK_BITS));
+            format!("{}/{IPV4_NETWORK_BITS}", std::net::Ipv4Addr::from(bits))
+        }
+        IpAddr::V6(v6) => {
+            let bits = u128::from(v6) & (u128::MAX << (128 - IPV6_NETWORK_BITS));
+            format!("{}/{IPV6_NETWORK_BITS}", std::net::Ipv6Addr::from(bits))
+        }
+    }
+}
+
+/// Digest of `user_agent` with its digits removed, so that a browser
+/// updating itself keeps its fingerprint.
+fn agent_digest(user_agent: &str) -> String {
+    // ---
+    let family: String = user_agent.chars().filter(|c| !c.is_ascii_digit()).collect();
+    let mut digest = hex::encode(Sha256::digest(family.as_bytes()));
+    digest.truncate(AGENT_DIGEST_LEN);
+    digest
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+
+    const FIREFOX: &str = "Mozilla/5.0 (X11; Linux x86_64; rv:128.0) Gecko/20100101 Firefox/128.0";
+    const CURL: &str = "curl/8.5.0";
+
+    fn client(ip: &str, user_agent: Option<&str>) -> ClientFingerprint {
+        // ---
+        ClientFingerprint::new(Some(ip.parse().unwrap()), user_agent)
+    }
+
+    #[test]
+    fn networks_are_coarse() {
+        // ---
+        assert_eq!(network("198.51.100.4".parse().unwrap()), "198.51.0.0/16");
+        assert_eq!(
+            network("2001:db8:1234::1".parse().unwrap()),
+            "2001:db8::/32"
+        );
+    }
+
+    #[test]
+    fn agents_ignore_versions() {
+        // ---
+        let updated = FIREFOX.replace("128.0", "131.0");
+        assert_eq!(agent_digest(FIREFOX), agent_digest(&updated));
+        assert_ne!(agent_digest(FIREFOX), agent_digest(CURL));
+        assert_eq!(agent_digest(CURL).len(), AGENT_DIGEST_LEN);
+    }
+
+    #[test]
+    fn only_network_and_agent_changing_together_is_a_change() {
+        // ---
+        let issued = client("198.51.100.4", Some(FIREFOX));
+
+        assert_eq!(client("198.51.7.9", Some(CURL)).change_from(&issued), None);
+        assert_eq!(
+            client("203.0.113.9", Some(FIREFOX)).change_from(&issued),
+            None
+        );
+        assert_eq!(
+            client("203.0.113.9", Some(CURL)).change_from(&issued),
+            Some(FingerprintChange {
+                from_network: "198.51.0.0/16".into(),
+                to_network: "203.0.0.0/16".into(),
+            })
+        );
+
+        // Unknown parts never count as different
+        assert_eq!(client("203.0.113.9", None).change_from(&issued), None);
+        let unknown = ClientFingerprint::new(None, Some(FIREFOX));
+        assert_eq!(
+            client("203.0.113.9", Some(CURL)).change_from(&unknown),
+            None
+        );
+    }
+}
@@ -0,0 +1,79 @@
+//! Session types shared by the Redis and signed token backends.
+
+use super::fingerprint::ClientFingerprint;
+use crate::tenant::Tenant;
+use axum::http::{header, HeaderMap};
+use chrono::{DateTime, Utc};
+use std::net::IpAddr;
+use uuid::Uuid;
+
+/// Validated session information extracted from a session token.
+///
+/// This struct is returned after successful session token validation
+/// and contains the authenticated user's details.
+#[derive(Debug, Clone)]
+pub struct SessionInfo {
+    // ---
+    pub user_id: Uuid,
+    pub username: String,
+
+    /// Tenant the session was created in. It is only accepted on hosts
+    /// served as that tenant.
+    pub tenant_id: String,
+
+    /// Address the session was created from, if it was known.
+    pub client_ip: Option<IpAddr>,
+
+    /// Digest of the user agent the session was created by, without
+    /// version numbers, if it sent one.
+    pub user_agent_digest: Option<String>,
+
+    /// When the token stops being accepted, unless revoked first.
+    pub expires_at: DateTime<Utc>,
+}
+
+impl SessionInfo {
+    // ---
+    /// The client the session was created by.
+    pub(crate) fn issued_to(&self) -> ClientFingerprint {
+        // ---
+        ClientFingerprint {
+            ip: self.client_ip,
+            agent: self.user_agent_digest.clone(),
+        }
+    }
+
+    /// Whether the session was created in `tenant`.
+    pub(crate) fn belongs_to(&self, tenant: &Tenant) -> bool {
+        // ---
+        self.tenant_id == tenant.id()
+    }
+}
+
+// ---
+
+/// Session token time-to-live in seconds (7 days).
+pub(crate) const SESSION_TTL_SECONDS: i64 = 604_800;
+
+// ---
+
+/// Cookie carrying the session token for browser (cookie-authenticated)
+/// sessions. Requests authenticated this way are subject to CSRF checks;
+/// `Authorization: Bearer` requests are not.
+pub const SESSION_COOKIE: &str = "axum_session";
+
+// ---
+
+/// Returns the session token from the [`SESSION_COOKIE`] cookie, if present.
+pub(crate) fn session_cookie(headers: &HeaderMap) -> Option<&str> {
+    // ---
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(name, _)| *name == SESSION_COOKIE)
+        .map(|(_, token)| token)
+        .filter(|token| !token.is_empty())
+}
@@ -0,0 +1,105 @@
+//! Session backend selected by [`SessionConfig`].
+
+use super::fingerprint::ClientFingerprint;
+use super::info::SessionInfo;
+use super::redis_store::{create_session_for, delete_session, validate_session};
+use super::signed::{
+    is_signed_token, revoke_signed_session, validate_signed_session, SessionSigner,
+};
+use crate::config::{FingerprintPolicy, SessionConfig, SessionMode};
+use crate::redact;
+use crate::redis_source::RedisConnection;
+use axum::http::StatusCode;
+use uuid::Uuid;
+
+/// Creates, validates, and revokes session tokens for the configured mode.
+///
+/// In signed mode, opaque tokens issued before the switch are still
+/// validated against Redis until they expire, so changing modes does not
+/// sign everyone out.
+#[derive(Clone, Default)]
+pub(crate) struct SessionManager {
+    // ---
+    signer: Option<SessionSigner>,
+    fingerprint: FingerprintPolicy,
+}
+
+impl SessionManager {
+    // ---
+
+    pub fn from_config(config: &SessionConfig) -> Self {
+        // ---
+        let signer = match (config.mode, config.signing_key.as_deref()) {
+            (SessionMode::Signed, Some(key)) => Some(SessionSigner::new(key.as_bytes())),
+            _ => None,
+        };
+        Self {
+            signer,
+            fingerprint: config.fingerprint,
+        }
+    }
+
+    /// How tokens used by a very different client are handled.
+    pub fn fingerprint_policy(&self) -> FingerprintPolicy {
+        // ---
+        self.fingerprint
+    }
+
+    /// Issues a session token for a freshly authenticated user of
+    /// `tenant_id`, recording the client's fingerprint.
+    ///
+    /// Signed tokens are created without touching Redis.
+    pub async fn create(
+        &self,
+        redis_conn: &mut RedisConnection,
+        user_id: Uuid,
+        username: String,
+        tenant_id: &str,
+        client: &ClientFingerprint,
+    ) -> Result<String, StatusCode> {
+        // ---
+        match &self.signer {
+            Some(signer) => {
+                tracing::info!(
+                    "Created signed session for user: {}",
+                    redact::username(&username)
+                );
+                Ok(signer.issue(user_id, username, tenant_id, client))
+            }
+            None => create_session_for(redis_conn, tenant_id, user_id, username, client).await,
+        }
+    }
+
+    /// Validates a session token and returns the authenticated user, who is
+    /// also noted for the request's access log.
+    pub async fn validate(
+        &self,
+        redis_conn: &mut RedisConnection,
+        token: &str,
+    ) -> Result<SessionInfo, StatusCode> {
+        // ---
+        let session = match &self.signer {
+            Some(signer) if is_signed_token(token) => {
+                validate_signed_session(redis_conn, signer, token).await
+            }
+            _ => validate_session(redis_conn, token).await,
+        }?;
+        crate::middleware::record_access_user(session.user_id);
+        Ok(session)
+    }
+
+    /// Ends a session so its token is no longer accepted.
+    pub async fn revoke(
+        &self,
+        redis_conn: &mut RedisConnection,
+        token: &str,
+    ) -> Result<(), StatusCode> {
+        // ---
+        match &self.signer {
+            Some(signer) if is_signed_token(token) => {
+                revoke_signed_session(redis_conn, signer, token).await
+            }
+            _ => delete_session(redis_conn, token).await,
+        }
+    }
+}
@@ -0,0 +1,16 @@
+// Gateway module - session tokens (Redis-backed or signed)
+// Modules are private, only exported symbols are public
+
+mod fingerprint;
+mod info;
+mod manager;
+mod redis_store;
+mod signed;
+
+pub(crate) use fingerprint::{ClientFingerprint, FingerprintChange};
+pub(crate) use info::{session_cookie, SESSION_TTL_SECONDS};
+pub use info::{SessionInfo, SESSION_COOKIE};
+pub(crate) use manager::SessionManager;
+#[cfg(feature = "test-utils")]
+pub(crate) use redis_store::create_session_for;
+pub use redis_store::{create_session, validate_session};
@@ -0,0 +1,295 @@
+//! Redis-backed sessions with opaque tokens.
+//!
+//! Provides session token generation and storage in Redis with configurable TTL.
+
+use super::fingerprint::ClientFingerprint;
+use super::info::{SessionInfo, SESSION_TTL_SECONDS};
+use crate::domain::DEFAULT_TENANT;
+use crate::redact;
+use crate::redis_keys;
+use axum::http::StatusCode;
+use chrono::DateTime;
+use redis::aio::ConnectionLike;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::net::IpAddr;
+use uuid::Uuid;
+
+// ---
+
+/// Session data stored in Redis.
+#[derive(Debug, Serialize)]
+struct SessionData {
+    //
+    user_id: String,
+    username: String,
+    tenant_id: String,
+    expires_at: i64,
+
+    // Absent in sessions created before client IPs were recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    client_ip: Option<IpAddr>,
+
+    // Digest of the user agent; absent in older sessions too
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    agent: Option<String>,
+}
+
+/// [`SessionData`] as read back. Strings without escapes are borrowed from
+/// the stored JSON, and the user ID is parsed in place.
+#[derive(Deserialize)]
+struct StoredSession<'a> {
+    // ---
+    user_id: Uuid,
+
+    #[serde(borrow)]
+    username: Cow<'a, str>,
+
+    /// Absent in sessions created before tenants were recorded, which
+    /// were all in the default tenant.
+    #[serde(default, borrow)]
+    tenant_id: Option<Cow<'a, str>>,
+
+    expires_at: i64,
+
+    #[serde(default)]
+    client_ip: Option<IpAddr>,
+
+    #[serde(default, borrow)]
+    agent: Option<Cow<'a, str>>,
+}
+
+// ---
+
+/// Creates a new session token in the default tenant and stores it in
+/// Redis.
+///
+/// # Arguments
+/// * `redis_conn` - Active Redis connection
+/// * `user_id` - User's unique identifier
+/// * `username` - User's username
+/// * `client_ip` - Address the user signed in from, recorded as session metadata
+///
+/// # Returns
+/// Session token (UUID) on success, or HTTP status code on failure
+pub async fn create_session(
+    redis_conn: &mut (impl ConnectionLike + Send + Sync),
+    user_id: Uuid,
+    username: String,
+    client_ip: Option<IpAddr>,
+) -> Result<String, StatusCode> {
+    // ---
+    let client = ClientFingerprint::new(client_ip, None);
+    create_session_for(redis_conn, DEFAULT_TENANT, user_id, username, &client).await
+}
+
+/// [`create_session`] in `tenant_id`, recording the full fingerprint of
+/// the client.
+pub(crate) async fn create_session_for(
+    redis_conn: &mut (impl ConnectionLike + Send + Sync),
+    tenant_id: &str,
+    user_id: Uuid,
+    username: String,
+    client: &ClientFingerprint,
+) -> Result<String, StatusCode> {
+    //
+    let token = Uuid::new_v4().to_string();
+    let expires_at = chrono::Utc::now().timestamp() + SESSION_TTL_SECONDS;
+
+    let session_data = SessionData {
+        //
+        user_id: user_id.to_string(),
+        username: username.clone(),
+        tenant_id: tenant_id.to_string(),
+        expires_at,
+        client_ip: client.ip,
+        agent: client.agent.clone(),
+    };
+
+    let session_json = serde_json::to_string(&session_data).map_err(|e| {
+        //
+        tracing::error!("Failed to serialize session data: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let redis_key = redis_keys::session(&token);
+
+    redis_conn
+        .set_ex::<_, _, ()>(&redis_key, session_json, SESSION_TTL_SECONDS as u64)
+        .await
+        .map_err(|e| {
+            //
+            tracing::error!("Failed to store session in Redis: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    tracing::info!("Created session for user: {}", redact::username(&username));
+
+    Ok(token)
+}
+
+// ---
+
+/// Validates a session token and returns the authenticated user's information.
+///
+/// Extracts the session token from Redis and verifies it hasn't expired.
+///
+/// # Security
+///
+/// - Validates token exists in Redis (stateful session management)
+/// - Checks expiration timestamp
+/// - Returns user_id for authorization checks
+///
+/// # Arguments
+/// * `redis_conn` - Active Redis connection
+/// * `token` - Session token (typically from Authorization header)
+///
+/// # Returns
+/// SessionInfo on success, or HTTP status code on failure
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - Token is not found in Redis (expired or invalid)
+/// - Session data cannot be deserialized
+/// - Session has expired
+pub async fn validate_session(
+    redis_conn: &mut (impl ConnectionLike + Send + Sync),
+    token: &str,
+) -> Result<SessionInfo, StatusCode> {
+    // ---
+    // Runs on every authenticated request: the key is built on the stack,
+    // the stored JSON is read as bytes, and only the fields SessionInfo
+    // keeps are copied out of it
+    let redis_key = redis_keys::session_key(token);
+
+    // Fetch session data from Redis
+    let session_json: Option<Vec<u8>> = redis_conn
+        .get(redis_key.as_str().as_bytes())
+        .await
+        .map_err(|e| {
+            // ---
+            tracing::error!("Failed to query Redis for session: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let session_json = session_json.ok_or_else(|| {
+        // ---
+        tracing::debug!(
+            "Session token not found or expired: {}",
+            redact::secret(token)
+        );
+        StatusCode::UNAUTHORIZED
+    })?;
+
+    parse_session(&session_json, chrono::Utc::now().timestamp())
+}
+
+/// Checks stored session JSON against the time `now` and returns the
+/// session it describes.
+fn parse_session(session_json: &[u8], now: i64) -> Result<SessionInfo, StatusCode> {
+    // ---
+    let session_data: StoredSession<'_> = serde_json::from_slice(session_json).map_err(|e| {
+        // ---
+        tracing::error!("Failed to deserialize session data: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    // Check if session has expired
+    if session_data.expires_at < now {
+        // ---
+        tracing::debug!(
+            "Session expired for user: {}",
+            redact::username(&session_data.username)
+        );
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+
+    let expires_at = DateTime::from_timestamp(session_data.expires_at, 0).ok_or_else(|| {
+        // ---
+        tracing::error!(
+            "Invalid expiry in session data: {}",
+            session_data.expires_at
+        );
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(SessionInfo {
+        user_id: session_data.user_id,
+        username: session_data.username.into_owned(),
+        tenant_id: session_data
+            .tenant_id
+            .map_or_else(|| DEFAULT_TENANT.to_string(), Cow::into_owned),
+        client_ip: session_data.client_ip,
+        user_agent_digest: session_data.agent.map(Cow::into_owned),
+        expires_at,
+    })
+}
+
+// ---
+
+/// Deletes a session from Redis, ending it immediately.
+///
+/// Deleting a session that does not exist is not an error.
+pub(crate) async fn delete_session(
+    redis_conn: &mut (impl ConnectionLike + Send + Sync),
+    token: &str,
+) -> Result<(), StatusCode> {
+    // ---
+    redis_conn
+        .del::<_, ()>(redis_keys::session(token))
+        .await
+        .map_err(|e| {
+            // ---
+            tracing::error!("Failed to delete session from Redis: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+
+    #[test]
+    fn stored_sessions_parse_with_and_without_escapes() {
+        // ---
+        let user_id = Uuid::new_v4();
+        let stored = serde_json::to_vec(&SessionData {
+            user_id: user_id.to_string(),
+            username: "al\"ice".to_string(),
+            tenant_id: "acme".to_string(),
+            expires_at: 2_000,
+            client_ip: Some("198.51.100.4".parse().unwrap()),
+            agent: Some("firefox/linux".to_string()),
+        })
+        .unwrap();
+
+        let session = parse_session(&stored, 1_000).unwrap();
+        assert_eq!(session.user_id, user_id);
+        assert_eq!(session.username, "al\"ice");
+        assert_eq!(session.tenant_id, "acme");
+        assert_eq!(session.user_agent_digest.as_deref(), Some("firefox/linux"));
+        assert_eq!(session.expires_at.timestamp(), 2_000);
+
+        // Sessions from before client IPs and agents were recorded
+        let old = format!(r#"{{"user_id":"{user_id}","username":"bob","expires_at":2000}}"#);
+        let session = parse_session(old.as_bytes(), 1_000).unwrap();
+        assert_eq!(session.client_ip, None);
+        assert_eq!(session.tenant_id, DEFAULT_TENANT);
+
+        assert_eq!(
+            parse_session(&stored, 3_000).unwrap_err(),
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            parse_session(
+                br#"{"user_id":"nope","username":"bob","expires_at":2000}"#,
+                0
+            )
+            .unwrap_err(),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+    }
+}
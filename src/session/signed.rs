@@ -0,0 +1,277 @@
+//! Signed, self-contained session tokens.
+//!
+//! Tokens are compact HS256 JWTs carrying the user ID, username, tenant,
+//! client IP, and a unique `jti`. Validation checks the signature and expiry locally;
+//! Redis is only consulted for a denylist of revoked `jti`s, a single
+//! `EXISTS` on a key that is usually absent.
+
+use super::fingerprint::ClientFingerprint;
+use super::info::{SessionInfo, SESSION_TTL_SECONDS};
+use crate::domain::DEFAULT_TENANT;
+use crate::redis_keys;
+use crate::redis_source::RedisConnection;
+use axum::http::StatusCode;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::net::IpAddr;
+use std::sync::Arc;
+use uuid::Uuid;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Only algorithm issued or accepted.
+const ALGORITHM: &str = "HS256";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Header {
+    // ---
+    alg: String,
+    typ: String,
+}
+
+/// Token payload. Field names follow the registered JWT claims.
+#[derive(Debug, Serialize, Deserialize)]
+struct Claims {
+    // ---
+    sub: Uuid,
+    name: String,
+    jti: Uuid,
+    iat: i64,
+    exp: i64,
+
+    /// Tenant ID; absent in tokens issued before tenants were recorded,
+    /// which were all in the default tenant.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tid: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ip: Option<IpAddr>,
+
+    /// Digest of the user agent (see [`ClientFingerprint`]).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ua: Option<String>,
+}
+
+/// Issues and verifies signed session tokens with a shared HMAC key.
+#[derive(Clone)]
+pub(crate) struct SessionSigner {
+    // ---
+    key: Arc<[u8]>,
+}
+
+impl SessionSigner {
+    // ---
+
+    pub fn new(key: &[u8]) -> Self {
+        // ---
+        Self { key: key.into() }
+    }
+
+    fn mac(&self) -> HmacSha256 {
+        // ---
+        HmacSha256::new_from_slice(&self.key).expect("HMAC accepts keys of any length")
+    }
+
+    /// Issues a token valid for the session TTL.
+    pub fn issue(
+        &self,
+        user_id: Uuid,
+        username: String,
+        tenant_id: &str,
+        client: &ClientFingerprint,
+    ) -> String {
+        // ---
+        let iat = chrono::Utc::now().timestamp();
+        let claims = Claims {
+            sub: user_id,
+            name: username,
+            jti: Uuid::new_v4(),
+            iat,
+            exp: iat + SESSION_TTL_SECONDS,
+            tid: Some(tenant_id.to_string()),
+            ip: client.ip,
+            ua: client.agent.clone(),
+        };
+        let header = Header {
+            alg: ALGORITHM.to_string(),
+            typ: "JWT".to_string(),
+        };
+
+        // Serializing these plain structs cannot fail
+        let signing_input = format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(serde_json::to_vec(&header).unwrap_or_default()),
+            URL_SAFE_NO_PAD.encode(serde_json::to_vec(&claims).unwrap_or_default()),
+        );
+
+        let mut mac = self.mac();
+        mac.update(signing_input.as_bytes());
+        let signature = URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes());
+
+        format!("{signing_input}.{signature}")
+    }
+
+    /// Returns the claims if the signature is valid and the token unexpired.
+    fn verify(&self, token: &str) -> Option<Claims> {
+        // ---
+        let (signing_input, signature) = token.rsplit_once('.')?;
+        let (header, claims) = signing_input.split_once('.')?;
+
+        let mut mac = self.mac();
+        mac.update(signing_input.as_bytes());
+        mac.verify_slice(&URL_SAFE_NO_PAD.decode(signature).ok()?)
+            .ok()?;
+
+        let header: Header = serde_json::from_slice(&URL_SAFE_NO_PAD.decode(header).ok()?).ok()?;
+        if header.alg != ALGORITHM {
+            return None;
+        }
+
+        let claims: Claims = serde_json::from_slice(&URL_SAFE_NO_PAD.decode(claims).ok()?).ok()?;
+        (claims.exp >= chrono::Utc::now().timestamp()).then_some(claims)
+    }
+}
+
+/// Returns true if `token` has the shape of a signed token rather than an
+/// opaque Redis session ID.
+pub(crate) fn is_signed_token(token: &str) -> bool {
+    // ---
+    token.bytes().filter(|b| *b == b'.').count() == 2
+}
+
+/// Validates a signed token and checks it has not been revoked.
+///
+/// # Errors
+///
+/// Returns UNAUTHORIZED if the token is malformed, forged, expired, or
+/// revoked, and INTERNAL_SERVER_ERROR if the denylist cannot be read.
+pub(crate) async fn validate_signed_session(
+    redis_conn: &mut RedisConnection,
+    signer: &SessionSigner,
+    token: &str,
+) -> Result<SessionInfo, StatusCode> {
+    // ---
+    let claims = signer.verify(token).ok_or_else(|| {
+        // ---
+        tracing::debug!("Signed session token invalid or expired");
+        StatusCode::UNAUTHORIZED
+    })?;
+    let expires_at = chrono::DateTime::from_timestamp(claims.exp, 0).ok_or_else(|| {
+        // ---
+        tracing::debug!("Signed session token expiry out of range");
+        StatusCode::UNAUTHORIZED
+    })?;
+
+    let revoked: bool = redis_conn
+        .exists(redis_keys::revoked_session(claims.jti))
+        .await
+        .map_err(|e| {
+            // ---
+            tracing::error!("Failed to query session denylist: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    if revoked {
+        tracing::debug!("Signed session revoked for user: {}", claims.name);
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+
+    Ok(SessionInfo {
+        user_id: claims.sub,
+        username: claims.name,
+        tenant_id: claims.tid.unwrap_or_else(|| DEFAULT_TENANT.to_string()),
+        client_ip: claims.ip,
+        user_agent_digest: claims.ua,
+        expires_at,
+    })
+}
+
+/// Revokes a signed token by denylisting its `jti` until it would expire.
+///
+/// Invalid or already expired tokens need no denylist entry and are ignored.
+pub(crate) async fn revoke_signed_session(
+    redis_conn: &mut RedisConnection,
+    signer: &SessionSigner,
+    token: &str,
+) -> Result<(), StatusCode> {
+    // ---
+    let Some(claims) = signer.verify(token) else {
+        return Ok(());
+    };
+
+    let remaining = (claims.exp - chrono::Utc::now().timestamp()).max(1) as u64;
+    redis_conn
+        .set_ex::<_, _, ()>(redis_keys::revoked_session(claims.jti), 1, remaining)
+        .await
+        .map_err(|e| {
+            // ---
+            tracing::error!("Failed to denylist session: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+
+    #[test]
+    fn issued_tokens_verify_with_same_key_only() {
+        // ---
+        let signer = SessionSigner::new(b"0123456789abcdef0123456789abcdef");
+        let user_id = Uuid::new_v4();
+        let ip: IpAddr = "198.51.100.4".parse().unwrap();
+
+        let client = ClientFingerprint::new(Some(ip), Some("curl/8.5.0"));
+        let token = signer.issue(user_id, "alice".to_string(), "acme", &client);
+        assert!(is_signed_token(&token));
+
+        let claims = signer.verify(&token).expect("token should verify");
+        assert_eq!(claims.sub, user_id);
+        assert_eq!(claims.name, "alice");
+        assert_eq!(claims.tid.as_deref(), Some("acme"));
+        assert_eq!(claims.ip, Some(ip));
+        assert_eq!(claims.ua, client.agent);
+        assert_eq!(claims.exp - claims.iat, SESSION_TTL_SECONDS);
+
+        let other = SessionSigner::new(b"fedcba9876543210fedcba9876543210");
+        assert!(other.verify(&token).is_none());
+    }
+
+    #[test]
+    fn tampered_tokens_are_rejected() {
+        // ---
+        let signer = SessionSigner::new(b"0123456789abcdef0123456789abcdef");
+        let token = signer.issue(
+            Uuid::new_v4(),
+            "alice".to_string(),
+            DEFAULT_TENANT,
+            &ClientFingerprint::default(),
+        );
+        let (header, rest) = token.split_once('.').unwrap();
+        let (_, signature) = rest.split_once('.').unwrap();
+
+        // Swap in claims for another user, keeping the original signature
+        let forged_claims = URL_SAFE_NO_PAD.encode(
+            serde_json::json!({
+                "sub": Uuid::new_v4(), "name": "mallory", "jti": Uuid::new_v4(),
+                "iat": 0, "exp": i64::MAX,
+            })
+            .to_string(),
+        );
+        assert!(signer
+            .verify(&format!("{header}.{forged_claims}.{signature}"))
+            .is_none());
+
+        // Unsigned "alg: none" token
+        let none_header = URL_SAFE_NO_PAD.encode(r#"{"alg":"none","typ":"JWT"}"#);
+        assert!(signer
+            .verify(&format!("{none_header}.{forged_claims}."))
+            .is_none());
+
+        assert!(!is_signed_token(&Uuid::new_v4().to_string()));
+    }
+}
@@ -0,0 +1,103 @@
+//! Shutdown notification for long-lived connections.
+//!
+//! `axum::serve(..).with_graceful_shutdown(..)` waits for in-flight HTTP
+//! requests, but upgraded connections (WebSockets) are detached from the
+//! server and would simply be dropped when the process exits. Handlers
+//! holding such connections subscribe to a [`ShutdownSignal`] so they can
+//! close cleanly, and `main` drains them before returning.
+
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+
+/// Broadcasts "shutting down" to long-lived connection tasks.
+///
+/// Cheap to clone; all clones share the same signal.
+#[derive(Clone)]
+pub struct ShutdownSignal {
+    // ---
+    sender: Arc<watch::Sender<bool>>,
+}
+
+impl ShutdownSignal {
+    // ---
+    /// Creates a signal that has not been triggered.
+    pub fn new() -> Self {
+        // ---
+        let (sender, _) = watch::channel(false);
+        Self {
+            sender: Arc::new(sender),
+        }
+    }
+
+    /// Notifies every subscriber that the server is shutting down.
+    pub fn trigger(&self) {
+        // ---
+        self.sender.send_replace(true);
+    }
+
+    /// Whether [`trigger`](Self::trigger) has been called.
+    pub fn is_triggered(&self) -> bool {
+        // ---
+        *self.sender.borrow()
+    }
+
+    /// Completes once the signal is triggered (immediately if it already was).
+    pub fn triggered(&self) -> impl std::future::Future<Output = ()> + Send + 'static {
+        // ---
+        let mut receiver = self.sender.subscribe();
+        async move {
+            // Err means the sender is gone, which also means shutdown
+            let _ = receiver.wait_for(|&shutting_down| shutting_down).await;
+        }
+    }
+
+    /// Waits until every [`triggered`](Self::triggered) future has been
+    /// dropped, i.e. all subscribed connections have finished, or until
+    /// `timeout` elapses. Returns `false` on timeout.
+    pub async fn drain(&self, timeout: Duration) -> bool {
+        // ---
+        tokio::time::timeout(timeout, self.sender.closed())
+            .await
+            .is_ok()
+    }
+}
+
+impl Default for ShutdownSignal {
+    // ---
+    fn default() -> Self {
+        // ---
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+
+    #[tokio::test]
+    async fn subscribers_see_trigger_and_drain_completes() {
+        // ---
+        let signal = ShutdownSignal::new();
+        let waiter = tokio::spawn(signal.triggered());
+
+        assert!(!signal.is_triggered());
+        signal.trigger();
+
+        waiter.await.unwrap();
+        assert!(signal.drain(Duration::from_secs(1)).await);
+
+        // Subscribing after the trigger resolves immediately
+        signal.triggered().await;
+    }
+
+    #[tokio::test]
+    async fn drain_times_out_while_subscribers_remain() {
+        // ---
+        let signal = ShutdownSignal::new();
+        let _pending = signal.triggered();
+
+        assert!(!signal.drain(Duration::from_millis(10)).await);
+    }
+}
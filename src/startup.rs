@@ -0,0 +1,238 @@
+//! Waiting for dependencies, and warming them up, before the server starts.
+//!
+//! Containers and orchestrators rarely start the database and Redis before
+//! the service that needs them. [`wait_for_dependencies`] probes both
+//! concurrently, each with its own [`WaitStrategy`], and the whole phase is
+//! bounded by `StartupConfig::wait_timeout` (or `--wait-timeout`).
+//!
+//! With `AXUM_WARMUP`, [`warm_up`] then opens the database pool's minimum
+//! connections and prepares the sign-in queries on them, so the first
+//! requests after a deploy are not slower than the rest.
+//!
+//! Before either, [`log_startup_banner`] logs what is being started.
+
+use crate::config::{AppConfig, WaitStrategy};
+use crate::domain::{RepositoryPtr, DEFAULT_TENANT};
+use crate::infrastructure::probe_database;
+use crate::redis_source::verify_redis;
+use anyhow::{anyhow, Result};
+use std::future::Future;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// Delay before the first retry; doubles on each further retry.
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(1);
+
+/// Retry delays stop doubling at `RETRY_BASE_DELAY` times this.
+const RETRY_MAX_FACTOR: u32 = 8;
+
+/// Waits until the database and Redis answer, as configured in
+/// `config.startup`.
+///
+/// # Errors
+/// Returns an error if a dependency is still unreachable when its strategy
+/// gives up, or the wait timeout passes first.
+pub async fn wait_for_dependencies(config: &AppConfig) -> Result<()> {
+    // ---
+    let startup = &config.startup;
+    let database = wait_for("database", startup.database, RETRY_BASE_DELAY, || {
+        probe_database(&config.database)
+    });
+    let redis = wait_for("Redis", startup.redis, RETRY_BASE_DELAY, || {
+        verify_redis(&config.redis)
+    });
+    let both = async {
+        let (database, redis) = tokio::join!(database, redis);
+        database.and(redis)
+    };
+
+    match startup.wait_timeout {
+        Some(limit) => tokio::time::timeout(limit, both).await.map_err(|_| {
+            anyhow!(
+                "Dependencies not ready within the {}s wait timeout",
+                limit.as_secs()
+            )
+        })?,
+        None => both.await,
+    }
+}
+
+/// Logs one `startup` event with the version, the compiled-in features,
+/// the repository and metrics backends, the listener address, and the
+/// effective configuration (its `Debug` form, with secrets redacted), so a
+/// deployment can be diagnosed from its first log line.
+pub fn log_startup_banner(config: &AppConfig, metrics: &str, listen_addr: &str) {
+    // ---
+    tracing::info!(
+        target: "startup",
+        version = env!("CARGO_PKG_VERSION"),
+        features = ?enabled_features(),
+        repository = ?config.database.repository_type,
+        metrics,
+        listen_addr,
+        tls = config.tls.is_enabled(),
+        config = ?config,
+        "Starting axum-quickstart"
+    );
+}
+
+/// The cargo features this binary was built with.
+fn enabled_features() -> Vec<&'static str> {
+    // ---
+    [
+        ("sqlite", cfg!(feature = "sqlite")),
+        ("pprof", cfg!(feature = "pprof")),
+        ("test-utils", cfg!(feature = "test-utils")),
+        ("fuzzing", cfg!(feature = "fuzzing")),
+        ("chaos", cfg!(feature = "chaos")),
+        ("grpc", cfg!(feature = "grpc")),
+        ("tls", cfg!(feature = "tls")),
+        ("password", cfg!(feature = "password")),
+        ("kafka", cfg!(feature = "kafka")),
+        ("nats", cfg!(feature = "nats")),
+        ("lambda", cfg!(feature = "lambda")),
+    ]
+    .into_iter()
+    .filter_map(|(feature, enabled)| enabled.then_some(feature))
+    .collect()
+}
+
+/// Warms up `repository` and Redis if `config.startup.warmup` is set.
+/// Failures are logged, not returned: warming up is an optimization, and
+/// the dependencies already answered [`wait_for_dependencies`].
+pub async fn warm_up(config: &AppConfig, repository: &RepositoryPtr) {
+    // ---
+    if !config.startup.warmup {
+        return;
+    }
+    let start = Instant::now();
+    let connections = config.database.min_connections.max(1);
+
+    let (database, redis) = tokio::join!(
+        warm_up_repository(repository, connections),
+        verify_redis(&config.redis),
+    );
+    if let Err(e) = database {
+        tracing::warn!("Database warm-up failed: {e:#}");
+    }
+    if let Err(e) = redis {
+        tracing::warn!("Redis warm-up failed: {e:#}");
+    }
+    tracing::info!(
+        "Warm-up finished in {}ms ({connections} database connections)",
+        start.elapsed().as_millis()
+    );
+}
+
+/// Runs the queries of a sign-in `connections` times concurrently. Each
+/// concurrent round needs a connection of its own, so the pool opens that
+/// many, and each connection prepares (and caches) the statements it runs.
+/// The lookups are for a user and credentials that do not exist.
+pub(crate) async fn warm_up_repository(repository: &RepositoryPtr, connections: u32) -> Result<()> {
+    // ---
+    let round = || async {
+        repository.ping().await?;
+        repository
+            .get_user_by_username_in(DEFAULT_TENANT, "")
+            .await?;
+        repository.get_credentials_by_user(Uuid::nil()).await?;
+        repository.get_credential_by_id(&[]).await?;
+        Ok::<_, anyhow::Error>(())
+    };
+    futures::future::try_join_all((0..connections).map(|_| round())).await?;
+    Ok(())
+}
+
+/// Runs `probe` until it succeeds or `strategy` gives up, backing off
+/// exponentially from `base_delay` between attempts.
+async fn wait_for<F, Fut>(
+    name: &str,
+    strategy: WaitStrategy,
+    base_delay: Duration,
+    probe: F,
+) -> Result<()>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<()>>,
+{
+    // ---
+    let attempts = match strategy {
+        WaitStrategy::FailFast => Some(1),
+        WaitStrategy::Retry(attempts) => Some(attempts),
+        WaitStrategy::Forever => None,
+    };
+
+    let mut attempt: u32 = 1;
+    loop {
+        let err = match probe().await {
+            Ok(()) => {
+                if attempt > 1 {
+                    tracing::info!("{name} ready after {attempt} attempts");
+                }
+                return Ok(());
+            }
+            Err(err) => err,
+        };
+        if attempts.is_some_and(|attempts| attempt >= attempts) {
+            return Err(err.context(format!("{name} not ready after {attempt} attempt(s)")));
+        }
+
+        let delay = base_delay * 2u32.saturating_pow(attempt - 1).min(RETRY_MAX_FACTOR);
+        let of = attempts.map_or_else(String::new, |attempts| format!("/{attempts}"));
+        tracing::warn!("{name} not ready (attempt {attempt}{of}): {err:#}; retrying in {delay:?}");
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// A probe that fails until its `ready_at`-th call.
+    fn flaky(calls: &AtomicU32, ready_at: u32) -> impl Fn() -> std::future::Ready<Result<()>> + '_ {
+        // ---
+        move || {
+            let call = calls.fetch_add(1, Ordering::SeqCst) + 1;
+            std::future::ready(match call >= ready_at {
+                true => Ok(()),
+                false => Err(anyhow!("connection refused")),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn strategies_bound_the_attempts() {
+        // ---
+        let delay = Duration::from_millis(1);
+
+        let calls = AtomicU32::new(0);
+        let err = wait_for("db", WaitStrategy::FailFast, delay, flaky(&calls, 2))
+            .await
+            .unwrap_err();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert!(format!("{err:#}").contains("db not ready after 1 attempt(s)"));
+
+        let calls = AtomicU32::new(0);
+        assert!(
+            wait_for("db", WaitStrategy::Retry(3), delay, flaky(&calls, 4))
+                .await
+                .is_err()
+        );
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        let calls = AtomicU32::new(0);
+        wait_for("db", WaitStrategy::Retry(3), delay, flaky(&calls, 3))
+            .await
+            .unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        let calls = AtomicU32::new(0);
+        wait_for("db", WaitStrategy::Forever, delay, flaky(&calls, 6))
+            .await
+            .unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 6);
+    }
+}
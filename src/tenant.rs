@@ -0,0 +1,213 @@
+//! Multi-tenancy: one WebAuthn relying party per tenant.
+//!
+//! Each tenant listed in `AXUM_TENANTS` has its own RP ID and origins, and
+//! its own users: usernames are unique per tenant, and credentials belong
+//! to a tenant through their user. A request is served as the tenant that
+//! claims its host, taken from [`ExternalOrigin`] so that `X-Forwarded-Host`
+//! from trusted proxies is honoured. Every other host is served as the
+//! default tenant, configured by the base `AXUM_WEBAUTHN_*` settings.
+//!
+//! A configured host without a port matches that host on any port.
+//!
+//! Sessions are bound to the tenant they were created in: a session token
+//! presented on a host served as another tenant is rejected as invalid.
+
+use crate::app_state::AppState;
+use crate::client_ip::ExternalOrigin;
+use crate::config::TenantConfig;
+use crate::domain::DEFAULT_TENANT;
+use crate::infrastructure::create_webauthn;
+use anyhow::Result;
+use axum::{
+    extract::{FromRequestParts, OptionalFromRequestParts},
+    http::{request::Parts, uri::Authority},
+};
+use std::convert::Infallible;
+use std::sync::Arc;
+use webauthn_rs::prelude::Url;
+use webauthn_rs::Webauthn;
+
+/// The tenant a request is served as.
+///
+/// Extracting it never fails; requests for unclaimed hosts get the default
+/// tenant.
+#[derive(Clone)]
+pub(crate) struct Tenant {
+    // ---
+    id: Arc<str>,
+    webauthn: Arc<Webauthn>,
+}
+
+impl Tenant {
+    // ---
+
+    /// Tenant ID, as stored with each user.
+    pub(crate) fn id(&self) -> &str {
+        // ---
+        &self.id
+    }
+
+    /// The tenant's WebAuthn relying party.
+    pub(crate) fn webauthn(&self) -> &Webauthn {
+        // ---
+        &self.webauthn
+    }
+
+    /// Whether passkey ceremonies run from `origin` (`scheme://host[:port]`)
+    /// can succeed, i.e. it is one of the tenant's WebAuthn origins.
+    pub(crate) fn allows_origin(&self, origin: &str) -> bool {
+        // ---
+        let Ok(origin) = Url::parse(origin) else {
+            return false;
+        };
+        self.webauthn
+            .get_allowed_origins()
+            .iter()
+            .any(|allowed| allowed.origin() == origin.origin())
+    }
+}
+
+impl FromRequestParts<AppState> for Tenant {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Infallible> {
+        // ---
+        let origin = <ExternalOrigin as OptionalFromRequestParts<AppState>>::from_request_parts(
+            parts, state,
+        )
+        .await?;
+
+        Ok(state
+            .tenants()
+            .resolve(origin.as_ref().map(|o| o.host.as_str()))
+            .clone())
+    }
+}
+
+/// The default tenant and the configured ones, with the hosts each claims.
+pub(crate) struct TenantRegistry {
+    // ---
+    default: Tenant,
+    tenants: Vec<(Vec<String>, Tenant)>,
+}
+
+impl TenantRegistry {
+    // ---
+
+    /// Serves every host as the default tenant, using `webauthn`.
+    pub(crate) fn single(webauthn: Arc<Webauthn>) -> Self {
+        // ---
+        Self {
+            default: Tenant {
+                id: DEFAULT_TENANT.into(),
+                webauthn,
+            },
+            tenants: Vec::new(),
+        }
+    }
+
+    /// Adds a WebAuthn instance for each of `tenants` to the default tenant.
+    ///
+    /// # Errors
+    /// Returns an error if a tenant's WebAuthn configuration is invalid.
+    pub(crate) fn new(default: Arc<Webauthn>, tenants: &[TenantConfig]) -> Result<Self> {
+        // ---
+        let mut registry = Self::single(default);
+        for config in tenants {
+            let tenant = Tenant {
+                id: config.id.as_str().into(),
+                webauthn: Arc::new(create_webauthn(&config.webauthn)?),
+            };
+            registry.tenants.push((config.hosts.clone(), tenant));
+        }
+        Ok(registry)
+    }
+
+    /// The tenant that serves requests for `host`.
+    pub(crate) fn resolve(&self, host: Option<&str>) -> &Tenant {
+        // ---
+        let Some(host) = host.map(str::to_ascii_lowercase) else {
+            return &self.default;
+        };
+        self.tenants
+            .iter()
+            .find(|(hosts, _)| hosts.iter().any(|claimed| host_matches(claimed, &host)))
+            .map_or(&self.default, |(_, tenant)| tenant)
+    }
+}
+
+/// Whether `host` (lowercase, with an optional port) is `claimed`.
+fn host_matches(claimed: &str, host: &str) -> bool {
+    // ---
+    if claimed == host {
+        return true;
+    }
+    match claimed.parse::<Authority>() {
+        Ok(authority) if authority.port().is_none() => host
+            .parse::<Authority>()
+            .is_ok_and(|host| host.host() == authority.host()),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+    use crate::config::WebAuthnConfig;
+
+    fn webauthn_config(rp_id: &str, origin: &str) -> WebAuthnConfig {
+        // ---
+        WebAuthnConfig {
+            rp_id: rp_id.to_string(),
+            rp_name: rp_id.to_string(),
+            origin: origin.to_string(),
+            additional_origins: Vec::new(),
+        }
+    }
+
+    fn registry() -> TenantRegistry {
+        // ---
+        let default = create_webauthn(&webauthn_config("localhost", "http://localhost:3000"));
+        let tenants = [
+            TenantConfig {
+                id: "acme".to_string(),
+                hosts: vec!["acme.example".to_string()],
+                webauthn: webauthn_config("acme.example", "https://acme.example"),
+            },
+            TenantConfig {
+                id: "globex".to_string(),
+                hosts: vec!["globex.example:8443".to_string()],
+                webauthn: webauthn_config("globex.example", "https://globex.example:8443"),
+            },
+        ];
+        TenantRegistry::new(Arc::new(default.unwrap()), &tenants).unwrap()
+    }
+
+    #[test]
+    fn hosts_resolve_to_their_tenant() {
+        // ---
+        let registry = registry();
+        let id = |host| registry.resolve(host).id().to_string();
+
+        assert_eq!(id(Some("acme.example")), "acme");
+        assert_eq!(id(Some("ACME.example:8080")), "acme");
+        assert_eq!(id(Some("globex.example:8443")), "globex");
+        assert_eq!(id(Some("globex.example")), DEFAULT_TENANT);
+        assert_eq!(id(Some("evil.example")), DEFAULT_TENANT);
+        assert_eq!(id(None), DEFAULT_TENANT);
+    }
+
+    #[test]
+    fn origins_are_checked_per_tenant() {
+        // ---
+        let registry = registry();
+        let acme = registry.resolve(Some("acme.example"));
+
+        assert!(acme.allows_origin("https://acme.example"));
+        assert!(!acme.allows_origin("http://localhost:3000"));
+        assert!(registry
+            .resolve(None)
+            .allows_origin("http://localhost:3000"));
+    }
+}
@@ -0,0 +1,364 @@
+//! Known test data for integration tests.
+//!
+//! [`Fixtures`] inserts users, credentials, sessions, and movies through the
+//! same repository and Redis layout the server uses, remembers everything it
+//! inserted, and removes it again in [`Fixtures::reset`]. Values are
+//! deterministic: usernames are taken as given and credential IDs derive
+//! from the username, so a test can assert on them directly.
+//!
+//! Inserting a user first removes any existing user with that name, so a
+//! test that panicked before `reset` does not break the next run.
+
+use crate::application::{remove_movie, save_movie, Movie};
+use crate::domain::{Credential, RepositoryPtr, User, DEFAULT_TENANT};
+use crate::redis_keys;
+use crate::redis_source::RedisConnection;
+use crate::session::{create_session_for, ClientFingerprint};
+use anyhow::{anyhow, Context, Result};
+use redis::aio::MultiplexedConnection;
+use redis::AsyncCommands;
+use serde_json::json;
+use std::net::IpAddr;
+use uuid::Uuid;
+
+/// Inserts known data and cleans it up again.
+///
+/// ```no_run
+/// # async fn demo(repo: axum_quickstart::domain::RepositoryPtr,
+/// #               redis: redis::aio::MultiplexedConnection) -> anyhow::Result<()> {
+/// use axum_quickstart::test_utils::Fixtures;
+///
+/// let mut fixtures = Fixtures::new(repo, redis);
+/// let alice = fixtures.user("alice").insert().await?;
+/// let passkey = fixtures.credential(&alice).counter(7).insert().await?;
+/// let token = fixtures.session(&alice).insert().await?;
+/// let movie_id = fixtures.movie("Alien", 1979).stars(4.5).insert().await?;
+/// // ... exercise the API ...
+/// fixtures.reset().await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Fixtures {
+    // ---
+    repository: RepositoryPtr,
+    redis: RedisConnection,
+    users: Vec<Uuid>,
+    redis_keys: Vec<String>,
+    movies: Vec<String>,
+    credentials_issued: u32,
+}
+
+impl Fixtures {
+    // ---
+
+    pub fn new(repository: RepositoryPtr, redis: MultiplexedConnection) -> Self {
+        // ---
+        Self {
+            repository,
+            redis: redis.into(),
+            users: Vec::new(),
+            redis_keys: Vec::new(),
+            movies: Vec::new(),
+            credentials_issued: 0,
+        }
+    }
+
+    /// A user named `username`.
+    pub fn user(&mut self, username: &str) -> UserFixture<'_> {
+        // ---
+        UserFixture {
+            fixtures: self,
+            username: username.to_string(),
+            soft_deleted: false,
+        }
+    }
+
+    /// A passkey belonging to `user`, with ID `<username>-credential-<n>`
+    /// where `n` counts credentials inserted by these fixtures.
+    pub fn credential(&mut self, user: &User) -> CredentialFixture<'_> {
+        // ---
+        self.credentials_issued += 1;
+        let id = format!("{}-credential-{}", user.username, self.credentials_issued);
+        CredentialFixture {
+            fixtures: self,
+            credential: Credential::new(
+                id.into_bytes(),
+                user.id,
+                b"fixture-public-key".to_vec(),
+                0,
+            ),
+            compromised: false,
+        }
+    }
+
+    /// A Redis-backed session for `user`.
+    pub fn session(&mut self, user: &User) -> SessionFixture<'_> {
+        // ---
+        SessionFixture {
+            fixtures: self,
+            user_id: user.id,
+            username: user.username.clone(),
+            client_ip: None,
+            user_agent: None,
+        }
+    }
+
+    /// A movie rated 3 stars, indexed by title and year like one added
+    /// through `POST /movies/add`.
+    pub fn movie(&mut self, title: &str, year: u16) -> MovieFixture<'_> {
+        // ---
+        MovieFixture {
+            fixtures: self,
+            title: title.to_string(),
+            year,
+            stars: 3.0,
+            genres: Vec::new(),
+        }
+    }
+
+    /// Removes everything these fixtures inserted: users (with their
+    /// credentials), sessions, and movies.
+    ///
+    /// # Errors
+    /// Returns the first error from the repository or Redis; the remaining
+    /// data is still removed.
+    pub async fn reset(&mut self) -> Result<()> {
+        // ---
+        let mut result = Ok(());
+
+        for user_id in self.users.drain(..) {
+            if let Err(e) = self.repository.delete_user(user_id).await {
+                result = result.and(Err(e));
+            }
+        }
+
+        for movie_id in self.movies.drain(..) {
+            if let Err(status) = remove_movie(&mut self.redis, &movie_id).await {
+                result = result.and(Err(anyhow!("deleting fixture movie failed: {status}")));
+            }
+        }
+
+        if !self.redis_keys.is_empty() {
+            let keys: Vec<String> = self.redis_keys.drain(..).collect();
+            let deleted: redis::RedisResult<()> = self.redis.del(keys).await;
+            result = result.and(deleted.context("deleting fixture keys from Redis"));
+        }
+
+        self.credentials_issued = 0;
+        result
+    }
+}
+
+/// Builder returned by [`Fixtures::user`].
+pub struct UserFixture<'a> {
+    // ---
+    fixtures: &'a mut Fixtures,
+    username: String,
+    soft_deleted: bool,
+}
+
+impl UserFixture<'_> {
+    // ---
+
+    /// Soft-deletes the user after inserting it.
+    pub fn soft_deleted(mut self) -> Self {
+        // ---
+        self.soft_deleted = true;
+        self
+    }
+
+    /// Inserts the user, replacing any active user with the same name.
+    ///
+    /// # Errors
+    /// Returns an error if the repository fails.
+    pub async fn insert(self) -> Result<User> {
+        // ---
+        let repository = &self.fixtures.repository;
+
+        if let Some(stale) = repository.get_user_by_username(&self.username).await? {
+            repository.delete_user(stale.id).await?;
+        }
+
+        let user = repository.create_user(&self.username).await?;
+        self.fixtures.users.push(user.id);
+
+        if self.soft_deleted {
+            repository.soft_delete_user(user.id).await?;
+        }
+        Ok(user)
+    }
+}
+
+/// Builder returned by [`Fixtures::credential`].
+pub struct CredentialFixture<'a> {
+    // ---
+    fixtures: &'a mut Fixtures,
+    credential: Credential,
+    compromised: bool,
+}
+
+impl CredentialFixture<'_> {
+    // ---
+
+    pub fn id(mut self, id: impl Into<Vec<u8>>) -> Self {
+        // ---
+        self.credential.id = id.into();
+        self
+    }
+
+    pub fn public_key(mut self, public_key: impl Into<Vec<u8>>) -> Self {
+        // ---
+        self.credential.public_key = public_key.into();
+        self
+    }
+
+    pub fn counter(mut self, counter: i64) -> Self {
+        // ---
+        self.credential.counter = counter;
+        self
+    }
+
+    /// Sets the backup eligible (BE) and backed up (BS) flags.
+    pub fn backup(mut self, eligible: bool, backed_up: bool) -> Self {
+        // ---
+        self.credential.backup_eligible = Some(eligible);
+        self.credential.backup_state = Some(backed_up);
+        self
+    }
+
+    /// Flags the credential as possibly cloned.
+    pub fn compromised(mut self) -> Self {
+        // ---
+        self.compromised = true;
+        self
+    }
+
+    /// Inserts the credential and returns it as stored.
+    ///
+    /// # Errors
+    /// Returns an error if the repository fails, e.g. the ID is taken.
+    pub async fn insert(self) -> Result<Credential> {
+        // ---
+        let repository = &self.fixtures.repository;
+        let id = self.credential.id.clone();
+
+        repository.save_credential(self.credential).await?;
+        if self.compromised {
+            repository.mark_credential_compromised(&id).await?;
+        }
+
+        repository
+            .get_credential_by_id(&id)
+            .await?
+            .ok_or_else(|| anyhow!("credential missing after insert"))
+    }
+}
+
+/// Builder returned by [`Fixtures::session`].
+pub struct SessionFixture<'a> {
+    // ---
+    fixtures: &'a mut Fixtures,
+    user_id: Uuid,
+    username: String,
+    client_ip: Option<IpAddr>,
+    user_agent: Option<String>,
+}
+
+impl SessionFixture<'_> {
+    // ---
+
+    /// Records `client_ip` as the address the session was created from.
+    pub fn client_ip(mut self, client_ip: IpAddr) -> Self {
+        // ---
+        self.client_ip = Some(client_ip);
+        self
+    }
+
+    /// Records `user_agent` as the user agent the session was created by.
+    pub fn user_agent(mut self, user_agent: &str) -> Self {
+        // ---
+        self.user_agent = Some(user_agent.to_string());
+        self
+    }
+
+    /// Stores the session and returns its token.
+    ///
+    /// # Errors
+    /// Returns an error if Redis fails.
+    pub async fn insert(self) -> Result<String> {
+        // ---
+        let client = ClientFingerprint::new(self.client_ip, self.user_agent.as_deref());
+        let token = create_session_for(
+            &mut self.fixtures.redis,
+            DEFAULT_TENANT,
+            self.user_id,
+            self.username,
+            &client,
+        )
+        .await
+        .map_err(|status| anyhow!("creating session failed: {status}"))?;
+
+        self.fixtures.redis_keys.push(redis_keys::session(&token));
+        Ok(token)
+    }
+}
+
+/// Builder returned by [`Fixtures::movie`].
+pub struct MovieFixture<'a> {
+    // ---
+    fixtures: &'a mut Fixtures,
+    title: String,
+    year: u16,
+    stars: f32,
+    genres: Vec<String>,
+}
+
+impl MovieFixture<'_> {
+    // ---
+
+    pub fn stars(mut self, stars: f32) -> Self {
+        // ---
+        self.stars = stars;
+        self
+    }
+
+    pub fn genres(mut self, genres: &[&str]) -> Self {
+        // ---
+        self.genres = genres.iter().map(|genre| genre.to_string()).collect();
+        self
+    }
+
+    /// Stores the movie under a new ID, replacing any with the same title
+    /// and year, and returns the ID.
+    ///
+    /// # Errors
+    /// Returns an error if the movie fails the API's validation or Redis
+    /// fails.
+    pub async fn insert(self) -> Result<String> {
+        // ---
+        let mut movie: Movie = serde_json::from_value(json!({
+            "title": self.title,
+            "year": self.year,
+            "stars": self.stars,
+            "genres": self.genres,
+        }))?;
+        let hash_key = movie
+            .sanitize()
+            .map_err(|status| anyhow!("invalid movie fixture: {status}"))?;
+
+        let redis = &mut self.fixtures.redis;
+        if let Some(stale) = redis.get::<_, Option<String>>(hash_key.index_key()).await? {
+            remove_movie(redis, &stale)
+                .await
+                .map_err(|status| anyhow!("deleting stale movie failed: {status}"))?;
+        }
+
+        let id = Uuid::new_v4().to_string();
+        save_movie(redis, &id, &movie, &hash_key)
+            .await
+            .map_err(|status| anyhow!("storing movie fixture failed: {status}"))?;
+
+        self.fixtures.movies.push(id.clone());
+        Ok(id)
+    }
+}
@@ -0,0 +1,288 @@
+//! Software FIDO2 authenticator for end-to-end WebAuthn tests.
+//!
+//! Produces the same `RegisterPublicKeyCredential` / `PublicKeyCredential`
+//! JSON a browser would send, signed with real ES256 (P-256) keys, so the
+//! registration and sign-in finish handlers can be exercised without a
+//! browser or hardware key.
+//!
+//! Attestation is `none` and every ceremony reports user presence and user
+//! verification.
+
+use anyhow::{anyhow, Context, Result};
+use openssl::bn::BigNumContext;
+use openssl::ec::{EcGroup, EcKey, PointConversionForm};
+use openssl::hash::MessageDigest;
+use openssl::nid::Nid;
+use openssl::pkey::{PKey, Private};
+use openssl::sha::sha256;
+use openssl::sign::Signer;
+use rand::RngCore;
+use serde::de::DeserializeOwned;
+use serde_cbor_2::Value;
+use serde_json::json;
+use std::collections::BTreeMap;
+use webauthn_rs::prelude::{
+    Base64UrlSafeData, CreationChallengeResponse, PublicKeyCredential, RegisterPublicKeyCredential,
+    RequestChallengeResponse,
+};
+
+/// Authenticator data flag: user present.
+const FLAG_UP: u8 = 0x01;
+
+/// Authenticator data flag: user verified.
+const FLAG_UV: u8 = 0x04;
+
+/// Authenticator data flag: attested credential data included.
+const FLAG_AT: u8 = 0x40;
+
+/// COSE algorithm identifier for ES256.
+const COSE_ES256: i128 = -7;
+
+/// A passkey held by the [`MockAuthenticator`].
+struct MockCredential {
+    // ---
+    id: Vec<u8>,
+    rp_id: String,
+    user_handle: Vec<u8>,
+    key: PKey<Private>,
+    sign_count: u32,
+}
+
+/// An in-memory authenticator holding any number of passkeys.
+///
+/// ```no_run
+/// # fn demo(creation: webauthn_rs::prelude::CreationChallengeResponse,
+/// #         request: webauthn_rs::prelude::RequestChallengeResponse) -> anyhow::Result<()> {
+/// use axum_quickstart::test_utils::MockAuthenticator;
+///
+/// let mut authenticator = MockAuthenticator::new("http://localhost:8080");
+/// let credential = authenticator.register(&creation)?; // body of register/finish
+/// let assertion = authenticator.authenticate(&request)?; // body of auth/finish
+/// # Ok(())
+/// # }
+/// ```
+pub struct MockAuthenticator {
+    // ---
+    origin: String,
+    credentials: Vec<MockCredential>,
+}
+
+impl MockAuthenticator {
+    // ---
+
+    /// Creates an authenticator that reports `origin` (e.g.
+    /// `http://localhost:8080`) as the page origin in client data.
+    pub fn new(origin: impl Into<String>) -> Self {
+        // ---
+        Self {
+            origin: origin.into(),
+            credentials: Vec::new(),
+        }
+    }
+
+    /// Creates a new passkey for the relying party and user in `options`,
+    /// the `challenge` returned by `register/start`.
+    ///
+    /// # Errors
+    /// Returns an error if key generation or encoding fails.
+    pub fn register(
+        &mut self,
+        options: &CreationChallengeResponse,
+    ) -> Result<RegisterPublicKeyCredential> {
+        // ---
+        let options = &options.public_key;
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)?;
+        let key = EcKey::generate(&group)?;
+        let mut id = vec![0u8; 32];
+        rand::thread_rng().fill_bytes(&mut id);
+
+        let client_data = self.client_data("webauthn.create", &options.challenge)?;
+
+        let mut auth_data = authenticator_data(&options.rp.id, FLAG_UP | FLAG_UV | FLAG_AT, 0);
+        auth_data.extend_from_slice(&[0u8; 16]); // AAGUID
+        auth_data.extend_from_slice(&u16::try_from(id.len())?.to_be_bytes());
+        auth_data.extend_from_slice(&id);
+        auth_data.extend_from_slice(&cose_key(&key)?);
+
+        let attestation_object = serde_cbor_2::to_vec(&Value::Map(BTreeMap::from([
+            (text("fmt"), text("none")),
+            (text("attStmt"), Value::Map(BTreeMap::new())),
+            (text("authData"), Value::Bytes(auth_data)),
+        ])))?;
+
+        let credential = credential_json(
+            &id,
+            json!({
+                "attestationObject": b64(attestation_object),
+                "clientDataJSON": b64(client_data),
+                "transports": ["internal"],
+            }),
+        )?;
+
+        self.credentials.push(MockCredential {
+            id,
+            rp_id: options.rp.id.clone(),
+            user_handle: options.user.id.to_vec(),
+            key: PKey::from_ec_key(key)?,
+            sign_count: 0,
+        });
+        Ok(credential)
+    }
+
+    /// Signs the challenge in `options`, the `options` returned by
+    /// `auth/start`, with a passkey it allows. Each call increments that
+    /// passkey's signature counter.
+    ///
+    /// # Errors
+    /// Returns an error if this authenticator holds none of the allowed
+    /// passkeys, or signing fails.
+    pub fn authenticate(
+        &mut self,
+        options: &RequestChallengeResponse,
+    ) -> Result<PublicKeyCredential> {
+        // ---
+        let options = &options.public_key;
+        let client_data = self.client_data("webauthn.get", &options.challenge)?;
+
+        let credential = self
+            .credentials
+            .iter_mut()
+            .find(|c| {
+                c.rp_id == options.rp_id
+                    && options
+                        .allow_credentials
+                        .iter()
+                        .any(|allowed| allowed.id.as_slice() == c.id.as_slice())
+            })
+            .ok_or_else(|| anyhow!("no passkey for {} in the allow list", options.rp_id))?;
+
+        credential.sign_count += 1;
+        let auth_data =
+            authenticator_data(&credential.rp_id, FLAG_UP | FLAG_UV, credential.sign_count);
+
+        let mut signer = Signer::new(MessageDigest::sha256(), &credential.key)?;
+        signer.update(&auth_data)?;
+        signer.update(&sha256(&client_data))?;
+        let signature = signer.sign_to_vec()?;
+
+        credential_json(
+            &credential.id,
+            json!({
+                "authenticatorData": b64(auth_data),
+                "clientDataJSON": b64(client_data),
+                "signature": b64(signature),
+                "userHandle": b64(credential.user_handle.clone()),
+            }),
+        )
+    }
+
+    /// Sets the signature counter of every passkey, e.g. back to an earlier
+    /// value to look like a cloned authenticator.
+    pub fn set_sign_count(&mut self, sign_count: u32) {
+        // ---
+        for credential in &mut self.credentials {
+            credential.sign_count = sign_count;
+        }
+    }
+
+    /// IDs of the passkeys created so far, in creation order.
+    pub fn credential_ids(&self) -> Vec<Vec<u8>> {
+        // ---
+        self.credentials.iter().map(|c| c.id.clone()).collect()
+    }
+
+    fn client_data(&self, ceremony: &str, challenge: &Base64UrlSafeData) -> Result<Vec<u8>> {
+        // ---
+        Ok(serde_json::to_vec(&json!({
+            "type": ceremony,
+            "challenge": challenge,
+            "origin": self.origin,
+            "crossOrigin": false,
+        }))?)
+    }
+}
+
+/// `rpIdHash || flags || signCount`.
+fn authenticator_data(rp_id: &str, flags: u8, sign_count: u32) -> Vec<u8> {
+    // ---
+    let mut data = sha256(rp_id.as_bytes()).to_vec();
+    data.push(flags);
+    data.extend_from_slice(&sign_count.to_be_bytes());
+    data
+}
+
+/// The public half of `key` as a CBOR-encoded COSE EC2 key.
+fn cose_key(key: &EcKey<Private>) -> Result<Vec<u8>> {
+    // ---
+    let mut ctx = BigNumContext::new()?;
+    let point =
+        key.public_key()
+            .to_bytes(key.group(), PointConversionForm::UNCOMPRESSED, &mut ctx)?;
+    // 0x04 || x (32 bytes) || y (32 bytes)
+    let (x, y) = point[1..].split_at(32);
+
+    let cose = Value::Map(BTreeMap::from([
+        (Value::Integer(1), Value::Integer(2)), // kty: EC2
+        (Value::Integer(3), Value::Integer(COSE_ES256)),
+        (Value::Integer(-1), Value::Integer(1)), // crv: P-256
+        (Value::Integer(-2), Value::Bytes(x.to_vec())),
+        (Value::Integer(-3), Value::Bytes(y.to_vec())),
+    ]));
+    serde_cbor_2::to_vec(&cose).context("encoding COSE key")
+}
+
+/// A `public-key` credential with the given ID and `response` fields.
+fn credential_json<T: DeserializeOwned>(id: &[u8], response: serde_json::Value) -> Result<T> {
+    // ---
+    let id = b64(id.to_vec());
+    Ok(serde_json::from_value(json!({
+        "id": id,
+        "rawId": id,
+        "type": "public-key",
+        "response": response,
+    }))?)
+}
+
+fn b64(bytes: Vec<u8>) -> Base64UrlSafeData {
+    // ---
+    Base64UrlSafeData::from(bytes)
+}
+
+fn text(s: &str) -> Value {
+    // ---
+    Value::Text(s.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+    use webauthn_rs::prelude::{Url, WebauthnBuilder};
+
+    #[test]
+    fn register_and_authenticate_verify_with_webauthn_rs() {
+        // ---
+        let origin = Url::parse("http://localhost:8080").unwrap();
+        let webauthn = WebauthnBuilder::new("localhost", &origin)
+            .unwrap()
+            .build()
+            .unwrap();
+        let mut authenticator = MockAuthenticator::new("http://localhost:8080");
+
+        let (creation, reg_state) = webauthn
+            .start_passkey_registration(uuid::Uuid::new_v4(), "alice", "alice", None)
+            .unwrap();
+        let credential = authenticator.register(&creation).unwrap();
+        let passkey = webauthn
+            .finish_passkey_registration(&credential, &reg_state)
+            .unwrap();
+
+        let (request, auth_state) = webauthn.start_passkey_authentication(&[passkey]).unwrap();
+        let assertion = authenticator.authenticate(&request).unwrap();
+        let result = webauthn
+            .finish_passkey_authentication(&assertion, &auth_state)
+            .unwrap();
+        assert_eq!(result.counter(), 1);
+        assert!(result.user_verified());
+    }
+}
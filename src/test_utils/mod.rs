@@ -0,0 +1,14 @@
+// Gateway module - helpers for testing code built on this crate
+// Only compiled with the `test-utils` feature
+
+mod fixtures;
+mod mock_authenticator;
+
+pub use fixtures::{
+    CredentialFixture,
+    Fixtures,
+    MovieFixture,
+    SessionFixture,
+    UserFixture, // ---
+};
+pub use mock_authenticator::MockAuthenticator;
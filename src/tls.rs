@@ -0,0 +1,482 @@
+//! HTTPS listener with optional client certificate verification (mutual
+//! TLS), behind the `tls` feature.
+//!
+//! [`TlsListener`] wraps a plain listener such as [`ServerListener`] and
+//! performs TLS handshakes off the accept loop, so a slow client cannot hold
+//! up others. When `AXUM_TLS_CLIENT_CA_FILE` is set, a client certificate
+//! issued by one of its CAs is verified and mapped to a [`ServiceIdentity`];
+//! clients without a certificate are still accepted. Certificates from any
+//! other CA fail the handshake.
+//!
+//! The certificates are held in a [`LiveTls`], which
+//! [`ConfigReloader`](crate::ConfigReloader) re-reads on reload; connections
+//! made afterwards use the new ones.
+//!
+//! Serve with [`MakeServiceWithIdentity`], which gives requests the peer
+//! address (`ConnectInfo<SocketAddr>`) and the identity:
+//!
+//! ```no_run
+//! # async fn run(router: axum::Router) -> anyhow::Result<()> {
+//! use axum_quickstart::{
+//!     LiveTls, MakeServiceWithIdentity, ServerConfig, ServerListener, TlsConfig, TlsListener,
+//! };
+//!
+//! let tls = LiveTls::load(&TlsConfig::from_env()?)?;
+//! let listener = ServerListener::bind("127.0.0.1:8443", &ServerConfig::from_env()?).await?;
+//!
+//! axum::serve(TlsListener::new(listener, tls)?, MakeServiceWithIdentity::new(router)).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`ServerListener`]: crate::ServerListener
+
+use crate::config::TlsConfig;
+use crate::service_identity::ServiceIdentity;
+use anyhow::{Context as _, Result};
+use arc_swap::ArcSwap;
+use axum::extract::{ConnectInfo, Request};
+use axum::response::Response;
+use axum::routing::future::RouteFuture;
+use axum::serve::{IncomingStream, Listener};
+use axum::Router;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::WebPkiClientVerifier;
+use rustls::RootCertStore;
+use sha2::{Digest, Sha256};
+use std::convert::Infallible;
+use std::future::{ready, Ready};
+use std::io;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::sync::mpsc;
+use tokio_rustls::server::TlsStream;
+use tokio_rustls::TlsAcceptor;
+use tower::Service;
+use x509_parser::extensions::GeneralName;
+
+/// Clients that have not completed the handshake by then are dropped.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Handshaken connections waiting for the server to accept them.
+const ACCEPT_QUEUE: usize = 64;
+
+/// Builds the rustls server configuration from the `AXUM_TLS_*` settings,
+/// offering HTTP/2 and HTTP/1.1 via ALPN.
+///
+/// # Errors
+/// Returns an error if TLS is not configured, or a certificate, key, or CA
+/// file cannot be read or is invalid.
+pub fn tls_server_config(config: &TlsConfig) -> Result<Arc<rustls::ServerConfig>> {
+    // ---
+    let (Some(cert_file), Some(key_file)) = (&config.cert_file, &config.key_file) else {
+        anyhow::bail!("Missing required configuration: AXUM_TLS_CERT_FILE");
+    };
+    let certs = read_certs(cert_file)?;
+    let key = PrivateKeyDer::from_pem_file(key_file)
+        .with_context(|| format!("Failed to read TLS key {}", key_file.display()))?;
+
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let builder = rustls::ServerConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()?;
+    let builder = match &config.client_ca_file {
+        Some(ca_file) => {
+            let mut roots = RootCertStore::empty();
+            for cert in read_certs(ca_file)? {
+                roots
+                    .add(cert)
+                    .with_context(|| format!("Invalid client CA in {}", ca_file.display()))?;
+            }
+            let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider)
+                .allow_unauthenticated()
+                .build()?;
+            builder.with_client_cert_verifier(verifier)
+        }
+        None => builder.with_no_client_auth(),
+    };
+
+    let mut server = builder
+        .with_single_cert(certs, key)
+        .context("TLS certificate does not match its key")?;
+    server.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(Arc::new(server))
+}
+
+/// The server's TLS configuration, replaceable while the server runs.
+///
+/// Cloning is cheap; clones share the configuration. Replacing it affects
+/// only connections made afterwards.
+#[derive(Clone)]
+pub struct LiveTls {
+    // ---
+    config: Arc<ArcSwap<rustls::ServerConfig>>,
+
+    /// Digest of the files last loaded, to tell whether a reload changed them.
+    files: Arc<Mutex<Vec<u8>>>,
+}
+
+impl LiveTls {
+    // ---
+
+    /// Builds the configuration from the `AXUM_TLS_*` files; see
+    /// [`tls_server_config`].
+    ///
+    /// # Errors
+    /// Returns an error if a certificate, key, or CA file cannot be read or
+    /// is invalid.
+    pub fn load(config: &TlsConfig) -> Result<Self> {
+        // ---
+        let live = Self::from(tls_server_config(config)?);
+        *live.files.lock().unwrap_or_else(PoisonError::into_inner) = files_digest(config)?;
+        Ok(live)
+    }
+
+    /// Re-reads the certificate, key, and client CA files and serves new
+    /// connections with them. Returns whether their contents changed.
+    ///
+    /// # Errors
+    /// Returns an error if a file cannot be read or is invalid; the current
+    /// configuration is then kept.
+    pub fn reload(&self, config: &TlsConfig) -> Result<bool> {
+        // ---
+        let mut files = self.files.lock().unwrap_or_else(PoisonError::into_inner);
+        let digest = files_digest(config)?;
+        if digest == *files {
+            return Ok(false);
+        }
+        self.config.store(tls_server_config(config)?);
+        *files = digest;
+        Ok(true)
+    }
+
+    fn current(&self) -> Arc<rustls::ServerConfig> {
+        // ---
+        self.config.load_full()
+    }
+}
+
+impl From<Arc<rustls::ServerConfig>> for LiveTls {
+    fn from(config: Arc<rustls::ServerConfig>) -> Self {
+        // ---
+        Self {
+            config: Arc::new(ArcSwap::new(config)),
+            files: Arc::default(),
+        }
+    }
+}
+
+/// SHA-256 over the certificate, key, and client CA files, in that order.
+fn files_digest(config: &TlsConfig) -> Result<Vec<u8>> {
+    // ---
+    let mut hasher = Sha256::new();
+    for path in [&config.cert_file, &config.key_file, &config.client_ca_file] {
+        match path {
+            Some(path) => {
+                let contents = std::fs::read(path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+                hasher.update(Sha256::digest(contents));
+            }
+            None => hasher.update([0u8; 32]),
+        }
+    }
+    Ok(hasher.finalize().to_vec())
+}
+
+/// Reads every certificate in a PEM file.
+fn read_certs(path: &Path) -> Result<Vec<CertificateDer<'static>>> {
+    // ---
+    let certs = CertificateDer::pem_file_iter(path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .with_context(|| format!("Failed to read certificates from {}", path.display()))?;
+    if certs.is_empty() {
+        anyhow::bail!("No certificates in {}", path.display());
+    }
+    Ok(certs)
+}
+
+/// Maps a DER-encoded client certificate to the service it names: the
+/// subject Common Name, else the first DNS or URI subject alternative name.
+fn identity_from_der(der: &[u8]) -> Option<ServiceIdentity> {
+    // ---
+    let (_, cert) = x509_parser::parse_x509_certificate(der).ok()?;
+
+    let common_name = cert
+        .subject()
+        .iter_common_name()
+        .find_map(|cn| cn.as_str().ok())
+        .filter(|cn| !cn.is_empty())
+        .map(str::to_string);
+
+    let alt_name = || {
+        let names = cert.subject_alternative_name().ok().flatten()?;
+        names
+            .value
+            .general_names
+            .iter()
+            .find_map(|name| match name {
+                GeneralName::DNSName(name) | GeneralName::URI(name) => Some(name.to_string()),
+                _ => None,
+            })
+    };
+
+    common_name.or_else(alt_name).map(ServiceIdentity)
+}
+
+/// Accepts TLS connections from an inner listener.
+#[derive(Debug)]
+pub struct TlsListener<Io> {
+    // ---
+    connections: mpsc::Receiver<(TlsConnection<Io>, SocketAddr)>,
+    local_addr: SocketAddr,
+}
+
+impl<Io> TlsListener<Io>
+where
+    Io: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    // ---
+
+    /// Starts accepting from `listener` in the background. Accepting stops
+    /// once the `TlsListener` is dropped. Each handshake uses the
+    /// configuration `tls` holds at the time.
+    ///
+    /// # Errors
+    /// Returns an error if the listener's local address is unavailable.
+    pub fn new<L>(mut listener: L, tls: impl Into<LiveTls>) -> io::Result<Self>
+    where
+        L: Listener<Io = Io, Addr = SocketAddr>,
+    {
+        // ---
+        let local_addr = listener.local_addr()?;
+        let tls = tls.into();
+        let (sender, connections) = mpsc::channel(ACCEPT_QUEUE);
+
+        tokio::spawn(async move {
+            loop {
+                let (io, addr) = tokio::select! {
+                    conn = listener.accept() => conn,
+                    () = sender.closed() => break,
+                };
+
+                let (acceptor, sender) = (TlsAcceptor::from(tls.current()), sender.clone());
+                tokio::spawn(async move {
+                    match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(io)).await {
+                        Ok(Ok(stream)) => {
+                            let identity = stream
+                                .get_ref()
+                                .1
+                                .peer_certificates()
+                                .and_then(|certs| certs.first())
+                                .and_then(|cert| identity_from_der(cert));
+                            let conn = TlsConnection { stream, identity };
+                            // Fails only when the server has stopped
+                            let _ = sender.send((conn, addr)).await;
+                        }
+                        Ok(Err(e)) => tracing::debug!("TLS handshake with {addr} failed: {e}"),
+                        Err(_) => tracing::debug!("TLS handshake with {addr} timed out"),
+                    }
+                });
+            }
+        });
+
+        Ok(Self {
+            connections,
+            local_addr,
+        })
+    }
+}
+
+impl<Io> Listener for TlsListener<Io>
+where
+    Io: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    // ---
+    type Io = TlsConnection<Io>;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        // ---
+        match self.connections.recv().await {
+            Some(conn) => conn,
+            // The accept task only ends after this listener is dropped
+            None => std::future::pending().await,
+        }
+    }
+
+    fn local_addr(&self) -> io::Result<Self::Addr> {
+        // ---
+        Ok(self.local_addr)
+    }
+}
+
+/// A connection that has completed the TLS handshake.
+#[derive(Debug)]
+pub struct TlsConnection<Io> {
+    // ---
+    stream: TlsStream<Io>,
+    identity: Option<ServiceIdentity>,
+}
+
+impl<Io> TlsConnection<Io> {
+    // ---
+
+    /// The service named by the client's verified certificate, if it sent
+    /// one.
+    pub fn identity(&self) -> Option<&ServiceIdentity> {
+        // ---
+        self.identity.as_ref()
+    }
+}
+
+impl<Io: AsyncRead + AsyncWrite + Unpin> AsyncRead for TlsConnection<Io> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        // ---
+        Pin::new(&mut self.stream).poll_read(cx, buf)
+    }
+}
+
+impl<Io: AsyncRead + AsyncWrite + Unpin> AsyncWrite for TlsConnection<Io> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        // ---
+        Pin::new(&mut self.stream).poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        // ---
+        Pin::new(&mut self.stream).poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        // ---
+        self.stream.is_write_vectored()
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        // ---
+        Pin::new(&mut self.stream).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        // ---
+        Pin::new(&mut self.stream).poll_shutdown(cx)
+    }
+}
+
+/// Serves a router over a [`TlsListener`], adding `ConnectInfo<SocketAddr>`
+/// and, for clients with a verified certificate, their [`ServiceIdentity`]
+/// to every request. The TLS counterpart of
+/// `into_make_service_with_connect_info`.
+#[derive(Debug, Clone)]
+pub struct MakeServiceWithIdentity {
+    // ---
+    router: Router,
+}
+
+impl MakeServiceWithIdentity {
+    // ---
+
+    pub fn new(router: Router) -> Self {
+        // ---
+        Self { router }
+    }
+}
+
+impl<'a, Io> Service<IncomingStream<'a, TlsListener<Io>>> for MakeServiceWithIdentity
+where
+    Io: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    type Response = ConnectionService;
+    type Error = Infallible;
+    type Future = Ready<Result<ConnectionService, Infallible>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+        // ---
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, stream: IncomingStream<'a, TlsListener<Io>>) -> Self::Future {
+        // ---
+        ready(Ok(ConnectionService {
+            router: self.router.clone(),
+            peer: *stream.remote_addr(),
+            identity: stream.io().identity().cloned(),
+        }))
+    }
+}
+
+/// The router for one TLS connection; see [`MakeServiceWithIdentity`].
+#[derive(Debug, Clone)]
+pub struct ConnectionService {
+    // ---
+    router: Router,
+    peer: SocketAddr,
+    identity: Option<ServiceIdentity>,
+}
+
+impl Service<Request> for ConnectionService {
+    type Response = Response;
+    type Error = Infallible;
+    type Future = RouteFuture<Infallible>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+        // ---
+        Service::<Request>::poll_ready(&mut self.router, cx)
+    }
+
+    fn call(&mut self, mut req: Request) -> Self::Future {
+        // ---
+        req.extensions_mut().insert(ConnectInfo(self.peer));
+        if let Some(identity) = &self.identity {
+            req.extensions_mut().insert(identity.clone());
+        }
+        self.router.call(req)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+
+    fn fixture_cert(name: &str) -> CertificateDer<'static> {
+        // ---
+        let path = format!("{}/tests/fixtures/tls/{name}", env!("CARGO_MANIFEST_DIR"));
+        read_certs(Path::new(&path)).unwrap().remove(0)
+    }
+
+    #[test]
+    fn identity_is_the_common_name() {
+        // ---
+        let identity = identity_from_der(&fixture_cert("client.pem"));
+        assert_eq!(identity, Some(ServiceIdentity("billing-service".into())));
+    }
+
+    #[test]
+    fn identity_falls_back_to_the_alt_name() {
+        // ---
+        let identity = identity_from_der(&fixture_cert("client-san.pem"));
+        assert_eq!(
+            identity,
+            Some(ServiceIdentity("spiffe://example.org/reports".into()))
+        );
+        assert_eq!(identity_from_der(b"not a certificate"), None);
+    }
+}
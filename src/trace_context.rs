@@ -0,0 +1,321 @@
+//! W3C Trace Context (`traceparent` / `tracestate`) propagation.
+//!
+//! An inbound request carrying a valid `traceparent` continues the caller's
+//! trace; any other request starts a new one. Either way the request gets a
+//! span ID of its own, recorded with the trace ID on its tracing span, so
+//! log lines from every service in a trace can be matched up.
+//!
+//! The context of the request being handled is available through
+//! [`TraceContext::current`]. Outbound calls made on its behalf send
+//! [`child`](TraceContext::child) headers, so the callee's spans are
+//! parented to this service rather than to our caller. `tracestate` is
+//! passed through unchanged.
+//!
+//! See <https://www.w3.org/TR/trace-context/>.
+
+use axum::http::{HeaderMap, HeaderValue};
+use rand::RngCore;
+use std::fmt;
+
+/// Header carrying version, trace ID, parent span ID, and flags.
+pub(crate) const TRACEPARENT: &str = "traceparent";
+
+/// Header carrying vendor-specific trace data.
+pub(crate) const TRACESTATE: &str = "tracestate";
+
+/// `tracestate` longer than this is dropped rather than propagated.
+const MAX_TRACESTATE_LEN: usize = 512;
+
+/// The `sampled` trace flag.
+const SAMPLED: u8 = 0x01;
+
+tokio::task_local! {
+    static CURRENT: TraceContext;
+}
+
+/// Position of one span in a distributed trace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceContext {
+    // ---
+    trace_id: [u8; 16],
+    span_id: [u8; 8],
+
+    /// The caller's span, if the trace came in with the request.
+    parent_id: Option<[u8; 8]>,
+    flags: u8,
+    tracestate: Option<String>,
+}
+
+impl TraceContext {
+    // ---
+
+    /// Starts a new, sampled trace.
+    pub fn new_root() -> Self {
+        // ---
+        let mut rng = rand::thread_rng();
+        let mut trace_id = [0; 16];
+        while trace_id == [0; 16] {
+            rng.fill_bytes(&mut trace_id);
+        }
+        Self {
+            trace_id,
+            span_id: new_span_id(),
+            parent_id: None,
+            flags: SAMPLED,
+            tracestate: None,
+        }
+    }
+
+    /// Continues the trace in `headers`, or returns `None` if there is no
+    /// valid `traceparent`.
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        // ---
+        let traceparent = headers.get(TRACEPARENT)?.to_str().ok()?;
+        let (trace_id, parent_id, flags) = parse_traceparent(traceparent.trim())?;
+
+        // Several `tracestate` headers form one comma-separated list
+        let tracestate = headers
+            .get_all(TRACESTATE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .collect::<Vec<_>>()
+            .join(",");
+
+        Some(Self {
+            trace_id,
+            span_id: new_span_id(),
+            parent_id: Some(parent_id),
+            flags,
+            tracestate: (!tracestate.is_empty() && tracestate.len() <= MAX_TRACESTATE_LEN)
+                .then_some(tracestate),
+        })
+    }
+
+    /// Continues the trace in `headers`, or starts a new one.
+    pub fn from_headers_or_root(headers: &HeaderMap) -> Self {
+        // ---
+        Self::from_headers(headers).unwrap_or_else(Self::new_root)
+    }
+
+    /// The context restored from stored `traceparent` and `tracestate`
+    /// values, as written by [`traceparent`](Self::traceparent).
+    pub fn from_stored(traceparent: &str, tracestate: Option<&str>) -> Option<Self> {
+        // ---
+        let (trace_id, span_id, flags) = parse_traceparent(traceparent)?;
+        Some(Self {
+            trace_id,
+            span_id,
+            parent_id: None,
+            flags,
+            tracestate: tracestate.map(str::to_string),
+        })
+    }
+
+    /// A new span in the same trace, parented to this one: the context of
+    /// an outbound call.
+    pub fn child(&self) -> Self {
+        // ---
+        Self {
+            trace_id: self.trace_id,
+            span_id: new_span_id(),
+            parent_id: Some(self.span_id),
+            flags: self.flags,
+            tracestate: self.tracestate.clone(),
+        }
+    }
+
+    /// The context of the request being handled, if any.
+    pub fn current() -> Option<Self> {
+        // ---
+        CURRENT.try_with(Clone::clone).ok()
+    }
+
+    /// Runs `f` with `self` as the [`current`](Self::current) context.
+    pub(crate) async fn scope<F: std::future::Future>(self, f: F) -> F::Output {
+        // ---
+        CURRENT.scope(self, f).await
+    }
+
+    /// Trace ID as 32 lowercase hex digits.
+    pub fn trace_id(&self) -> String {
+        // ---
+        hex::encode(self.trace_id)
+    }
+
+    /// This span's ID as 16 lowercase hex digits.
+    pub fn span_id(&self) -> String {
+        // ---
+        hex::encode(self.span_id)
+    }
+
+    /// The caller's span ID, if the trace was continued from a request.
+    pub fn parent_id(&self) -> Option<String> {
+        // ---
+        self.parent_id.map(hex::encode)
+    }
+
+    /// Whether the trace is sampled.
+    pub fn is_sampled(&self) -> bool {
+        // ---
+        self.flags & SAMPLED != 0
+    }
+
+    /// `traceparent` naming this span as the parent.
+    pub fn traceparent(&self) -> String {
+        // ---
+        self.to_string()
+    }
+
+    pub fn tracestate(&self) -> Option<&str> {
+        // ---
+        self.tracestate.as_deref()
+    }
+
+    /// Sets `traceparent`, and `tracestate` if there is one, on `headers`.
+    pub fn inject(&self, headers: &mut HeaderMap) {
+        // ---
+        if let Ok(value) = HeaderValue::from_str(&self.traceparent()) {
+            headers.insert(TRACEPARENT, value);
+        }
+        if let Some(Ok(value)) = self.tracestate.as_deref().map(HeaderValue::from_str) {
+            headers.insert(TRACESTATE, value);
+        }
+    }
+}
+
+impl fmt::Display for TraceContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // ---
+        write!(
+            f,
+            "00-{}-{}-{:02x}",
+            self.trace_id(),
+            self.span_id(),
+            self.flags
+        )
+    }
+}
+
+fn new_span_id() -> [u8; 8] {
+    // ---
+    let mut rng = rand::thread_rng();
+    let mut span_id = [0; 8];
+    while span_id == [0; 8] {
+        rng.fill_bytes(&mut span_id);
+    }
+    span_id
+}
+
+/// `{version}-{trace-id}-{parent-id}-{flags}`. Versions after `00` may
+/// append fields, which are ignored; version `ff` is invalid.
+fn parse_traceparent(value: &str) -> Option<([u8; 16], [u8; 8], u8)> {
+    // ---
+    let mut fields = value.split('-');
+    let version = lower_hex::<1>(fields.next()?)?[0];
+    let trace_id = lower_hex::<16>(fields.next()?)?;
+    let parent_id = lower_hex::<8>(fields.next()?)?;
+    let flags = lower_hex::<1>(fields.next()?)?[0];
+
+    let valid = version != 0xff
+        && (version != 0 || fields.next().is_none())
+        && trace_id != [0; 16]
+        && parent_id != [0; 8];
+    valid.then_some((trace_id, parent_id, flags))
+}
+
+/// Exactly `N` bytes as `2 * N` lowercase hex digits.
+fn lower_hex<const N: usize>(field: &str) -> Option<[u8; N]> {
+    // ---
+    if field.len() != 2 * N || field.bytes().any(|b| b.is_ascii_uppercase()) {
+        return None;
+    }
+    let mut bytes = [0; N];
+    hex::decode_to_slice(field, &mut bytes).ok()?;
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+
+    const TRACEPARENT_EXAMPLE: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        // ---
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.append(*name, HeaderValue::from_str(value).unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn continues_inbound_trace_with_a_new_span() {
+        // ---
+        let ctx = TraceContext::from_headers(&headers(&[
+            (TRACEPARENT, TRACEPARENT_EXAMPLE),
+            (TRACESTATE, "rojo=00f067aa0ba902b7"),
+            (TRACESTATE, "congo=t61rcWkgMzE"),
+        ]))
+        .unwrap();
+
+        assert_eq!(ctx.trace_id(), "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(ctx.parent_id().as_deref(), Some("00f067aa0ba902b7"));
+        assert_ne!(ctx.span_id(), "00f067aa0ba902b7");
+        assert!(ctx.is_sampled());
+        assert_eq!(
+            ctx.tracestate(),
+            Some("rojo=00f067aa0ba902b7,congo=t61rcWkgMzE")
+        );
+
+        let child = ctx.child();
+        assert_eq!(child.trace_id(), ctx.trace_id());
+        assert_eq!(child.parent_id(), Some(ctx.span_id()));
+        assert_eq!(
+            child.traceparent(),
+            format!("00-{}-{}-01", ctx.trace_id(), child.span_id())
+        );
+    }
+
+    #[test]
+    fn invalid_traceparent_is_ignored() {
+        // ---
+        for value in [
+            "",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+            "00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01",
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+            "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra",
+        ] {
+            let parsed = TraceContext::from_headers(&headers(&[(TRACEPARENT, value)]));
+            assert!(parsed.is_none(), "accepted {value:?}");
+        }
+
+        // Later versions may append fields
+        let future = "01-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-00-extra";
+        let ctx = TraceContext::from_headers(&headers(&[(TRACEPARENT, future)])).unwrap();
+        assert!(!ctx.is_sampled());
+    }
+
+    #[test]
+    fn root_and_stored_contexts_round_trip() {
+        // ---
+        let root = TraceContext::from_headers_or_root(&HeaderMap::new());
+        assert_eq!(root.parent_id(), None);
+        assert!(root.is_sampled());
+
+        let stored = TraceContext::from_stored(&root.traceparent(), Some("a=b")).unwrap();
+        assert_eq!(stored.traceparent(), root.traceparent());
+        assert_eq!(stored.tracestate(), Some("a=b"));
+
+        let mut outbound = HeaderMap::new();
+        stored.inject(&mut outbound);
+        assert_eq!(outbound[TRACEPARENT], root.traceparent().as_str());
+        assert_eq!(outbound[TRACESTATE], "a=b");
+    }
+}
@@ -0,0 +1,344 @@
+//! Background delivery of webhook events with signing and retries.
+//!
+//! Each request carries:
+//!
+//! | Header                | Value                                             |
+//! |:----------------------|:--------------------------------------------------|
+//! | `X-Webhook-Id`        | Event ID (stable across retries)                  |
+//! | `X-Webhook-Event`     | Event type, e.g. `user.registered`                |
+//! | `X-Webhook-Timestamp` | Unix seconds when this attempt was sent           |
+//! | `X-Webhook-Signature` | `sha256=<hex HMAC-SHA256(secret, "{ts}.{body}")>` |
+//! | `traceparent`         | W3C trace of the emitting request, if it had one  |
+//! | `tracestate`          | Passed through from the emitting request          |
+//!
+//! Receivers should recompute the signature and reject stale timestamps.
+
+use super::endpoints::{list_endpoints, WebhookEndpoint};
+use super::event::WebhookEvent;
+use crate::config::WebhookConfig;
+use crate::domain::{MetricsPtr, OutboxEvent, Repository, RepositoryPtr};
+use crate::infrastructure::HttpClient;
+use crate::redis_source::RedisSource;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, Notify};
+
+/// Header carrying the payload signature.
+const SIGNATURE_HEADER: &str = "x-webhook-signature";
+
+/// Upper bound on the delay between retries.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(300);
+
+/// Events claimed from the outbox per round.
+const OUTBOX_BATCH: u32 = 100;
+
+/// Receives events and fans each one out to every registered endpoint.
+///
+/// Each endpoint delivery runs in its own task, so a slow or failing
+/// receiver delays only its own retries.
+pub(super) async fn run_worker(
+    mut receiver: mpsc::Receiver<WebhookEvent>,
+    redis: RedisSource,
+    http: HttpClient,
+    metrics: MetricsPtr,
+    cfg: WebhookConfig,
+) {
+    // ---
+    let cfg = Arc::new(cfg);
+
+    while let Some(event) = receiver.recv().await {
+        // ---
+        let endpoints = match load_endpoints(&redis).await {
+            Ok(endpoints) => endpoints,
+            Err(e) => {
+                tracing::error!(
+                    "Cannot load webhook endpoints, dropping event {}: {e}",
+                    event.id
+                );
+                metrics.record_webhook_dead_letter();
+                continue;
+            }
+        };
+
+        let (http, metrics, cfg) = (http.clone(), metrics.clone(), cfg.clone());
+        tokio::spawn(async move {
+            deliver_to_all(&http, endpoints, event, &metrics, &cfg).await;
+        });
+    }
+}
+
+/// Drains the outbox: claims stored events, delivers each to every
+/// registered endpoint, and deletes it once every delivery has succeeded
+/// or been dead-lettered.
+///
+/// Runs when `wake` is notified after a write and every
+/// `cfg.outbox_poll_interval`. A claimed event is leased for as long as
+/// its deliveries can take; if they are cut short (Redis unreachable, the
+/// process stopping), the event is claimed again once the lease expires.
+/// Receivers may therefore see an event more than once, always with the
+/// same ID.
+pub(super) async fn run_outbox_relay(
+    repository: RepositoryPtr,
+    redis: RedisSource,
+    http: HttpClient,
+    metrics: MetricsPtr,
+    cfg: WebhookConfig,
+    wake: Arc<Notify>,
+) {
+    // ---
+    let lease = delivery_budget(&cfg);
+    let cfg = Arc::new(cfg);
+
+    loop {
+        // ---
+        let claimed = repository
+            .claim_events(OUTBOX_BATCH, lease)
+            .await
+            .inspect_err(|e| tracing::warn!("Cannot claim outbox events: {e}"))
+            .unwrap_or_default();
+        let mut more = claimed.len() == OUTBOX_BATCH as usize;
+
+        if !claimed.is_empty() {
+            match load_endpoints(&redis).await {
+                Ok(endpoints) => {
+                    for stored in claimed {
+                        let (http, metrics, cfg) = (http.clone(), metrics.clone(), cfg.clone());
+                        let (repository, endpoints) = (repository.clone(), endpoints.clone());
+
+                        tokio::spawn(async move {
+                            relay(&http, &*repository, endpoints, stored, &metrics, &cfg).await;
+                        });
+                    }
+                }
+                Err(e) => {
+                    tracing::warn!(
+                        "Cannot load webhook endpoints, retrying {} outbox events later: {e}",
+                        claimed.len()
+                    );
+                    more = false;
+                }
+            }
+        }
+
+        if !more {
+            tokio::select! {
+                _ = wake.notified() => {}
+                _ = tokio::time::sleep(cfg.outbox_poll_interval) => {}
+            }
+        }
+    }
+}
+
+/// Delivers one claimed outbox event, then removes it from the outbox.
+async fn relay(
+    http: &HttpClient,
+    repository: &dyn Repository,
+    endpoints: Vec<WebhookEndpoint>,
+    stored: OutboxEvent,
+    metrics: &MetricsPtr,
+    cfg: &WebhookConfig,
+) {
+    // ---
+    match WebhookEvent::from_outbox(&stored) {
+        Ok(event) => deliver_to_all(http, endpoints, event, metrics, cfg).await,
+        Err(e) => {
+            tracing::error!("Cannot parse outbox event {}: {e}", stored.id);
+            metrics.record_webhook_dead_letter();
+        }
+    }
+
+    if let Err(e) = repository.delete_event(stored.id).await {
+        tracing::warn!("Cannot remove delivered outbox event {}: {e}", stored.id);
+    }
+}
+
+async fn load_endpoints(redis: &RedisSource) -> anyhow::Result<Vec<WebhookEndpoint>> {
+    // ---
+    let mut conn = redis.connect().await?;
+    list_endpoints(&mut conn).await
+}
+
+/// Longest time [`deliver`] can take for one endpoint: every attempt
+/// timing out, with the backoff delays between them, plus one timeout of
+/// margin.
+fn delivery_budget(cfg: &WebhookConfig) -> Duration {
+    // ---
+    let mut budget = cfg.timeout * (cfg.max_attempts + 1);
+    let mut delay = cfg.retry_base;
+    for _ in 1..cfg.max_attempts {
+        budget += delay;
+        delay = (delay * 2).min(MAX_RETRY_DELAY);
+    }
+    budget
+}
+
+/// Delivers `event` to every endpoint concurrently, recording each
+/// delivery or dead letter. Resolves once all of them have settled.
+async fn deliver_to_all(
+    http: &HttpClient,
+    endpoints: Vec<WebhookEndpoint>,
+    event: WebhookEvent,
+    metrics: &MetricsPtr,
+    cfg: &WebhookConfig,
+) {
+    // ---
+    let body = match serde_json::to_vec(&event) {
+        Ok(body) => body,
+        Err(e) => {
+            tracing::error!("Cannot serialize webhook event {}: {e}", event.id);
+            return;
+        }
+    };
+
+    let deliveries = endpoints.iter().map(|endpoint| async {
+        if deliver(http, endpoint, &event, &body, cfg).await {
+            metrics.record_webhook_delivered();
+        } else {
+            tracing::error!(
+                "Webhook {} to {} dead-lettered after {} attempts",
+                event.id,
+                endpoint.url,
+                cfg.max_attempts
+            );
+            metrics.record_webhook_dead_letter();
+        }
+    });
+    futures::future::join_all(deliveries).await;
+}
+
+/// POSTs `body` to `endpoint`, retrying with exponential backoff.
+///
+/// Any 2xx response counts as delivered. Returns `false` once
+/// `cfg.max_attempts` attempts have failed.
+pub(super) async fn deliver(
+    http: &HttpClient,
+    endpoint: &WebhookEndpoint,
+    event: &WebhookEvent,
+    body: &[u8],
+    cfg: &WebhookConfig,
+) -> bool {
+    // ---
+    let mut delay = cfg.retry_base;
+
+    for attempt in 1..=cfg.max_attempts {
+        // ---
+        let timestamp = chrono::Utc::now().timestamp();
+
+        // Each attempt is a span of its own in the emitting request's trace
+        let mut trace_headers = reqwest::header::HeaderMap::new();
+        if let Some(trace) = &event.trace {
+            trace.child().inject(&mut trace_headers);
+        }
+
+        let request = http
+            .post(&endpoint.url)
+            .timeout(cfg.timeout)
+            .headers(trace_headers)
+            .header("content-type", "application/json")
+            .header("x-webhook-id", event.id.to_string())
+            .header("x-webhook-event", event.kind.as_str())
+            .header("x-webhook-timestamp", timestamp.to_string())
+            .header(SIGNATURE_HEADER, sign(&endpoint.secret, timestamp, body))
+            .body(body.to_vec());
+        let result = http.send_once(request).await;
+
+        match result {
+            Ok(response) if response.status().is_success() => return true,
+            Ok(response) => tracing::warn!(
+                "Webhook {} to {} attempt {attempt}/{}: HTTP {}",
+                event.id,
+                endpoint.url,
+                cfg.max_attempts,
+                response.status()
+            ),
+            Err(e) => tracing::warn!(
+                "Webhook {} to {} attempt {attempt}/{}: {e}",
+                event.id,
+                endpoint.url,
+                cfg.max_attempts
+            ),
+        }
+
+        if attempt < cfg.max_attempts {
+            tokio::time::sleep(delay).await;
+            delay = (delay * 2).min(MAX_RETRY_DELAY);
+        }
+    }
+
+    false
+}
+
+/// Computes the `X-Webhook-Signature` value for `body` sent at `timestamp`.
+pub(super) fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    // ---
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+    use crate::create_noop_metrics;
+    use crate::webhooks::WebhookEventKind;
+    use uuid::Uuid;
+
+    #[test]
+    fn signature_is_hmac_sha256_of_timestamp_and_body() {
+        // ---
+        // printf '1700000000.{"a":1}' | openssl dgst -sha256 -hmac secret
+        assert_eq!(
+            sign("secret", 1_700_000_000, br#"{"a":1}"#),
+            "sha256=49f24e537407743fa4a0242bb63b94b9a47ee99cbbe071ccd8a22550ae411686"
+        );
+        assert_ne!(
+            sign("secret", 1_700_000_001, br#"{"a":1}"#),
+            sign("secret", 1_700_000_000, br#"{"a":1}"#)
+        );
+    }
+
+    #[tokio::test]
+    async fn unreachable_endpoint_gives_up_after_max_attempts() {
+        // ---
+        let cfg = WebhookConfig {
+            max_attempts: 3,
+            retry_base: Duration::from_millis(1),
+            timeout: Duration::from_secs(1),
+            queue_capacity: 1,
+            outbox_poll_interval: Duration::from_secs(5),
+        };
+        let endpoint = WebhookEndpoint {
+            id: Uuid::new_v4(),
+            // Nothing listens on port 1
+            url: "http://127.0.0.1:1/hook".to_string(),
+            secret: "secret".to_string(),
+            created_at: chrono::Utc::now(),
+        };
+        let event = WebhookEvent::new(WebhookEventKind::UserRegistered, Uuid::nil(), "x", "AQ");
+
+        let http = HttpClient::new(&Default::default(), create_noop_metrics().unwrap()).unwrap();
+        let delivered = deliver(&http, &endpoint, &event, b"{}", &cfg).await;
+        assert!(!delivered);
+    }
+
+    #[test]
+    fn lease_covers_every_attempt_and_backoff() {
+        // ---
+        let cfg = WebhookConfig {
+            max_attempts: 3,
+            retry_base: Duration::from_secs(1),
+            timeout: Duration::from_secs(10),
+            queue_capacity: 1,
+            outbox_poll_interval: Duration::from_secs(5),
+        };
+        // 3 timeouts, 1s + 2s of backoff, one timeout of margin
+        assert_eq!(delivery_budget(&cfg), Duration::from_secs(43));
+    }
+}
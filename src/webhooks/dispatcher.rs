@@ -0,0 +1,152 @@
+//! Handler-facing entry point for emitting webhook events.
+
+use super::delivery::{run_outbox_relay, run_worker};
+use super::event::WebhookEvent;
+use crate::config::WebhookConfig;
+use crate::domain::{MetricsPtr, OutboxWrite, Repository, RepositoryPtr};
+use crate::infrastructure::HttpClient;
+use crate::redis_source::RedisSource;
+use anyhow::Result;
+use redis::Client;
+use std::sync::Arc;
+use tokio::sync::{mpsc, Notify};
+
+/// Queues webhook events for the background delivery worker.
+///
+/// Emitting never blocks or fails the request: if the queue is full the
+/// event is dropped and counted as a dead letter. Cheap to clone.
+///
+/// With a repository, events about database changes go through the
+/// transactional outbox instead (see [`emit_with`](Self::emit_with)), so
+/// they survive Redis or the endpoints being down when the change is made.
+#[derive(Clone)]
+pub struct WebhookDispatcher {
+    // ---
+    sender: Option<mpsc::Sender<WebhookEvent>>,
+    metrics: Option<MetricsPtr>,
+
+    /// Wakes the outbox relay, if one runs.
+    outbox: Option<Arc<Notify>>,
+}
+
+impl WebhookDispatcher {
+    // ---
+    /// Starts the delivery worker on the current Tokio runtime and returns
+    /// a dispatcher feeding it. Deliveries are sent through `http`.
+    ///
+    /// # Panics
+    /// Panics if called outside a Tokio runtime.
+    pub fn spawn(
+        redis_client: Client,
+        http: HttpClient,
+        metrics: MetricsPtr,
+        cfg: WebhookConfig,
+    ) -> Self {
+        // ---
+        Self::spawn_from(redis_client.into(), None, http, metrics, cfg)
+    }
+
+    /// Like [`spawn`](Self::spawn), loading endpoints through `redis`. With
+    /// a `repository`, also starts the relay draining its outbox.
+    pub(crate) fn spawn_from(
+        redis: RedisSource,
+        repository: Option<RepositoryPtr>,
+        http: HttpClient,
+        metrics: MetricsPtr,
+        cfg: WebhookConfig,
+    ) -> Self {
+        // ---
+        let outbox = repository.map(|repository| {
+            let wake = Arc::new(Notify::new());
+            tokio::spawn(run_outbox_relay(
+                repository,
+                redis.clone(),
+                http.clone(),
+                metrics.clone(),
+                cfg.clone(),
+                wake.clone(),
+            ));
+            wake
+        });
+
+        let (sender, receiver) = mpsc::channel(cfg.queue_capacity);
+        tokio::spawn(run_worker(receiver, redis, http, metrics.clone(), cfg));
+
+        Self {
+            sender: Some(sender),
+            metrics: Some(metrics),
+            outbox,
+        }
+    }
+
+    /// A dispatcher that discards every event.
+    pub fn disabled() -> Self {
+        // ---
+        Self {
+            sender: None,
+            metrics: None,
+            outbox: None,
+        }
+    }
+
+    /// Queues `event` for delivery to all registered endpoints.
+    pub fn emit(&self, event: WebhookEvent) {
+        // ---
+        let Some(sender) = &self.sender else {
+            tracing::debug!("Webhooks disabled; dropping {} event", event.kind.as_str());
+            return;
+        };
+
+        if let Err(e) = sender.try_send(event) {
+            tracing::error!("Webhook queue unavailable, dropping event: {e}");
+            if let Some(metrics) = &self.metrics {
+                metrics.record_webhook_dead_letter();
+            }
+        }
+    }
+
+    /// Applies `write` to `repository` and, if it changed anything, emits
+    /// `event`. Returns whether anything changed.
+    ///
+    /// With the outbox the event is stored in the same transaction as the
+    /// change and delivered from there; otherwise it is queued as by
+    /// [`emit`](Self::emit) once the write has succeeded.
+    ///
+    /// # Errors
+    /// Returns an error if the write fails; the event is then not emitted.
+    pub(crate) async fn emit_with(
+        &self,
+        repository: &dyn Repository,
+        write: OutboxWrite,
+        event: WebhookEvent,
+    ) -> Result<bool> {
+        // ---
+        let Some(wake) = &self.outbox else {
+            let changed = match write {
+                OutboxWrite::SaveCredential(credential) => {
+                    repository.save_credential(credential).await?;
+                    true
+                }
+                OutboxWrite::SoftDeleteCredential(id) => {
+                    repository.soft_delete_credential(&id).await?
+                }
+                OutboxWrite::MarkCredentialCompromised(id) => {
+                    repository.mark_credential_compromised(&id).await?
+                }
+                OutboxWrite::EventOnly => true,
+            };
+            if changed {
+                self.emit(event);
+            }
+            return Ok(changed);
+        };
+
+        let changed = repository
+            .write_with_event(write, &event.to_outbox()?)
+            .await?;
+        if changed {
+            wake.notify_one();
+        }
+        Ok(changed)
+    }
+}
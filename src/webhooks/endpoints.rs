@@ -0,0 +1,81 @@
+//! Webhook endpoint registry stored in Redis.
+//!
+//! All endpoints live in a single hash (`webhooks:endpoints`, see
+//! [`redis_keys`](crate::redis_keys)) keyed by
+//! endpoint ID, with the JSON-encoded [`WebhookEndpoint`] as the value.
+
+use crate::redis_keys;
+use crate::redis_source::RedisConnection;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// A registered webhook receiver.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookEndpoint {
+    // ---
+    pub id: Uuid,
+
+    /// URL each event is POSTed to.
+    pub url: String,
+
+    /// Shared secret used to sign payloads (HMAC-SHA256). Never returned by
+    /// the admin API after creation.
+    pub secret: String,
+
+    pub created_at: DateTime<Utc>,
+}
+
+/// Returns all registered endpoints, skipping (and logging) any that fail to decode.
+pub async fn list_endpoints(conn: &mut RedisConnection) -> Result<Vec<WebhookEndpoint>> {
+    // ---
+    let values: Vec<String> = conn.hvals(redis_keys::webhook_endpoints()).await?;
+
+    Ok(values
+        .iter()
+        .filter_map(|v| match serde_json::from_str(v) {
+            Ok(endpoint) => Some(endpoint),
+            Err(e) => {
+                tracing::error!("Skipping malformed webhook endpoint: {e}");
+                None
+            }
+        })
+        .collect())
+}
+
+/// Returns the endpoint with `id`, if registered.
+pub async fn get_endpoint(conn: &mut RedisConnection, id: Uuid) -> Result<Option<WebhookEndpoint>> {
+    // ---
+    let value: Option<String> = conn
+        .hget(redis_keys::webhook_endpoints(), id.to_string())
+        .await?;
+
+    Ok(value.map(|v| serde_json::from_str(&v)).transpose()?)
+}
+
+/// Inserts or replaces an endpoint.
+pub async fn save_endpoint(conn: &mut RedisConnection, endpoint: &WebhookEndpoint) -> Result<()> {
+    // ---
+    let value = serde_json::to_string(endpoint)?;
+    let _: () = conn
+        .hset(
+            redis_keys::webhook_endpoints(),
+            endpoint.id.to_string(),
+            value,
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Removes an endpoint. Returns `false` if it did not exist.
+pub async fn delete_endpoint(conn: &mut RedisConnection, id: Uuid) -> Result<bool> {
+    // ---
+    let removed: u32 = conn
+        .hdel(redis_keys::webhook_endpoints(), id.to_string())
+        .await?;
+
+    Ok(removed > 0)
+}
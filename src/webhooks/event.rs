@@ -0,0 +1,210 @@
+//! Auth events delivered to webhook endpoints.
+
+use crate::domain::OutboxEvent;
+use crate::trace_context::TraceContext;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Type of auth event, serialized as the payload `type` and the
+/// `X-Webhook-Event` header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WebhookEventKind {
+    // ---
+    /// A passkey registration completed.
+    #[serde(rename = "user.registered")]
+    UserRegistered,
+
+    /// A user signed in with a passkey for the first time.
+    #[serde(rename = "auth.new_device")]
+    NewDeviceLogin,
+
+    /// A user deleted one of their passkeys.
+    #[serde(rename = "credential.deleted")]
+    CredentialDeleted,
+
+    /// A sign-in suggested a passkey's authenticator has been cloned.
+    #[serde(rename = "credential.suspected_clone")]
+    CredentialSuspectedClone,
+
+    /// A session token was used from another network and user agent than
+    /// it was issued to.
+    #[serde(rename = "session.fingerprint_changed")]
+    SessionFingerprintChanged,
+}
+
+impl WebhookEventKind {
+    // ---
+    /// Wire name of the event (`user.registered`, ...).
+    pub fn as_str(&self) -> &'static str {
+        // ---
+        match self {
+            Self::UserRegistered => "user.registered",
+            Self::NewDeviceLogin => "auth.new_device",
+            Self::CredentialDeleted => "credential.deleted",
+            Self::CredentialSuspectedClone => "credential.suspected_clone",
+            Self::SessionFingerprintChanged => "session.fingerprint_changed",
+        }
+    }
+}
+
+/// Subject of an auth event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookEventData {
+    // ---
+    pub user_id: Uuid,
+    pub username: String,
+
+    /// Base64url-encoded credential ID (same encoding as the credentials API).
+    /// Empty, and left out of the payload, for session events.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub credential_id: String,
+}
+
+/// JSON body POSTed to every webhook endpoint.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookEvent {
+    // ---
+    /// Unique per event; identical across retries so receivers can deduplicate.
+    pub id: Uuid,
+
+    #[serde(rename = "type")]
+    pub kind: WebhookEventKind,
+
+    pub created_at: DateTime<Utc>,
+    pub data: WebhookEventData,
+
+    /// Trace of the request the event was emitted in. Sent as
+    /// `traceparent` / `tracestate` headers, not in the body.
+    #[serde(skip)]
+    pub trace: Option<TraceContext>,
+}
+
+impl WebhookEvent {
+    // ---
+    /// Creates an event of `kind` about `credential_id` (base64url) owned by
+    /// the user, in the trace of the current request, if any.
+    pub fn new(kind: WebhookEventKind, user_id: Uuid, username: &str, credential_id: &str) -> Self {
+        // ---
+        Self {
+            id: Uuid::new_v4(),
+            kind,
+            created_at: Utc::now(),
+            data: WebhookEventData {
+                user_id,
+                username: username.to_string(),
+                credential_id: credential_id.to_string(),
+            },
+            trace: TraceContext::current(),
+        }
+    }
+
+    /// Creates an event of `kind` about one of the user's sessions, in the
+    /// trace of the current request, if any.
+    pub fn for_session(kind: WebhookEventKind, user_id: Uuid, username: &str) -> Self {
+        // ---
+        Self::new(kind, user_id, username, "")
+    }
+
+    /// The event as stored in the outbox; the payload is the JSON body.
+    pub(crate) fn to_outbox(&self) -> serde_json::Result<OutboxEvent> {
+        // ---
+        Ok(OutboxEvent {
+            id: self.id,
+            kind: self.kind.as_str().to_string(),
+            payload: serde_json::to_string(self)?,
+            created_at: self.created_at,
+            attempts: 0,
+            traceparent: self.trace.as_ref().map(TraceContext::traceparent),
+            tracestate: self
+                .trace
+                .as_ref()
+                .and_then(|trace| trace.tracestate().map(str::to_string)),
+        })
+    }
+
+    /// The event stored as `stored` by [`to_outbox`](Self::to_outbox).
+    pub(crate) fn from_outbox(stored: &OutboxEvent) -> serde_json::Result<Self> {
+        // ---
+        let mut event: Self = serde_json::from_str(&stored.payload)?;
+        event.trace = stored.traceparent.as_deref().and_then(|traceparent| {
+            TraceContext::from_stored(traceparent, stored.tracestate.as_deref())
+        });
+        Ok(event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+
+    #[test]
+    fn event_serializes_with_dotted_type() {
+        // ---
+        let event = WebhookEvent::new(
+            WebhookEventKind::CredentialDeleted,
+            Uuid::nil(),
+            "bilbo",
+            "AQID",
+        );
+        let json = serde_json::to_value(&event).unwrap();
+
+        assert_eq!(json["type"], "credential.deleted");
+        assert_eq!(json["type"], event.kind.as_str());
+        assert_eq!(json["data"]["username"], "bilbo");
+        assert_eq!(json["data"]["credential_id"], "AQID");
+    }
+
+    #[test]
+    fn session_events_carry_no_credential() {
+        // ---
+        let event = WebhookEvent::for_session(
+            WebhookEventKind::SessionFingerprintChanged,
+            Uuid::nil(),
+            "bilbo",
+        );
+        let json = serde_json::to_value(&event).unwrap();
+
+        assert_eq!(json["type"], "session.fingerprint_changed");
+        assert!(json["data"].get("credential_id").is_none());
+        let restored: WebhookEvent = serde_json::from_value(json).unwrap();
+        assert!(restored.data.credential_id.is_empty());
+    }
+
+    #[test]
+    fn event_round_trips_through_the_outbox() {
+        // ---
+        let event = WebhookEvent::new(WebhookEventKind::UserRegistered, Uuid::nil(), "bilbo", "AQ");
+        let stored = event.to_outbox().unwrap();
+        assert_eq!(stored.id, event.id);
+        assert_eq!(stored.kind, "user.registered");
+
+        let restored = WebhookEvent::from_outbox(&stored).unwrap();
+        assert_eq!(restored.id, event.id);
+        assert_eq!(restored.created_at, event.created_at);
+        assert_eq!(restored.data.username, "bilbo");
+        assert!(restored.trace.is_none());
+    }
+
+    #[tokio::test]
+    async fn trace_is_kept_out_of_the_body_but_stored() {
+        // ---
+        let trace = TraceContext::new_root();
+        let event = trace
+            .clone()
+            .scope(async {
+                WebhookEvent::new(WebhookEventKind::UserRegistered, Uuid::nil(), "bilbo", "AQ")
+            })
+            .await;
+        assert_eq!(event.trace.as_ref(), Some(&trace));
+
+        let stored = event.to_outbox().unwrap();
+        assert!(!stored.payload.contains(&trace.trace_id()));
+        assert_eq!(stored.traceparent, Some(trace.traceparent()));
+
+        let restored = WebhookEvent::from_outbox(&stored).unwrap();
+        let restored = restored.trace.unwrap();
+        assert_eq!(restored.traceparent(), trace.traceparent());
+    }
+}
@@ -0,0 +1,13 @@
+// Gateway module - outbound webhook notifications
+// Modules are private, only exported symbols are public
+
+mod delivery;
+mod dispatcher;
+mod endpoints;
+mod event;
+
+pub use dispatcher::WebhookDispatcher;
+pub use endpoints::WebhookEndpoint;
+pub use event::{WebhookEvent, WebhookEventData, WebhookEventKind};
+
+pub(crate) use endpoints::{delete_endpoint, get_endpoint, list_endpoints, save_endpoint};
@@ -0,0 +1,222 @@
+// Passkey demo client for the axum-quickstart WebAuthn API.
+//
+// The server (webauthn-rs) exchanges binary fields as base64url strings,
+// while navigator.credentials works with ArrayBuffers. The helpers below
+// convert between the two in both directions.
+
+const API = "/api/v1";
+
+// ---------------------------------------------------------------------------
+// base64url <-> ArrayBuffer
+// ---------------------------------------------------------------------------
+
+function bufferToBase64url(buffer) {
+  const bytes = new Uint8Array(buffer);
+  let binary = "";
+  for (const byte of bytes) {
+    binary += String.fromCharCode(byte);
+  }
+  return btoa(binary).replace(/\+/g, "-").replace(/\//g, "_").replace(/=+$/, "");
+}
+
+function base64urlToBuffer(value) {
+  const base64 = value.replace(/-/g, "+").replace(/_/g, "/");
+  const padded = base64 + "=".repeat((4 - (base64.length % 4)) % 4);
+  const binary = atob(padded);
+  const bytes = new Uint8Array(binary.length);
+  for (let i = 0; i < binary.length; i++) {
+    bytes[i] = binary.charCodeAt(i);
+  }
+  return bytes.buffer;
+}
+
+// ---------------------------------------------------------------------------
+// Challenge JSON -> navigator.credentials options
+// ---------------------------------------------------------------------------
+
+function decodeCredentialDescriptors(list) {
+  return (list || []).map((c) => ({ ...c, id: base64urlToBuffer(c.id) }));
+}
+
+/** `challenge.publicKey` from /register/start -> options for credentials.create() */
+function decodeCreationOptions(publicKey) {
+  return {
+    ...publicKey,
+    challenge: base64urlToBuffer(publicKey.challenge),
+    user: { ...publicKey.user, id: base64urlToBuffer(publicKey.user.id) },
+    excludeCredentials: decodeCredentialDescriptors(publicKey.excludeCredentials),
+  };
+}
+
+/** `options.publicKey` from /auth/start -> options for credentials.get() */
+function decodeRequestOptions(publicKey) {
+  return {
+    ...publicKey,
+    challenge: base64urlToBuffer(publicKey.challenge),
+    allowCredentials: decodeCredentialDescriptors(publicKey.allowCredentials),
+  };
+}
+
+// ---------------------------------------------------------------------------
+// PublicKeyCredential -> JSON for /register/finish and /auth/finish
+// ---------------------------------------------------------------------------
+
+function encodeRegistration(credential) {
+  return {
+    id: credential.id,
+    rawId: bufferToBase64url(credential.rawId),
+    type: credential.type,
+    extensions: credential.getClientExtensionResults(),
+    response: {
+      attestationObject: bufferToBase64url(credential.response.attestationObject),
+      clientDataJSON: bufferToBase64url(credential.response.clientDataJSON),
+    },
+  };
+}
+
+function encodeAssertion(credential) {
+  const { response } = credential;
+  return {
+    id: credential.id,
+    rawId: bufferToBase64url(credential.rawId),
+    type: credential.type,
+    extensions: credential.getClientExtensionResults(),
+    response: {
+      authenticatorData: bufferToBase64url(response.authenticatorData),
+      clientDataJSON: bufferToBase64url(response.clientDataJSON),
+      signature: bufferToBase64url(response.signature),
+      userHandle: response.userHandle ? bufferToBase64url(response.userHandle) : null,
+    },
+  };
+}
+
+// ---------------------------------------------------------------------------
+// API calls
+// ---------------------------------------------------------------------------
+
+let sessionToken = null;
+
+async function api(method, path, body) {
+  const headers = { "Content-Type": "application/json" };
+  if (sessionToken) {
+    headers.Authorization = `Bearer ${sessionToken}`;
+  }
+  const response = await fetch(API + path, {
+    method,
+    headers,
+    body: body === undefined ? undefined : JSON.stringify(body),
+  });
+  const json = await response.json().catch(() => ({}));
+  if (!response.ok) {
+    throw new Error(json.error || `${method} ${path} failed with HTTP ${response.status}`);
+  }
+  return json;
+}
+
+async function register(username) {
+  const { challenge, flow_id } = await api("POST", "/webauthn/register/start", { username });
+  const credential = await navigator.credentials.create({
+    publicKey: decodeCreationOptions(challenge.publicKey),
+  });
+  return api("POST", "/webauthn/register/finish", {
+    username,
+    flow_id,
+    credential: encodeRegistration(credential),
+  });
+}
+
+async function signIn(username) {
+  const { options, flow_id } = await api("POST", "/webauthn/auth/start", { username });
+  const credential = await navigator.credentials.get({
+    publicKey: decodeRequestOptions(options.publicKey),
+  });
+  const result = await api("POST", "/webauthn/auth/finish", {
+    username,
+    flow_id,
+    credential: encodeAssertion(credential),
+  });
+  sessionToken = result.session_token;
+  return result;
+}
+
+async function listCredentials() {
+  const { data } = await api("GET", "/webauthn/credentials");
+  return data.credentials;
+}
+
+async function deleteCredential(id) {
+  return api("DELETE", `/webauthn/credentials/${encodeURIComponent(id)}`);
+}
+
+// ---------------------------------------------------------------------------
+// Page wiring
+// ---------------------------------------------------------------------------
+
+function log(message, isError = false) {
+  const line = document.createElement("div");
+  line.textContent = `${new Date().toLocaleTimeString()}  ${message}`;
+  if (isError) {
+    line.className = "error";
+  }
+  document.getElementById("log").prepend(line);
+}
+
+async function refreshCredentials() {
+  const list = document.getElementById("credentials");
+  list.replaceChildren();
+  for (const credential of await listCredentials()) {
+    const item = document.createElement("li");
+    const label = document.createElement("code");
+    label.textContent = `${credential.id} (created ${credential.created_at})`;
+    const remove = document.createElement("button");
+    remove.textContent = "Delete";
+    remove.onclick = () =>
+      run(async () => {
+        await deleteCredential(credential.id);
+        log(`Deleted passkey ${credential.id}`);
+        await refreshCredentials();
+      });
+    item.append(label, " ", remove);
+    list.append(item);
+  }
+}
+
+async function run(action) {
+  try {
+    await action();
+  } catch (e) {
+    log(e.message || String(e), true);
+  }
+}
+
+function username() {
+  const value = document.getElementById("username").value.trim();
+  if (!value) {
+    throw new Error("Enter a username first");
+  }
+  return value;
+}
+
+document.addEventListener("DOMContentLoaded", () => {
+  if (!window.PublicKeyCredential) {
+    log("This browser does not support WebAuthn", true);
+  }
+
+  document.getElementById("register").onclick = () =>
+    run(async () => {
+      const name = username();
+      const result = await register(name);
+      log(`Registered passkey ${result.credential_id} for ${name}`);
+    });
+
+  document.getElementById("signin").onclick = () =>
+    run(async () => {
+      const name = username();
+      await signIn(name);
+      log(`Signed in as ${name}`);
+      document.getElementById("session").hidden = false;
+      await refreshCredentials();
+    });
+
+  document.getElementById("refresh").onclick = () => run(refreshCredentials);
+});
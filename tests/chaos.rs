@@ -0,0 +1,210 @@
+//! Resilience tests driven by injected Redis and database faults.
+//!
+//! Run with `cargo test --features chaos --test chaos`.
+
+#![cfg(feature = "chaos")]
+
+use axum::body::Body;
+use axum::http::Request;
+use axum::Router;
+use axum_quickstart::domain::{Metrics, RuntimeSample};
+use axum_quickstart::{create_repository, AppBuilder, AppConfig, Chaos, Fault};
+use serde_json::Value;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tower::ServiceExt;
+
+mod common;
+
+/// Counts retried Redis operations and challenge failures; ignores
+/// everything else.
+#[derive(Default)]
+struct RetryCounter {
+    // ---
+    recovered: AtomicU32,
+    exhausted: AtomicU32,
+    expired_challenges: AtomicU32,
+    unavailable_challenges: AtomicU32,
+}
+
+impl Metrics for RetryCounter {
+    // ---
+    fn render(&self) -> String {
+        String::new()
+    }
+    fn record_movie_created(&self) {}
+    fn record_movie_updated(&self) {}
+    fn record_movie_deleted(&self) {}
+    fn record_http_request(&self, _: Instant, _: &str, _: &str, _: u16) {}
+    fn record_orphan_users_removed(&self, _: u64, _: bool) {}
+    fn record_retention_purged(&self, _: &str, _: u64, _: bool) {}
+    fn record_event_stream(&self, _: &str, _: u64) {}
+    fn record_event_stream_buffered(&self, _: usize) {}
+    fn record_webhook_delivered(&self) {}
+    fn record_webhook_dead_letter(&self) {}
+    fn record_sign_count_anomaly(&self, _: bool) {}
+    fn record_redis_retry(&self, recovered: bool) {
+        let counter = match recovered {
+            true => &self.recovered,
+            false => &self.exhausted,
+        };
+        counter.fetch_add(1, Ordering::SeqCst);
+    }
+    fn record_in_flight_request(&self, _: bool) {}
+    fn record_runtime_sample(&self, _: &RuntimeSample) {}
+    fn record_outbound_request(&self, _: &str, _: Option<u16>) {}
+    fn record_db_query(&self, _: &str, _: Duration) {}
+    fn record_challenge_failure(&self, _: &str, redis_error: bool) {
+        let counter = match redis_error {
+            true => &self.unavailable_challenges,
+            false => &self.expired_challenges,
+        };
+        counter.fetch_add(1, Ordering::SeqCst);
+    }
+    fn record_panic(&self) {}
+}
+
+async fn setup(chaos: &Chaos, metrics: Arc<RetryCounter>) -> Router {
+    // ---
+    common::setup_test_env().await;
+
+    let mut config = AppConfig::from_env().expect("config should load");
+    config.redis.retry_attempts = 3;
+    config.redis.retry_base_delay = Duration::from_millis(1);
+    let repository = create_repository(&config.database).await.unwrap();
+
+    AppBuilder::new()
+        .config(config)
+        .repository(repository)
+        .metrics(metrics)
+        .chaos(chaos.clone())
+        .build()
+        .unwrap()
+}
+
+/// `GET /api/v1/health?mode=full`: the status code and body.
+async fn full_health(router: &Router) -> (u16, Value) {
+    // ---
+    let request = Request::builder()
+        .uri("/api/v1/health?mode=full")
+        .body(Body::empty())
+        .unwrap();
+    let response = router.clone().oneshot(request).await.unwrap();
+    let status = response.status().as_u16();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn database_faults_degrade_health_until_cleared() {
+    // ---
+    let chaos = Chaos::new();
+    let router = setup(&chaos, Arc::default()).await;
+
+    chaos.set_database(Fault::failing(100));
+    let (status, body) = full_health(&router).await;
+    assert_eq!(status, 500);
+    assert_eq!(body["dependencies"]["database"]["status"], "degraded");
+    assert_eq!(body["dependencies"]["redis"]["status"], "ok");
+
+    chaos.clear();
+    let (status, body) = full_health(&router).await;
+    assert_eq!(status, 200, "{body}");
+
+    let request = Request::builder()
+        .uri("/api/v1/health/history")
+        .body(Body::empty())
+        .unwrap();
+    let response = router.clone().oneshot(request).await.unwrap();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let history: Value = serde_json::from_slice(&body).unwrap();
+    let transitions = history["transitions"].as_array().unwrap();
+    assert_eq!(transitions.len(), 2, "{history}");
+    assert_eq!(transitions[0]["dependency"], "database");
+    assert_eq!(transitions[0]["status"], "ok");
+    assert_eq!(transitions[1]["status"], "degraded");
+    assert!(transitions[1]["error"].is_string());
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn redis_faults_are_retried_then_reported() {
+    // ---
+    let chaos = Chaos::new();
+    let metrics = Arc::new(RetryCounter::default());
+    let router = setup(&chaos, metrics.clone()).await;
+
+    chaos.set_redis(Fault::failing(100));
+    let (status, body) = full_health(&router).await;
+    assert_eq!(status, 500);
+    assert_eq!(body["dependencies"]["redis"]["status"], "degraded");
+    assert_eq!(body["dependencies"]["database"]["status"], "ok");
+    assert_eq!(metrics.exhausted.load(Ordering::SeqCst), 1);
+
+    chaos.clear();
+    let (status, body) = full_health(&router).await;
+    assert_eq!(status, 200, "{body}");
+    assert_eq!(metrics.recovered.load(Ordering::SeqCst), 0);
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn latency_is_reported_by_health() {
+    // ---
+    let chaos = Chaos::new();
+    let router = setup(&chaos, Arc::default()).await;
+
+    chaos.set_database(Fault::default().with_latency(Duration::from_millis(200), 100));
+    let (status, body) = full_health(&router).await;
+    assert_eq!(status, 200, "{body}");
+    let latency_ms = body["dependencies"]["database"]["latency_ms"]
+        .as_f64()
+        .unwrap();
+    assert!(latency_ms >= 200.0, "latency_ms = {latency_ms}");
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn redis_outage_at_register_finish_is_not_an_expired_challenge() {
+    // ---
+    let chaos = Chaos::new();
+    let metrics = Arc::new(RetryCounter::default());
+    let router = setup(&chaos, metrics.clone()).await;
+
+    // Never verified: taking the challenge fails first
+    let finish = || {
+        let body = serde_json::json!({
+            "username": "chaos_user",
+            "flow_id": uuid::Uuid::new_v4(),
+            "credential": {
+                "id": "AA",
+                "rawId": "AA",
+                "response": { "attestationObject": "AA", "clientDataJSON": "AA" },
+                "type": "public-key",
+                "extensions": {},
+            },
+        });
+        Request::builder()
+            .method("POST")
+            .uri("/api/v1/webauthn/register/finish")
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
+
+    chaos.set_redis(Fault::failing(100));
+    let response = router.clone().oneshot(finish()).await.unwrap();
+    assert_eq!(response.status(), 503);
+    assert_eq!(metrics.unavailable_challenges.load(Ordering::SeqCst), 1);
+
+    chaos.clear();
+    let response = router.clone().oneshot(finish()).await.unwrap();
+    assert_eq!(response.status(), 400);
+    assert_eq!(metrics.expired_challenges.load(Ordering::SeqCst), 1);
+}
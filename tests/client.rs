@@ -0,0 +1,120 @@
+//! Integration tests for the typed API client (`--features client`).
+//!
+//! The client calls a server on a local port, so these also check that the
+//! client's types match the JSON the handlers send and accept.
+#![cfg(feature = "client")]
+
+use axum_quickstart::client::{Client, ClientError, ListMovies, Movie};
+#[cfg(feature = "test-utils")]
+use base64::Engine;
+use reqwest::StatusCode;
+use uuid::Uuid;
+
+mod common;
+
+async fn client() -> Client {
+    // ---
+    Client::new(format!("http://{}", common::serve_isolated().await))
+}
+
+#[tokio::test]
+async fn movies_round_trip_through_the_client() -> Result<(), ClientError> {
+    // ---
+    let client = client().await;
+    let genre = format!("g{}", &Uuid::new_v4().simple().to_string()[..12]);
+    let movie = Movie {
+        title: format!("Client movie {}", Uuid::new_v4()),
+        year: 1984,
+        stars: 4.0,
+        genres: vec![genre.clone()],
+    };
+
+    let stored = client.add_movie(&movie).await?;
+    assert_eq!(stored.movie, movie);
+    assert_eq!(client.get_movie(&stored.id).await?, movie);
+
+    let page = client
+        .list_movies(&ListMovies {
+            genre: Some(genre.clone()),
+            ..Default::default()
+        })
+        .await?;
+    assert_eq!(page.movies, vec![stored.clone()]);
+    assert_eq!(page.pagination.total, 1);
+    assert_eq!(page.links.next, None);
+
+    let updated = Movie {
+        stars: 5.0,
+        ..movie.clone()
+    };
+    client.update_movie(&stored.id, &updated).await?;
+    assert_eq!(client.get_movie(&stored.id).await?.stars, 5.0);
+
+    client.delete_movie(&stored.id).await?;
+    let missing = client.get_movie(&stored.id).await.unwrap_err();
+    assert_eq!(missing.status(), Some(StatusCode::NOT_FOUND));
+    assert_eq!(missing.code(), Some("movie_not_found"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn error_responses_carry_status_and_code() -> Result<(), ClientError> {
+    // ---
+    let client = client().await;
+    let movie = Movie {
+        title: format!("Client duplicate {}", Uuid::new_v4()),
+        year: 2001,
+        stars: 3.0,
+        genres: Vec::new(),
+    };
+    let stored = client.add_movie(&movie).await?;
+
+    let conflict = client.add_movie(&movie).await.unwrap_err();
+    assert!(matches!(conflict, ClientError::Api { .. }), "{conflict}");
+    assert_eq!(conflict.status(), Some(StatusCode::CONFLICT));
+    assert_eq!(conflict.code(), Some("movie_exists"));
+
+    // Calls needing a session fail before sending without one
+    let no_session = client.list_credentials(None, None).await.unwrap_err();
+    assert!(matches!(no_session, ClientError::NoSession));
+
+    client.delete_movie(&stored.id).await
+}
+
+#[cfg(feature = "test-utils")]
+#[tokio::test]
+async fn passkeys_register_sign_in_and_log_out() -> Result<(), Box<dyn std::error::Error>> {
+    // ---
+    let mut client = client().await;
+    let mut authenticator = common::mock_authenticator();
+    let username = format!("client_{}", &Uuid::new_v4().simple().to_string()[..12]);
+
+    let start = client.register_start(&username).await?;
+    let credential = authenticator.register(&start.challenge)?;
+    let credential_id = client
+        .register_finish(&username, start.flow_id, &credential)
+        .await?;
+
+    let start = client.auth_start(&username).await?;
+    let assertion = authenticator.authenticate(&start.options)?;
+    let token = client
+        .auth_finish(&username, start.flow_id, &assertion)
+        .await?;
+    assert_eq!(client.session_token(), Some(token.as_str()));
+
+    let page = client.list_credentials(None, None).await?;
+    assert_eq!(page.pagination.total, 1);
+    let listed_id =
+        base64::engine::general_purpose::URL_SAFE_NO_PAD.decode(&page.credentials[0].id)?;
+    assert_eq!(hex::encode(listed_id), credential_id);
+    assert!(page.credentials[0].last_used_at.is_some());
+
+    client.logout().await?;
+    assert_eq!(client.session_token(), None);
+
+    // The old token no longer works
+    let client = client.with_session_token(token);
+    let expired = client.list_credentials(None, None).await.unwrap_err();
+    assert_eq!(expired.status(), Some(StatusCode::UNAUTHORIZED));
+    Ok(())
+}
@@ -0,0 +1,261 @@
+//! Contract tests between the typed client and the server
+//! (`--features client,test-utils`).
+//!
+//! Every endpoint the client covers is called through [`Client`] against the
+//! router served in-process, and its raw JSON is fetched alongside. Each
+//! client model must read that JSON and write it back unchanged, so a field
+//! added, renamed, or dropped on either side fails here.
+
+#![cfg(all(feature = "client", feature = "test-utils"))]
+
+use axum_quickstart::client::{
+    AuthenticationStart, Client, ClientError, CredentialInfo, ListMovies, Movie, RegistrationStart,
+    StoredMovie,
+};
+use axum_quickstart::prelude::{Pagination, ResponseLinks};
+use reqwest::{Method, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use uuid::Uuid;
+
+mod common;
+
+/// The server under test, on a local port.
+struct Server {
+    // ---
+    addr: SocketAddr,
+    http: reqwest::Client,
+}
+
+impl Server {
+    // ---
+    async fn start() -> Self {
+        // ---
+        Self {
+            addr: common::serve_isolated().await,
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// A typed client of this server.
+    fn client(&self) -> Client {
+        // ---
+        Client::new(format!("http://{}", self.addr))
+    }
+
+    /// The raw JSON body of a call, and its status.
+    async fn raw(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<Value>,
+        token: Option<&str>,
+    ) -> (StatusCode, Value) {
+        // ---
+        let url = format!("http://{}/api/v1{path}", self.addr);
+        let mut request = self.http.request(method, url);
+        if let Some(body) = body {
+            request = request.json(&body);
+        }
+        if let Some(token) = token {
+            request = request.bearer_auth(token);
+        }
+        let response = request.send().await.unwrap();
+        let status = response.status();
+        (status, response.json().await.unwrap_or(Value::Null))
+    }
+}
+
+/// Reads `raw` as a `T` and checks that `T` writes it back unchanged.
+fn round_trip<T: Serialize + DeserializeOwned>(raw: &Value) -> T {
+    // ---
+    let value: T = serde_json::from_value(raw.clone())
+        .unwrap_or_else(|e| panic!("{} does not read {raw}: {e}", std::any::type_name::<T>()));
+    assert_eq!(
+        &serde_json::to_value(&value).unwrap(),
+        raw,
+        "{} does not write back what it read",
+        std::any::type_name::<T>()
+    );
+    value
+}
+
+fn unique(prefix: &str) -> String {
+    // ---
+    format!("{prefix}{}", &Uuid::new_v4().simple().to_string()[..12])
+}
+
+#[tokio::test]
+async fn movie_models_match_the_server() -> Result<(), ClientError> {
+    // ---
+    let server = Server::start().await;
+    let client = server.client();
+    let genre = unique("c");
+
+    // Stars are binary fractions so they survive the f32 round trip
+    let movies = [
+        Movie {
+            title: unique("Contract A "),
+            year: 1968,
+            stars: 4.5,
+            genres: vec![genre.clone(), "drama".into()],
+        },
+        Movie {
+            title: unique("Contract B "),
+            year: 1979,
+            stars: 3.25,
+            genres: vec![genre.clone()],
+        },
+    ];
+
+    // add: the 201 body is the stored movie
+    let mut stored = Vec::new();
+    for movie in &movies {
+        let added = client.add_movie(movie).await?;
+        assert_eq!(&added.movie, movie);
+        stored.push(added);
+    }
+
+    // get
+    let path = format!("/movies/get/{}", stored[0].id);
+    let (status, body) = server.raw(Method::GET, &path, None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    let movie: Movie = round_trip(&body["data"]);
+    assert_eq!(client.get_movie(&stored[0].id).await?, movie);
+
+    // list: both pages of one movie, with their pagination and links
+    for page in [1, 2] {
+        let query = ListMovies {
+            genre: Some(genre.clone()),
+            page: Some(page),
+            per_page: Some(1),
+        };
+        let path = format!("/movies/list?genre={genre}&page={page}&per_page=1");
+        let (status, body) = server.raw(Method::GET, &path, None, None).await;
+        assert_eq!(status, StatusCode::OK);
+        let movies: Vec<StoredMovie> = round_trip(&body["data"]["movies"]);
+        let pagination: Pagination = round_trip(&body["meta"]["pagination"]);
+        let links: ResponseLinks = round_trip(&body["links"]);
+
+        let typed = client.list_movies(&query).await?;
+        assert_eq!(typed.movies, movies);
+        assert_eq!(typed.pagination, pagination);
+        assert_eq!(typed.links, links);
+        assert_eq!(typed.pagination.total, 2);
+        assert_eq!(typed.links.next.is_some(), page == 1);
+        assert_eq!(typed.links.prev.is_some(), page == 2);
+    }
+
+    // update and delete
+    let updated = Movie {
+        stars: 5.0,
+        genres: Vec::new(),
+        ..movies[1].clone()
+    };
+    client.update_movie(&stored[1].id, &updated).await?;
+    assert_eq!(client.get_movie(&stored[1].id).await?, updated);
+    for movie in &stored {
+        client.delete_movie(&movie.id).await?;
+    }
+
+    // Errors keep the server's status and code
+    let missing = client.get_movie(&stored[0].id).await.unwrap_err();
+    assert_eq!(missing.status(), Some(StatusCode::NOT_FOUND));
+    assert_eq!(missing.code(), Some("movie_not_found"));
+    let invalid = Movie {
+        stars: 7.0,
+        ..movies[0].clone()
+    };
+    let rejected = client.add_movie(&invalid).await.unwrap_err();
+    assert_eq!(rejected.status(), Some(StatusCode::BAD_REQUEST));
+    assert!(rejected.code().is_some(), "{rejected}");
+    Ok(())
+}
+
+#[tokio::test]
+async fn webauthn_models_match_the_server() -> Result<(), Box<dyn std::error::Error>> {
+    // ---
+    let server = Server::start().await;
+    let mut client = server.client();
+    let mut authenticator = common::mock_authenticator();
+    let username = unique("contract_");
+
+    // Start responses are single-use, so the raw ones come from their own
+    // flows; the typed flows are finished below.
+    let start = json!({ "username": username });
+    let (status, body) = server
+        .raw(
+            Method::POST,
+            "/webauthn/register/start",
+            Some(start.clone()),
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    round_trip::<RegistrationStart>(&body);
+
+    // Two passkeys, so one can be deleted
+    let mut registered = Vec::new();
+    for _ in 0..2 {
+        let start = client.register_start(&username).await?;
+        let credential = authenticator.register(&start.challenge)?;
+        registered.push(
+            client
+                .register_finish(&username, start.flow_id, &credential)
+                .await?,
+        );
+    }
+    assert_ne!(registered[0], registered[1]);
+
+    let (status, body) = server
+        .raw(Method::POST, "/webauthn/auth/start", Some(start), None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    round_trip::<AuthenticationStart>(&body);
+
+    let start = client.auth_start(&username).await?;
+    let assertion = authenticator.authenticate(&start.options)?;
+    let token = client
+        .auth_finish(&username, start.flow_id, &assertion)
+        .await?;
+
+    // credentials: the listing, its pagination, and links
+    let (status, body) = server
+        .raw(
+            Method::GET,
+            "/webauthn/credentials?per_page=1",
+            None,
+            Some(&token),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let credentials: Vec<CredentialInfo> = round_trip(&body["data"]["credentials"]);
+    let pagination: Pagination = round_trip(&body["meta"]["pagination"]);
+    let links: ResponseLinks = round_trip(&body["links"]);
+
+    let page = client.list_credentials(None, Some(1)).await?;
+    assert_eq!(
+        serde_json::to_value(&page.credentials)?,
+        serde_json::to_value(&credentials)?
+    );
+    assert_eq!(page.pagination, pagination);
+    assert_eq!(page.links, links);
+    assert_eq!(page.pagination.total, 2);
+
+    // delete one of them
+    let all = client.list_credentials(None, None).await?;
+    client.delete_credential(&all.credentials[0].id).await?;
+    let remaining = client.list_credentials(None, None).await?;
+    assert_eq!(remaining.pagination.total, 1);
+    assert_eq!(remaining.credentials[0].id, all.credentials[1].id);
+
+    // logout
+    client.logout().await?;
+    let (status, _) = server
+        .raw(Method::GET, "/webauthn/credentials", None, Some(&token))
+        .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    Ok(())
+}
@@ -0,0 +1,109 @@
+//! Throwaway Postgres and Redis for integration tests.
+//!
+//! With `AXUM_TEST_CONTAINERS=1`, [`start`] runs `postgres:16-alpine` and
+//! `redis:7-alpine` (the images in `docker-compose.yml`) through
+//! `testcontainers`, points `DATABASE_URL` and `REDIS_URL` at them, and
+//! applies `./migrations`. Each test binary gets its own pair, started on
+//! first use and removed when the binary exits, so no services need to be
+//! running on localhost. Requires a Docker daemon.
+
+use sqlx::migrate::Migrator;
+use sqlx::postgres::PgPoolOptions;
+use std::path::Path;
+use std::sync::Mutex;
+use testcontainers_modules::postgres::Postgres;
+use testcontainers_modules::redis::{Redis, REDIS_PORT};
+use testcontainers_modules::testcontainers::runners::SyncRunner;
+use testcontainers_modules::testcontainers::{Container, ImageExt};
+use tokio::sync::OnceCell;
+
+const POSTGRES_PORT: u16 = 5432;
+
+/// Kept alive until exit; dropping a container removes it.
+static CONTAINERS: Mutex<Option<(Container<Postgres>, Container<Redis>)>> = Mutex::new(None);
+
+static STARTED: OnceCell<()> = OnceCell::const_new();
+
+/// Whether `AXUM_TEST_CONTAINERS=1` is set.
+pub fn enabled() -> bool {
+    // ---
+    std::env::var("AXUM_TEST_CONTAINERS").is_ok_and(|v| v == "1")
+}
+
+/// Starts the containers once per test binary, overrides `DATABASE_URL`
+/// and `REDIS_URL`, and migrates the database.
+///
+/// # Panics
+/// Panics if Docker is unavailable or the migrations fail.
+pub async fn start() {
+    // ---
+    STARTED
+        .get_or_init(|| async {
+            // The sync runner drives its own runtime, which cannot start
+            // inside the test's
+            let (database_url, redis_url) = std::thread::spawn(run_containers)
+                .join()
+                .expect("Failed to start test containers");
+
+            std::env::set_var("DATABASE_URL", &database_url);
+            std::env::set_var("REDIS_URL", &redis_url);
+            migrate(&database_url).await;
+
+            // SAFETY: `remove_containers` is a plain `extern "C"` function
+            // with no arguments, as `atexit` expects
+            unsafe { libc::atexit(remove_containers) };
+        })
+        .await;
+}
+
+/// Starts both containers and returns their `(DATABASE_URL, REDIS_URL)`.
+fn run_containers() -> (String, String) {
+    // ---
+    let postgres = Postgres::default()
+        .with_db_name("axum_db")
+        .with_tag("16-alpine")
+        .start()
+        .expect("Failed to start Postgres container (is Docker running?)");
+    let redis = Redis::default()
+        .with_tag("7-alpine")
+        .start()
+        .expect("Failed to start Redis container (is Docker running?)");
+
+    let database_url = format!(
+        "postgres://postgres:postgres@{}:{}/axum_db",
+        postgres.get_host().unwrap(),
+        postgres.get_host_port_ipv4(POSTGRES_PORT).unwrap()
+    );
+    let redis_url = format!(
+        "redis://{}:{}",
+        redis.get_host().unwrap(),
+        redis.get_host_port_ipv4(REDIS_PORT).unwrap()
+    );
+
+    *CONTAINERS.lock().unwrap() = Some((postgres, redis));
+    (database_url, redis_url)
+}
+
+async fn migrate(database_url: &str) {
+    // ---
+    let pool = PgPoolOptions::new()
+        .max_connections(1)
+        .connect(database_url)
+        .await
+        .expect("Failed to connect to Postgres container");
+    Migrator::new(Path::new(env!("CARGO_MANIFEST_DIR")).join("migrations"))
+        .await
+        .expect("Failed to load migrations")
+        .run(&pool)
+        .await
+        .expect("Failed to run migrations");
+    pool.close().await;
+}
+
+/// Statics are never dropped, so the containers are removed at exit.
+extern "C" fn remove_containers() {
+    // ---
+    if let Ok(mut containers) = CONTAINERS.lock() {
+        drop(containers.take());
+    }
+}
@@ -1,13 +1,22 @@
 // Test helpers are intentionally partially used
 #![allow(dead_code)]
 
-use axum_quickstart::create_router;
+mod containers;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use axum::Router;
 use axum_quickstart::domain::init_database_with_retry_from_env;
+#[cfg(feature = "test-utils")]
+use axum_quickstart::test_utils::MockAuthenticator;
+use axum_quickstart::{create_repository, create_router, AppBuilder, AppConfig};
 use reqwest::Client;
+use serde_json::Value;
 use std::sync::Once;
 use std::time::Duration;
 use tokio::net::TcpListener;
 use tokio::time::sleep;
+use tower::ServiceExt;
 
 macro_rules! set_env_if_unset {
     // ---
@@ -25,8 +34,15 @@ static INIT: Once = Once::new();
 // ============================================================================
 
 /// Initialize test environment (database, Redis, env vars) variables once
+///
+/// With `AXUM_TEST_CONTAINERS=1`, Postgres and Redis run in throwaway
+/// containers instead of on localhost (see [`containers`]).
 pub async fn setup_test_env() {
     // ---
+    if containers::enabled() {
+        containers::start().await;
+    }
+
     // Set required environment variables for testing
     INIT.call_once(|| {
         // ---
@@ -78,3 +94,128 @@ impl TestServer {
         format!("http://{}{}", self.addr, path)
     }
 }
+
+/// A router with its own repository. Unlike [`create_router`], which uses
+/// the process-wide repository, no connection pool outlives the calling
+/// test's runtime.
+pub async fn isolated_router() -> Router {
+    // ---
+    setup_test_env().await;
+
+    let config = AppConfig::from_env().unwrap();
+    let repository = create_repository(&config.database).await.unwrap();
+    AppBuilder::new()
+        .config(config)
+        .repository(repository)
+        .build()
+        .unwrap()
+}
+
+/// Serves [`isolated_router`] on a local port and returns the address.
+pub async fn serve_isolated() -> std::net::SocketAddr {
+    // ---
+    let router = isolated_router().await;
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+    addr
+}
+
+// ============================================================================
+// Request Helpers
+// ============================================================================
+
+/// POSTs `body` as JSON through `router`; returns the status and JSON body
+/// (`Value::Null` if the body is not JSON).
+pub async fn post_json(router: &Router, uri: &str, body: Value) -> (StatusCode, Value) {
+    // ---
+    let request = Request::builder()
+        .method("POST")
+        .uri(uri)
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+
+    let response = router.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (
+        status,
+        serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+    )
+}
+
+// ============================================================================
+// WebAuthn Helpers (software authenticator)
+// ============================================================================
+
+/// A software authenticator for the configured `AXUM_WEBAUTHN_ORIGIN`.
+#[cfg(feature = "test-utils")]
+pub fn mock_authenticator() -> MockAuthenticator {
+    // ---
+    MockAuthenticator::new(std::env::var("AXUM_WEBAUTHN_ORIGIN").unwrap())
+}
+
+/// Runs `register/start` and `register/finish` for `username` with a new
+/// passkey from `authenticator`; returns the finish status and body.
+#[cfg(feature = "test-utils")]
+pub async fn register_passkey(
+    router: &Router,
+    authenticator: &mut MockAuthenticator,
+    username: &str,
+) -> (StatusCode, Value) {
+    // ---
+    let (status, start) = post_json(
+        router,
+        "/api/v1/webauthn/register/start",
+        serde_json::json!({ "username": username }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "register/start failed: {start}");
+
+    let options = serde_json::from_value(start["challenge"].clone()).unwrap();
+    let credential = authenticator.register(&options).unwrap();
+    post_json(
+        router,
+        "/api/v1/webauthn/register/finish",
+        serde_json::json!({
+            "username": username,
+            "flow_id": start["flow_id"],
+            "credential": credential,
+        }),
+    )
+    .await
+}
+
+/// Runs `auth/start` and `auth/finish` for `username` with `authenticator`;
+/// returns the finish status and body.
+#[cfg(feature = "test-utils")]
+pub async fn sign_in(
+    router: &Router,
+    authenticator: &mut MockAuthenticator,
+    username: &str,
+) -> (StatusCode, Value) {
+    // ---
+    let (status, start) = post_json(
+        router,
+        "/api/v1/webauthn/auth/start",
+        serde_json::json!({ "username": username }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "auth/start failed: {start}");
+
+    let options = serde_json::from_value(start["options"].clone()).unwrap();
+    let credential = authenticator.authenticate(&options).unwrap();
+    post_json(
+        router,
+        "/api/v1/webauthn/auth/finish",
+        serde_json::json!({
+            "username": username,
+            "flow_id": start["flow_id"],
+            "credential": credential,
+        }),
+    )
+    .await
+}
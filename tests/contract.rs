@@ -0,0 +1,787 @@
+//! Golden-file contract tests for the JSON API.
+//!
+//! Every JSON endpoint's success and error responses are snapshotted with
+//! `insta` (`tests/snapshots/`), so renaming or dropping a field fails here
+//! before it breaks a client. Streams (`/events`, `/ws`), `/metrics`, and
+//! the HTML pages are not JSON and are covered by the integration tests. Values that differ between runs (IDs,
+//! challenges, tokens, timestamps, timings) are redacted; the field names
+//! and the rest of the shape are not.
+//!
+//! After an intended change, review and accept the new snapshots with
+//! `cargo insta review` (or `INSTA_UPDATE=always cargo test ...`).
+
+#![cfg(feature = "test-utils")]
+
+use axum::body::Body;
+use axum::http::Request;
+use axum::Router;
+use axum_quickstart::test_utils::Fixtures;
+use axum_quickstart::{create_repository, AppBuilder, AppConfig};
+use serde_json::{json, Value};
+use tower::ServiceExt;
+
+mod common;
+
+const ADMIN_TOKEN: &str = "contract-admin-token";
+const SERVICE_TOKEN: &str = "contract-service-token";
+
+/// Snapshots a response from [`send`] with run-specific values redacted.
+macro_rules! snapshot {
+    // ---
+    ($name:expr, $response:expr) => {
+        insta::assert_json_snapshot!($name, $response, {
+            ".**.elapsed_ms" => "[elapsed_ms]",
+            ".**.latency_ms" => "[latency_ms]",
+            ".**.flow_id" => "[flow_id]",
+            ".**.challenge.publicKey.challenge" => "[challenge]",
+            ".**.challenge.publicKey.user.id" => "[user_id]",
+            ".**.options.publicKey.challenge" => "[challenge]",
+            ".**.options.publicKey.allowCredentials[].id" => "[credential_id]",
+            ".**.credential_id" => "[credential_id]",
+            ".**.session_token" => "[session_token]",
+            ".**.csrf_token" => "[csrf_token]",
+            ".**.created_at" => "[timestamp]",
+            ".**.last_used_at" => "[timestamp]",
+            ".body.data.id" => "[id]",
+            ".body.data[].id" => "[id]",
+            ".body.data.credentials[].id" => "[credential_id]",
+            ".body.data.movies[].id" => "[id]",
+            ".body.data.secret" => "[secret]",
+            ".body.data.cutoff" => "[timestamp]",
+            ".body.data.user_id" => "[user_id]",
+            ".body.data.expires_at" => "[timestamp]",
+            ".body.data.*.resets_at" => "[timestamp]",
+        });
+    };
+}
+
+/// A router with the admin API enabled, and fixtures on the same stores.
+async fn setup() -> (Router, Fixtures) {
+    // ---
+    setup_with(|_| {}).await
+}
+
+/// Like [`setup`], with further changes to the configuration.
+async fn setup_with(configure: impl FnOnce(&mut AppConfig)) -> (Router, Fixtures) {
+    // ---
+    common::setup_test_env().await;
+
+    let mut config = AppConfig::from_env().unwrap();
+    config.admin.api_token = Some(ADMIN_TOKEN.to_string());
+    config.admin.service_tokens = vec![SERVICE_TOKEN.to_string()];
+    configure(&mut config);
+    let repository = create_repository(&config.database).await.unwrap();
+    let redis = redis::Client::open(config.redis.url.clone())
+        .unwrap()
+        .get_multiplexed_async_connection()
+        .await
+        .unwrap();
+
+    let fixtures = Fixtures::new(repository.clone(), redis);
+    let router = AppBuilder::new()
+        .config(config)
+        .repository(repository)
+        .build()
+        .unwrap();
+    (router, fixtures)
+}
+
+/// Sends a request and returns `{ "status": ..., "body": ... }`. The body
+/// is JSON if it parses, a string if not, and null if empty.
+async fn send(
+    router: &Router,
+    method: &str,
+    uri: &str,
+    bearer: Option<&str>,
+    body: Option<Value>,
+) -> Value {
+    // ---
+    let mut request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("x-request-id", "contract-test")
+        .header("content-type", "application/json");
+    if let Some(token) = bearer {
+        request = request.header("authorization", format!("Bearer {token}"));
+    }
+    let request = request
+        .body(body.map_or_else(Body::empty, |b| Body::from(b.to_string())))
+        .unwrap();
+
+    let response = router.clone().oneshot(request).await.unwrap();
+    let status = response.status().as_u16();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body = if bytes.is_empty() {
+        Value::Null
+    } else {
+        serde_json::from_slice(&bytes)
+            .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&bytes).into_owned()))
+    };
+    json!({ "status": status, "body": body })
+}
+
+// ============================================================================
+// Core
+// ============================================================================
+
+#[tokio::test]
+#[serial_test::serial]
+async fn health_contract() {
+    // ---
+    let (router, _) = setup().await;
+
+    snapshot!(
+        "health",
+        send(&router, "GET", "/api/v1/health", None, None).await
+    );
+    snapshot!(
+        "health_full",
+        send(&router, "GET", "/api/v1/health?mode=full", None, None).await
+    );
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn csrf_contract() {
+    // ---
+    let (router, mut fixtures) = setup().await;
+    let user = fixtures.user("contract_csrf").insert().await.unwrap();
+    let token = fixtures.session(&user).insert().await.unwrap();
+
+    snapshot!(
+        "csrf_without_session",
+        send(&router, "GET", "/api/v1/csrf", None, None).await
+    );
+
+    let request = Request::builder()
+        .uri("/api/v1/csrf")
+        .header("x-request-id", "contract-test")
+        .header(
+            "cookie",
+            format!("{}={token}", axum_quickstart::SESSION_COOKIE),
+        )
+        .body(Body::empty())
+        .unwrap();
+    let response = router.clone().oneshot(request).await.unwrap();
+    let status = response.status().as_u16();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body: Value = serde_json::from_slice(&bytes).unwrap();
+    snapshot!("csrf", json!({ "status": status, "body": body }));
+
+    fixtures.reset().await.unwrap();
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn fallback_contract() {
+    // ---
+    let (router, _) = setup().await;
+
+    snapshot!(
+        "not_found",
+        send(&router, "GET", "/api/v1/nonexistent", None, None).await
+    );
+    snapshot!(
+        "method_not_allowed",
+        send(&router, "PATCH", "/api/v1/movies/add", None, None).await
+    );
+}
+
+// ============================================================================
+// Movies
+// ============================================================================
+
+#[tokio::test]
+#[serial_test::serial]
+async fn movies_contract() {
+    // ---
+    let (router, mut fixtures) = setup().await;
+    let movie = json!({ "title": "Contract Movie", "year": 2001, "stars": 4.5 });
+
+    // Clear a copy left by an interrupted run
+    let stale = fixtures
+        .movie("Contract Movie", 2001)
+        .insert()
+        .await
+        .unwrap();
+    send(
+        &router,
+        "DELETE",
+        &format!("/api/v1/movies/delete/{stale}"),
+        None,
+        None,
+    )
+    .await;
+
+    let created = send(
+        &router,
+        "POST",
+        "/api/v1/movies/add",
+        None,
+        Some(movie.clone()),
+    )
+    .await;
+    let id = created["body"]["data"]["id"].as_str().unwrap().to_string();
+    snapshot!("movies_add", created);
+    snapshot!(
+        "movies_add_conflict",
+        send(&router, "POST", "/api/v1/movies/add", None, Some(movie)).await
+    );
+    snapshot!(
+        "movies_add_invalid",
+        send(
+            &router,
+            "POST",
+            "/api/v1/movies/add",
+            None,
+            Some(json!({ "title": " ", "year": 2001, "stars": 4.5 }))
+        )
+        .await
+    );
+
+    snapshot!(
+        "movies_get",
+        send(
+            &router,
+            "GET",
+            &format!("/api/v1/movies/get/{id}"),
+            None,
+            None
+        )
+        .await
+    );
+    fixtures
+        .movie("Contract Listed", 1999)
+        .genres(&["contract"])
+        .insert()
+        .await
+        .unwrap();
+    snapshot!(
+        "movies_list",
+        send(
+            &router,
+            "GET",
+            "/api/v1/movies/list?genre=contract",
+            None,
+            None
+        )
+        .await
+    );
+    snapshot!(
+        "movies_list_invalid_genre",
+        send(&router, "GET", "/api/v1/movies/list?genre=%21", None, None).await
+    );
+    fixtures.reset().await.unwrap();
+
+    snapshot!(
+        "movies_get_not_found",
+        send(&router, "GET", "/api/v1/movies/get/missing", None, None).await
+    );
+
+    let updated = json!({ "title": "Contract Movie", "year": 2001, "stars": 2.0 });
+    snapshot!(
+        "movies_update",
+        send(
+            &router,
+            "PUT",
+            &format!("/api/v1/movies/update/{id}"),
+            None,
+            Some(updated.clone())
+        )
+        .await
+    );
+
+    snapshot!(
+        "movies_delete",
+        send(
+            &router,
+            "DELETE",
+            &format!("/api/v1/movies/delete/{id}"),
+            None,
+            None
+        )
+        .await
+    );
+    snapshot!(
+        "movies_delete_not_found",
+        send(
+            &router,
+            "DELETE",
+            &format!("/api/v1/movies/delete/{id}"),
+            None,
+            None
+        )
+        .await
+    );
+
+    // Update stores the movie whether or not it exists
+    snapshot!(
+        "movies_update_absent",
+        send(
+            &router,
+            "PUT",
+            &format!("/api/v1/movies/update/{id}"),
+            None,
+            Some(updated)
+        )
+        .await
+    );
+    send(
+        &router,
+        "DELETE",
+        &format!("/api/v1/movies/delete/{id}"),
+        None,
+        None,
+    )
+    .await;
+}
+
+// ============================================================================
+// WebAuthn
+// ============================================================================
+
+#[tokio::test]
+#[serial_test::serial]
+async fn registration_contract() {
+    // ---
+    let (router, mut fixtures) = setup().await;
+    let user = fixtures.user("contract_register").insert().await.unwrap();
+    let mut authenticator = common::mock_authenticator();
+
+    let start = send(
+        &router,
+        "POST",
+        "/api/v1/webauthn/register/start",
+        None,
+        Some(json!({ "username": user.username })),
+    )
+    .await;
+    snapshot!("register_start", start.clone());
+
+    let options = serde_json::from_value(start["body"]["challenge"].clone()).unwrap();
+    let credential = authenticator.register(&options).unwrap();
+    let finish = json!({
+        "username": user.username,
+        "flow_id": start["body"]["flow_id"],
+        "credential": credential,
+    });
+    snapshot!(
+        "register_finish",
+        send(
+            &router,
+            "POST",
+            "/api/v1/webauthn/register/finish",
+            None,
+            Some(finish.clone())
+        )
+        .await
+    );
+    snapshot!(
+        "register_finish_expired",
+        send(
+            &router,
+            "POST",
+            "/api/v1/webauthn/register/finish",
+            None,
+            Some(finish)
+        )
+        .await
+    );
+
+    fixtures.reset().await.unwrap();
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn authentication_contract() {
+    // ---
+    let (router, mut fixtures) = setup().await;
+    let user = fixtures.user("contract_auth").insert().await.unwrap();
+    let mut authenticator = common::mock_authenticator();
+    common::register_passkey(&router, &mut authenticator, &user.username).await;
+
+    snapshot!(
+        "auth_start_unknown_user",
+        send(
+            &router,
+            "POST",
+            "/api/v1/webauthn/auth/start",
+            None,
+            Some(json!({ "username": "contract_nobody" }))
+        )
+        .await
+    );
+
+    let start = send(
+        &router,
+        "POST",
+        "/api/v1/webauthn/auth/start",
+        None,
+        Some(json!({ "username": user.username })),
+    )
+    .await;
+    snapshot!("auth_start", start.clone());
+
+    let options = serde_json::from_value(start["body"]["options"].clone()).unwrap();
+    let credential = authenticator.authenticate(&options).unwrap();
+    let finish = json!({
+        "username": user.username,
+        "flow_id": start["body"]["flow_id"],
+        "credential": credential,
+    });
+    let signed_in = send(
+        &router,
+        "POST",
+        "/api/v1/webauthn/auth/finish",
+        None,
+        Some(finish.clone()),
+    )
+    .await;
+    let token = signed_in["body"]["session_token"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    snapshot!("auth_finish", signed_in);
+    snapshot!(
+        "auth_finish_expired",
+        send(
+            &router,
+            "POST",
+            "/api/v1/webauthn/auth/finish",
+            None,
+            Some(finish)
+        )
+        .await
+    );
+
+    snapshot!(
+        "logout",
+        send(
+            &router,
+            "POST",
+            "/api/v1/webauthn/logout",
+            Some(&token),
+            None
+        )
+        .await
+    );
+    snapshot!(
+        "logout_without_session",
+        send(&router, "POST", "/api/v1/webauthn/logout", None, None).await
+    );
+
+    fixtures.reset().await.unwrap();
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn credentials_contract() {
+    // ---
+    let (router, mut fixtures) = setup().await;
+    let user = fixtures
+        .user("contract_credentials")
+        .insert()
+        .await
+        .unwrap();
+    let credential = fixtures
+        .credential(&user)
+        .backup(true, true)
+        .insert()
+        .await
+        .unwrap();
+    fixtures.credential(&user).insert().await.unwrap();
+    let token = fixtures.session(&user).insert().await.unwrap();
+
+    snapshot!(
+        "credentials_list",
+        send(
+            &router,
+            "GET",
+            "/api/v1/webauthn/credentials",
+            Some(&token),
+            None
+        )
+        .await
+    );
+    snapshot!(
+        "credentials_list_unauthorized",
+        send(&router, "GET", "/api/v1/webauthn/credentials", None, None).await
+    );
+
+    let id = base64_url(&credential.id);
+    snapshot!(
+        "credentials_delete",
+        send(
+            &router,
+            "DELETE",
+            &format!("/api/v1/webauthn/credentials/{id}"),
+            Some(&token),
+            None
+        )
+        .await
+    );
+    snapshot!(
+        "credentials_delete_not_found",
+        send(
+            &router,
+            "DELETE",
+            &format!("/api/v1/webauthn/credentials/{id}"),
+            Some(&token),
+            None
+        )
+        .await
+    );
+
+    fixtures.reset().await.unwrap();
+}
+
+fn base64_url(bytes: &[u8]) -> String {
+    // ---
+    use base64::Engine;
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(bytes)
+}
+
+// ============================================================================
+// Session introspection
+// ============================================================================
+
+#[tokio::test]
+#[serial_test::serial]
+async fn introspect_contract() {
+    // ---
+    let (router, mut fixtures) = setup().await;
+    let user = fixtures.user("contract_introspect").insert().await.unwrap();
+    let token = fixtures.session(&user).insert().await.unwrap();
+    let introspect = |bearer, token: &str| {
+        send(
+            &router,
+            "POST",
+            "/api/v1/auth/introspect",
+            bearer,
+            Some(json!({ "token": token })),
+        )
+    };
+
+    snapshot!(
+        "introspect_active",
+        introspect(Some(SERVICE_TOKEN), &token).await
+    );
+    snapshot!(
+        "introspect_inactive",
+        introspect(Some(SERVICE_TOKEN), "not-a-session").await
+    );
+    snapshot!(
+        "introspect_unauthorized",
+        introspect(Some("wrong"), &token).await
+    );
+
+    fixtures.reset().await.unwrap();
+}
+
+// ============================================================================
+// Account
+// ============================================================================
+
+#[tokio::test]
+#[serial_test::serial]
+async fn account_usage_contract() {
+    // ---
+    let (router, mut fixtures) = setup_with(|config| config.quotas.user_daily = 2).await;
+    let user = fixtures.user("contract_usage").insert().await.unwrap();
+    let token = fixtures.session(&user).insert().await.unwrap();
+    let usage = |bearer| send(&router, "GET", "/api/v1/account/usage", bearer, None);
+
+    snapshot!("account_usage", usage(Some(&token)).await);
+    snapshot!("account_usage_unauthorized", usage(None).await);
+    usage(Some(&token)).await;
+    snapshot!("account_usage_quota_exceeded", usage(Some(&token)).await);
+
+    fixtures.reset().await.unwrap();
+}
+
+// ============================================================================
+// Admin
+// ============================================================================
+
+#[tokio::test]
+#[serial_test::serial]
+async fn admin_contract() {
+    // ---
+    let (router, mut fixtures) = setup().await;
+    let user = fixtures.user("contract_admin").insert().await.unwrap();
+    let limit = format!("/api/v1/admin/users/{}/credential-limit", user.username);
+
+    snapshot!(
+        "admin_unauthorized",
+        send(&router, "POST", "/api/v1/admin/purge", Some("wrong"), None).await
+    );
+    snapshot!(
+        "admin_purge",
+        send(
+            &router,
+            "POST",
+            "/api/v1/admin/purge?older_than_days=36500",
+            Some(ADMIN_TOKEN),
+            None
+        )
+        .await
+    );
+    snapshot!(
+        "admin_reencrypt",
+        send(
+            &router,
+            "POST",
+            "/api/v1/admin/credentials/reencrypt",
+            Some(ADMIN_TOKEN),
+            None
+        )
+        .await
+    );
+    snapshot!(
+        "admin_export_unconfigured",
+        send(
+            &router,
+            "POST",
+            "/api/v1/admin/export",
+            Some(ADMIN_TOKEN),
+            None
+        )
+        .await
+    );
+
+    // Counts depend on what other tests left behind; only the shape is stable
+    insta::assert_json_snapshot!(
+        "admin_stats",
+        send(
+            &router,
+            "GET",
+            "/api/v1/admin/stats?refresh=true",
+            Some(ADMIN_TOKEN),
+            None
+        )
+        .await,
+        {
+            ".body.meta.elapsed_ms" => "[elapsed_ms]",
+            ".body.data.generated_at" => "[timestamp]",
+            ".body.data.*.total" => "[count]",
+            ".body.data.credentials.per_user[\"0\"]" => "[count]",
+            ".body.data.credentials.per_user[\"1\"]" => "[count]",
+            ".body.data.credentials.per_user[\"2\"]" => "[count]",
+            ".body.data.credentials.per_user[\"3\"]" => "[count]",
+            ".body.data.credentials.per_user[\"4\"]" => "[count]",
+            ".body.data.credentials.per_user[\"5+\"]" => "[count]",
+            ".body.data.sessions.active" => "[count]",
+            ".body.data.auth_last_24h.succeeded" => "[count]",
+            ".body.data.auth_last_24h.failed" => "[count]",
+            ".body.data.auth_last_24h.success_rate" => "[rate]",
+            ".body.data.auth_last_24h.hourly" => "[hourly]",
+        }
+    );
+
+    snapshot!(
+        "admin_credential_limit_get",
+        send(&router, "GET", &limit, Some(ADMIN_TOKEN), None).await
+    );
+    snapshot!(
+        "admin_credential_limit_set",
+        send(
+            &router,
+            "PUT",
+            &limit,
+            Some(ADMIN_TOKEN),
+            Some(json!({ "max_credentials": 3 }))
+        )
+        .await
+    );
+    snapshot!(
+        "admin_credential_limit_delete",
+        send(&router, "DELETE", &limit, Some(ADMIN_TOKEN), None).await
+    );
+    snapshot!(
+        "admin_credential_limit_unknown_user",
+        send(
+            &router,
+            "GET",
+            "/api/v1/admin/users/contract_nobody/credential-limit",
+            Some(ADMIN_TOKEN),
+            None
+        )
+        .await
+    );
+
+    fixtures.reset().await.unwrap();
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn admin_webhooks_contract() {
+    // ---
+    let (router, _) = setup().await;
+    let endpoint = json!({ "url": "https://hooks.example.com/contract", "secret": "s3cret" });
+
+    let created = send(
+        &router,
+        "POST",
+        "/api/v1/admin/webhooks",
+        Some(ADMIN_TOKEN),
+        Some(endpoint),
+    )
+    .await;
+    let id = created["body"]["data"]["id"].as_str().unwrap().to_string();
+    let uri = format!("/api/v1/admin/webhooks/{id}");
+    snapshot!("webhooks_create", created);
+    snapshot!(
+        "webhooks_create_invalid_url",
+        send(
+            &router,
+            "POST",
+            "/api/v1/admin/webhooks",
+            Some(ADMIN_TOKEN),
+            Some(json!({ "url": "ftp://example.com" }))
+        )
+        .await
+    );
+
+    snapshot!(
+        "webhooks_get",
+        send(&router, "GET", &uri, Some(ADMIN_TOKEN), None).await
+    );
+
+    // Other tests' endpoints may be listed too; keep only this one
+    let mut listed = send(
+        &router,
+        "GET",
+        "/api/v1/admin/webhooks",
+        Some(ADMIN_TOKEN),
+        None,
+    )
+    .await;
+    listed["body"]["data"]
+        .as_array_mut()
+        .unwrap()
+        .retain(|endpoint| endpoint["id"] == id.as_str());
+    snapshot!("webhooks_list", listed);
+    snapshot!(
+        "webhooks_update",
+        send(
+            &router,
+            "PUT",
+            &uri,
+            Some(ADMIN_TOKEN),
+            Some(json!({ "url": "https://hooks.example.com/updated" }))
+        )
+        .await
+    );
+    snapshot!(
+        "webhooks_delete",
+        send(&router, "DELETE", &uri, Some(ADMIN_TOKEN), None).await
+    );
+    snapshot!(
+        "webhooks_get_not_found",
+        send(&router, "GET", &uri, Some(ADMIN_TOKEN), None).await
+    );
+}
@@ -0,0 +1,289 @@
+//! Integration tests for the gRPC server (`--features grpc`).
+//!
+//! Calls go through the gRPC router in-process, framed as a client would
+//! send them. The messages below are declared here, against
+//! `proto/quickstart.proto`, so the tests also check the wire format.
+#![cfg(feature = "grpc")]
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    Router,
+};
+use axum_quickstart::{create_repository, create_session, AppBuilder, AppConfig};
+use prost::Message;
+use tower::ServiceExt;
+
+mod common;
+
+const ADMIN_TOKEN: &str = "grpc-admin-secret";
+
+// gRPC status codes
+const NOT_FOUND: i32 = 5;
+const INVALID_ARGUMENT: i32 = 3;
+const UNAUTHENTICATED: i32 = 16;
+const UNIMPLEMENTED: i32 = 12;
+
+#[derive(Clone, PartialEq, Message)]
+struct Movie {
+    #[prost(string, tag = "1")]
+    title: String,
+    #[prost(uint32, tag = "2")]
+    year: u32,
+    #[prost(float, tag = "3")]
+    stars: f32,
+    #[prost(string, repeated, tag = "4")]
+    genres: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct StoredMovie {
+    #[prost(string, tag = "1")]
+    id: String,
+    #[prost(message, optional, tag = "2")]
+    movie: Option<Movie>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct MovieId {
+    #[prost(string, tag = "1")]
+    id: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct AddMovieRequest {
+    #[prost(message, optional, tag = "1")]
+    movie: Option<Movie>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct Empty {}
+
+#[derive(Clone, PartialEq, Message)]
+struct IntrospectRequest {
+    #[prost(string, tag = "1")]
+    token: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct IntrospectResponse {
+    #[prost(bool, tag = "1")]
+    active: bool,
+    #[prost(string, tag = "2")]
+    user_id: String,
+    #[prost(string, tag = "3")]
+    username: String,
+    #[prost(int64, tag = "4")]
+    expires_at: i64,
+    #[prost(string, tag = "5")]
+    role: String,
+}
+
+// ---
+
+/// Test helper: The gRPC router, with the admin token set
+async fn grpc_router() -> Router {
+    // ---
+    common::setup_test_env().await;
+    let mut config = AppConfig::from_env().expect("config should load");
+    config.admin.api_token = Some(ADMIN_TOKEN.to_string());
+    let repository = create_repository(&config.database).await.unwrap();
+
+    let (_, grpc) = AppBuilder::new()
+        .config(config)
+        .repository(repository)
+        .build_with_grpc()
+        .unwrap();
+    grpc
+}
+
+/// Test helper: Make a unary call; returns the response or the gRPC status
+/// code
+async fn call<Res: Message + Default>(
+    router: &Router,
+    method: &str,
+    request: impl Message,
+    token: Option<&str>,
+) -> Result<Res, i32> {
+    // ---
+    let message = request.encode_to_vec();
+    let mut frame = vec![0];
+    frame.extend_from_slice(&(message.len() as u32).to_be_bytes());
+    frame.extend_from_slice(&message);
+
+    let mut builder = Request::builder()
+        .method("POST")
+        .uri(method)
+        .header("content-type", "application/grpc")
+        .header("te", "trailers");
+    if let Some(token) = token {
+        builder = builder.header("authorization", format!("Bearer {token}"));
+    }
+    let response = router
+        .clone()
+        .oneshot(builder.body(Body::from(frame)).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Errors are sent trailers-only, i.e. with the status in the headers
+    let code = response
+        .headers()
+        .get("grpc-status")
+        .map(|code| code.to_str().unwrap().parse().unwrap());
+    if let Some(code) = code.filter(|&code| code != 0) {
+        return Err(code);
+    }
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    Ok(Res::decode(&body[5..]).unwrap())
+}
+
+// ---
+
+#[tokio::test]
+async fn movies_crud_over_grpc() {
+    // ---
+    let router = grpc_router().await;
+    let movie = Movie {
+        title: format!("gRPC Movie {}", uuid::Uuid::new_v4()),
+        year: 1999,
+        stars: 4.0,
+        genres: vec!["Drama".to_string()],
+    };
+
+    let stored: StoredMovie = call(
+        &router,
+        "/quickstart.v1.Movies/AddMovie",
+        AddMovieRequest {
+            movie: Some(movie.clone()),
+        },
+        None,
+    )
+    .await
+    .unwrap();
+    assert!(!stored.id.is_empty());
+
+    let fetched: StoredMovie = call(
+        &router,
+        "/quickstart.v1.Movies/GetMovie",
+        MovieId {
+            id: stored.id.clone(),
+        },
+        None,
+    )
+    .await
+    .unwrap();
+    assert_eq!(fetched.movie.unwrap().title, movie.title);
+
+    let _: Empty = call(
+        &router,
+        "/quickstart.v1.Movies/DeleteMovie",
+        MovieId {
+            id: stored.id.clone(),
+        },
+        None,
+    )
+    .await
+    .unwrap();
+
+    let missing = call::<StoredMovie>(
+        &router,
+        "/quickstart.v1.Movies/GetMovie",
+        MovieId { id: stored.id },
+        None,
+    )
+    .await;
+    assert_eq!(missing, Err(NOT_FOUND));
+}
+
+#[tokio::test]
+async fn invalid_movies_are_rejected() {
+    // ---
+    let router = grpc_router().await;
+
+    let missing = call::<StoredMovie>(
+        &router,
+        "/quickstart.v1.Movies/AddMovie",
+        AddMovieRequest { movie: None },
+        None,
+    )
+    .await;
+    assert_eq!(missing, Err(INVALID_ARGUMENT));
+
+    let untitled = Movie {
+        title: String::new(),
+        year: 1999,
+        stars: 4.0,
+        genres: Vec::new(),
+    };
+    let invalid = call::<StoredMovie>(
+        &router,
+        "/quickstart.v1.Movies/AddMovie",
+        AddMovieRequest {
+            movie: Some(untitled),
+        },
+        None,
+    )
+    .await;
+    assert_eq!(invalid, Err(INVALID_ARGUMENT));
+
+    let unknown = call::<Empty>(&router, "/quickstart.v1.Movies/RenameMovie", Empty {}, None).await;
+    assert_eq!(unknown, Err(UNIMPLEMENTED));
+}
+
+#[tokio::test]
+async fn introspection_requires_the_admin_token() {
+    // ---
+    let router = grpc_router().await;
+    let config = AppConfig::from_env().unwrap();
+    let mut redis_conn = redis::Client::open(config.redis.url)
+        .unwrap()
+        .get_multiplexed_async_connection()
+        .await
+        .unwrap();
+    let user_id = uuid::Uuid::new_v4();
+    let token = create_session(&mut redis_conn, user_id, "grpc_user".into(), None)
+        .await
+        .unwrap();
+    let method = "/quickstart.v1.Sessions/Introspect";
+
+    let denied = call::<IntrospectResponse>(
+        &router,
+        method,
+        IntrospectRequest {
+            token: token.clone(),
+        },
+        Some("wrong"),
+    )
+    .await;
+    assert_eq!(denied, Err(UNAUTHENTICATED));
+
+    let active: IntrospectResponse = call(
+        &router,
+        method,
+        IntrospectRequest { token },
+        Some(ADMIN_TOKEN),
+    )
+    .await
+    .unwrap();
+    assert!(active.active);
+    assert_eq!(active.user_id, user_id.to_string());
+    assert_eq!(active.username, "grpc_user");
+    assert_eq!(active.role, "user");
+    assert!(active.expires_at > chrono::Utc::now().timestamp());
+
+    let unknown: IntrospectResponse = call(
+        &router,
+        method,
+        IntrospectRequest {
+            token: "not-a-session".to_string(),
+        },
+        Some(ADMIN_TOKEN),
+    )
+    .await
+    .unwrap();
+    assert_eq!(unknown, IntrospectResponse::default());
+}
@@ -1,6 +1,12 @@
 use anyhow::{ensure, Result};
-use axum_quickstart::create_router;
+use axum::{body::Body, http::Request};
+use axum_quickstart::{
+    create_noop_metrics, create_prom_metrics, create_repository, create_router, create_session,
+    route_table, AppBuilder, AppConfig, ConfigReloader, RouteAuth, SessionConfig, SessionMode,
+    CSRF_HEADER, SESSION_COOKIE,
+};
 use serde_json::json;
+use tower::ServiceExt;
 
 mod common;
 
@@ -13,6 +19,1037 @@ async fn basic_integration_test() {
     let _router = create_router().expect("Should be able to create router");
 }
 
+#[tokio::test]
+#[serial_test::serial]
+async fn app_builder_accepts_injected_dependencies() {
+    // ---
+    common::setup_test_env().await;
+
+    let config = AppConfig::from_env().expect("config should load");
+    let redis_client = redis::Client::open(config.redis.url.clone()).unwrap();
+
+    // A repository with its own pool, independent of the env-initialized default
+    let repository = create_repository(&config.database)
+        .await
+        .expect("repository should connect");
+
+    let router = AppBuilder::new()
+        .config(config)
+        .metrics(create_noop_metrics().unwrap())
+        .repository(repository)
+        .redis_client(redis_client)
+        .build()
+        .expect("builder should produce a router");
+
+    let request = Request::builder()
+        .uri("/api/v1/health")
+        .body(Body::empty())
+        .unwrap();
+    let response = router.oneshot(request).await.unwrap();
+
+    assert_eq!(response.status(), 200);
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn app_builder_requires_repository() {
+    // ---
+    common::setup_test_env().await;
+
+    let result = AppBuilder::new()
+        .config(AppConfig::from_env().unwrap())
+        .build();
+
+    assert!(result.is_err(), "building without a repository should fail");
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn admin_purge_disabled_without_token() {
+    // ---
+    common::setup_test_env().await;
+    let server = common::TestServer::new().await;
+
+    let response = server
+        .client
+        .post(server.url("/api/v1/admin/purge"))
+        .bearer_auth("anything")
+        .send()
+        .await
+        .expect("Failed to send request");
+
+    assert_eq!(response.status(), 403);
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn admin_purge_requires_matching_token() {
+    // ---
+    common::setup_test_env().await;
+
+    let mut config = AppConfig::from_env().expect("config should load");
+    config.admin.api_token = Some("admin-secret".to_string());
+    let repository = create_repository(&config.database).await.unwrap();
+
+    let router = AppBuilder::new()
+        .config(config)
+        .repository(repository)
+        .build()
+        .unwrap();
+
+    let purge = |token: &str| {
+        Request::builder()
+            .method("POST")
+            .uri("/api/v1/admin/purge?older_than_days=3650")
+            .header("authorization", format!("Bearer {token}"))
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    let response = router.clone().oneshot(purge("wrong")).await.unwrap();
+    assert_eq!(response.status(), 401);
+
+    let response = router.oneshot(purge("admin-secret")).await.unwrap();
+    assert_eq!(response.status(), 200);
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert!(json["data"]["users_purged"].is_u64());
+    assert!(json["data"]["credentials_purged"].is_u64());
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn admin_retention_shows_the_next_purge() {
+    // ---
+    common::setup_test_env().await;
+
+    let mut config = AppConfig::from_env().expect("config should load");
+    config.admin.api_token = Some("admin-secret".to_string());
+    config.admin.soft_delete_retention = std::time::Duration::from_secs(14 * 86_400);
+    config.retention.interval = Some(std::time::Duration::from_secs(3600));
+    config.retention.dry_run = true;
+    let repository = create_repository(&config.database).await.unwrap();
+
+    let router = AppBuilder::new()
+        .config(config)
+        .repository(repository)
+        .build()
+        .unwrap();
+
+    let request = Request::builder()
+        .uri("/api/v1/admin/retention")
+        .header("authorization", "Bearer admin-secret")
+        .body(Body::empty())
+        .unwrap();
+    let response = router.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), 200);
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let data = &json["data"];
+    assert_eq!(data["enabled"], true);
+    assert_eq!(data["dry_run"], true);
+    assert_eq!(data["interval_secs"], 3600);
+    assert!(data["next_run"].is_string());
+    assert!(data["last_run"].is_null());
+
+    let rules = data["rules"].as_array().unwrap();
+    assert_eq!(rules[0]["rule"], "soft_deleted_users");
+    assert_eq!(rules[0]["max_age_days"], 14);
+    assert_eq!(rules[0]["enabled"], true);
+
+    // Without an export bucket there are no audit files to expire
+    assert_eq!(rules[1]["rule"], "audit_logs");
+    assert_eq!(rules[1]["enabled"], false);
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn app_service_serves_without_a_listener() {
+    // ---
+    use axum::extract::ConnectInfo;
+    use std::net::SocketAddr;
+
+    common::setup_test_env().await;
+
+    let mut config = AppConfig::from_env().expect("config should load");
+    config.admin.api_token = Some("admin-secret".to_string());
+    config.access.admin_allow = vec!["203.0.113.0/24".parse().unwrap()];
+    let repository = create_repository(&config.database).await.unwrap();
+    let service = AppBuilder::new()
+        .config(config)
+        .repository(repository)
+        .build_service()
+        .unwrap();
+
+    let request = Request::builder()
+        .uri("/api/v1/health")
+        .body(Body::empty())
+        .unwrap();
+    let response = service.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), 200);
+
+    // Without a peer address the admin allow list denies the request
+    let request = Request::builder()
+        .uri("/api/v1/admin/stats")
+        .body(Body::empty())
+        .unwrap();
+    let response = service.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), 403);
+
+    // The embedder supplies the client address, here one the list admits
+    let mut request = Request::builder()
+        .uri("/api/v1/admin/stats")
+        .header("authorization", "Bearer admin-secret")
+        .body(Body::empty())
+        .unwrap();
+    request
+        .extensions_mut()
+        .insert(ConnectInfo(SocketAddr::from(([203, 0, 113, 7], 0))));
+    let response = service.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), 200);
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn separate_admin_moves_operator_routes_off_the_app() {
+    // ---
+    common::setup_test_env().await;
+
+    let config = AppConfig::from_env().expect("config should load");
+    let repository = create_repository(&config.database).await.unwrap();
+    let routers = AppBuilder::new()
+        .config(config)
+        .repository(repository)
+        .separate_admin(true)
+        .build_routers()
+        .unwrap();
+    let admin = routers.admin.expect("admin router should be split off");
+
+    let get = |uri: &str| Request::builder().uri(uri).body(Body::empty()).unwrap();
+    for uri in [
+        "/api/v1/health",
+        "/health",
+        "/api/v1/metrics",
+        "/api/v1/admin/stats",
+    ] {
+        let response = routers.app.clone().oneshot(get(uri)).await.unwrap();
+        assert_eq!(response.status(), 404, "{uri} should leave the app");
+    }
+    let response = admin.clone().oneshot(get("/api/v1/health")).await.unwrap();
+    assert_eq!(response.status(), 200);
+    let response = admin.oneshot(get("/api/v1/movies/list")).await.unwrap();
+    assert_eq!(response.status(), 404);
+    let response = routers
+        .app
+        .oneshot(get("/api/v1/movies/list"))
+        .await
+        .unwrap();
+    assert_ne!(response.status(), 404);
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn base_path_prefixes_routes_and_links() {
+    // ---
+    common::setup_test_env().await;
+
+    let mut config = AppConfig::from_env().expect("config should load");
+    config.server.base_path = "/edge".to_string();
+    let repository = create_repository(&config.database).await.unwrap();
+    let router = AppBuilder::new()
+        .config(config)
+        .repository(repository)
+        .build()
+        .unwrap();
+
+    let get = |uri: &str| Request::builder().uri(uri).body(Body::empty()).unwrap();
+    let response = router.clone().oneshot(get("/api/v1/health")).await.unwrap();
+    assert_eq!(response.status(), 404);
+    let response = router
+        .clone()
+        .oneshot(get("/edge/api/v1/health"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+
+    // Legacy aliases point at their successor below the base path
+    let response = router.clone().oneshot(get("/edge/health")).await.unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(
+        response.headers()["link"],
+        "</edge/api/v1/health>; rel=\"successor-version\""
+    );
+
+    let response = router.oneshot(get("/edge/app/")).await.unwrap();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert!(String::from_utf8_lossy(&body).contains("\"/edge/app/webauthn.js\""));
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn every_registered_route_is_routed() {
+    // ---
+    common::setup_test_env().await;
+
+    let config = AppConfig::from_env().expect("config should load");
+    let repository = create_repository(&config.database).await.unwrap();
+    let router = AppBuilder::new()
+        .config(config)
+        .repository(repository)
+        .build()
+        .unwrap();
+
+    for route in route_table("").routes {
+        for path in std::iter::once(&route.path).chain(route.legacy_path.as_ref()) {
+            // Any value will do for path parameters
+            let uri = path
+                .split('/')
+                .map(|segment| match segment.starts_with('{') {
+                    true => "x",
+                    false => segment,
+                })
+                .collect::<Vec<_>>()
+                .join("/");
+            let request = Request::builder()
+                .method(route.method)
+                .uri(&uri)
+                .body(Body::empty())
+                .unwrap();
+            let response = router.clone().oneshot(request).await.unwrap();
+
+            let label = format!("{} {uri}", route.method);
+            assert_ne!(response.status(), 405, "{label} is not routed");
+            if response.status() == 404 {
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                let body: serde_json::Value = serde_json::from_slice(&body).unwrap_or_default();
+                assert_ne!(body["code"], "not_found", "{label} is not routed");
+            }
+        }
+    }
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn registered_auth_is_enforced() {
+    // ---
+    common::setup_test_env().await;
+
+    let mut config = AppConfig::from_env().expect("config should load");
+    config.admin.api_token = None;
+    config.admin.service_tokens = Vec::new();
+    let repository = create_repository(&config.database).await.unwrap();
+
+    let serve = |config: AppConfig| {
+        let router = AppBuilder::new()
+            .config(config)
+            .repository(repository.clone())
+            .build()
+            .unwrap();
+        async move {
+            // A listener, so `/ws` can see an upgradable connection
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let base = format!("http://{}", listener.local_addr().unwrap());
+            tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+            base
+        }
+    };
+    let disabled = serve(config.clone()).await;
+    config.admin.api_token = Some("admin-secret".to_string());
+    let enabled = serve(config).await;
+
+    let client = reqwest::Client::new();
+    for route in route_table("").routes {
+        // A UUID will do for any path parameter
+        let path = route
+            .path
+            .split('/')
+            .map(|segment| match segment.starts_with('{') {
+                true => "00000000-0000-0000-0000-000000000000",
+                false => segment,
+            })
+            .collect::<Vec<_>>()
+            .join("/");
+        // A body every handler that parses it before checking credentials
+        // accepts (introspection, credential limits, webhooks)
+        let body = json!({ "token": "x", "max_credentials": 1, "url": "https://example.com" });
+        let status = |base: &str| {
+            client
+                .request(route.method.parse().unwrap(), format!("{base}{path}"))
+                .json(&body)
+                .header("connection", "upgrade")
+                .header("upgrade", "websocket")
+                .header("sec-websocket-version", "13")
+                .header("sec-websocket-key", "dGhlIHNhbXBsZSBub25jZQ==")
+                .send()
+        };
+        let without_admin = status(&disabled).await.unwrap().status().as_u16();
+        let with_admin = status(&enabled).await.unwrap().status().as_u16();
+
+        // Without credentials, with the admin token unset and then set
+        let expected = match route.auth {
+            RouteAuth::None => None,
+            RouteAuth::Session | RouteAuth::SessionOrService | RouteAuth::SessionOrAdmin => {
+                Some((401, 401))
+            }
+            RouteAuth::Service | RouteAuth::Admin => Some((403, 401)),
+        };
+        let label = format!("{} {path} ({})", route.method, route.auth.as_str());
+        match expected {
+            Some(expected) => assert_eq!((without_admin, with_admin), expected, "{label}"),
+            None => {
+                assert!(
+                    ![401, 403].contains(&without_admin),
+                    "{label}: {without_admin}"
+                );
+                assert!(![401, 403].contains(&with_admin), "{label}: {with_admin}");
+            }
+        }
+    }
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn reload_applies_admin_token_without_restart() {
+    // ---
+    common::setup_test_env().await;
+
+    let mut config = AppConfig::from_env().expect("config should load");
+    config.admin.api_token = None;
+    let repository = create_repository(&config.database).await.unwrap();
+    let reloader = ConfigReloader::new(&config);
+
+    let router = AppBuilder::new()
+        .config(config.clone())
+        .repository(repository)
+        .reloader(reloader.clone())
+        .build()
+        .unwrap();
+
+    let purge = || {
+        Request::builder()
+            .method("POST")
+            .uri("/api/v1/admin/purge?older_than_days=3650")
+            .header("authorization", "Bearer rotated-secret")
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    let response = router.clone().oneshot(purge()).await.unwrap();
+    assert_eq!(response.status(), 403);
+
+    // Reloadable change applied; the database URL change is ignored
+    let mut reloaded = config.clone();
+    reloaded.admin.api_token = Some("rotated-secret".to_string());
+    reloaded.database.database_url = "postgres://elsewhere/none".to_string();
+    let changes = reloader.reload(reloaded);
+    assert_eq!(changes, ["admin.api_token changed"]);
+
+    let response = router.oneshot(purge()).await.unwrap();
+    assert_eq!(response.status(), 200);
+}
+
+#[cfg(feature = "pprof")]
+#[tokio::test]
+#[serial_test::serial]
+async fn pprof_profile_requires_admin_and_returns_flamegraph() {
+    // ---
+    common::setup_test_env().await;
+
+    let mut config = AppConfig::from_env().expect("config should load");
+    config.admin.api_token = Some("admin-secret".to_string());
+    let repository = create_repository(&config.database).await.unwrap();
+
+    let router = AppBuilder::new()
+        .config(config)
+        .repository(repository)
+        .build()
+        .unwrap();
+
+    let profile = |token: &str, query: &str| {
+        Request::builder()
+            .uri(format!("/api/v1/debug/pprof/profile?{query}"))
+            .header("authorization", format!("Bearer {token}"))
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    let response = router
+        .clone()
+        .oneshot(profile("wrong", "seconds=1"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 401);
+
+    let response = router
+        .clone()
+        .oneshot(profile("admin-secret", "seconds=0"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 400);
+
+    // Keep a thread busy so the profile has samples
+    let busy = std::thread::spawn(|| {
+        let start = std::time::Instant::now();
+        let mut n = 0u64;
+        while start.elapsed() < std::time::Duration::from_millis(1500) {
+            for _ in 0..1_000_000 {
+                n = std::hint::black_box(n.wrapping_mul(31).wrapping_add(7));
+            }
+        }
+    });
+    let response = router
+        .oneshot(profile("admin-secret", "seconds=1"))
+        .await
+        .unwrap();
+    busy.join().unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers()["content-type"], "image/svg+xml");
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert!(String::from_utf8_lossy(&body).contains("<svg"));
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn admin_credential_limit_override_round_trip() {
+    // ---
+    common::setup_test_env().await;
+
+    let mut config = AppConfig::from_env().expect("config should load");
+    config.admin.api_token = Some("admin-secret".to_string());
+    let default_limit = config.credentials.max_per_user;
+    let repository = create_repository(&config.database).await.unwrap();
+
+    let username = format!("limit_{}", uuid::Uuid::new_v4());
+    repository.create_user(&username).await.unwrap();
+
+    let router = AppBuilder::new()
+        .config(config)
+        .repository(repository)
+        .build()
+        .unwrap();
+
+    let call = |method: &str, body: Option<serde_json::Value>| {
+        Request::builder()
+            .method(method)
+            .uri(format!("/api/v1/admin/users/{username}/credential-limit"))
+            .header("authorization", "Bearer admin-secret")
+            .header("content-type", "application/json")
+            .body(body.map_or_else(Body::empty, |b| Body::from(b.to_string())))
+            .unwrap()
+    };
+    let send = |request: Request<Body>| async {
+        let response = router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap_or_default();
+        (status, json)
+    };
+
+    let (status, json) = send(call("GET", None)).await;
+    assert_eq!(status, 200);
+    assert_eq!(json["data"]["max_credentials"], default_limit);
+    assert_eq!(json["data"]["overridden"], false);
+    assert_eq!(json["data"]["credentials"], 0);
+
+    let (status, json) = send(call("PUT", Some(json!({ "max_credentials": 25 })))).await;
+    assert_eq!(status, 200);
+    assert_eq!(json["data"]["max_credentials"], 25);
+    assert_eq!(json["data"]["overridden"], true);
+
+    let (status, json) = send(call("DELETE", None)).await;
+    assert_eq!(status, 200);
+    assert_eq!(json["data"]["max_credentials"], default_limit);
+    assert_eq!(json["data"]["overridden"], false);
+
+    let missing = Request::builder()
+        .uri("/api/v1/admin/users/no-such-user/credential-limit")
+        .header("authorization", "Bearer admin-secret")
+        .body(Body::empty())
+        .unwrap();
+    assert_eq!(send(missing).await.0, 404);
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn quotas_limit_users_and_service_tokens() {
+    // ---
+    common::setup_test_env().await;
+
+    let service_token = format!("quota-service-{}", uuid::Uuid::new_v4());
+    let mut config = AppConfig::from_env().expect("config should load");
+    config.admin.service_tokens = vec![service_token.clone()];
+    config.quotas.user_daily = 3;
+    config.quotas.key_monthly = 100;
+    let mut redis_conn = redis::Client::open(config.redis.url.clone())
+        .unwrap()
+        .get_multiplexed_async_connection()
+        .await
+        .unwrap();
+    let repository = create_repository(&config.database).await.unwrap();
+    let router = AppBuilder::new()
+        .config(config)
+        .repository(repository)
+        .build()
+        .unwrap();
+
+    let user_token = create_session(
+        &mut redis_conn,
+        uuid::Uuid::new_v4(),
+        "quota_user".into(),
+        None,
+    )
+    .await
+    .unwrap();
+    let usage = |token: &str| {
+        Request::builder()
+            .uri("/api/v1/account/usage")
+            .header("authorization", format!("Bearer {token}"))
+            .body(Body::empty())
+            .unwrap()
+    };
+    let header = |response: &axum::response::Response, name: &str| {
+        response
+            .headers()
+            .get(name)
+            .map(|v| v.to_str().unwrap().to_string())
+    };
+
+    for expected_remaining in ["2", "1", "0"] {
+        let response = router.clone().oneshot(usage(&user_token)).await.unwrap();
+        assert_eq!(response.status(), 200);
+        assert_eq!(header(&response, "x-ratelimit-limit").as_deref(), Some("3"));
+        assert_eq!(
+            header(&response, "x-ratelimit-remaining").as_deref(),
+            Some(expected_remaining)
+        );
+        assert!(header(&response, "x-ratelimit-reset").is_some());
+    }
+
+    let refused = router.clone().oneshot(usage(&user_token)).await.unwrap();
+    assert_eq!(refused.status(), 429);
+    assert_eq!(
+        header(&refused, "x-ratelimit-remaining").as_deref(),
+        Some("0")
+    );
+    assert!(header(&refused, "retry-after").is_some());
+
+    // Services have their own quota
+    let response = router.clone().oneshot(usage(&service_token)).await.unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(
+        header(&response, "x-ratelimit-limit").as_deref(),
+        Some("100")
+    );
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["data"]["caller"], "key");
+    assert_eq!(json["data"]["monthly"]["used"], 1);
+    assert_eq!(json["data"]["monthly"]["remaining"], 99);
+    assert_eq!(json["data"]["daily"]["limit"], serde_json::Value::Null);
+
+    // Anonymous requests are not counted
+    let health = Request::builder()
+        .uri("/api/v1/health")
+        .body(Body::empty())
+        .unwrap();
+    let response = router.clone().oneshot(health).await.unwrap();
+    assert!(header(&response, "x-ratelimit-limit").is_none());
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn event_stream_requires_auth() {
+    // ---
+    common::setup_test_env().await;
+    let server = common::TestServer::new().await;
+
+    let response = server
+        .client
+        .get(server.url("/api/v1/events"))
+        .send()
+        .await
+        .expect("Failed to send request");
+
+    assert_eq!(response.status(), 401);
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn admin_event_stream_receives_audit_events() {
+    // ---
+    common::setup_test_env().await;
+
+    let mut config = AppConfig::from_env().expect("config should load");
+    config.admin.api_token = Some("admin-secret".to_string());
+    let repository = create_repository(&config.database).await.unwrap();
+
+    let router = AppBuilder::new()
+        .config(config)
+        .repository(repository)
+        .build()
+        .unwrap();
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}/api/v1", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+
+    let client = reqwest::Client::new();
+    let mut stream = client
+        .get(format!("{base}/events"))
+        .bearer_auth("admin-secret")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(stream.status(), 200);
+    assert_eq!(
+        stream.headers()["content-type"].to_str().unwrap(),
+        "text/event-stream"
+    );
+
+    let purge = client
+        .post(format!("{base}/admin/purge?older_than_days=3650"))
+        .bearer_auth("admin-secret")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(purge.status(), 200);
+
+    let mut received = String::new();
+    let found = tokio::time::timeout(std::time::Duration::from_secs(5), async {
+        while let Some(chunk) = stream.chunk().await.unwrap() {
+            received.push_str(&String::from_utf8_lossy(&chunk));
+            if received.contains("event: audit") && received.contains("admin.purge") {
+                return true;
+            }
+        }
+        false
+    })
+    .await;
+
+    assert_eq!(found, Ok(true), "stream so far: {received}");
+}
+
+/// Test helper: An S3 stand-in recording each `PUT` as (path,
+/// authorization, body)
+async fn spawn_fake_s3() -> (
+    String,
+    std::sync::Arc<std::sync::Mutex<Vec<(String, String, String)>>>,
+) {
+    // ---
+    use axum::{extract::State, http::HeaderMap, http::Uri, routing::put, Router};
+    use std::sync::{Arc, Mutex};
+
+    type Uploads = Arc<Mutex<Vec<(String, String, String)>>>;
+    async fn store(State(uploads): State<Uploads>, uri: Uri, headers: HeaderMap, body: String) {
+        // ---
+        let authorization = headers["authorization"].to_str().unwrap().to_string();
+        uploads
+            .lock()
+            .unwrap()
+            .push((uri.path().to_string(), authorization, body));
+    }
+
+    let uploads = Uploads::default();
+    let app = Router::new()
+        .route("/{*key}", put(store))
+        .with_state(uploads.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let endpoint = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    (endpoint, uploads)
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn admin_export_uploads_audit_events_and_metrics() {
+    // ---
+    common::setup_test_env().await;
+    let (endpoint, uploads) = spawn_fake_s3().await;
+
+    let mut config = AppConfig::from_env().expect("config should load");
+    config.admin.api_token = Some("admin-secret".to_string());
+    config.export.bucket = Some("audit".to_string());
+    config.export.endpoint = endpoint;
+    config.export.access_key_id = "minio".to_string();
+    config.export.secret_access_key = "minio-secret".to_string();
+    config.export.prefix = "test/".to_string();
+    let repository = create_repository(&config.database).await.unwrap();
+    let router = AppBuilder::new()
+        .config(config)
+        .repository(repository)
+        .metrics(create_prom_metrics().unwrap())
+        .build()
+        .unwrap();
+
+    let admin_post = |uri: &str| {
+        Request::builder()
+            .method("POST")
+            .uri(uri)
+            .header("authorization", "Bearer admin-secret")
+            .body(Body::empty())
+            .unwrap()
+    };
+    let purge = router
+        .clone()
+        .oneshot(admin_post("/api/v1/admin/purge"))
+        .await
+        .unwrap();
+    assert_eq!(purge.status(), 200);
+
+    // The purge's audit event reaches the exporter asynchronously
+    let mut exported = 0;
+    for _ in 0..50 {
+        let response = router
+            .clone()
+            .oneshot(admin_post("/api/v1/admin/export"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert!(body["data"]["metrics_samples"].as_u64().unwrap() > 0);
+        exported = body["data"]["audit_events"].as_u64().unwrap();
+        if exported > 0 {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    assert!(exported > 0);
+
+    let uploads = uploads.lock().unwrap();
+    let (path, authorization, body) = uploads
+        .iter()
+        .find(|(path, _, _)| path.starts_with("/audit/test/audit/"))
+        .expect("an audit file was uploaded");
+    assert!(path.ends_with(".ndjson"));
+    assert!(authorization.starts_with("AWS4-HMAC-SHA256 Credential=minio/"));
+    let event: serde_json::Value = serde_json::from_str(body.lines().next().unwrap()).unwrap();
+    assert_eq!(event["type"], "audit");
+    assert_eq!(event["data"]["action"], "admin.purge");
+    assert!(uploads
+        .iter()
+        .any(|(path, _, _)| path.starts_with("/audit/test/metrics/")));
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn admin_stats_counts_users_credentials_and_sessions() {
+    // ---
+    common::setup_test_env().await;
+
+    let mut config = AppConfig::from_env().expect("config should load");
+    config.admin.api_token = Some("admin-secret".to_string());
+    let redis_url = config.redis.url.clone();
+    let repository = create_repository(&config.database).await.unwrap();
+
+    let user = repository
+        .create_user(&format!("stats_{}", uuid::Uuid::new_v4()))
+        .await
+        .unwrap();
+    repository
+        .save_credential(axum_quickstart::domain::Credential {
+            id: uuid::Uuid::new_v4().as_bytes().to_vec(),
+            user_id: user.id,
+            public_key: b"dummy_public_key".to_vec(),
+            counter: 0,
+            created_at: chrono::Utc::now(),
+            compromised_at: None,
+            last_used_at: None,
+            backup_eligible: None,
+            backup_state: None,
+        })
+        .await
+        .unwrap();
+    let mut redis_conn = redis::Client::open(redis_url)
+        .unwrap()
+        .get_multiplexed_async_connection()
+        .await
+        .unwrap();
+    create_session(&mut redis_conn, user.id, user.username.clone(), None)
+        .await
+        .unwrap();
+
+    let router = AppBuilder::new()
+        .config(config)
+        .repository(repository.clone())
+        .build()
+        .unwrap();
+    let stats = |uri: &'static str| {
+        let router = router.clone();
+        async move {
+            let response = router
+                .oneshot(
+                    Request::builder()
+                        .uri(uri)
+                        .header("authorization", "Bearer admin-secret")
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), 200);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap()["data"].clone()
+        }
+    };
+
+    let fresh = stats("/api/v1/admin/stats?refresh=true").await;
+    let users = fresh["users"]["total"].as_u64().unwrap();
+    assert!(users >= 1);
+    assert!(fresh["credentials"]["total"].as_u64().unwrap() >= 1);
+    assert!(fresh["credentials"]["per_user"]["1"].as_u64().unwrap() >= 1);
+    assert!(fresh["sessions"]["active"].as_u64().unwrap() >= 1);
+    assert!(fresh["movies"]["total"].is_u64());
+    assert_eq!(
+        fresh["auth_last_24h"]["hourly"].as_array().unwrap().len(),
+        24
+    );
+
+    // Served from the cache until refreshed
+    repository
+        .create_user(&format!("stats_{}", uuid::Uuid::new_v4()))
+        .await
+        .unwrap();
+    let cached = stats("/api/v1/admin/stats").await;
+    assert_eq!(cached["generated_at"], fresh["generated_at"]);
+    let refreshed = stats("/api/v1/admin/stats?refresh=true").await;
+    assert_eq!(refreshed["users"]["total"].as_u64().unwrap(), users + 1);
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn admin_ip_lists_filter_operator_routes() {
+    // ---
+    common::setup_test_env().await;
+
+    let mut config = AppConfig::from_env().expect("config should load");
+    config.admin.api_token = Some("admin-secret".to_string());
+    config.access.admin_allow = vec!["10.0.0.0/8".parse().unwrap()];
+    config.access.trusted_proxies = vec!["127.0.0.1".parse().unwrap()];
+    let repository = create_repository(&config.database).await.unwrap();
+
+    let router = AppBuilder::new()
+        .config(config)
+        .repository(repository)
+        .build()
+        .unwrap();
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}/api/v1", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        axum::serve(
+            listener,
+            router.into_make_service_with_connect_info::<std::net::SocketAddr>(),
+        )
+        .await
+        .unwrap()
+    });
+
+    let client = reqwest::Client::new();
+
+    // Loopback peer is a trusted proxy but not in the allow list
+    let denied = client
+        .get(format!("{base}/admin/webhooks"))
+        .bearer_auth("admin-secret")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(denied.status(), 403);
+    let body: serde_json::Value = denied.json().await.unwrap();
+    assert_eq!(body["error"], "Access denied");
+
+    let metrics = client.get(format!("{base}/metrics")).send().await.unwrap();
+    assert_eq!(metrics.status(), 403);
+
+    // Forwarded client inside the allow list, behind the trusted proxy
+    let allowed = client
+        .get(format!("{base}/admin/webhooks"))
+        .bearer_auth("admin-secret")
+        .header("x-forwarded-for", "10.1.2.3")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(allowed.status(), 200);
+
+    // Non-operator routes are unaffected
+    let health = client.get(format!("{base}/health")).send().await.unwrap();
+    assert_eq!(health.status(), 200);
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn full_health_reports_forwarded_origin() {
+    // ---
+    common::setup_test_env().await;
+
+    let mut config = AppConfig::from_env().expect("config should load");
+    config.access.trusted_proxies = vec!["127.0.0.1".parse().unwrap()];
+    config.webauthn.rp_id = "localhost".to_string();
+    config.webauthn.additional_origins = vec!["https://localhost".to_string()];
+    let repository = create_repository(&config.database).await.unwrap();
+
+    let router = AppBuilder::new()
+        .config(config)
+        .repository(repository)
+        .build()
+        .unwrap();
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(
+            listener,
+            router.into_make_service_with_connect_info::<std::net::SocketAddr>(),
+        )
+        .await
+        .unwrap()
+    });
+
+    let client = reqwest::Client::new();
+    let url = format!("http://{addr}/api/v1/health?mode=full");
+
+    // Direct request: the Host header, over plain HTTP
+    let direct: serde_json::Value = client.get(&url).send().await.unwrap().json().await.unwrap();
+    assert_eq!(direct["origin"]["url"], format!("http://{addr}"));
+    assert_eq!(direct["origin"]["webauthn_allowed"], false);
+
+    // TLS terminated by the (trusted) loopback proxy
+    let proxied: serde_json::Value = client
+        .get(&url)
+        .header("x-forwarded-for", "198.51.100.4")
+        .header("x-forwarded-proto", "https")
+        .header("x-forwarded-host", "localhost")
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(proxied["origin"]["url"], "https://localhost");
+    assert_eq!(proxied["origin"]["webauthn_allowed"], true);
+    assert_eq!(proxied["origin"]["tenant"], "default");
+}
+
 #[tokio::test]
 #[serial_test::serial]
 async fn health_endpoint_works() {
@@ -22,116 +1059,755 @@ async fn health_endpoint_works() {
 
     let response = server
         .client
-        .get(server.url("/health"))
+        .get(server.url("/api/v1/health"))
+        .send()
+        .await
+        .expect("Failed to send request");
+
+    assert!(response.status().is_success());
+
+    let body = response.text().await.expect("Failed to read response body");
+    assert!(!body.is_empty());
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn full_health_reports_each_dependency() {
+    // ---
+    common::setup_test_env().await;
+
+    // Own repository: the global pool behind `create_router()` may belong to
+    // another test's runtime.
+    let config = AppConfig::from_env().expect("config should load");
+    let repository = create_repository(&config.database).await.unwrap();
+    let router = AppBuilder::new()
+        .config(config)
+        .repository(repository)
+        .build()
+        .unwrap();
+
+    let request = Request::builder()
+        .uri("/api/v1/health?mode=full")
+        .body(Body::empty())
+        .unwrap();
+    let response = router.oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(status, 200, "{json}");
+
+    assert_eq!(json["status"], "ok");
+    for dependency in ["redis", "database"] {
+        assert_eq!(json["dependencies"][dependency]["status"], "ok");
+        assert!(json["dependencies"][dependency]["latency_ms"].is_f64());
+    }
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn passkey_demo_is_served() {
+    // ---
+    common::setup_test_env().await;
+    let server = common::TestServer::new().await;
+
+    let page = server.client.get(server.url("/app/")).send().await.unwrap();
+    assert_eq!(page.status(), 200);
+    assert!(page.headers()["content-type"]
+        .to_str()
+        .unwrap()
+        .starts_with("text/html"));
+    assert!(page.text().await.unwrap().contains("/app/webauthn.js"));
+
+    let script = server
+        .client
+        .get(server.url("/app/webauthn.js"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(script.status(), 200);
+    assert!(script.headers()["content-type"]
+        .to_str()
+        .unwrap()
+        .starts_with("text/javascript"));
+    assert!(script
+        .text()
+        .await
+        .unwrap()
+        .contains("navigator.credentials.create"));
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn root_endpoint_works() {
+    // ---
+    common::setup_test_env().await;
+    let server = common::TestServer::new().await;
+
+    let response = server
+        .client
+        .get(server.url("/"))
+        .send()
+        .await
+        .expect("Failed to send request");
+
+    assert_eq!(response.status(), 200);
+    assert!(response.headers()["content-type"]
+        .to_str()
+        .unwrap()
+        .starts_with("text/html"));
+
+    // The endpoint list comes from the route registry
+    let body = response.text().await.expect("Failed to read response body");
+    assert!(body.contains("<code>/api/v1/movies/list?genre&amp;page&amp;per_page</code>"));
+    assert!(!body.contains("/admin/purge"));
+
+    let response = server
+        .client
+        .get(server.url("/"))
+        .header("accept", "application/json")
+        .send()
+        .await
+        .expect("Failed to send request");
+
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers()["vary"], "accept");
+    let info: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(info["data"]["version"], env!("CARGO_PKG_VERSION"));
+    assert_eq!(info["data"]["api_prefix"], "/api/v1");
+    let routes = info["data"]["routes"].as_array().unwrap();
+    assert!(routes
+        .iter()
+        .any(|route| route["path"] == "/api/v1/health" && route["query"][0] == "mode"));
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn root_lists_every_route_outside_the_admin_api() {
+    // ---
+    common::setup_test_env().await;
+    let server = common::TestServer::new().await;
+
+    let page = |accept: &'static str| {
+        server
+            .client
+            .get(server.url("/"))
+            .header("accept", accept)
+            .send()
+    };
+    let html = page("text/html").await.unwrap().text().await.unwrap();
+    let info: serde_json::Value = page("application/json")
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let listed = info["data"]["routes"].as_array().unwrap();
+
+    for route in route_table("").routes {
+        let label = format!("{} {}", route.method, route.path);
+        let in_json = listed
+            .iter()
+            .any(|listed| listed["method"] == route.method && listed["path"] == route.path);
+        let query = match route.query {
+            [] => String::new(),
+            params => format!("?{}", params.join("&amp;")),
+        };
+        let row = format!(
+            "<tr><td>{}</td><td><code>{}{query}</code></td><td>{}</td>",
+            route.method,
+            route.path,
+            route.auth.as_str()
+        );
+
+        match route.auth {
+            RouteAuth::Admin => {
+                assert!(!in_json, "{label} is listed");
+                assert!(!html.contains(&row), "{label} is listed");
+            }
+            _ => {
+                assert!(in_json, "{label} is missing from the JSON listing");
+                assert!(html.contains(&row), "{label} is missing from the page");
+            }
+        }
+    }
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn movies_crud_operations() -> Result<()> {
+    // ---
+    common::setup_test_env().await;
+    let server = common::TestServer::new().await;
+
+    // Test GET /movies (should be empty initially)
+    let response = server
+        .client
+        .get(server.url("/api/v1/movies/get/1"))
+        .send()
+        .await
+        .expect("Failed to get movies");
+
+    assert_eq!(response.status(), 404);
+    let error: serde_json::Value = response.json().await?;
+    assert_eq!(error["code"], "movie_not_found");
+
+    let random_title = format!(
+        "Test Movie {}",
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis()
+    );
+
+    // Test POST /movies
+    let new_movie = json!({
+        "title": random_title,
+        "stars": 4.5,
+        "year": 2023
+    });
+
+    let response = server
+        .client
+        .post(server.url("/api/v1/movies/add"))
+        .json(&new_movie)
+        .send()
+        .await
+        .expect("Failed to create movie");
+
+    assert_eq!(response.status(), 201);
+    let location = response.headers()["location"].to_str()?.to_string();
+
+    // Extract the movie ID from the response
+    let created_response: serde_json::Value = response.json().await?;
+    let movie_id = created_response["data"]["id"]
+        .as_str()
+        .ok_or_else(|| anyhow::anyhow!("No ID in response"))?;
+
+    // The server assigns a UUID and points Location at the new movie
+    uuid::Uuid::parse_str(movie_id)?;
+    assert_eq!(location, format!("/api/v1/movies/get/{movie_id}"));
+    assert_eq!(created_response["data"]["title"], random_title);
+    assert_eq!(created_response["data"]["stars"], 4.5);
+
+    // Test GET /movies again (should now have one movie)
+    let response = server
+        .client
+        .get(server.url(&format!("/api/v1/movies/get/{movie_id}")))
+        .send()
+        .await
+        .expect("Failed to get movies after creation");
+
+    assert_eq!(response.status(), 200);
+    let movies: serde_json::Value = response.json().await.expect("Failed to parse JSON");
+
+    // Verify the movie was created (exact structure depends on your implementation)
+    ensure!(movies.is_array() || movies.is_object());
+    Ok(())
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn movie_titles_are_freed_by_update_and_delete() -> Result<()> {
+    // ---
+    common::setup_test_env().await;
+    let server = common::TestServer::new().await;
+    let suffix = uuid::Uuid::new_v4();
+    let first = json!({ "title": format!("Index A {suffix}"), "year": 2001, "stars": 3.0 });
+    let second = json!({ "title": format!("Index B {suffix}"), "year": 2001, "stars": 3.0 });
+
+    let add = |movie: &serde_json::Value| {
+        server
+            .client
+            .post(server.url("/api/v1/movies/add"))
+            .json(movie)
+            .send()
+    };
+
+    let created: serde_json::Value = add(&first).await?.json().await?;
+    let id = created["data"]["id"].as_str().unwrap().to_string();
+    let response = add(&first).await?;
+    assert_eq!(response.status(), 409);
+    let error: serde_json::Value = response.json().await?;
+    assert_eq!(error["code"], "movie_exists");
+
+    // Renaming frees the old title and takes the new one
+    let response = server
+        .client
+        .put(server.url(&format!("/api/v1/movies/update/{id}")))
+        .json(&second)
+        .send()
+        .await?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(add(&second).await?.status(), 409);
+    let readded: serde_json::Value = add(&first).await?.json().await?;
+    let other = readded["data"]["id"].as_str().unwrap().to_string();
+    assert_ne!(other, id);
+
+    // Another movie cannot be renamed onto a taken title
+    let response = server
+        .client
+        .put(server.url(&format!("/api/v1/movies/update/{other}")))
+        .json(&second)
+        .send()
+        .await?;
+    assert_eq!(response.status(), 409);
+    let error: serde_json::Value = response.json().await?;
+    assert_eq!(error["code"], "movie_exists");
+
+    // Deleting frees the title
+    for id in [&id, &other] {
+        let response = server
+            .client
+            .delete(server.url(&format!("/api/v1/movies/delete/{id}")))
+            .send()
+            .await?;
+        assert_eq!(response.status(), 204);
+    }
+    let response = add(&second).await?;
+    assert_eq!(response.status(), 201);
+    let created: serde_json::Value = response.json().await?;
+    server
+        .client
+        .delete(server.url(&format!(
+            "/api/v1/movies/delete/{}",
+            created["data"]["id"].as_str().unwrap()
+        )))
+        .send()
+        .await?;
+    Ok(())
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn concurrent_adds_of_one_title_create_one_movie() -> Result<()> {
+    // ---
+    common::setup_test_env().await;
+    let server = common::TestServer::new().await;
+    let movie = json!({
+        "title": format!("Race {}", uuid::Uuid::new_v4()),
+        "year": 1999,
+        "stars": 4.0,
+    });
+
+    let adds = (0..8).map(|_| {
+        server
+            .client
+            .post(server.url("/api/v1/movies/add"))
+            .json(&movie)
+            .send()
+    });
+    let mut statuses = Vec::new();
+    let mut created = Vec::new();
+    for response in futures::future::join_all(adds).await {
+        let response = response?;
+        statuses.push(response.status().as_u16());
+        if response.status() == 201 {
+            let body: serde_json::Value = response.json().await?;
+            created.push(body["data"]["id"].as_str().unwrap().to_string());
+        }
+    }
+    statuses.sort();
+    assert_eq!(statuses, [201, 409, 409, 409, 409, 409, 409, 409]);
+
+    let response = server
+        .client
+        .delete(server.url(&format!("/api/v1/movies/delete/{}", created[0])))
         .send()
+        .await?;
+    assert_eq!(response.status(), 204);
+    Ok(())
+}
+
+#[cfg(feature = "test-utils")]
+#[tokio::test]
+#[serial_test::serial]
+async fn movie_fixtures_match_the_api() {
+    // ---
+    use axum_quickstart::test_utils::Fixtures;
+
+    common::setup_test_env().await;
+    let config = AppConfig::from_env().unwrap();
+    let repository = create_repository(&config.database).await.unwrap();
+    let redis = redis::Client::open(config.redis.url)
+        .unwrap()
+        .get_multiplexed_async_connection()
         .await
-        .expect("Failed to send request");
+        .unwrap();
+    let mut fixtures = Fixtures::new(repository, redis);
+    let server = common::TestServer::new().await;
 
-    assert!(response.status().is_success());
+    let id = fixtures
+        .movie("  Fixture   Movie ", 1999)
+        .stars(4.0)
+        .insert()
+        .await
+        .unwrap();
 
-    let body = response.text().await.expect("Failed to read response body");
-    assert!(!body.is_empty());
+    let response = server
+        .client
+        .get(server.url(&format!("/api/v1/movies/get/{id}")))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["data"]["title"], "Fixture Movie");
+
+    // Indexed by title and year like the API, so adding it again conflicts
+    let response = server
+        .client
+        .post(server.url("/api/v1/movies/add"))
+        .json(&json!({ "title": "fixture movie", "year": 1999, "stars": 1.0 }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 409);
+
+    fixtures.reset().await.unwrap();
+    let response = server
+        .client
+        .get(server.url(&format!("/api/v1/movies/get/{id}")))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 404);
 }
 
+#[cfg(feature = "test-utils")]
 #[tokio::test]
 #[serial_test::serial]
-async fn root_endpoint_works() {
+async fn movies_list_filters_by_genre() {
     // ---
+    use axum_quickstart::test_utils::Fixtures;
+
     common::setup_test_env().await;
+    let config = AppConfig::from_env().unwrap();
+    let repository = create_repository(&config.database).await.unwrap();
+    let redis = redis::Client::open(config.redis.url)
+        .unwrap()
+        .get_multiplexed_async_connection()
+        .await
+        .unwrap();
+    let mut fixtures = Fixtures::new(repository, redis);
     let server = common::TestServer::new().await;
 
+    // Genres unique to this run, so other movies never match
+    let run = &uuid::Uuid::new_v4().simple().to_string()[..8];
+    let scifi = format!("scifi-{run}");
+    let drama = format!("drama-{run}");
+
+    let alien = fixtures
+        .movie("Alien", 1979)
+        .genres(&[&scifi])
+        .insert()
+        .await
+        .unwrap();
+    fixtures
+        .movie("Arrival", 2016)
+        .genres(&[&scifi, &drama])
+        .insert()
+        .await
+        .unwrap();
+
+    let list = |query: String| {
+        let url = server.url(&format!("/api/v1/movies/list?{query}"));
+        let client = server.client.clone();
+        async move {
+            let response = client.get(url).send().await.unwrap();
+            let status = response.status();
+            (status, response.json::<serde_json::Value>().await.unwrap())
+        }
+    };
+    let titles = |body: &serde_json::Value| -> Vec<String> {
+        body["data"]["movies"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|m| m["title"].as_str().unwrap().to_string())
+            .collect()
+    };
+
+    // Filter is case-insensitive and results are sorted by title
+    let (status, body) = list(format!("genre={}", scifi.to_uppercase())).await;
+    assert_eq!(status, 200);
+    assert_eq!(titles(&body), ["Alien", "Arrival"]);
+    assert_eq!(body["meta"]["pagination"]["total"], 2);
+
+    let (_, body) = list(format!("genre={scifi}&per_page=1")).await;
+    assert_eq!(titles(&body), ["Alien"]);
+    assert_eq!(
+        body["links"]["next"],
+        format!("/api/v1/movies/list?genre={scifi}&page=2&per_page=1")
+    );
+
+    let (_, body) = list(format!("genre={drama}")).await;
+    assert_eq!(titles(&body), ["Arrival"]);
+
+    // Updating the genres moves the movie between sets
     let response = server
         .client
-        .get(server.url("/"))
+        .put(server.url(&format!("/api/v1/movies/update/{alien}")))
+        .json(&json!({ "title": "Alien", "year": 1979, "stars": 5.0, "genres": [drama] }))
         .send()
         .await
-        .expect("Failed to send request");
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let (_, body) = list(format!("genre={scifi}")).await;
+    assert_eq!(titles(&body), ["Arrival"]);
+    let (_, body) = list(format!("genre={drama}")).await;
+    assert_eq!(titles(&body), ["Alien", "Arrival"]);
+
+    // Deleting removes it from every list
+    let response = server
+        .client
+        .delete(server.url(&format!("/api/v1/movies/delete/{alien}")))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 204);
+    let (_, body) = list(format!("genre={drama}")).await;
+    assert_eq!(titles(&body), ["Arrival"]);
+    let (_, body) = list("per_page=100".to_string()).await;
+    assert!(!body["data"]["movies"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|m| m["id"] == alien.as_str()));
 
+    let (status, body) = list("genre=sci%20fi".to_string()).await;
+    assert_eq!(status, 400);
+    assert_eq!(body["code"], "invalid_genre");
+
+    fixtures.reset().await.unwrap();
+    let (_, body) = list(format!("genre={drama}")).await;
+    assert_eq!(body["meta"]["pagination"]["total"], 0);
+}
+
+#[cfg(feature = "test-utils")]
+#[tokio::test]
+#[serial_test::serial]
+async fn movie_responses_follow_accept() {
+    // ---
+    use axum_quickstart::test_utils::Fixtures;
+
+    common::setup_test_env().await;
+    let config = AppConfig::from_env().unwrap();
+    let repository = create_repository(&config.database).await.unwrap();
+    let redis = redis::Client::open(config.redis.url)
+        .unwrap()
+        .get_multiplexed_async_connection()
+        .await
+        .unwrap();
+    let mut fixtures = Fixtures::new(repository, redis);
+    let server = common::TestServer::new().await;
+
+    let genre = format!("noir-{}", &uuid::Uuid::new_v4().simple().to_string()[..8]);
+    let id = fixtures
+        .movie("Chinatown, Revisited", 1974)
+        .stars(4.5)
+        .genres(&[&genre, "mystery"])
+        .insert()
+        .await
+        .unwrap();
+
+    let get = |path: String, accept: &'static str| {
+        let request = server
+            .client
+            .get(server.url(&path))
+            .header("accept", accept);
+        async move { request.send().await.unwrap() }
+    };
+
+    // MessagePack carries the same envelope as JSON
+    let response = get(format!("/api/v1/movies/get/{id}"), "application/msgpack").await;
     assert_eq!(response.status(), 200);
+    assert_eq!(response.headers()["content-type"], "application/msgpack");
+    assert_eq!(response.headers()["vary"], "accept");
+    let body: serde_json::Value = rmp_serde::from_slice(&response.bytes().await.unwrap()).unwrap();
+    assert_eq!(body["data"]["title"], "Chinatown, Revisited");
+    assert!(body["meta"]["request_id"].is_string());
 
-    let body = response.text().await.expect("Failed to read response body");
-    assert!(!body.is_empty());
+    // CSV is only offered for lists
+    let response = get(format!("/api/v1/movies/get/{id}"), "text/csv").await;
+    assert_eq!(response.headers()["content-type"], "application/json");
+
+    let response = get(format!("/api/v1/movies/list?genre={genre}"), "text/csv").await;
+    assert_eq!(response.status(), 200);
+    assert_eq!(
+        response.headers()["content-type"],
+        "text/csv; charset=utf-8"
+    );
+    let expected = format!(
+        "id,title,year,stars,genres\n{id},\"Chinatown, Revisited\",1974,4.5,mystery;{genre}\n"
+    );
+    assert_eq!(response.text().await.unwrap(), expected);
+
+    let response = get(format!("/api/v1/movies/list?genre={genre}"), "*/*").await;
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["meta"]["pagination"]["total"], 1);
+
+    fixtures.reset().await.unwrap();
 }
 
+#[cfg(feature = "test-utils")]
 #[tokio::test]
 #[serial_test::serial]
-async fn movies_crud_operations() -> Result<()> {
+async fn credential_listing_answers_if_modified_since() {
+    // ---
+    use axum_quickstart::test_utils::Fixtures;
+
+    common::setup_test_env().await;
+    let config = AppConfig::from_env().unwrap();
+    let repository = create_repository(&config.database).await.unwrap();
+    let redis = redis::Client::open(config.redis.url)
+        .unwrap()
+        .get_multiplexed_async_connection()
+        .await
+        .unwrap();
+    let mut fixtures = Fixtures::new(repository.clone(), redis);
+    let server = common::TestServer::new().await;
+
+    let username = format!("poller_{}", &uuid::Uuid::new_v4().simple().to_string()[..8]);
+    let user = fixtures.user(&username).insert().await.unwrap();
+    let mut credential = fixtures.credential(&user).insert().await.unwrap();
+    let token = fixtures.session(&user).insert().await.unwrap();
+    let list = |since: Option<String>| {
+        let mut request = server
+            .client
+            .get(server.url("/api/v1/webauthn/credentials"))
+            .bearer_auth(&token);
+        if let Some(since) = since {
+            request = request.header("if-modified-since", since);
+        }
+        async move { request.send().await.unwrap() }
+    };
+
+    let response = list(None).await;
+    assert_eq!(response.status(), 200);
+    let last_modified = response.headers()["last-modified"]
+        .to_str()
+        .unwrap()
+        .to_string();
+    assert!(last_modified.ends_with(" GMT"));
+
+    // Nothing changed since
+    let response = list(Some(last_modified.clone())).await;
+    assert_eq!(response.status(), 304);
+    assert_eq!(response.headers()["last-modified"], last_modified.as_str());
+    assert!(response.bytes().await.unwrap().is_empty());
+
+    // An older date, or one that does not parse, gets the list
+    let response = list(Some("Mon, 01 Jan 2024 00:00:00 GMT".to_string())).await;
+    assert_eq!(response.status(), 200);
+    let response = list(Some("yesterday".to_string())).await;
+    assert_eq!(response.status(), 200);
+
+    // A sign-in advances it
+    credential.last_used_at = Some(chrono::Utc::now() + chrono::Duration::minutes(1));
+    repository.update_credential(credential).await.unwrap();
+    let response = list(Some(last_modified.clone())).await;
+    assert_eq!(response.status(), 200);
+    assert_ne!(response.headers()["last-modified"], last_modified.as_str());
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert!(body["data"]["credentials"][0]["last_used_at"].is_string());
+
+    fixtures.reset().await.unwrap();
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn invalid_routes_return_404() {
     // ---
     common::setup_test_env().await;
     let server = common::TestServer::new().await;
 
-    // Test GET /movies (should be empty initially)
     let response = server
         .client
-        .get(server.url("/movies/get/1"))
+        .get(server.url("/nonexistent"))
         .send()
         .await
-        .expect("Failed to get movies");
+        .expect("Failed to send request");
 
     assert_eq!(response.status(), 404);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["code"], "not_found");
+}
 
-    let random_title = format!(
-        "Test Movie {}",
-        std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_millis()
-    );
+#[tokio::test]
+#[serial_test::serial]
+async fn unsupported_methods_return_405_with_allow() {
+    // ---
+    common::setup_test_env().await;
+    let server = common::TestServer::new().await;
 
-    // Test POST /movies
-    let new_movie = json!({
-        "title": random_title,
-        "stars": 4.5,
-        "year": 2023
-    });
+    for path in [
+        "/api/v1/movies/add",
+        "/api/v1/webauthn/credentials",
+        "/movies/add",
+    ] {
+        let response = server
+            .client
+            .patch(server.url(path))
+            .send()
+            .await
+            .expect("Failed to send request");
+
+        assert_eq!(response.status(), 405, "{path}");
+        let allow = response.headers()["allow"].to_str().unwrap().to_string();
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["code"], "method_not_allowed", "{path}");
+        assert!(!allow.is_empty(), "{path}");
+    }
 
     let response = server
         .client
-        .post(server.url("/movies/add"))
-        .json(&new_movie)
+        .delete(server.url("/api/v1/movies/get/some-id"))
         .send()
         .await
-        .expect("Failed to create movie");
-
-    assert_eq!(response.status(), 201);
+        .expect("Failed to send request");
+    assert_eq!(response.headers()["allow"], "GET,HEAD");
+}
 
-    // Extract the movie ID from the response
-    let created_response: serde_json::Value = response.json().await?;
-    let movie_id = created_response["id"]
-        .as_str()
-        .ok_or_else(|| anyhow::anyhow!("No ID in response"))?;
+#[tokio::test]
+#[serial_test::serial]
+async fn legacy_routes_emit_deprecation_headers() {
+    // ---
+    common::setup_test_env().await;
+    let server = common::TestServer::new().await;
 
-    // Test GET /movies again (should now have one movie)
     let response = server
         .client
-        .get(server.url(&format!("/movies/get/{movie_id}")))
+        .get(server.url("/health"))
         .send()
         .await
-        .expect("Failed to get movies after creation");
+        .expect("Failed to send request");
 
     assert_eq!(response.status(), 200);
-    let movies: serde_json::Value = response.json().await.expect("Failed to parse JSON");
-
-    // Verify the movie was created (exact structure depends on your implementation)
-    ensure!(movies.is_array() || movies.is_object());
-    Ok(())
+    assert_eq!(response.headers()["deprecation"], "true");
+    assert_eq!(
+        response.headers()["link"],
+        "</api/v1/health>; rel=\"successor-version\""
+    );
 }
 
 #[tokio::test]
 #[serial_test::serial]
-async fn invalid_routes_return_404() {
+async fn versioned_routes_are_not_deprecated() {
     // ---
     common::setup_test_env().await;
     let server = common::TestServer::new().await;
 
     let response = server
         .client
-        .get(server.url("/nonexistent"))
+        .get(server.url("/api/v1/health"))
         .send()
         .await
         .expect("Failed to send request");
 
-    assert_eq!(response.status(), 404);
+    assert_eq!(response.status(), 200);
+    assert!(response.headers().get("deprecation").is_none());
 }
 
 #[tokio::test]
@@ -142,7 +1818,7 @@ async fn server_handles_concurrent_requests() {
     let server = common::TestServer::new().await;
 
     // Make multiple concurrent requests
-    let futures = (0..10).map(|_| server.client.get(server.url("/health")).send());
+    let futures = (0..10).map(|_| server.client.get(server.url("/api/v1/health")).send());
 
     let responses = futures::future::join_all(futures).await;
 
@@ -163,7 +1839,7 @@ async fn server_handles_malformed_json() {
     // Send malformed JSON to movies endpoint
     let response = server
         .client
-        .post(server.url("/movies/add"))
+        .post(server.url("/api/v1/movies/add"))
         .header("content-type", "application/json")
         .body("{ invalid json }")
         .send()
@@ -174,6 +1850,78 @@ async fn server_handles_malformed_json() {
     assert_eq!(response.status(), 400);
 }
 
+#[tokio::test]
+#[serial_test::serial]
+async fn csrf_token_required_for_cookie_sessions() {
+    // ---
+    common::setup_test_env().await;
+    let server = common::TestServer::new().await;
+
+    let config = AppConfig::from_env().unwrap();
+    let mut redis_conn = redis::Client::open(config.redis.url)
+        .unwrap()
+        .get_multiplexed_async_connection()
+        .await
+        .unwrap();
+    let session = create_session(
+        &mut redis_conn,
+        uuid::Uuid::new_v4(),
+        "csrf_user".into(),
+        None,
+    )
+    .await
+    .unwrap();
+    let cookie = format!("{SESSION_COOKIE}={session}");
+
+    // Malformed JSON: a request that gets past CSRF fails with 400 in the handler
+    let add_movie = |csrf: Option<&str>, bearer: bool| {
+        let mut request = server
+            .client
+            .post(server.url("/api/v1/movies/add"))
+            .header("cookie", &cookie)
+            .header("content-type", "application/json")
+            .body("{ invalid json }");
+        if let Some(token) = csrf {
+            request = request.header(CSRF_HEADER, token);
+        }
+        if bearer {
+            request = request.bearer_auth(&session);
+        }
+        request.send()
+    };
+
+    assert_eq!(add_movie(None, false).await.unwrap().status(), 403);
+    assert_eq!(
+        add_movie(Some("forged"), false).await.unwrap().status(),
+        403
+    );
+
+    // Bearer-token requests bypass CSRF
+    assert_eq!(add_movie(None, true).await.unwrap().status(), 400);
+
+    let response = server
+        .client
+        .get(server.url("/api/v1/csrf"))
+        .header("cookie", &cookie)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let json: serde_json::Value = response.json().await.unwrap();
+    let token = json["data"]["csrf_token"].as_str().unwrap();
+
+    assert_eq!(add_movie(Some(token), false).await.unwrap().status(), 400);
+
+    // Issuing requires a valid session cookie
+    let response = server
+        .client
+        .get(server.url("/api/v1/csrf"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 401);
+}
+
 #[tokio::test]
 #[serial_test::serial]
 async fn redis_integration_works() {
@@ -188,7 +1936,7 @@ async fn redis_integration_works() {
     // Make some requests that would use Redis (if your app caches anything)
     let response = server
         .client
-        .get(server.url("/health"))
+        .get(server.url("/api/v1/health"))
         .send()
         .await
         .expect("Failed to send request");
@@ -197,3 +1945,278 @@ async fn redis_integration_works() {
 
     // Add more specific Redis integration tests based on your app's usage
 }
+
+/// Mints an HS256 session token the way a standard JWT library would.
+fn mint_jwt(key: &[u8], claims: serde_json::Value) -> String {
+    // ---
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+    use hmac::{Hmac, Mac};
+
+    let header = URL_SAFE_NO_PAD.encode(r#"{"alg":"HS256","typ":"JWT"}"#);
+    let claims = URL_SAFE_NO_PAD.encode(claims.to_string());
+    let mut mac = Hmac::<sha2::Sha256>::new_from_slice(key).unwrap();
+    mac.update(format!("{header}.{claims}").as_bytes());
+    let signature = URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes());
+    format!("{header}.{claims}.{signature}")
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn logout_ends_redis_session() {
+    // ---
+    common::setup_test_env().await;
+    let server = common::TestServer::new().await;
+
+    let config = AppConfig::from_env().unwrap();
+    let mut redis_conn = redis::Client::open(config.redis.url)
+        .unwrap()
+        .get_multiplexed_async_connection()
+        .await
+        .unwrap();
+    let session = create_session(
+        &mut redis_conn,
+        uuid::Uuid::new_v4(),
+        "logout_user".into(),
+        None,
+    )
+    .await
+    .unwrap();
+
+    let logout = || {
+        server
+            .client
+            .post(server.url("/api/v1/webauthn/logout"))
+            .bearer_auth(&session)
+            .send()
+    };
+
+    assert_eq!(logout().await.unwrap().status(), 204);
+    assert_eq!(logout().await.unwrap().status(), 401);
+}
+
+#[cfg(feature = "test-utils")]
+#[tokio::test]
+#[serial_test::serial]
+async fn sessions_used_by_a_different_client_are_revoked() {
+    // ---
+    use axum_quickstart::test_utils::Fixtures;
+    use axum_quickstart::FingerprintPolicy;
+
+    const FIREFOX: &str = "Mozilla/5.0 (X11; Linux x86_64; rv:128.0) Gecko/20100101 Firefox/128.0";
+
+    common::setup_test_env().await;
+    let mut config = AppConfig::from_env().expect("config should load");
+    config.session.fingerprint = FingerprintPolicy::Revoke;
+    config.access.trusted_proxies = vec!["127.0.0.1".parse().unwrap()];
+    let repository = create_repository(&config.database).await.unwrap();
+    let redis = redis::Client::open(config.redis.url.clone())
+        .unwrap()
+        .get_multiplexed_async_connection()
+        .await
+        .unwrap();
+    let mut fixtures = Fixtures::new(repository.clone(), redis);
+
+    let router = AppBuilder::new()
+        .config(config)
+        .repository(repository)
+        .build()
+        .unwrap();
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}/api/v1", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        axum::serve(
+            listener,
+            router.into_make_service_with_connect_info::<std::net::SocketAddr>(),
+        )
+        .await
+        .unwrap()
+    });
+
+    let username = format!("roamer_{}", &uuid::Uuid::new_v4().simple().to_string()[..8]);
+    let user = fixtures.user(&username).insert().await.unwrap();
+    let token = fixtures
+        .session(&user)
+        .client_ip("198.51.100.4".parse().unwrap())
+        .user_agent(FIREFOX)
+        .insert()
+        .await
+        .unwrap();
+
+    let client = reqwest::Client::new();
+    let list = |forwarded_for: &str, user_agent: &str| {
+        client
+            .get(format!("{base}/webauthn/credentials"))
+            .bearer_auth(&token)
+            .header("x-forwarded-for", forwarded_for)
+            .header("user-agent", user_agent)
+            .send()
+    };
+
+    // Another network, or another browser version, alone is fine
+    let updated = FIREFOX.replace("128.0", "131.0");
+    assert_eq!(list("198.51.7.9", &updated).await.unwrap().status(), 200);
+    assert_eq!(list("203.0.113.9", FIREFOX).await.unwrap().status(), 200);
+
+    // Both at once ends the session, for every client
+    assert_eq!(
+        list("203.0.113.9", "curl/8.5.0").await.unwrap().status(),
+        401
+    );
+    assert_eq!(list("198.51.100.4", FIREFOX).await.unwrap().status(), 401);
+
+    fixtures.reset().await.unwrap();
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn signed_sessions_validate_and_revoke() {
+    // ---
+    common::setup_test_env().await;
+
+    let key = "integration-test-signing-key-0123456789";
+    let mut config = AppConfig::from_env().expect("config should load");
+    config.session = SessionConfig {
+        mode: SessionMode::Signed,
+        signing_key: Some(key.to_string()),
+        ..SessionConfig::default()
+    };
+    let repository = create_repository(&config.database).await.unwrap();
+
+    let router = AppBuilder::new()
+        .config(config)
+        .repository(repository)
+        .build()
+        .unwrap();
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}/api/v1", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+
+    let now = chrono::Utc::now().timestamp();
+    let claims = json!({
+        "sub": uuid::Uuid::new_v4(),
+        "name": "signed_user",
+        "jti": uuid::Uuid::new_v4(),
+        "iat": now,
+        "exp": now + 3600,
+    });
+    let token = mint_jwt(key.as_bytes(), claims.clone());
+
+    let client = reqwest::Client::new();
+    let csrf = |token: String| {
+        client
+            .get(format!("{base}/csrf"))
+            .header("cookie", format!("{SESSION_COOKIE}={token}"))
+            .send()
+    };
+
+    assert_eq!(csrf(token.clone()).await.unwrap().status(), 200);
+    assert_eq!(
+        csrf(mint_jwt(b"some-other-key-entirely-0123456789", claims))
+            .await
+            .unwrap()
+            .status(),
+        401
+    );
+
+    let logout = client
+        .post(format!("{base}/webauthn/logout"))
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(logout.status(), 204);
+
+    // The jti is now on the denylist
+    assert_eq!(csrf(token).await.unwrap().status(), 401);
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn sessions_are_rejected_on_another_tenants_host() {
+    // ---
+    use axum_quickstart::{TenantConfig, WebAuthnConfig};
+
+    common::setup_test_env().await;
+
+    let key = "integration-test-signing-key-0123456789";
+    let mut config = AppConfig::from_env().expect("config should load");
+    config.tenants = vec![TenantConfig {
+        id: "acme".to_string(),
+        hosts: vec!["acme.example".to_string()],
+        webauthn: WebAuthnConfig {
+            rp_id: "acme.example".to_string(),
+            rp_name: "Acme".to_string(),
+            origin: "https://acme.example".to_string(),
+            additional_origins: Vec::new(),
+        },
+    }];
+    let repository = create_repository(&config.database).await.unwrap();
+    let mut redis_conn = redis::Client::open(config.redis.url.clone())
+        .unwrap()
+        .get_multiplexed_async_connection()
+        .await
+        .unwrap();
+    let redis_router = AppBuilder::new()
+        .config(config.clone())
+        .repository(repository.clone())
+        .build()
+        .unwrap();
+    config.session = SessionConfig {
+        mode: SessionMode::Signed,
+        signing_key: Some(key.to_string()),
+        ..SessionConfig::default()
+    };
+    let signed_router = AppBuilder::new()
+        .config(config)
+        .repository(repository)
+        .build()
+        .unwrap();
+
+    let csrf = |router: axum::Router, host: &str, token: &str| {
+        let request = Request::builder()
+            .uri("/api/v1/csrf")
+            .header("host", host)
+            .header("cookie", format!("{SESSION_COOKIE}={token}"))
+            .body(Body::empty())
+            .unwrap();
+        async move { router.oneshot(request).await.unwrap().status() }
+    };
+
+    // A Redis session of the default tenant is not accepted by acme
+    let default_session = create_session(
+        &mut redis_conn,
+        uuid::Uuid::new_v4(),
+        "tenant_session_user".into(),
+        None,
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        csrf(redis_router.clone(), "acme.example", &default_session).await,
+        401
+    );
+    assert_eq!(csrf(redis_router, "localhost", &default_session).await, 200);
+
+    // A signed session of acme is not accepted by the default tenant
+    let now = chrono::Utc::now().timestamp();
+    let acme_session = mint_jwt(
+        key.as_bytes(),
+        json!({
+            "sub": uuid::Uuid::new_v4(),
+            "name": "acme_user",
+            "tid": "acme",
+            "jti": uuid::Uuid::new_v4(),
+            "iat": now,
+            "exp": now + 3600,
+        }),
+    );
+    assert_eq!(
+        csrf(signed_router.clone(), "localhost", &acme_session).await,
+        401
+    );
+    assert_eq!(
+        csrf(signed_router, "acme.example", &acme_session).await,
+        200
+    );
+}
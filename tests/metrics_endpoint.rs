@@ -1,3 +1,4 @@
+use serde_json::json;
 use serial_test::serial;
 use std::sync::Arc;
 use std::time::Duration;
@@ -22,25 +23,60 @@ async fn metrics_endpoint_with_prometheus() {
     // First, hit some endpoints to generate metrics
     let _ = server
         .client
-        .get(server.url("/health"))
+        .get(server.url("/api/v1/health"))
         .send()
         .await
         .unwrap();
     let _ = server.client.get(server.url("/")).send().await.unwrap();
     let _ = server
         .client
-        .get(server.url("/movies"))
+        .get(server.url("/api/v1/movies"))
         .send()
         .await
         .unwrap();
 
+    // Create, update, and delete a movie so each movie counter is recorded
+    let mut movie = json!({
+        "title": format!("Metrics Movie {}", uuid::Uuid::new_v4()),
+        "year": 2024,
+        "stars": 3.5
+    });
+    let created = server
+        .client
+        .post(server.url("/api/v1/movies/add"))
+        .json(&movie)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(created.status(), 201);
+    let created: serde_json::Value = created.json().await.unwrap();
+    let id = created["data"]["id"].as_str().unwrap().to_string();
+
+    movie["stars"] = json!(4.0);
+    let updated = server
+        .client
+        .put(server.url(&format!("/api/v1/movies/update/{id}")))
+        .json(&movie)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(updated.status(), 200);
+
+    let deleted = server
+        .client
+        .delete(server.url(&format!("/api/v1/movies/delete/{id}")))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(deleted.status(), 204);
+
     // Give metrics a moment to be recorded
     sleep(Duration::from_millis(50)).await;
 
     // Now check the metrics endpoint
     let res = server
         .client
-        .get(server.url("/metrics"))
+        .get(server.url("/api/v1/metrics"))
         .send()
         .await
         .unwrap();
@@ -59,6 +95,14 @@ async fn metrics_endpoint_with_prometheus() {
     // The metrics endpoint should return some content
     assert!(!body.is_empty(), "Metrics should not be empty");
 
+    for counter in [
+        "movies_created_total",
+        "movies_updated_total",
+        "movies_deleted_total",
+    ] {
+        assert!(body.contains(counter), "Metrics should include {counter}");
+    }
+
     // For Prometheus format, we expect specific patterns
     if body.contains("# HELP") || body.contains("# TYPE") {
         // This looks like Prometheus format
@@ -85,7 +129,7 @@ async fn metrics_endpoint_with_noop() {
     // Hit some endpoints
     let _ = server
         .client
-        .get(server.url("/health"))
+        .get(server.url("/api/v1/health"))
         .send()
         .await
         .unwrap();
@@ -94,7 +138,7 @@ async fn metrics_endpoint_with_noop() {
     // Check the metrics endpoint
     let res = server
         .client
-        .get(server.url("/metrics"))
+        .get(server.url("/api/v1/metrics"))
         .send()
         .await
         .unwrap();
@@ -126,9 +170,9 @@ async fn metrics_endpoint_survives_load() {
         let server = Arc::clone(&server);
         async move {
             let endpoint = match i % 3 {
-                0 => "/health",
+                0 => "/api/v1/health",
                 1 => "/",
-                _ => "/metrics",
+                _ => "/api/v1/metrics",
             };
             server.client.get(server.url(endpoint)).send().await
         }
@@ -153,7 +197,7 @@ async fn metrics_endpoint_survives_load() {
     // Now check metrics
     let res = server
         .client
-        .get(server.url("/metrics"))
+        .get(server.url("/api/v1/metrics"))
         .send()
         .await
         .unwrap();
@@ -177,7 +221,7 @@ async fn metrics_content_type_is_correct() {
 
     let res = server
         .client
-        .get(server.url("/metrics"))
+        .get(server.url("/api/v1/metrics"))
         .send()
         .await
         .unwrap();
@@ -0,0 +1,111 @@
+//! Integration tests for password sign-in (`--features password`).
+//!
+//! Run with `cargo test --features password --test password`.
+
+#![cfg(feature = "password")]
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use serde_json::json;
+use tower::ServiceExt;
+use uuid::Uuid;
+
+mod common;
+
+fn unique_username() -> String {
+    // ---
+    format!("pw_{}", Uuid::new_v4().simple())
+}
+
+#[tokio::test]
+async fn registered_password_signs_in_with_a_session() {
+    // ---
+    let router = common::isolated_router().await;
+    let username = unique_username();
+    let password = "correct horse battery staple";
+
+    let (status, body) = common::post_json(
+        &router,
+        "/api/v1/auth/password/register",
+        json!({ "username": username, "password": password }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{body}");
+    assert!(body["user_id"].is_string());
+
+    let (status, body) = common::post_json(
+        &router,
+        "/api/v1/auth/password/login",
+        json!({ "username": username, "password": password }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let token = body["session_token"].as_str().unwrap();
+
+    // The session works on the session-authenticated routes
+    let request = Request::builder()
+        .uri("/api/v1/webauthn/credentials")
+        .header("authorization", format!("Bearer {token}"))
+        .body(Body::empty())
+        .unwrap();
+    let response = router.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn failed_sign_ins_are_indistinguishable() {
+    // ---
+    let router = common::isolated_router().await;
+    let username = unique_username();
+
+    let (status, _) = common::post_json(
+        &router,
+        "/api/v1/auth/password/register",
+        json!({ "username": username, "password": "correct horse battery staple" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let (wrong_status, wrong_body) = common::post_json(
+        &router,
+        "/api/v1/auth/password/login",
+        json!({ "username": username, "password": "wrong horse battery staple" }),
+    )
+    .await;
+    let (unknown_status, unknown_body) = common::post_json(
+        &router,
+        "/api/v1/auth/password/login",
+        json!({ "username": unique_username(), "password": "correct horse battery staple" }),
+    )
+    .await;
+
+    assert_eq!(wrong_status, StatusCode::UNAUTHORIZED);
+    assert_eq!(unknown_status, StatusCode::UNAUTHORIZED);
+    assert_eq!(wrong_body, unknown_body);
+}
+
+#[tokio::test]
+async fn register_enforces_policy_and_unique_usernames() {
+    // ---
+    let router = common::isolated_router().await;
+    let username = unique_username();
+
+    let (status, body) = common::post_json(
+        &router,
+        "/api/v1/auth/password/register",
+        json!({ "username": username, "password": "short" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "invalid_password");
+
+    let register = json!({ "username": username, "password": "correct horse battery staple" });
+    let (status, _) =
+        common::post_json(&router, "/api/v1/auth/password/register", register.clone()).await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let (status, body) =
+        common::post_json(&router, "/api/v1/auth/password/register", register).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["code"], "username_taken");
+}
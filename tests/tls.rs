@@ -0,0 +1,212 @@
+//! Integration tests for HTTPS and mutual TLS (`--features tls`).
+//!
+//! The server runs on a real socket with the certificates in
+//! `tests/fixtures/tls` (see `scripts/gen-test-certs.sh`), and requests are
+//! written as raw HTTP/1.1 over a rustls client connection.
+#![cfg(feature = "tls")]
+
+use axum_quickstart::{
+    create_repository, tls_server_config, AppBuilder, AppConfig, ConfigReloader, LiveTls,
+    MakeServiceWithIdentity, ServerConfig, ServerListener, TlsConfig, TlsListener,
+};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio_rustls::TlsConnector;
+
+mod common;
+
+const SERVICE_TOKEN: &str = "tls-service-secret";
+
+fn fixture(name: &str) -> PathBuf {
+    // ---
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/tls")
+        .join(name)
+}
+
+// ---
+
+/// Test helper: Serves the app over mutual TLS, with client certificates
+/// required on service routes
+async fn spawn_tls_server() -> SocketAddr {
+    // ---
+    common::setup_test_env().await;
+    let mut config = AppConfig::from_env().expect("config should load");
+    config.admin.service_tokens = vec![SERVICE_TOKEN.to_string()];
+    config.access.require_client_cert = true;
+    let repository = create_repository(&config.database).await.unwrap();
+    let router = AppBuilder::new()
+        .config(config)
+        .repository(repository)
+        .build()
+        .unwrap();
+
+    let tls = tls_server_config(&TlsConfig {
+        cert_file: Some(fixture("server.pem")),
+        key_file: Some(fixture("server.key")),
+        client_ca_file: Some(fixture("ca.pem")),
+    })
+    .unwrap();
+    let listener = ServerListener::bind("127.0.0.1:0", &ServerConfig::default())
+        .await
+        .unwrap();
+    let listener = TlsListener::new(listener, tls).unwrap();
+    let addr = axum::serve::Listener::local_addr(&listener).unwrap();
+
+    tokio::spawn(async move {
+        axum::serve(listener, MakeServiceWithIdentity::new(router))
+            .await
+            .unwrap();
+    });
+    addr
+}
+
+/// Test helper: Sends `request` over TLS, presenting the named client
+/// certificate if any; returns the raw response, or an I/O error if the
+/// server rejected the connection
+async fn send(
+    addr: SocketAddr,
+    client_cert: Option<&str>,
+    request: &str,
+) -> std::io::Result<String> {
+    // ---
+    let mut roots = rustls::RootCertStore::empty();
+    for cert in CertificateDer::pem_file_iter(fixture("ca.pem")).unwrap() {
+        roots.add(cert.unwrap()).unwrap();
+    }
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let builder = rustls::ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_root_certificates(roots);
+    let config = match client_cert {
+        Some(name) => {
+            let certs = CertificateDer::pem_file_iter(fixture(&format!("{name}.pem")))
+                .unwrap()
+                .map(Result::unwrap)
+                .collect();
+            let key = PrivateKeyDer::from_pem_file(fixture(&format!("{name}.key"))).unwrap();
+            builder.with_client_auth_cert(certs, key).unwrap()
+        }
+        None => builder.with_no_client_auth(),
+    };
+
+    let tcp = TcpStream::connect(addr).await?;
+    let server_name = ServerName::try_from("localhost").unwrap();
+    let mut stream = TlsConnector::from(Arc::new(config))
+        .connect(server_name, tcp)
+        .await?;
+    stream.write_all(request.as_bytes()).await?;
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await?;
+    Ok(response)
+}
+
+/// Test helper: A `POST /api/v1/auth/introspect` request
+fn introspect_request() -> String {
+    // ---
+    let body = r#"{"token":"not-a-session"}"#;
+    format!(
+        "POST /api/v1/auth/introspect HTTP/1.1\r\n\
+         Host: localhost\r\n\
+         Authorization: Bearer {SERVICE_TOKEN}\r\n\
+         Content-Type: application/json\r\n\
+         Content-Length: {}\r\n\
+         Connection: close\r\n\r\n{body}",
+        body.len()
+    )
+}
+
+// ---
+
+#[tokio::test]
+async fn service_routes_require_a_client_certificate() {
+    // ---
+    let addr = spawn_tls_server().await;
+
+    for name in ["client", "client-san"] {
+        let response = send(addr, Some(name), &introspect_request()).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200"), "{name}: {response}");
+        assert!(response.contains(r#""active":false"#));
+    }
+
+    let response = send(addr, None, &introspect_request()).await.unwrap();
+    assert!(response.starts_with("HTTP/1.1 403"), "{response}");
+    assert!(response.contains("Client certificate required"));
+
+    // Other routes do not need one
+    let root = "GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n";
+    let response = send(addr, None, root).await.unwrap();
+    assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+}
+
+#[tokio::test]
+async fn untrusted_client_certificates_are_refused() {
+    // ---
+    let addr = spawn_tls_server().await;
+
+    // With TLS 1.3 the client learns of the rejection on its first read
+    let response = send(addr, Some("untrusted-client"), &introspect_request()).await;
+    assert!(response.is_err(), "{response:?}");
+}
+
+#[tokio::test]
+async fn reload_applies_new_certificates_to_new_connections() {
+    // ---
+    common::setup_test_env().await;
+    let mut config = AppConfig::from_env().expect("config should load");
+    config.admin.service_tokens = vec![SERVICE_TOKEN.to_string()];
+    config.access.require_client_cert = true;
+    config.tls = TlsConfig {
+        cert_file: Some(fixture("server.pem")),
+        key_file: Some(fixture("server.key")),
+        client_ca_file: None,
+    };
+    let repository = create_repository(&config.database).await.unwrap();
+    let tls = LiveTls::load(&config.tls).unwrap();
+    let reloader = ConfigReloader::new(&config).with_tls(tls.clone());
+    let router = AppBuilder::new()
+        .config(config.clone())
+        .repository(repository)
+        .reloader(reloader.clone())
+        .build()
+        .unwrap();
+
+    let listener = ServerListener::bind("127.0.0.1:0", &ServerConfig::default())
+        .await
+        .unwrap();
+    let listener = TlsListener::new(listener, tls).unwrap();
+    let addr = axum::serve::Listener::local_addr(&listener).unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, MakeServiceWithIdentity::new(router))
+            .await
+            .unwrap();
+    });
+
+    // Without a client CA the server does not ask for a certificate
+    let response = send(addr, Some("client"), &introspect_request())
+        .await
+        .unwrap();
+    assert!(response.starts_with("HTTP/1.1 403"), "{response}");
+
+    // A missing file keeps the current certificates
+    let mut broken = config.clone();
+    broken.tls.client_ca_file = Some(fixture("missing.pem"));
+    assert!(reloader.reload(broken).is_empty());
+    assert!(reloader.reload(config.clone()).is_empty());
+
+    let mut reloaded = config.clone();
+    reloaded.tls.client_ca_file = Some(fixture("ca.pem"));
+    assert_eq!(reloader.reload(reloaded), ["tls: certificates reloaded"]);
+
+    let response = send(addr, Some("client"), &introspect_request())
+        .await
+        .unwrap();
+    assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+}
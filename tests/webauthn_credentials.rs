@@ -3,13 +3,14 @@
 //! Tests credential listing and deletion endpoints with session-based authentication.
 
 use axum_quickstart::create_postgres_repository;
-use axum_quickstart::domain::{Credential, RepositoryPtr, User};
-use axum_quickstart::{create_session, validate_session};
+#[cfg(feature = "test-utils")]
+use axum_quickstart::domain::RepositoryPtr;
+#[cfg(feature = "test-utils")]
+use axum_quickstart::test_utils::Fixtures;
+use axum_quickstart::validate_session;
 use once_cell::sync::Lazy;
-use redis::AsyncCommands;
 use std::sync::Arc;
 use tokio::runtime::Runtime;
-use uuid::Uuid;
 
 mod common;
 
@@ -28,40 +29,6 @@ static TEST_RUNTIME: Lazy<Arc<Runtime>> = Lazy::new(|| {
 
 // ---
 
-/// Test helper: Create test user in database
-async fn create_test_user(repo: &RepositoryPtr, username: &str) -> User {
-    //
-    repo.create_user(username)
-        .await
-        .expect("Failed to create test user")
-}
-
-// ---
-
-/// Test helper: Create test credential for user
-async fn create_test_credential(
-    repo: &RepositoryPtr,
-    user_id: Uuid,
-    credential_id: Vec<u8>,
-) -> Credential {
-    //
-    let credential = Credential {
-        id: credential_id,
-        user_id,
-        public_key: b"dummy_public_key".to_vec(),
-        counter: 0,
-        created_at: chrono::Utc::now(),
-    };
-
-    repo.save_credential(credential.clone())
-        .await
-        .expect("Failed to save credential");
-
-    credential
-}
-
-// ---
-
 /// Test helper: Get Redis connection
 async fn get_redis_connection() -> redis::aio::MultiplexedConnection {
     //
@@ -74,10 +41,22 @@ async fn get_redis_connection() -> redis::aio::MultiplexedConnection {
         .expect("Failed to connect to Redis")
 }
 
+// ---
+
+/// Test helper: Fixtures on the Postgres repository and Redis
+#[cfg(feature = "test-utils")]
+async fn fixtures() -> (RepositoryPtr, Fixtures) {
+    //
+    let repo = create_postgres_repository().expect("Failed to create repository");
+    let fixtures = Fixtures::new(repo.clone(), get_redis_connection().await);
+    (repo, fixtures)
+}
+
 // ============================================================================
 // Session Validation Tests
 // ============================================================================
 
+#[cfg(feature = "test-utils")]
 #[test]
 fn test_session_validation_success() {
     //
@@ -87,12 +66,16 @@ fn test_session_validation_success() {
 
         //
         // Setup
-        let repo = create_postgres_repository().expect("Failed to create repository");
-        let user = create_test_user(&repo, "test_session_user").await;
+        let (_repo, mut fixtures) = fixtures().await;
+        let user = fixtures.user("test_session_user").insert().await.unwrap();
         let mut redis_conn = get_redis_connection().await;
 
         // Create session
-        let token = create_session(&mut redis_conn, user.id, user.username.clone())
+        let client_ip = "198.51.100.4".parse().unwrap();
+        let token = fixtures
+            .session(&user)
+            .client_ip(client_ip)
+            .insert()
             .await
             .expect("Failed to create session");
 
@@ -104,10 +87,9 @@ fn test_session_validation_success() {
         // Verify
         assert_eq!(session_info.user_id, user.id);
         assert_eq!(session_info.username, user.username);
+        assert_eq!(session_info.client_ip, Some(client_ip));
 
-        // Cleanup
-        let _: Result<(), _> = redis_conn.del(format!("session:{}", token)).await;
-        let _ = repo.delete_credential(user.id.as_bytes().as_ref()).await;
+        fixtures.reset().await.unwrap();
     });
 }
 
@@ -136,6 +118,7 @@ fn test_session_validation_invalid_token() {
 // List Credentials Tests
 // ============================================================================
 
+#[cfg(feature = "test-utils")]
 #[test]
 fn test_list_credentials_with_session() {
     //
@@ -145,16 +128,18 @@ fn test_list_credentials_with_session() {
 
         //
         // Setup
-        let repo = create_postgres_repository().expect("Failed to create repository");
-        let user = create_test_user(&repo, "test_list_user").await;
-        let mut redis_conn = get_redis_connection().await;
+        let (repo, mut fixtures) = fixtures().await;
+        let user = fixtures.user("test_list_user").insert().await.unwrap();
 
         // Create multiple credentials for user
-        let cred1 = create_test_credential(&repo, user.id, b"credential_1".to_vec()).await;
-        let cred2 = create_test_credential(&repo, user.id, b"credential_2".to_vec()).await;
+        let cred1 = fixtures.credential(&user).insert().await.unwrap();
+        let cred2 = fixtures.credential(&user).insert().await.unwrap();
+        assert_eq!(cred1.id, b"test_list_user-credential-1");
 
         // Create session
-        let token = create_session(&mut redis_conn, user.id, user.username.clone())
+        fixtures
+            .session(&user)
+            .insert()
             .await
             .expect("Failed to create session");
 
@@ -169,15 +154,13 @@ fn test_list_credentials_with_session() {
         assert!(credentials.iter().any(|c| c.id == cred1.id));
         assert!(credentials.iter().any(|c| c.id == cred2.id));
 
-        // Cleanup
-        let _: Result<(), _> = redis_conn.del(format!("session:{}", token)).await;
-        let _ = repo.delete_credential(&cred1.id).await;
-        let _ = repo.delete_credential(&cred2.id).await;
+        fixtures.reset().await.unwrap();
     });
 }
 
 // ---
 
+#[cfg(feature = "test-utils")]
 #[test]
 fn test_list_credentials_empty_list() {
     //
@@ -187,12 +170,17 @@ fn test_list_credentials_empty_list() {
 
         //
         // Setup
-        let repo = create_postgres_repository().expect("Failed to create repository");
-        let user = create_test_user(&repo, "test_empty_list_user").await;
-        let mut redis_conn = get_redis_connection().await;
+        let (repo, mut fixtures) = fixtures().await;
+        let user = fixtures
+            .user("test_empty_list_user")
+            .insert()
+            .await
+            .unwrap();
 
         // Create session but no credentials
-        let token = create_session(&mut redis_conn, user.id, user.username.clone())
+        fixtures
+            .session(&user)
+            .insert()
             .await
             .expect("Failed to create session");
 
@@ -205,8 +193,7 @@ fn test_list_credentials_empty_list() {
         // Verify empty list
         assert_eq!(credentials.len(), 0);
 
-        // Cleanup
-        let _: Result<(), _> = redis_conn.del(format!("session:{}", token)).await;
+        fixtures.reset().await.unwrap();
     });
 }
 
@@ -214,6 +201,7 @@ fn test_list_credentials_empty_list() {
 // Delete Credential Tests
 // ============================================================================
 
+#[cfg(feature = "test-utils")]
 #[test]
 fn test_delete_credential_success() {
     //
@@ -223,16 +211,9 @@ fn test_delete_credential_success() {
 
         //
         // Setup
-        let repo = create_postgres_repository().expect("Failed to create repository");
-        let user = create_test_user(&repo, "test_delete_user").await;
-        let credential =
-            create_test_credential(&repo, user.id, b"credential_to_delete".to_vec()).await;
-        let mut redis_conn = get_redis_connection().await;
-
-        // Create session
-        let token = create_session(&mut redis_conn, user.id, user.username.clone())
-            .await
-            .expect("Failed to create session");
+        let (repo, mut fixtures) = fixtures().await;
+        let user = fixtures.user("test_delete_user").insert().await.unwrap();
+        let credential = fixtures.credential(&user).insert().await.unwrap();
 
         // Verify credential exists
         let found = repo
@@ -253,13 +234,13 @@ fn test_delete_credential_success() {
             .expect("Failed to query credential");
         assert!(not_found.is_none());
 
-        // Cleanup
-        let _: Result<(), _> = redis_conn.del(format!("session:{}", token)).await;
+        fixtures.reset().await.unwrap();
     });
 }
 
 // ---
 
+#[cfg(feature = "test-utils")]
 #[test]
 fn test_delete_credential_ownership_check() {
     //
@@ -269,13 +250,12 @@ fn test_delete_credential_ownership_check() {
 
         //
         // Setup
-        let repo = create_postgres_repository().expect("Failed to create repository");
-        let user1 = create_test_user(&repo, "test_owner_user").await;
-        let user2 = create_test_user(&repo, "test_other_user").await;
+        let (repo, mut fixtures) = fixtures().await;
+        let user1 = fixtures.user("test_owner_user").insert().await.unwrap();
+        let user2 = fixtures.user("test_other_user").insert().await.unwrap();
 
         // Create credential for user1
-        let credential =
-            create_test_credential(&repo, user1.id, b"user1_credential".to_vec()).await;
+        let credential = fixtures.credential(&user1).insert().await.unwrap();
 
         // Simulate user2 trying to access user1's credential
         let fetched = repo
@@ -288,8 +268,7 @@ fn test_delete_credential_ownership_check() {
         assert_ne!(fetched.user_id, user2.id);
         assert_eq!(fetched.user_id, user1.id);
 
-        // Cleanup
-        let _ = repo.delete_credential(&credential.id).await;
+        fixtures.reset().await.unwrap();
     });
 }
 
@@ -1,30 +1,29 @@
 //! Integration tests for WebAuthn registration endpoints.
 //!
 //! Tests the full registration flow including:
-//! - Challenge generation and storage
+//! - Challenge generation and storage (one flow per start call)
 //! - Challenge expiration (TTL)
 //! - Credential verification API contract
 //! - Error handling
 //!
-//! ## Testing Limitations
+//! ## Credential Verification
 //!
-//! These tests validate the API layer but do NOT test actual WebAuthn
-//! credential verification or counter validation. Full end-to-end testing
-//! requires browser automation (e.g., Playwright) to generate real
-//! authenticator responses.
+//! The finish tests sign real authenticator responses with the software
+//! authenticator in `axum_quickstart::test_utils`, so they only run with
+//! `--features test-utils`:
 //!
-//! **TODO (Future Work):**
-//! - Add e2e tests with Playwright for full credential flow
-//! - Test counter validation with real authenticator responses
-//! - Test replay attack prevention
-//!
-//! **Reference:** The cr8s project demonstrates e2e WebAuthn testing with Playwright.
+//! ```text
+//! cargo test --features test-utils --test webauthn_registration
+//! ```
 
 use axum::{
     body::Body,
     http::{Request, StatusCode},
 };
-use axum_quickstart::create_router;
+use axum_quickstart::{
+    create_repository, create_router, redis_key, AppBuilder, AppConfig, TenantConfig,
+    WebAuthnConfig,
+};
 use once_cell::sync::Lazy;
 use redis::Client;
 use serde_json::json;
@@ -52,12 +51,26 @@ async fn cleanup_redis(username: &str) {
     let client = Client::open(redis_url).unwrap();
     let mut conn = client.get_multiplexed_async_connection().await.unwrap();
 
-    let key = format!("webauthn:reg:{username}");
-    let _: () = redis::cmd("DEL")
-        .arg(&key)
+    let keys: Vec<String> = redis::cmd("KEYS")
+        .arg(redis_key(format!("webauthn:reg:{username}:*")))
         .query_async(&mut conn)
         .await
         .unwrap();
+    for key in keys {
+        let _: () = redis::cmd("DEL")
+            .arg(&key)
+            .query_async(&mut conn)
+            .await
+            .unwrap();
+    }
+}
+
+/// Redis key of the challenge for the flow in a register/start response body.
+fn challenge_key(username: &str, body: &[u8]) -> String {
+    // ---
+    let json: serde_json::Value = serde_json::from_slice(body).unwrap();
+    let flow_id = json["flow_id"].as_str().expect("flow_id in response");
+    redis_key(format!("webauthn:reg:{username}:{flow_id}"))
 }
 
 // ============================================================================
@@ -76,7 +89,7 @@ fn test_register_start_creates_challenge() {
 
         let request = Request::builder()
             .method("POST")
-            .uri("/webauthn/register/start")
+            .uri("/api/v1/webauthn/register/start")
             .header("content-type", "application/json")
             .body(Body::from(
                 json!({
@@ -116,7 +129,7 @@ fn test_register_start_creates_user_if_not_exists() {
 
         let request = Request::builder()
             .method("POST")
-            .uri("/webauthn/register/start")
+            .uri("/api/v1/webauthn/register/start")
             .header("content-type", "application/json")
             .body(Body::from(
                 json!({
@@ -133,7 +146,7 @@ fn test_register_start_creates_user_if_not_exists() {
         let app = create_router().expect("Failed to create router");
         let request = Request::builder()
             .method("POST")
-            .uri("/webauthn/register/start")
+            .uri("/api/v1/webauthn/register/start")
             .header("content-type", "application/json")
             .body(Body::from(
                 json!({
@@ -162,7 +175,7 @@ fn test_register_start_stores_challenge_in_redis() {
 
         let request = Request::builder()
             .method("POST")
-            .uri("/webauthn/register/start")
+            .uri("/api/v1/webauthn/register/start")
             .header("content-type", "application/json")
             .body(Body::from(
                 json!({
@@ -174,13 +187,16 @@ fn test_register_start_stores_challenge_in_redis() {
 
         let response = app.oneshot(request).await.unwrap();
         assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let key = challenge_key(username, &body);
 
         // Verify challenge is in Redis
         let redis_url = env::var("REDIS_URL").unwrap();
         let client = Client::open(redis_url).unwrap();
         let mut conn = client.get_multiplexed_async_connection().await.unwrap();
 
-        let key = format!("webauthn:reg:{username}");
         let exists: bool = redis::cmd("EXISTS")
             .arg(&key)
             .query_async(&mut conn)
@@ -193,126 +209,211 @@ fn test_register_start_stores_challenge_in_redis() {
     })
 }
 
-// ============================================================================
-// Registration Finish Tests
-// ============================================================================
+#[test]
+fn test_concurrent_register_starts_keep_separate_challenges() {
+    // ---
+    run_async(async {
+        // ---
+        common::setup_test_env().await;
+
+        let username = "two_devices_user@example.com";
+
+        // Two devices start registering the same user before either finishes
+        let mut keys = Vec::new();
+        for _ in 0..2 {
+            let app = create_router().expect("Failed to create router");
+            let request = Request::builder()
+                .method("POST")
+                .uri("/api/v1/webauthn/register/start")
+                .header("content-type", "application/json")
+                .body(Body::from(json!({ "username": username }).to_string()))
+                .unwrap();
+
+            let response = app.oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            keys.push(challenge_key(username, &body));
+        }
+        assert_ne!(keys[0], keys[1], "each start should open its own flow");
+
+        let redis_url = env::var("REDIS_URL").unwrap();
+        let client = Client::open(redis_url).unwrap();
+        let mut conn = client.get_multiplexed_async_connection().await.unwrap();
+
+        for key in &keys {
+            let exists: bool = redis::cmd("EXISTS")
+                .arg(key)
+                .query_async(&mut conn)
+                .await
+                .unwrap();
+            assert!(exists, "Challenge {key} should not be overwritten");
+        }
+
+        cleanup_redis(username).await;
+    })
+}
 
-#[ignore = "Requires injectable WebAuthn verifier; see GH-33"]
 #[test]
-fn test_register_finish_fails_without_challenge() {
+fn test_register_start_scopes_user_to_host_tenant() {
     // ---
     run_async(async {
         // ---
         common::setup_test_env().await;
 
-        let app = create_router().expect("Failed to create router");
-        let username = "no_challenge_user@example.com";
+        let mut config = AppConfig::from_env().expect("config should load");
+        config.tenants = vec![TenantConfig {
+            id: "acme".to_string(),
+            hosts: vec!["acme.example".to_string()],
+            webauthn: WebAuthnConfig {
+                rp_id: "acme.example".to_string(),
+                rp_name: "Acme".to_string(),
+                origin: "https://acme.example".to_string(),
+                additional_origins: Vec::new(),
+            },
+        }];
+        let repository = create_repository(&config.database).await.unwrap();
+        let app = AppBuilder::new()
+            .config(config)
+            .repository(repository.clone())
+            .build()
+            .unwrap();
+        let username = "tenant_user@example.com";
 
-        // Try to finish registration without starting it
         let request = Request::builder()
             .method("POST")
-            .uri("/webauthn/register/finish")
+            .uri("/api/v1/webauthn/register/start")
+            .header("host", "acme.example")
             .header("content-type", "application/json")
-            .body(Body::from(
-                json!({
-                    "username": username,
-                    "credential": {
-                        "id": "fake_credential_id",
-                        "rawId": "fake_raw_id",
-                        "type": "public-key",
-                        "response": {
-                            "attestationObject": "fake_attestation",
-                            "clientDataJSON": "fake_client_data",
-                            "signature": "fake_signature",
-                            "userHandle": null
-                        },
-                        "type": "public-key"
-                    }
-                })
-                .to_string(),
-            ))
+            .body(Body::from(json!({ "username": username }).to_string()))
             .unwrap();
 
         let response = app.oneshot(request).await.unwrap();
-
-        // Should fail because challenge doesn't exist
-        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(response.status(), StatusCode::OK);
 
         let body = axum::body::to_bytes(response.into_body(), usize::MAX)
             .await
             .unwrap();
         let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["challenge"]["publicKey"]["rp"]["id"], "acme.example");
 
-        assert!(json
-            .get("error")
+        let user = repository
+            .get_user_by_username_in("acme", username)
+            .await
             .unwrap()
-            .as_str()
+            .expect("user created in the acme tenant");
+        assert_eq!(user.tenant_id, "acme");
+        assert!(repository
+            .get_user_by_username(username)
+            .await
             .unwrap()
-            .contains("not found or expired"));
+            .is_none());
+
+        let key = challenge_key(username, &body).replacen("webauthn:", "webauthn:acme:", 1);
+        let redis_url = env::var("REDIS_URL").unwrap();
+        let mut conn = Client::open(redis_url)
+            .unwrap()
+            .get_multiplexed_async_connection()
+            .await
+            .unwrap();
+        let deleted: u32 = redis::cmd("DEL")
+            .arg(&key)
+            .query_async(&mut conn)
+            .await
+            .unwrap();
+        assert_eq!(deleted, 1, "challenge stored under {key}");
+        repository.delete_user(user.id).await.unwrap();
     })
 }
 
-#[ignore = "Requires injectable WebAuthn verifier; see GH-33"]
+// ============================================================================
+// Registration Finish Tests
+// ============================================================================
+
+#[cfg(feature = "test-utils")]
 #[test]
-fn test_register_finish_challenge_is_single_use() {
+fn test_register_finish_fails_without_challenge() {
     // ---
     run_async(async {
         // ---
         common::setup_test_env().await;
 
-        let username = "single_use_user@example.com";
-
-        // Start registration to create challenge
         let app = create_router().expect("Failed to create router");
-        let request = Request::builder()
-            .method("POST")
-            .uri("/webauthn/register/start")
-            .header("content-type", "application/json")
-            .body(Body::from(
-                json!({
-                    "username": username
-                })
-                .to_string(),
-            ))
-            .unwrap();
+        let username = "no_challenge_user@example.com";
+        let mut authenticator = common::mock_authenticator();
+
+        // A well-formed credential, answered under a flow that was never started
+        let (status, start) = common::post_json(
+            &app,
+            "/api/v1/webauthn/register/start",
+            json!({ "username": username }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let options = serde_json::from_value(start["challenge"].clone()).unwrap();
+        let credential = authenticator.register(&options).unwrap();
+
+        let (status, json) = common::post_json(
+            &app,
+            "/api/v1/webauthn/register/finish",
+            json!({
+                "username": username,
+                "flow_id": uuid::Uuid::new_v4(),
+                "credential": credential,
+            }),
+        )
+        .await;
 
-        let response = app.oneshot(request).await.unwrap();
-        assert_eq!(response.status(), StatusCode::OK);
+        // Should fail because challenge doesn't exist
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(json["error"]
+            .as_str()
+            .unwrap()
+            .contains("not found or expired"));
 
-        // Try to finish with invalid credential (will fail but consume challenge)
-        let app = create_router().expect("Failed to create router");
-        let request = Request::builder()
-            .method("POST")
-            .uri("/webauthn/register/finish")
-            .header("content-type", "application/json")
-            .body(Body::from(
-                json!({
-                    "username": username,
-                    "credential": {
-                        "id": "fake_credential_id",
-                        "rawId": "fake_raw_id",
-                        "type": "public-key",
-                        "response": {
-                            "attestationObject": "fake_attestation",
-                            "clientDataJSON": "fake_client_data",
-                            "signature": "fake_signature",
-                            "userHandle": null
-                        },
-                        "type": "public-key"
-                    }
-                })
-                .to_string(),
-            ))
-            .unwrap();
+        cleanup_redis(username).await;
+    })
+}
 
-        let _response = app.oneshot(request).await.unwrap();
+#[cfg(feature = "test-utils")]
+#[test]
+fn test_register_finish_challenge_is_single_use() {
+    // ---
+    run_async(async {
+        // ---
+        common::setup_test_env().await;
+
+        let app = create_router().expect("Failed to create router");
+        let username = format!("single_use_{}@example.com", uuid::Uuid::new_v4());
+        let mut authenticator = common::mock_authenticator();
+
+        let (status, start) = common::post_json(
+            &app,
+            "/api/v1/webauthn/register/start",
+            json!({ "username": username }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let key = challenge_key(&username, start.to_string().as_bytes());
+        let options = serde_json::from_value(start["challenge"].clone()).unwrap();
+        let credential = authenticator.register(&options).unwrap();
+        let finish = json!({
+            "username": username,
+            "flow_id": start["flow_id"],
+            "credential": credential,
+        });
+
+        // First finish verifies the passkey and consumes the challenge
+        let (status, json) =
+            common::post_json(&app, "/api/v1/webauthn/register/finish", finish.clone()).await;
+        assert_eq!(status, StatusCode::OK, "{json}");
+        assert_eq!(json["success"], true);
 
-        // Verify challenge is deleted from Redis
         let redis_url = env::var("REDIS_URL").unwrap();
         let client = Client::open(redis_url).unwrap();
         let mut conn = client.get_multiplexed_async_connection().await.unwrap();
 
-        let key = format!("webauthn:reg:{username}");
         let exists: bool = redis::cmd("EXISTS")
             .arg(&key)
             .query_async(&mut conn)
@@ -321,7 +422,11 @@ fn test_register_finish_challenge_is_single_use() {
 
         assert!(!exists, "Challenge should be deleted after use");
 
-        cleanup_redis(username).await;
+        // Replaying the same response is rejected
+        let (status, _) = common::post_json(&app, "/api/v1/webauthn/register/finish", finish).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        cleanup_redis(&username).await;
     })
 }
 
@@ -341,7 +446,7 @@ fn test_challenge_has_ttl_in_redis() {
 
         let request = Request::builder()
             .method("POST")
-            .uri("/webauthn/register/start")
+            .uri("/api/v1/webauthn/register/start")
             .header("content-type", "application/json")
             .body(Body::from(
                 json!({
@@ -353,13 +458,16 @@ fn test_challenge_has_ttl_in_redis() {
 
         let response = app.oneshot(request).await.unwrap();
         assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let key = challenge_key(username, &body);
 
         // Check TTL in Redis
         let redis_url = env::var("REDIS_URL").unwrap();
         let client = Client::open(redis_url).unwrap();
         let mut conn = client.get_multiplexed_async_connection().await.unwrap();
 
-        let key = format!("webauthn:reg:{username}");
         let ttl: i64 = redis::cmd("TTL")
             .arg(&key)
             .query_async(&mut conn)
@@ -389,7 +497,7 @@ fn test_register_start_invalid_json() {
 
         let request = Request::builder()
             .method("POST")
-            .uri("/webauthn/register/start")
+            .uri("/api/v1/webauthn/register/start")
             .header("content-type", "application/json")
             .body(Body::from("invalid json"))
             .unwrap();
@@ -412,7 +520,7 @@ fn test_register_finish_invalid_json() {
 
         let request = Request::builder()
             .method("POST")
-            .uri("/webauthn/register/finish")
+            .uri("/api/v1/webauthn/register/finish")
             .header("content-type", "application/json")
             .body(Body::from("invalid json"))
             .unwrap();
@@ -0,0 +1,303 @@
+//! Integration tests for outbound webhooks.
+//!
+//! Covers the `/admin/webhooks` CRUD API and end-to-end delivery of a
+//! signed `credential.deleted` event, through the outbox, to a local
+//! receiver.
+
+use axum::{
+    body::{Body, Bytes},
+    extract::State,
+    http::{HeaderMap, Request, StatusCode},
+    routing::post,
+    Router,
+};
+use axum_quickstart::domain::Credential;
+use axum_quickstart::{create_repository, create_session, AppBuilder, AppConfig};
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tower::ServiceExt;
+
+mod common;
+
+const ADMIN_TOKEN: &str = "webhook-admin-secret";
+
+// ---
+
+/// Trace of the request that deletes the credential.
+const TRACEPARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+/// Test helper: Router with the admin API enabled
+async fn admin_router() -> Router {
+    // ---
+    let mut config = AppConfig::from_env().expect("config should load");
+    config.admin.api_token = Some(ADMIN_TOKEN.to_string());
+    config.webhooks.retry_base = Duration::from_millis(10);
+    let repository = create_repository(&config.database).await.unwrap();
+
+    AppBuilder::new()
+        .config(config)
+        .repository(repository)
+        .build()
+        .unwrap()
+}
+
+// ---
+
+/// Test helper: Send an admin request and return status and JSON body
+async fn admin_call(
+    router: &Router,
+    method: &str,
+    uri: &str,
+    body: Option<serde_json::Value>,
+) -> (StatusCode, serde_json::Value) {
+    // ---
+    let request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("authorization", format!("Bearer {ADMIN_TOKEN}"))
+        .header("content-type", "application/json")
+        .body(match body {
+            Some(json) => Body::from(json.to_string()),
+            None => Body::empty(),
+        })
+        .unwrap();
+
+    let response = router.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json = serde_json::from_slice(&bytes).unwrap_or(serde_json::Value::Null);
+
+    (status, json)
+}
+
+// ============================================================================
+// CRUD Tests
+// ============================================================================
+
+#[tokio::test]
+#[serial_test::serial]
+async fn webhook_crud_round_trip() {
+    // ---
+    common::setup_test_env().await;
+    let router = admin_router().await;
+
+    // Create: secret is generated and shown once
+    let (status, created) = admin_call(
+        &router,
+        "POST",
+        "/api/v1/admin/webhooks",
+        Some(serde_json::json!({ "url": "https://example.com/hooks" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let id = created["data"]["id"].as_str().unwrap().to_string();
+    assert_eq!(created["data"]["secret"].as_str().unwrap().len(), 64);
+
+    // Get and list never reveal the secret
+    let (status, fetched) = admin_call(
+        &router,
+        "GET",
+        &format!("/api/v1/admin/webhooks/{id}"),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(fetched["data"]["url"], "https://example.com/hooks");
+    assert!(fetched["data"].get("secret").is_none());
+
+    let (status, listed) = admin_call(&router, "GET", "/api/v1/admin/webhooks", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(listed["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|w| w["id"] == id.as_str() && w.get("secret").is_none()));
+
+    // Update URL and rotate secret
+    let (status, updated) = admin_call(
+        &router,
+        "PUT",
+        &format!("/api/v1/admin/webhooks/{id}"),
+        Some(serde_json::json!({ "url": "https://example.org/v2", "secret": "rotated" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(updated["data"]["url"], "https://example.org/v2");
+    assert_eq!(updated["data"]["secret"], "rotated");
+
+    // Delete, then it is gone
+    let (status, _) = admin_call(
+        &router,
+        "DELETE",
+        &format!("/api/v1/admin/webhooks/{id}"),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    let (status, _) = admin_call(
+        &router,
+        "GET",
+        &format!("/api/v1/admin/webhooks/{id}"),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+// ---
+
+#[tokio::test]
+#[serial_test::serial]
+async fn webhook_create_rejects_non_http_url() {
+    // ---
+    common::setup_test_env().await;
+    let router = admin_router().await;
+
+    let (status, _) = admin_call(
+        &router,
+        "POST",
+        "/api/v1/admin/webhooks",
+        Some(serde_json::json!({ "url": "ftp://example.com/hooks" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+// ============================================================================
+// Delivery Tests
+// ============================================================================
+
+/// Receiver that forwards every request it gets to the test.
+async fn capture(
+    State(tx): State<mpsc::UnboundedSender<(HeaderMap, Bytes)>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> StatusCode {
+    // ---
+    let _ = tx.send((headers, body));
+    StatusCode::NO_CONTENT
+}
+
+// ---
+
+#[tokio::test]
+#[serial_test::serial]
+async fn credential_deletion_delivers_signed_webhook() {
+    // ---
+    common::setup_test_env().await;
+
+    // Local receiver
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let receiver = Router::new().route("/hook", post(capture)).with_state(tx);
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let hook_url = format!("http://{}/hook", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, receiver).await.unwrap() });
+
+    let router = admin_router().await;
+    let (status, created) = admin_call(
+        &router,
+        "POST",
+        "/api/v1/admin/webhooks",
+        Some(serde_json::json!({ "url": hook_url, "secret": "hook-secret" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let webhook_id = created["data"]["id"].as_str().unwrap().to_string();
+
+    // User with one credential and a session
+    let config = AppConfig::from_env().unwrap();
+    let repo = create_repository(&config.database).await.unwrap();
+    let user = repo
+        .create_user(&format!("webhook_user_{}", uuid::Uuid::new_v4()))
+        .await
+        .unwrap();
+    let credential_id = uuid::Uuid::new_v4().as_bytes().to_vec();
+    repo.save_credential(Credential {
+        id: credential_id.clone(),
+        user_id: user.id,
+        public_key: b"dummy_public_key".to_vec(),
+        counter: 0,
+        created_at: chrono::Utc::now(),
+        compromised_at: None,
+        last_used_at: None,
+        backup_eligible: None,
+        backup_state: None,
+    })
+    .await
+    .unwrap();
+
+    let mut redis_conn = redis::Client::open(config.redis.url.clone())
+        .unwrap()
+        .get_multiplexed_async_connection()
+        .await
+        .unwrap();
+    let token = create_session(&mut redis_conn, user.id, user.username.clone(), None)
+        .await
+        .unwrap();
+
+    let encoded_id = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(&credential_id);
+    let response = router
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("DELETE")
+                .uri(format!("/api/v1/webauthn/credentials/{encoded_id}"))
+                .header("authorization", format!("Bearer {token}"))
+                .header("traceparent", TRACEPARENT)
+                .header("tracestate", "rojo=00f067aa0ba902b7")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Events left in the outbox by other tests may be delivered first
+    let (headers, body) = tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            let (headers, body) = rx.recv().await.unwrap();
+            if headers["x-webhook-event"] == "credential.deleted" {
+                break (headers, body);
+            }
+        }
+    })
+    .await
+    .expect("webhook should be delivered");
+
+    // Signature verifies against the configured secret
+    let timestamp = headers["x-webhook-timestamp"].to_str().unwrap();
+    let mut mac = Hmac::<Sha256>::new_from_slice(b"hook-secret").unwrap();
+    mac.update(format!("{timestamp}.").as_bytes());
+    mac.update(&body);
+    let expected = format!("sha256={}", hex::encode(mac.finalize().into_bytes()));
+    assert_eq!(headers["x-webhook-signature"], expected.as_str());
+    assert_eq!(headers["x-webhook-event"], "credential.deleted");
+
+    let event: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(event["type"], "credential.deleted");
+    assert_eq!(event["data"]["user_id"], user.id.to_string());
+    assert_eq!(event["data"]["credential_id"], encoded_id);
+
+    // Delivered in the deleting request's trace, as a span of its own
+    let traceparent = headers["traceparent"].to_str().unwrap();
+    assert!(traceparent.starts_with("00-4bf92f3577b34da6a3ce929d0e0e4736-"));
+    assert!(!traceparent.contains("00f067aa0ba902b7"));
+    assert_eq!(headers["tracestate"], "rojo=00f067aa0ba902b7");
+
+    // Cleanup
+    admin_call(
+        &router,
+        "DELETE",
+        &format!("/api/v1/admin/webhooks/{webhook_id}"),
+        None,
+    )
+    .await;
+    let _ = repo.delete_credential(&credential_id).await;
+}
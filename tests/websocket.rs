@@ -0,0 +1,146 @@
+//! Integration tests for the `/ws` WebSocket endpoint.
+//!
+//! Covers session auth on upgrade, echo, per-user notifications, and the
+//! close frame sent on graceful shutdown.
+
+use axum_quickstart::{
+    create_repository, create_session, AppBuilder, AppConfig, EventBus, ServerEvent,
+    ServerEventKind, ShutdownSignal,
+};
+use futures::{SinkExt, StreamExt};
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio_tungstenite::tungstenite::{self, protocol::frame::coding::CloseCode, Message};
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+use uuid::Uuid;
+
+mod common;
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+// ---
+
+/// Test helper: Serve the app with injected event bus and shutdown signal
+async fn start_server(events: EventBus, shutdown: ShutdownSignal) -> SocketAddr {
+    // ---
+    let config = AppConfig::from_env().expect("config should load");
+    let repository = create_repository(&config.database).await.unwrap();
+
+    let router = AppBuilder::new()
+        .config(config)
+        .repository(repository)
+        .events(events)
+        .shutdown(shutdown)
+        .build()
+        .unwrap();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+
+    addr
+}
+
+// ---
+
+/// Test helper: Create a user and return its ID and a session token
+async fn signed_in_user() -> (Uuid, String) {
+    // ---
+    let config = AppConfig::from_env().unwrap();
+    let repo = create_repository(&config.database).await.unwrap();
+    let user = repo
+        .create_user(&format!("ws_user_{}", Uuid::new_v4()))
+        .await
+        .unwrap();
+
+    let mut conn = redis::Client::open(config.redis.url)
+        .unwrap()
+        .get_multiplexed_async_connection()
+        .await
+        .unwrap();
+    let token = create_session(&mut conn, user.id, user.username, None)
+        .await
+        .unwrap();
+
+    (user.id, token)
+}
+
+// ---
+
+/// Test helper: Next frame from the server, failing the test after 5s
+async fn next_message(socket: &mut Socket) -> Message {
+    // ---
+    tokio::time::timeout(Duration::from_secs(5), socket.next())
+        .await
+        .expect("message should arrive")
+        .unwrap()
+        .unwrap()
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[tokio::test]
+#[serial_test::serial]
+async fn ws_rejects_upgrade_without_session() {
+    // ---
+    common::setup_test_env().await;
+    let addr = start_server(EventBus::default(), ShutdownSignal::new()).await;
+
+    let result = tokio_tungstenite::connect_async(format!("ws://{addr}/api/v1/ws")).await;
+
+    match result {
+        Err(tungstenite::Error::Http(response)) => assert_eq!(response.status(), 401),
+        other => panic!("expected HTTP 401, got {other:?}"),
+    }
+}
+
+// ---
+
+#[tokio::test]
+#[serial_test::serial]
+async fn ws_echoes_notifies_and_closes_on_shutdown() {
+    // ---
+    common::setup_test_env().await;
+    let events = EventBus::default();
+    let shutdown = ShutdownSignal::new();
+    let addr = start_server(events.clone(), shutdown.clone()).await;
+    let (user_id, token) = signed_in_user().await;
+
+    let (mut socket, _) =
+        tokio_tungstenite::connect_async(format!("ws://{addr}/api/v1/ws?token={token}"))
+            .await
+            .expect("upgrade should succeed");
+
+    // Echo
+    socket.send(Message::text("hello")).await.unwrap();
+    assert_eq!(next_message(&mut socket).await, Message::text("hello"));
+
+    // Another user's event is filtered out; ours is delivered
+    let event = |user| {
+        ServerEvent::new(
+            ServerEventKind::CredentialDeleted,
+            Some(user),
+            serde_json::json!({ "credential_id": "AQID" }),
+        )
+    };
+    events.publish(event(Uuid::new_v4()));
+    events.publish(event(user_id));
+
+    let Message::Text(text) = next_message(&mut socket).await else {
+        panic!("expected a text notification");
+    };
+    let json: serde_json::Value = serde_json::from_str(&text).unwrap();
+    assert_eq!(json["type"], "credential.deleted");
+    assert_eq!(json["user_id"], user_id.to_string());
+
+    // Graceful shutdown sends 1001 Going Away
+    shutdown.trigger();
+    let Message::Close(Some(frame)) = next_message(&mut socket).await else {
+        panic!("expected a close frame");
+    };
+    assert_eq!(frame.code, CloseCode::Away);
+    assert!(shutdown.drain(Duration::from_secs(5)).await);
+}
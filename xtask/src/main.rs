@@ -0,0 +1,127 @@
+//! # Development Tasks
+//!
+//! Run with `cargo xtask <task>`:
+//! - `prepare`: regenerate the sqlx offline query data in `.sqlx/` from the
+//!   database at `DATABASE_URL`, which must have every migration applied.
+//!   Commit the result; builds without `DATABASE_URL` check the `query!`
+//!   macros against it.
+//! - `prepare --check`: fail if `.sqlx/` does not match what `prepare`
+//!   would write, e.g. after a query or migration changed without
+//!   regenerating. Used by CI.
+//!
+//! This does what `cargo sqlx prepare` does, without needing `sqlx-cli`
+//! installed at the matching version.
+
+use anyhow::{bail, Context, Result};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::SystemTime;
+
+const USAGE: &str = "usage: cargo xtask prepare [--check]";
+
+fn main() -> Result<()> {
+    // ---
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    match args.as_slice() {
+        ["prepare"] => prepare(false),
+        ["prepare", "--check"] => prepare(true),
+        _ => bail!(USAGE),
+    }
+}
+
+/// Checks the library with the query macros online, having them write their
+/// metadata to `.sqlx/` (or, with `check`, to a scratch directory compared
+/// against `.sqlx/`).
+fn prepare(check: bool) -> Result<()> {
+    // ---
+    let root = workspace_root();
+    let committed = root.join(".sqlx");
+    let out = match check {
+        true => root.join("target").join("sqlx-check"),
+        false => committed.clone(),
+    };
+
+    let database_url = std::env::var("DATABASE_URL")
+        .context("DATABASE_URL must point at a database with all migrations applied")?;
+
+    // Stale files for queries that no longer exist must not survive
+    if out.exists() {
+        fs::remove_dir_all(&out).with_context(|| format!("Failed to clear {}", out.display()))?;
+    }
+    fs::create_dir_all(&out).with_context(|| format!("Failed to create {}", out.display()))?;
+
+    // Macros only run when the crate is rebuilt
+    fs::File::options()
+        .write(true)
+        .open(root.join("src").join("lib.rs"))
+        .and_then(|lib| lib.set_modified(SystemTime::now()))
+        .context("Failed to touch src/lib.rs")?;
+
+    let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+    let status = Command::new(cargo)
+        .current_dir(&root)
+        .args(["check", "--quiet", "--package", "axum-quickstart", "--lib"])
+        .env("DATABASE_URL", database_url)
+        .env("SQLX_OFFLINE", "false")
+        .env("SQLX_OFFLINE_DIR", &out)
+        .status()
+        .context("Failed to run cargo check")?;
+    if !status.success() {
+        bail!("cargo check failed; fix the queries above and rerun");
+    }
+
+    let generated = query_files(&out)?;
+    if !check {
+        println!("Wrote {} queries to .sqlx/", generated.len());
+        return Ok(());
+    }
+
+    let committed = query_files(&committed)?;
+    let stale: BTreeSet<&String> = generated
+        .keys()
+        .chain(committed.keys())
+        .filter(|name| generated.get(*name) != committed.get(*name))
+        .collect();
+    if !stale.is_empty() {
+        for name in &stale {
+            eprintln!("out of date: .sqlx/{name}");
+        }
+        bail!("sqlx offline data is out of date; run `cargo xtask prepare` and commit .sqlx/");
+    }
+    println!(
+        "sqlx offline data is up to date ({} queries)",
+        generated.len()
+    );
+    Ok(())
+}
+
+/// `query-*.json` files in `dir` by name, with their contents.
+fn query_files(dir: &Path) -> Result<BTreeMap<String, String>> {
+    // ---
+    let mut files = BTreeMap::new();
+    if !dir.exists() {
+        return Ok(files);
+    }
+    for entry in fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))? {
+        let path = entry?.path();
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        if name.starts_with("query-") && name.ends_with(".json") {
+            files.insert(name.to_string(), fs::read_to_string(&path)?);
+        }
+    }
+    Ok(files)
+}
+
+fn workspace_root() -> PathBuf {
+    // ---
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .expect("xtask lives in the workspace root")
+        .to_path_buf()
+}